@@ -0,0 +1,340 @@
+//! `#[derive(FromDom)]` for `generational-arena-dom`. Use it through the `derive` feature of
+//! that crate rather than depending on this one directly.
+
+use proc_macro::{Delimiter, Group, Span, TokenStream, TokenTree};
+
+/// Derive `generational_arena_dom::extract::FromDom` for a struct whose fields are annotated
+/// with `#[select(...)]`. See the `FromDom` trait for the attribute syntax.
+#[proc_macro_derive(FromDom, attributes(select))]
+pub fn derive_from_dom(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(output) => output.parse().unwrap(),
+        Err(error) => compile_error(&error.message, error.span),
+    }
+}
+
+/// A derive error, reported at `span`.
+struct Error {
+    message: String,
+    span: Span,
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error {
+            message,
+            span: Span::call_site(),
+        }
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::from(message.to_string())
+    }
+}
+
+/// `compile_error!(message)`, with every token at `span` so the error points there.
+fn compile_error(message: &str, span: Span) -> TokenStream {
+    fn respan(stream: TokenStream, span: Span) -> TokenStream {
+        stream
+            .into_iter()
+            .map(|token| match token {
+                TokenTree::Group(group) => {
+                    let mut group = Group::new(group.delimiter(), respan(group.stream(), span));
+                    group.set_span(span);
+                    TokenTree::Group(group)
+                }
+                mut token => {
+                    token.set_span(span);
+                    token
+                }
+            })
+            .collect()
+    }
+    respan(
+        format!("::core::compile_error!({:?});", message)
+            .parse()
+            .unwrap(),
+        span,
+    )
+}
+
+/// What a field takes from the elements it selects.
+enum Kind {
+    Text,
+    Html,
+    Attr(String),
+    Nested,
+}
+
+/// How many elements a field uses, from its type.
+enum Arity {
+    One,
+    Optional,
+    All,
+}
+
+struct Field {
+    name: String,
+    ty: String,
+    selector: String,
+    kind: Kind,
+    arity: Arity,
+}
+
+fn expand(input: TokenStream) -> Result<String, Error> {
+    let mut tokens = input.into_iter().peekable();
+    let mut name = None;
+    let mut body = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "struct" => match tokens.next() {
+                Some(TokenTree::Ident(ident)) => name = Some(ident.to_string()),
+                _ => return Err("expected a struct name".into()),
+            },
+            TokenTree::Ident(ident)
+                if ident.to_string() == "enum" || ident.to_string() == "union" =>
+            {
+                return Err("`FromDom` can only be derived for structs".into());
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '<' && name.is_some() => {
+                return Err("`FromDom` can't be derived for generic structs".into());
+            }
+            TokenTree::Group(group) if name.is_some() && group.delimiter() == Delimiter::Brace => {
+                body = Some(group.stream());
+            }
+            _ => {}
+        }
+    }
+    let name = name.ok_or("`FromDom` can only be derived for structs")?;
+    let body = body.ok_or("`FromDom` can only be derived for structs with named fields")?;
+
+    let fields = split_commas(body)
+        .into_iter()
+        .filter(|tokens| !tokens.is_empty())
+        .map(|tokens| {
+            let span = field_span(&tokens);
+            parse_field(tokens).map_err(|message| Error { message, span })
+        })
+        .collect::<Result<Vec<Field>, Error>>()?;
+
+    let mut output = format!(
+        "impl ::generational_arena_dom::extract::FromDom for {name} {{\n\
+         fn from_dom(\n\
+         dom: &::generational_arena_dom::GenerationalArenaDom,\n\
+         scope: ::generational_arena_dom::Handle,\n\
+         ) -> ::core::result::Result<Self, ::generational_arena_dom::extract::FromDomError> {{\n\
+         use ::generational_arena_dom::extract::__private;\n"
+    );
+    for field in &fields {
+        let function = match (&field.kind, &field.arity) {
+            (Kind::Nested, Arity::One) => "nested_one",
+            (Kind::Nested, Arity::Optional) => "nested_optional",
+            (Kind::Nested, Arity::All) => "nested_all",
+            (_, Arity::One) => "one",
+            (_, Arity::Optional) => "optional",
+            (_, Arity::All) => "all",
+        };
+        let kind = match &field.kind {
+            Kind::Text => Some("__private::Kind::Text".to_string()),
+            Kind::Html => Some("__private::Kind::Html".to_string()),
+            Kind::Attr(attr) => Some(format!(
+                "__private::Kind::Attr(::std::string::String::from({:?}))",
+                attr
+            )),
+            Kind::Nested => None,
+        };
+        let field_name = field.name.trim_start_matches("r#");
+        let extra = match (&kind, &field.arity) {
+            (Some(kind), _) => format!(", &{}, {:?}", kind, field_name),
+            (None, Arity::One) => format!(", {:?}", field_name),
+            (None, _) => String::new(),
+        };
+        output += &format!(
+            "let __field_{field_name}: {ty} = __private::{function}(\
+             dom, &__private::select(dom, scope, {selector:?}, {field_name:?})?{extra})?;\n",
+            ty = field.ty,
+            selector = field.selector,
+        );
+    }
+    // Values are bound to prefixed names so a field called e.g. `dom` can't shadow the arguments.
+    let names: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            format!(
+                "{}: __field_{}",
+                field.name,
+                field.name.trim_start_matches("r#")
+            )
+        })
+        .collect();
+    output += &format!(
+        "::core::result::Result::Ok({} {{ {} }})\n}}\n}}\n",
+        name,
+        names.join(", ")
+    );
+    Ok(output)
+}
+
+/// Split a token stream on the commas outside angle brackets.
+fn split_commas(stream: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut parts = vec![Vec::new()];
+    let mut depth = 0usize;
+    for token in stream {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                '>' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    parts.push(Vec::new());
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        parts.last_mut().unwrap().push(token);
+    }
+    parts
+}
+
+/// The span of a field's name, or of its first token if it has no name.
+fn field_span(tokens: &[TokenTree]) -> Span {
+    let mut after_attributes = tokens.iter().filter(|token| match token {
+        TokenTree::Punct(punct) => punct.as_char() != '#',
+        TokenTree::Group(group) => group.delimiter() != Delimiter::Bracket,
+        _ => true,
+    });
+    after_attributes
+        .find(|token| matches!(token, TokenTree::Ident(ident) if ident.to_string() != "pub"))
+        .or(tokens.first())
+        .map_or_else(Span::call_site, TokenTree::span)
+}
+
+fn parse_field(tokens: Vec<TokenTree>) -> Result<Field, String> {
+    let mut select = None;
+    let mut rest = tokens.into_iter().peekable();
+    // Attributes.
+    while let Some(TokenTree::Punct(punct)) = rest.peek() {
+        if punct.as_char() != '#' {
+            break;
+        }
+        rest.next();
+        let Some(TokenTree::Group(group)) = rest.next() else {
+            return Err("expected an attribute".to_string());
+        };
+        let mut attr = group.stream().into_iter();
+        if let (Some(TokenTree::Ident(ident)), Some(TokenTree::Group(args))) =
+            (attr.next(), attr.next())
+        {
+            if ident.to_string() == "select" {
+                select = Some(args.stream());
+            }
+        }
+    }
+    // Visibility, then the name.
+    let mut name = None;
+    for token in rest.by_ref() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "pub" => {}
+            TokenTree::Group(group) if group.delimiter() == Delimiter::Parenthesis => {}
+            TokenTree::Ident(ident) => {
+                name = Some(ident.to_string());
+                break;
+            }
+            _ => return Err("`FromDom` can only be derived for structs with named fields".into()),
+        }
+    }
+    let name = name.ok_or("expected a field name")?;
+    match rest.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => {}
+        _ => return Err("`FromDom` can only be derived for structs with named fields".into()),
+    }
+    let ty: Vec<TokenTree> = rest.collect();
+    // The last path segment before the generic arguments, so `std::option::Option<T>` counts as
+    // an `Option`. Aliases of `Option` and `Vec` aren't recognized.
+    let last_segment = ty
+        .iter()
+        .take_while(|token| !matches!(token, TokenTree::Punct(punct) if punct.as_char() == '<'))
+        .filter_map(|token| match token {
+            TokenTree::Ident(ident) => Some(ident.to_string()),
+            _ => None,
+        })
+        .last();
+    let arity = match last_segment.as_deref() {
+        Some("Option") => Arity::Optional,
+        Some("Vec") => Arity::All,
+        _ => Arity::One,
+    };
+    let ty = ty.into_iter().collect::<TokenStream>().to_string();
+
+    let select =
+        select.ok_or_else(|| format!("field `{}` has no #[select(...)] attribute", name))?;
+    let (selector, kind) = parse_select(select)
+        .map_err(|message| format!("invalid #[select(...)] on field `{}`: {}", name, message))?;
+    Ok(Field {
+        name,
+        ty,
+        selector,
+        kind,
+        arity,
+    })
+}
+
+/// Parse the arguments of `#[select("selector", kind)]`.
+fn parse_select(args: TokenStream) -> Result<(String, Kind), String> {
+    let parts = split_commas(args);
+    let mut parts = parts.into_iter().filter(|part| !part.is_empty());
+    let selector = match parts.next().as_deref() {
+        Some([TokenTree::Literal(literal)]) => string_literal(&literal.to_string())?,
+        _ => return Err("expected a selector string".to_string()),
+    };
+    let kind = match parts.next().as_deref() {
+        None => Kind::Text,
+        Some([TokenTree::Ident(ident)]) => match ident.to_string().as_str() {
+            "text" => Kind::Text,
+            "html" => Kind::Html,
+            "nested" => Kind::Nested,
+            other => return Err(format!("unknown kind `{}`", other)),
+        },
+        Some([TokenTree::Ident(ident), TokenTree::Punct(eq), TokenTree::Literal(literal)])
+            if ident.to_string() == "attr" && eq.as_char() == '=' =>
+        {
+            Kind::Attr(string_literal(&literal.to_string())?)
+        }
+        _ => return Err("expected `text`, `html`, `nested` or `attr = \"...\"`".to_string()),
+    };
+    if parts.next().is_some() {
+        return Err("unexpected arguments".to_string());
+    }
+    Ok((selector, kind))
+}
+
+/// The value of a plain string literal, handling the escapes that are likely in selectors.
+fn string_literal(literal: &str) -> Result<String, String> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        return Ok(raw[hashes + 1..raw.len() - hashes - 1].to_string());
+    }
+    let inner = literal
+        .strip_prefix('"')
+        .and_then(|literal| literal.strip_suffix('"'))
+        .ok_or("expected a string")?;
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('0') => value.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => value.push(c),
+            _ => return Err("unsupported escape in string".to_string()),
+        }
+    }
+    Ok(value)
+}
@@ -0,0 +1,456 @@
+//! Building trees from Emmet-style abbreviations.
+
+use std::fmt;
+
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::{GenerationalArenaDom, Handle, InsertPosition};
+
+/// The most nodes an abbreviation may create, so that repetitions can't exhaust memory.
+const MAX_NODES: usize = 100_000;
+
+/// An error from parsing an abbreviation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbbreviationError {
+    /// The byte offset in the abbreviation at which parsing failed.
+    pub position: usize,
+    /// What went wrong.
+    pub message: &'static str,
+}
+
+impl fmt::Display for AbbreviationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for AbbreviationError {}
+
+#[derive(Debug)]
+struct Item {
+    kind: ItemKind,
+    repeat: usize,
+    /// The byte offset just after the item itself, where its repetition count is.
+    repeat_at: usize,
+    children: Vec<Item>,
+}
+
+#[derive(Debug)]
+enum ItemKind {
+    Element {
+        name: String,
+        attrs: Vec<(String, String)>,
+        text: Option<String>,
+    },
+    Text(String),
+    Group(Vec<Item>),
+}
+
+impl GenerationalArenaDom {
+    /// Build the nodes described by an [Emmet] abbreviation and insert them at `position`,
+    /// returning the top-level nodes created.
+    ///
+    /// Supported syntax: element names (defaulting to `div`), `#id`, `.class`,
+    /// `[attr=value attr2="quoted value"]`, `{text}`, the `>` child, `+` sibling and `^` climb-up
+    /// operators, `(...)` groups and `*N` repetition, with `$` in names and text numbering the
+    /// repetitions (`$$` zero-pads to two digits). For example `ul>li.item$*3>a[href=#]{Item $}`.
+    /// Abbreviations that would create more than 100,000 nodes are refused. Nothing is inserted
+    /// into a [frozen](GenerationalArenaDom::freeze) element.
+    ///
+    /// [Emmet]: https://docs.emmet.io/abbreviations/syntax/
+    pub fn expand_abbreviation(
+        &mut self,
+        abbreviation: &str,
+        position: InsertPosition,
+    ) -> Result<Vec<Handle>, AbbreviationError> {
+        if self.check_insertable(position).is_err() {
+            return Err(AbbreviationError {
+                position: 0,
+                message: "insertion point is frozen",
+            });
+        }
+        let mut parser = AbbreviationParser {
+            input: abbreviation,
+            pos: 0,
+        };
+        let items = parser.sequence()?;
+        if parser.pos != abbreviation.len() {
+            return Err(parser.error("unexpected character"));
+        }
+        node_count(&items)?;
+        let mut roots = vec![];
+        for item in &items {
+            self.build_item(item, 1, &mut roots);
+        }
+        self.insert_at(&roots, position);
+        Ok(roots)
+    }
+
+    /// Build a new document whose children are described by `abbreviation`, e.g.
+    /// `html>(head>title{Fixture})+body>main>p*3`.
+    pub fn from_abbreviation(
+        abbreviation: &str,
+    ) -> Result<GenerationalArenaDom, AbbreviationError> {
+        let mut dom = GenerationalArenaDom::default();
+        let document = dom.document;
+        dom.expand_abbreviation(abbreviation, InsertPosition::AppendTo(document))?;
+        Ok(dom)
+    }
+
+    /// Create the nodes for `item`, numbering with `index` unless it is repeated itself.
+    fn build_item(&mut self, item: &Item, index: usize, out: &mut Vec<Handle>) {
+        for repetition in 1..=item.repeat {
+            let index = if item.repeat > 1 { repetition } else { index };
+            let mut created = vec![];
+            match &item.kind {
+                ItemKind::Element { name, attrs, text } => {
+                    let attrs = attrs
+                        .iter()
+                        .map(|(name, value)| Attribute {
+                            name: QualName::new(
+                                None,
+                                ns!(),
+                                LocalName::from(&*number(name, index)),
+                            ),
+                            value: number(value, index).into(),
+                        })
+                        .collect();
+                    let element = self.create_html_element(&number(name, index), attrs);
+                    if let Some(text) = text {
+                        let text = self.create_text(&number(text, index));
+                        element.append(text, &mut self.arena);
+                    }
+                    created.push(element);
+                }
+                ItemKind::Text(text) => created.push(self.create_text(&number(text, index))),
+                ItemKind::Group(items) => {
+                    for item in items {
+                        self.build_item(item, index, &mut created);
+                    }
+                }
+            }
+            if let Some(&parent) = created.last() {
+                let mut children = vec![];
+                for child in &item.children {
+                    self.build_item(child, index, &mut children);
+                }
+                for child in children {
+                    parent.append(child, &mut self.arena);
+                }
+            }
+            out.extend(created);
+        }
+    }
+}
+
+/// Replace each run of `$` in `template` with `index`, zero-padded to the run's length.
+fn number(template: &str, index: usize) -> String {
+    let mut numbered = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            numbered.push(c);
+            continue;
+        }
+        let mut width = 1;
+        while chars.next_if_eq(&'$').is_some() {
+            width += 1;
+        }
+        numbered.push_str(&format!("{:0width$}", index, width = width));
+    }
+    numbered
+}
+
+struct AbbreviationParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> AbbreviationParser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn error(&self, message: &'static str) -> AbbreviationError {
+        AbbreviationError {
+            position: self.pos,
+            message,
+        }
+    }
+
+    /// Parse items joined by operators, up to the end of input or a closing `)`.
+    fn sequence(&mut self) -> Result<Vec<Item>, AbbreviationError> {
+        // Each level holds the siblings being built at one depth; `>` opens a level for the
+        // children of the last item and `^` closes one.
+        let mut levels: Vec<Vec<Item>> = vec![vec![self.item()?]];
+        loop {
+            match self.peek() {
+                Some('>') => {
+                    self.pos += 1;
+                    levels.push(vec![self.item()?]);
+                }
+                Some('+') => {
+                    self.pos += 1;
+                    let item = self.item()?;
+                    levels.last_mut().unwrap().push(item);
+                }
+                Some('^') => {
+                    while self.peek() == Some('^') {
+                        self.pos += 1;
+                        if levels.len() > 1 {
+                            close_level(&mut levels);
+                        }
+                    }
+                    let item = self.item()?;
+                    levels.last_mut().unwrap().push(item);
+                }
+                _ => break,
+            }
+        }
+        while levels.len() > 1 {
+            close_level(&mut levels);
+        }
+        Ok(levels.pop().unwrap())
+    }
+
+    fn item(&mut self) -> Result<Item, AbbreviationError> {
+        let kind = match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let items = self.sequence()?;
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected `)`"));
+                }
+                self.pos += 1;
+                ItemKind::Group(items)
+            }
+            _ => self.element()?,
+        };
+        let repeat_at = self.pos;
+        let mut repeat = 1;
+        if self.peek() == Some('*') {
+            self.pos += 1;
+            let digits = self.take_while(|c| c.is_ascii_digit());
+            if digits.is_empty() {
+                return Err(self.error("expected a repetition count"));
+            }
+            repeat = digits.parse().map_err(|_| AbbreviationError {
+                position: repeat_at,
+                message: "repetition count too large",
+            })?;
+        }
+        Ok(Item {
+            kind,
+            repeat,
+            repeat_at,
+            children: vec![],
+        })
+    }
+
+    fn element(&mut self) -> Result<ItemKind, AbbreviationError> {
+        let start = self.pos;
+        let name = self.take_while(is_name_char).to_string();
+        let mut attrs: Vec<(String, String)> = vec![];
+        let mut classes: Vec<String> = vec![];
+        let mut text = None;
+        loop {
+            match self.peek() {
+                Some('#') => {
+                    self.pos += 1;
+                    let id = self.name()?;
+                    attrs.push(("id".to_string(), id));
+                }
+                Some('.') => {
+                    self.pos += 1;
+                    classes.push(self.name()?);
+                }
+                Some('[') => {
+                    self.pos += 1;
+                    self.attributes(&mut attrs)?;
+                }
+                Some('{') => {
+                    self.pos += 1;
+                    let len = self.input[self.pos..]
+                        .find('}')
+                        .ok_or_else(|| self.error("expected `}`"))?;
+                    text = Some(self.input[self.pos..self.pos + len].to_string());
+                    self.pos += len + 1;
+                }
+                _ => break,
+            }
+        }
+        if self.pos == start {
+            return Err(self.error("expected an element"));
+        }
+        if !classes.is_empty() {
+            attrs.push(("class".to_string(), classes.join(" ")));
+        }
+        if name.is_empty() && attrs.is_empty() {
+            if let Some(text) = text {
+                return Ok(ItemKind::Text(text));
+            }
+        }
+        let name = if name.is_empty() {
+            "div".to_string()
+        } else {
+            name
+        };
+        Ok(ItemKind::Element { name, attrs, text })
+    }
+
+    fn attributes(&mut self, attrs: &mut Vec<(String, String)>) -> Result<(), AbbreviationError> {
+        loop {
+            self.take_while(|c| c == ' ');
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(());
+            }
+            let name = self.name()?;
+            let value = if self.peek() == Some('=') {
+                self.pos += 1;
+                match self.peek() {
+                    Some(quote @ ('"' | '\'')) => {
+                        self.pos += 1;
+                        let len = self.input[self.pos..]
+                            .find(quote)
+                            .ok_or_else(|| self.error("unterminated string"))?;
+                        let value = self.input[self.pos..self.pos + len].to_string();
+                        self.pos += len + 1;
+                        value
+                    }
+                    _ => self.take_while(|c| c != ' ' && c != ']').to_string(),
+                }
+            } else {
+                String::new()
+            };
+            attrs.push((name, value));
+        }
+    }
+
+    fn name(&mut self) -> Result<String, AbbreviationError> {
+        let name = self.take_while(is_name_char);
+        if name.is_empty() {
+            return Err(self.error("expected a name"));
+        }
+        Ok(name.to_string())
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|&c| predicate(c)) {
+            self.pos += c.len_utf8();
+        }
+        &self.input[start..self.pos]
+    }
+}
+
+/// How many nodes `items` create, or an error at the repetition that takes it over
+/// [`MAX_NODES`].
+fn node_count(items: &[Item]) -> Result<usize, AbbreviationError> {
+    let mut total: usize = 0;
+    for item in items {
+        let own = match &item.kind {
+            ItemKind::Element { text, .. } => 1 + text.is_some() as usize,
+            ItemKind::Text(_) => 1,
+            ItemKind::Group(items) => node_count(items)?,
+        };
+        let once = own + node_count(&item.children)?;
+        total = once
+            .checked_mul(item.repeat)
+            .and_then(|count| count.checked_add(total))
+            .filter(|&count| count <= MAX_NODES)
+            .ok_or(AbbreviationError {
+                position: item.repeat_at,
+                message: "repetition count too large",
+            })?;
+    }
+    Ok(total)
+}
+
+/// Make the innermost level the children of the last item on the level above it.
+fn close_level(levels: &mut Vec<Vec<Item>>) {
+    let children = levels.pop().unwrap();
+    if let Some(parent) = levels.last_mut().and_then(|level| level.last_mut()) {
+        parent.children.extend(children);
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GenerationalArenaDom, InsertPosition};
+
+    fn html(abbreviation: &str) -> String {
+        let dom = GenerationalArenaDom::from_abbreviation(abbreviation).unwrap();
+        dom.inner_html(dom.document)
+    }
+
+    #[test]
+    fn expands_abbreviations() {
+        assert_eq!(
+            html("ul>li.item$*3>a[href=#]{Item $}"),
+            "<ul><li class=\"item1\"><a href=\"#\">Item 1</a></li>\
+             <li class=\"item2\"><a href=\"#\">Item 2</a></li>\
+             <li class=\"item3\"><a href=\"#\">Item 3</a></li></ul>"
+        );
+        assert_eq!(
+            html("#main.a.b>p[title=\"x y\" lang=en]+p{$$}*2^footer"),
+            "<div id=\"main\" class=\"a b\"><p title=\"x y\" lang=\"en\"></p><p>01</p><p>02</p>\
+             </div><footer></footer>"
+        );
+        assert_eq!(html("(dt+dd)*2"), "<dt></dt><dd></dd><dt></dt><dd></dd>");
+    }
+
+    #[test]
+    fn expands_at_positions() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("p#a+p#b").unwrap();
+        let b = dom.query_selector(dom.document, "#b").unwrap().unwrap();
+        let created = dom
+            .expand_abbreviation("hr", InsertPosition::Before(b))
+            .unwrap();
+        assert_eq!(dom.arena[b].previous_sibling(), Some(created[0]));
+
+        dom.freeze(b);
+        let error = dom
+            .expand_abbreviation("span", InsertPosition::AppendTo(b))
+            .unwrap_err();
+        assert_eq!(error.message, "insertion point is frozen");
+    }
+
+    #[test]
+    fn reports_errors() {
+        for (abbreviation, position) in [("p>", 2), ("(p", 2), ("p[a=b", 5), ("p}", 1)] {
+            let error = match GenerationalArenaDom::from_abbreviation(abbreviation) {
+                Ok(_) => panic!("{} should not expand", abbreviation),
+                Err(error) => error,
+            };
+            assert_eq!(error.position, position, "{}", abbreviation);
+        }
+    }
+
+    #[test]
+    fn refuses_too_many_nodes() {
+        for (abbreviation, position) in [
+            ("p*999999999", 1),
+            ("(a*10000)*10000", 9),
+            ("ul>li*1000>a{x}*1000", 5),
+            ("p*99999999999999999999999", 1),
+        ] {
+            let error = GenerationalArenaDom::from_abbreviation(abbreviation)
+                .err()
+                .unwrap();
+            assert_eq!(error.position, position, "{}", abbreviation);
+        }
+        assert_eq!(
+            GenerationalArenaDom::from_abbreviation("p*100001")
+                .err()
+                .map(|error| error.message),
+            Some("repetition count too large")
+        );
+        let dom = GenerationalArenaDom::from_abbreviation("(p*100)*1000").unwrap();
+        assert_eq!(dom.arena.count(), 100_001);
+    }
+}
@@ -0,0 +1,158 @@
+//! Limiting the length of attribute values.
+
+use std::fmt;
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::{Attribute, QualName};
+
+/// An attribute value that exceeded an [`AttributeLimit`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OversizedAttribute {
+    /// The name of the element the attribute is on.
+    pub element: QualName,
+
+    /// The name of the attribute.
+    pub name: QualName,
+
+    /// The length of the value the parser produced, in bytes.
+    pub length: usize,
+
+    /// Whether the value was truncated, rather than the attribute removed.
+    pub truncated: bool,
+}
+
+/// A limit on the length of attribute values, for
+/// [`DomOptions::attribute_limit`](crate::DomOptions::attribute_limit).
+///
+/// Hostile or broken pages can have attribute values megabytes long, such as inlined `data:`
+/// URLs or runaway quoting. Values longer than the limit are truncated or removed as elements
+/// are created, and the callback is invoked with each one so that the caller can log what was
+/// cut.
+pub struct AttributeLimit {
+    max_len: usize,
+    truncate: bool,
+    callback: Box<dyn FnMut(&OversizedAttribute)>,
+}
+
+impl AttributeLimit {
+    /// Cut values longer than `max_len` bytes down to at most `max_len` bytes, at a character
+    /// boundary, invoking `callback` for each.
+    pub fn truncate(
+        max_len: usize,
+        callback: impl FnMut(&OversizedAttribute) + 'static,
+    ) -> AttributeLimit {
+        AttributeLimit {
+            max_len,
+            truncate: true,
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Remove attributes with values longer than `max_len` bytes, invoking `callback` for
+    /// each.
+    pub fn remove(
+        max_len: usize,
+        callback: impl FnMut(&OversizedAttribute) + 'static,
+    ) -> AttributeLimit {
+        AttributeLimit {
+            max_len,
+            truncate: false,
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Apply the limit to `attrs`, the attributes of an `element`.
+    pub(crate) fn apply(&mut self, element: &QualName, attrs: &mut Vec<Attribute>) {
+        let max_len = self.max_len;
+        attrs.retain_mut(|attr| {
+            if attr.value.len() <= max_len {
+                return true;
+            }
+            (self.callback)(&OversizedAttribute {
+                element: element.clone(),
+                name: attr.name.clone(),
+                length: attr.value.len(),
+                truncated: self.truncate,
+            });
+            if self.truncate {
+                let mut end = max_len;
+                while !attr.value.is_char_boundary(end) {
+                    end -= 1;
+                }
+                attr.value = StrTendril::from(&attr.value[..end]);
+            }
+            self.truncate
+        });
+    }
+}
+
+impl fmt::Debug for AttributeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AttributeLimit")
+            .field("max_len", &self.max_len)
+            .field("truncate", &self.truncate)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{AttributeLimit, OversizedAttribute};
+    use crate::parse::{body_html, parse_html_with};
+    use crate::DomOptions;
+
+    fn parse(html: &str, truncate: bool) -> (String, Vec<(String, String, usize, bool)>) {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let log = Rc::clone(&seen);
+        let callback = move |oversized: &OversizedAttribute| {
+            log.borrow_mut().push((
+                oversized.element.local.to_string(),
+                oversized.name.local.to_string(),
+                oversized.length,
+                oversized.truncated,
+            ))
+        };
+        let limit = if truncate {
+            AttributeLimit::truncate(4, callback)
+        } else {
+            AttributeLimit::remove(4, callback)
+        };
+        let opts = DomOptions {
+            attribute_limit: Some(limit),
+            ..DomOptions::default()
+        };
+        let dom = parse_html_with(html, opts);
+        let seen = seen.borrow().clone();
+        (body_html(&dom), seen)
+    }
+
+    #[test]
+    fn truncates_long_values() {
+        let (html, seen) = parse(
+            "<p title=abcdef class=abcd>x</p><img alt=abcé><body data-x=123456>",
+            true,
+        );
+        assert_eq!(
+            html,
+            "<p title=\"abcd\" class=\"abcd\">x</p><img alt=\"abc\">"
+        );
+        assert_eq!(
+            seen,
+            [
+                ("p".to_string(), "title".to_string(), 6, true),
+                ("img".to_string(), "alt".to_string(), 5, true),
+                ("body".to_string(), "data-x".to_string(), 6, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn removes_long_values() {
+        let (html, seen) = parse("<p title=abcdef class=abcd>x</p>", false);
+        assert_eq!(html, "<p class=\"abcd\">x</p>");
+        assert_eq!(seen, [("p".to_string(), "title".to_string(), 6, false)]);
+    }
+}
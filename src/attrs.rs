@@ -0,0 +1,299 @@
+//! Reading and writing attributes.
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::{GenerationalArenaDom, Handle, MutationError, NodeData, NodeFilter};
+
+/// HTML attributes whose meaning comes from their presence alone.
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "controls",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nomodule",
+    "novalidate",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+];
+
+/// Whether `name` is an HTML [boolean attribute], like `disabled` or `checked`.
+///
+/// [boolean attribute]: https://html.spec.whatwg.org/multipage/#boolean-attributes
+pub fn is_boolean_attribute(name: &str) -> bool {
+    BOOLEAN_ATTRIBUTES
+        .iter()
+        .any(|boolean| boolean.eq_ignore_ascii_case(name))
+}
+
+impl GenerationalArenaDom {
+    /// Whether the boolean attribute `name` is set on `handle`.
+    ///
+    /// A boolean attribute is on whenever it is present, whatever its value: even
+    /// `disabled="false"` disables an element.
+    pub fn has_bool_attr(&self, handle: Handle, name: &str) -> bool {
+        self.get_attr(handle, name).is_some()
+    }
+
+    /// Turn the boolean attribute `name` on `handle` on or off, by adding it with an empty value
+    /// or removing it. An attribute that is already present keeps its value. Fails if `handle`
+    /// is [frozen](GenerationalArenaDom::freeze).
+    pub fn set_bool_attr(
+        &mut self,
+        handle: Handle,
+        name: &str,
+        value: bool,
+    ) -> Result<(), MutationError> {
+        self.check_mutable(handle)?;
+        match (value, self.has_bool_attr(handle, name)) {
+            (true, false) => self.set_attr(handle, name, StrTendril::new()),
+            (false, true) => {
+                self.remove_attr(handle, name);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Rewrite the attributes of the elements under `root`, including `root` itself, that
+    /// `filter` matches, returning how many attributes changed or were removed.
+    ///
+    /// `rewrite` is called with the local name and value of each attribute of a matching
+    /// element, and returns the value to keep, which may be the same one, or `None` to remove
+    /// the attribute. Elements are matched before any attribute is rewritten, so rewrites don't
+    /// change which elements are visited. Frozen elements are skipped.
+    ///
+    /// For URLs, [`rewrite_urls`](GenerationalArenaDom::rewrite_urls) also knows which
+    /// attributes hold them and how to split lists such as `srcset`.
+    pub fn rewrite_attrs<P, F>(&mut self, root: Handle, filter: &P, mut rewrite: F) -> usize
+    where
+        P: NodeFilter + ?Sized,
+        F: FnMut(&str, &str) -> Option<String>,
+    {
+        let elements: Vec<Handle> = root
+            .descendants(&self.arena)
+            .filter(|&node| self.element_name(node).is_some() && filter.matches(self, node))
+            .collect();
+        let mut changed = 0;
+        for element in elements {
+            let attrs = match self.get_node(&element) {
+                NodeData::Element { attrs, .. } => attrs,
+                _ => continue,
+            };
+            let rewritten: Vec<Option<String>> = attrs
+                .borrow()
+                .iter()
+                .map(|attr| rewrite(&attr.name.local, &attr.value))
+                .collect();
+            let changes = attrs
+                .borrow()
+                .iter()
+                .zip(&rewritten)
+                .filter(|(attr, value)| value.as_deref() != Some(&*attr.value))
+                .count();
+            if changes == 0 || self.skip_frozen(element) {
+                continue;
+            }
+            let mut rewritten = rewritten.into_iter();
+            attrs
+                .borrow_mut()
+                .retain_mut(|attr| match rewritten.next().flatten() {
+                    Some(value) => {
+                        if *value != *attr.value {
+                            attr.value = StrTendril::from(value);
+                        }
+                        true
+                    }
+                    None => false,
+                });
+            changed += changes;
+        }
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        changed
+    }
+
+    /// The value of the attribute called `name` (with no namespace) on `handle`.
+    pub(crate) fn get_attr(&self, handle: Handle, name: &str) -> Option<StrTendril> {
+        match self.arena.get(handle)?.get() {
+            NodeData::Element { attrs, .. } => attrs
+                .borrow()
+                .iter()
+                .find(|attr| attr.name.ns == ns!() && &*attr.name.local == name)
+                .map(|attr| attr.value.clone()),
+            _ => None,
+        }
+    }
+
+    /// Set the attribute called `name` (with no namespace) on `handle`, replacing any existing
+    /// value. Does nothing if `handle` isn't an element or is frozen.
+    pub(crate) fn set_attr(&mut self, handle: Handle, name: &str, value: StrTendril) {
+        if self.skip_frozen(handle) {
+            return;
+        }
+        if let NodeData::Element { attrs, .. } = self.get_node(&handle) {
+            let mut attrs = attrs.borrow_mut();
+            match attrs
+                .iter_mut()
+                .find(|attr| attr.name.ns == ns!() && &*attr.name.local == name)
+            {
+                Some(attr) => attr.value = value,
+                None => attrs.push(Attribute {
+                    name: QualName::new(None, ns!(), LocalName::from(name)),
+                    value,
+                }),
+            }
+        }
+        self.mark_mutated();
+    }
+
+    /// Remove the attribute called `name` (with no namespace) from `handle`, returning its value.
+    /// Does nothing if `handle` is frozen.
+    pub(crate) fn remove_attr(&mut self, handle: Handle, name: &str) -> Option<StrTendril> {
+        if self.skip_frozen(handle) {
+            return None;
+        }
+        let removed = match self.get_node(&handle) {
+            NodeData::Element { attrs, .. } => {
+                let mut attrs = attrs.borrow_mut();
+                let index = attrs
+                    .iter()
+                    .position(|attr| attr.name.ns == ns!() && &*attr.name.local == name)?;
+                Some(attrs.remove(index).value)
+            }
+            _ => None,
+        };
+        self.mark_mutated();
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_boolean_attribute;
+    use crate::parse::{body_html, parse_html};
+    use crate::{GenerationalArenaDom, Handle, MutationError, Selector, SerializeOpts};
+
+    #[test]
+    fn reads_and_writes_boolean_attributes() {
+        assert!(is_boolean_attribute("Disabled"));
+        assert!(!is_boolean_attribute("value"));
+
+        let mut dom = parse_html("<input disabled=false><input checked=checked>");
+        let inputs = dom.select(dom.document, &"input".parse().unwrap());
+        assert!(dom.has_bool_attr(inputs[0], "disabled"));
+        assert!(!dom.has_bool_attr(inputs[0], "checked"));
+
+        dom.set_bool_attr(inputs[0], "disabled", false).unwrap();
+        dom.set_bool_attr(inputs[0], "required", true).unwrap();
+        dom.set_bool_attr(inputs[1], "checked", true).unwrap();
+        assert_eq!(dom.outer_html(inputs[0]), "<input required=\"\">");
+        let minimized = SerializeOpts {
+            minimize_boolean_attributes: true,
+            ..SerializeOpts::default()
+        };
+        assert_eq!(dom.serialize(inputs[0], &minimized), "<input required>");
+        assert_eq!(dom.serialize(inputs[1], &minimized), "<input checked>");
+
+        dom.freeze(inputs[1]);
+        assert_eq!(
+            dom.set_bool_attr(inputs[1], "checked", false),
+            Err(MutationError::Frozen { node: inputs[1] })
+        );
+    }
+
+    #[test]
+    fn rewrites_attributes_of_selected_elements() {
+        let mut dom = parse_html(
+            "<a href=\"http://a/\" title=\"http://t\">a</a><img src=\"http://i/\">\
+             <a href=\"https://b/\">b</a>",
+        );
+        let selector = Selector::parse("a").unwrap();
+        let changed = dom.rewrite_attrs(dom.document, &selector, |name, value| {
+            Some(match name {
+                "href" | "src" => value.replace("http://", "https://"),
+                _ => value.to_string(),
+            })
+        });
+        assert_eq!(changed, 1);
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"https://a/\" title=\"http://t\">a</a><img src=\"http://i/\">\
+             <a href=\"https://b/\">b</a>"
+        );
+    }
+
+    #[test]
+    fn rewrites_attributes_of_elements_matching_a_predicate() {
+        let mut dom = parse_html("<p id=a class=x>a</p><p id=b>b</p><div id=c class=x></div>");
+        let has_class =
+            |dom: &GenerationalArenaDom, handle: Handle| dom.get_attr(handle, "class").is_some();
+        let changed = dom.rewrite_attrs(dom.document, &has_class, |name, value| {
+            Some(if name == "id" {
+                format!("x-{}", value)
+            } else {
+                value.to_string()
+            })
+        });
+        assert_eq!(changed, 2);
+        assert_eq!(
+            body_html(&dom),
+            "<p id=\"x-a\" class=\"x\">a</p><p id=\"b\">b</p><div id=\"x-c\" class=\"x\"></div>"
+        );
+    }
+
+    #[test]
+    fn removes_attributes_rewritten_to_none() {
+        let mut dom = parse_html(
+            "<a href=h onclick=x() data-track=1 title=t>a</a><b onload=y()>b</b><i>c</i>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let version = dom.version();
+        let all = |_: &GenerationalArenaDom, _: Handle| true;
+        let changed = dom.rewrite_attrs(body, &all, |name, value| {
+            (!name.starts_with("on") && !name.starts_with("data-")).then(|| value.to_string())
+        });
+        assert_eq!(changed, 3);
+        assert!(dom.version() > version);
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"h\" title=\"t\">a</a><b>b</b><i>c</i>"
+        );
+
+        let version = dom.version();
+        assert_eq!(
+            dom.rewrite_attrs(body, &all, |_, value| Some(value.to_string())),
+            0
+        );
+        assert_eq!(dom.version(), version);
+    }
+
+    #[test]
+    fn skips_frozen_elements_when_rewriting() {
+        let mut dom = parse_html("<p title=a>a</p><p title=b>b</p>");
+        let ps = dom.select(dom.document, &"p".parse().unwrap());
+        dom.freeze(ps[0]);
+        let changed =
+            dom.rewrite_attrs(dom.document, &"p".parse::<Selector>().unwrap(), |_, _| None);
+        assert_eq!(changed, 1);
+        assert_eq!(body_html(&dom), "<p title=\"a\">a</p><p>b</p>");
+    }
+}
@@ -0,0 +1,89 @@
+//! Caching the results of selector queries.
+
+use std::collections::HashMap;
+
+use crate::{GenerationalArenaDom, Handle, Selector, SelectorError};
+
+/// A cache of [`select`](GenerationalArenaDom::select) results keyed by selector and scope.
+///
+/// Entries are tied to the [`version`](GenerationalArenaDom::version) of the DOM they were
+/// computed from: any mutation through the DOM's methods invalidates the whole cache. Mutations
+/// made directly through the arena must be reported with
+/// [`mark_mutated`](GenerationalArenaDom::mark_mutated) to have the same effect.
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    /// The DOM and version the entries were computed from.
+    source: Option<(u64, u64)>,
+    selectors: HashMap<String, Selector>,
+    results: HashMap<(String, Handle), Vec<Handle>>,
+}
+
+impl QueryCache {
+    /// Create an empty cache.
+    pub fn new() -> QueryCache {
+        QueryCache::default()
+    }
+
+    /// The elements under `scope` matching `selector`, computed at most once per version of
+    /// `dom`.
+    pub fn select(
+        &mut self,
+        dom: &GenerationalArenaDom,
+        scope: Handle,
+        selector: &str,
+    ) -> Result<&[Handle], SelectorError> {
+        let source = Some((dom.id, dom.version()));
+        if self.source != source {
+            self.results.clear();
+            self.source = source;
+        }
+        let key = (selector.to_string(), scope);
+        if !self.results.contains_key(&key) {
+            if !self.selectors.contains_key(selector) {
+                self.selectors
+                    .insert(selector.to_string(), Selector::parse(selector)?);
+            }
+            let matches = dom.select(scope, &self.selectors[selector]);
+            self.results.insert(key.clone(), matches);
+        }
+        Ok(&self.results[&key])
+    }
+
+    /// Drop every cached result.
+    pub fn clear(&mut self) {
+        self.results.clear();
+        self.source = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryCache;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn invalidates_on_mutation() {
+        let mut dom = parse_html("<p>a</p><p>b</p>");
+        let mut cache = QueryCache::new();
+        let paragraphs = cache.select(&dom, dom.document, "p").unwrap().to_vec();
+        assert_eq!(paragraphs.len(), 2);
+
+        // Detaching through the arena isn't seen until it is reported.
+        paragraphs[0].detach(&mut dom.arena);
+        assert_eq!(cache.select(&dom, dom.document, "p").unwrap().len(), 2);
+        dom.mark_mutated();
+        assert_eq!(
+            cache.select(&dom, dom.document, "p").unwrap(),
+            &paragraphs[1..]
+        );
+
+        // Mutations through the DOM's methods are seen straight away.
+        dom.remove_subtree(paragraphs[1]).unwrap();
+        assert!(cache.select(&dom, dom.document, "p").unwrap().is_empty());
+
+        // Results from another DOM aren't reused, even at the same version.
+        let other = parse_html("<p>c</p>");
+        assert_eq!(cache.select(&other, other.document, "p").unwrap().len(), 1);
+        assert!(cache.select(&other, other.document, "p[").is_err());
+    }
+}
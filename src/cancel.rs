@@ -0,0 +1,74 @@
+//! Cooperative cancellation of parsing.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A flag that can be used to abort parsing from another thread.
+///
+/// Clones share the same flag: pass one to [`DomOptions::cancellation`](crate::DomOptions) and
+/// keep another to call [`cancel`](CancellationToken::cancel) on, e.g. when the client that
+/// requested the page disconnects. The DOM checks the flag every time the parser creates a node.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that hasn't been cancelled.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Request that any parse using this token stops.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`cancel`](CancellationToken::cancel) has been called on this token or a clone.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CancellationToken;
+    use crate::parse::parse_html_with;
+    use crate::{AbortError, DomOptions, ProgressReporter};
+
+    /// Misnested markup after the point where the parse is cancelled still goes through the tree
+    /// builder's adoption agency.
+    const HTML: &str = "<ul><li>1<li>2<li>3<li>4<li>5<li>6</ul><p><b><i>x</p><div>y</b></div>";
+
+    #[test]
+    fn aborts_once_cancelled() {
+        let token = CancellationToken::new();
+        let dom = parse_html_with(
+            HTML,
+            DomOptions {
+                cancellation: Some(token.clone()),
+                ..DomOptions::default()
+            },
+        );
+        assert_eq!(dom.abort_error(), None);
+        assert!(!token.is_cancelled());
+        let full = dom.arena.count();
+
+        // Cancel part way through, as another thread would.
+        let canceller = token.clone();
+        let dom = parse_html_with(
+            HTML,
+            DomOptions {
+                cancellation: Some(token.clone()),
+                progress: Some(ProgressReporter::every_nodes(6, move |_| {
+                    canceller.cancel()
+                })),
+                ..DomOptions::default()
+            },
+        );
+        assert!(token.is_cancelled());
+        assert_eq!(dom.abort_error(), Some(&AbortError::Cancelled));
+        assert!(dom.arena.count() < full);
+        assert_eq!(dom.into_result().err(), Some(AbortError::Cancelled));
+    }
+}
@@ -0,0 +1,71 @@
+//! A normalized serialization for snapshot tests.
+
+use crate::transform::WhitespaceOptions;
+use crate::{AttributeOrder, GenerationalArenaDom, SerializeOpts};
+
+impl GenerationalArenaDom {
+    /// Serialize the document in a canonical form suited to golden-file snapshot tests.
+    ///
+    /// Whitespace runs in text are collapsed and whitespace-only text nodes dropped (except
+    /// where whitespace is significant, such as in `<pre>`), attributes are sorted by name, and
+    /// every element is written with explicit start and end tags, including the ones the parser
+    /// implied. Two documents that only differ in formatting produce the same string. The DOM
+    /// itself isn't modified.
+    pub fn canonical_string(&self) -> String {
+        let copy = self.canonical_copy();
+        copy.serialize(copy.document, &canonical_opts())
+    }
+
+    /// A copy of the document with whitespace normalized as in
+    /// [`canonical_string`](GenerationalArenaDom::canonical_string).
+    pub(crate) fn canonical_copy(&self) -> GenerationalArenaDom {
+        let mut copy = GenerationalArenaDom::default();
+        for child in self.document.children(&self.arena) {
+            let child = copy.import_subtree(self, child);
+            copy.document.append(child, &mut copy.arena);
+        }
+        let document = copy.document;
+        copy.collapse_whitespace(
+            document,
+            &WhitespaceOptions {
+                remove_blank: true,
+                ..WhitespaceOptions::default()
+            },
+        );
+        copy
+    }
+}
+
+/// The serialization options used for canonical output.
+pub(crate) fn canonical_opts() -> SerializeOpts {
+    SerializeOpts {
+        attribute_order: AttributeOrder::Alphabetical,
+        ..SerializeOpts::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_html;
+
+    #[test]
+    fn ignores_formatting_differences() {
+        let compact = parse_html("<p class=a id=b>x  y</p><pre>  kept\n</pre>");
+        let spread = parse_html(
+            "<html>\n<body>\n  <p id=\"b\" class=\"a\">x\n y</p>\n  <pre>  kept\n</pre>\n</body>",
+        );
+        assert_eq!(compact.canonical_string(), spread.canonical_string());
+        assert_eq!(
+            compact.canonical_string(),
+            "<html><head></head><body><p class=\"a\" id=\"b\">x y</p><pre>  kept\n</pre></body></html>"
+        );
+    }
+
+    #[test]
+    fn leaves_the_document_alone() {
+        let dom = parse_html("<p b=1 a=2>x  y</p>");
+        let before = dom.serialize(dom.document, &Default::default());
+        dom.canonical_string();
+        assert_eq!(dom.serialize(dom.document, &Default::default()), before);
+    }
+}
@@ -0,0 +1,240 @@
+//! Copying nodes within and between DOMs.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::{DomOptions, GenerationalArenaDom, Handle, MutationError, NodeData};
+
+/// Whether [`GenerationalArenaDom::extract_to_document`] leaves the subtree in place.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtractMode {
+    /// Copy the subtree, leaving the source document unchanged.
+    Copy,
+    /// Remove the subtree from the source document, freeing its nodes there.
+    Move,
+}
+
+impl GenerationalArenaDom {
+    /// Put `handle` and its descendants into a new DOM under a fresh document node.
+    ///
+    /// This is useful for splitting a page into one document per article. If `handle` is itself a
+    /// document node its children are extracted instead. The new DOM has the source's quirks mode
+    /// and no parse errors. With [`ExtractMode::Move`], fails if any of the nodes to move is
+    /// [frozen](GenerationalArenaDom::freeze).
+    pub fn extract_to_document(
+        &mut self,
+        handle: Handle,
+        mode: ExtractMode,
+    ) -> Result<GenerationalArenaDom, MutationError> {
+        if mode == ExtractMode::Copy {
+            return Ok(self.copy_to_document(handle));
+        }
+        let roots = self.extraction_roots(handle);
+        for &root in &roots {
+            self.check_removable(root)?;
+        }
+        let target = self.copy_to_document(handle);
+        for root in roots {
+            self.free_subtree(root);
+        }
+        Ok(target)
+    }
+
+    /// Copy `handle` and its descendants into a new DOM under a fresh document node, as
+    /// [`extract_to_document`](GenerationalArenaDom::extract_to_document) does with
+    /// [`ExtractMode::Copy`].
+    pub(crate) fn copy_to_document(&self, handle: Handle) -> GenerationalArenaDom {
+        let mut target = GenerationalArenaDom::with_options(DomOptions::default());
+        target.quirks_mode = self.quirks_mode;
+        for root in self.extraction_roots(handle) {
+            let copy = target.import_subtree(self, root);
+            target.document.append(copy, &mut target.arena);
+        }
+        target
+    }
+
+    /// The nodes extracting `handle` copies: its children if it's a document, or else itself.
+    fn extraction_roots(&self, handle: Handle) -> Vec<Handle> {
+        match self.get_node(&handle) {
+            NodeData::Document => handle.children(&self.arena).collect(),
+            _ => vec![handle],
+        }
+    }
+
+    /// Copy `handle` and its descendants out of `source` into this DOM, returning the detached
+    /// copy. Template contents are copied along with their elements.
+    pub fn import_subtree(&mut self, source: &GenerationalArenaDom, handle: Handle) -> Handle {
+        let plan = source.plan_copy(handle);
+        self.build_copy(plan)
+    }
+
+    /// Copy `handle` and its descendants, returning the detached copy.
+    pub fn clone_subtree(&mut self, handle: Handle) -> Handle {
+        let plan = self.plan_copy(handle);
+        self.build_copy(plan)
+    }
+
+    /// The data of `handle`, its descendants and their template contents, in the order their
+    /// copies are to be created, each with where its copy goes. This walks the tree with
+    /// explicit stacks, so deeply nested documents can't overflow the call stack.
+    fn plan_copy(&self, handle: Handle) -> Vec<(NodeData, Option<CopyLink>)> {
+        let mut plan = Vec::new();
+        let mut planned: HashMap<Handle, usize> = HashMap::new();
+        let mut roots = vec![(handle, None)];
+        while let Some((root, link)) = roots.pop() {
+            for node in root.descendants(&self.arena) {
+                let link = if node == root {
+                    link
+                } else {
+                    let parent = self.arena[node].parent().expect("descendants have parents");
+                    Some(CopyLink::ChildOf(planned[&parent]))
+                };
+                if let Some(contents) = self.template_contents_of(node) {
+                    roots.push((contents, Some(CopyLink::ContentsOf(plan.len()))));
+                }
+                planned.insert(node, plan.len());
+                plan.push((copy_data(self.get_node(&node)), link));
+            }
+        }
+        plan
+    }
+
+    /// Create the nodes planned by [`plan_copy`](GenerationalArenaDom::plan_copy), returning the
+    /// copy of its root.
+    fn build_copy(&mut self, plan: Vec<(NodeData, Option<CopyLink>)>) -> Handle {
+        let mut copies: Vec<Handle> = Vec::with_capacity(plan.len());
+        for (data, link) in plan {
+            let copy = self.arena.new_node(data);
+            match link {
+                Some(CopyLink::ChildOf(parent)) => copies[parent].append(copy, &mut self.arena),
+                Some(CopyLink::ContentsOf(template)) => {
+                    if let NodeData::Element {
+                        template_contents, ..
+                    } = self.get_node(&copies[template])
+                    {
+                        *template_contents.borrow_mut() = Some(copy);
+                    }
+                    self.template_owners.insert(copy, copies[template]);
+                }
+                None => {}
+            }
+            copies.push(copy);
+        }
+        copies[0]
+    }
+}
+
+/// Where a copied node goes, given the index of another copy in the plan.
+#[derive(Clone, Copy, Debug)]
+enum CopyLink {
+    ChildOf(usize),
+    ContentsOf(usize),
+}
+
+/// Copy the data of a node. Template contents are left out, to be linked up by the caller.
+fn copy_data(data: &NodeData) -> NodeData {
+    match data {
+        NodeData::Element {
+            name,
+            attrs,
+            mathml_annotation_xml_integration_point,
+            ..
+        } => NodeData::Element {
+            name: name.clone(),
+            attrs: RefCell::new(attrs.borrow().clone()),
+            template_contents: RefCell::new(None),
+            mathml_annotation_xml_integration_point: *mathml_annotation_xml_integration_point,
+        },
+        other => copy_leaf(other),
+    }
+}
+
+/// Copy the data of a node that can't refer to other nodes.
+fn copy_leaf(data: &NodeData) -> NodeData {
+    match data {
+        NodeData::Document => NodeData::Document,
+        NodeData::Doctype {
+            name,
+            public_id,
+            system_id,
+        } => NodeData::Doctype {
+            name: name.clone(),
+            public_id: public_id.clone(),
+            system_id: system_id.clone(),
+        },
+        NodeData::Text { contents } => NodeData::Text {
+            contents: RefCell::new(contents.borrow().clone()),
+        },
+        NodeData::Comment { contents } => NodeData::Comment {
+            contents: contents.clone(),
+        },
+        NodeData::ProcessingInstruction { target, contents } => NodeData::ProcessingInstruction {
+            target: target.clone(),
+            contents: contents.clone(),
+        },
+        NodeData::Element { .. } => unreachable!("elements are copied by the caller"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ExtractMode;
+    use crate::parse::{body_html, parse_html};
+    use crate::GenerationalArenaDom;
+
+    #[test]
+    fn copies_deeply_nested_trees() {
+        let mut dom = GenerationalArenaDom::default();
+        let root = dom.create_html_element("div", vec![]);
+        let mut parent = root;
+        for _ in 0..100_000 {
+            let child = dom.create_html_element("div", vec![]);
+            parent.append(child, &mut dom.arena);
+            parent = child;
+        }
+        let copy = dom.clone_subtree(root);
+        assert_eq!(copy.descendants(&dom.arena).count(), 100_001);
+        let mut other = GenerationalArenaDom::default();
+        let imported = other.import_subtree(&dom, root);
+        assert_eq!(imported.descendants(&other.arena).count(), 100_001);
+    }
+
+    #[test]
+    fn copies_template_contents() {
+        let mut dom = parse_html("<div id=a><template><p>x</p></template>y</div>");
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        let copy = dom.clone_subtree(div);
+        assert!(dom.arena[copy].parent().is_none());
+        assert_eq!(
+            dom.serialize(copy, &Default::default()),
+            "<div id=\"a\"><template><p>x</p></template>y</div>"
+        );
+        let template = dom.arena[copy].first_child().unwrap();
+        let original = dom.arena[div].first_child().unwrap();
+        assert_ne!(
+            dom.template_contents_of(template),
+            dom.template_contents_of(original)
+        );
+        assert!(dom.orphans().contains(&copy));
+    }
+
+    #[test]
+    fn extracts_to_documents() {
+        let mut dom = parse_html("<article><h1>a</h1></article><article>b</article>");
+        let first = dom
+            .query_selector(dom.document, "article")
+            .unwrap()
+            .unwrap();
+        let copied = dom.extract_to_document(first, ExtractMode::Copy).unwrap();
+        assert_eq!(
+            copied.inner_html(copied.document),
+            "<article><h1>a</h1></article>"
+        );
+        let moved = dom.extract_to_document(first, ExtractMode::Move).unwrap();
+        assert_eq!(
+            moved.inner_html(moved.document),
+            "<article><h1>a</h1></article>"
+        );
+        assert_eq!(body_html(&dom), "<article>b</article>");
+    }
+}
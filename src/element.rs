@@ -0,0 +1,236 @@
+//! Typed access to nodes, without matching on [`NodeData`].
+
+use std::cell::{Ref, RefCell};
+use std::fmt;
+
+use markup5ever::{Attribute, QualName};
+
+use crate::{ChildNodes, GenerationalArenaDom, Handle, NodeData};
+
+/// An element of a DOM, from [`GenerationalArenaDom::as_element`].
+#[derive(Clone, Copy)]
+pub struct ElementRef<'a> {
+    dom: &'a GenerationalArenaDom,
+    handle: Handle,
+    name: &'a QualName,
+    attrs: &'a RefCell<Vec<Attribute>>,
+    template_contents: &'a RefCell<Option<Handle>>,
+}
+
+impl GenerationalArenaDom {
+    /// `handle` as an element, if it is one.
+    pub fn as_element(&self, handle: Handle) -> Option<ElementRef<'_>> {
+        match self.arena.get(handle)?.get() {
+            NodeData::Element {
+                name,
+                attrs,
+                template_contents,
+                ..
+            } => Some(ElementRef {
+                dom: self,
+                handle,
+                name,
+                attrs,
+                template_contents,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The contents of `handle`, if it is a text node.
+    pub fn as_text(&self, handle: Handle) -> Option<Ref<'_, str>> {
+        match self.arena.get(handle)?.get() {
+            NodeData::Text { contents } => Some(Ref::map(contents.borrow(), |text| &**text)),
+            _ => None,
+        }
+    }
+
+    /// The contents of `handle`, if it is a comment.
+    pub fn as_comment(&self, handle: Handle) -> Option<&str> {
+        match self.arena.get(handle)?.get() {
+            NodeData::Comment { contents } => Some(contents),
+            _ => None,
+        }
+    }
+
+    /// The local name of `handle`, e.g. `div`, if it is an element. Unlike the DOM's
+    /// `tagName`, it has no prefix and isn't uppercased.
+    pub fn tag_name(&self, handle: Handle) -> Option<&str> {
+        self.element_name(handle).map(|name| &*name.local)
+    }
+
+    /// The value of the attribute called `name` (with no namespace) of `handle`, if it is an
+    /// element that has one.
+    pub fn attr(&self, handle: Handle, name: &str) -> Option<String> {
+        self.get_attr(handle, name).map(|value| value.to_string())
+    }
+}
+
+impl<'a> ElementRef<'a> {
+    /// The DOM the element is in.
+    pub(crate) fn dom(&self) -> &'a GenerationalArenaDom {
+        self.dom
+    }
+
+    /// The element's handle.
+    pub fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// The element's name, with its namespace.
+    pub fn name(&self) -> &'a QualName {
+        self.name
+    }
+
+    /// The element's local name, e.g. `div`.
+    pub fn local_name(&self) -> &'a str {
+        &self.name.local
+    }
+
+    /// The value of the attribute called `name` (with no namespace), if there is one.
+    pub fn attr(&self, name: &str) -> Option<String> {
+        self.dom.attr(self.handle, name)
+    }
+
+    /// Whether the element has an attribute called `name` (with no namespace).
+    pub fn has_attr(&self, name: &str) -> bool {
+        self.dom.get_attr(self.handle, name).is_some()
+    }
+
+    /// All the element's attributes, in order.
+    pub fn attrs(&self) -> Ref<'a, [Attribute]> {
+        Ref::map(self.attrs.borrow(), Vec::as_slice)
+    }
+
+    /// The element's parent, if it has one.
+    pub fn parent(&self) -> Option<Handle> {
+        self.dom.arena[self.handle].parent()
+    }
+
+    /// The element's children, first to last.
+    pub fn children(&self) -> ChildNodes<'a> {
+        self.dom.child_nodes(self.handle)
+    }
+
+    /// The children that are elements, first to last.
+    pub fn child_elements(&self) -> impl DoubleEndedIterator<Item = ElementRef<'a>> + 'a {
+        let dom = self.dom;
+        self.children()
+            .filter_map(move |child| dom.as_element(child))
+    }
+
+    /// The template contents, if this is a `<template>`.
+    pub fn template_contents(&self) -> Option<Handle> {
+        *self.template_contents.borrow()
+    }
+
+    /// The concatenated contents of all text nodes under the element, in document order.
+    pub fn text_content(&self) -> String {
+        self.dom.text_content(self.handle)
+    }
+}
+
+impl fmt::Debug for ElementRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElementRef")
+            .field("handle", &self.handle)
+            .field("name", self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::{namespace_url, ns};
+
+    use crate::parse::parse_html;
+    use crate::GenerationalArenaDom;
+
+    fn dom() -> GenerationalArenaDom {
+        parse_html(
+            "<div id=d class=c>a<!--note--><p>b</p><svg><g></g></svg></div>\
+             <template><i>t</i></template>",
+        )
+    }
+
+    #[test]
+    fn accesses_elements() {
+        let dom = dom();
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        let element = dom.as_element(div).unwrap();
+        assert_eq!(element.handle(), div);
+        assert_eq!(element.local_name(), "div");
+        assert_eq!(element.name().ns, ns!(html));
+        assert_eq!(element.attr("id").as_deref(), Some("d"));
+        assert_eq!(element.attr("title"), None);
+        assert!(element.has_attr("class") && !element.has_attr("title"));
+        let attrs: Vec<(String, String)> = element
+            .attrs()
+            .iter()
+            .map(|attr| (attr.name.local.to_string(), attr.value.to_string()))
+            .collect();
+        assert_eq!(
+            attrs,
+            [("id".into(), "d".into()), ("class".into(), "c".into())]
+        );
+        assert_eq!(element.children().count(), 4);
+        let names: Vec<&str> = element
+            .child_elements()
+            .map(|child| child.local_name())
+            .collect();
+        assert_eq!(names, ["p", "svg"]);
+        assert_eq!(element.child_elements().last().unwrap().name().ns, ns!(svg));
+        assert_eq!(element.text_content(), "ab");
+        assert_eq!(element.template_contents(), None);
+        let body = element.parent().unwrap();
+        assert_eq!(dom.tag_name(body), Some("body"));
+
+        let template = dom
+            .query_selector(dom.document, "template")
+            .unwrap()
+            .unwrap();
+        let contents = dom.as_element(template).unwrap().template_contents();
+        assert_eq!(contents, dom.template_contents_of(template));
+        assert!(contents.is_some());
+
+        assert!(dom.as_element(dom.document).is_none());
+        assert!(dom
+            .as_element(dom.arena[div].first_child().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn accesses_text_and_comments() {
+        let dom = dom();
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        let text = dom.arena[div].first_child().unwrap();
+        let comment = dom.arena[text].next_sibling().unwrap();
+        assert_eq!(dom.as_text(text).as_deref(), Some("a"));
+        assert_eq!(dom.as_comment(comment), Some("note"));
+        assert!(dom.as_text(comment).is_none());
+        assert_eq!(dom.as_comment(text), None);
+        assert!(dom.as_text(div).is_none());
+        assert_eq!(dom.as_comment(div), None);
+    }
+
+    #[test]
+    fn reads_names_and_attributes() {
+        let mut dom = dom();
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        let g = dom.query_selector(dom.document, "g").unwrap().unwrap();
+        let text = dom.arena[div].first_child().unwrap();
+        assert_eq!(dom.tag_name(div), Some("div"));
+        assert_eq!(dom.tag_name(g), Some("g"));
+        assert_eq!(dom.tag_name(text), None);
+        assert_eq!(dom.tag_name(dom.document), None);
+        assert_eq!(dom.attr(div, "class").as_deref(), Some("c"));
+        assert_eq!(dom.attr(div, "missing"), None);
+        assert_eq!(dom.attr(text, "class"), None);
+
+        dom.remove_subtree(div).unwrap();
+        assert!(dom.as_element(div).is_none());
+        assert!(dom.as_text(text).is_none());
+        assert_eq!(dom.tag_name(div), None);
+        assert_eq!(dom.attr(div, "class"), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! Preparing documents to be the content documents of an EPUB.
+//!
+//! [`spine_documents`] turns parsed pages into the XHTML files of a book's spine. Writing the
+//! package document and zipping it all up is left to the caller.
+
+use std::collections::HashMap;
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns, Attribute, LocalName, Prefix, QualName};
+
+use crate::url::resolve;
+use crate::{GenerationalArenaDom, InsertPosition, NodeData, SerializeOpts};
+
+/// Options for [`spine_documents`].
+#[derive(Clone, Debug)]
+pub struct EpubOptions {
+    /// The title given to documents without one of their own. Defaults to `Untitled`.
+    pub title: String,
+
+    /// The language of the book, given to documents that don't declare one. Defaults to none.
+    pub language: Option<String>,
+
+    /// Paths in the package of stylesheets to link from every document. Defaults to none.
+    pub stylesheets: Vec<String>,
+
+    /// What the documents' file names start with, before their number and `.xhtml`. Defaults
+    /// to `chapter`, for `chapter-1.xhtml`, `chapter-2.xhtml` and so on.
+    pub file_prefix: String,
+}
+
+impl Default for EpubOptions {
+    fn default() -> EpubOptions {
+        EpubOptions {
+            title: "Untitled".to_string(),
+            language: None,
+            stylesheets: Vec::new(),
+            file_prefix: "chapter".to_string(),
+        }
+    }
+}
+
+/// A content document made by [`spine_documents`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpineItem {
+    /// An ID for its manifest item and `itemref`, such as `chapter-1`.
+    pub id: String,
+    /// Its path in the package, such as `chapter-1.xhtml`.
+    pub href: String,
+    /// Its title, for the table of contents.
+    pub title: String,
+    /// Its contents, an XHTML document.
+    pub xhtml: String,
+}
+
+/// Turn `documents` into the content documents of an EPUB, in spine order.
+///
+/// Each document is given a `<title>` if it has none (from its first `<h1>`, or else
+/// [`EpubOptions::title`]), the book's language if it declares none, and links to the
+/// [`stylesheets`](EpubOptions::stylesheets). Then its URLs are rewritten for the package:
+/// links to another of `documents`, identified by its [URL](GenerationalArenaDom::url), point
+/// to that document's file, keeping the fragment, and every other URL is resolved against the
+/// document's base URL and passed to `resource`, which returns its path in the package or
+/// `None` to leave it alone, as is usual for links to the web. `<base>` elements are removed.
+/// Finally the document is serialized as [polyglot XHTML](SerializeOpts::xhtml), with an XML
+/// declaration and a doctype.
+///
+/// `documents` are modified along the way, except for their frozen nodes.
+pub fn spine_documents<F>(
+    documents: &mut [GenerationalArenaDom],
+    opts: &EpubOptions,
+    mut resource: F,
+) -> Vec<SpineItem>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let hrefs: Vec<String> = (1..=documents.len())
+        .map(|number| format!("{}-{}.xhtml", opts.file_prefix, number))
+        .collect();
+    let by_url: HashMap<String, usize> = documents
+        .iter()
+        .enumerate()
+        .filter_map(|(index, dom)| Some((strip_fragment(dom.url()?).to_string(), index)))
+        .collect();
+
+    let mut items = Vec::with_capacity(documents.len());
+    for (index, dom) in documents.iter_mut().enumerate() {
+        let base = dom.base_url();
+        // Removed first, so that their own URLs aren't taken for resources.
+        let bases: Vec<_> = dom
+            .document
+            .descendants(&dom.arena)
+            .filter(|&node| dom.is_html_element(node, "base"))
+            .collect();
+        for base in bases {
+            if !dom.skip_frozen_removal(base) {
+                dom.free_subtree(base);
+            }
+        }
+        dom.rewrite_urls(dom.document, |url| {
+            if url.starts_with('#') {
+                return None;
+            }
+            let absolute = match &base {
+                Some(base) => resolve(base, url)?,
+                None => url.to_string(),
+            };
+            let fragment = &absolute[strip_fragment(&absolute).len()..];
+            match by_url.get(strip_fragment(&absolute)) {
+                Some(&target) if target == index && !fragment.is_empty() => {
+                    Some(fragment.to_string())
+                }
+                Some(&target) => Some(format!("{}{}", hrefs[target], fragment)),
+                None => resource(&absolute),
+            }
+        });
+
+        let title = prepare_head(dom, opts);
+        let opts = SerializeOpts {
+            xhtml: true,
+            ..SerializeOpts::default()
+        };
+        let mut xhtml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let has_doctype = dom
+            .document
+            .children(&dom.arena)
+            .any(|child| matches!(dom.get_node(&child), NodeData::Doctype { .. }));
+        if !has_doctype {
+            xhtml.push_str("<!DOCTYPE html>\n");
+        }
+        xhtml.push_str(&dom.serialize(dom.document, &opts));
+        items.push(SpineItem {
+            id: hrefs[index].trim_end_matches(".xhtml").to_string(),
+            href: hrefs[index].clone(),
+            title,
+            xhtml,
+        });
+    }
+    items
+}
+
+/// Give the document its title, language and stylesheets, returning the title.
+fn prepare_head(dom: &mut GenerationalArenaDom, opts: &EpubOptions) -> String {
+    let html = match dom.document_element() {
+        Some(html) => html,
+        None if dom.skip_frozen_insertion(InsertPosition::AppendTo(dom.document)) => {
+            return opts.title.clone();
+        }
+        None => {
+            let html = dom.create_html_element("html", Vec::new());
+            dom.insert_at(&[html], InsertPosition::AppendTo(dom.document));
+            html
+        }
+    };
+    let head = match dom.head() {
+        Some(head) => head,
+        None if dom.skip_frozen_insertion(InsertPosition::PrependTo(html)) => {
+            return opts.title.clone();
+        }
+        None => {
+            let head = dom.create_html_element("head", Vec::new());
+            dom.insert_at(&[head], InsertPosition::PrependTo(html));
+            head
+        }
+    };
+
+    if let Some(language) = &opts.language {
+        let declared = dom.get_attr(html, "lang").is_some()
+            || match dom.get_node(&html) {
+                NodeData::Element { attrs, .. } => attrs
+                    .borrow()
+                    .iter()
+                    .any(|attr| attr.name.ns == ns!(xml) && &*attr.name.local == "lang"),
+                _ => false,
+            };
+        if !declared && !dom.skip_frozen(html) {
+            dom.set_attr(html, "lang", StrTendril::from(&**language));
+            if let NodeData::Element { attrs, .. } = dom.get_node(&html) {
+                attrs.borrow_mut().push(Attribute {
+                    name: QualName::new(
+                        Some(Prefix::from("xml")),
+                        ns!(xml),
+                        LocalName::from("lang"),
+                    ),
+                    value: StrTendril::from(&**language),
+                });
+            }
+        }
+    }
+
+    let existing = dom.html_child(head, "title");
+    let mut title = existing
+        .map(|title| collapse(&dom.text_content(title)))
+        .unwrap_or_default();
+    let untitled = title.is_empty();
+    if untitled {
+        title = dom
+            .document
+            .descendants(&dom.arena)
+            .find(|&node| dom.is_html_element(node, "h1"))
+            .map(|h1| collapse(&dom.text_content(h1)))
+            .filter(|text| !text.is_empty())
+            .unwrap_or_else(|| opts.title.clone());
+    }
+    let replace_title = untitled
+        && !dom.skip_frozen(head)
+        && existing.is_none_or(|existing| !dom.skip_frozen_removal(existing));
+    if replace_title {
+        if let Some(existing) = existing {
+            dom.free_subtree(existing);
+        }
+        let element = dom.create_html_element("title", Vec::new());
+        let text = dom.create_text(&title);
+        dom.insert_at(&[text], InsertPosition::AppendTo(element));
+        dom.insert_at(&[element], InsertPosition::PrependTo(head));
+    }
+
+    if !opts.stylesheets.is_empty() && dom.skip_frozen(head) {
+        return title;
+    }
+    for stylesheet in &opts.stylesheets {
+        let attrs = [
+            ("rel", "stylesheet"),
+            ("type", "text/css"),
+            ("href", stylesheet),
+        ]
+        .iter()
+        .map(|&(name, value)| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(name)),
+            value: StrTendril::from(value),
+        })
+        .collect();
+        let link = dom.create_html_element("link", attrs);
+        dom.insert_at(&[link], InsertPosition::AppendTo(head));
+    }
+    title
+}
+
+fn strip_fragment(url: &str) -> &str {
+    url.split('#').next().unwrap_or(url)
+}
+
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spine_documents, EpubOptions};
+    use crate::parse::parse_html;
+
+    #[test]
+    fn makes_spine_documents() {
+        let mut one = parse_html(
+            "<!DOCTYPE html><base href=https://example.com/book/><h1>First  chapter</h1>\
+             <a href=two.html#end>next</a><a href=#top>top</a><a href=one.html#top>self</a>\
+             <img src=cover.png><a href=https://other.example/>web</a>",
+        );
+        one.set_url("https://example.com/book/one.html");
+        let mut two = parse_html("<title>Second</title><p id=end>End<br></p>");
+        two.set_url("https://example.com/book/two.html");
+        let mut documents = [one, two];
+        let opts = EpubOptions {
+            language: Some("en".to_string()),
+            stylesheets: vec!["style.css".to_string()],
+            ..EpubOptions::default()
+        };
+        let mut resources = Vec::new();
+        let items = spine_documents(&mut documents, &opts, |url| {
+            resources.push(url.to_string());
+            url.ends_with(".png")
+                .then(|| "images/cover.png".to_string())
+        });
+        assert_eq!(
+            resources,
+            [
+                "https://example.com/book/cover.png",
+                "https://other.example/"
+            ]
+        );
+
+        let summary: Vec<(&str, &str, &str)> = items
+            .iter()
+            .map(|item| (item.id.as_str(), item.href.as_str(), item.title.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("chapter-1", "chapter-1.xhtml", "First chapter"),
+                ("chapter-2", "chapter-2.xhtml", "Second"),
+            ]
+        );
+        assert_eq!(
+            items[0].xhtml,
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\
+             <html xmlns=\"http://www.w3.org/1999/xhtml\" lang=\"en\" xml:lang=\"en\"><head>\
+             <title>First chapter</title>\
+             <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" /></head><body>\
+             <h1>First  chapter</h1><a href=\"chapter-2.xhtml#end\">next</a>\
+             <a href=\"#top\">top</a><a href=\"#top\">self</a><img src=\"images/cover.png\" />\
+             <a href=\"https://other.example/\">web</a></body></html>"
+        );
+        assert!(items[1]
+            .xhtml
+            .starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html"));
+        assert!(items[1].xhtml.contains("<p id=\"end\">End<br /></p>"));
+    }
+}
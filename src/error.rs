@@ -0,0 +1,123 @@
+//! Errors reported while building a DOM.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::QualName;
+
+use crate::Handle;
+
+/// A parse error reported by the tree builder.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The message the tree builder reported.
+    pub message: Cow<'static, str>,
+
+    /// The element the tree builder was working in when the error fired, if any. This is usually
+    /// the element the offending markup belongs to.
+    pub node: Option<Handle>,
+
+    /// The line of the input the parser was at when the error fired, starting at 1.
+    pub line: u64,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// An attribute dropped because its element already had one with the same name, recorded when
+/// [`DomOptions::record_duplicate_attributes`](crate::DomOptions::record_duplicate_attributes)
+/// is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateAttribute {
+    /// The element the attribute was meant for.
+    pub element: Handle,
+
+    /// The name of the attribute.
+    pub name: QualName,
+
+    /// The value that was discarded. The element keeps the value of the first attribute.
+    pub value: StrTendril,
+}
+
+/// The reason building a DOM was stopped before the parser finished.
+///
+/// Once the DOM has been aborted it ignores the rest of the parser's output, so the partial tree
+/// shouldn't be relied upon.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AbortError {
+    /// The [`CancellationToken`](crate::CancellationToken) passed in the options was cancelled.
+    Cancelled,
+
+    /// Building the DOM would have created more than
+    /// [`DomOptions::max_nodes`](crate::DomOptions::max_nodes) nodes.
+    NodeLimitExceeded {
+        /// The configured limit.
+        limit: usize,
+    },
+
+    /// The approximate memory used by the DOM exceeded
+    /// [`DomOptions::memory_budget`](crate::DomOptions::memory_budget).
+    MemoryBudgetExceeded {
+        /// The configured budget, in bytes.
+        budget: usize,
+        /// The approximate number of bytes in use when parsing was aborted.
+        used: usize,
+    },
+}
+
+impl fmt::Display for AbortError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbortError::Cancelled => f.write_str("parsing was cancelled"),
+            AbortError::NodeLimitExceeded { limit } => {
+                write!(f, "document has more than {} nodes", limit)
+            }
+            AbortError::MemoryBudgetExceeded { budget, used } => write!(
+                f,
+                "document uses about {} bytes, over the budget of {} bytes",
+                used, budget
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AbortError {}
+
+/// The reason a checked mutation, such as one made through a
+/// [`SubtreeMut`](crate::SubtreeMut), was refused.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MutationError {
+    /// The node isn't in the subtree the mutation is restricted to, or it is the root of that
+    /// subtree and the mutation would change the root's parent.
+    OutsideSubtree {
+        /// The node that was to be changed.
+        node: Handle,
+    },
+
+    /// The node is [frozen](crate::GenerationalArenaDom::freeze).
+    Frozen {
+        /// The frozen node that was to be changed.
+        node: Handle,
+    },
+}
+
+impl fmt::Display for MutationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MutationError::OutsideSubtree { .. } => {
+                f.write_str("node is outside the subtree that may be changed")
+            }
+            MutationError::Frozen { .. } => f.write_str("node is frozen"),
+        }
+    }
+}
+
+impl std::error::Error for MutationError {}
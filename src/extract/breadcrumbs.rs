@@ -0,0 +1,336 @@
+#[cfg(feature = "serde")]
+use serde_json::Value;
+
+use crate::{GenerationalArenaDom, Handle};
+
+/// One step of a breadcrumb trail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Breadcrumb {
+    /// The step's label.
+    pub label: String,
+    /// The resolved URL the step links to. The last step, the current page, often has none.
+    pub url: Option<String>,
+}
+
+/// Find a document's breadcrumb trail, from the top level down to the current page.
+///
+/// Trails are looked for, in order, in a schema.org `BreadcrumbList` in JSON-LD (with the
+/// `serde` feature), the same in microdata, and a `<nav>` or list labelled or classed as a
+/// breadcrumb (e.g. `<nav aria-label="breadcrumb">` or `<ol class="breadcrumb">`). The first one
+/// found is returned, or an empty list if there is none.
+pub fn breadcrumbs(dom: &GenerationalArenaDom) -> Vec<Breadcrumb> {
+    #[cfg(feature = "serde")]
+    let from_json_ld = || {
+        dom.document
+            .descendants(&dom.arena)
+            .filter(|&node| dom.is_json_ld(node))
+            .filter_map(|node| serde_json::from_str::<Value>(&dom.text_content(node)).ok())
+            .find_map(|value| json_ld_list(&value).map(|items| json_ld_breadcrumbs(dom, items)))
+    };
+    #[cfg(not(feature = "serde"))]
+    let from_json_ld = || None::<Vec<Breadcrumb>>;
+    let from_microdata = || {
+        dom.document
+            .descendants(&dom.arena)
+            .find(|&node| {
+                dom.get_attr(node, "itemscope").is_some()
+                    && dom.get_attr(node, "itemtype").is_some_and(|itemtype| {
+                        itemtype
+                            .split_ascii_whitespace()
+                            .any(|ty| ty.ends_with("schema.org/BreadcrumbList"))
+                    })
+            })
+            .map(|list| microdata_breadcrumbs(dom, list))
+    };
+    let from_markup = || {
+        dom.document
+            .descendants(&dom.arena)
+            .find(|&node| dom.is_breadcrumb_container(node))
+            .map(|container| markup_breadcrumbs(dom, container))
+    };
+    from_json_ld()
+        .filter(|trail| !trail.is_empty())
+        .or_else(|| from_microdata().filter(|trail| !trail.is_empty()))
+        .or_else(from_markup)
+        .unwrap_or_default()
+}
+
+impl GenerationalArenaDom {
+    /// Whether `handle` is a `<script type="application/ld+json">`.
+    #[cfg(feature = "serde")]
+    fn is_json_ld(&self, handle: Handle) -> bool {
+        self.is_html_element(handle, "script")
+            && self.get_attr(handle, "type").is_some_and(|ty| {
+                ty.split(';')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .eq_ignore_ascii_case("application/ld+json")
+            })
+    }
+
+    fn is_breadcrumb_container(&self, handle: Handle) -> bool {
+        let is_container = ["nav", "ol", "ul", "div"]
+            .iter()
+            .any(|name| self.is_html_element(handle, name));
+        let mentions = |attr: &str| {
+            self.get_attr(handle, attr)
+                .is_some_and(|value| value.to_ascii_lowercase().contains("breadcrumb"))
+        };
+        is_container && (mentions("aria-label") || mentions("class") || mentions("id"))
+    }
+}
+
+/// Find the `itemListElement`s of a `BreadcrumbList` anywhere in a JSON-LD value.
+#[cfg(feature = "serde")]
+fn json_ld_list(value: &Value) -> Option<&[Value]> {
+    match value {
+        Value::Array(items) => items.iter().find_map(json_ld_list),
+        Value::Object(members) => {
+            let is_list = match value.get("@type") {
+                Some(Value::String(ty)) => ty.ends_with("BreadcrumbList"),
+                Some(Value::Array(types)) => types
+                    .iter()
+                    .any(|ty| ty.as_str().is_some_and(|ty| ty.ends_with("BreadcrumbList"))),
+                _ => false,
+            };
+            match value.get("itemListElement") {
+                Some(Value::Array(items)) if is_list => Some(items),
+                _ => members.values().find_map(json_ld_list),
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(feature = "serde")]
+fn json_ld_breadcrumbs(dom: &GenerationalArenaDom, items: &[Value]) -> Vec<Breadcrumb> {
+    let mut items: Vec<(f64, Breadcrumb)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let position = match item.get("position") {
+                Some(Value::Number(position)) => position.as_f64()?,
+                Some(Value::String(position)) => position.trim().parse().ok()?,
+                _ => index as f64,
+            };
+            let target = item.get("item");
+            let url = match target {
+                Some(Value::String(url)) => Some(url.as_str()),
+                Some(target) => target
+                    .get("@id")
+                    .or_else(|| target.get("url"))
+                    .and_then(Value::as_str),
+                None => item.get("url").and_then(Value::as_str),
+            };
+            let label = item
+                .get("name")
+                .or_else(|| target.and_then(|target| target.get("name")))
+                .and_then(Value::as_str)?;
+            Some((
+                position,
+                Breadcrumb {
+                    label: collapse(label),
+                    url: url.map(|url| dom.resolve_url(url)),
+                },
+            ))
+        })
+        .collect();
+    items.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    items.into_iter().map(|(_, crumb)| crumb).collect()
+}
+
+fn microdata_breadcrumbs(dom: &GenerationalArenaDom, list: Handle) -> Vec<Breadcrumb> {
+    let mut items: Vec<(f64, Breadcrumb)> = microdata_props(dom, list, "itemListElement")
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let position = microdata_props(dom, item, "position")
+                .first()
+                .and_then(|&prop| microdata_value(dom, prop).trim().parse().ok())
+                .unwrap_or(index as f64);
+            let target = microdata_props(dom, item, "item").first().copied();
+            let name = microdata_props(dom, item, "name")
+                .first()
+                .copied()
+                .or_else(|| {
+                    let target = target?;
+                    microdata_props(dom, target, "name").first().copied()
+                })
+                .map(|prop| microdata_value(dom, prop))
+                .or_else(|| target.map(|target| dom.text_content(target)))?;
+            let url = target.and_then(|target| {
+                let url = dom
+                    .get_attr(target, "href")
+                    .or_else(|| dom.get_attr(target, "itemid"))
+                    .map(|url| url.to_string())
+                    .or_else(|| {
+                        let value = microdata_value(dom, target);
+                        (!value.trim().is_empty() && dom.get_attr(target, "itemscope").is_none())
+                            .then_some(value)
+                    })?;
+                Some(dom.resolve_url(&url))
+            });
+            Some((
+                position,
+                Breadcrumb {
+                    label: collapse(&name),
+                    url,
+                },
+            ))
+        })
+        .collect();
+    items.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    items.into_iter().map(|(_, crumb)| crumb).collect()
+}
+
+/// The elements under `item` that give it the property `prop`, skipping nested items.
+fn microdata_props(dom: &GenerationalArenaDom, item: Handle, prop: &str) -> Vec<Handle> {
+    let mut props = Vec::new();
+    let mut stack: Vec<Handle> = item.children(&dom.arena).collect();
+    stack.reverse();
+    while let Some(node) = stack.pop() {
+        let has_prop = dom
+            .get_attr(node, "itemprop")
+            .is_some_and(|itemprop| itemprop.split_ascii_whitespace().any(|name| name == prop));
+        if has_prop {
+            props.push(node);
+        }
+        if dom.get_attr(node, "itemscope").is_none() {
+            let start = stack.len();
+            stack.extend(node.children(&dom.arena));
+            stack[start..].reverse();
+        }
+    }
+    props
+}
+
+/// The value of a microdata property element.
+fn microdata_value(dom: &GenerationalArenaDom, prop: Handle) -> String {
+    let attr = if dom.is_html_element(prop, "meta") {
+        Some("content")
+    } else if ["a", "area", "link"]
+        .iter()
+        .any(|name| dom.is_html_element(prop, name))
+    {
+        Some("href")
+    } else if [
+        "audio", "embed", "iframe", "img", "source", "track", "video",
+    ]
+    .iter()
+    .any(|name| dom.is_html_element(prop, name))
+    {
+        Some("src")
+    } else if ["data", "meter"]
+        .iter()
+        .any(|name| dom.is_html_element(prop, name))
+    {
+        Some("value")
+    } else if dom.is_html_element(prop, "time") {
+        Some("datetime")
+    } else {
+        None
+    };
+    match attr.and_then(|attr| dom.get_attr(prop, attr)) {
+        Some(value) => value.to_string(),
+        None => dom.text_content(prop),
+    }
+}
+
+fn markup_breadcrumbs(dom: &GenerationalArenaDom, container: Handle) -> Vec<Breadcrumb> {
+    let items: Vec<Handle> = container
+        .descendants(&dom.arena)
+        .filter(|&node| dom.is_html_element(node, "li"))
+        .collect();
+    if items.is_empty() {
+        // Without list items, every link is a step.
+        return container
+            .descendants(&dom.arena)
+            .filter(|&node| dom.is_html_element(node, "a"))
+            .filter_map(|link| link_crumb(dom, link))
+            .collect();
+    }
+    items
+        .into_iter()
+        .filter_map(|item| {
+            match item
+                .descendants(&dom.arena)
+                .find(|&node| dom.is_html_element(node, "a"))
+            {
+                Some(link) => link_crumb(dom, link),
+                None => {
+                    let label = collapse(&dom.text_content(item));
+                    (!label.is_empty()).then_some(Breadcrumb { label, url: None })
+                }
+            }
+        })
+        .collect()
+}
+
+fn link_crumb(dom: &GenerationalArenaDom, link: Handle) -> Option<Breadcrumb> {
+    let label = collapse(&dom.text_content(link));
+    if label.is_empty() {
+        return None;
+    }
+    let url = dom
+        .get_attr(link, "href")
+        .filter(|href| !href.trim().is_empty())
+        .map(|href| dom.resolve_url(&href));
+    Some(Breadcrumb { label, url })
+}
+
+fn collapse(text: &str) -> String {
+    text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{breadcrumbs, Breadcrumb};
+    use crate::parse::parse_html;
+
+    fn crumb(label: &str, url: Option<&str>) -> Breadcrumb {
+        Breadcrumb {
+            label: label.to_string(),
+            url: url.map(str::to_string),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn reads_json_ld() {
+        let mut dom = parse_html(
+            r#"<script type="application/ld+json">{"@graph": [{"@type": "WebPage"},
+            {"@type": "BreadcrumbList", "itemListElement": [
+                {"position": 2, "name": "Lamps", "item": {"@id": "/lamps"}},
+                {"position": "1", "name": "Home", "item": "/"},
+                {"position": 3, "name": "Desk lamp"}]}]}</script>
+            <nav aria-label=breadcrumb><a href=/other>Other</a></nav>"#,
+        );
+        dom.set_url("https://example.com/lamps/desk");
+        assert_eq!(
+            breadcrumbs(&dom),
+            [
+                crumb("Home", Some("https://example.com/")),
+                crumb("Lamps", Some("https://example.com/lamps")),
+                crumb("Desk lamp", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_markup() {
+        let mut dom = parse_html(
+            "<ol class=breadcrumb><li><a href=/>Home</a></li><li><a href=/lamps>Lamps</a></li>\
+             <li>Desk  lamp</li></ol>",
+        );
+        dom.set_url("https://example.com/lamps/desk");
+        assert_eq!(
+            breadcrumbs(&dom),
+            [
+                crumb("Home", Some("https://example.com/")),
+                crumb("Lamps", Some("https://example.com/lamps")),
+                crumb("Desk lamp", None),
+            ]
+        );
+    }
+}
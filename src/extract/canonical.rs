@@ -0,0 +1,68 @@
+use crate::extract::has_rel;
+use crate::url::resolve;
+use crate::{GenerationalArenaDom, Handle};
+
+/// The canonical URL of a document loaded from `page_url`.
+///
+/// In order of precedence, this is the first `<link rel="canonical">`, the `og:url` Open Graph
+/// property, or `page_url` itself. Relative URLs are resolved against the document's base URL
+/// as if it was loaded from `page_url`.
+pub fn canonical_url(dom: &GenerationalArenaDom, page_url: &str) -> String {
+    let link = first_attr(dom, "href", |node| {
+        dom.is_html_element(node, "link") && has_rel(dom, node, "canonical")
+    });
+    let og_url = || {
+        first_attr(dom, "content", |node| {
+            dom.is_html_element(node, "meta")
+                && dom
+                    .get_attr(node, "property")
+                    .is_some_and(|property| property.trim().eq_ignore_ascii_case("og:url"))
+        })
+    };
+    match link.or_else(og_url) {
+        Some(url) => dom
+            .base_url_for(Some(page_url))
+            .and_then(|base| resolve(&base, &url))
+            .unwrap_or(url),
+        None => page_url.to_string(),
+    }
+}
+
+/// The first non-empty value of `attr` on an element matching `matches`.
+fn first_attr(
+    dom: &GenerationalArenaDom,
+    attr: &str,
+    matches: impl Fn(Handle) -> bool,
+) -> Option<String> {
+    dom.document
+        .descendants(&dom.arena)
+        .filter(|&node| matches(node))
+        .filter_map(|node| dom.get_attr(node, attr))
+        .map(|value| value.trim().to_string())
+        .find(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::canonical_url;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn prefers_links_then_open_graph_then_the_page() {
+        let page = "https://example.com/a/page?utm=1";
+        let dom = parse_html(
+            "<meta property=og:url content=https://example.com/og>\
+             <link rel=canonical href=' '><link rel='Canonical' href=../canonical>",
+        );
+        assert_eq!(canonical_url(&dom, page), "https://example.com/canonical");
+
+        let dom = parse_html("<meta property=' OG:URL ' content=/og><link rel=alternate href=/x>");
+        assert_eq!(canonical_url(&dom, page), "https://example.com/og");
+
+        let dom = parse_html("<base href=https://cdn.example.com/><link rel=canonical href=c>");
+        assert_eq!(canonical_url(&dom, page), "https://cdn.example.com/c");
+
+        let dom = parse_html("<p>nothing</p>");
+        assert_eq!(canonical_url(&dom, page), page);
+    }
+}
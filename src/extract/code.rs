@@ -0,0 +1,77 @@
+use crate::{space_tokens, GenerationalArenaDom, Handle};
+
+/// A block of code in a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// The language named by a `language-*` or `lang-*` class on the `<code>` or its `<pre>`,
+    /// as used by highlighters such as Prism and highlight.js, e.g. `rust` for
+    /// `class="language-rust"`.
+    pub language: Option<String>,
+    /// The code, exactly as it appears in the document.
+    pub text: String,
+    /// The `<code>` element.
+    pub handle: Handle,
+}
+
+/// Find the code blocks in a document: `<code>` elements that are children of a `<pre>`, in
+/// document order.
+pub fn code_blocks(dom: &GenerationalArenaDom) -> Vec<CodeBlock> {
+    dom.document
+        .descendants(&dom.arena)
+        .filter(|&node| dom.is_html_element(node, "code"))
+        .filter_map(|code| {
+            let pre = code
+                .ancestors(&dom.arena)
+                .nth(1)
+                .filter(|&parent| dom.is_html_element(parent, "pre"))?;
+            Some(CodeBlock {
+                language: language(dom, code).or_else(|| language(dom, pre)),
+                text: dom.text_content(code),
+                handle: code,
+            })
+        })
+        .collect()
+}
+
+fn language(dom: &GenerationalArenaDom, handle: Handle) -> Option<String> {
+    let class = dom.get_attr(handle, "class")?;
+    space_tokens(&class)
+        .find_map(|class| {
+            class
+                .strip_prefix("language-")
+                .or_else(|| class.strip_prefix("lang-"))
+        })
+        .filter(|language| !language.is_empty())
+        .map(|language| language.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::code_blocks;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn finds_code_blocks_with_languages() {
+        let dom = parse_html(
+            "<pre><code class='hljs language-rust'>fn main() {\n    x < y;\n}</code></pre>\
+             <pre class=lang-py><code>print(1)</code></pre>\
+             <pre><code class=language->plain</code></pre>\
+             <p><code class=language-js>inline()</code></p>\
+             <pre>text <code>nested</code></pre>",
+        );
+        let blocks = code_blocks(&dom);
+        let found: Vec<(Option<&str>, &str)> = blocks
+            .iter()
+            .map(|block| (block.language.as_deref(), block.text.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (Some("rust"), "fn main() {\n    x < y;\n}"),
+                (Some("py"), "print(1)"),
+                (None, "plain"),
+                (None, "nested"),
+            ]
+        );
+    }
+}
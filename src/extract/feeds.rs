@@ -0,0 +1,150 @@
+use crate::extract::has_rel;
+use crate::{GenerationalArenaDom, Handle};
+
+/// What kind of document a [`Feed`] points to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FeedKind {
+    /// An RSS feed.
+    Rss,
+    /// An Atom feed.
+    Atom,
+    /// A [JSON Feed](https://www.jsonfeed.org/).
+    JsonFeed,
+    /// An XML sitemap or sitemap index.
+    Sitemap,
+}
+
+/// A feed or sitemap advertised by a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Feed {
+    /// What kind of document the URL points to.
+    pub kind: FeedKind,
+    /// The resolved URL.
+    pub url: String,
+    /// The title given for it, if any.
+    pub title: Option<String>,
+    /// The `<link>` or `<a>` element it was found on.
+    pub handle: Handle,
+}
+
+/// Find the feeds and sitemaps a document links to, in document order.
+///
+/// Feeds are declared by `<link rel="alternate">` elements with an RSS, Atom or JSON Feed
+/// `type`. Sitemaps are declared by `<link rel="sitemap">`, or hinted at by links to files named
+/// `sitemap.xml`, `sitemap_index.xml` and the like. Each URL is reported once per kind.
+pub fn feeds(dom: &GenerationalArenaDom) -> Vec<Feed> {
+    let mut feeds: Vec<Feed> = Vec::new();
+    for node in dom.document.descendants(&dom.arena) {
+        let href = match dom.get_attr(node, "href") {
+            Some(href) if !href.trim().is_empty() => href,
+            _ => continue,
+        };
+        let kind = if dom.is_html_element(node, "link") {
+            if has_rel(dom, node, "sitemap") {
+                Some(FeedKind::Sitemap)
+            } else if has_rel(dom, node, "alternate") {
+                dom.get_attr(node, "type").and_then(|ty| feed_type(&ty))
+            } else {
+                None
+            }
+        } else if dom.is_html_element(node, "a") && is_sitemap_path(&href) {
+            Some(FeedKind::Sitemap)
+        } else {
+            None
+        };
+        let kind = match kind {
+            Some(kind) => kind,
+            None => continue,
+        };
+        let url = dom.resolve_url(&href);
+        if feeds
+            .iter()
+            .any(|feed| feed.kind == kind && feed.url == url)
+        {
+            continue;
+        }
+        let title = dom
+            .get_attr(node, "title")
+            .map(|title| title.trim().to_string())
+            .filter(|title| !title.is_empty());
+        feeds.push(Feed {
+            kind,
+            url,
+            title,
+            handle: node,
+        });
+    }
+    feeds
+}
+
+/// The kind of feed a `type` attribute declares.
+fn feed_type(ty: &str) -> Option<FeedKind> {
+    let essence = ty.split(';').next().unwrap_or_default().trim();
+    if essence.eq_ignore_ascii_case("application/rss+xml") {
+        Some(FeedKind::Rss)
+    } else if essence.eq_ignore_ascii_case("application/atom+xml") {
+        Some(FeedKind::Atom)
+    } else if essence.eq_ignore_ascii_case("application/feed+json") {
+        // Plain `application/json` alternates are usually APIs, such as WordPress's REST API.
+        Some(FeedKind::JsonFeed)
+    } else {
+        None
+    }
+}
+
+/// Whether a link target looks like a sitemap, e.g. `/sitemap.xml` or `sitemap_index.xml.gz`.
+fn is_sitemap_path(href: &str) -> bool {
+    let path = href.split(['?', '#']).next().unwrap_or_default();
+    let file = path
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let file = file.strip_suffix(".gz").unwrap_or(&file);
+    file.starts_with("sitemap") && file.ends_with(".xml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{feeds, FeedKind};
+    use crate::GenerationalArenaDom;
+
+    #[test]
+    fn finds_feeds_and_sitemaps() {
+        let mut dom = GenerationalArenaDom::from_abbreviation(
+            "link[rel=alternate type='application/rss+xml; charset=utf-8' href=/rss title=News]\
+             +link[rel='Alternate' type=application/atom+xml href=/atom]\
+             +link[rel=alternate type=application/feed+json href=/feed.json]\
+             +link[rel=alternate type=application/json href=/wp-json/wp/v2/pages/2]\
+             +link[rel=alternate type=text/html href=/other]\
+             +a[href=/sitemap_index.xml.gz]+a[href=/sitemap_index.xml.gz]+a[href=/map.xml]",
+        )
+        .unwrap();
+        dom.set_url("https://example.com/blog/");
+        let found: Vec<_> = feeds(&dom)
+            .into_iter()
+            .map(|feed| (feed.kind, feed.url, feed.title))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    FeedKind::Rss,
+                    "https://example.com/rss".to_string(),
+                    Some("News".to_string())
+                ),
+                (FeedKind::Atom, "https://example.com/atom".to_string(), None),
+                (
+                    FeedKind::JsonFeed,
+                    "https://example.com/feed.json".to_string(),
+                    None
+                ),
+                (
+                    FeedKind::Sitemap,
+                    "https://example.com/sitemap_index.xml.gz".to_string(),
+                    None
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt;
+
+use crate::{GenerationalArenaDom, Handle, SelectorError};
+
+/// A type that can be scraped from the elements under a scope, usually by deriving it with
+/// `#[derive(FromDom)]` (enabled by the `derive` feature).
+///
+/// Each field of a derived struct carries a `#[select(...)]` attribute naming a selector,
+/// relative to the scope, and what to take from the matching elements:
+///
+/// - `#[select("h1.title")]` or `#[select("h1.title", text)]`: the text, with whitespace
+///   collapsed.
+/// - `#[select(".body", html)]`: the inner HTML.
+/// - `#[select("a.more", attr = "href")]`: an attribute. URL attributes are resolved against
+///   the document's base URL, as in [`Schema`](crate::extract::Schema).
+/// - `#[select(".review", nested)]`: another `FromDom` type, scraped with the matching element
+///   as its scope.
+///
+/// The field's type decides how many elements are used: `Vec<T>` takes every match,
+/// `Option<T>` the first match if there is one, and any other type the first match, which must
+/// exist. Paths such as `std::option::Option<T>` are recognized by their last segment, but type
+/// aliases of `Option` and `Vec` count as other types. Text values are converted with [`FromText`], so fields can be numbers or
+/// [`Price`](crate::extract::Price)s as well as strings.
+pub trait FromDom: Sized {
+    /// Scrape a value from the elements under `scope`.
+    fn from_dom(dom: &GenerationalArenaDom, scope: Handle) -> Result<Self, FromDomError>;
+}
+
+/// Conversion from the text or attribute value a [`FromDom`] field selects.
+pub trait FromText: Sized {
+    /// Convert `text`, or return `None` if it isn't valid for this type.
+    fn from_text(text: &str) -> Option<Self>;
+}
+
+impl FromText for String {
+    fn from_text(text: &str) -> Option<String> {
+        Some(text.to_string())
+    }
+}
+
+impl FromText for bool {
+    fn from_text(text: &str) -> Option<bool> {
+        text.trim().parse().ok()
+    }
+}
+
+macro_rules! from_text_via_parse {
+    ($($ty:ty),*) => {
+        $(
+            impl FromText for $ty {
+                fn from_text(text: &str) -> Option<$ty> {
+                    text.trim().parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+from_text_via_parse!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+/// Why a [`FromDom`] value couldn't be scraped.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FromDomError {
+    /// The field that failed.
+    pub field: &'static str,
+    /// What went wrong.
+    pub kind: FromDomErrorKind,
+}
+
+/// What went wrong scraping a [`FromDom`] field.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FromDomErrorKind {
+    /// The field's selector is invalid.
+    Selector(SelectorError),
+    /// A required field's selector matched nothing, or the element lacked the attribute.
+    Missing,
+    /// The selected text couldn't be converted to the field's type.
+    Invalid(String),
+}
+
+impl fmt::Display for FromDomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            FromDomErrorKind::Selector(err) => {
+                write!(f, "invalid selector for field `{}`: {}", self.field, err)
+            }
+            FromDomErrorKind::Missing => write!(f, "no value for field `{}`", self.field),
+            FromDomErrorKind::Invalid(text) => {
+                write!(f, "invalid value for field `{}`: {:?}", self.field, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FromDomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            FromDomErrorKind::Selector(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Support code for `#[derive(FromDom)]`. Not part of the public API.
+#[doc(hidden)]
+pub mod __private {
+    use super::{FromDom, FromDomError, FromDomErrorKind, FromText};
+    use crate::extract::schema::field_text;
+    use crate::{GenerationalArenaDom, Handle, Selector};
+
+    pub use crate::extract::schema::FieldKind as Kind;
+
+    pub fn select(
+        dom: &GenerationalArenaDom,
+        scope: Handle,
+        selector: &str,
+        field: &'static str,
+    ) -> Result<Vec<Handle>, FromDomError> {
+        match selector.trim() {
+            "" => Ok(vec![scope]),
+            selector => Selector::parse(selector)
+                .map(|selector| dom.select(scope, &selector))
+                .map_err(|err| FromDomError {
+                    field,
+                    kind: FromDomErrorKind::Selector(err),
+                }),
+        }
+    }
+
+    fn convert<T: FromText>(text: String, field: &'static str) -> Result<T, FromDomError> {
+        T::from_text(&text).ok_or(FromDomError {
+            field,
+            kind: FromDomErrorKind::Invalid(text),
+        })
+    }
+
+    pub fn one<T: FromText>(
+        dom: &GenerationalArenaDom,
+        nodes: &[Handle],
+        kind: &Kind,
+        field: &'static str,
+    ) -> Result<T, FromDomError> {
+        match optional(dom, nodes, kind, field)? {
+            Some(value) => Ok(value),
+            None => Err(FromDomError {
+                field,
+                kind: FromDomErrorKind::Missing,
+            }),
+        }
+    }
+
+    pub fn optional<T: FromText>(
+        dom: &GenerationalArenaDom,
+        nodes: &[Handle],
+        kind: &Kind,
+        field: &'static str,
+    ) -> Result<Option<T>, FromDomError> {
+        nodes
+            .first()
+            .and_then(|&node| field_text(dom, node, kind))
+            .map(|text| convert(text, field))
+            .transpose()
+    }
+
+    pub fn all<T: FromText>(
+        dom: &GenerationalArenaDom,
+        nodes: &[Handle],
+        kind: &Kind,
+        field: &'static str,
+    ) -> Result<Vec<T>, FromDomError> {
+        nodes
+            .iter()
+            .filter_map(|&node| field_text(dom, node, kind))
+            .map(|text| convert(text, field))
+            .collect()
+    }
+
+    pub fn nested_one<T: FromDom>(
+        dom: &GenerationalArenaDom,
+        nodes: &[Handle],
+        field: &'static str,
+    ) -> Result<T, FromDomError> {
+        match nodes.first() {
+            Some(&node) => T::from_dom(dom, node),
+            None => Err(FromDomError {
+                field,
+                kind: FromDomErrorKind::Missing,
+            }),
+        }
+    }
+
+    pub fn nested_optional<T: FromDom>(
+        dom: &GenerationalArenaDom,
+        nodes: &[Handle],
+    ) -> Result<Option<T>, FromDomError> {
+        nodes
+            .first()
+            .map(|&node| T::from_dom(dom, node))
+            .transpose()
+    }
+
+    pub fn nested_all<T: FromDom>(
+        dom: &GenerationalArenaDom,
+        nodes: &[Handle],
+    ) -> Result<Vec<T>, FromDomError> {
+        nodes.iter().map(|&node| T::from_dom(dom, node)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "derive")]
+    use super::FromDom;
+    use super::{FromDomError, FromDomErrorKind, FromText};
+    #[cfg(feature = "derive")]
+    use crate::parse::parse_html;
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, PartialEq, crate::extract::FromDom)]
+    struct Review {
+        #[select(".author")]
+        author: String,
+        #[select(".stars", attr = "data-stars")]
+        stars: u8,
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, PartialEq, crate::extract::FromDom)]
+    struct Product {
+        #[select("h1")]
+        name: String,
+        #[select(".desc", html)]
+        description: String,
+        #[select("a.more", attr = "href")]
+        link: Option<String>,
+        #[select(".tag", text)]
+        tags: Vec<String>,
+        #[select(".review", nested)]
+        reviews: Vec<Review>,
+        #[select(".missing", nested)]
+        featured: Option<Review>,
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, PartialEq, crate::extract::FromDom)]
+    struct Qualified {
+        #[select(".missing")]
+        subtitle: std::option::Option<String>,
+        #[select(".tag")]
+        tags: ::std::vec::Vec<String>,
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derives_from_dom() {
+        let mut dom = parse_html(
+            "<h1> Lamp\n </h1><div class=desc><b>Bright</b></div><a class=more href=lamp>more</a>\
+             <span class=tag>home</span><span class=tag>light</span>\
+             <div class=review><i class=author>Ann</i><b class=stars data-stars=5></b></div>\
+             <div class=review><i class=author>Bo</i><b class=stars data-stars=4></b></div>",
+        );
+        dom.set_url("https://shop.example/items/");
+        let product = Product::from_dom(&dom, dom.document).unwrap();
+        assert_eq!(
+            product,
+            Product {
+                name: "Lamp".to_string(),
+                description: "<b>Bright</b>".to_string(),
+                link: Some("https://shop.example/items/lamp".to_string()),
+                tags: vec!["home".to_string(), "light".to_string()],
+                reviews: vec![
+                    Review {
+                        author: "Ann".to_string(),
+                        stars: 5
+                    },
+                    Review {
+                        author: "Bo".to_string(),
+                        stars: 4
+                    },
+                ],
+                featured: None,
+            }
+        );
+
+        let dom =
+            parse_html("<div class=review><i class=author>Cy</i><b class=stars data-stars=lots>");
+        assert_eq!(
+            Review::from_dom(&dom, dom.document),
+            Err(FromDomError {
+                field: "stars",
+                kind: FromDomErrorKind::Invalid("lots".to_string()),
+            })
+        );
+        let dom = parse_html("<p>nothing</p>");
+        assert_eq!(
+            Product::from_dom(&dom, dom.document).unwrap_err().kind,
+            FromDomErrorKind::Missing
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn recognizes_qualified_option_and_vec() {
+        let dom = parse_html("<span class=tag>a</span><span class=tag>b</span>");
+        assert_eq!(
+            Qualified::from_dom(&dom, dom.document),
+            Ok(Qualified {
+                subtitle: None,
+                tags: vec!["a".to_string(), "b".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn converts_text() {
+        assert_eq!(i32::from_text(" -4 "), Some(-4));
+        assert_eq!(f64::from_text("2.5"), Some(2.5));
+        assert_eq!(bool::from_text("true"), Some(true));
+        assert_eq!(u8::from_text("300"), None);
+        assert_eq!(String::from_text(" kept "), Some(" kept ".to_string()));
+
+        let error = FromDomError {
+            field: "price",
+            kind: FromDomErrorKind::Invalid("free".to_string()),
+        };
+        assert_eq!(
+            error.to_string(),
+            "invalid value for field `price`: \"free\""
+        );
+        let error = FromDomError {
+            field: "name",
+            kind: FromDomErrorKind::Missing,
+        };
+        assert_eq!(error.to_string(), "no value for field `name`");
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{has_token, icon_sizes, GenerationalArenaDom, Handle, IconSize};
+
+/// What kind of declaration an [`Icon`] came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IconKind {
+    /// `<link rel="icon">` or `<link rel="shortcut icon">`.
+    Icon,
+    /// `<link rel="apple-touch-icon">` or `apple-touch-icon-precomposed`.
+    AppleTouchIcon,
+    /// Safari's `<link rel="mask-icon">`, a monochrome SVG.
+    MaskIcon,
+    /// `<meta name="msapplication-TileImage">`.
+    TileImage,
+    /// `<link rel="manifest">`, a web app manifest that may list more icons. It isn't an image
+    /// itself.
+    Manifest,
+}
+
+/// An icon declared by a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Icon {
+    /// What declared the icon.
+    pub kind: IconKind,
+    /// The resolved URL.
+    pub url: String,
+    /// The sizes listed in the `sizes` attribute, as (width, height).
+    pub sizes: Vec<(u32, u32)>,
+    /// Whether `sizes` contains `any`, meaning the icon is scalable.
+    pub any_size: bool,
+    /// The MIME type from the `type` attribute, if any.
+    pub media_type: Option<String>,
+    /// The element it was declared by.
+    pub handle: Handle,
+}
+
+/// Find every icon a document declares, in document order.
+pub fn icons(dom: &GenerationalArenaDom) -> Vec<Icon> {
+    let mut icons = Vec::new();
+    for node in dom.document.descendants(&dom.arena) {
+        let (kind, url) = if dom.is_html_element(node, "link") {
+            let rel = dom.get_attr(node, "rel").unwrap_or_default();
+            let kind = if has_token(&rel, "icon") {
+                IconKind::Icon
+            } else if has_token(&rel, "apple-touch-icon")
+                || has_token(&rel, "apple-touch-icon-precomposed")
+            {
+                IconKind::AppleTouchIcon
+            } else if has_token(&rel, "mask-icon") {
+                IconKind::MaskIcon
+            } else if has_token(&rel, "manifest") {
+                IconKind::Manifest
+            } else {
+                continue;
+            };
+            (kind, dom.get_attr(node, "href"))
+        } else if dom.is_html_element(node, "meta")
+            && dom
+                .get_attr(node, "name")
+                .is_some_and(|name| name.eq_ignore_ascii_case("msapplication-TileImage"))
+        {
+            (IconKind::TileImage, dom.get_attr(node, "content"))
+        } else {
+            continue;
+        };
+        let url = match url {
+            Some(url) if !url.trim().is_empty() => dom.resolve_url(&url),
+            _ => continue,
+        };
+        let sizes_attr = dom.get_attr(node, "sizes").unwrap_or_default();
+        let mut sizes = Vec::new();
+        let mut any_size = false;
+        for size in icon_sizes(&sizes_attr) {
+            match size {
+                IconSize::Any => any_size = true,
+                IconSize::Pixels { width, height } => sizes.push((width, height)),
+            }
+        }
+        icons.push(Icon {
+            kind,
+            url,
+            sizes,
+            any_size,
+            media_type: dom
+                .get_attr(node, "type")
+                .map(|ty| ty.trim().to_string())
+                .filter(|ty| !ty.is_empty()),
+            handle: node,
+        });
+    }
+    icons
+}
+
+/// Pick the icon that best fits a square of `size` pixels.
+///
+/// The smallest icon at least `size` wide wins, since it can be scaled down cleanly; then
+/// scalable icons; then the largest smaller icon; then icons without declared sizes, which are
+/// usually small favicons. Manifests and mask icons are never picked.
+pub fn best_icon(icons: &[Icon], size: u32) -> Option<&Icon> {
+    icons
+        .iter()
+        .filter(|icon| !matches!(icon.kind, IconKind::Manifest | IconKind::MaskIcon))
+        .min_by_key(|icon| {
+            let largest = icon.sizes.iter().map(|&(width, _)| width).max();
+            let fitting = icon
+                .sizes
+                .iter()
+                .map(|&(width, _)| width)
+                .filter(|&width| width >= size)
+                .min();
+            match (fitting, icon.any_size, largest) {
+                (Some(width), _, _) => (0, width - size),
+                (None, true, _) => (1, 0),
+                (None, false, Some(width)) => (2, size - width),
+                (None, false, None) => (3, 0),
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best_icon, icons, IconKind};
+    use crate::parse::parse_html;
+
+    #[test]
+    fn finds_and_picks_icons() {
+        let mut dom = parse_html(
+            "<link rel='shortcut icon' href=/favicon.ico>\
+             <link rel=icon sizes='16x16 32X32' type=' image/png ' href=/small.png>\
+             <link rel=apple-touch-icon sizes=180x180 href=/touch.png>\
+             <link rel=icon sizes=any type=image/svg+xml href=/icon.svg>\
+             <link rel=mask-icon sizes=any href=/mask.svg>\
+             <link rel=manifest href=/site.webmanifest>\
+             <link rel=icon href=' '>\
+             <meta name=msapplication-tileimage content=/tile.png>",
+        );
+        dom.set_url("https://example.com/");
+        let found = icons(&dom);
+        let kinds: Vec<(IconKind, &str)> = found
+            .iter()
+            .map(|icon| (icon.kind, icon.url.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (IconKind::Icon, "https://example.com/favicon.ico"),
+                (IconKind::Icon, "https://example.com/small.png"),
+                (IconKind::AppleTouchIcon, "https://example.com/touch.png"),
+                (IconKind::Icon, "https://example.com/icon.svg"),
+                (IconKind::MaskIcon, "https://example.com/mask.svg"),
+                (IconKind::Manifest, "https://example.com/site.webmanifest"),
+                (IconKind::TileImage, "https://example.com/tile.png"),
+            ]
+        );
+        assert_eq!(found[1].sizes, [(16, 16), (32, 32)]);
+        assert_eq!(found[1].media_type.as_deref(), Some("image/png"));
+        assert!(found[3].any_size && found[3].sizes.is_empty());
+
+        let best = |size| best_icon(&found, size).map(|icon| icon.url.as_str());
+        assert_eq!(best(24), Some("https://example.com/small.png"));
+        assert_eq!(best(64), Some("https://example.com/touch.png"));
+        assert_eq!(best(512), Some("https://example.com/icon.svg"));
+
+        let unsized_only = &found[..1];
+        assert_eq!(
+            best_icon(unsized_only, 32).map(|icon| icon.kind),
+            Some(IconKind::Icon)
+        );
+        assert_eq!(best_icon(&found[4..6], 32), None);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::interactive::parse_integer;
+use crate::{srcset_candidates, GenerationalArenaDom, Handle, ImageDescriptor};
+
+/// An image in a document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    /// The resolved `src`, if the element has a non-empty one.
+    pub src: Option<String>,
+    /// The candidates of the `srcset` attribute, with resolved URLs.
+    pub srcset: Vec<(String, ImageDescriptor)>,
+    /// The `alt` text. `Some("")` marks a decorative image, while `None` means the alt text is
+    /// missing.
+    pub alt: Option<String>,
+    /// The `width` attribute, if it is a valid integer.
+    pub width: Option<u32>,
+    /// The `height` attribute, if it is a valid integer.
+    pub height: Option<u32>,
+    /// The `loading` attribute, lowercased, e.g. `lazy`.
+    pub loading: Option<String>,
+    /// The `<figure>` the image is in, if any.
+    pub figure: Option<Handle>,
+    /// The text of that figure's `<figcaption>`, with whitespace collapsed.
+    pub caption: Option<String>,
+    /// The `<img>` element.
+    pub handle: Handle,
+}
+
+/// Find the `<img>` elements of a document, in document order.
+///
+/// URLs are resolved against the document's base URL. Images without a `src` are included, as
+/// they may still have a `srcset`.
+pub fn images(dom: &GenerationalArenaDom) -> Vec<Image> {
+    dom.document
+        .descendants(&dom.arena)
+        .filter(|&node| dom.is_html_element(node, "img"))
+        .map(|img| {
+            let figure = img
+                .ancestors(&dom.arena)
+                .skip(1)
+                .find(|&ancestor| dom.is_html_element(ancestor, "figure"));
+            let caption = figure
+                .and_then(|figure| dom.html_child(figure, "figcaption"))
+                .map(|caption| {
+                    dom.text_content(caption)
+                        .split_ascii_whitespace()
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                });
+            let dimension = |name| {
+                dom.get_attr(img, name)
+                    .and_then(|value| parse_integer(&value))
+                    .and_then(|value| u32::try_from(value).ok())
+            };
+            Image {
+                src: dom
+                    .get_attr(img, "src")
+                    .filter(|src| !src.trim().is_empty())
+                    .map(|src| dom.resolve_url(src.trim())),
+                srcset: dom
+                    .get_attr(img, "srcset")
+                    .map(|srcset| {
+                        srcset_candidates(&srcset)
+                            .map(|candidate| (dom.resolve_url(candidate.url), candidate.descriptor))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                alt: dom.get_attr(img, "alt").map(|alt| alt.to_string()),
+                width: dimension("width"),
+                height: dimension("height"),
+                loading: dom
+                    .get_attr(img, "loading")
+                    .map(|loading| loading.trim().to_ascii_lowercase()),
+                figure,
+                caption,
+                handle: img,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::images;
+    use crate::parse::parse_html;
+    use crate::ImageDescriptor;
+
+    #[test]
+    fn finds_images() {
+        let mut dom = parse_html(
+            "<img src=' a.png ' alt='A cat' width=640px height=-1 loading=LAZY \
+              srcset='a-2x.png 2x, a-big.png 1200w'>\
+             <figure><div><img srcset=b.png alt=''></div>\
+             <figcaption> The   caption </figcaption></figure><img src=''>",
+        );
+        dom.set_url("https://example.com/gallery/");
+        let found = images(&dom);
+        assert_eq!(found.len(), 3);
+
+        let first = &found[0];
+        assert_eq!(
+            first.src.as_deref(),
+            Some("https://example.com/gallery/a.png")
+        );
+        assert_eq!(
+            first.srcset,
+            [
+                (
+                    "https://example.com/gallery/a-2x.png".to_string(),
+                    ImageDescriptor::Density(2.0)
+                ),
+                (
+                    "https://example.com/gallery/a-big.png".to_string(),
+                    ImageDescriptor::Width(1200)
+                ),
+            ]
+        );
+        assert_eq!(first.alt.as_deref(), Some("A cat"));
+        assert_eq!((first.width, first.height), (Some(640), None));
+        assert_eq!(first.loading.as_deref(), Some("lazy"));
+        assert_eq!((first.figure, first.caption.as_deref()), (None, None));
+
+        let second = &found[1];
+        assert_eq!(second.src, None);
+        assert_eq!(second.alt.as_deref(), Some(""));
+        let figure = dom.query_selector(dom.document, "figure").unwrap();
+        assert_eq!(second.figure, figure);
+        assert_eq!(second.caption.as_deref(), Some("The caption"));
+
+        assert_eq!(found[2].src, None);
+        assert_eq!(found[2].alt, None);
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeMap;
+
+use crate::extract::has_rel;
+use crate::GenerationalArenaDom;
+
+/// Map each language a document declares an alternate version for to that version's URL.
+///
+/// Entries come from `<link rel="alternate" hreflang="…">`. Language tags are lowercased, since
+/// they are case-insensitive, and include `x-default` if declared. If a language is declared
+/// more than once, the first declaration wins.
+pub fn alternate_languages(dom: &GenerationalArenaDom) -> BTreeMap<String, String> {
+    let mut languages = BTreeMap::new();
+    for node in dom.document.descendants(&dom.arena) {
+        if !dom.is_html_element(node, "link") || !has_rel(dom, node, "alternate") {
+            continue;
+        }
+        let (lang, href) = match (dom.get_attr(node, "hreflang"), dom.get_attr(node, "href")) {
+            (Some(lang), Some(href)) if !lang.trim().is_empty() => (lang, href),
+            _ => continue,
+        };
+        languages
+            .entry(lang.trim().to_ascii_lowercase())
+            .or_insert_with(|| dom.resolve_url(&href));
+    }
+    languages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::alternate_languages;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn maps_languages_to_urls() {
+        let mut dom = parse_html(
+            "<link rel=alternate hreflang=EN-us href=/en>\
+             <link rel='Alternate stylesheet' hreflang=de href=https://example.de/>\
+             <link rel=alternate hreflang=en-US href=/second>\
+             <link rel=alternate hreflang=x-default href=/>\
+             <link rel=alternate hreflang=' ' href=/blank>\
+             <link rel=alternate href=/no-language>\
+             <link rel=canonical hreflang=fr href=/fr>\
+             <a rel=alternate hreflang=es href=/es>es</a>",
+        );
+        dom.set_url("https://example.com/page");
+        let languages = alternate_languages(&dom);
+        let found: Vec<(&str, &str)> = languages
+            .iter()
+            .map(|(lang, url)| (lang.as_str(), url.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("de", "https://example.de/"),
+                ("en-us", "https://example.com/en"),
+                ("x-default", "https://example.com/"),
+            ]
+        );
+    }
+}
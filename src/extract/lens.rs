@@ -0,0 +1,201 @@
+use serde_json::{Map, Value};
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// What [`project`] includes of a subtree.
+#[derive(Clone, Debug)]
+pub struct Lens {
+    /// How many levels of elements below the root are included as objects. Deeper elements are
+    /// summarized by their text. Defaults to no limit.
+    pub max_depth: Option<usize>,
+
+    /// The attributes included, or `None` for all of them. Defaults to `id`, `class`, `href`,
+    /// `src`, `alt`, `title`, `name`, `type`, `value` and `role`.
+    pub attributes: Option<Vec<String>>,
+
+    /// Elements left out along with their contents. Defaults to `script`, `style`, `template`
+    /// and `noscript`.
+    pub skipped_elements: Vec<String>,
+
+    /// Whether runs of whitespace in text are collapsed to single spaces and text that is
+    /// only whitespace is dropped. Defaults to `true`.
+    pub collapse_whitespace: bool,
+}
+
+impl Default for Lens {
+    fn default() -> Lens {
+        Lens {
+            max_depth: None,
+            attributes: Some(
+                [
+                    "id", "class", "href", "src", "alt", "title", "name", "type", "value", "role",
+                ]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            ),
+            skipped_elements: ["script", "style", "template", "noscript"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            collapse_whitespace: true,
+        }
+    }
+}
+
+/// A compact JSON view of the subtree rooted at `root`, trimmed by `lens`, for sending the
+/// structure of a page somewhere that doesn't need its full HTML.
+///
+/// Elements become objects with a `tag`, their `attrs` if any are included, and either `text`,
+/// if they contain nothing but text, or `children`, an array of the objects of child elements
+/// and strings of text in between. Elements at [`Lens::max_depth`] get all the text under
+/// them as `text` instead of children. Comments and doctypes are left out, and a document
+/// root becomes an object with only `children`. A text root becomes a string, and anything
+/// else `null`.
+pub fn project(dom: &GenerationalArenaDom, root: Handle, lens: &Lens) -> Value {
+    project_node(dom, root, lens, 0).unwrap_or(Value::Null)
+}
+
+fn project_node(
+    dom: &GenerationalArenaDom,
+    node: Handle,
+    lens: &Lens,
+    depth: usize,
+) -> Option<Value> {
+    match dom.get_node(&node) {
+        NodeData::Text { contents } => text_value(&contents.borrow(), lens),
+        NodeData::Document => Some(Value::Object(Map::from_iter([(
+            "children".to_string(),
+            Value::Array(project_children(dom, node, lens, depth + 1)),
+        )]))),
+        NodeData::Element { name, attrs, .. } => {
+            let skipped = lens
+                .skipped_elements
+                .iter()
+                .any(|skipped| dom.is_html_element(node, skipped));
+            if skipped {
+                return None;
+            }
+            let mut members = Map::new();
+            members.insert("tag".to_string(), Value::String(name.local.to_string()));
+            let attrs: Map<String, Value> = attrs
+                .borrow()
+                .iter()
+                .filter(|attr| {
+                    lens.attributes.as_ref().is_none_or(|included| {
+                        included
+                            .iter()
+                            .any(|name| name.eq_ignore_ascii_case(&attr.name.local))
+                    })
+                })
+                .map(|attr| {
+                    (
+                        attr.name.local.to_string(),
+                        Value::String(attr.value.to_string()),
+                    )
+                })
+                .collect();
+            if !attrs.is_empty() {
+                members.insert("attrs".to_string(), Value::Object(attrs));
+            }
+
+            let at_limit = lens.max_depth.is_some_and(|max_depth| depth >= max_depth);
+            let only_text = node
+                .children(&dom.arena)
+                .all(|child| matches!(dom.get_node(&child), NodeData::Text { .. }));
+            if at_limit || only_text {
+                if let Some(text) = text_value(&summary_text(dom, node, lens), lens) {
+                    members.insert("text".to_string(), text);
+                }
+            } else {
+                let children = project_children(dom, node, lens, depth + 1);
+                if !children.is_empty() {
+                    members.insert("children".to_string(), Value::Array(children));
+                }
+            }
+            Some(Value::Object(members))
+        }
+        _ => None,
+    }
+}
+
+fn project_children(
+    dom: &GenerationalArenaDom,
+    node: Handle,
+    lens: &Lens,
+    depth: usize,
+) -> Vec<Value> {
+    node.children(&dom.arena)
+        .filter_map(|child| project_node(dom, child, lens, depth))
+        .collect()
+}
+
+/// The text under `node`, outside skipped elements. Text nodes are separated by spaces when
+/// whitespace is collapsed, so that the words of neighbouring elements stay apart.
+fn summary_text(dom: &GenerationalArenaDom, node: Handle, lens: &Lens) -> String {
+    let separator = if lens.collapse_whitespace { " " } else { "" };
+    let mut texts = Vec::new();
+    for descendant in node.descendants(&dom.arena) {
+        if let NodeData::Text { contents } = dom.get_node(&descendant) {
+            let skipped = descendant.ancestors(&dom.arena).any(|ancestor| {
+                lens.skipped_elements
+                    .iter()
+                    .any(|skipped| dom.is_html_element(ancestor, skipped))
+            });
+            if !skipped {
+                texts.push(contents.borrow().to_string());
+            }
+        }
+    }
+    texts.join(separator)
+}
+
+/// `text` as a string value, collapsed if `lens` says so, or `None` if that leaves nothing.
+fn text_value(text: &str, lens: &Lens) -> Option<Value> {
+    if lens.collapse_whitespace {
+        let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!collapsed.is_empty()).then_some(Value::String(collapsed))
+    } else {
+        (!text.is_empty()).then(|| Value::String(text.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{project, Lens};
+    use crate::parse::parse_html;
+
+    #[test]
+    fn projects_subtrees() {
+        let dom = parse_html(
+            "<main id=top data-x=1><h1>Hello,\n  world</h1><script>track()</script>\
+             <ul><li><a href=/a>A</a> and <b>B</b></li></ul></main>",
+        );
+        let main = dom.query_selector(dom.document, "main").unwrap().unwrap();
+        assert_eq!(
+            project(&dom, main, &Lens::default()),
+            json!({
+                "tag": "main",
+                "attrs": {"id": "top"},
+                "children": [
+                    {"tag": "h1", "text": "Hello, world"},
+                    {"tag": "ul", "children": [{"tag": "li", "children": [
+                        {"tag": "a", "attrs": {"href": "/a"}, "text": "A"},
+                        "and",
+                        {"tag": "b", "text": "B"},
+                    ]}]},
+                ],
+            })
+        );
+        let lens = Lens {
+            max_depth: Some(1),
+            ..Lens::default()
+        };
+        assert_eq!(
+            project(&dom, main, &lens)["children"][1],
+            json!({"tag": "ul", "text": "A and B"})
+        );
+    }
+}
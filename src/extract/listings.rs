@@ -0,0 +1,300 @@
+use crate::extract::{Field, FieldKind, Schema};
+use crate::unique_selector::is_ident;
+use crate::{subtree_similarity, GenerationalArenaDom, Handle, NodeData, Selector};
+
+/// How many similar siblings make a listing.
+const MIN_ITEMS: usize = 3;
+
+/// How structurally similar neighbouring items must be on average, as scored by
+/// [`subtree_similarity`].
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// Elements whose children aren't listing items.
+const SKIPPED_CONTAINERS: &[&str] = &["head", "html", "script", "select", "style", "template"];
+
+/// A run of similar sibling elements, such as product cards or search results.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Listing {
+    /// The parent of the items.
+    pub container: Handle,
+
+    /// The items, in document order.
+    pub items: Vec<Handle>,
+
+    /// The fields found in at least half of the items, with selectors relative to an item:
+    /// the text of elements with their own text, the `href` of links and the `src` of images.
+    /// Run it on each item with [`Schema::extract`].
+    pub template: Schema,
+}
+
+/// Find the listings in a document: runs of at least three sibling elements with the same tag
+/// and classes and similar structure, in document order of their containers.
+///
+/// A container can hold several listings, one for each kind of item, and listings can be
+/// nested in the items of others. Each listing comes with a template of the fields its items
+/// have in common, so
+/// `listing.items.iter().map(|&item| listing.template.extract(&dom, item))` turns it into
+/// data.
+pub fn listings(dom: &GenerationalArenaDom) -> Vec<Listing> {
+    let mut listings = Vec::new();
+    for container in dom.document.descendants(&dom.arena) {
+        let skipped = dom.element_name(container).is_none()
+            || SKIPPED_CONTAINERS
+                .iter()
+                .any(|name| dom.is_html_element(container, name));
+        if skipped {
+            continue;
+        }
+        let mut groups: Vec<(String, Vec<Handle>)> = Vec::new();
+        for child in container.children(&dom.arena) {
+            let key = match step_key(dom, child) {
+                Some(key) => key,
+                None => continue,
+            };
+            match groups.iter_mut().find(|(group, _)| *group == key) {
+                Some((_, items)) => items.push(child),
+                None => groups.push((key, vec![child])),
+            }
+        }
+        for (_, items) in groups {
+            if items.len() < MIN_ITEMS {
+                continue;
+            }
+            let total: f64 = items
+                .windows(2)
+                .map(|pair| subtree_similarity(dom, pair[0], dom, pair[1]))
+                .sum();
+            if total / ((items.len() - 1) as f64) < MIN_SIMILARITY {
+                continue;
+            }
+            let template = infer_template(dom, &items);
+            if template.fields.is_empty() {
+                continue;
+            }
+            listings.push(Listing {
+                container,
+                items,
+                template,
+            });
+        }
+    }
+    listings
+}
+
+/// The fields found in at least half of `items`.
+fn infer_template(dom: &GenerationalArenaDom, items: &[Handle]) -> Schema {
+    // Each candidate field's selector and kind, an element it was found at, and how many
+    // items have it, in order of first appearance.
+    let mut candidates: Vec<(String, FieldKind, Handle, usize)> = Vec::new();
+    for &item in items {
+        let mut counted = Vec::new();
+        for node in item.descendants(&dom.arena) {
+            let path = match relative_path(dom, item, node) {
+                Some(path) => path,
+                None => continue,
+            };
+            for kind in field_kinds(dom, node) {
+                let index = candidates
+                    .iter()
+                    .position(|(other, other_kind, ..)| *other == path && *other_kind == kind);
+                match index {
+                    Some(index) if counted.contains(&index) => {}
+                    Some(index) => {
+                        candidates[index].3 += 1;
+                        counted.push(index);
+                    }
+                    None => {
+                        counted.push(candidates.len());
+                        candidates.push((path.clone(), kind, node, 1));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut schema = Schema::new();
+    let mut names: Vec<String> = Vec::new();
+    for (path, kind, example, count) in candidates {
+        if count * 2 < items.len() {
+            continue;
+        }
+        let selector = match Selector::parse(&path) {
+            Ok(selector) => selector,
+            Err(_) => continue,
+        };
+        let mut name = field_name(dom, example, &path);
+        if let FieldKind::Attr(attr) = &kind {
+            name = format!("{}_{}", name, attr);
+        }
+        let base = name.clone();
+        let mut suffix = 1;
+        while names.contains(&name) {
+            suffix += 1;
+            name = format!("{}_{}", base, suffix);
+        }
+        names.push(name.clone());
+        schema = schema.field(
+            &name,
+            Field {
+                selector: Some(selector),
+                kind,
+                list: false,
+                parser: None,
+            },
+        );
+    }
+    schema
+}
+
+/// What fields `node` provides.
+fn field_kinds(dom: &GenerationalArenaDom, node: Handle) -> Vec<FieldKind> {
+    let mut kinds = Vec::new();
+    let own_text = node
+        .children(&dom.arena)
+        .any(|child| match dom.get_node(&child) {
+            NodeData::Text { contents } => !contents.borrow().trim().is_empty(),
+            _ => false,
+        });
+    if own_text {
+        kinds.push(FieldKind::Text);
+    }
+    if dom.is_html_element(node, "a") && dom.get_attr(node, "href").is_some() {
+        kinds.push(FieldKind::Attr("href".to_string()));
+    }
+    if dom.is_html_element(node, "img") && dom.get_attr(node, "src").is_some() {
+        kinds.push(FieldKind::Attr("src".to_string()));
+    }
+    kinds
+}
+
+/// A selector for `node` relative to its ancestor `item`, as a chain of child steps, or `None`
+/// if `node` is `item` or a step can't be written.
+fn relative_path(dom: &GenerationalArenaDom, item: Handle, node: Handle) -> Option<String> {
+    if node == item {
+        return None;
+    }
+    let mut steps = Vec::new();
+    for step in node
+        .ancestors(&dom.arena)
+        .take_while(|&ancestor| ancestor != item)
+    {
+        let key = step_key(dom, step)?;
+        let parent = dom.arena[step].parent()?;
+        let alike: Vec<Handle> = parent
+            .children(&dom.arena)
+            .filter(|&sibling| step_key(dom, sibling).as_ref() == Some(&key))
+            .collect();
+        if alike.len() > 1 {
+            let index = parent
+                .children(&dom.arena)
+                .filter(|&sibling| dom.element_name(sibling).is_some())
+                .position(|sibling| sibling == step)?;
+            steps.push(format!("{}:nth-child({})", key, index + 1));
+        } else {
+            steps.push(key);
+        }
+    }
+    steps.reverse();
+    Some(steps.join(" > "))
+}
+
+/// A selector for an element on its own, from its tag and classes, or `None` if it isn't an
+/// element or they can't be written in a selector.
+fn step_key(dom: &GenerationalArenaDom, node: Handle) -> Option<String> {
+    let name = dom.element_name(node)?;
+    if !is_ident(&name.local) {
+        return None;
+    }
+    let mut key = name.local.to_string();
+    if let Some(class) = dom.get_attr(node, "class") {
+        let mut classes: Vec<&str> = class
+            .split_ascii_whitespace()
+            .filter(|class| is_ident(class))
+            .collect();
+        classes.sort_unstable();
+        classes.dedup();
+        for class in classes {
+            key.push('.');
+            key.push_str(class);
+        }
+    }
+    Some(key)
+}
+
+/// A name for the field found at `example`: its first class, or else its tag.
+fn field_name(dom: &GenerationalArenaDom, example: Handle, path: &str) -> String {
+    dom.get_attr(example, "class")
+        .and_then(|class| {
+            class
+                .split_ascii_whitespace()
+                .find(|class| is_ident(class))
+                .map(str::to_string)
+        })
+        .or_else(|| dom.element_name(example).map(|name| name.local.to_string()))
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::listings;
+    use crate::extract::FieldKind;
+    use crate::parse::parse_html;
+
+    const HTML: &str = "<ul class=results>\
+        <li class=card><a href=/a>A</a><span class=price>1</span><img src=a.png></li>\
+        <li class=card><a href=/b>B</a><span class=price>2</span></li>\
+        <li class=card><a href=/c>C</a><span class=price>3</span></li>\
+        <li class=ad>Ad</li>\
+        </ul><p>x</p><p>y</p><div><p>one</p><p>two</p></div>";
+
+    #[test]
+    fn finds_repeated_items() {
+        let dom = parse_html(HTML);
+        let found = listings(&dom);
+        assert_eq!(found.len(), 1);
+        let listing = &found[0];
+        let ul = dom.query_selector(dom.document, "ul").unwrap().unwrap();
+        let cards: Vec<_> = dom.query_selector_all(ul, ".card").unwrap().collect();
+        assert_eq!(listing.container, ul);
+        assert_eq!(listing.items, cards);
+        let fields: Vec<(&str, &FieldKind)> = listing
+            .template
+            .fields
+            .iter()
+            .map(|(name, field)| (name.as_str(), &field.kind))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("a", &FieldKind::Text),
+                ("a_href", &FieldKind::Attr("href".to_string())),
+                ("price", &FieldKind::Text),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_dissimilar_siblings() {
+        let dom = parse_html(
+            "<div><section><h2>a</h2></section><section><table><tr><td>b</td></tr></table>\
+             </section><section><ul><li>c</li></ul><p>d</p></section></div>",
+        );
+        assert!(listings(&dom).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn extracts_items() {
+        let dom = parse_html(HTML);
+        let listing = &listings(&dom)[0];
+        let items: Vec<serde_json::Value> = listing
+            .items
+            .iter()
+            .map(|&item| listing.template.extract(&dom, item))
+            .collect();
+        assert_eq!(
+            items[1],
+            serde_json::json!({"a": "B", "a_href": "/b", "price": "2"})
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{GenerationalArenaDom, Handle};
+
+/// The valid values of `<track kind>`. Invalid values mean `metadata`; a missing one means
+/// `subtitles`.
+const TRACK_KINDS: &[&str] = &[
+    "subtitles",
+    "captions",
+    "descriptions",
+    "chapters",
+    "metadata",
+];
+
+/// Whether a [`Media`] element is a video or audio player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    /// `<video>`.
+    Video,
+    /// `<audio>`.
+    Audio,
+}
+
+/// A `<source>` of a media element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MediaSource {
+    /// The resolved URL.
+    pub url: String,
+    /// The MIME type from the `type` attribute, which may include a `codecs` parameter.
+    pub media_type: Option<String>,
+    /// The `media` query the source applies under.
+    pub media: Option<String>,
+}
+
+/// A text `<track>` of a media element, such as subtitles or captions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MediaTrack {
+    /// The resolved URL of the track file, usually WebVTT.
+    pub url: String,
+    /// The `kind` of track, lowercased: `subtitles` (the default), `captions`,
+    /// `descriptions`, `chapters` or `metadata`.
+    pub kind: String,
+    /// The track's language, from `srclang`.
+    pub language: Option<String>,
+    /// The track's user-visible label.
+    pub label: Option<String>,
+    /// Whether the track has the `default` attribute.
+    pub default: bool,
+}
+
+/// A `<video>` or `<audio>` element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Media {
+    /// Whether it is video or audio.
+    pub kind: MediaKind,
+    /// The resolved `src` attribute. Browsers ignore the `<source>` children when it is set.
+    pub src: Option<String>,
+    /// The `<source>` children, in order.
+    pub sources: Vec<MediaSource>,
+    /// The resolved `poster` image of a video.
+    pub poster: Option<String>,
+    /// The `<track>` children, in order.
+    pub tracks: Vec<MediaTrack>,
+    /// The `<video>` or `<audio>` element.
+    pub handle: Handle,
+}
+
+impl Media {
+    /// The tracks meant to be shown over the media: subtitles and captions.
+    pub fn caption_tracks(&self) -> impl Iterator<Item = &MediaTrack> {
+        self.tracks
+            .iter()
+            .filter(|track| track.kind == "subtitles" || track.kind == "captions")
+    }
+}
+
+/// Find the `<video>` and `<audio>` elements of a document, in document order, with their
+/// sources, poster and text tracks. URLs are resolved against the document's base URL, and
+/// sources and tracks without one are skipped.
+pub fn media(dom: &GenerationalArenaDom) -> Vec<Media> {
+    dom.document
+        .descendants(&dom.arena)
+        .filter_map(|node| {
+            let kind = if dom.is_html_element(node, "video") {
+                MediaKind::Video
+            } else if dom.is_html_element(node, "audio") {
+                MediaKind::Audio
+            } else {
+                return None;
+            };
+            let attr = |handle, name| {
+                dom.get_attr(handle, name)
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+            };
+            let url = |handle, name| attr(handle, name).map(|url| dom.resolve_url(&url));
+            let children = |name| {
+                node.children(&dom.arena)
+                    .filter(move |&child| dom.is_html_element(child, name))
+            };
+            Some(Media {
+                kind,
+                src: url(node, "src"),
+                sources: children("source")
+                    .filter_map(|source| {
+                        Some(MediaSource {
+                            url: url(source, "src")?,
+                            media_type: attr(source, "type"),
+                            media: attr(source, "media"),
+                        })
+                    })
+                    .collect(),
+                poster: match kind {
+                    MediaKind::Video => url(node, "poster"),
+                    MediaKind::Audio => None,
+                },
+                tracks: children("track")
+                    .filter_map(|track| {
+                        Some(MediaTrack {
+                            url: url(track, "src")?,
+                            kind: match attr(track, "kind").map(|kind| kind.to_ascii_lowercase()) {
+                                Some(kind) if TRACK_KINDS.contains(&kind.as_str()) => kind,
+                                Some(_) => "metadata".to_string(),
+                                None => "subtitles".to_string(),
+                            },
+                            language: attr(track, "srclang"),
+                            label: attr(track, "label"),
+                            default: dom.has_bool_attr(track, "default"),
+                        })
+                    })
+                    .collect(),
+                handle: node,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{media, MediaKind, MediaSource, MediaTrack};
+    use crate::parse::parse_html;
+
+    #[test]
+    fn finds_media_with_sources_and_tracks() {
+        let mut dom = parse_html(
+            "<video poster=poster.jpg controls>\
+               <source src=clip.webm type='video/webm; codecs=\"vp9\"'>\
+               <source src=' ' type=video/mp4>\
+               <source src=clip.mp4 media='(min-width: 800px)'>\
+               <track src=en.vtt srclang=en label=English default>\
+               <track src=chapters.vtt kind=CHAPTERS>\
+               <track src=odd.vtt kind=karaoke>\
+               <track kind=captions>\
+             </video>\
+             <audio src=/song.mp3 poster=ignored.jpg><track src=lyrics.vtt kind=captions></audio>",
+        );
+        dom.set_url("https://example.com/media/");
+        let found = media(&dom);
+        assert_eq!(found.len(), 2);
+
+        let video = &found[0];
+        assert_eq!(video.kind, MediaKind::Video);
+        assert_eq!(video.src, None);
+        assert_eq!(
+            video.poster.as_deref(),
+            Some("https://example.com/media/poster.jpg")
+        );
+        assert_eq!(
+            video.sources,
+            [
+                MediaSource {
+                    url: "https://example.com/media/clip.webm".to_string(),
+                    media_type: Some("video/webm; codecs=\"vp9\"".to_string()),
+                    media: None,
+                },
+                MediaSource {
+                    url: "https://example.com/media/clip.mp4".to_string(),
+                    media_type: None,
+                    media: Some("(min-width: 800px)".to_string()),
+                },
+            ]
+        );
+        let kinds: Vec<&str> = video
+            .tracks
+            .iter()
+            .map(|track| track.kind.as_str())
+            .collect();
+        assert_eq!(kinds, ["subtitles", "chapters", "metadata"]);
+        assert_eq!(
+            video.caption_tracks().collect::<Vec<_>>(),
+            [&MediaTrack {
+                url: "https://example.com/media/en.vtt".to_string(),
+                kind: "subtitles".to_string(),
+                language: Some("en".to_string()),
+                label: Some("English".to_string()),
+                default: true,
+            }]
+        );
+
+        let audio = &found[1];
+        assert_eq!(audio.kind, MediaKind::Audio);
+        assert_eq!(audio.src.as_deref(), Some("https://example.com/song.mp3"));
+        assert_eq!(audio.poster, None);
+        assert_eq!(audio.caption_tracks().count(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+//! Pulling structured information out of a parsed document.
+//!
+//! Extractors are free functions taking the DOM, e.g. [`feeds(&dom)`](feeds). URLs they return
+//! are resolved against the document's [base URL](crate::GenerationalArenaDom::base_url), so
+//! set the document's URL with [`set_url`](crate::GenerationalArenaDom::set_url) (or load it
+//! with `fetch`) to get absolute URLs.
+//!
+//! Extractors producing free-form JSON, such as `Schema::extract` and `project`, return
+//! `serde_json::Value`s and need the `serde` feature.
+
+mod breadcrumbs;
+mod canonical;
+mod code;
+mod feeds;
+mod from_dom;
+mod icons;
+mod images;
+mod languages;
+#[cfg(feature = "serde")]
+mod lens;
+mod listings;
+mod media;
+mod outline;
+mod pagination;
+mod schema;
+mod stats;
+mod svgs;
+mod values;
+
+pub use self::breadcrumbs::{breadcrumbs, Breadcrumb};
+pub use self::canonical::canonical_url;
+pub use self::code::{code_blocks, CodeBlock};
+pub use self::feeds::{feeds, Feed, FeedKind};
+#[doc(hidden)]
+pub use self::from_dom::__private;
+pub use self::from_dom::{FromDom, FromDomError, FromDomErrorKind, FromText};
+pub use self::icons::{best_icon, icons, Icon, IconKind};
+pub use self::images::{images, Image};
+pub use self::languages::alternate_languages;
+#[cfg(feature = "serde")]
+pub use self::lens::{project, Lens};
+pub use self::listings::{listings, Listing};
+pub use self::media::{media, Media, MediaKind, MediaSource, MediaTrack};
+pub use self::outline::{outline, OutlineEntry};
+pub use self::pagination::{pagination, Pagination};
+pub use self::schema::{Field, FieldKind, Schema};
+pub use self::stats::{text_stats, TextStats};
+pub use self::svgs::{svgs, InlineSvg};
+pub use self::values::{parse_date, parse_number, parse_percent, parse_price, Price, ValueParser};
+#[cfg(feature = "derive")]
+pub use generational_arena_dom_derive::FromDom;
+
+use crate::{GenerationalArenaDom, Handle};
+
+/// Whether the `rel` attribute of `handle` contains `token`, ignoring ASCII case.
+fn has_rel(dom: &GenerationalArenaDom, handle: Handle, token: &str) -> bool {
+    dom.get_attr(handle, "rel")
+        .is_some_and(|rel| crate::has_token(&rel, token))
+}
@@ -0,0 +1,136 @@
+use crate::{GenerationalArenaDom, Handle};
+
+/// Elements whose `<h1>` headings rank below the headings of the enclosing section.
+const SECTIONING_ELEMENTS: &[&str] = &["article", "aside", "nav", "section"];
+
+/// A heading in a document outline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// The heading element.
+    pub handle: Handle,
+    /// The heading's level, from 1 upwards.
+    pub level: usize,
+    /// The heading's text, with whitespace collapsed.
+    pub text: String,
+    /// The headings nested under this one.
+    pub children: Vec<OutlineEntry>,
+}
+
+/// Build a nested outline of a document's `h1`–`h6` headings, e.g. for a table of contents.
+///
+/// A heading nests under the closest preceding heading of a lower level. An `<h1>` inside
+/// sectioning elements (`section`, `article`, `aside` and `nav`) is ranked one level deeper per
+/// enclosing section, so pages that use `<h1>` for every section still get a nested outline.
+/// Of the headings in an `<hgroup>`, only the highest-ranked one is included.
+pub fn outline(dom: &GenerationalArenaDom) -> Vec<OutlineEntry> {
+    let mut roots: Vec<OutlineEntry> = Vec::new();
+    // The path of indices from `roots` to the most recent entry.
+    let mut path: Vec<usize> = Vec::new();
+    for node in dom.document.descendants(&dom.arena) {
+        let level = match dom.heading_rank(node) {
+            Some(rank) => rank,
+            None => continue,
+        };
+        if let Some(hgroup) = node
+            .ancestors(&dom.arena)
+            .find(|&ancestor| dom.is_html_element(ancestor, "hgroup"))
+        {
+            let top = hgroup
+                .descendants(&dom.arena)
+                .filter_map(|heading| Some((dom.heading_rank(heading)?, heading)))
+                .min_by_key(|&(rank, _)| rank);
+            if top.map(|(_, heading)| heading) != Some(node) {
+                continue;
+            }
+        }
+        let level = if level == 1 {
+            1 + node
+                .ancestors(&dom.arena)
+                .filter(|&ancestor| {
+                    SECTIONING_ELEMENTS
+                        .iter()
+                        .any(|name| dom.is_html_element(ancestor, name))
+                })
+                .count()
+        } else {
+            level
+        };
+        let entry = OutlineEntry {
+            handle: node,
+            level,
+            text: dom
+                .text_content(node)
+                .split_ascii_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+            children: Vec::new(),
+        };
+
+        // Walk down to the deepest entry with a lower level than this one.
+        let mut siblings = &mut roots;
+        let mut depth = 0;
+        while depth < path.len() && siblings[path[depth]].level < level {
+            siblings = &mut siblings[path[depth]].children;
+            depth += 1;
+        }
+        path.truncate(depth);
+        path.push(siblings.len());
+        siblings.push(entry);
+    }
+    roots
+}
+
+impl GenerationalArenaDom {
+    /// The rank of `handle` if it is an `h1`–`h6` element.
+    pub(crate) fn heading_rank(&self, handle: Handle) -> Option<usize> {
+        ["h1", "h2", "h3", "h4", "h5", "h6"]
+            .iter()
+            .position(|name| self.is_html_element(handle, name))
+            .map(|index| index + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{outline, OutlineEntry};
+    use crate::parse::parse_html;
+
+    fn summarize(entries: &[OutlineEntry]) -> String {
+        entries
+            .iter()
+            .map(|entry| {
+                let children = summarize(&entry.children);
+                if children.is_empty() {
+                    format!("{}:{}", entry.level, entry.text)
+                } else {
+                    format!("{}:{}({})", entry.level, entry.text, children)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn nests_headings() {
+        let dom = parse_html(
+            "<h1>Title</h1><h2>A</h2><h4>A  deep\n</h4><h3>A sub</h3><h2>B</h2><h1>Second</h1>",
+        );
+        assert_eq!(
+            summarize(&outline(&dom)),
+            "1:Title(2:A(4:A deep 3:A sub) 2:B) 1:Second"
+        );
+    }
+
+    #[test]
+    fn ranks_section_headings_and_hgroups() {
+        let dom = parse_html(
+            "<h1>Page</h1><section><h1>Part</h1><article><h1>Post</h1></article></section>\
+             <hgroup><h3>Sub</h3><h2>Group</h2></hgroup><h3>After</h3>",
+        );
+        assert_eq!(
+            summarize(&outline(&dom)),
+            "1:Page(2:Part(3:Post) 2:Group(3:After))"
+        );
+        assert!(outline(&parse_html("<p>no headings</p>")).is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::extract::has_rel;
+use crate::{GenerationalArenaDom, Handle};
+
+/// Link texts that mean "next page", compared after lowercasing and collapsing whitespace.
+const NEXT_TEXTS: &[&str] = &[
+    "next",
+    "next page",
+    "next »",
+    "next ›",
+    "next →",
+    "»",
+    "›",
+    "→",
+    ">",
+    ">>",
+    "older posts",
+    "older entries",
+];
+
+/// Link texts that mean "previous page".
+const PREV_TEXTS: &[&str] = &[
+    "prev",
+    "previous",
+    "previous page",
+    "« previous",
+    "‹ previous",
+    "← previous",
+    "« prev",
+    "‹ prev",
+    "«",
+    "‹",
+    "←",
+    "<",
+    "<<",
+    "newer posts",
+    "newer entries",
+];
+
+/// Links to the neighbouring pages of a multipage document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pagination {
+    /// The resolved URL of the next page.
+    pub next: Option<String>,
+    /// The resolved URL of the previous page.
+    pub prev: Option<String>,
+}
+
+/// Find the next and previous pages of a multipage document or listing.
+///
+/// `rel="next"` and `rel="prev"` (or `previous`) on `<link>`, `<a>` and `<area>` elements take
+/// precedence. Failing that, links whose text, `aria-label` or `class` says "next" or
+/// "previous" (including arrows like `»`) are used. Links to fragments of the same page and
+/// `javascript:` links are ignored.
+pub fn pagination(dom: &GenerationalArenaDom) -> Pagination {
+    let links: Vec<Handle> = dom
+        .document
+        .descendants(&dom.arena)
+        .filter(|&node| {
+            ["link", "a", "area"]
+                .iter()
+                .any(|name| dom.is_html_element(node, name))
+        })
+        .filter(|&node| {
+            dom.get_attr(node, "href").is_some_and(|href| {
+                let href = href.trim();
+                !href.is_empty()
+                    && !href.starts_with('#')
+                    && !href.to_ascii_lowercase().starts_with("javascript:")
+            })
+        })
+        .collect();
+    let find = |rels: &[&str], texts: &[&str], word: &str| {
+        let by_rel = links
+            .iter()
+            .find(|&&link| rels.iter().any(|rel| has_rel(dom, link, rel)));
+        let by_text = || {
+            links.iter().find(|&&link| {
+                if dom.is_html_element(link, "link") {
+                    return false;
+                }
+                let text = dom
+                    .text_content(link)
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase();
+                let label = dom
+                    .get_attr(link, "aria-label")
+                    .map(|label| label.trim().to_lowercase());
+                let class = dom.get_attr(link, "class").unwrap_or_default();
+                texts.contains(&text.as_str())
+                    || label.is_some_and(|label| {
+                        label == word || label.starts_with(&format!("{} ", word))
+                    })
+                    || class
+                        .split_ascii_whitespace()
+                        .any(|class| class.eq_ignore_ascii_case(word))
+            })
+        };
+        by_rel
+            .or_else(by_text)
+            .and_then(|&link| dom.get_attr(link, "href"))
+            .map(|href| dom.resolve_url(&href))
+    };
+    Pagination {
+        next: find(&["next"], NEXT_TEXTS, "next"),
+        prev: find(&["prev", "previous"], PREV_TEXTS, "previous")
+            .or_else(|| find(&[], &[], "prev")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pagination, Pagination};
+    use crate::parse::parse_html;
+
+    fn found(html: &str) -> Pagination {
+        let mut dom = parse_html(html);
+        dom.set_url("https://example.com/list/2");
+        pagination(&dom)
+    }
+
+    #[test]
+    fn prefers_rel_links() {
+        assert_eq!(
+            found(
+                "<a href=/wrong>Next</a><link rel=next href=3>\
+                 <a rel='nofollow Previous' href=1>back</a>"
+            ),
+            Pagination {
+                next: Some("https://example.com/list/3".to_string()),
+                prev: Some("https://example.com/list/1".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn falls_back_to_link_text_labels_and_classes() {
+        assert_eq!(
+            found(
+                "<a href=#top>Next</a><a href='javascript:go()'>»</a><a href=?p=3> Next\n » </a>"
+            ),
+            Pagination {
+                next: Some("https://example.com/list/2?p=3".to_string()),
+                prev: None,
+            }
+        );
+        assert_eq!(
+            found("<a aria-label='Previous page' href=1>1</a><a class='btn NEXT' href=3>3</a>"),
+            Pagination {
+                next: Some("https://example.com/list/3".to_string()),
+                prev: Some("https://example.com/list/1".to_string()),
+            }
+        );
+        assert_eq!(
+            found("<a class=prev href=1>1</a><link href=/next title=next>"),
+            Pagination {
+                next: None,
+                prev: Some("https://example.com/list/1".to_string()),
+            }
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::extract::ValueParser;
+use crate::{GenerationalArenaDom, Handle, Selector, SelectorError};
+
+/// What value a [`Field`] takes from the elements it selects.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    /// The element's text, with whitespace collapsed.
+    Text,
+    /// The element's inner HTML.
+    Html,
+    /// The value of an attribute. Attributes holding URLs (`href`, `src`, `action`, `poster`,
+    /// `cite` and `data`) are resolved against the document's base URL.
+    Attr(String),
+    /// An object extracted from the element with another schema.
+    Nested(Schema),
+}
+
+/// One field of a [`Schema`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    /// The selector for the elements the field's value comes from, relative to the scope the
+    /// schema is run on. `None` selects the scope itself.
+    pub selector: Option<Selector>,
+    /// What value to take from each element.
+    pub kind: FieldKind,
+    /// Whether to collect the values of all selected elements into an array rather than
+    /// taking the first.
+    pub list: bool,
+    /// How to turn the text or attribute value taken from each element into a typed value.
+    /// `None` keeps it as a string.
+    pub parser: Option<ValueParser>,
+}
+
+impl Field {
+    /// A field taking the text of the first element matching `selector`.
+    pub fn text(selector: &str) -> Result<Field, SelectorError> {
+        Field::new(selector, FieldKind::Text)
+    }
+
+    /// A field taking the inner HTML of the first element matching `selector`.
+    pub fn html(selector: &str) -> Result<Field, SelectorError> {
+        Field::new(selector, FieldKind::Html)
+    }
+
+    /// A field taking the attribute `name` of the first element matching `selector`.
+    pub fn attr(selector: &str, name: &str) -> Result<Field, SelectorError> {
+        Field::new(selector, FieldKind::Attr(name.to_string()))
+    }
+
+    /// A field running `schema` on the first element matching `selector`.
+    pub fn nested(selector: &str, schema: Schema) -> Result<Field, SelectorError> {
+        Field::new(selector, FieldKind::Nested(schema))
+    }
+
+    /// Collect the values of all matching elements into an array instead.
+    pub fn list(mut self) -> Field {
+        self.list = true;
+        self
+    }
+
+    /// Convert each value with `parser`, e.g. to read `"1,299.00 €"` as a price. Values that
+    /// `parser` can't read become `null`.
+    pub fn parse(mut self, parser: ValueParser) -> Field {
+        self.parser = Some(parser);
+        self
+    }
+
+    /// A field of the given kind. An empty `selector` selects the scope itself.
+    pub fn new(selector: &str, kind: FieldKind) -> Result<Field, SelectorError> {
+        let selector = match selector.trim() {
+            "" => None,
+            selector => Some(Selector::parse(selector)?),
+        };
+        Ok(Field {
+            selector,
+            kind,
+            list: false,
+            parser: None,
+        })
+    }
+}
+
+/// A declarative description of the data to scrape from a document: a list of named fields,
+/// each taking a value from the elements matched by a selector. Fields can nest schemas, so
+/// e.g. every `.review` on a product page can become an object in a `reviews` array.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Schema {
+    /// The fields, in the order they appear in the output.
+    pub fields: Vec<(String, Field)>,
+}
+
+impl Schema {
+    /// A schema without fields.
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// Add a field called `name`.
+    pub fn field(mut self, name: &str, field: Field) -> Schema {
+        self.fields.push((name.to_string(), field));
+        self
+    }
+
+    /// Run the schema on the elements under `scope`, producing an object with a member per
+    /// field.
+    ///
+    /// A field whose selector matches nothing is `null`, or an empty array for list fields, as
+    /// is an attribute the element doesn't have.
+    #[cfg(feature = "serde")]
+    pub fn extract(&self, dom: &GenerationalArenaDom, scope: Handle) -> serde_json::Value {
+        use serde_json::Value;
+
+        let members = self
+            .fields
+            .iter()
+            .map(|(name, field)| {
+                let matches = match &field.selector {
+                    Some(selector) => dom.select(scope, selector),
+                    None => vec![scope],
+                };
+                let value = if field.list {
+                    Value::Array(
+                        matches
+                            .into_iter()
+                            .map(|node| typed_value(dom, node, field))
+                            .collect(),
+                    )
+                } else {
+                    matches
+                        .first()
+                        .map_or(Value::Null, |&node| typed_value(dom, node, field))
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        Value::Object(members)
+    }
+}
+
+/// The value of `field` for `node`, converted by its parser if it has one.
+#[cfg(feature = "serde")]
+fn typed_value(dom: &GenerationalArenaDom, node: Handle, field: &Field) -> serde_json::Value {
+    use serde_json::Value;
+
+    match (&field.kind, &field.parser) {
+        (FieldKind::Nested(schema), _) => schema.extract(dom, node),
+        (kind, parser) => match (field_text(dom, node, kind), parser) {
+            (Some(text), Some(parser)) => parser.parse(&text).unwrap_or(Value::Null),
+            (Some(text), None) => Value::String(text),
+            (None, _) => Value::Null,
+        },
+    }
+}
+
+/// The text a field of `kind` takes from `node`, or `None` for a missing attribute or a nested
+/// schema.
+pub(crate) fn field_text(
+    dom: &GenerationalArenaDom,
+    node: Handle,
+    kind: &FieldKind,
+) -> Option<String> {
+    match kind {
+        FieldKind::Text => Some(
+            dom.text_content(node)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+        FieldKind::Html => Some(dom.inner_html(node)),
+        FieldKind::Attr(name) => match dom.get_attr(node, name) {
+            Some(value) if is_url_attr(name) => Some(dom.resolve_url(&value)),
+            Some(value) => Some(value.to_string()),
+            None => None,
+        },
+        FieldKind::Nested(_) => None,
+    }
+}
+
+fn is_url_attr(name: &str) -> bool {
+    ["href", "src", "action", "poster", "cite", "data"]
+        .iter()
+        .any(|attr| attr.eq_ignore_ascii_case(name))
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use serde_json::json;
+
+    use super::{Field, Schema};
+    use crate::extract::ValueParser;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn extracts_values() {
+        let mut dom = parse_html(
+            "<div class=product><h1> Lamp\n</h1><span class=price>1,299.00 €</span>\
+             <a href=/lamp>Details</a><p class=review>Bright</p><p class=review>Tall</p></div>",
+        );
+        dom.set_url("https://example.com/shop/");
+        let review = Schema::new().field("text", Field::text("").unwrap());
+        let schema = Schema::new()
+            .field("name", Field::text("h1").unwrap())
+            .field(
+                "price",
+                Field::text(".price").unwrap().parse(ValueParser::Price),
+            )
+            .field("link", Field::attr("a", "href").unwrap())
+            .field("title", Field::attr("a", "title").unwrap())
+            .field("reviews", Field::nested(".review", review).unwrap().list())
+            .field("missing", Field::text("table").unwrap());
+        let value = schema.extract(&dom, dom.document);
+        assert_eq!(
+            value,
+            json!({
+                "name": "Lamp",
+                "price": {"amount": 1299.0, "currency": "EUR"},
+                "link": "https://example.com/lamp",
+                "title": null,
+                "reviews": [{"text": "Bright"}, {"text": "Tall"}],
+                "missing": null,
+            })
+        );
+        let keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        assert_eq!(
+            keys,
+            ["name", "price", "link", "title", "reviews", "missing"]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Elements whose text isn't displayed.
+const HIDDEN_ELEMENTS: &[&str] = &["script", "style", "noscript", "template", "head"];
+
+/// The average silent reading speed of adults, in words per minute, used for
+/// [`TextStats::reading_time`].
+const WORDS_PER_MINUTE: f64 = 238.0;
+
+/// Statistics about the visible text under an element, from [`text_stats`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextStats {
+    /// The number of words.
+    pub words: usize,
+    /// The number of sentences. A sentence ends at `.`, `!`, `?` or `…` followed by whitespace,
+    /// or at the end of a block element such as a heading or list item.
+    pub sentences: usize,
+    /// The number of `<p>` elements containing words.
+    pub paragraphs: usize,
+    /// The number of characters, not counting whitespace.
+    pub characters: usize,
+    /// An estimate of how long the text takes to read at 238 words per minute. Divide
+    /// [`words`](TextStats::words) by another speed for a different estimate.
+    pub reading_time: Duration,
+}
+
+/// Count the words, sentences and paragraphs of the visible text under `handle`.
+///
+/// Text inside `<script>`, `<style>`, `<noscript>`, `<template>` and `<head>` elements and
+/// inside elements with the `hidden` attribute is ignored. Block elements separate words, so
+/// `<li>one</li><li>two</li>` is two words while `un<b>usual</b>` is one.
+pub fn text_stats(dom: &GenerationalArenaDom, handle: Handle) -> TextStats {
+    let mut stats = TextStats {
+        words: 0,
+        sentences: 0,
+        paragraphs: 0,
+        characters: 0,
+        reading_time: Duration::ZERO,
+    };
+    // The visible text, with a line break at each block boundary.
+    let mut text = String::new();
+    let mut stack = vec![(handle, false)];
+    while let Some((node, leaving)) = stack.pop() {
+        if leaving {
+            text.push('\n');
+            continue;
+        }
+        match dom.get_node(&node) {
+            NodeData::Text { contents } => {
+                text.push_str(&contents.borrow());
+                continue;
+            }
+            NodeData::Element { .. } => {
+                let hidden = HIDDEN_ELEMENTS
+                    .iter()
+                    .any(|name| dom.is_html_element(node, name))
+                    || dom.has_bool_attr(node, "hidden");
+                if hidden && node != handle {
+                    continue;
+                }
+                if dom.is_html_element(node, "p")
+                    && dom.text_content(node).chars().any(|c| !c.is_whitespace())
+                {
+                    stats.paragraphs += 1;
+                }
+                if dom.is_block(node) || dom.is_html_element(node, "br") {
+                    text.push('\n');
+                    stack.push((node, true));
+                }
+            }
+            NodeData::Document => {}
+            _ => continue,
+        }
+        let start = stack.len();
+        stack.extend(node.children(&dom.arena).map(|child| (child, false)));
+        stack[start..].reverse();
+    }
+
+    for block in text.split('\n') {
+        let words: Vec<&str> = block.split_whitespace().collect();
+        stats.words += words.len();
+        stats.characters += words.iter().map(|word| word.chars().count()).sum::<usize>();
+        stats.sentences += words.iter().filter(|word| ends_sentence(word)).count();
+        if words.last().is_some_and(|word| !ends_sentence(word)) {
+            stats.sentences += 1;
+        }
+    }
+    stats.reading_time = Duration::from_secs_f64(stats.words as f64 / WORDS_PER_MINUTE * 60.0);
+    stats
+}
+
+/// Whether `word` ends a sentence, looking past closing quotes and brackets.
+fn ends_sentence(word: &str) -> bool {
+    word.trim_end_matches(['"', '\'', ')', ']', '\u{201d}', '\u{2019}'])
+        .ends_with([
+            '.', '!', '?', '\u{2026}', '\u{3002}', '\u{ff01}', '\u{ff1f}',
+        ])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{text_stats, TextStats};
+    use crate::parse::parse_html;
+
+    #[test]
+    fn counts_visible_text() {
+        let dom = parse_html(
+            "<title>Not counted</title><h1>A heading</h1>\
+             <p>First sentence. Second one! \"Quoted?\" un<b>usual</b> end</p>\
+             <ul><li>one</li><li>two</li></ul><p> </p><p hidden>secret words</p>\
+             <script>var x = 1;</script>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let stats = text_stats(&dom, body);
+        assert_eq!(
+            stats,
+            TextStats {
+                words: 11,
+                sentences: 7,
+                paragraphs: 1,
+                characters: 57,
+                reading_time: Duration::from_secs_f64(11.0 / 238.0 * 60.0),
+            }
+        );
+        assert_eq!(text_stats(&dom, dom.document).words, 11);
+    }
+}
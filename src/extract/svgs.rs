@@ -0,0 +1,212 @@
+use std::cell::RefCell;
+use std::fmt;
+
+use markup5ever::{namespace_url, ns, LocalName, QualName};
+
+use crate::url::resolve;
+use crate::{GenerationalArenaDom, Handle, NodeData, SerializeOpts};
+
+/// An inline SVG image copied out of a document by [`svgs`].
+pub struct InlineSvg {
+    /// The outermost `<svg>` element in the source document.
+    pub handle: Handle,
+    /// A document holding a copy of the image, with the `<svg>` element as its root.
+    pub document: GenerationalArenaDom,
+}
+
+impl InlineSvg {
+    /// The image as a standalone SVG file: an XML declaration followed by the `<svg>` element
+    /// serialized as XML, with the `xmlns` (and if needed `xmlns:xlink`) declarations a browser
+    /// infers for inline SVG spelled out.
+    pub fn to_xml(&self) -> String {
+        let opts = SerializeOpts {
+            xhtml: true,
+            ..SerializeOpts::default()
+        };
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&self.document.serialize(self.document.document, &opts));
+        xml
+    }
+}
+
+impl fmt::Debug for InlineSvg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InlineSvg")
+            .field("handle", &self.handle)
+            .field("xml", &self.to_xml())
+            .finish()
+    }
+}
+
+/// Copy each inline SVG image of a document into a standalone document, in document order, for
+/// harvesting icons and illustrations.
+///
+/// Every outermost SVG element is copied, usually an `<svg>` in HTML. Elements elsewhere in the
+/// document that the image refers to with `<use href="#id">`, as icon sprite sheets do, are
+/// copied into a `<defs>` at the start of the image so that it renders on its own. URLs other
+/// than such fragment references are resolved against the document's base URL.
+pub fn svgs(dom: &GenerationalArenaDom) -> Vec<InlineSvg> {
+    let base = dom.base_url();
+    dom.svg_roots()
+        .filter(|&root| {
+            dom.element_name(root)
+                .is_some_and(|name| &*name.local == "svg")
+        })
+        .map(|root| {
+            let mut document = GenerationalArenaDom::default();
+            let svg = document.import_subtree(dom, root);
+            document.document.append(svg, &mut document.arena);
+
+            let referenced = external_references(dom, root);
+            if !referenced.is_empty() {
+                let defs = document.arena.new_node(NodeData::Element {
+                    name: QualName::new(None, ns!(svg), LocalName::from("defs")),
+                    attrs: RefCell::new(Vec::new()),
+                    template_contents: RefCell::new(None),
+                    mathml_annotation_xml_integration_point: false,
+                });
+                for element in referenced {
+                    let copy = document.import_subtree(dom, element);
+                    defs.append(copy, &mut document.arena);
+                }
+                svg.prepend(defs, &mut document.arena);
+            }
+
+            if let Some(base) = &base {
+                document.rewrite_urls(svg, |url| {
+                    if url.starts_with('#') {
+                        None
+                    } else {
+                        resolve(base, url)
+                    }
+                });
+            }
+            InlineSvg {
+                handle: root,
+                document,
+            }
+        })
+        .collect()
+}
+
+/// The elements outside `root` that `<use>` elements under it refer to, directly or through
+/// other referenced elements, in the order they are first referred to.
+fn external_references(dom: &GenerationalArenaDom, root: Handle) -> Vec<Handle> {
+    // The subtrees to scan for references: `root`, then each element referenced so far.
+    let mut subtrees = vec![root];
+    let mut next = 0;
+    while next < subtrees.len() {
+        let subtree = subtrees[next];
+        next += 1;
+        for node in subtree.descendants(&dom.arena) {
+            if !dom
+                .element_name(node)
+                .is_some_and(|name| name.ns == ns!(svg) && &*name.local == "use")
+            {
+                continue;
+            }
+            let id = match use_href(dom, node) {
+                Some(href) if href.starts_with('#') && href.len() > 1 => href[1..].to_string(),
+                _ => continue,
+            };
+            let target = dom.document.descendants(&dom.arena).find(|&element| {
+                dom.get_attr(element, "id")
+                    .is_some_and(|value| *value == *id)
+            });
+            if let Some(target) = target {
+                let inside = target
+                    .ancestors(&dom.arena)
+                    .any(|ancestor| ancestor == root);
+                if !inside && !subtrees.contains(&target) {
+                    subtrees.push(target);
+                }
+            }
+        }
+    }
+    subtrees.remove(0);
+    subtrees
+}
+
+/// The `href` of a `<use>` element, or its older `xlink:href`.
+fn use_href(dom: &GenerationalArenaDom, node: Handle) -> Option<String> {
+    match dom.get_node(&node) {
+        NodeData::Element { attrs, .. } => {
+            let attrs = attrs.borrow();
+            let href = |namespace| {
+                attrs
+                    .iter()
+                    .find(|attr| attr.name.ns == namespace && &*attr.name.local == "href")
+                    .map(|attr| attr.value.trim().to_string())
+            };
+            href(ns!()).or_else(|| href(ns!(xlink)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::svgs;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn declares_namespaces() {
+        let dom = parse_html(
+            "<p><svg viewBox=\"0 0 1 1\"><use xlink:href=\"#i\"></use></svg>\
+             <svg><g id=i></g></svg>",
+        );
+        let images = svgs(&dom);
+        assert_eq!(images.len(), 2);
+        assert_eq!(
+            images[0].to_xml(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\" viewBox=\"0 0 1 1\">\
+             <defs><g id=\"i\" /></defs><use xlink:href=\"#i\" /></svg>"
+        );
+        assert_eq!(
+            images[1].to_xml(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\"><g id=\"i\" /></svg>"
+        );
+    }
+
+    #[test]
+    fn copies_referenced_elements() {
+        let dom = parse_html(
+            "<svg><symbol id=icon><use href=#dot></use></symbol><circle id=dot></circle></svg>\
+             <svg><use xlink:href=\"#icon\"></use><use href=#missing></use></svg>",
+        );
+        let xml = svgs(&dom)[1].to_xml();
+        assert!(xml.ends_with(
+            "<defs><symbol id=\"icon\"><use href=\"#dot\" /></symbol><circle id=\"dot\" />\
+             </defs><use xlink:href=\"#icon\" /><use href=\"#missing\" /></svg>"
+        ));
+    }
+
+    #[test]
+    fn escapes_text_and_attributes() {
+        let dom = parse_html(
+            "<svg><title>a &lt; b &amp; \"c\"</title><text data-x='1 < 2 & \"3\"'>x</text></svg>",
+        );
+        assert!(svgs(&dom)[0].to_xml().ends_with(
+            "<title>a &lt; b &amp; \"c\"</title>\
+             <text data-x=\"1 &lt; 2 &amp; &quot;3&quot;\">x</text></svg>"
+        ));
+    }
+
+    #[test]
+    fn keeps_foreign_object_content() {
+        let dom = parse_html(
+            "<svg><foreignObject><div><p>hi<br>there</p><svg id=inner></svg></div>\
+             </foreignObject></svg>",
+        );
+        let images = svgs(&dom);
+        // The `<svg>` inside the `<foreignObject>` isn't an outermost SVG element.
+        assert_eq!(images.len(), 2);
+        assert!(images[0].to_xml().ends_with(
+            "<foreignObject><div xmlns=\"http://www.w3.org/1999/xhtml\"><p>hi<br />there</p>\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" id=\"inner\" /></div></foreignObject></svg>"
+        ));
+    }
+}
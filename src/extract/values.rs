@@ -0,0 +1,433 @@
+use crate::extract::FromText;
+
+/// How a [`Field`](crate::extract::Field) turns the text it selects into a typed value, set
+/// with [`Field::parse`](crate::extract::Field::parse).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueParser {
+    /// A number, as read by [`parse_number`].
+    Number,
+    /// A price, as read by [`parse_price`], which becomes an object with an `amount` and a
+    /// `currency` (`null` if there is no recognizable currency).
+    Price,
+    /// A percentage, as read by [`parse_percent`], which becomes a fraction.
+    Percent,
+    /// A date, as read by [`parse_date`], which becomes an ISO 8601 string.
+    Date,
+}
+
+impl ValueParser {
+    /// Parse `text`, or return `None` if it isn't a value of this kind.
+    #[cfg(feature = "serde")]
+    pub fn parse(&self, text: &str) -> Option<serde_json::Value> {
+        use serde_json::{json, Value};
+
+        match self {
+            ValueParser::Number => parse_number(text).map(Value::from),
+            ValueParser::Price => parse_price(text).map(|price| {
+                json!({
+                    "amount": price.amount,
+                    "currency": price.currency,
+                })
+            }),
+            ValueParser::Percent => parse_percent(text).map(Value::from),
+            ValueParser::Date => parse_date(text).map(Value::String),
+        }
+    }
+}
+
+/// A price read by [`parse_price`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Price {
+    /// The amount, in the currency's main unit.
+    pub amount: f64,
+    /// The ISO 4217 code of the currency, e.g. `EUR`, if the text names one.
+    pub currency: Option<String>,
+}
+
+impl FromText for Price {
+    fn from_text(text: &str) -> Option<Price> {
+        parse_price(text)
+    }
+}
+
+/// Read the first number in `text`, allowing for the grouping and decimal separators of
+/// different locales: `1,299.00`, `1.299,00`, `1 299,00` and `1'299.00` are all 1299.
+///
+/// When a number has both `,` and `.`, the last one is the decimal separator. A separator used
+/// more than once groups digits. A single `,` followed by exactly three digits is taken to
+/// group thousands, as in `1,299`, while a single `.` is always a decimal point. Text around the
+/// number, such as a currency or unit, is ignored.
+pub fn parse_number(text: &str) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let negative = text[..start].trim_end().ends_with(['-', '\u{2212}']);
+    let mut digits = String::new();
+    let mut chars = text[start..].chars();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == ',' || c == '.' {
+            digits.push(c);
+        } else if matches!(c, ' ' | '\'' | '’' | '\u{a0}' | '\u{202f}' | '\u{2009}') {
+            // A space or apostrophe only groups digits if exactly three follow.
+            let rest: String = chars.clone().take(4).collect();
+            let group = rest.chars().take_while(char::is_ascii_digit).count();
+            if group != 3 {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+    let digits = digits.trim_end_matches([',', '.']);
+
+    let last_comma = digits.rfind(',');
+    let last_dot = digits.rfind('.');
+    let decimal = match (last_comma, last_dot) {
+        (Some(comma), Some(dot)) => Some(comma.max(dot)),
+        (Some(comma), None) => {
+            let single = digits.matches(',').count() == 1;
+            (single && digits.len() - comma - 1 != 3).then_some(comma)
+        }
+        (None, Some(dot)) => (digits.matches('.').count() == 1).then_some(dot),
+        (None, None) => None,
+    };
+    let number: String = digits
+        .char_indices()
+        .filter_map(|(i, c)| match c {
+            ',' | '.' if Some(i) == decimal => Some('.'),
+            ',' | '.' => None,
+            c => Some(c),
+        })
+        .collect();
+    let number: f64 = number.parse().ok()?;
+    Some(if negative { -number } else { number })
+}
+
+/// Read a price such as `1,299.00 €`, `$12.50` or `CHF 40`, with the amount read as by
+/// [`parse_number`].
+///
+/// The currency is recognized from a symbol, or else from an ISO 4217 code right next to the
+/// amount, so words like `NEW` or `USB` aren't mistaken for codes. Symbols shared by several
+/// currencies mean the most common one, so `$` is US dollars unless it is written as `C$`, `A$`
+/// and so on.
+pub fn parse_price(text: &str) -> Option<Price> {
+    let amount = parse_number(text)?;
+    Some(Price {
+        amount,
+        currency: currency(text),
+    })
+}
+
+/// The currency named in `text`.
+fn currency(text: &str) -> Option<String> {
+    const SYMBOLS: &[(&str, &str)] = &[
+        ("US$", "USD"),
+        ("C$", "CAD"),
+        ("CA$", "CAD"),
+        ("A$", "AUD"),
+        ("AU$", "AUD"),
+        ("NZ$", "NZD"),
+        ("HK$", "HKD"),
+        ("S$", "SGD"),
+        ("R$", "BRL"),
+        ("MX$", "MXN"),
+        ("€", "EUR"),
+        ("£", "GBP"),
+        ("¥", "JPY"),
+        ("₹", "INR"),
+        ("₽", "RUB"),
+        ("₩", "KRW"),
+        ("₺", "TRY"),
+        ("₴", "UAH"),
+        ("₪", "ILS"),
+        ("₫", "VND"),
+        ("฿", "THB"),
+        ("zł", "PLN"),
+        ("Kč", "CZK"),
+        ("$", "USD"),
+    ];
+    if let Some((_, code)) = SYMBOLS.iter().find(|(symbol, _)| text.contains(symbol)) {
+        return Some(code.to_string());
+    }
+
+    // Otherwise a code written right before or after the number, as in `CHF 40` or `40 CHF`.
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let before = text[..start]
+        .trim_end_matches(|c: char| c.is_whitespace() || "-+\u{2212}".contains(c))
+        .rsplit(|c: char| !c.is_ascii_alphabetic())
+        .next();
+    let after = text[start..]
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c.is_whitespace() || ",.'’".contains(c))
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next();
+    [before, after]
+        .into_iter()
+        .flatten()
+        .find(|word| ISO_4217.contains(word))
+        .map(str::to_string)
+}
+
+/// Active ISO 4217 currency codes.
+const ISO_4217: &[&str] = &[
+    "AED", "AFN", "ALL", "AMD", "ANG", "AOA", "ARS", "AUD", "AWG", "AZN", "BAM", "BBD", "BDT",
+    "BGN", "BHD", "BIF", "BMD", "BND", "BOB", "BRL", "BSD", "BTN", "BWP", "BYN", "BZD", "CAD",
+    "CDF", "CHF", "CLP", "CNY", "COP", "CRC", "CUP", "CVE", "CZK", "DJF", "DKK", "DOP", "DZD",
+    "EGP", "ERN", "ETB", "EUR", "FJD", "FKP", "GBP", "GEL", "GHS", "GIP", "GMD", "GNF", "GTQ",
+    "GYD", "HKD", "HNL", "HTG", "HUF", "IDR", "ILS", "INR", "IQD", "IRR", "ISK", "JMD", "JOD",
+    "JPY", "KES", "KGS", "KHR", "KMF", "KPW", "KRW", "KWD", "KYD", "KZT", "LAK", "LBP", "LKR",
+    "LRD", "LSL", "LYD", "MAD", "MDL", "MGA", "MKD", "MMK", "MNT", "MOP", "MRU", "MUR", "MVR",
+    "MWK", "MXN", "MYR", "MZN", "NAD", "NGN", "NIO", "NOK", "NPR", "NZD", "OMR", "PAB", "PEN",
+    "PGK", "PHP", "PKR", "PLN", "PYG", "QAR", "RON", "RSD", "RUB", "RWF", "SAR", "SBD", "SCR",
+    "SDG", "SEK", "SGD", "SHP", "SLE", "SOS", "SRD", "SSP", "STN", "SVC", "SYP", "SZL", "THB",
+    "TJS", "TMT", "TND", "TOP", "TRY", "TTD", "TWD", "TZS", "UAH", "UGX", "USD", "UYU", "UZS",
+    "VES", "VND", "VUV", "WST", "XAF", "XCD", "XOF", "XPF", "YER", "ZAR", "ZMW", "ZWL",
+];
+
+/// Read a percentage such as `45%` or `12,5 %` as a fraction, e.g. 0.45, with the number read
+/// as by [`parse_number`]. Text without a `%` isn't a percentage.
+pub fn parse_percent(text: &str) -> Option<f64> {
+    if !text.contains(['%', '％']) {
+        return None;
+    }
+    parse_number(text).map(|number| number / 100.0)
+}
+
+/// Read a date and return it in ISO 8601 form, e.g. `2024-03-05`.
+///
+/// ISO 8601 dates (`2024-03-05`) are accepted, and date-times (`2024-03-05T10:00:00Z`) are
+/// returned unchanged once their date is checked. So are `2024/03/05`, `05.03.2024` (day
+/// first) and dates written out with English month names, such as `5 March 2024`,
+/// `March 5th, 2024` or `Tue, 5 Mar 2024 10:00`, where other words and times are ignored.
+/// Dates like `05/03/2024` are returned only if one of the numbers is over 12, as otherwise
+/// it can't be told whether the day or the month comes first.
+pub fn parse_date(text: &str) -> Option<String> {
+    let text = text.trim();
+    if let Some(date) = iso_date(text) {
+        return Some(date);
+    }
+    if let Some(date) = numeric_date(text) {
+        return Some(date);
+    }
+    written_date(text)
+}
+
+/// `text` if it starts with an ISO 8601 date, followed by nothing or a time.
+fn iso_date(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let year = text.get(..4)?.parse().ok()?;
+    let month = text.get(5..7)?.parse().ok()?;
+    let day = text.get(8..10)?.parse().ok()?;
+    let date = format_date(year, month, day)?;
+    match bytes.get(10) {
+        None => Some(date),
+        Some(b'T') | Some(b't') => Some(text.to_string()),
+        Some(b' ') if bytes.get(11).is_some_and(u8::is_ascii_digit) => {
+            Some(format!("{}T{}", date, &text[11..]))
+        }
+        _ => None,
+    }
+}
+
+/// The date in `text` if it is three numbers separated by `/`, `.` or `-`.
+fn numeric_date(text: &str) -> Option<String> {
+    let separator = text.chars().find(|c| matches!(c, '/' | '.' | '-'))?;
+    let parts: Vec<&str> = text.split(separator).map(str::trim).collect();
+    if parts.len() != 3
+        || parts
+            .iter()
+            .any(|part| part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+    let numbers: Vec<u32> = parts.iter().filter_map(|part| part.parse().ok()).collect();
+    if numbers.len() != 3 {
+        return None;
+    }
+    let (first, second, third) = (numbers[0], numbers[1], numbers[2]);
+    if parts[0].len() == 4 {
+        return format_date(first, second, third);
+    }
+    if parts[2].len() != 4 {
+        return None;
+    }
+    let day_first = match separator {
+        '.' => true,
+        _ if first > 12 => true,
+        _ if second > 12 => false,
+        _ if first == second => true,
+        _ => return None,
+    };
+    if day_first {
+        format_date(third, second, first)
+    } else {
+        format_date(third, first, second)
+    }
+}
+
+/// The date in `text` if it has an English month name, a day and a four-digit year.
+fn written_date(text: &str) -> Option<String> {
+    const MONTHS: [&str; 12] = [
+        "january",
+        "february",
+        "march",
+        "april",
+        "may",
+        "june",
+        "july",
+        "august",
+        "september",
+        "october",
+        "november",
+        "december",
+    ];
+    let mut month = None;
+    let mut day = None;
+    let mut year = None;
+    for word in text.split(|c: char| c.is_whitespace() || c == ',' || c == '/') {
+        let word = word.trim_end_matches('.').to_ascii_lowercase();
+        if word.contains(':') {
+            continue;
+        }
+        let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+        if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            let suffix = &word[digits.len()..];
+            if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+                continue;
+            }
+            match digits.len() {
+                1 | 2 if day.is_none() => {
+                    day = digits.parse().ok();
+                }
+                4 if year.is_none() && suffix.is_empty() => year = digits.parse().ok(),
+                _ => {}
+            }
+        } else if month.is_none() && word.len() >= 3 {
+            month = MONTHS
+                .iter()
+                .position(|name| {
+                    name.starts_with(word.as_str()) || (word == "sept" && *name == "september")
+                })
+                .map(|index| index as u32 + 1);
+        }
+    }
+    format_date(year?, month?, day?)
+}
+
+/// `year-month-day`, if that is a real date.
+fn format_date(year: u32, month: u32, day: u32) -> Option<String> {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    ((1..=9999).contains(&year) && (1..=days).contains(&day))
+        .then(|| format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_date, parse_number, parse_percent, parse_price, Price};
+
+    fn price(amount: f64, currency: Option<&str>) -> Option<Price> {
+        Some(Price {
+            amount,
+            currency: currency.map(str::to_string),
+        })
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(parse_number("1,299.00"), Some(1299.0));
+        assert_eq!(parse_number("1.299,00"), Some(1299.0));
+        assert_eq!(parse_number("1 299,50"), Some(1299.5));
+        assert_eq!(parse_number("1'299.00"), Some(1299.0));
+        assert_eq!(parse_number("1.234.567"), Some(1234567.0));
+        assert_eq!(parse_number("1,299"), Some(1299.0));
+        assert_eq!(parse_number("12,5"), Some(12.5));
+        assert_eq!(parse_number("3.5"), Some(3.5));
+        assert_eq!(parse_number("about -4 °C"), Some(-4.0));
+        assert_eq!(parse_number("12 items, 3 left"), Some(12.0));
+        assert_eq!(parse_number("none"), None);
+    }
+
+    #[test]
+    fn parses_prices() {
+        assert_eq!(parse_price("1,299.00 €"), price(1299.0, Some("EUR")));
+        assert_eq!(parse_price("$12.50"), price(12.5, Some("USD")));
+        assert_eq!(parse_price("C$ 5"), price(5.0, Some("CAD")));
+        assert_eq!(parse_price("CHF 40"), price(40.0, Some("CHF")));
+        assert_eq!(parse_price("40.00 GBP incl. VAT"), price(40.0, Some("GBP")));
+        assert_eq!(parse_price("12"), price(12.0, None));
+        assert_eq!(parse_price("free"), None);
+    }
+
+    #[test]
+    fn ignores_words_that_look_like_codes() {
+        assert_eq!(parse_price("USB cable 12,99 €"), price(12.99, Some("EUR")));
+        assert_eq!(parse_price("NEW! only $12.50"), price(12.5, Some("USD")));
+        assert_eq!(parse_price("NEW 12.50"), price(12.5, None));
+        assert_eq!(parse_price("USD in total: 12"), price(12.0, None));
+    }
+
+    #[test]
+    fn parses_percentages() {
+        assert_eq!(parse_percent("45%"), Some(0.45));
+        assert_eq!(parse_percent("12,5 %"), Some(0.125));
+        assert_eq!(parse_percent("45"), None);
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(parse_date("2024-03-05").as_deref(), Some("2024-03-05"));
+        assert_eq!(
+            parse_date("2024-03-05T10:00:00Z").as_deref(),
+            Some("2024-03-05T10:00:00Z")
+        );
+        assert_eq!(
+            parse_date("2024-03-05 10:00").as_deref(),
+            Some("2024-03-05T10:00")
+        );
+        assert_eq!(parse_date("2024/03/05").as_deref(), Some("2024-03-05"));
+        assert_eq!(parse_date("05.03.2024").as_deref(), Some("2024-03-05"));
+        assert_eq!(parse_date("25/03/2024").as_deref(), Some("2024-03-25"));
+        assert_eq!(parse_date("03/25/2024").as_deref(), Some("2024-03-25"));
+        assert_eq!(parse_date("05/03/2024"), None);
+        assert_eq!(parse_date("5 March 2024").as_deref(), Some("2024-03-05"));
+        assert_eq!(parse_date("March 5th, 2024").as_deref(), Some("2024-03-05"));
+        assert_eq!(
+            parse_date("Tue, 5 Mar 2024 10:00").as_deref(),
+            Some("2024-03-05")
+        );
+        assert_eq!(parse_date("29 Feb 2023"), None);
+        assert_eq!(parse_date("2024-13-01"), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn parses_values() {
+        use serde_json::json;
+
+        use super::ValueParser;
+
+        assert_eq!(ValueParser::Number.parse("1.5"), Some(json!(1.5)));
+        assert_eq!(
+            ValueParser::Price.parse("USB cable 12,99 €"),
+            Some(json!({"amount": 12.99, "currency": "EUR"}))
+        );
+        assert_eq!(
+            ValueParser::Price.parse("12"),
+            Some(json!({"amount": 12.0, "currency": null}))
+        );
+        assert_eq!(ValueParser::Percent.parse("50%"), Some(json!(0.5)));
+        assert_eq!(
+            ValueParser::Date.parse("5 March 2024"),
+            Some(json!("2024-03-05"))
+        );
+        assert_eq!(ValueParser::Date.parse("soon"), None);
+    }
+}
@@ -0,0 +1,364 @@
+//! Downloading and parsing pages. Enabled by the `http` feature.
+//!
+//! Requests are made with [reqwest](https://docs.rs/reqwest) over `http://` or `https://`, with
+//! TLS from rustls. Documents are decoded with [encoding_rs](https://docs.rs/encoding_rs), so
+//! any encoding of the WHATWG Encoding Standard is supported, and responses larger than 64 MiB
+//! are refused.
+
+use std::fmt;
+use std::io::{self, Read};
+use std::time::Duration;
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use reqwest::redirect::Policy;
+
+use crate::url::UrlParts;
+use crate::{GenerationalArenaDom, Parser};
+
+/// How many redirects are followed before giving up.
+const MAX_REDIRECTS: usize = 10;
+
+/// How long to wait for the server to connect or send data.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest response body that is read before giving up.
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// How many bytes at the start of a document are searched for a `<meta>` charset.
+const PRESCAN_BYTES: usize = 1024;
+
+/// The `User-Agent` requests are made with.
+const USER_AGENT: &str = "generational-arena-dom";
+
+/// The `Accept` header requests are made with.
+const ACCEPT: &str = "text/html,application/xhtml+xml,*/*;q=0.8";
+
+/// Why a page couldn't be fetched.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FetchError {
+    /// The URL couldn't be parsed.
+    InvalidUrl(String),
+    /// The URL's scheme isn't `http` or `https`.
+    UnsupportedScheme(String),
+    /// Connecting to the server or reading the response failed.
+    Io(io::Error),
+    /// The request failed, e.g. because the server couldn't be reached, the TLS handshake
+    /// failed or the response wasn't valid HTTP.
+    Http(reqwest::Error),
+    /// The server responded with an error status.
+    Status(u16),
+    /// More than 10 redirects were followed.
+    TooManyRedirects,
+    /// The response was larger than 64 MiB.
+    TooLarge,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::InvalidUrl(url) => write!(f, "invalid URL {:?}", url),
+            FetchError::UnsupportedScheme(scheme) => write!(f, "unsupported scheme {:?}", scheme),
+            FetchError::Io(err) => write!(f, "{}", err),
+            FetchError::Http(err) => write!(f, "{}", err),
+            FetchError::Status(status) => write!(f, "server responded with status {}", status),
+            FetchError::TooManyRedirects => f.write_str("too many redirects"),
+            FetchError::TooLarge => f.write_str("response too large"),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FetchError::Io(err) => Some(err),
+            FetchError::Http(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FetchError {
+    fn from(err: io::Error) -> FetchError {
+        FetchError::Io(err)
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> FetchError {
+        if err.is_redirect() {
+            FetchError::TooManyRedirects
+        } else if let Some(status) = err.status() {
+            FetchError::Status(status.as_u16())
+        } else {
+            FetchError::Http(err)
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Download the page at `url`, following redirects, and parse it with `parser`.
+    ///
+    /// The page is decoded using its byte order mark, the `charset` of its `Content-Type`
+    /// header or a `<meta>` charset declaration near its start, in that order, defaulting to
+    /// UTF-8. The DOM's [`url`](GenerationalArenaDom::url) is set to the final URL after any
+    /// redirects.
+    ///
+    /// This blocks the calling thread, and like reqwest's blocking client, panics if called
+    /// from within an async runtime; use [`fetch_async`](GenerationalArenaDom::fetch_async)
+    /// there instead.
+    pub fn fetch<P>(url: &str, parser: &P) -> Result<GenerationalArenaDom, FetchError>
+    where
+        P: Parser + ?Sized,
+    {
+        check_url(url)?;
+        let client = reqwest::blocking::Client::builder()
+            .user_agent(USER_AGENT)
+            .redirect(Policy::limited(MAX_REDIRECTS))
+            .timeout(TIMEOUT)
+            .build()?;
+        let response = client
+            .get(url.trim())
+            .header(reqwest::header::ACCEPT, ACCEPT)
+            .send()?
+            .error_for_status()?;
+        let url = response.url().to_string();
+        let charset = charset(response.headers());
+        let mut body = Vec::new();
+        response
+            .take(MAX_RESPONSE_BYTES + 1)
+            .read_to_end(&mut body)?;
+        if body.len() as u64 > MAX_RESPONSE_BYTES {
+            return Err(FetchError::TooLarge);
+        }
+        let html = decode(&body, charset.as_deref());
+        Ok(parse(parser, url, &html))
+    }
+
+    /// Like [`fetch`](GenerationalArenaDom::fetch), but doesn't block the calling task.
+    ///
+    /// The download runs on the caller's executor, which must be a Tokio runtime, as reqwest
+    /// requires. The page is parsed once it has been downloaded.
+    pub async fn fetch_async<P>(url: &str, parser: &P) -> Result<GenerationalArenaDom, FetchError>
+    where
+        P: Parser + ?Sized,
+    {
+        check_url(url)?;
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .redirect(Policy::limited(MAX_REDIRECTS))
+            .timeout(TIMEOUT)
+            .build()?;
+        let mut response = client
+            .get(url.trim())
+            .header(reqwest::header::ACCEPT, ACCEPT)
+            .send()
+            .await?
+            .error_for_status()?;
+        let url = response.url().to_string();
+        let charset = charset(response.headers());
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (body.len() + chunk.len()) as u64 > MAX_RESPONSE_BYTES {
+                return Err(FetchError::TooLarge);
+            }
+            body.extend_from_slice(&chunk);
+        }
+        let html = decode(&body, charset.as_deref());
+        Ok(parse(parser, url, &html))
+    }
+}
+
+fn parse<P>(parser: &P, url: String, html: &str) -> GenerationalArenaDom
+where
+    P: Parser + ?Sized,
+{
+    let mut dom = parser.parse(GenerationalArenaDom::default(), html);
+    dom.set_url(url);
+    dom
+}
+
+/// Check that `url` is an `http` or `https` URL with a host.
+fn check_url(url: &str) -> Result<(), FetchError> {
+    let parts = UrlParts::parse(url.trim());
+    match parts.scheme {
+        Some(scheme)
+            if ["http", "https"]
+                .iter()
+                .any(|ok| scheme.eq_ignore_ascii_case(ok)) => {}
+        Some(scheme) => return Err(FetchError::UnsupportedScheme(scheme.to_string())),
+        None => return Err(FetchError::InvalidUrl(url.to_string())),
+    }
+    match parts.host() {
+        Some(host) if !host.is_empty() => Ok(()),
+        _ => Err(FetchError::InvalidUrl(url.to_string())),
+    }
+}
+
+/// The `charset` parameter of a response's `Content-Type` header.
+fn charset(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    let content_type = headers.get(reqwest::header::CONTENT_TYPE)?.to_str().ok()?;
+    charset_param(content_type).map(str::to_string)
+}
+
+/// The `charset` parameter of a `Content-Type` value.
+fn charset_param(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(|c| c == '"' || c == '\''))
+    })
+}
+
+/// Decode a document, preferring its BOM, then `header_charset`, then a `<meta>` declaration,
+/// then UTF-8. As in the WHATWG sniffing algorithm, a label that names no known encoding is
+/// skipped rather than treated as an error.
+fn decode(bytes: &[u8], header_charset: Option<&str>) -> String {
+    if let Some((encoding, bom)) = Encoding::for_bom(bytes) {
+        return decode_with(encoding, &bytes[bom..]);
+    }
+    let header = header_charset.and_then(|label| Encoding::for_label(label.as_bytes()));
+    let encoding = header.or_else(|| {
+        let label = meta_charset(&bytes[..bytes.len().min(PRESCAN_BYTES)])?;
+        match Encoding::for_label(label.as_bytes())? {
+            // A document whose `<meta>` can be read as ASCII isn't UTF-16.
+            encoding if encoding == UTF_16BE || encoding == UTF_16LE => Some(UTF_8),
+            encoding => Some(encoding),
+        }
+    });
+    decode_with(encoding.unwrap_or(UTF_8), bytes)
+}
+
+fn decode_with(encoding: &'static Encoding, bytes: &[u8]) -> String {
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// Find a charset declared by `<meta charset>` or `<meta http-equiv="content-type">`.
+fn meta_charset(bytes: &[u8]) -> Option<String> {
+    let head = String::from_utf8_lossy(bytes).to_ascii_lowercase();
+    let mut rest = head.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag = &rest[start..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if let Some(position) = tag.find("charset=") {
+            let value = tag[position + "charset=".len()..].trim_start_matches(['"', '\'']);
+            let end = value
+                .find(|c: char| matches!(c, '"' | '\'' | ';' | '/') || c.is_ascii_whitespace())
+                .unwrap_or(value.len());
+            if end > 0 {
+                return Some(value[..end].to_string());
+            }
+        }
+        rest = &rest[start + 5..];
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use html5ever::tendril::TendrilSink;
+
+    use super::{decode, FetchError};
+    use crate::parse::body_html;
+    use crate::GenerationalArenaDom;
+
+    fn parse(sink: GenerationalArenaDom, html: &str) -> GenerationalArenaDom {
+        html5ever::parse_document(sink, Default::default()).one(html)
+    }
+
+    /// Start a server answering a connection each with the responses `responses` makes from
+    /// its URL, and return the URL.
+    fn serve(responses: impl FnOnce(&str) -> Vec<Vec<u8>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = responses(&url);
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let read = stream.read(&mut buffer).unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                }
+                stream.write_all(&response).unwrap();
+            }
+        });
+        url
+    }
+
+    /// A redirect to `/page`, which is in windows-1251.
+    fn redirect_to_page(url: &str) -> Vec<Vec<u8>> {
+        let page = b"<p>\xE4\xE0</p>";
+        let redirect = format!(
+            "HTTP/1.1 301 Moved Permanently\r\nLocation: {}/page\r\nContent-Length: 0\r\n\
+             Connection: close\r\n\r\n",
+            url
+        );
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=windows-1251\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n",
+            page.len()
+        );
+        vec![redirect.into_bytes(), [head.as_bytes(), page].concat()]
+    }
+
+    #[test]
+    fn decodes_by_bom_header_and_meta() {
+        assert_eq!(decode(b"\xFE\xFF\0h\0i", Some("latin1")), "hi");
+        assert_eq!(decode(b"caf\xE9", Some("ISO-8859-1")), "café");
+        assert_eq!(decode(b"\x83n\x83C", Some("shift_jis")), "ハイ");
+        let koi8 = b"<meta charset=\"koi8-r\"><p>\xE4\xC1";
+        assert_eq!(decode(koi8, None), "<meta charset=\"koi8-r\"><p>Да");
+        let utf16 = "<meta charset=utf-16><p>é";
+        assert_eq!(decode(utf16.as_bytes(), None), utf16);
+        // Unknown labels are skipped: the header's for the `<meta>`, the `<meta>`'s for UTF-8.
+        let bogus_meta = b"<meta charset=\"x-bogus\"><p>\xC3\xA9";
+        assert_eq!(
+            decode(bogus_meta, Some("x-bogus")),
+            "<meta charset=\"x-bogus\"><p>é"
+        );
+        assert_eq!(decode(koi8, Some("x-bogus")), decode(koi8, None));
+    }
+
+    #[test]
+    fn fetches_pages() {
+        let url = serve(redirect_to_page);
+        let dom = GenerationalArenaDom::fetch(&url, &parse).unwrap();
+        assert_eq!(dom.url(), Some(&*format!("{}/page", url)));
+        assert_eq!(body_html(&dom), "<p>да</p>");
+
+        let url = serve(|_| vec![b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_vec()]);
+        assert!(matches!(
+            GenerationalArenaDom::fetch(&url, &parse),
+            Err(FetchError::Status(404))
+        ));
+        assert!(matches!(
+            GenerationalArenaDom::fetch("ftp://example.com/", &parse),
+            Err(FetchError::UnsupportedScheme(_))
+        ));
+        assert!(matches!(
+            GenerationalArenaDom::fetch("https://", &parse),
+            Err(FetchError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn fetches_pages_asynchronously() {
+        let url = serve(redirect_to_page);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let dom = runtime
+            .block_on(GenerationalArenaDom::fetch_async(&url, &parse))
+            .unwrap();
+        assert_eq!(dom.url(), Some(&*format!("{}/page", url)));
+        assert_eq!(body_html(&dom), "<p>да</p>");
+    }
+}
@@ -0,0 +1,330 @@
+//! SVG and MathML content.
+
+use markup5ever::{namespace_url, ns, LocalName, Namespace};
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// SVG element names that aren't all lowercase, keyed by their lowercase form.
+///
+/// From the [adjust SVG tag names] step of the tree construction algorithm.
+///
+/// [adjust SVG tag names]: https://html.spec.whatwg.org/multipage/#parsing-main-inforeign
+const SVG_TAG_NAMES: &[(&str, &str)] = &[
+    ("altglyph", "altGlyph"),
+    ("altglyphdef", "altGlyphDef"),
+    ("altglyphitem", "altGlyphItem"),
+    ("animatecolor", "animateColor"),
+    ("animatemotion", "animateMotion"),
+    ("animatetransform", "animateTransform"),
+    ("clippath", "clipPath"),
+    ("feblend", "feBlend"),
+    ("fecolormatrix", "feColorMatrix"),
+    ("fecomponenttransfer", "feComponentTransfer"),
+    ("fecomposite", "feComposite"),
+    ("feconvolvematrix", "feConvolveMatrix"),
+    ("fediffuselighting", "feDiffuseLighting"),
+    ("fedisplacementmap", "feDisplacementMap"),
+    ("fedistantlight", "feDistantLight"),
+    ("fedropshadow", "feDropShadow"),
+    ("feflood", "feFlood"),
+    ("fefunca", "feFuncA"),
+    ("fefuncb", "feFuncB"),
+    ("fefuncg", "feFuncG"),
+    ("fefuncr", "feFuncR"),
+    ("fegaussianblur", "feGaussianBlur"),
+    ("feimage", "feImage"),
+    ("femerge", "feMerge"),
+    ("femergenode", "feMergeNode"),
+    ("femorphology", "feMorphology"),
+    ("feoffset", "feOffset"),
+    ("fepointlight", "fePointLight"),
+    ("fespecularlighting", "feSpecularLighting"),
+    ("fespotlight", "feSpotLight"),
+    ("fetile", "feTile"),
+    ("feturbulence", "feTurbulence"),
+    ("foreignobject", "foreignObject"),
+    ("glyphref", "glyphRef"),
+    ("lineargradient", "linearGradient"),
+    ("radialgradient", "radialGradient"),
+    ("textpath", "textPath"),
+];
+
+/// SVG attribute names that aren't all lowercase, keyed by their lowercase form.
+///
+/// From the [adjust SVG attributes] algorithm.
+///
+/// [adjust SVG attributes]: https://html.spec.whatwg.org/multipage/#adjust-svg-attributes
+const SVG_ATTR_NAMES: &[(&str, &str)] = &[
+    ("attributename", "attributeName"),
+    ("attributetype", "attributeType"),
+    ("basefrequency", "baseFrequency"),
+    ("baseprofile", "baseProfile"),
+    ("calcmode", "calcMode"),
+    ("clippathunits", "clipPathUnits"),
+    ("diffuseconstant", "diffuseConstant"),
+    ("edgemode", "edgeMode"),
+    ("filterunits", "filterUnits"),
+    ("glyphref", "glyphRef"),
+    ("gradienttransform", "gradientTransform"),
+    ("gradientunits", "gradientUnits"),
+    ("kernelmatrix", "kernelMatrix"),
+    ("kernelunitlength", "kernelUnitLength"),
+    ("keypoints", "keyPoints"),
+    ("keysplines", "keySplines"),
+    ("keytimes", "keyTimes"),
+    ("lengthadjust", "lengthAdjust"),
+    ("limitingconeangle", "limitingConeAngle"),
+    ("markerheight", "markerHeight"),
+    ("markerunits", "markerUnits"),
+    ("markerwidth", "markerWidth"),
+    ("maskcontentunits", "maskContentUnits"),
+    ("maskunits", "maskUnits"),
+    ("numoctaves", "numOctaves"),
+    ("pathlength", "pathLength"),
+    ("patterncontentunits", "patternContentUnits"),
+    ("patterntransform", "patternTransform"),
+    ("patternunits", "patternUnits"),
+    ("pointsatx", "pointsAtX"),
+    ("pointsaty", "pointsAtY"),
+    ("pointsatz", "pointsAtZ"),
+    ("preservealpha", "preserveAlpha"),
+    ("preserveaspectratio", "preserveAspectRatio"),
+    ("primitiveunits", "primitiveUnits"),
+    ("refx", "refX"),
+    ("refy", "refY"),
+    ("repeatcount", "repeatCount"),
+    ("repeatdur", "repeatDur"),
+    ("requiredextensions", "requiredExtensions"),
+    ("requiredfeatures", "requiredFeatures"),
+    ("specularconstant", "specularConstant"),
+    ("specularexponent", "specularExponent"),
+    ("spreadmethod", "spreadMethod"),
+    ("startoffset", "startOffset"),
+    ("stddeviation", "stdDeviation"),
+    ("stitchtiles", "stitchTiles"),
+    ("surfacescale", "surfaceScale"),
+    ("systemlanguage", "systemLanguage"),
+    ("tablevalues", "tableValues"),
+    ("targetx", "targetX"),
+    ("targety", "targetY"),
+    ("textlength", "textLength"),
+    ("viewbox", "viewBox"),
+    ("viewtarget", "viewTarget"),
+    ("xchannelselector", "xChannelSelector"),
+    ("ychannelselector", "yChannelSelector"),
+    ("zoomandpan", "zoomAndPan"),
+];
+
+/// MathML attribute names that aren't all lowercase, keyed by their lowercase form.
+const MATHML_ATTR_NAMES: &[(&str, &str)] = &[("definitionurl", "definitionURL")];
+
+fn lookup(table: &[(&str, &'static str)], name: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(lower, _)| lower.eq_ignore_ascii_case(name))
+        .map(|&(_, adjusted)| adjusted)
+}
+
+/// The correctly-cased form of the SVG element name `name`, if it differs from lowercase, as
+/// the HTML parser adjusts it, e.g. `foreignObject` for `foreignobject`. Matching ignores ASCII
+/// case.
+pub fn svg_tag_name(name: &str) -> Option<&'static str> {
+    lookup(SVG_TAG_NAMES, name)
+}
+
+/// The correctly-cased form of the SVG attribute name `name`, if it differs from lowercase, as
+/// the HTML parser adjusts it, e.g. `viewBox` for `viewbox`. Matching ignores ASCII case.
+pub fn svg_attr_name(name: &str) -> Option<&'static str> {
+    lookup(SVG_ATTR_NAMES, name)
+}
+
+/// The correctly-cased form of the MathML attribute name `name`, if it differs from lowercase,
+/// as the HTML parser adjusts it, i.e. `definitionURL` for `definitionurl`. Matching ignores
+/// ASCII case.
+pub fn mathml_attr_name(name: &str) -> Option<&'static str> {
+    lookup(MATHML_ATTR_NAMES, name)
+}
+
+impl GenerationalArenaDom {
+    /// Whether `handle` is an element in the SVG namespace.
+    pub fn is_svg(&self, handle: Handle) -> bool {
+        self.in_namespace(handle, &ns!(svg))
+    }
+
+    /// Whether `handle` is an element in the MathML namespace.
+    pub fn is_mathml(&self, handle: Handle) -> bool {
+        self.in_namespace(handle, &ns!(mathml))
+    }
+
+    /// The outermost SVG elements in the document, in document order: those whose parent isn't
+    /// an SVG element, which is usually an `<svg>` element embedded in HTML. SVG nested in
+    /// HTML inside a `<foreignObject>` starts a subtree of its own.
+    pub fn svg_roots(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.foreign_roots(ns!(svg))
+    }
+
+    /// The outermost MathML elements in the document, in document order, usually `<math>`
+    /// elements embedded in HTML, as for [`svg_roots`](GenerationalArenaDom::svg_roots).
+    pub fn mathml_roots(&self) -> impl Iterator<Item = Handle> + '_ {
+        self.foreign_roots(ns!(mathml))
+    }
+
+    fn in_namespace(&self, handle: Handle, namespace: &Namespace) -> bool {
+        match self.arena.get(handle).map(|node| node.get()) {
+            Some(NodeData::Element { name, .. }) => name.ns == *namespace,
+            _ => false,
+        }
+    }
+
+    fn foreign_roots(&self, namespace: Namespace) -> impl Iterator<Item = Handle> + '_ {
+        self.document.descendants(&self.arena).filter(move |&node| {
+            self.in_namespace(node, &namespace)
+                && !self.arena[node]
+                    .parent()
+                    .is_some_and(|parent| self.in_namespace(parent, &namespace))
+        })
+    }
+
+    /// Restore the case of SVG and MathML element and attribute names under `root` that were
+    /// lowercased, e.g. `foreignobject` to `foreignObject` and `viewbox` to `viewBox`.
+    ///
+    /// html5ever already adjusts names while parsing foreign content, so this is only needed for
+    /// trees built by other means, such as by hand or by a case-folding pipeline. The serializer
+    /// writes names exactly as they are stored, so adjusted names round-trip. Frozen elements
+    /// keep their names.
+    pub fn restore_foreign_case(&mut self, root: Handle) {
+        let nodes: Vec<Handle> = root.descendants(&self.arena).collect();
+        for node in nodes {
+            if self.is_frozen(node) {
+                if self.has_foreign_case(node) {
+                    self.skip_frozen(node);
+                }
+                continue;
+            }
+            if let NodeData::Element { name, attrs, .. } = self.arena[node].get_mut() {
+                let attr_table = if name.ns == ns!(svg) {
+                    if let Some(adjusted) = svg_tag_name(&name.local) {
+                        name.local = LocalName::from(adjusted);
+                    }
+                    svg_attr_name
+                } else if name.ns == ns!(mathml) {
+                    mathml_attr_name
+                } else {
+                    continue;
+                };
+                for attr in attrs.get_mut().iter_mut() {
+                    if attr.name.ns == ns!() {
+                        if let Some(adjusted) = attr_table(&attr.name.local) {
+                            attr.name.local = LocalName::from(adjusted);
+                        }
+                    }
+                }
+            }
+        }
+        self.mark_mutated();
+    }
+
+    /// Whether [`restore_foreign_case`](GenerationalArenaDom::restore_foreign_case) would
+    /// change the name of `node` or of one of its attributes.
+    fn has_foreign_case(&self, node: Handle) -> bool {
+        let (name, attrs) = match self.get_node(&node) {
+            NodeData::Element { name, attrs, .. } => (name, attrs),
+            _ => return false,
+        };
+        let attr_table = if name.ns == ns!(svg) {
+            if svg_tag_name(&name.local).is_some_and(|adjusted| adjusted != &*name.local) {
+                return true;
+            }
+            svg_attr_name
+        } else if name.ns == ns!(mathml) {
+            mathml_attr_name
+        } else {
+            return false;
+        };
+        attrs.borrow().iter().any(|attr| {
+            attr.name.ns == ns!()
+                && attr_table(&attr.name.local)
+                    .is_some_and(|adjusted| adjusted != &*attr.name.local)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::LocalName;
+
+    use super::{mathml_attr_name, svg_attr_name, svg_tag_name};
+    use crate::parse::{body_html, parse_html};
+    use crate::{GenerationalArenaDom, NodeData};
+
+    const HTML: &str = "<svg viewBox=\"0 0 1 1\"><foreignObject><p x=\"1\"></p></foreignObject>\
+                        <linearGradient gradientUnits=\"x\"></linearGradient></svg>\
+                        <math><mi definitionURL=\"y\"></mi></math>";
+
+    /// Lowercase every element and attribute name in `dom`, as a case-folding tool would.
+    fn lowercase_names(dom: &mut GenerationalArenaDom) {
+        let nodes: Vec<_> = dom.document.descendants(&dom.arena).collect();
+        for node in nodes {
+            if let NodeData::Element { name, attrs, .. } = dom.arena[node].get_mut() {
+                name.local = LocalName::from(name.local.to_ascii_lowercase());
+                for attr in attrs.get_mut() {
+                    attr.name.local = LocalName::from(attr.name.local.to_ascii_lowercase());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn restores_foreign_case() {
+        let mut dom = parse_html(HTML);
+        assert_eq!(body_html(&dom), HTML);
+        lowercase_names(&mut dom);
+        assert!(body_html(&dom).contains("<foreignobject>"));
+        dom.restore_foreign_case(dom.document);
+        assert_eq!(body_html(&dom), HTML);
+    }
+
+    #[test]
+    fn frozen_elements_keep_their_names() {
+        let mut dom = parse_html(HTML);
+        lowercase_names(&mut dom);
+        let math = dom.query_selector(dom.document, "math").unwrap().unwrap();
+        dom.freeze(math);
+        dom.restore_foreign_case(dom.document);
+        assert!(body_html(&dom).contains("<linearGradient gradientUnits=\"x\">"));
+        assert!(body_html(&dom).contains("<mi definitionurl=\"y\">"));
+    }
+
+    #[test]
+    fn adjusts_names() {
+        assert_eq!(svg_tag_name("FOREIGNOBJECT"), Some("foreignObject"));
+        assert_eq!(svg_tag_name("circle"), None);
+        assert_eq!(svg_attr_name("viewbox"), Some("viewBox"));
+        assert_eq!(svg_attr_name("definitionurl"), None);
+        assert_eq!(mathml_attr_name("DefinitionURL"), Some("definitionURL"));
+    }
+
+    #[test]
+    fn finds_foreign_roots() {
+        let dom = parse_html(
+            "<svg id=a><g><foreignObject><div><svg id=b></svg></div></foreignObject></g></svg>\
+             <math id=c><mi>x</mi></math><svg id=d></svg>",
+        );
+        let ids = |roots: Vec<_>| -> Vec<String> {
+            roots
+                .into_iter()
+                .map(|root| dom.get_attr(root, "id").unwrap_or_default().to_string())
+                .collect()
+        };
+        assert_eq!(ids(dom.svg_roots().collect()), ["a", "b", "d"]);
+        assert_eq!(ids(dom.mathml_roots().collect()), ["c"]);
+
+        let g = dom.query_selector(dom.document, "g").unwrap().unwrap();
+        let mi = dom.query_selector(dom.document, "mi").unwrap().unwrap();
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        assert!(dom.is_svg(g) && !dom.is_mathml(g));
+        assert!(dom.is_mathml(mi) && !dom.is_svg(mi));
+        assert!(!dom.is_svg(div));
+        assert!(!dom.is_svg(dom.arena[mi].first_child().unwrap()));
+    }
+}
@@ -0,0 +1,105 @@
+//! Form-associated elements.
+
+use crate::{GenerationalArenaDom, Handle};
+
+/// HTML elements that can belong to a form and honor its `form` attribute.
+const LISTED_ELEMENTS: &[&str] = &[
+    "button", "fieldset", "input", "object", "output", "select", "textarea",
+];
+
+impl GenerationalArenaDom {
+    /// The [form owner] of the form-associated element `handle`.
+    ///
+    /// In order of precedence, this is the form named by the element's `form` attribute, the
+    /// form the parser associated it with, or its nearest ancestor `<form>`. The parser
+    /// association matters for markup like `<table><form><tr><td><input>`, where the form is
+    /// closed straight away and the input ends up outside it. It is kept as long as the form is
+    /// still in the same tree as the element.
+    ///
+    /// [form owner]: https://html.spec.whatwg.org/multipage/#form-owner
+    pub fn form_owner(&self, handle: Handle) -> Option<Handle> {
+        let is_listed = LISTED_ELEMENTS
+            .iter()
+            .any(|name| self.is_html_element(handle, name));
+        if !is_listed && !self.is_html_element(handle, "img") {
+            return None;
+        }
+        if is_listed {
+            if let Some(id) = self.get_attr(handle, "form") {
+                let root = self.tree_root(handle);
+                return root
+                    .descendants(&self.arena)
+                    .find(|&node| self.get_attr(node, "id").as_deref() == Some(&*id))
+                    .filter(|&node| self.is_html_element(node, "form"));
+            }
+        }
+        if let Some(&form) = self.form_owners.get(&handle) {
+            if self.arena.get(form).is_some() && self.tree_root(form) == self.tree_root(handle) {
+                return Some(form);
+            }
+        }
+        handle
+            .ancestors(&self.arena)
+            .skip(1)
+            .find(|&ancestor| self.is_html_element(ancestor, "form"))
+    }
+
+    /// The form-associated elements owned by `form`, in tree order.
+    pub fn form_elements(&self, form: Handle) -> Vec<Handle> {
+        self.tree_root(form)
+            .descendants(&self.arena)
+            .filter(|&node| node != form && self.form_owner(node) == Some(form))
+            .collect()
+    }
+
+    /// The root of the tree `handle` is in, e.g. the document if it is attached.
+    fn tree_root(&self, handle: Handle) -> Handle {
+        handle.ancestors(&self.arena).last().unwrap_or(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_html;
+
+    #[test]
+    fn finds_form_owners() {
+        let dom = parse_html(
+            "<form id=f><input id=inner><p><img id=picture></p></form>\
+             <table><form id=g><tr><td><input id=misnested></td></tr></form></table>\
+             <input id=linked form=f><input id=broken form=missing><input id=loose><p id=other>",
+        );
+        let find = |id: &str| {
+            dom.query_selector(dom.document, &format!("#{}", id))
+                .unwrap()
+                .unwrap()
+        };
+        let (f, g) = (find("f"), find("g"));
+        assert_eq!(dom.form_owner(find("inner")), Some(f));
+        assert_eq!(dom.form_owner(find("picture")), Some(f));
+        assert_eq!(dom.form_owner(find("misnested")), Some(g));
+        assert!(dom.arena[g].first_child().is_none());
+        assert_eq!(dom.form_owner(find("linked")), Some(f));
+        assert_eq!(dom.form_owner(find("broken")), None);
+        assert_eq!(dom.form_owner(find("loose")), None);
+        assert_eq!(dom.form_owner(find("other")), None);
+
+        assert_eq!(
+            dom.form_elements(f),
+            [find("inner"), find("picture"), find("linked")]
+        );
+        assert_eq!(dom.form_elements(g), [find("misnested")]);
+    }
+
+    #[test]
+    fn forgets_forms_in_other_trees() {
+        let mut dom = parse_html("<table><form id=g><tr><td><input></td></tr></form></table>");
+        let g = dom.query_selector(dom.document, "form").unwrap().unwrap();
+        let input = dom.query_selector(dom.document, "input").unwrap().unwrap();
+        assert_eq!(dom.form_owner(input), Some(g));
+        g.detach(&mut dom.arena);
+        assert_eq!(dom.form_owner(input), None);
+        g.remove_subtree(&mut dom.arena);
+        assert_eq!(dom.form_owner(input), None);
+    }
+}
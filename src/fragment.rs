@@ -0,0 +1,298 @@
+//! Detached pieces of a document that can be moved around as values.
+
+use markup5ever::QualName;
+
+use crate::{ExtractMode, FragmentParser, GenerationalArenaDom, Handle, MutationError, NodeData};
+
+/// Where to insert nodes relative to an existing node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertPosition {
+    /// As the last children of the given node.
+    AppendTo(Handle),
+    /// As the first children of the given node.
+    PrependTo(Handle),
+    /// As siblings immediately before the given node.
+    Before(Handle),
+    /// As siblings immediately after the given node.
+    After(Handle),
+}
+
+/// An owned sequence of sibling subtrees that are out of any document.
+///
+/// Unlike a detached handle, which leaves its nodes in the arena where nothing can reach them,
+/// a fragment owns its nodes and frees them when dropped. It can be inserted into any DOM,
+/// including the one it was detached from.
+pub struct Fragment {
+    dom: GenerationalArenaDom,
+}
+
+impl Fragment {
+    /// Parse `input` as the contents of a `context` element.
+    ///
+    /// Parsers put the parsed nodes under an `<html>` element, as html5ever does; they become
+    /// the roots of the fragment. For a `template` context that is still where they end up, and
+    /// inserting them into a template's contents is up to the caller, as
+    /// [`set_inner_html`](GenerationalArenaDom::set_inner_html) does.
+    pub fn parse<P>(context: &QualName, input: &str, parser: &P) -> Fragment
+    where
+        P: FragmentParser + ?Sized,
+    {
+        let mut dom = parser.parse_fragment(GenerationalArenaDom::default(), context, input);
+        if let Some(root) = dom.document_element() {
+            let children: Vec<Handle> = root.children(&dom.arena).collect();
+            for child in children {
+                root.insert_before(child, &mut dom.arena);
+            }
+            dom.free_subtree(root);
+        }
+        Fragment { dom }
+    }
+
+    /// The DOM holding the fragment's nodes. Its document node is the fragment's parent.
+    pub fn dom(&self) -> &GenerationalArenaDom {
+        &self.dom
+    }
+
+    /// The top-level nodes of the fragment, in order.
+    pub fn roots(&self) -> Vec<Handle> {
+        self.dom.document.children(&self.dom.arena).collect()
+    }
+
+    /// Whether the fragment has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.roots().is_empty()
+    }
+}
+
+impl From<GenerationalArenaDom> for Fragment {
+    /// Treat the children of a DOM's document node as a fragment.
+    fn from(dom: GenerationalArenaDom) -> Fragment {
+        Fragment { dom }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Remove `handle` and its descendants from this DOM, returning them as a [`Fragment`].
+    /// Fails if any of them is [frozen](GenerationalArenaDom::freeze).
+    pub fn detach_fragment(&mut self, handle: Handle) -> Result<Fragment, MutationError> {
+        Ok(Fragment {
+            dom: self.extract_to_document(handle, ExtractMode::Move)?,
+        })
+    }
+
+    /// Copy `handle` and its descendants into a [`Fragment`], leaving this DOM unchanged.
+    pub fn copy_fragment(&self, handle: Handle) -> Fragment {
+        Fragment {
+            dom: self.copy_to_document(handle),
+        }
+    }
+
+    /// Insert the nodes of `fragment` at `position`, returning their handles in this DOM.
+    /// Fails if the parent they would be inserted into is frozen.
+    pub fn insert_fragment(
+        &mut self,
+        fragment: Fragment,
+        position: InsertPosition,
+    ) -> Result<Vec<Handle>, MutationError> {
+        self.check_insertable(position)?;
+        let roots: Vec<Handle> = fragment
+            .roots()
+            .into_iter()
+            .map(|root| self.import_subtree(&fragment.dom, root))
+            .collect();
+        self.insert_at(&roots, position);
+        Ok(roots)
+    }
+
+    /// Replace the children of the element `handle` with `input` parsed as a fragment in its
+    /// context, returning the new children.
+    ///
+    /// For a `<template>`, the template contents are replaced instead, as in the browser. Fails
+    /// if the element or any of its current children is frozen.
+    pub fn set_inner_html<P>(
+        &mut self,
+        handle: Handle,
+        input: &str,
+        parser: &P,
+    ) -> Result<Vec<Handle>, MutationError>
+    where
+        P: FragmentParser + ?Sized,
+    {
+        let (context, contents) = match self.get_node(&handle) {
+            NodeData::Element {
+                name,
+                template_contents,
+                ..
+            } => (name.clone(), *template_contents.borrow()),
+            _ => panic!("not an element!"),
+        };
+        let fragment = Fragment::parse(&context, input, parser);
+        self.replace_children(contents.unwrap_or(handle), fragment)
+    }
+
+    /// Replace the children of `parent` with the nodes of `fragment`, returning their handles.
+    /// Fails if `parent` or any of its current children is frozen.
+    pub(crate) fn replace_children(
+        &mut self,
+        parent: Handle,
+        fragment: Fragment,
+    ) -> Result<Vec<Handle>, MutationError> {
+        self.check_mutable(parent)?;
+        let old: Vec<Handle> = parent.children(&self.arena).collect();
+        for &child in &old {
+            self.check_removable(child)?;
+        }
+        for child in old {
+            self.free_subtree(child);
+        }
+        self.insert_fragment(fragment, InsertPosition::AppendTo(parent))
+    }
+
+    /// Insert detached `nodes` at `position`, keeping them in order.
+    pub(crate) fn insert_at(&mut self, nodes: &[Handle], position: InsertPosition) {
+        self.mark_mutated();
+        match position {
+            InsertPosition::AppendTo(parent) => {
+                for &node in nodes {
+                    parent.append(node, &mut self.arena);
+                }
+            }
+            InsertPosition::PrependTo(parent) => {
+                for &node in nodes.iter().rev() {
+                    parent.prepend(node, &mut self.arena);
+                }
+            }
+            InsertPosition::Before(sibling) => {
+                for &node in nodes {
+                    sibling.insert_before(node, &mut self.arena);
+                }
+            }
+            InsertPosition::After(sibling) => {
+                for &node in nodes.iter().rev() {
+                    sibling.insert_after(node, &mut self.arena);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::{local_name, namespace_url, ns, QualName};
+
+    use super::{Fragment, InsertPosition};
+    use crate::parse::{body_html, parse_fragment_html, parse_html};
+    use crate::MutationError;
+
+    #[test]
+    fn moves_and_copies_fragments_between_documents() {
+        let mut source = parse_html("<ul><li>a</li><li>b</li></ul>");
+        let mut target = parse_html("<p>x</p><p>y</p>");
+        let ul = source
+            .query_selector(source.document, "ul")
+            .unwrap()
+            .unwrap();
+        let x = target
+            .query_selector(target.document, "p")
+            .unwrap()
+            .unwrap();
+
+        let copy = source.copy_fragment(ul);
+        assert_eq!(copy.roots().len(), 1);
+        let inserted = target
+            .insert_fragment(copy, InsertPosition::After(x))
+            .unwrap();
+        assert_eq!(
+            body_html(&target),
+            "<p>x</p><ul><li>a</li><li>b</li></ul><p>y</p>"
+        );
+        assert_eq!(target.arena[x].next_sibling(), Some(inserted[0]));
+        assert_eq!(body_html(&source), "<ul><li>a</li><li>b</li></ul>");
+
+        let moved = source.detach_fragment(ul).unwrap();
+        assert!(!source.is_alive(ul));
+        assert_eq!(body_html(&source), "");
+        target
+            .insert_fragment(moved, InsertPosition::PrependTo(x))
+            .unwrap();
+        assert!(body_html(&target).starts_with("<p><ul><li>a</li><li>b</li></ul>x</p>"));
+    }
+
+    #[test]
+    fn parses_fragments_in_context() {
+        let context = QualName::new(None, ns!(html), local_name!("tr"));
+        let fragment = Fragment::parse(&context, "<td>1</td><td>2</td>", &parse_fragment_html);
+        assert_eq!(fragment.roots().len(), 2);
+        assert!(!fragment.is_empty());
+
+        let mut dom = parse_html("<table><tr id=row></tr></table>");
+        let row = dom.query_selector(dom.document, "#row").unwrap().unwrap();
+        dom.insert_fragment(fragment, InsertPosition::AppendTo(row))
+            .unwrap();
+        assert_eq!(dom.inner_html(row), "<td>1</td><td>2</td>");
+
+        let empty = Fragment::parse(&context, "", &parse_fragment_html);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn refuses_frozen_parents() {
+        let mut dom = parse_html("<div></div>");
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        dom.freeze(div);
+        let other = parse_html("<p>new</p>");
+        let p = other.query_selector(other.document, "p").unwrap().unwrap();
+        let fragment = other.copy_fragment(p);
+        assert_eq!(
+            dom.insert_fragment(fragment, InsertPosition::AppendTo(div)),
+            Err(MutationError::Frozen { node: div })
+        );
+        assert_eq!(dom.inner_html(div), "");
+    }
+
+    #[test]
+    fn copies_frozen_nodes_through_a_shared_borrow() {
+        let mut source = parse_html("<ul><li>a</li></ul>");
+        let ul = source
+            .query_selector(source.document, "ul")
+            .unwrap()
+            .unwrap();
+        source.freeze(ul);
+        let version = source.version();
+        let shared = &source;
+        let copy = shared.copy_fragment(ul);
+        assert_eq!(source.version(), version);
+
+        let mut target = parse_html("");
+        let body = target
+            .query_selector(target.document, "body")
+            .unwrap()
+            .unwrap();
+        target
+            .insert_fragment(copy, InsertPosition::AppendTo(body))
+            .unwrap();
+        assert_eq!(body_html(&target), "<ul><li>a</li></ul>");
+    }
+
+    #[test]
+    fn parses_into_template_contents() {
+        let context = QualName::new(None, ns!(html), local_name!("template"));
+        let fragment = Fragment::parse(&context, "<tr><td>x</td></tr>", &parse_fragment_html);
+        let roots = fragment.roots();
+        assert_eq!(roots.len(), 1);
+        assert!(fragment.dom().is_html_element(roots[0], "tr"));
+
+        let mut dom = parse_html("<template id=t><p>old</p></template>");
+        let template = dom.query_selector(dom.document, "#t").unwrap().unwrap();
+        let contents = dom.template_contents_of(template).unwrap();
+        let inserted = dom
+            .set_inner_html(template, "<td>1</td><td>2</td>", &parse_fragment_html)
+            .unwrap();
+        assert_eq!(inserted.len(), 2);
+        assert!(dom.is_html_element(inserted[0], "td"));
+        assert!(dom.is_html_element(inserted[1], "td"));
+        assert_eq!(dom.arena[inserted[0]].parent(), Some(contents));
+        assert!(dom.arena[template].first_child().is_none());
+        assert_eq!(dom.query_selector(dom.document, "p").unwrap(), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Marking subtrees as not to be changed.
+
+use crate::{GenerationalArenaDom, Handle, InsertPosition, MutationError, NodeData};
+
+impl GenerationalArenaDom {
+    /// Mark `root` and its descendants, including the contents of templates among them, as
+    /// frozen. This protects a region that an earlier stage of a pipeline has finished with,
+    /// e.g. sanitized or signed content, from later stages.
+    ///
+    /// Mutations that target a node, such as
+    /// [`remove_subtree`](GenerationalArenaDom::remove_subtree) or those made through a
+    /// [`SubtreeMut`](crate::SubtreeMut), fail with [`MutationError::Frozen`] if it is frozen.
+    /// Mutations that work through a whole subtree, such as the transforms,
+    /// [`replace_text`](GenerationalArenaDom::replace_text) or
+    /// [`remove_all`](GenerationalArenaDom::remove_all), leave frozen nodes as they are and
+    /// carry on; [`take_frozen_skips`](GenerationalArenaDom::take_frozen_skips) tells which
+    /// they skipped. Whatever is under `root` when a mutation is checked counts as frozen,
+    /// including nodes added later. Only the `TreeSink` implementation ignores freezing.
+    pub fn freeze(&mut self, root: Handle) {
+        self.frozen.insert(root);
+    }
+
+    /// Undo a [`freeze`](GenerationalArenaDom::freeze) of `root`. Nodes under it stay frozen if
+    /// they or another ancestor were frozen separately.
+    pub fn unfreeze(&mut self, root: Handle) {
+        self.frozen.remove(&root);
+    }
+
+    /// Whether `handle` is frozen, because it or one of its ancestors was
+    /// [frozen](GenerationalArenaDom::freeze), or it is in the contents of a frozen template.
+    pub fn is_frozen(&self, handle: Handle) -> bool {
+        if self.frozen.is_empty() || !self.is_alive(handle) {
+            return false;
+        }
+        let mut node = handle;
+        loop {
+            let mut top = node;
+            for ancestor in node.ancestors(&self.arena) {
+                if self.frozen.contains(&ancestor) {
+                    return true;
+                }
+                top = ancestor;
+            }
+            match self.template_owner(top) {
+                Some(template) => node = template,
+                None => return false,
+            }
+        }
+    }
+
+    /// The frozen nodes that mutations of whole subtrees left alone since the last call, in the
+    /// order they were first skipped.
+    pub fn take_frozen_skips(&mut self) -> Vec<Handle> {
+        self.frozen_skips.take()
+    }
+
+    /// Check that `handle` may be changed, failing with [`MutationError::Frozen`] if it is
+    /// frozen. Code that mutates the DOM directly can call this to respect freezing.
+    pub fn check_mutable(&self, handle: Handle) -> Result<(), MutationError> {
+        if self.is_frozen(handle) {
+            Err(MutationError::Frozen { node: handle })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check that `handle` may be removed along with its descendants, none of which may be
+    /// frozen.
+    pub(crate) fn check_removable(&self, handle: Handle) -> Result<(), MutationError> {
+        self.check_mutable(handle)?;
+        if self.frozen.is_empty() {
+            return Ok(());
+        }
+        let mut roots = vec![handle];
+        while let Some(root) = roots.pop() {
+            for node in root.descendants(&self.arena) {
+                if self.frozen.contains(&node) {
+                    return Err(MutationError::Frozen { node });
+                }
+                if let NodeData::Element {
+                    template_contents, ..
+                } = self.get_node(&node)
+                {
+                    roots.extend(*template_contents.borrow());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that nodes may be inserted at `position`, whose parent may not be frozen.
+    pub(crate) fn check_insertable(&self, position: InsertPosition) -> Result<(), MutationError> {
+        let parent = match position {
+            InsertPosition::AppendTo(parent) | InsertPosition::PrependTo(parent) => Some(parent),
+            InsertPosition::Before(sibling) | InsertPosition::After(sibling) => {
+                self.arena[sibling].parent()
+            }
+        };
+        match parent {
+            Some(parent) => self.check_mutable(parent),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether a mutation of a whole subtree has to leave `handle` as it is because it is
+    /// frozen, recording it as skipped if so.
+    pub(crate) fn skip_frozen(&self, handle: Handle) -> bool {
+        self.skip_if_err(self.check_mutable(handle))
+    }
+
+    /// Like [`skip_frozen`](GenerationalArenaDom::skip_frozen), for removing `handle` along
+    /// with its descendants.
+    pub(crate) fn skip_frozen_removal(&self, handle: Handle) -> bool {
+        self.skip_if_err(self.check_removable(handle))
+    }
+
+    /// Like [`skip_frozen`](GenerationalArenaDom::skip_frozen), for inserting nodes at
+    /// `position`.
+    pub(crate) fn skip_frozen_insertion(&self, position: InsertPosition) -> bool {
+        self.skip_if_err(self.check_insertable(position))
+    }
+
+    fn skip_if_err(&self, check: Result<(), MutationError>) -> bool {
+        match check {
+            Ok(()) => false,
+            Err(MutationError::Frozen { node }) | Err(MutationError::OutsideSubtree { node }) => {
+                let mut skips = self.frozen_skips.borrow_mut();
+                if !skips.contains(&node) {
+                    skips.push(node);
+                }
+                true
+            }
+        }
+    }
+
+    /// The `<template>` whose contents `handle` is, if it is template contents created by the
+    /// tree builder or this crate.
+    pub(crate) fn template_owner(&self, handle: Handle) -> Option<Handle> {
+        let template = *self.template_owners.get(&handle)?;
+        (self.template_contents_of(template) == Some(handle)).then_some(template)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GenerationalArenaDom, Handle, InsertPosition, MutationError, Selector};
+
+    fn select(dom: &GenerationalArenaDom, selector: &str) -> Vec<Handle> {
+        dom.select(dom.document, &Selector::parse(selector).unwrap())
+    }
+
+    #[test]
+    fn targeted_mutations_fail_and_bulk_ones_skip() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("div>p{one}+p{two}").unwrap();
+        let (first, second) = match select(&dom, "p")[..] {
+            [first, second] => (first, second),
+            _ => panic!("expected two paragraphs"),
+        };
+        dom.freeze(first);
+        let div = select(&dom, "div")[0];
+        assert_eq!(
+            dom.remove_subtree(div),
+            Err(MutationError::Frozen { node: first })
+        );
+        assert!(dom.set_bool_attr(first, "hidden", true).is_err());
+        assert!(dom.detach_fragment(first).is_err());
+
+        assert_eq!(dom.set_attr_all(dom.document, "p", "class", "x"), Ok(1));
+        assert_eq!(dom.remove_all(dom.document, "p"), Ok(1));
+        assert!(!dom.is_alive(second));
+        assert_eq!(dom.get_attr(first, "class"), None);
+        assert_eq!(dom.take_frozen_skips(), vec![first]);
+        assert!(dom.take_frozen_skips().is_empty());
+    }
+
+    #[test]
+    fn template_contents_are_frozen_with_their_template() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("template").unwrap();
+        let template = select(&dom, "template")[0];
+        let contents = dom
+            .as_element(template)
+            .unwrap()
+            .template_contents()
+            .unwrap();
+        let text = dom
+            .expand_abbreviation("{inside}", InsertPosition::AppendTo(contents))
+            .unwrap()[0];
+        assert!(!dom.is_frozen(text));
+        dom.freeze(template);
+        assert!(dom.is_frozen(contents));
+        assert!(dom.is_frozen(text));
+        assert!(dom
+            .expand_abbreviation("p", InsertPosition::AppendTo(contents))
+            .is_err());
+        assert!(dom.remove_subtree(template).is_err());
+    }
+
+    #[test]
+    fn cloned_and_parsed_template_contents_know_their_template() {
+        let mut dom = crate::parse::parse_html("<template><p>x</p></template>");
+        let template = select(&dom, "template")[0];
+        let contents = dom.template_contents_of(template).unwrap();
+        assert_eq!(dom.template_owner(contents), Some(template));
+
+        let copy = dom.clone_subtree(template);
+        let copy_contents = dom.template_contents_of(copy).unwrap();
+        assert_eq!(dom.template_owner(copy_contents), Some(copy));
+        let paragraph = dom.arena[copy_contents].first_child().unwrap();
+        dom.freeze(copy);
+        assert!(dom.is_frozen(paragraph));
+        assert!(!dom.is_frozen(dom.arena[contents].first_child().unwrap()));
+
+        dom.unfreeze(copy);
+        dom.remove_subtree(copy).unwrap();
+        assert_eq!(dom.template_owner(copy_contents), None);
+        assert_eq!(dom.template_owner(dom.document), None);
+    }
+}
@@ -0,0 +1,234 @@
+//! Callbacks fired while a DOM is being built.
+
+use std::fmt;
+use std::mem;
+
+use markup5ever::interface::tree_builder::NodeOrText;
+
+use crate::{GenerationalArenaDom, Handle};
+
+type Callback = Box<dyn FnMut(&GenerationalArenaDom, Handle)>;
+
+/// A callback invoked whenever the parser closes an element, i.e. pops it off the stack of open
+/// elements.
+///
+/// By then the element and all its descendants have been parsed (unless the parser later moves
+/// misnested content into it), so it can be processed while the rest of the document is still
+/// being parsed, e.g. to emit a record for each `<article>` as soon as it is complete. The
+/// callback sees the DOM built so far, and is not invoked after parsing was aborted.
+///
+/// html5ever doesn't report every element it closes, so an element closed by its end tag may
+/// only be seen to be closed once the parser inserts the next node outside it, or at the end of
+/// the input. Either way, an element is reported after the elements inside it, and only once.
+pub struct ElementCloseHook {
+    callback: Callback,
+}
+
+impl ElementCloseHook {
+    /// Invoke `callback` with every element the parser closes.
+    pub fn new(callback: impl FnMut(&GenerationalArenaDom, Handle) + 'static) -> ElementCloseHook {
+        ElementCloseHook {
+            callback: Box::new(callback),
+        }
+    }
+
+    pub(crate) fn call(&mut self, dom: &GenerationalArenaDom, element: Handle) {
+        (self.callback)(dom, element);
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Note that the tree builder created `node`, so inserting it can be told apart from moving
+    /// a node that was already in the tree.
+    pub(crate) fn note_created(&mut self, node: Handle) {
+        if self.opts.on_element_close.is_some() {
+            self.unplaced.insert(node);
+        }
+    }
+
+    /// Note that the tree builder inserted `child` into `parent`.
+    ///
+    /// The DOM keeps its own stack of the elements the tree builder has open, since html5ever
+    /// only passes some of the elements it closes to `TreeSink::pop`. A new node inserted into
+    /// one of them closes the elements above it, unless it was foster parented, as the table it
+    /// was inserted before is still open.
+    pub(crate) fn note_inserted(
+        &mut self,
+        parent: Handle,
+        child: &NodeOrText<Handle>,
+        foster_parented: bool,
+    ) {
+        if self.opts.on_element_close.is_none() {
+            return;
+        }
+        let element = match child {
+            NodeOrText::AppendText(_) => None,
+            NodeOrText::AppendNode(node) => {
+                if !self.unplaced.remove(node) {
+                    return;
+                }
+                Some(*node).filter(|&node| self.element_name(node).is_some())
+            }
+        };
+        if !foster_parented {
+            let position = self.open_elements.iter().rposition(|&open| {
+                open == parent || self.template_contents_of(open) == Some(parent)
+            });
+            if let Some(position) = position {
+                for closed in self.open_elements.split_off(position + 1).into_iter().rev() {
+                    self.close_element(closed);
+                }
+            }
+        }
+        self.open_elements.extend(element);
+    }
+
+    /// Note that the tree builder popped `element`, which also closes the elements inside it that
+    /// it closed without saying so.
+    pub(crate) fn note_popped(&mut self, element: Handle) {
+        if self.opts.on_element_close.is_none() {
+            return;
+        }
+        let position = match self.open_elements.iter().rposition(|&open| open == element) {
+            Some(position) => position,
+            // Already closed.
+            None => return,
+        };
+        let stack = self.open_elements.split_off(position);
+        let (closed, open): (Vec<Handle>, Vec<Handle>) = stack[1..]
+            .iter()
+            .partition(|&&node| self.is_inside(node, element, &stack));
+        self.open_elements.extend(open);
+        for closed in closed.into_iter().rev() {
+            self.close_element(closed);
+        }
+        self.close_element(element);
+    }
+
+    /// Note that the tree builder finished, which closes every element still open.
+    pub(crate) fn note_finished(&mut self) {
+        self.unplaced.clear();
+        let open = mem::take(&mut self.open_elements);
+        if self.abort.is_none() {
+            for element in open.into_iter().rev() {
+                self.close_element(element);
+            }
+        }
+    }
+
+    /// Whether `node` is inside `element`, looking through the contents of the templates in
+    /// `open`.
+    fn is_inside(&self, node: Handle, element: Handle, open: &[Handle]) -> bool {
+        let mut current = Some(node);
+        while let Some(handle) = current {
+            if handle == element {
+                return true;
+            }
+            current = self.arena[handle].parent().or_else(|| {
+                open.iter()
+                    .copied()
+                    .find(|&template| self.template_contents_of(template) == Some(handle))
+            });
+        }
+        false
+    }
+
+    fn close_element(&mut self, element: Handle) {
+        if let Some(mut hook) = self.opts.on_element_close.take() {
+            hook.call(self, element);
+            self.opts.on_element_close = Some(hook);
+        }
+    }
+}
+
+impl fmt::Debug for ElementCloseHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ElementCloseHook").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::ElementCloseHook;
+    use crate::parse::parse_html_with;
+    use crate::DomOptions;
+
+    #[test]
+    fn fires_when_elements_close() {
+        let records = Rc::new(RefCell::new(Vec::new()));
+        let sink = records.clone();
+        let hook = ElementCloseHook::new(move |dom, element| {
+            if dom.is_html_element(element, "article") {
+                sink.borrow_mut().push(dom.text_content(element));
+            }
+        });
+        parse_html_with(
+            "<article>one <b>1</b></article><article>two<p>2</article><div>skip</div>",
+            DomOptions {
+                on_element_close: Some(hook),
+                ..DomOptions::default()
+            },
+        );
+        assert_eq!(*records.borrow(), ["one 1", "two2"]);
+    }
+
+    fn closed(html: &str) -> Vec<String> {
+        let names = Rc::new(RefCell::new(Vec::new()));
+        let sink = names.clone();
+        let hook = ElementCloseHook::new(move |dom, element| {
+            let name = dom.element_name(element).unwrap().local.to_string();
+            sink.borrow_mut().push(name);
+        });
+        parse_html_with(
+            html,
+            DomOptions {
+                on_element_close: Some(hook),
+                ..DomOptions::default()
+            },
+        );
+        Rc::try_unwrap(names).unwrap().into_inner()
+    }
+
+    #[test]
+    fn reports_elements_once_after_their_contents() {
+        assert_eq!(
+            closed("<ul><li>a<li>b</ul><p>c"),
+            ["head", "li", "li", "ul", "p", "body", "html"]
+        );
+        assert_eq!(
+            closed("<table><tr><td>x</td></tr>y<div>z</div></table><br>"),
+            ["head", "td", "tr", "tbody", "div", "table", "br", "body", "html"]
+        );
+        assert_eq!(
+            closed("<template><p>a</p><i>b</i></template><hr>"),
+            ["p", "i", "template", "head", "hr", "body", "html"]
+        );
+        assert_eq!(
+            closed("<b>1<p>2</b>3</p>"),
+            ["head", "b", "b", "p", "body", "html"]
+        );
+        assert_eq!(
+            closed("<div><span>x"),
+            ["head", "span", "div", "body", "html"]
+        );
+    }
+
+    #[test]
+    fn stops_firing_after_an_abort() {
+        let closed = Rc::new(RefCell::new(0));
+        let count = closed.clone();
+        let dom = parse_html_with(
+            &"<p>x</p>".repeat(20),
+            DomOptions {
+                max_nodes: Some(10),
+                on_element_close: Some(ElementCloseHook::new(move |_, _| *count.borrow_mut() += 1)),
+                ..DomOptions::default()
+            },
+        );
+        assert!(dom.abort_error().is_some());
+        assert!(*closed.borrow() < 5);
+    }
+}
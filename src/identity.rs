@@ -0,0 +1,228 @@
+//! Correlating the nodes of two versions of the same document.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Above this many child pairs, children are aligned greedily instead of with a full LCS.
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// Pairs of corresponding nodes in an old and a new version of a document.
+///
+/// Built by [`match_nodes`]. Annotations stored against handles of the old tree can be migrated
+/// to the new tree by looking up each handle's counterpart.
+#[derive(Clone, Debug, Default)]
+pub struct NodeMatching {
+    old_to_new: HashMap<Handle, Handle>,
+    new_to_old: HashMap<Handle, Handle>,
+}
+
+impl NodeMatching {
+    /// The node in the new document corresponding to `old`.
+    pub fn new_for(&self, old: Handle) -> Option<Handle> {
+        self.old_to_new.get(&old).copied()
+    }
+
+    /// The node in the old document corresponding to `new`.
+    pub fn old_for(&self, new: Handle) -> Option<Handle> {
+        self.new_to_old.get(&new).copied()
+    }
+
+    /// All matched `(old, new)` pairs, in no particular order.
+    pub fn pairs(&self) -> impl Iterator<Item = (Handle, Handle)> + '_ {
+        self.old_to_new.iter().map(|(&old, &new)| (old, new))
+    }
+
+    /// The number of matched pairs.
+    pub fn len(&self) -> usize {
+        self.old_to_new.len()
+    }
+
+    /// Whether no nodes were matched.
+    pub fn is_empty(&self) -> bool {
+        self.old_to_new.is_empty()
+    }
+
+    fn insert(&mut self, old: Handle, new: Handle) {
+        self.old_to_new.insert(old, new);
+        self.new_to_old.insert(new, old);
+    }
+}
+
+/// Find corresponding nodes in `old` and `new`, two parses of (roughly) the same document.
+///
+/// Children of matched nodes are aligned in order by their kind, element name and `id`, so
+/// nodes keep their identity when their text or attributes change or when siblings are added
+/// or removed around them. Subtrees that are left unmatched are then paired with identical
+/// unmatched subtrees elsewhere in the other document, which catches content that moved.
+pub fn match_nodes(old: &GenerationalArenaDom, new: &GenerationalArenaDom) -> NodeMatching {
+    let mut matching = NodeMatching::default();
+    match_subtrees(old, new, old.document, new.document, &mut matching);
+
+    let mut old_by_hash: HashMap<u64, Vec<Handle>> = HashMap::new();
+    for node in old.document.descendants(&old.arena) {
+        if matching.new_for(node).is_none() {
+            old_by_hash
+                .entry(subtree_hash(old, node))
+                .or_default()
+                .push(node);
+        }
+    }
+    let unmatched_new: Vec<Handle> = new
+        .document
+        .descendants(&new.arena)
+        .filter(|&node| matching.old_for(node).is_none())
+        .collect();
+    for node in unmatched_new {
+        if matching.old_for(node).is_some() {
+            continue;
+        }
+        if let Some(candidates) = old_by_hash.get_mut(&subtree_hash(new, node)) {
+            candidates.retain(|&candidate| matching.new_for(candidate).is_none());
+            if let Some(candidate) = candidates.pop() {
+                match_subtrees(old, new, candidate, node, &mut matching);
+            }
+        }
+    }
+    matching
+}
+
+fn match_subtrees(
+    old: &GenerationalArenaDom,
+    new: &GenerationalArenaDom,
+    old_node: Handle,
+    new_node: Handle,
+    matching: &mut NodeMatching,
+) {
+    matching.insert(old_node, new_node);
+    let old_children: Vec<Handle> = old_node.children(&old.arena).collect();
+    let new_children: Vec<Handle> = new_node.children(&new.arena).collect();
+    let old_keys: Vec<String> = old_children.iter().map(|&c| key(old, c)).collect();
+    let new_keys: Vec<String> = new_children.iter().map(|&c| key(new, c)).collect();
+    for (i, j) in align(&old_keys, &new_keys) {
+        match_subtrees(old, new, old_children[i], new_children[j], matching);
+    }
+}
+
+/// Pairs of indices of equal keys, in order.
+fn align(old: &[String], new: &[String]) -> Vec<(usize, usize)> {
+    if old.len() * new.len() > MAX_LCS_CELLS {
+        let mut pairs = vec![];
+        let mut j = 0;
+        for (i, key) in old.iter().enumerate() {
+            if let Some(offset) = new[j..].iter().position(|k| k == key) {
+                pairs.push((i, j + offset));
+                j += offset + 1;
+            }
+        }
+        return pairs;
+    }
+
+    // lengths[i][j] is the LCS length of old[i..] and new[j..].
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut pairs = vec![];
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// What has to agree for two siblings to be considered the same node.
+fn key(dom: &GenerationalArenaDom, handle: Handle) -> String {
+    match dom.get_node(&handle) {
+        NodeData::Document => "#document".to_string(),
+        NodeData::Doctype { .. } => "#doctype".to_string(),
+        NodeData::Text { .. } => "#text".to_string(),
+        NodeData::Comment { .. } => "#comment".to_string(),
+        NodeData::ProcessingInstruction { target, .. } => format!("?{}", target),
+        NodeData::Element { name, attrs, .. } => {
+            let attrs = attrs.borrow();
+            let id = attrs.iter().find(|attr| &*attr.name.local == "id");
+            match id {
+                Some(id) => format!("{}:{}#{}", name.ns, name.local, id.value),
+                None => format!("{}:{}", name.ns, name.local),
+            }
+        }
+    }
+}
+
+/// A hash of the structure and content of the subtree rooted at `handle`.
+fn subtree_hash(dom: &GenerationalArenaDom, handle: Handle) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for node in handle.descendants(&dom.arena) {
+        match dom.get_node(&node) {
+            NodeData::Text { contents } => contents.borrow().hash(&mut hasher),
+            NodeData::Comment { contents } => contents.hash(&mut hasher),
+            NodeData::Element { attrs, .. } => {
+                for attr in attrs.borrow().iter() {
+                    attr.name.hash(&mut hasher);
+                    attr.value.hash(&mut hasher);
+                }
+            }
+            _ => {}
+        }
+        key(dom, node).hash(&mut hasher);
+        node.children(&dom.arena).count().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::match_nodes;
+    use crate::parse::parse_html;
+    use crate::{GenerationalArenaDom, Handle};
+
+    fn find(dom: &GenerationalArenaDom, selector: &str) -> Handle {
+        dom.query_selector(dom.document, selector).unwrap().unwrap()
+    }
+
+    #[test]
+    fn matches_edited_and_moved_nodes() {
+        let old = parse_html(
+            "<h1>Title</h1><p id=intro>Hello</p><ul><li>a</li><li>b</li></ul>\
+             <aside><em>moved</em></aside>",
+        );
+        let new = parse_html(
+            "<h1>New title</h1><p>Inserted</p><p id=intro class=x>Hello!</p>\
+             <ul><li>b</li></ul><footer><em>moved</em></footer>",
+        );
+        let matching = match_nodes(&old, &new);
+        assert_eq!(matching.new_for(old.document), Some(new.document));
+        assert_eq!(matching.new_for(find(&old, "h1")), Some(find(&new, "h1")));
+        // The `id` outweighs the inserted paragraph before it.
+        assert_eq!(
+            matching.new_for(find(&old, "#intro")),
+            Some(find(&new, "#intro"))
+        );
+        assert_eq!(matching.old_for(find(&new, "p")), None);
+        // The subtree moved from the aside to the footer.
+        assert_eq!(matching.new_for(find(&old, "em")), Some(find(&new, "em")));
+        assert_eq!(matching.old_for(find(&new, "footer")), None);
+        assert!(matching.pairs().all(|(old_node, new_node)| {
+            matching.new_for(old_node) == Some(new_node)
+                && matching.old_for(new_node) == Some(old_node)
+        }));
+        assert!(!matching.is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+//! Generalizing example elements into selectors.
+
+use crate::{GenerationalArenaDom, Handle, Selector};
+
+/// How many ancestors of each example are considered for context.
+const MAX_CONTEXT_DEPTH: usize = 4;
+
+/// How many selectors [`GenerationalArenaDom::induce_selectors`] proposes at most.
+const MAX_PROPOSALS: usize = 10;
+
+/// A selector proposed by [`GenerationalArenaDom::induce_selectors`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InducedSelector {
+    /// The selector.
+    pub selector: String,
+    /// The elements it matches in the document, in document order, which include all the
+    /// examples.
+    pub matches: Vec<Handle>,
+}
+
+impl GenerationalArenaDom {
+    /// Propose selectors that match all of `examples`, elements marked as wanted, and
+    /// generalize them to similar elements in the document, for building scrapers.
+    ///
+    /// Candidates are made from what the examples have in common: their tag, classes and a few
+    /// identifying attributes, optionally qualified by something all of them have among their
+    /// nearest ancestors, as a parent (`ul.results > li`) or further up (`#main li`). Each
+    /// candidate is run with the query engine, and those that miss an example are dropped. Of
+    /// candidates matching the same elements, only the shortest is kept. The rest are returned
+    /// from the fewest matches to the most, so the first is the tightest fit and later ones
+    /// generalize further; callers should review what each one matches.
+    ///
+    /// Returns nothing if `examples` is empty or holds something other than elements in the
+    /// document.
+    pub fn induce_selectors(&self, examples: &[Handle]) -> Vec<InducedSelector> {
+        if examples.is_empty() {
+            return Vec::new();
+        }
+        let mut own: Option<Vec<String>> = None;
+        for &example in examples {
+            let candidates = match self.selector_candidates(example) {
+                Some(candidates) if self.path_of(example).is_some() => candidates,
+                _ => return Vec::new(),
+            };
+            own = Some(match own {
+                Some(common) => common
+                    .into_iter()
+                    .filter(|candidate| candidates.contains(candidate))
+                    .collect(),
+                None => candidates,
+            });
+        }
+        let own = own.unwrap_or_default();
+
+        // Ancestor selectors shared by all examples, and whether each is always the parent.
+        let mut context: Vec<(String, bool)> = Vec::new();
+        for (i, &example) in examples.iter().enumerate() {
+            let ancestors: Vec<Vec<String>> = example
+                .ancestors(&self.arena)
+                .skip(1)
+                .filter(|&ancestor| self.element_name(ancestor).is_some())
+                .take(MAX_CONTEXT_DEPTH)
+                .map(|ancestor| self.selector_candidates(ancestor).unwrap_or_default())
+                .collect();
+            if i == 0 {
+                for (depth, candidates) in ancestors.iter().enumerate() {
+                    for candidate in candidates {
+                        if !context.iter().any(|(other, _)| other == candidate) {
+                            context.push((candidate.clone(), depth == 0));
+                        }
+                    }
+                }
+            } else {
+                context.retain_mut(|(candidate, parent)| {
+                    *parent &= ancestors
+                        .first()
+                        .is_some_and(|candidates| candidates.contains(candidate));
+                    ancestors
+                        .iter()
+                        .any(|candidates| candidates.contains(candidate))
+                });
+            }
+        }
+
+        let mut candidates: Vec<String> = own.clone();
+        for (outer, parent) in &context {
+            for inner in &own {
+                if *parent {
+                    candidates.push(format!("{} > {}", outer, inner));
+                }
+                candidates.push(format!("{} {}", outer, inner));
+            }
+        }
+        candidates.sort_by_key(String::len);
+        candidates.dedup();
+
+        let mut proposals: Vec<InducedSelector> = Vec::new();
+        for candidate in candidates {
+            let selector = match Selector::parse(&candidate) {
+                Ok(selector) => selector,
+                Err(_) => continue,
+            };
+            let matches = self.select(self.document, &selector);
+            let covers = examples.iter().all(|example| matches.contains(example));
+            // Candidates are shortest first, so an earlier one with the same matches wins.
+            if covers && !proposals.iter().any(|proposal| proposal.matches == matches) {
+                proposals.push(InducedSelector {
+                    selector: candidate,
+                    matches,
+                });
+            }
+        }
+        proposals.sort_by_key(|proposal| proposal.matches.len());
+        proposals.truncate(MAX_PROPOSALS);
+        proposals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_html;
+
+    #[test]
+    fn generalizes_examples() {
+        let dom = parse_html(
+            "<div id=main><ul class=results><li class=item>a</li><li class=item>b</li>\
+             <li class=item>c</li></ul></div><ul><li class=item>x</li></ul><li>y</li>",
+        );
+        let items: Vec<_> = dom
+            .query_selector_all(dom.document, ".results li")
+            .unwrap()
+            .collect();
+        let proposals = dom.induce_selectors(&items[..2]);
+        let found: Vec<(&str, usize)> = proposals
+            .iter()
+            .map(|proposal| (proposal.selector.as_str(), proposal.matches.len()))
+            .collect();
+        assert_eq!(found, [("div li", 3), (".item", 4), ("li", 5)]);
+        assert_eq!(proposals[0].matches, items);
+    }
+
+    #[test]
+    fn needs_elements_in_the_document() {
+        let mut dom = parse_html("<p>a</p>");
+        assert!(dom.induce_selectors(&[]).is_empty());
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let text = dom.arena[p].first_child().unwrap();
+        assert!(dom.induce_selectors(&[text]).is_empty());
+        p.detach(&mut dom.arena);
+        assert!(dom.induce_selectors(&[p]).is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+//! Interactive, editable and focusable elements.
+
+use markup5ever::{namespace_url, ns};
+
+use crate::{GenerationalArenaDom, Handle};
+
+/// Form controls that can be disabled with the `disabled` attribute or a disabled fieldset.
+const DISABLEABLE_ELEMENTS: &[&str] = &[
+    "button", "fieldset", "input", "optgroup", "option", "select", "textarea",
+];
+
+impl GenerationalArenaDom {
+    /// Whether `handle` is [interactive content], i.e. an element meant for user interaction
+    /// such as a link, form control or media player with controls.
+    ///
+    /// [interactive content]: https://html.spec.whatwg.org/multipage/#interactive-content
+    pub fn is_interactive(&self, handle: Handle) -> bool {
+        let has = |name| self.get_attr(handle, name).is_some();
+        let is = |name| self.is_html_element(handle, name);
+        (is("a") && has("href"))
+            || ((is("audio") || is("video")) && has("controls"))
+            || ((is("img") || is("object")) && has("usemap"))
+            || (is("input") && !self.is_hidden_input(handle))
+            || [
+                "button", "details", "embed", "iframe", "label", "select", "textarea",
+            ]
+            .iter()
+            .any(|name| is(name))
+    }
+
+    /// Whether `handle` is an [editing host]: an element made editable by its own
+    /// `contenteditable` attribute, rather than by inheriting editability from its parent.
+    ///
+    /// [editing host]: https://html.spec.whatwg.org/multipage/#editing-host
+    pub fn is_editable_host(&self, handle: Handle) -> bool {
+        self.content_editable(handle) == Some(true)
+            && handle
+                .ancestors(&self.arena)
+                .nth(1)
+                .is_none_or(|parent| !self.is_editable(parent))
+    }
+
+    /// Whether the contents of `handle` can be edited by the user, because it is or is inside
+    /// an element whose `contenteditable` state is true or plaintext-only.
+    ///
+    /// The nearest ancestor (or `handle` itself) with a valid `contenteditable` value decides:
+    /// `contenteditable="false"` makes a region inside an editing host read-only again.
+    pub fn is_editable(&self, handle: Handle) -> bool {
+        handle
+            .ancestors(&self.arena)
+            .find_map(|node| self.content_editable(node))
+            .unwrap_or(false)
+    }
+
+    /// Whether `handle` can be focused, by clicking or with the keyboard.
+    ///
+    /// This follows the [focusable areas] the spec suggests, without layout: links with an
+    /// `href`, enabled form controls, the summary of a `<details>`, embedded documents, media
+    /// with controls, editing hosts, and any element with a valid `tabindex`. Elements that are
+    /// disabled, `inert`, `hidden` or inside a `<template>` can't be focused.
+    ///
+    /// [focusable areas]: https://html.spec.whatwg.org/multipage/#focusable-area
+    pub fn is_focusable(&self, handle: Handle) -> bool {
+        let Some(name) = self.element_name(handle) else {
+            return false;
+        };
+        let out_of_reach = handle.ancestors(&self.arena).any(|node| {
+            self.is_html_element(node, "template")
+                || self.template_owner(node).is_some()
+                || self.has_bool_attr(node, "inert")
+                || self.has_bool_attr(node, "hidden")
+        });
+        if out_of_reach || self.is_disabled(handle) {
+            return false;
+        }
+        if self.tab_index(handle).is_some() || self.is_editable_host(handle) {
+            return true;
+        }
+        let has = |attr| self.get_attr(handle, attr).is_some();
+        match &*name.local {
+            _ if name.ns != ns!(html) => false,
+            "a" | "area" => has("href"),
+            "button" | "select" | "textarea" | "iframe" | "embed" | "object" => true,
+            "input" => !self.is_hidden_input(handle),
+            "audio" | "video" => has("controls"),
+            "summary" => {
+                handle
+                    .ancestors(&self.arena)
+                    .nth(1)
+                    .filter(|&parent| self.is_html_element(parent, "details"))
+                    .and_then(|parent| self.html_child(parent, "summary"))
+                    == Some(handle)
+            }
+            _ => false,
+        }
+    }
+
+    /// The element's `tabindex`, if it has one that is a valid integer.
+    pub fn tab_index(&self, handle: Handle) -> Option<i64> {
+        parse_integer(&self.get_attr(handle, "tabindex")?)
+    }
+
+    /// Whether `handle` is [actually disabled]: a form control with the `disabled` attribute,
+    /// or inside a disabled `<fieldset>` other than in its first `<legend>`.
+    ///
+    /// [actually disabled]: https://html.spec.whatwg.org/multipage/#concept-element-disabled
+    fn is_disabled(&self, handle: Handle) -> bool {
+        let disableable = DISABLEABLE_ELEMENTS
+            .iter()
+            .any(|name| self.is_html_element(handle, name));
+        if !disableable {
+            return false;
+        }
+        if self.has_bool_attr(handle, "disabled") {
+            return true;
+        }
+        let mut child = handle;
+        for ancestor in handle.ancestors(&self.arena).skip(1) {
+            if self.is_html_element(ancestor, "fieldset")
+                && self.has_bool_attr(ancestor, "disabled")
+            {
+                let in_legend = self.html_child(ancestor, "legend") == Some(child);
+                if !in_legend {
+                    return true;
+                }
+            }
+            child = ancestor;
+        }
+        false
+    }
+
+    fn is_hidden_input(&self, handle: Handle) -> bool {
+        self.get_attr(handle, "type")
+            .is_some_and(|ty| ty.eq_ignore_ascii_case("hidden"))
+    }
+
+    /// The state of the `contenteditable` attribute of `handle`: `Some(true)` for true or
+    /// plaintext-only, `Some(false)` for false, and `None` when it is missing or invalid and the
+    /// state is inherited.
+    fn content_editable(&self, handle: Handle) -> Option<bool> {
+        let value = self.get_attr(handle, "contenteditable")?;
+        if value.is_empty()
+            || value.eq_ignore_ascii_case("true")
+            || value.eq_ignore_ascii_case("plaintext-only")
+        {
+            Some(true)
+        } else if value.eq_ignore_ascii_case("false") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parse `value` with the HTML [rules for parsing integers]: optional leading whitespace and
+/// sign, then digits, ignoring anything after them.
+///
+/// [rules for parsing integers]: https://html.spec.whatwg.org/multipage/#rules-for-parsing-integers
+pub(crate) fn parse_integer(value: &str) -> Option<i64> {
+    let value = value.trim_start_matches(['\t', '\n', '\u{c}', '\r', ' ']);
+    let (negative, digits) = match value.as_bytes().first()? {
+        b'-' => (true, &value[1..]),
+        b'+' => (false, &value[1..]),
+        _ => (false, value),
+    };
+    let end = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let number: i64 = digits[..end].parse().ok()?;
+    Some(if negative { -number } else { number })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_integer;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn finds_interactive_elements() {
+        let dom = parse_html(
+            "<a id=link href=/>x</a><a id=anchor>x</a><video id=player controls></video>\
+             <video id=plain></video><img id=map usemap=#m><input id=text>\
+             <input id=hidden type=HIDDEN><label id=label>x</label><p id=para>x</p>",
+        );
+        let interactive = |id: &str| {
+            let handle = dom
+                .query_selector(dom.document, &format!("#{}", id))
+                .unwrap()
+                .unwrap();
+            dom.is_interactive(handle)
+        };
+        for id in ["link", "player", "map", "text", "label"] {
+            assert!(interactive(id), "{}", id);
+        }
+        for id in ["anchor", "plain", "hidden", "para"] {
+            assert!(!interactive(id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn tracks_editable_regions() {
+        let dom = parse_html(
+            "<div id=host contenteditable><p id=inside>x<span id=locked contenteditable=false>\
+             <b id=deep>y</b></span></p><i id=nested contenteditable=PLAINTEXT-ONLY>z</i></div>\
+             <p id=bogus contenteditable=maybe>w</p>",
+        );
+        let find = |id: &str| {
+            dom.query_selector(dom.document, &format!("#{}", id))
+                .unwrap()
+                .unwrap()
+        };
+        assert!(dom.is_editable_host(find("host")));
+        assert!(dom.is_editable(find("inside")));
+        assert!(!dom.is_editable_host(find("inside")));
+        assert!(!dom.is_editable(find("deep")));
+        assert!(dom.is_editable(find("nested")));
+        assert!(!dom.is_editable_host(find("nested")));
+        assert!(!dom.is_editable(find("bogus")));
+    }
+
+    #[test]
+    fn finds_focusable_elements() {
+        let dom = parse_html(
+            "<a id=link href=/>x</a><a id=anchor>x</a><span id=tabbable tabindex=' -1'>x</span>\
+             <span id=badtab tabindex=x>x</span><button id=button></button>\
+             <button id=disabled disabled></button>\
+             <fieldset disabled><legend><input id=legend></legend><input id=fieldset></fieldset>\
+             <details><summary id=summary>s</summary><summary id=second>t</summary></details>\
+             <div inert><button id=inert></button></div><div id=host contenteditable></div>\
+             <template><button id=templated></button></template>",
+        );
+        let focusable = |id: &str| {
+            let handle = dom
+                .select(dom.document, &format!("#{}", id).parse().unwrap())
+                .first()
+                .copied()
+                .or_else(|| {
+                    let template = dom.query_selector(dom.document, "template").unwrap()?;
+                    let contents = dom.template_contents_of(template)?;
+                    dom.select(contents, &format!("#{}", id).parse().unwrap())
+                        .first()
+                        .copied()
+                })
+                .unwrap();
+            dom.is_focusable(handle)
+        };
+        for id in ["link", "tabbable", "button", "legend", "summary", "host"] {
+            assert!(focusable(id), "{}", id);
+        }
+        for id in [
+            "anchor",
+            "badtab",
+            "disabled",
+            "fieldset",
+            "second",
+            "inert",
+            "templated",
+        ] {
+            assert!(!focusable(id), "{}", id);
+        }
+        assert!(!dom.is_focusable(dom.document));
+    }
+
+    #[test]
+    fn parses_integers() {
+        assert_eq!(parse_integer("  42px"), Some(42));
+        assert_eq!(parse_integer("-3"), Some(-3));
+        assert_eq!(parse_integer("+7"), Some(7));
+        assert_eq!(parse_integer("x1"), None);
+        assert_eq!(parse_integer("-"), None);
+        assert_eq!(parse_integer(""), None);
+    }
+}
@@ -0,0 +1,119 @@
+//! Deduplication of repeated attribute values.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::Attribute;
+
+/// Values this short are stored inline in a tendril, so sharing them saves nothing.
+const MAX_INLINE_LEN: usize = 8;
+
+/// An interned value, hashed and compared as the string it holds so the table can be searched
+/// by `&str` without storing a second copy of every value as its key.
+#[derive(Debug)]
+struct Interned(StrTendril);
+
+impl std::borrow::Borrow<str> for Interned {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Interned {
+    fn eq(&self, other: &Interned) -> bool {
+        *self.0 == *other.0
+    }
+}
+
+impl Eq for Interned {}
+
+impl Hash for Interned {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (*self.0).hash(state);
+    }
+}
+
+/// An interner for attribute values.
+///
+/// Cloning an `Interner` is cheap and the clones share their table, so a single interner can be
+/// handed to every DOM parsed in a crawl batch. Interned values are shared tendrils, so each
+/// distinct value is only stored once no matter how many attributes use it.
+#[derive(Clone, Debug, Default)]
+pub struct Interner {
+    values: Rc<RefCell<HashSet<Interned>>>,
+}
+
+impl Interner {
+    /// Create an empty interner.
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// Return a tendril equal to `value` that shares its buffer with every other interned copy.
+    pub fn intern(&self, value: &StrTendril) -> StrTendril {
+        if value.len() <= MAX_INLINE_LEN {
+            return value.clone();
+        }
+        let mut values = self.values.borrow_mut();
+        if let Some(interned) = values.get(&**value) {
+            return interned.0.clone();
+        }
+        // Cloning a heap tendril turns it into a shared one, so the stored copy and the returned
+        // copy point at the same buffer.
+        let interned = value.clone();
+        values.insert(Interned(interned.clone()));
+        interned
+    }
+
+    /// Intern the values of all of `attrs` in place.
+    pub fn intern_attrs(&self, attrs: &mut [Attribute]) {
+        for attr in attrs {
+            attr.value = self.intern(&attr.value);
+        }
+    }
+
+    /// The number of distinct values in the interner.
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    /// Whether no values have been interned yet.
+    pub fn is_empty(&self) -> bool {
+        self.values.borrow().is_empty()
+    }
+
+    /// Forget every interned value.
+    pub fn clear(&self) {
+        self.values.borrow_mut().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::tendril::StrTendril;
+
+    use super::Interner;
+
+    #[test]
+    fn shares_repeated_values() {
+        let interner = Interner::new();
+        let first = interner.intern(&StrTendril::from("https://example.com/"));
+        let second = interner.intern(&StrTendril::from("https://example.com/"));
+        assert_eq!(first, second);
+        assert_eq!(first.as_ptr(), second.as_ptr());
+        assert_eq!(interner.len(), 1);
+
+        // Short values are stored inline and aren't worth a table entry.
+        interner.intern(&StrTendril::from("nav"));
+        assert_eq!(interner.len(), 1);
+
+        let clone = interner.clone();
+        clone.intern(&StrTendril::from("https://example.org/"));
+        assert_eq!(interner.len(), 2);
+        interner.clear();
+        assert!(clone.is_empty());
+    }
+}
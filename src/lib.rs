@@ -12,21 +12,133 @@
 // Modified to use generational_indextree
 // The main implementation work here was implementing `TreeSink` for GenerationalArenaDom
 
+// Lets `#[derive(FromDom)]`, which refers to this crate by name, be used in its own tests.
+#[cfg(all(test, feature = "derive"))]
+extern crate self as generational_arena_dom;
+
 use generational_indextree::{Arena as TreeArena, NodeId};
 
 use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::default::Default;
+use std::mem;
 
 use markup5ever::tendril::StrTendril;
 
 use markup5ever::interface::tree_builder;
-use markup5ever::interface::tree_builder::{ElementFlags, NodeOrText, QuirksMode, TreeSink};
+use markup5ever::interface::tree_builder::{
+    ElementFlags, NextParserState, NodeOrText, QuirksMode, TreeSink,
+};
 use markup5ever::Attribute;
 use markup5ever::ExpandedName;
 use markup5ever::QualName;
 
+pub use crate::abbrev::AbbreviationError;
+pub use crate::attr_limit::{AttributeLimit, OversizedAttribute};
+pub use crate::attrs::is_boolean_attribute;
+pub use crate::cache::QueryCache;
+pub use crate::cancel::CancellationToken;
+pub use crate::copy::ExtractMode;
+pub use crate::element::ElementRef;
+pub use crate::error::{AbortError, DuplicateAttribute, MutationError, ParseError};
+#[cfg(feature = "http")]
+pub use crate::fetch::FetchError;
+pub use crate::foreign::{mathml_attr_name, svg_attr_name, svg_tag_name};
+pub use crate::fragment::{Fragment, InsertPosition};
+pub use crate::hook::ElementCloseHook;
+pub use crate::identity::{match_nodes, NodeMatching};
+pub use crate::induce::InducedSelector;
+pub use crate::intern::Interner;
+pub use crate::merge::HeadMergePolicy;
+pub use crate::options::DomOptions;
+pub use crate::order::{ChildNodes, DocumentOrder, NodeList, ReverseDocumentOrder};
+#[cfg(feature = "rayon")]
+pub use crate::parse::par_parse_many;
+pub use crate::parse::{parse_many, FragmentParser, Parser};
+pub use crate::path::TreePath;
+pub use crate::progress::{Progress, ProgressReporter};
+pub use crate::provenance::Provenance;
+pub use crate::range::{TextPoint, TextRange};
+pub use crate::reparse::{ReparseError, ReparseOutcome, TextEdit};
+pub use crate::replace::{FindWith, TextPattern};
+pub use crate::select::{
+    CssLocalName, CssString, DomSelectorImpl, NodeFilter, PseudoClass, PseudoElement, Selector,
+    SelectorError, SelectorMatches,
+};
+pub use crate::serialize::{
+    AttributeOrder, SerializableHandle, SerializeOpts, SourceMap, SourceMapEntry,
+};
+pub use crate::similarity::{similarity, subtree_similarity};
+pub use crate::text::{TextNormalizer, TextStorage};
+pub use crate::tokens::{
+    comma_tokens, has_token, icon_sizes, source_sizes, space_tokens, srcset_candidates,
+    unique_tokens, CommaTokens, IconSize, IconSizes, ImageDescriptor, SourceSize, SourceSizes,
+    SpaceTokens, SrcsetCandidate, SrcsetCandidates,
+};
+pub use crate::view::{SubtreeMut, SubtreeView};
+pub use crate::weak::WeakHandle;
+#[cfg(feature = "xml")]
+pub use crate::xml::parse_xml;
+
+use crate::trace::ParseSpan;
+
+mod abbrev;
+mod attr_limit;
+mod attrs;
+mod cache;
+mod cancel;
+mod canonical;
+mod copy;
+mod element;
+pub mod epub;
+mod error;
+pub mod extract;
+#[cfg(feature = "http")]
+mod fetch;
+mod foreign;
+mod form;
+mod fragment;
+mod freeze;
+mod hook;
+mod identity;
+mod induce;
+mod interactive;
+mod intern;
+mod mathml;
+mod merge;
+pub mod model;
+mod mutate;
+mod node;
+mod options;
+mod order;
+mod orphans;
+mod parse;
+mod path;
+mod progress;
+mod provenance;
+mod range;
+mod remove;
+mod reparse;
+mod replace;
+mod script;
+mod select;
+mod serialize;
+mod similarity;
+#[cfg(feature = "test-utils")]
+pub mod testing;
+mod text;
+mod tokens;
+mod trace;
+pub mod transform;
+mod tree_dump;
+mod unique_selector;
+mod url;
+mod view;
+mod weak;
+#[cfg(feature = "xml")]
+mod xml;
+
 /// The different kinds of nodes in the DOM.
 #[derive(Debug)]
 pub enum NodeData {
@@ -101,11 +213,274 @@ pub struct GenerationalArenaDom {
     /// Errors that occurred during parsing.
     pub errors: Vec<Cow<'static, str>>,
 
+    /// The same errors as [`errors`](GenerationalArenaDom::errors), each with the element and
+    /// line the tree builder was at when it was reported.
+    pub parse_errors: Vec<ParseError>,
+
     /// The document's quirks mode.
     pub quirks_mode: QuirksMode,
+
+    opts: DomOptions,
+
+    /// The URL the document was loaded from, if known.
+    url: Option<String>,
+
+    /// Identifies this DOM to the weak handles created from it.
+    id: u64,
+
+    /// Incremented whenever the tree is mutated through the DOM's APIs.
+    version: u64,
+
+    /// The element the tree builder is working in, used to locate parse errors: the element
+    /// most recently created, or the parent of the one most recently closed.
+    current_node: Option<Handle>,
+
+    /// The line of the input the tree builder is at.
+    current_line: u64,
+
+    /// The line each node created by the tree builder started on, if lines are recorded.
+    lines: HashMap<Handle, u64>,
+
+    /// Attributes dropped as duplicates, if they are recorded.
+    duplicate_attributes: Vec<DuplicateAttribute>,
+
+    /// Roots of frozen subtrees.
+    frozen: HashSet<Handle>,
+
+    /// The `<template>` each template contents node belongs to.
+    template_owners: HashMap<Handle, Handle>,
+
+    /// Frozen nodes that mutations of whole subtrees skipped.
+    frozen_skips: RefCell<Vec<Handle>>,
+
+    /// Nodes the parser foster-parented, if provenance is recorded.
+    foster_parented: HashSet<Handle>,
+
+    /// Nodes the parser moved after inserting them, if provenance is recorded.
+    reparented: HashSet<Handle>,
+
+    /// Elements the parser may have created without a start tag, and whether it certainly did,
+    /// if provenance is recorded.
+    implied: HashMap<Handle, Provenance>,
+
+    /// How many parse errors had been reported when the tree builder last created an element.
+    errors_before_element: usize,
+
+    /// Form-associated elements and the form the parser associated them with.
+    form_owners: HashMap<Handle, Handle>,
+
+    /// Scripts the parser marked as "already started", which must not be run.
+    started_scripts: HashSet<Handle>,
+
+    /// Scripts the parser finished, in the order it finished them.
+    completed_scripts: Vec<Handle>,
+
+    /// The elements the tree builder has open, innermost last, if an element close hook is set.
+    open_elements: Vec<Handle>,
+
+    /// Nodes the tree builder created and hasn't inserted yet, if an element close hook is set.
+    unplaced: HashSet<Handle>,
+
+    /// Why parsing was aborted, if it was.
+    abort: Option<AbortError>,
+
+    /// Nodes handed to the tree builder after parsing was aborted, which are never attached and
+    /// are freed when it finishes.
+    discarded: Vec<Handle>,
+
+    /// What the parser has produced so far.
+    progress: Progress,
+
+    /// Approximate number of bytes allocated for the parser's output.
+    allocated: usize,
+
+    /// Opened when the DOM was created, to trace how long building it took.
+    parse_span: ParseSpan,
 }
 
 impl GenerationalArenaDom {
+    /// Create an empty DOM that is built according to `opts`.
+    pub fn with_options(opts: DomOptions) -> GenerationalArenaDom {
+        let mut arena = Arena::new();
+        let document = arena.new_node(NodeData::Document);
+        GenerationalArenaDom {
+            arena,
+            document,
+            errors: vec![],
+            parse_errors: vec![],
+            quirks_mode: tree_builder::NoQuirks,
+            opts,
+            url: None,
+            id: weak::next_dom_id(),
+            version: 0,
+            current_node: None,
+            current_line: 1,
+            lines: HashMap::new(),
+            duplicate_attributes: Vec::new(),
+            frozen: HashSet::new(),
+            frozen_skips: RefCell::new(Vec::new()),
+            template_owners: HashMap::new(),
+            foster_parented: HashSet::new(),
+            reparented: HashSet::new(),
+            implied: HashMap::new(),
+            errors_before_element: 0,
+            form_owners: HashMap::new(),
+            started_scripts: HashSet::new(),
+            completed_scripts: Vec::new(),
+            open_elements: Vec::new(),
+            unplaced: HashSet::new(),
+            abort: None,
+            discarded: Vec::new(),
+            progress: Progress::default(),
+            allocated: 0,
+            parse_span: ParseSpan::start(),
+        }
+    }
+
+    /// A counter that changes whenever the tree is mutated through this type's methods, so
+    /// derived data such as [`QueryCache`] entries can tell when they are stale.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Record a mutation made directly through [`arena`](GenerationalArenaDom::arena), which
+    /// the DOM can't observe itself.
+    pub fn mark_mutated(&mut self) {
+        self.version += 1;
+    }
+
+    /// Why parsing was aborted, or `None` if the parser's output was used in full.
+    pub fn abort_error(&self) -> Option<&AbortError> {
+        self.abort.as_ref()
+    }
+
+    /// The attributes the parser gave an element that already had one with the same name, in
+    /// the order they were dropped, if the DOM was built with
+    /// [`DomOptions::record_duplicate_attributes`].
+    ///
+    /// html5ever's tokenizer drops repeated attributes within a single tag itself, reporting
+    /// only a `Duplicate attribute` [parse error](GenerationalArenaDom::errors), so they reach
+    /// the DOM, and are recorded here, only from tokenizers that pass them on. What is always
+    /// recorded are the attributes of stray `<html>` and `<body>` tags, which the tree builder
+    /// merges into the existing elements, keeping the values already there.
+    pub fn duplicate_attributes(&self) -> &[DuplicateAttribute] {
+        &self.duplicate_attributes
+    }
+
+    /// Turn a finished DOM into an error if parsing was aborted.
+    pub fn into_result(self) -> Result<GenerationalArenaDom, AbortError> {
+        match self.abort {
+            Some(err) => Err(err),
+            None => Ok(self),
+        }
+    }
+
+    /// Check whether parsing should stop, recording the reason if so.
+    fn should_abort(&mut self) -> bool {
+        if self.abort.is_none() {
+            if let Some(token) = &self.opts.cancellation {
+                if token.is_cancelled() {
+                    self.abort = Some(AbortError::Cancelled);
+                }
+            }
+        }
+        if self.abort.is_none() {
+            if let Some(limit) = self.opts.max_nodes {
+                if self.progress.nodes >= limit {
+                    self.abort = Some(AbortError::NodeLimitExceeded { limit });
+                }
+            }
+        }
+        if self.abort.is_none() {
+            if let Some(budget) = self.opts.memory_budget {
+                if self.allocated > budget {
+                    self.abort = Some(AbortError::MemoryBudgetExceeded {
+                        budget,
+                        used: self.allocated,
+                    });
+                }
+            }
+        }
+        self.abort.is_some()
+    }
+
+    /// Record `attrs` as duplicates dropped from `element`.
+    fn record_duplicates(&mut self, element: Handle, attrs: Vec<Attribute>) {
+        self.duplicate_attributes
+            .extend(attrs.into_iter().map(|attr| DuplicateAttribute {
+                element,
+                name: attr.name,
+                value: attr.value,
+            }));
+    }
+
+    /// Create a node on behalf of the tree builder, keeping track of parsing progress.
+    fn new_node(&mut self, data: NodeData) -> Handle {
+        self.progress.nodes += 1;
+        self.allocated += mem::size_of::<generational_indextree::Node<NodeData>>();
+        self.report_progress();
+        let handle = self.arena.new_node(data);
+        if self.opts.record_lines {
+            self.lines.insert(handle, self.current_line);
+        }
+        handle
+    }
+
+    /// Account for `bytes` of text or attribute values received from the tree builder.
+    fn add_bytes(&mut self, bytes: usize) {
+        self.progress.bytes += bytes;
+        self.allocated += bytes;
+        self.report_progress();
+    }
+
+    fn report_progress(&mut self) {
+        if let Some(reporter) = &mut self.opts.progress {
+            reporter.update(&self.progress);
+        }
+    }
+
+    /// Append `child` to `parent` on behalf of the tree builder. `foster_parented` is set when
+    /// `parent` is where a node that belongs in a table is put instead.
+    fn append_node(&mut self, parent: &Handle, child: NodeOrText<Handle>, foster_parented: bool) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.mark_mutated();
+        if let NodeOrText::AppendText(ref text) = child {
+            self.add_bytes(text.len());
+            if self.should_abort() {
+                return;
+            }
+        }
+        self.note_inserted(*parent, &child, foster_parented);
+        let parent_node = self.arena.get(*parent).expect("Invalid node!");
+        // Append to an existing Text node if we have one.
+        if let NodeOrText::AppendText(ref text) = child {
+            if let Some(h) = parent_node.last_child() {
+                if append_to_existing_text(&self.arena, h, text) {
+                    return;
+                }
+            }
+        }
+
+        let new_child = match child {
+            NodeOrText::AppendText(text) => self.new_node(NodeData::Text {
+                contents: RefCell::new(text),
+            }),
+            NodeOrText::AppendNode(node) => node,
+        };
+        parent.append(new_child, &mut self.arena);
+    }
+
+    /// A node for the tree builder to work with once parsing was aborted. It is never attached,
+    /// as all mutations are ignored after an abort, but is distinct from every other node so
+    /// the tree builder's bookkeeping still holds.
+    fn discarded_node(&mut self, data: NodeData) -> Handle {
+        let handle = self.arena.new_node(data);
+        self.discarded.push(handle);
+        handle
+    }
+
     fn get_node(&self, target: &Handle) -> &NodeData {
         self.arena.get(*target).expect("Invalid node!").get()
     }
@@ -120,79 +495,142 @@ impl GenerationalArenaDom {
 
 impl TreeSink for GenerationalArenaDom {
     type Output = Self;
-    fn finish(self) -> Self {
+    fn finish(mut self) -> Self {
+        for node in mem::take(&mut self.discarded) {
+            node.remove(&mut self.arena);
+        }
+        self.note_finished();
+        if let Some(reporter) = &mut self.opts.progress {
+            reporter.finish(&self.progress);
+        }
+        if let Some(normalizer) = self.opts.text_normalizer.take() {
+            let _span = self.parse_span.phase("text_normalization");
+            self.normalize_text(&normalizer);
+            self.opts.text_normalizer = Some(normalizer);
+        }
+        if self.opts.text_storage == TextStorage::Shared {
+            let _span = self.parse_span.phase("text_sharing");
+            self.share_text();
+        }
+        let (nodes, errors) = (self.arena.count(), self.errors.len());
+        self.parse_span.finish(nodes, errors);
         self
     }
 
     type Handle = Handle;
 
     fn parse_error(&mut self, msg: Cow<'static, str>) {
-        self.errors.push(msg);
+        self.errors.push(msg.clone());
+        self.parse_errors.push(ParseError {
+            message: msg,
+            node: self.current_node,
+            line: self.current_line,
+        });
     }
 
     fn get_document(&mut self) -> Handle {
-        self.document.clone()
+        self.document
     }
 
     fn elem_name(&self, target: &'_ Handle) -> ExpandedName<'_> {
-        return match self.get_node(target) {
+        match self.get_node(target) {
             NodeData::Element { ref name, .. } => name.expanded(),
             _ => panic!("not an element!"),
-        };
+        }
     }
 
     fn create_element(
         &mut self,
         name: QualName,
-        attrs: Vec<Attribute>,
+        mut attrs: Vec<Attribute>,
         flags: ElementFlags,
     ) -> Handle {
+        if self.should_abort() {
+            let template_contents = flags
+                .template
+                .then(|| self.discarded_node(NodeData::Document));
+            return self.discarded_node(NodeData::Element {
+                name,
+                attrs: RefCell::new(vec![]),
+                template_contents: RefCell::new(template_contents),
+                mathml_annotation_xml_integration_point: flags
+                    .mathml_annotation_xml_integration_point,
+            });
+        }
+        if let Some(limit) = &mut self.opts.attribute_limit {
+            limit.apply(&name, &mut attrs);
+        }
+        if let Some(interner) = &self.opts.attr_interner {
+            interner.intern_attrs(&mut attrs);
+        }
+        self.allocated += attrs.len() * mem::size_of::<Attribute>();
+        self.add_bytes(attrs.iter().map(|attr| attr.value.len()).sum());
+        let provenance = if attrs.is_empty() {
+            self.implied_provenance(&name)
+        } else {
+            Provenance::Written
+        };
         let template_inner = if flags.template {
-            Some(self.arena.new_node(NodeData::Document))
+            Some(self.new_node(NodeData::Document))
         } else {
             None
         };
-        self.arena.new_node(NodeData::Element {
+        let mut duplicates = Vec::new();
+        if self.opts.record_duplicate_attributes {
+            let mut seen = HashSet::new();
+            attrs.retain(|attr| {
+                if seen.insert(attr.name.clone()) {
+                    return true;
+                }
+                duplicates.push(attr.clone());
+                false
+            });
+        }
+        let element = self.new_node(NodeData::Element {
             name,
             attrs: RefCell::new(attrs),
             template_contents: RefCell::new(template_inner),
             mathml_annotation_xml_integration_point: flags.mathml_annotation_xml_integration_point,
-        })
+        });
+        if let Some(contents) = template_inner {
+            self.template_owners.insert(contents, element);
+        }
+        self.record_duplicates(element, duplicates);
+        self.record_implied(element, provenance);
+        self.errors_before_element = self.parse_errors.len();
+        self.note_created(element);
+        self.current_node = Some(element);
+        element
     }
 
     fn create_comment(&mut self, text: StrTendril) -> Handle {
-        self.arena.new_node(NodeData::Comment { contents: text })
+        if self.should_abort() {
+            return self.discarded_node(NodeData::Comment { contents: text });
+        }
+        self.allocated += text.len();
+        let comment = self.new_node(NodeData::Comment { contents: text });
+        self.note_created(comment);
+        comment
     }
 
     fn create_pi(&mut self, target: StrTendril, data: StrTendril) -> Handle {
-        self.arena.new_node(NodeData::ProcessingInstruction {
+        if self.should_abort() {
+            return self.discarded_node(NodeData::ProcessingInstruction {
+                target,
+                contents: data,
+            });
+        }
+        self.allocated += target.len() + data.len();
+        let pi = self.new_node(NodeData::ProcessingInstruction {
             target,
             contents: data,
-        })
+        });
+        self.note_created(pi);
+        pi
     }
 
     fn append(&mut self, parent: &Handle, child: NodeOrText<Handle>) {
-        let parent_node = self.arena.get(*parent).expect("Invalid node!");
-        // Append to an existing Text node if we have one.
-        match child {
-            NodeOrText::AppendText(ref text) => match parent_node.last_child() {
-                Some(h) => {
-                    if append_to_existing_text(&self.arena, h, text) {
-                        return;
-                    }
-                }
-                _ => (),
-            },
-            _ => (),
-        }
-
-        let new_child = match child {
-            NodeOrText::AppendText(text) => self.arena.new_node(NodeData::Text {
-                contents: RefCell::new(text),
-            }),
-            NodeOrText::AppendNode(node) => node,
-        };
-        parent.append(new_child, &mut self.arena);
+        self.append_node(parent, child, false);
     }
 
     fn append_based_on_parent_node(
@@ -201,12 +639,21 @@ impl TreeSink for GenerationalArenaDom {
         prev_element: &Self::Handle,
         child: NodeOrText<Self::Handle>,
     ) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.mark_mutated();
         let element_node = self.arena.get(*element).expect("Invalid handle!");
         let parent = element_node.parent();
         if parent.is_some() {
             self.append_before_sibling(element, child);
         } else {
-            self.append(prev_element, child);
+            let last = self.arena[*prev_element].last_child();
+            self.append_node(prev_element, child, true);
+            match self.arena[*prev_element].last_child() {
+                Some(appended) if Some(appended) != last => self.record_foster_parented(appended),
+                _ => {}
+            }
         }
     }
 
@@ -216,7 +663,11 @@ impl TreeSink for GenerationalArenaDom {
         public_id: StrTendril,
         system_id: StrTendril,
     ) {
-        let new_node = self.arena.new_node(NodeData::Doctype {
+        if self.abort.is_some() {
+            return;
+        }
+        self.mark_mutated();
+        let new_node = self.new_node(NodeData::Doctype {
             name,
             public_id,
             system_id,
@@ -230,11 +681,7 @@ impl TreeSink for GenerationalArenaDom {
             ..
         } = self.get_node(target)
         {
-            template_contents
-                .borrow()
-                .as_ref()
-                .expect("not a template element!")
-                .clone()
+            template_contents.borrow().expect("not a template element!")
         } else {
             panic!("not a template element!")
         }
@@ -249,10 +696,23 @@ impl TreeSink for GenerationalArenaDom {
     }
 
     fn append_before_sibling(&mut self, sibling: &Handle, child: NodeOrText<Handle>) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.mark_mutated();
+        if let NodeOrText::AppendText(ref text) = child {
+            self.add_bytes(text.len());
+            if self.should_abort() {
+                return;
+            }
+        }
+        if let Some(parent) = self.arena[*sibling].parent() {
+            self.note_inserted(parent, &child, true);
+        }
         let preceding = self.preceding_node(sibling);
         let child = match (child, preceding) {
             // No previous node.
-            (NodeOrText::AppendText(text), None) => self.arena.new_node(NodeData::Text {
+            (NodeOrText::AppendText(text), None) => self.new_node(NodeData::Text {
                 contents: RefCell::new(text),
             }),
 
@@ -261,7 +721,7 @@ impl TreeSink for GenerationalArenaDom {
                 if append_to_existing_text(&self.arena, prev, &text) {
                     return;
                 }
-                self.arena.new_node(NodeData::Text {
+                self.new_node(NodeData::Text {
                     contents: RefCell::new(text),
                 })
             }
@@ -272,10 +732,29 @@ impl TreeSink for GenerationalArenaDom {
             // Any other kind of node.
             (NodeOrText::AppendNode(node), _) => node,
         };
-        child.insert_before(*sibling, &mut self.arena);
+        sibling.insert_before(child, &mut self.arena);
+        self.record_foster_parented(child);
     }
 
-    fn add_attrs_if_missing(&mut self, target: &Handle, attrs: Vec<Attribute>) {
+    fn add_attrs_if_missing(&mut self, target: &Handle, mut attrs: Vec<Attribute>) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.mark_mutated();
+        if self.opts.attribute_limit.is_some() {
+            let name = match self.get_node(target) {
+                NodeData::Element { name, .. } => name.clone(),
+                _ => panic!("not an element"),
+            };
+            if let Some(limit) = &mut self.opts.attribute_limit {
+                limit.apply(&name, &mut attrs);
+            }
+        }
+        if let Some(interner) = &self.opts.attr_interner {
+            interner.intern_attrs(&mut attrs);
+        }
+        self.allocated += attrs.len() * mem::size_of::<Attribute>();
+        self.add_bytes(attrs.iter().map(|attr| attr.value.len()).sum());
         let mut existing = if let NodeData::Element { ref attrs, .. } = self.get_node(target) {
             attrs.borrow_mut()
         } else {
@@ -286,27 +765,39 @@ impl TreeSink for GenerationalArenaDom {
             .iter()
             .map(|e| e.name.clone())
             .collect::<HashSet<_>>();
-        existing.extend(
-            attrs
-                .into_iter()
-                .filter(|attr| !existing_names.contains(&attr.name)),
-        );
+        let (duplicates, added): (Vec<_>, Vec<_>) = attrs
+            .into_iter()
+            .partition(|attr| existing_names.contains(&attr.name));
+        existing.extend(added);
+        drop(existing);
+        if self.opts.record_duplicate_attributes {
+            self.record_duplicates(*target, duplicates);
+        }
     }
 
     fn remove_from_parent(&mut self, target: &Handle) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.mark_mutated();
         target.detach(&mut self.arena);
+        self.record_reparented(*target);
     }
 
     fn reparent_children(&mut self, node: &Handle, new_parent: &Handle) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.mark_mutated();
         let mut next_child = self
             .arena
             .get_mut(*node)
             .and_then(|node| node.first_child());
         while let Some(child) = next_child {
+            next_child = self.arena[child].next_sibling();
             child.detach(&mut self.arena);
             new_parent.append(child, &mut self.arena);
-            let child_node = self.arena.get_mut(child).unwrap();
-            next_child = child_node.next_sibling();
+            self.record_reparented(child);
         }
     }
 
@@ -321,17 +812,147 @@ impl TreeSink for GenerationalArenaDom {
             panic!("not an element!")
         }
     }
+
+    fn mark_script_already_started(&mut self, node: &Handle) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.started_scripts.insert(*node);
+    }
+
+    fn pop(&mut self, node: &Handle) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.current_node = self.arena[*node].parent();
+        self.note_popped(*node);
+    }
+
+    fn associate_with_form(
+        &mut self,
+        target: &Handle,
+        form: &Handle,
+        _nodes: (&Handle, Option<&Handle>),
+    ) {
+        if self.abort.is_some() {
+            return;
+        }
+        self.form_owners.insert(*target, *form);
+    }
+
+    fn set_current_line(&mut self, line_number: u64) {
+        self.current_line = line_number;
+    }
+
+    fn complete_script(&mut self, node: &Handle) -> NextParserState {
+        if self.abort.is_none() {
+            self.completed_scripts.push(*node);
+        }
+        NextParserState::Continue
+    }
 }
 
 impl Default for GenerationalArenaDom {
     fn default() -> GenerationalArenaDom {
-        let mut arena = Arena::new();
-        let document = arena.new_node(NodeData::Document);
-        GenerationalArenaDom {
-            arena,
-            document,
-            errors: vec![],
-            quirks_mode: tree_builder::NoQuirks,
+        GenerationalArenaDom::with_options(DomOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::tree_builder::{ElementFlags, TreeSink};
+    use markup5ever::tendril::StrTendril;
+    use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+    use crate::parse::{parse_html, parse_html_with};
+    use crate::{AbortError, DomOptions, GenerationalArenaDom};
+
+    #[test]
+    fn enforces_node_and_memory_budgets() {
+        let html = format!("<p>{}</p>", "<b>bold</b> ".repeat(100));
+        let dom = parse_html_with(
+            &html,
+            DomOptions {
+                max_nodes: Some(20),
+                ..DomOptions::default()
+            },
+        );
+        assert_eq!(
+            dom.abort_error(),
+            Some(&AbortError::NodeLimitExceeded { limit: 20 })
+        );
+        assert!(dom.arena.count() <= 22);
+
+        let dom = parse_html_with(
+            &html,
+            DomOptions {
+                memory_budget: Some(4096),
+                ..DomOptions::default()
+            },
+        );
+        match dom.abort_error() {
+            Some(&AbortError::MemoryBudgetExceeded { budget, used }) => {
+                assert_eq!(budget, 4096);
+                assert!(used > budget);
+            }
+            other => panic!("unexpected abort: {:?}", other),
         }
+
+        let dom = parse_html_with(
+            &html,
+            DomOptions {
+                max_nodes: Some(1000),
+                memory_budget: Some(1 << 20),
+                ..DomOptions::default()
+            },
+        );
+        assert_eq!(dom.abort_error(), None);
+    }
+
+    #[test]
+    fn locates_parse_errors() {
+        let dom = parse_html("<!DOCTYPE html><div>\n<p>\n</span></div>");
+        assert_eq!(dom.errors, ["Found special tag while closing generic tag"]);
+        assert_eq!(dom.parse_errors[0].message, dom.errors[0]);
+        let p = dom.query_selector(dom.document, "p").unwrap();
+        assert_eq!(dom.parse_errors[0].node, p);
+        assert_eq!(dom.parse_errors[0].line, 3);
+    }
+
+    #[test]
+    fn records_duplicate_attributes() {
+        let opts = || DomOptions {
+            record_duplicate_attributes: true,
+            ..DomOptions::default()
+        };
+        let dom = parse_html_with("<body class=a><p id=x id=y><body class=b lang=en>", opts());
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let duplicates: Vec<_> = dom
+            .duplicate_attributes()
+            .iter()
+            .map(|duplicate| (duplicate.element, &*duplicate.name.local, &*duplicate.value))
+            .collect();
+        assert_eq!(duplicates, [(body, "class", "b")]);
+        assert_eq!(dom.get_attr(body, "class").as_deref(), Some("a"));
+        assert_eq!(dom.get_attr(body, "lang").as_deref(), Some("en"));
+        assert!(parse_html("<body class=a><body class=b>")
+            .duplicate_attributes()
+            .is_empty());
+
+        // Tokenizers other than html5ever's may pass repeated attributes on.
+        let mut dom = GenerationalArenaDom::with_options(opts());
+        let attr = |name: &str, value: &str| Attribute {
+            name: QualName::new(None, ns!(), LocalName::from(name)),
+            value: StrTendril::from(value),
+        };
+        let p = dom.create_element(
+            QualName::new(None, ns!(html), LocalName::from("p")),
+            vec![attr("id", "x"), attr("class", "c"), attr("id", "y")],
+            ElementFlags::default(),
+        );
+        assert_eq!(dom.get_attr(p, "id").as_deref(), Some("x"));
+        assert_eq!(dom.duplicate_attributes().len(), 1);
+        assert_eq!(dom.duplicate_attributes()[0].element, p);
+        assert_eq!(&*dom.duplicate_attributes()[0].value, "y");
     }
 }
@@ -0,0 +1,579 @@
+//! Approximating MathML as plain text or LaTeX.
+
+use markup5ever::{namespace_url, ns};
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// LaTeX commands for characters common in formulas, and escapes for LaTeX's special
+/// characters.
+const LATEX_SYMBOLS: &[(char, &str)] = &[
+    ('\\', "\\backslash"),
+    ('{', "\\{"),
+    ('}', "\\}"),
+    ('%', "\\%"),
+    ('&', "\\&"),
+    ('#', "\\#"),
+    ('$', "\\$"),
+    ('_', "\\_"),
+    ('^', "\\hat{}"),
+    ('~', "\\sim"),
+    ('α', "\\alpha"),
+    ('β', "\\beta"),
+    ('γ', "\\gamma"),
+    ('δ', "\\delta"),
+    ('ε', "\\epsilon"),
+    ('ζ', "\\zeta"),
+    ('η', "\\eta"),
+    ('θ', "\\theta"),
+    ('ι', "\\iota"),
+    ('κ', "\\kappa"),
+    ('λ', "\\lambda"),
+    ('μ', "\\mu"),
+    ('ν', "\\nu"),
+    ('ξ', "\\xi"),
+    ('π', "\\pi"),
+    ('ρ', "\\rho"),
+    ('σ', "\\sigma"),
+    ('τ', "\\tau"),
+    ('υ', "\\upsilon"),
+    ('φ', "\\phi"),
+    ('ϕ', "\\phi"),
+    ('χ', "\\chi"),
+    ('ψ', "\\psi"),
+    ('ω', "\\omega"),
+    ('Γ', "\\Gamma"),
+    ('Δ', "\\Delta"),
+    ('Θ', "\\Theta"),
+    ('Λ', "\\Lambda"),
+    ('Ξ', "\\Xi"),
+    ('Π', "\\Pi"),
+    ('Σ', "\\Sigma"),
+    ('Φ', "\\Phi"),
+    ('Ψ', "\\Psi"),
+    ('Ω', "\\Omega"),
+    ('×', "\\times"),
+    ('÷', "\\div"),
+    ('±', "\\pm"),
+    ('∓', "\\mp"),
+    ('⋅', "\\cdot"),
+    ('·', "\\cdot"),
+    ('−', "-"),
+    ('≤', "\\leq"),
+    ('≥', "\\geq"),
+    ('≠', "\\neq"),
+    ('≈', "\\approx"),
+    ('≡', "\\equiv"),
+    ('∼', "\\sim"),
+    ('∝', "\\propto"),
+    ('→', "\\to"),
+    ('←', "\\leftarrow"),
+    ('↔', "\\leftrightarrow"),
+    ('⇒', "\\Rightarrow"),
+    ('⇔', "\\Leftrightarrow"),
+    ('∞', "\\infty"),
+    ('∂', "\\partial"),
+    ('∇', "\\nabla"),
+    ('∑', "\\sum"),
+    ('∏', "\\prod"),
+    ('∫', "\\int"),
+    ('∮', "\\oint"),
+    ('∀', "\\forall"),
+    ('∃', "\\exists"),
+    ('∈', "\\in"),
+    ('∉', "\\notin"),
+    ('∅', "\\emptyset"),
+    ('∪', "\\cup"),
+    ('∩', "\\cap"),
+    ('⊂', "\\subset"),
+    ('⊆', "\\subseteq"),
+    ('⊃', "\\supset"),
+    ('⊇', "\\supseteq"),
+    ('∧', "\\wedge"),
+    ('∨', "\\vee"),
+    ('¬', "\\neg"),
+    ('…', "\\ldots"),
+    ('⋯', "\\cdots"),
+    ('′', "'"),
+    ('ℝ', "\\mathbb{R}"),
+    ('ℕ', "\\mathbb{N}"),
+    ('ℤ', "\\mathbb{Z}"),
+    ('ℚ', "\\mathbb{Q}"),
+    ('ℂ', "\\mathbb{C}"),
+];
+
+/// Multi-letter identifiers that LaTeX has operator commands for.
+const LATEX_FUNCTIONS: &[&str] = &[
+    "arccos", "arcsin", "arctan", "cos", "cosh", "cot", "coth", "csc", "det", "dim", "exp", "gcd",
+    "inf", "lg", "lim", "ln", "log", "max", "min", "sec", "sin", "sinh", "sup", "tan", "tanh",
+];
+
+/// Operators that get spaces around them in plain text.
+const SPACED_OPERATORS: &[&str] = &[
+    "=", "+", "-", "−", "±", "∓", "×", "÷", "<", ">", "≤", "≥", "≠", "≈", "≡", "∼", "∝", "→", "←",
+    "↔", "⇒", "⇔", "∈", "∉", "∪", "∩", "⊂", "⊆", "⊃", "⊇", "∧", "∨",
+];
+
+/// Accents that `<mover accent="true">` puts over its base, and their LaTeX commands.
+const LATEX_ACCENTS: &[(&str, &str)] = &[
+    ("^", "\\hat"),
+    ("ˆ", "\\hat"),
+    ("~", "\\tilde"),
+    ("˜", "\\tilde"),
+    ("¯", "\\overline"),
+    ("‾", "\\overline"),
+    ("→", "\\vec"),
+    ("⃗", "\\vec"),
+    (".", "\\dot"),
+    ("˙", "\\dot"),
+    ("¨", "\\ddot"),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Notation {
+    Text,
+    Latex,
+}
+
+impl GenerationalArenaDom {
+    /// A plain-text approximation of the MathML element `handle`, such as `x = (-b ± √(b^2 -
+    /// 4ac))/(2a)`, for text extraction that shouldn't drop formulas.
+    ///
+    /// A `<math>` element's `alttext` is used if it has one. Otherwise fractions are written
+    /// with `/`, scripts with `^` and `_`, roots with `√`, and tables as rows of cells in
+    /// brackets, with parentheses added around anything longer than a single token.
+    pub fn math_to_text(&self, handle: Handle) -> String {
+        if self.is_math_element(handle, "math") {
+            if let Some(alttext) = self.get_attr(handle, "alttext") {
+                if !alttext.trim().is_empty() {
+                    return alttext.trim().to_string();
+                }
+            }
+        }
+        let text = self.convert_math(handle, Notation::Text);
+        text.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// A LaTeX approximation of the MathML element `handle`, such as
+    /// `x = \frac{-b \pm \sqrt{b^{2} - 4ac}}{2a}`, without delimiters such as `$`.
+    ///
+    /// A TeX `<annotation>` of a `<semantics>` element, as many converters emit alongside their
+    /// output, is used as is. Otherwise presentation markup is converted element by element,
+    /// with Greek letters and common operators written as commands, and other elements reduced
+    /// to their contents.
+    pub fn math_to_latex(&self, handle: Handle) -> String {
+        let latex = self.convert_math(handle, Notation::Latex);
+        latex.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    fn is_math_element(&self, handle: Handle, local: &str) -> bool {
+        self.element_name(handle)
+            .is_some_and(|name| name.ns == ns!(mathml) && &*name.local == local)
+    }
+
+    fn convert_math(&self, node: Handle, notation: Notation) -> String {
+        let name = match self.get_node(&node) {
+            NodeData::Text { contents } => return contents.borrow().trim().to_string(),
+            NodeData::Element { name, .. } if name.ns == ns!(mathml) => name.local.clone(),
+            NodeData::Element { .. } => return self.text_content(node).trim().to_string(),
+            _ => return String::new(),
+        };
+        let children: Vec<Handle> = node
+            .children(&self.arena)
+            .filter(|&child| self.element_name(child).is_some())
+            .collect();
+        let arg = |index: usize| {
+            children
+                .get(index)
+                .map(|&child| self.convert_math(child, notation))
+                .unwrap_or_default()
+        };
+        let latex = notation == Notation::Latex;
+        let token = self.text_content(node).trim().to_string();
+
+        match &*name {
+            "mi" => {
+                if !latex {
+                    // Keep names like `sin` apart from what follows.
+                    if token.chars().count() > 1 {
+                        format!(" {} ", token)
+                    } else {
+                        token
+                    }
+                } else if LATEX_FUNCTIONS.contains(&token.as_str()) {
+                    format!("\\{} ", token)
+                } else if token.chars().count() > 1 {
+                    format!("\\mathrm{{{}}}", latex_symbols(&token))
+                } else {
+                    latex_symbols(&token)
+                }
+            }
+            "mn" if latex => latex_symbols(&token),
+            "mn" => token,
+            "mo" => {
+                // Function application keeps `sin x` apart in text. Invisible times and
+                // separators are dropped.
+                let application = if latex { "" } else { " " };
+                let token = token
+                    .replace('\u{2061}', application)
+                    .replace(['\u{2062}', '\u{2063}'], "");
+                // A sign at the start of a row is unary, as in `-b`.
+                let unary = ["-", "−", "+", "±"].contains(&token.as_str())
+                    && !node
+                        .preceding_siblings(&self.arena)
+                        .skip(1)
+                        .any(|sibling| self.element_name(sibling).is_some());
+                if SPACED_OPERATORS.contains(&token.as_str()) {
+                    let token = if latex { latex_symbols(&token) } else { token };
+                    if unary {
+                        token.trim().to_string()
+                    } else {
+                        format!(" {} ", token.trim())
+                    }
+                } else if latex {
+                    latex_symbols(&token)
+                } else {
+                    token
+                }
+            }
+            "mtext" | "ms" => {
+                let token = if &*name == "ms" {
+                    format!("\"{}\"", token)
+                } else {
+                    token
+                };
+                if latex {
+                    format!("\\text{{{}}}", latex_text(&token))
+                } else {
+                    token
+                }
+            }
+            "mspace" => " ".to_string(),
+            "mfrac" => {
+                if latex {
+                    format!("\\frac{{{}}}{{{}}}", arg(0), arg(1))
+                } else {
+                    format!("{}/{}", text_group(&arg(0)), text_group(&arg(1)))
+                }
+            }
+            "msqrt" => {
+                let radicand = self.convert_math_children(&children, notation);
+                if latex {
+                    format!("\\sqrt{{{}}}", radicand)
+                } else {
+                    format!("√{}", text_group(&radicand))
+                }
+            }
+            "mroot" => {
+                if latex {
+                    format!("\\sqrt[{}]{{{}}}", arg(1), arg(0))
+                } else {
+                    format!("{}√{}", text_group(&arg(1)), text_group(&arg(0)))
+                }
+            }
+            "msup" => scripts(&arg(0), None, Some(&arg(1)), notation),
+            "msub" => scripts(&arg(0), Some(&arg(1)), None, notation),
+            "msubsup" => scripts(&arg(0), Some(&arg(1)), Some(&arg(2)), notation),
+            "munder" => self.under_over(&children, Some(arg(1)), None, notation),
+            "mover" => self.under_over(&children, None, Some(arg(1)), notation),
+            "munderover" => self.under_over(&children, Some(arg(1)), Some(arg(2)), notation),
+            "mmultiscripts" => {
+                // Only the postscripts, which are the common case.
+                let sub = arg(1);
+                let sup = arg(2);
+                scripts(
+                    &arg(0),
+                    (!sub.is_empty()).then_some(sub.as_str()),
+                    (!sup.is_empty()).then_some(sup.as_str()),
+                    notation,
+                )
+            }
+            "mfenced" => {
+                let open = self
+                    .get_attr(node, "open")
+                    .map_or("(".into(), |v| v.to_string());
+                let close = self
+                    .get_attr(node, "close")
+                    .map_or(")".into(), |v| v.to_string());
+                let separator = self
+                    .get_attr(node, "separators")
+                    .and_then(|v| v.trim().chars().next())
+                    .unwrap_or(',');
+                let items: Vec<String> = children
+                    .iter()
+                    .map(|&child| self.convert_math(child, notation))
+                    .collect();
+                let (open, close) = if latex {
+                    (latex_fence(&open, "\\left"), latex_fence(&close, "\\right"))
+                } else {
+                    (open, close)
+                };
+                format!(
+                    "{}{}{}",
+                    open,
+                    items.join(&format!("{} ", separator)),
+                    close
+                )
+            }
+            "mtable" => {
+                let rows: Vec<String> = children
+                    .iter()
+                    .map(|&row| {
+                        let cells: Vec<String> = row
+                            .children(&self.arena)
+                            .filter(|&cell| self.element_name(cell).is_some())
+                            .map(|cell| self.convert_math(cell, notation))
+                            .collect();
+                        cells.join(if latex { " & " } else { ", " })
+                    })
+                    .collect();
+                if latex {
+                    format!("\\begin{{matrix}} {} \\end{{matrix}}", rows.join(" \\\\ "))
+                } else {
+                    format!("[{}]", rows.join("; "))
+                }
+            }
+            "semantics" => {
+                let tex = children.iter().skip(1).find(|&&annotation| {
+                    self.is_math_element(annotation, "annotation")
+                        && self
+                            .get_attr(annotation, "encoding")
+                            .is_some_and(|encoding| {
+                                let encoding = encoding.to_ascii_lowercase();
+                                encoding.contains("tex")
+                            })
+                });
+                match tex {
+                    Some(&tex) if latex => self.text_content(tex).trim().to_string(),
+                    _ => arg(0),
+                }
+            }
+            "annotation" | "annotation-xml" | "mprescripts" | "none" | "mphantom" => String::new(),
+            _ => self.convert_math_children(&children, notation),
+        }
+    }
+
+    fn convert_math_children(&self, children: &[Handle], notation: Notation) -> String {
+        children
+            .iter()
+            .map(|&child| self.convert_math(child, notation))
+            .collect()
+    }
+
+    /// `<munder>`, `<mover>` and `<munderover>`: limits in LaTeX, or accents, or a general
+    /// stacking.
+    fn under_over(
+        &self,
+        children: &[Handle],
+        under: Option<String>,
+        over: Option<String>,
+        notation: Notation,
+    ) -> String {
+        let base = children
+            .first()
+            .map(|&base| self.convert_math(base, notation))
+            .unwrap_or_default();
+        let under = under.map(|under| under.trim().to_string());
+        let over = over.map(|over| over.trim().to_string());
+        if notation == Notation::Latex && under.is_none() {
+            if let Some(&over) = children.get(1) {
+                let over = self.text_content(over);
+                let accent = LATEX_ACCENTS.iter().find(|(mark, _)| *mark == over.trim());
+                if let Some((_, accent)) = accent {
+                    return format!("{}{{{}}}", accent, base);
+                }
+            }
+        }
+        let is_large_operator = ["\\sum", "\\prod", "\\int", "\\lim", "∑", "∏", "∫", "lim"]
+            .iter()
+            .any(|operator| base.trim() == *operator);
+        if notation == Notation::Latex && !is_large_operator {
+            let mut latex = base;
+            if let Some(over) = over {
+                latex = format!("\\overset{{{}}}{{{}}}", over, latex);
+            }
+            if let Some(under) = under {
+                latex = format!("\\underset{{{}}}{{{}}}", under, latex);
+            }
+            return latex;
+        }
+        scripts(base.trim(), under.as_deref(), over.as_deref(), notation)
+    }
+}
+
+/// A base with an optional subscript and superscript.
+fn scripts(base: &str, sub: Option<&str>, sup: Option<&str>, notation: Notation) -> String {
+    let mut out = base.to_string();
+    for (marker, script) in [('_', sub), ('^', sup)] {
+        if let Some(script) = script {
+            out.push(marker);
+            match notation {
+                Notation::Latex => {
+                    out.push('{');
+                    out.push_str(script.trim());
+                    out.push('}');
+                }
+                Notation::Text => out.push_str(&text_group(script)),
+            }
+        }
+    }
+    out
+}
+
+/// `text` as a single operand in plain text: parenthesized, unless it is a single character,
+/// number or word.
+fn text_group(text: &str) -> String {
+    let text = text.trim();
+    let single = text.chars().count() <= 1
+        || text.chars().all(|c| c.is_ascii_digit() || c == '.')
+        || text.chars().all(char::is_alphabetic)
+        || (text.starts_with('(') && text.ends_with(')'));
+    if single {
+        text.to_string()
+    } else {
+        format!("({})", text)
+    }
+}
+
+/// `text` with characters LaTeX has commands for replaced by them, and special characters
+/// escaped.
+fn latex_symbols(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match LATEX_SYMBOLS.iter().find(|&&(symbol, _)| symbol == c) {
+            Some((_, command)) => {
+                out.push_str(command);
+                // Keep a following letter from running into the command name.
+                if command
+                    .chars()
+                    .last()
+                    .is_some_and(|c| c.is_ascii_alphabetic())
+                {
+                    out.push(' ');
+                }
+            }
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+/// `text` inside `\text{}`, with LaTeX's special characters escaped.
+fn latex_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '{' | '}' | '%' | '&' | '#' | '$' | '_' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// A fence of `<mfenced>` as a sized LaTeX delimiter.
+fn latex_fence(fence: &str, size: &str) -> String {
+    match fence.trim() {
+        "" => format!("{}.", size),
+        "{" | "}" => format!("{}\\{} ", size, fence.trim()),
+        "|" | "(" | ")" | "[" | "]" => format!("{}{} ", size, fence.trim()),
+        fence => format!("{}{} ", size, latex_symbols(fence)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_html;
+    use crate::{GenerationalArenaDom, Handle};
+
+    fn math(body: &str) -> (GenerationalArenaDom, Handle) {
+        let dom = parse_html(&format!("<math>{}</math>", body));
+        let math = dom.query_selector(dom.document, "math").unwrap().unwrap();
+        (dom, math)
+    }
+
+    fn text(body: &str) -> String {
+        let (dom, math) = math(body);
+        dom.math_to_text(math)
+    }
+
+    fn latex(body: &str) -> String {
+        let (dom, math) = math(body);
+        dom.math_to_latex(math)
+    }
+
+    #[test]
+    fn converts_fractions() {
+        let frac = "<mfrac><mrow><mi>a</mi><mo>+</mo><mn>1</mn></mrow><mi>b</mi></mfrac>";
+        assert_eq!(text(frac), "(a + 1)/b");
+        assert_eq!(latex(frac), "\\frac{a + 1}{b}");
+    }
+
+    #[test]
+    fn converts_scripts() {
+        let sup = "<msup><mi>x</mi><mn>2</mn></msup>";
+        assert_eq!(text(sup), "x^2");
+        assert_eq!(latex(sup), "x^{2}");
+        let subsup = "<msubsup><mi>a</mi><mi>i</mi><mrow><mn>2</mn><mi>k</mi></mrow></msubsup>";
+        assert_eq!(text(subsup), "a_i^(2k)");
+        assert_eq!(latex(subsup), "a_{i}^{2k}");
+    }
+
+    #[test]
+    fn converts_roots() {
+        let sqrt = "<msqrt><mi>x</mi><mo>+</mo><mn>1</mn></msqrt>";
+        assert_eq!(text(sqrt), "√(x + 1)");
+        assert_eq!(latex(sqrt), "\\sqrt{x + 1}");
+        let root = "<mroot><mi>x</mi><mn>3</mn></mroot>";
+        assert_eq!(text(root), "3√x");
+        assert_eq!(latex(root), "\\sqrt[3]{x}");
+    }
+
+    #[test]
+    fn converts_fences() {
+        let fenced = "<mfenced><mi>a</mi><mi>b</mi></mfenced>";
+        assert_eq!(text(fenced), "(a, b)");
+        assert_eq!(latex(fenced), "\\left( a, b\\right)");
+        let braces = "<mfenced open=\"{\" close=\"\"><mi>x</mi></mfenced>";
+        assert_eq!(latex(braces), "\\left\\{ x\\right.");
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        let body = "<mtext>50% &amp; more_x</mtext>\
+                    <mfrac><mi>a</mi><mi>b</mi></mfrac>";
+        assert_eq!(text(body), "50% & more_xa/b");
+        assert_eq!(latex(body), "\\text{50\\% \\& more\\_x}\\frac{a}{b}");
+        assert_eq!(
+            latex("<mtext>\\ ^ ~ {#$}</mtext>"),
+            "\\text{\\textbackslash{} \\textasciicircum{} \\textasciitilde{} \\{\\#\\$\\}}"
+        );
+        assert_eq!(latex("<mi>%</mi><mo>&amp;</mo><mn>#</mn>"), "\\%\\&\\#");
+    }
+
+    #[test]
+    fn uses_annotations() {
+        let semantics = "<semantics><msup><mi>x</mi><mn>2</mn></msup>\
+                         <annotation-xml encoding=\"MathML-Content\"><apply></apply></annotation-xml>\
+                         <annotation encoding=\"application/x-tex\">x^2</annotation></semantics>";
+        assert_eq!(text(semantics), "x^2");
+        assert_eq!(latex(semantics), "x^2");
+        let content_only = "<semantics><mi>y</mi>\
+                            <annotation-xml encoding=\"MathML-Content\"><ci>z</ci></annotation-xml>\
+                            </semantics>";
+        assert_eq!(text(content_only), "y");
+        assert_eq!(latex(content_only), "y");
+    }
+
+    #[test]
+    fn prefers_alttext() {
+        let dom = parse_html("<math alttext=\" E = mc^2 \"><mi>E</mi></math>");
+        let math = dom.query_selector(dom.document, "math").unwrap().unwrap();
+        assert_eq!(dom.math_to_text(math), "E = mc^2");
+        assert_eq!(dom.math_to_latex(math), "E");
+    }
+}
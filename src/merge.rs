@@ -0,0 +1,136 @@
+//! Stitching several documents into one.
+
+use markup5ever::Attribute;
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// How the `<head>` of a merged document is combined with the target's `<head>`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HeadMergePolicy {
+    /// Leave the target's `<head>` untouched.
+    Ignore,
+
+    /// Append every child of the merged document's `<head>`.
+    AppendAll,
+
+    /// Append the elements of the merged document's `<head>` unless an element with the same name
+    /// and attributes is already present. `<title>` and `<base>` are only taken if the target has
+    /// none.
+    #[default]
+    AppendMissing,
+}
+
+impl GenerationalArenaDom {
+    /// Append copies of the children of `other`'s `<body>` to this document's `<body>`, and merge
+    /// the contents of the two `<head>`s according to `policy`.
+    ///
+    /// This is meant for stitching the pages of a paginated article back into one document.
+    /// Nothing is appended to a frozen `<body>` or `<head>`.
+    pub fn append_document(&mut self, other: &GenerationalArenaDom, policy: HeadMergePolicy) {
+        self.mark_mutated();
+        if let Some(source_body) = other.body() {
+            let target = self
+                .body()
+                .or_else(|| self.document_element())
+                .unwrap_or(self.document);
+            if !self.skip_frozen(target) {
+                for child in source_body.children(&other.arena) {
+                    let copy = self.import_subtree(other, child);
+                    target.append(copy, &mut self.arena);
+                }
+            }
+        }
+
+        if policy == HeadMergePolicy::Ignore {
+            return;
+        }
+        let (source_head, target_head) = match (other.head(), self.head()) {
+            (Some(source_head), Some(target_head)) => (source_head, target_head),
+            _ => return,
+        };
+        if self.skip_frozen(target_head) {
+            return;
+        }
+        for child in source_head.children(&other.arena) {
+            if policy == HeadMergePolicy::AppendMissing
+                && !self.head_is_missing(target_head, other, child)
+            {
+                continue;
+            }
+            let copy = self.import_subtree(other, child);
+            target_head.append(copy, &mut self.arena);
+        }
+    }
+
+    /// Whether `child` of `other`'s head should be added to `head` under
+    /// [`HeadMergePolicy::AppendMissing`].
+    fn head_is_missing(&self, head: Handle, other: &GenerationalArenaDom, child: Handle) -> bool {
+        let name = match other.element_name(child) {
+            Some(name) => name,
+            None => return false,
+        };
+        if &*name.local == "title" || &*name.local == "base" {
+            return head
+                .children(&self.arena)
+                .all(|existing| self.element_name(existing) != Some(name));
+        }
+        let attrs = sorted_attrs(other, child);
+        head.children(&self.arena).all(|existing| {
+            self.element_name(existing) != Some(name) || sorted_attrs(self, existing) != attrs
+        })
+    }
+}
+
+fn sorted_attrs(dom: &GenerationalArenaDom, handle: Handle) -> Vec<Attribute> {
+    let mut attrs = match dom.get_node(&handle) {
+        NodeData::Element { attrs, .. } => attrs.borrow().clone(),
+        _ => vec![],
+    };
+    attrs.sort();
+    attrs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeadMergePolicy;
+    use crate::parse::{body_html, parse_html};
+
+    const PAGE_1: &str = "<title>Story</title><link rel=stylesheet href=a.css><p>One</p>";
+    const PAGE_2: &str = "<title>Story, page 2</title><link rel=stylesheet href=a.css>\
+                          <link rel=stylesheet href=b.css><p>Two</p>";
+
+    #[test]
+    fn appends_bodies_and_merges_heads() {
+        for (policy, head) in [
+            (
+                HeadMergePolicy::Ignore,
+                "<title>Story</title><link rel=\"stylesheet\" href=\"a.css\">",
+            ),
+            (
+                HeadMergePolicy::AppendAll,
+                "<title>Story</title><link rel=\"stylesheet\" href=\"a.css\">\
+                 <title>Story, page 2</title><link rel=\"stylesheet\" href=\"a.css\">\
+                 <link rel=\"stylesheet\" href=\"b.css\">",
+            ),
+            (
+                HeadMergePolicy::AppendMissing,
+                "<title>Story</title><link rel=\"stylesheet\" href=\"a.css\">\
+                 <link rel=\"stylesheet\" href=\"b.css\">",
+            ),
+        ] {
+            let mut dom = parse_html(PAGE_1);
+            dom.append_document(&parse_html(PAGE_2), policy);
+            assert_eq!(body_html(&dom), "<p>One</p><p>Two</p>");
+            assert_eq!(dom.inner_html(dom.head().unwrap()), head, "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn leaves_frozen_bodies_alone() {
+        let mut dom = parse_html(PAGE_1);
+        dom.freeze(dom.body().unwrap());
+        dom.append_document(&parse_html(PAGE_2), HeadMergePolicy::AppendMissing);
+        assert_eq!(body_html(&dom), "<p>One</p>");
+        assert_eq!(dom.head().unwrap().children(&dom.arena).count(), 3);
+    }
+}
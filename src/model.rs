@@ -0,0 +1,478 @@
+//! A document model independent of HTML.
+//!
+//! [`convert`] turns a subtree into the headings, paragraphs, lists, tables, links and images
+//! it represents, in the manner of Pandoc's document model, so that renderers for Markdown,
+//! plain text or anything else can work from the same structure instead of each interpreting
+//! HTML in its own way.
+
+use crate::{space_tokens, GenerationalArenaDom, Handle, NodeData};
+
+/// Elements left out of the model along with their contents.
+const SKIPPED_ELEMENTS: &[&str] = &[
+    "head", "noscript", "script", "style", "template", "title", "button", "select", "textarea",
+];
+
+/// A block of a document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Block {
+    /// A heading of level 1 to 6.
+    Heading {
+        /// The level, from 1 for `<h1>` to 6.
+        level: u8,
+        /// The heading's text.
+        content: Vec<Inline>,
+    },
+    /// A paragraph, or a run of text outside any paragraph.
+    Paragraph(Vec<Inline>),
+    /// A bulleted or numbered list.
+    List {
+        /// Whether the list is numbered.
+        ordered: bool,
+        /// The number of the first item of a numbered list, if given.
+        start: Option<i64>,
+        /// The items, each a sequence of blocks.
+        items: Vec<Vec<Block>>,
+    },
+    /// A table.
+    Table(Table),
+    /// Preformatted code.
+    CodeBlock {
+        /// The language, from a `language-*` or `lang-*` class on the `<code>` or its `<pre>`.
+        language: Option<String>,
+        /// The code, exactly as written.
+        code: String,
+    },
+    /// A quotation.
+    Quote(Vec<Block>),
+    /// A thematic break, from `<hr>`.
+    Rule,
+}
+
+/// A [`Block::Table`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Table {
+    /// The caption, if any.
+    pub caption: Vec<Inline>,
+    /// The header rows: those in `<thead>`, or without one, leading rows of header cells.
+    pub head: Vec<Vec<Cell>>,
+    /// The other rows.
+    pub body: Vec<Vec<Cell>>,
+}
+
+/// A cell of a [`Table`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    /// Whether it's a header cell, `<th>`.
+    pub header: bool,
+    /// How many columns it spans.
+    pub colspan: usize,
+    /// How many rows it spans.
+    pub rowspan: usize,
+    /// Its contents.
+    pub content: Vec<Block>,
+}
+
+/// Text and what's inline with it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Inline {
+    /// Text, with whitespace collapsed.
+    Text(String),
+    /// Emphasized content, from `<em>` or `<i>`.
+    Emphasis(Vec<Inline>),
+    /// Strongly emphasized content, from `<strong>` or `<b>`.
+    Strong(Vec<Inline>),
+    /// Code, from `<code>`, `<kbd>` or `<samp>`, exactly as written.
+    Code(String),
+    /// A link.
+    Link {
+        /// The resolved URL it links to.
+        url: String,
+        /// Its `title`, if any.
+        title: Option<String>,
+        /// Its content.
+        content: Vec<Inline>,
+    },
+    /// An image.
+    Image {
+        /// The resolved URL of the image.
+        url: String,
+        /// Its alternative text.
+        alt: String,
+        /// Its `title`, if any.
+        title: Option<String>,
+    },
+    /// A line break, from `<br>`.
+    LineBreak,
+}
+
+impl Inline {
+    /// The text of `inlines`, without formatting. Images contribute their alternative text.
+    pub fn plain_text(inlines: &[Inline]) -> String {
+        let mut text = String::new();
+        for inline in inlines {
+            match inline {
+                Inline::Text(string) | Inline::Code(string) => text.push_str(string),
+                Inline::Emphasis(content)
+                | Inline::Strong(content)
+                | Inline::Link { content, .. } => text.push_str(&Inline::plain_text(content)),
+                Inline::Image { alt, .. } => text.push_str(alt),
+                Inline::LineBreak => text.push('\n'),
+            }
+        }
+        text
+    }
+}
+
+/// Convert the contents of `root` to blocks.
+///
+/// Headings, paragraphs, lists, tables, `<pre>`, `<blockquote>` and `<hr>` become the
+/// corresponding blocks, and other block-level containers such as `<div>` and `<section>`
+/// contribute their contents. Text outside paragraphs becomes paragraphs of its own. Inline
+/// formatting, links and images are kept; other inline elements contribute their contents.
+/// Scripts, styles, form controls and the document's `<head>` are left out. URLs are resolved
+/// against the document's base URL.
+pub fn convert(dom: &GenerationalArenaDom, root: Handle) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    convert_blocks(dom, root, &mut blocks);
+    blocks
+}
+
+fn convert_blocks(dom: &GenerationalArenaDom, parent: Handle, blocks: &mut Vec<Block>) {
+    let mut inlines = Vec::new();
+    for child in parent.children(&dom.arena) {
+        if is_skipped(dom, child) {
+            continue;
+        }
+        let is_block = dom.is_block(child)
+            || ["html", "hr", "pre", "blockquote"]
+                .iter()
+                .any(|name| dom.is_html_element(child, name));
+        if !is_block {
+            convert_inline(dom, child, &mut inlines);
+            continue;
+        }
+        flush_paragraph(&mut inlines, blocks);
+        let local = match dom.element_name(child) {
+            Some(name) => name.local.to_string(),
+            None => continue,
+        };
+        match &*local {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let content = trim_inlines(convert_inlines(dom, child));
+                if !content.is_empty() {
+                    blocks.push(Block::Heading {
+                        level: local.as_bytes()[1] - b'0',
+                        content,
+                    });
+                }
+            }
+            "p" => flush_paragraph(&mut convert_inlines(dom, child), blocks),
+            "ul" | "ol" => {
+                let items = child
+                    .children(&dom.arena)
+                    .filter(|&item| dom.element_name(item).is_some() && !is_skipped(dom, item))
+                    .map(|item| convert(dom, item))
+                    .collect();
+                let ordered = local == "ol";
+                let start = if ordered {
+                    dom.get_attr(child, "start")
+                        .and_then(|start| start.trim().parse().ok())
+                } else {
+                    None
+                };
+                blocks.push(Block::List {
+                    ordered,
+                    start,
+                    items,
+                });
+            }
+            "table" => blocks.push(Block::Table(convert_table(dom, child))),
+            "pre" => blocks.push(code_block(dom, child)),
+            "blockquote" => blocks.push(Block::Quote(convert(dom, child))),
+            "hr" => blocks.push(Block::Rule),
+            _ => convert_blocks(dom, child, blocks),
+        }
+    }
+    flush_paragraph(&mut inlines, blocks);
+}
+
+/// Add `inlines` to `blocks` as a paragraph, unless they're only whitespace, and clear them.
+fn flush_paragraph(inlines: &mut Vec<Inline>, blocks: &mut Vec<Block>) {
+    let content = trim_inlines(std::mem::take(inlines));
+    if !content.is_empty() {
+        blocks.push(Block::Paragraph(content));
+    }
+}
+
+fn convert_inlines(dom: &GenerationalArenaDom, parent: Handle) -> Vec<Inline> {
+    let mut inlines = Vec::new();
+    for child in parent.children(&dom.arena) {
+        convert_inline(dom, child, &mut inlines);
+    }
+    inlines
+}
+
+fn convert_inline(dom: &GenerationalArenaDom, node: Handle, inlines: &mut Vec<Inline>) {
+    if is_skipped(dom, node) {
+        return;
+    }
+    let local = match dom.get_node(&node) {
+        NodeData::Text { contents } => {
+            push_text(inlines, &contents.borrow());
+            return;
+        }
+        NodeData::Element { name, .. } => name.local.to_string(),
+        _ => return,
+    };
+    let title = || dom.get_attr(node, "title").map(|title| title.to_string());
+    match &*local {
+        "em" | "i" => inlines.push(Inline::Emphasis(convert_inlines(dom, node))),
+        "strong" | "b" => inlines.push(Inline::Strong(convert_inlines(dom, node))),
+        "code" | "kbd" | "samp" | "tt" => inlines.push(Inline::Code(dom.text_content(node))),
+        "a" => match dom.get_attr(node, "href") {
+            Some(href) => inlines.push(Inline::Link {
+                url: dom.resolve_url(href.trim()),
+                title: title(),
+                content: convert_inlines(dom, node),
+            }),
+            None => inlines.extend(convert_inlines(dom, node)),
+        },
+        "img" => {
+            if let Some(src) = dom.get_attr(node, "src") {
+                inlines.push(Inline::Image {
+                    url: dom.resolve_url(src.trim()),
+                    alt: dom
+                        .get_attr(node, "alt")
+                        .map(|alt| alt.to_string())
+                        .unwrap_or_default(),
+                    title: title(),
+                });
+            }
+        }
+        "br" => inlines.push(Inline::LineBreak),
+        _ => {
+            for child in node.children(&dom.arena) {
+                convert_inline(dom, child, inlines);
+            }
+            // Blocks inside inline content still separate words.
+            if dom.is_block(node) {
+                push_text(inlines, " ");
+            }
+        }
+    }
+}
+
+/// Append `text` with whitespace collapsed, merging it with preceding text.
+fn push_text(inlines: &mut Vec<Inline>, text: &str) {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = matches!(inlines.last(), Some(Inline::Text(last)) if last.ends_with(' '));
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+    if collapsed.is_empty() {
+        return;
+    }
+    match inlines.last_mut() {
+        Some(Inline::Text(last)) => last.push_str(&collapsed),
+        _ => inlines.push(Inline::Text(collapsed)),
+    }
+}
+
+/// `inlines` without whitespace at the start and end, or nothing if that's all there is.
+fn trim_inlines(mut inlines: Vec<Inline>) -> Vec<Inline> {
+    if let Some(Inline::Text(first)) = inlines.first_mut() {
+        *first = first.trim_start().to_string();
+    }
+    if let Some(Inline::Text(last)) = inlines.last_mut() {
+        *last = last.trim_end().to_string();
+    }
+    inlines.retain(|inline| !matches!(inline, Inline::Text(text) if text.is_empty()));
+    inlines
+}
+
+fn convert_table(dom: &GenerationalArenaDom, table: Handle) -> Table {
+    let mut converted = Table::default();
+    let mut rows: Vec<(Handle, bool)> = Vec::new();
+    for child in table.children(&dom.arena) {
+        if dom.is_html_element(child, "caption") {
+            converted.caption = trim_inlines(convert_inlines(dom, child));
+        } else if dom.is_html_element(child, "tr") {
+            rows.push((child, false));
+        } else if ["thead", "tbody", "tfoot"]
+            .iter()
+            .any(|name| dom.is_html_element(child, name))
+        {
+            let in_head = dom.is_html_element(child, "thead");
+            rows.extend(
+                child
+                    .children(&dom.arena)
+                    .filter(|&row| dom.is_html_element(row, "tr"))
+                    .map(|row| (row, in_head)),
+            );
+        }
+    }
+
+    let has_head = rows.iter().any(|&(_, in_head)| in_head);
+    let mut leading = !has_head;
+    for (row, in_head) in rows {
+        let cells: Vec<Cell> = row
+            .children(&dom.arena)
+            .filter(|&cell| dom.is_html_element(cell, "td") || dom.is_html_element(cell, "th"))
+            .map(|cell| {
+                let span = |name| {
+                    dom.get_attr(cell, name)
+                        .and_then(|span| span.trim().parse().ok())
+                        .filter(|&span| span > 0)
+                        .unwrap_or(1)
+                };
+                Cell {
+                    header: dom.is_html_element(cell, "th"),
+                    colspan: span("colspan"),
+                    rowspan: span("rowspan"),
+                    content: convert(dom, cell),
+                }
+            })
+            .collect();
+        leading &= !cells.is_empty() && cells.iter().all(|cell| cell.header);
+        if in_head || leading {
+            converted.head.push(cells);
+        } else {
+            converted.body.push(cells);
+        }
+    }
+    converted
+}
+
+fn code_block(dom: &GenerationalArenaDom, pre: Handle) -> Block {
+    let code = pre
+        .children(&dom.arena)
+        .find(|&child| dom.is_html_element(child, "code"));
+    let language = [code, Some(pre)].iter().flatten().find_map(|&node| {
+        let class = dom.get_attr(node, "class")?;
+        space_tokens(&class)
+            .find_map(|class| {
+                class
+                    .strip_prefix("language-")
+                    .or_else(|| class.strip_prefix("lang-"))
+            })
+            .filter(|language| !language.is_empty())
+            .map(|language| language.to_string())
+    });
+    Block::CodeBlock {
+        language,
+        code: dom.text_content(pre),
+    }
+}
+
+fn is_skipped(dom: &GenerationalArenaDom, node: Handle) -> bool {
+    SKIPPED_ELEMENTS
+        .iter()
+        .any(|name| dom.is_html_element(node, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert, Block, Cell, Inline, Table};
+    use crate::parse::parse_html;
+
+    fn text(text: &str) -> Inline {
+        Inline::Text(text.to_string())
+    }
+
+    fn paragraph(content: &str) -> Block {
+        Block::Paragraph(vec![text(content)])
+    }
+
+    #[test]
+    fn converts_blocks_and_inlines() {
+        let dom = parse_html(
+            "<head><base href=https://example.com/><title>T</title></head>\
+             <h2> Title </h2><div>Loose  <em>text</em><script>x</script></div>\
+             <p><a href=/a title=A>link <b>bold</b></a><br><img src=i.png alt=pic>\
+             <code>a  b</code></p>\
+             <ol start=3><li>one</li><li><p>two</p></li></ol>\
+             <blockquote>quoted</blockquote><hr>\
+             <pre class=lang-rust><code>fn  main() {}</code></pre>",
+        );
+        assert_eq!(
+            convert(&dom, dom.document),
+            [
+                Block::Heading {
+                    level: 2,
+                    content: vec![text("Title")],
+                },
+                Block::Paragraph(vec![text("Loose "), Inline::Emphasis(vec![text("text")])]),
+                Block::Paragraph(vec![
+                    Inline::Link {
+                        url: "https://example.com/a".to_string(),
+                        title: Some("A".to_string()),
+                        content: vec![text("link "), Inline::Strong(vec![text("bold")])],
+                    },
+                    Inline::LineBreak,
+                    Inline::Image {
+                        url: "https://example.com/i.png".to_string(),
+                        alt: "pic".to_string(),
+                        title: None,
+                    },
+                    Inline::Code("a  b".to_string()),
+                ]),
+                Block::List {
+                    ordered: true,
+                    start: Some(3),
+                    items: vec![vec![paragraph("one")], vec![paragraph("two")]],
+                },
+                Block::Quote(vec![paragraph("quoted")]),
+                Block::Rule,
+                Block::CodeBlock {
+                    language: Some("rust".to_string()),
+                    code: "fn  main() {}".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_tables() {
+        let dom = parse_html(
+            "<table><caption>Cap</caption><tr><th>A</th><th colspan=2>B</th></tr>\
+             <tr><th rowspan=0>1</th><td>2</td></tr></table>",
+        );
+        let cell = |header, colspan, rowspan, content: &str| Cell {
+            header,
+            colspan,
+            rowspan,
+            content: vec![paragraph(content)],
+        };
+        assert_eq!(
+            convert(&dom, dom.document),
+            [Block::Table(Table {
+                caption: vec![text("Cap")],
+                head: vec![vec![cell(true, 1, 1, "A"), cell(true, 2, 1, "B")]],
+                body: vec![vec![cell(true, 1, 1, "1"), cell(false, 1, 1, "2")]],
+            })]
+        );
+    }
+
+    #[test]
+    fn extracts_plain_text() {
+        let inlines = vec![
+            text("a "),
+            Inline::Strong(vec![Inline::Code("b".to_string())]),
+            Inline::LineBreak,
+            Inline::Image {
+                url: String::new(),
+                alt: "c".to_string(),
+                title: None,
+            },
+        ];
+        assert_eq!(Inline::plain_text(&inlines), "a b\nc");
+    }
+}
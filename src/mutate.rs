@@ -0,0 +1,203 @@
+//! Helpers for mutating the tree while walking it.
+//!
+//! Arena iterators borrow the arena, so code that wants to mutate as it walks has to collect
+//! handles first, and then cope with nodes that earlier mutations freed or moved. These helpers
+//! do that bookkeeping once.
+
+use markup5ever::tendril::StrTendril;
+
+use crate::{GenerationalArenaDom, Handle, Selector, SelectorError};
+
+impl GenerationalArenaDom {
+    /// Call `keep` on each child of `parent`, removing and freeing those it returns `false` for.
+    /// Children with anything frozen under them are kept whatever `keep` returns.
+    ///
+    /// The children are collected before the first call. `keep` may mutate the DOM freely:
+    /// children it adds aren't visited, and children that were freed or moved out of `parent`
+    /// by the time their turn comes are skipped.
+    pub fn retain_children<F>(&mut self, parent: Handle, mut keep: F)
+    where
+        F: FnMut(&mut GenerationalArenaDom, Handle) -> bool,
+    {
+        let children: Vec<Handle> = parent.children(&self.arena).collect();
+        for child in children {
+            if !self.is_child_of(child, parent) {
+                continue;
+            }
+            if !keep(self, child) && self.is_child_of(child, parent) {
+                self.free_subtree(child);
+            }
+        }
+    }
+
+    /// Call `f` on `root` and each of its descendants in document order, collecting the results.
+    ///
+    /// The descendants are collected before the first call. `f` may mutate the DOM freely:
+    /// nodes it adds aren't visited, and nodes that were freed by the time their turn comes are
+    /// skipped. Nodes that were moved are still visited.
+    pub fn map_descendants<T, F>(&mut self, root: Handle, mut f: F) -> Vec<T>
+    where
+        F: FnMut(&mut GenerationalArenaDom, Handle) -> T,
+    {
+        let nodes: Vec<Handle> = root.descendants(&self.arena).collect();
+        let mut results = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            if self.is_alive(node) {
+                results.push(f(self, node));
+            }
+        }
+        results
+    }
+
+    /// Remove and free the elements under `root` (not including `root` itself) that match
+    /// `selector`, e.g. `script, iframe`, returning how many matched. Matches with anything
+    /// frozen under them are left in place and not counted.
+    pub fn remove_all(&mut self, root: Handle, selector: &str) -> Result<usize, SelectorError> {
+        let matches = self.select(root, &Selector::parse(selector)?);
+        let mut removed = 0;
+        for &node in &matches {
+            // Matches inside an earlier match were freed along with it.
+            if !self.is_alive(node) {
+                removed += 1;
+            } else if !self.skip_frozen_removal(node) {
+                self.free_subtree(node);
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Replace the elements under `root` (not including `root` itself) that match `selector`
+    /// with their children, returning how many matched. Frozen matches are left in place and
+    /// not counted.
+    pub fn unwrap_all(&mut self, root: Handle, selector: &str) -> Result<usize, SelectorError> {
+        let matches = self.select(root, &Selector::parse(selector)?);
+        let mut unwrapped = 0;
+        for &node in &matches {
+            if !self.skip_frozen(node) {
+                self.unwrap_node(node);
+                unwrapped += 1;
+            }
+        }
+        Ok(unwrapped)
+    }
+
+    /// Set the attribute `name` (with no namespace) to `value` on the elements under `root`
+    /// (not including `root` itself) that match `selector`, replacing any existing value and
+    /// returning how many matched. Frozen matches are left alone and not counted.
+    pub fn set_attr_all(
+        &mut self,
+        root: Handle,
+        selector: &str,
+        name: &str,
+        value: &str,
+    ) -> Result<usize, SelectorError> {
+        let matches = self.select(root, &Selector::parse(selector)?);
+        let mut set = 0;
+        for &node in &matches {
+            if !self.skip_frozen(node) {
+                self.set_attr(node, name, StrTendril::from(value));
+                set += 1;
+            }
+        }
+        Ok(set)
+    }
+
+    fn is_child_of(&self, child: Handle, parent: Handle) -> bool {
+        self.arena
+            .get(child)
+            .is_some_and(|node| node.parent() == Some(parent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{body_html, parse_html};
+    use crate::InsertPosition;
+
+    #[test]
+    fn retains_children_while_mutating() {
+        let mut dom = parse_html("<ul><li>a</li><li>b</li><li>c</li><li>d</li></ul>");
+        let ul = dom.query_selector(dom.document, "ul").unwrap().unwrap();
+        let mut visited = vec![];
+        dom.retain_children(ul, |dom, li| {
+            let text = dom.text_content(li);
+            visited.push(text.clone());
+            if text == "a" {
+                // Free a later sibling and add a new one; neither is visited.
+                let c = dom.arena[li].next_sibling().unwrap();
+                let c = dom.arena[c].next_sibling().unwrap();
+                dom.remove_subtree(c).unwrap();
+                let copy = dom.copy_fragment(li);
+                dom.insert_fragment(copy, InsertPosition::AppendTo(ul))
+                    .unwrap();
+            }
+            text != "b"
+        });
+        assert_eq!(visited, ["a", "b", "d"]);
+        assert_eq!(body_html(&dom), "<ul><li>a</li><li>d</li><li>a</li></ul>");
+    }
+
+    #[test]
+    fn maps_descendants_while_mutating() {
+        let mut dom = parse_html("<div><p>one</p><p>two</p></div>");
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        let names = dom.map_descendants(div, |dom, node| {
+            if dom.text_content(node) == "one" && dom.element_name(node).is_some() {
+                let next = dom.arena[node].next_sibling().unwrap();
+                dom.remove_subtree(next).unwrap();
+            }
+            dom.element_name(node)
+                .map_or("#text".to_string(), |name| name.local.to_string())
+        });
+        assert_eq!(names, ["div", "p", "#text"]);
+    }
+
+    #[test]
+    fn removes_all_matches() {
+        let mut dom = parse_html(
+            "<div class=ad><p>x<span class=ad>y</span></p></div><p>kept</p>\
+             <aside class=ad>z</aside><script>s()</script>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        assert_eq!(dom.remove_all(body, ".ad, script"), Ok(4));
+        assert_eq!(body_html(&dom), "<p>kept</p>");
+        assert_eq!(dom.remove_all(body, ".ad"), Ok(0));
+        assert_eq!(dom.remove_all(body, "p"), Ok(1));
+        // `root` itself isn't a candidate.
+        assert_eq!(dom.remove_all(body, "body"), Ok(0));
+        assert!(dom.is_alive(body));
+    }
+
+    #[test]
+    fn unwraps_all_matches() {
+        let mut dom = parse_html("<p><span>a<span>b</span>c</span> <span>d</span></p>");
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        assert_eq!(dom.unwrap_all(p, "span"), Ok(3));
+        assert_eq!(dom.inner_html(p), "abc d");
+        assert_eq!(dom.unwrap_all(p, "span"), Ok(0));
+    }
+
+    #[test]
+    fn sets_attributes_on_all_matches() {
+        let mut dom = parse_html("<a href=a>a</a><p><a href=b rel=x>b</a></p><b>c</b>");
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        assert_eq!(dom.set_attr_all(body, "a", "rel", "nofollow"), Ok(2));
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"a\" rel=\"nofollow\">a</a><p><a href=\"b\" rel=\"nofollow\">b</a></p>\
+             <b>c</b>"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_selectors() {
+        let mut dom = parse_html("<p>a</p>");
+        let html = body_html(&dom);
+        let root = dom.document;
+        assert!(dom.remove_all(root, "p[").is_err());
+        assert!(dom.unwrap_all(root, "::").is_err());
+        assert!(dom.set_attr_all(root, "", "a", "b").is_err());
+        assert_eq!(body_html(&dom), html);
+    }
+}
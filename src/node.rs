@@ -0,0 +1,98 @@
+//! Helpers for looking at individual nodes.
+
+use std::cell::RefCell;
+
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+impl GenerationalArenaDom {
+    /// The root element of the document, usually `<html>`.
+    pub fn document_element(&self) -> Option<Handle> {
+        self.document
+            .children(&self.arena)
+            .find(|&child| self.element_name(child).is_some())
+    }
+
+    /// The document's `<head>` element.
+    pub fn head(&self) -> Option<Handle> {
+        self.html_child(self.document_element()?, "head")
+    }
+
+    /// The document's `<body>` element.
+    pub fn body(&self) -> Option<Handle> {
+        self.html_child(self.document_element()?, "body")
+    }
+
+    /// The name of `handle` if it is an element.
+    pub(crate) fn element_name(&self, handle: Handle) -> Option<&QualName> {
+        match self.arena.get(handle)?.get() {
+            NodeData::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Whether `handle` is an HTML element with the given local name.
+    pub(crate) fn is_html_element(&self, handle: Handle, local: &str) -> bool {
+        self.element_name(handle)
+            .is_some_and(|name| name.ns == ns!(html) && &*name.local == local)
+    }
+
+    /// The first child of `parent` that is an HTML element with the given local name.
+    pub(crate) fn html_child(&self, parent: Handle, local: &str) -> Option<Handle> {
+        parent
+            .children(&self.arena)
+            .find(|&child| self.is_html_element(child, local))
+    }
+
+    /// The concatenated contents of all text nodes under `handle`, in document order.
+    pub fn text_content(&self, handle: Handle) -> String {
+        let mut text = String::new();
+        for node in handle.descendants(&self.arena) {
+            if let NodeData::Text { contents } = self.get_node(&node) {
+                text.push_str(&contents.borrow());
+            }
+        }
+        text
+    }
+
+    /// Create a detached HTML element. `<template>` elements get template contents.
+    pub(crate) fn create_html_element(&mut self, local: &str, attrs: Vec<Attribute>) -> Handle {
+        let template_contents = if local == "template" {
+            Some(self.arena.new_node(NodeData::Document))
+        } else {
+            None
+        };
+        let element = self.arena.new_node(NodeData::Element {
+            name: QualName::new(None, ns!(html), LocalName::from(local)),
+            attrs: RefCell::new(attrs),
+            template_contents: RefCell::new(template_contents),
+            mathml_annotation_xml_integration_point: false,
+        });
+        if let Some(contents) = template_contents {
+            self.template_owners.insert(contents, element);
+        }
+        element
+    }
+
+    /// Change the element `handle` into an HTML element called `local`, keeping its attributes
+    /// and children. Does nothing if `handle` isn't an element or is frozen.
+    pub(crate) fn rename_html_element(&mut self, handle: Handle, local: &str) {
+        if self.skip_frozen(handle) {
+            return;
+        }
+        if let Some(NodeData::Element { name, .. }) =
+            self.arena.get_mut(handle).map(|node| node.get_mut())
+        {
+            *name = QualName::new(None, ns!(html), LocalName::from(local));
+        }
+        self.mark_mutated();
+    }
+
+    /// Create a detached text node.
+    pub(crate) fn create_text(&mut self, text: &str) -> Handle {
+        self.arena.new_node(NodeData::Text {
+            contents: RefCell::new(text.into()),
+        })
+    }
+}
@@ -0,0 +1,62 @@
+//! Options controlling how a [`GenerationalArenaDom`](crate::GenerationalArenaDom) is built.
+
+use crate::attr_limit::AttributeLimit;
+use crate::cancel::CancellationToken;
+use crate::hook::ElementCloseHook;
+use crate::intern::Interner;
+use crate::progress::ProgressReporter;
+use crate::text::{TextNormalizer, TextStorage};
+
+/// Options passed to [`GenerationalArenaDom::with_options`](crate::GenerationalArenaDom::with_options).
+///
+/// The defaults match the behaviour of [`GenerationalArenaDom::default`](Default::default).
+#[derive(Debug, Default)]
+pub struct DomOptions {
+    /// How the contents of text nodes are stored once parsing finishes.
+    pub text_storage: TextStorage,
+
+    /// If set, the contents of text nodes are normalized with it once parsing finishes.
+    pub text_normalizer: Option<TextNormalizer>,
+
+    /// If set, attribute values longer than this limit are truncated or removed as elements are
+    /// created.
+    pub attribute_limit: Option<AttributeLimit>,
+
+    /// If set, attribute values are deduplicated through this interner as elements are created.
+    pub attr_interner: Option<Interner>,
+
+    /// If set, parsing is aborted with [`AbortError::Cancelled`](crate::AbortError::Cancelled)
+    /// once the token is cancelled.
+    pub cancellation: Option<CancellationToken>,
+
+    /// If set, parsing is aborted with
+    /// [`AbortError::NodeLimitExceeded`](crate::AbortError::NodeLimitExceeded) rather than
+    /// creating more than this many nodes.
+    pub max_nodes: Option<usize>,
+
+    /// If set, parsing is aborted with
+    /// [`AbortError::MemoryBudgetExceeded`](crate::AbortError::MemoryBudgetExceeded) once the
+    /// approximate memory used by nodes, text and attributes exceeds this many bytes.
+    pub memory_budget: Option<usize>,
+
+    /// If set, invoked periodically with counts of what has been parsed so far.
+    pub progress: Option<ProgressReporter>,
+
+    /// Whether to record the line each node started on, for
+    /// [`GenerationalArenaDom::line_of`](crate::GenerationalArenaDom::line_of).
+    pub record_lines: bool,
+
+    /// Whether to record attributes dropped for duplicating one already on their element, for
+    /// [`GenerationalArenaDom::duplicate_attributes`](crate::GenerationalArenaDom::duplicate_attributes).
+    pub record_duplicate_attributes: bool,
+
+    /// Whether to record which nodes the parser foster-parented, moved or implied, for
+    /// [`GenerationalArenaDom::was_foster_parented`](crate::GenerationalArenaDom::was_foster_parented),
+    /// [`was_reparented`](crate::GenerationalArenaDom::was_reparented),
+    /// [`provenance`](crate::GenerationalArenaDom::provenance) and
+    /// [`was_implied`](crate::GenerationalArenaDom::was_implied).
+    pub record_provenance: bool,
+
+    /// If set, invoked whenever the parser closes an element.
+    pub on_element_close: Option<ElementCloseHook>,
+}
@@ -0,0 +1,399 @@
+//! Iterators over nodes with a guaranteed order.
+//!
+//! The traversals of [`generational_indextree`] go one way only. The iterators here can be
+//! walked from either end, so the same traversal gives document order forwards and reverse
+//! document order backwards, and [`snapshot`](DocumentOrder::snapshot) turns one into a
+//! [`NodeList`] of known length that doesn't borrow the DOM.
+
+use std::fmt;
+use std::iter::FusedIterator;
+use std::vec;
+
+use crate::{Arena, GenerationalArenaDom, Handle};
+
+/// The nodes of a subtree in document order: each node before its descendants, and those
+/// before its following siblings. From [`GenerationalArenaDom::document_order`].
+///
+/// Walking it from the back with [`next_back`](DoubleEndedIterator::next_back) or
+/// [`rev`](Iterator::rev) gives reverse document order, and the two ends can be mixed; they
+/// stop when they meet. Template contents aren't part of their element's subtree.
+#[derive(Clone)]
+pub struct DocumentOrder<'a> {
+    arena: &'a Arena,
+    root: Handle,
+    /// The next node from the front and from the back, or `None` once they have met.
+    ends: Option<(Handle, Handle)>,
+}
+
+/// The nodes of a subtree in reverse document order: the last descendant first and the root
+/// last. From [`GenerationalArenaDom::reverse_document_order`].
+///
+/// This is a [`DocumentOrder`] walked from the back, so walking it from the back gives document
+/// order.
+#[derive(Clone, Debug)]
+pub struct ReverseDocumentOrder<'a> {
+    inner: DocumentOrder<'a>,
+}
+
+/// The children of a node, first to last. From [`GenerationalArenaDom::child_nodes`].
+///
+/// Walking it from the back gives the children last to first.
+#[derive(Clone)]
+pub struct ChildNodes<'a> {
+    arena: &'a Arena,
+    /// The next child from the front and from the back, or `None` once they have met.
+    ends: Option<(Handle, Handle)>,
+}
+
+/// Handles collected from an ordered traversal, in the same order. From
+/// [`DocumentOrder::snapshot`] and the other iterators' `snapshot` methods.
+///
+/// Its length is known, and it doesn't borrow the DOM, so the nodes can be changed or removed
+/// while iterating. The handles of removed nodes stay in the list, and no nodes added after the
+/// snapshot are.
+#[derive(Clone, Debug)]
+pub struct NodeList {
+    handles: vec::IntoIter<Handle>,
+}
+
+impl GenerationalArenaDom {
+    /// `root` and its descendants in document order.
+    pub fn document_order(&self, root: Handle) -> DocumentOrder<'_> {
+        let ends = self
+            .is_alive(root)
+            .then(|| (root, last_descendant(&self.arena, root)));
+        DocumentOrder {
+            arena: &self.arena,
+            root,
+            ends,
+        }
+    }
+
+    /// `root` and its descendants in reverse document order.
+    pub fn reverse_document_order(&self, root: Handle) -> ReverseDocumentOrder<'_> {
+        ReverseDocumentOrder {
+            inner: self.document_order(root),
+        }
+    }
+
+    /// The children of `parent`, first to last.
+    pub fn child_nodes(&self, parent: Handle) -> ChildNodes<'_> {
+        let ends = match self.arena.get(parent) {
+            Some(node) => node.first_child().zip(node.last_child()),
+            None => None,
+        };
+        ChildNodes {
+            arena: &self.arena,
+            ends,
+        }
+    }
+}
+
+impl DocumentOrder<'_> {
+    /// The nodes not yet visited, in order.
+    pub fn snapshot(self) -> NodeList {
+        NodeList::new(self.collect())
+    }
+}
+
+impl ReverseDocumentOrder<'_> {
+    /// The nodes not yet visited, in order.
+    pub fn snapshot(self) -> NodeList {
+        NodeList::new(self.collect())
+    }
+}
+
+impl ChildNodes<'_> {
+    /// The children not yet visited, in order.
+    pub fn snapshot(self) -> NodeList {
+        NodeList::new(self.collect())
+    }
+}
+
+impl NodeList {
+    fn new(handles: Vec<Handle>) -> NodeList {
+        NodeList {
+            handles: handles.into_iter(),
+        }
+    }
+
+    /// The handles not yet visited, in order.
+    pub fn as_slice(&self) -> &[Handle] {
+        self.handles.as_slice()
+    }
+}
+
+/// The last node of the subtree rooted at `node` in document order.
+fn last_descendant(arena: &Arena, mut node: Handle) -> Handle {
+    while let Some(last) = arena[node].last_child() {
+        node = last;
+    }
+    node
+}
+
+impl Iterator for DocumentOrder<'_> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        let (front, back) = self.ends?;
+        self.ends = if front == back {
+            None
+        } else {
+            let mut next = self.arena[front].first_child();
+            let mut node = front;
+            while next.is_none() && node != self.root {
+                next = self.arena[node].next_sibling();
+                node = match self.arena[node].parent() {
+                    Some(parent) => parent,
+                    None => break,
+                };
+            }
+            next.map(|next| (next, back))
+        };
+        Some(front)
+    }
+}
+
+impl DoubleEndedIterator for DocumentOrder<'_> {
+    fn next_back(&mut self) -> Option<Handle> {
+        let (front, back) = self.ends?;
+        self.ends = if front == back || back == self.root {
+            None
+        } else {
+            let previous = match self.arena[back].previous_sibling() {
+                Some(sibling) => Some(last_descendant(self.arena, sibling)),
+                None => self.arena[back].parent(),
+            };
+            previous.map(|previous| (front, previous))
+        };
+        Some(back)
+    }
+}
+
+impl FusedIterator for DocumentOrder<'_> {}
+
+impl Iterator for ReverseDocumentOrder<'_> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        self.inner.next_back()
+    }
+}
+
+impl DoubleEndedIterator for ReverseDocumentOrder<'_> {
+    fn next_back(&mut self) -> Option<Handle> {
+        self.inner.next()
+    }
+}
+
+impl FusedIterator for ReverseDocumentOrder<'_> {}
+
+impl Iterator for ChildNodes<'_> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        let (front, back) = self.ends?;
+        self.ends = if front == back {
+            None
+        } else {
+            self.arena[front].next_sibling().map(|next| (next, back))
+        };
+        Some(front)
+    }
+}
+
+impl DoubleEndedIterator for ChildNodes<'_> {
+    fn next_back(&mut self) -> Option<Handle> {
+        let (front, back) = self.ends?;
+        self.ends = if front == back {
+            None
+        } else {
+            self.arena[back]
+                .previous_sibling()
+                .map(|previous| (front, previous))
+        };
+        Some(back)
+    }
+}
+
+impl FusedIterator for ChildNodes<'_> {}
+
+impl Iterator for NodeList {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        self.handles.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.handles.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for NodeList {
+    fn next_back(&mut self) -> Option<Handle> {
+        self.handles.next_back()
+    }
+}
+
+impl ExactSizeIterator for NodeList {}
+
+impl FusedIterator for NodeList {}
+
+impl fmt::Debug for DocumentOrder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DocumentOrder")
+            .field("root", &self.root)
+            .field("ends", &self.ends)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for ChildNodes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildNodes")
+            .field("ends", &self.ends)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GenerationalArenaDom, Handle, InsertPosition, Selector};
+
+    fn dom() -> GenerationalArenaDom {
+        GenerationalArenaDom::from_abbreviation("div>(p>em{a}+b)+(ul>li*2)+span{c}").unwrap()
+    }
+
+    fn select(dom: &GenerationalArenaDom, selector: &str) -> Handle {
+        dom.select(dom.document, &Selector::parse(selector).unwrap())[0]
+    }
+
+    #[test]
+    fn walks_in_document_order() {
+        let dom = dom();
+        let div = select(&dom, "div");
+        let expected: Vec<Handle> = div.descendants(&dom.arena).collect();
+        assert_eq!(expected.len(), 10);
+        assert_eq!(dom.document_order(div).collect::<Vec<_>>(), expected);
+
+        let mut reversed = expected.clone();
+        reversed.reverse();
+        assert_eq!(dom.document_order(div).rev().collect::<Vec<_>>(), reversed);
+        assert_eq!(
+            dom.reverse_document_order(div).collect::<Vec<_>>(),
+            reversed
+        );
+        assert_eq!(
+            dom.reverse_document_order(div).rev().collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    fn ends_meet() {
+        let dom = dom();
+        let div = select(&dom, "div");
+        let expected: Vec<Handle> = div.descendants(&dom.arena).collect();
+        // Take `split` nodes from the front, alternating with nodes from the back until
+        // the ends meet.
+        for split in 0..=expected.len() {
+            let mut order = dom.document_order(div);
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            loop {
+                let next = if front.len() < split {
+                    order.next().inspect(|&node| front.push(node))
+                } else {
+                    order.next_back().inspect(|&node| back.push(node))
+                };
+                if next.is_none() {
+                    break;
+                }
+            }
+            back.reverse();
+            front.extend(back);
+            assert_eq!(front, expected, "split at {}", split);
+            assert_eq!(order.next(), None);
+            assert_eq!(order.next_back(), None);
+        }
+
+        let mut order = dom.document_order(div);
+        let mut seen = Vec::new();
+        while let Some(node) = order.next() {
+            seen.push(node);
+            if let Some(node) = order.next_back() {
+                seen.push(node);
+            }
+        }
+        assert_eq!(seen.len(), expected.len());
+    }
+
+    #[test]
+    fn walks_children() {
+        let dom = dom();
+        let ul = select(&dom, "ul");
+        let items: Vec<Handle> = ul.children(&dom.arena).collect();
+        assert_eq!(dom.child_nodes(ul).collect::<Vec<_>>(), items);
+        assert_eq!(
+            dom.child_nodes(ul).rev().collect::<Vec<_>>(),
+            [items[1], items[0]]
+        );
+        let mut children = dom.child_nodes(ul);
+        assert_eq!(children.next_back(), Some(items[1]));
+        assert_eq!(children.next(), Some(items[0]));
+        assert_eq!(children.next(), None);
+        assert_eq!(children.next_back(), None);
+        assert_eq!(dom.child_nodes(items[0]).count(), 0);
+    }
+
+    #[test]
+    fn walks_single_nodes() {
+        let dom = dom();
+        let li = select(&dom, "li");
+        assert_eq!(dom.document_order(li).collect::<Vec<_>>(), [li]);
+        assert_eq!(dom.reverse_document_order(li).collect::<Vec<_>>(), [li]);
+        let mut order = dom.document_order(li);
+        assert_eq!(order.next_back(), Some(li));
+        assert_eq!(order.next(), None);
+
+        // A leaf whose siblings follow it isn't walked past.
+        let em = select(&dom, "em");
+        let text = dom.arena[em].first_child().unwrap();
+        assert_eq!(dom.document_order(text).collect::<Vec<_>>(), [text]);
+        assert_eq!(dom.document_order(em).collect::<Vec<_>>(), [em, text]);
+    }
+
+    #[test]
+    fn snapshots_are_stable() {
+        let mut dom = dom();
+        let div = select(&dom, "div");
+        let p = select(&dom, "p");
+        let ul = select(&dom, "ul");
+        let span = select(&dom, "span");
+        let text = dom.arena[span].first_child().unwrap();
+
+        let mut children = dom.child_nodes(div).snapshot();
+        assert_eq!(children.len(), 3);
+        assert_eq!(children.next(), Some(p));
+        assert_eq!(children.as_slice(), [ul, span]);
+        let mut nodes = dom.document_order(div).snapshot();
+
+        // Remove the rest of the children and add a new one while holding the snapshots.
+        for child in children.clone() {
+            dom.remove_subtree(child).unwrap();
+        }
+        let i = dom.create_html_element("i", Vec::new());
+        dom.insert_at(&[i], InsertPosition::AppendTo(div));
+
+        assert_eq!(children.collect::<Vec<_>>(), [ul, span]);
+        assert_eq!(nodes.len(), 10);
+        assert_eq!(nodes.next(), Some(div));
+        assert_eq!(nodes.next_back(), Some(text));
+        assert!(!dom.is_alive(text));
+        assert_eq!(nodes.len(), 8);
+        assert!(!nodes.any(|node| node == i));
+        assert_eq!(dom.document_order(div).count(), 6);
+        assert_eq!(dom.document_order(div).next_back(), Some(i));
+    }
+}
@@ -0,0 +1,115 @@
+//! Finding and freeing nodes that are no longer part of the document.
+
+use std::collections::HashSet;
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+impl GenerationalArenaDom {
+    /// All live nodes in the arena that can't be reached from the document node, either directly
+    /// or through template contents.
+    ///
+    /// Detaching a node (e.g. with `remove_from_parent`) leaves it and its descendants in the
+    /// arena, so these are the nodes that would otherwise leak for the lifetime of the DOM.
+    pub fn orphans(&self) -> Vec<Handle> {
+        let reachable = self.reachable();
+        self.arena
+            .iter_pairs()
+            .map(|(handle, _)| handle)
+            .filter(|handle| !reachable.contains(handle))
+            .collect()
+    }
+
+    /// Free every node returned by [`orphans`](GenerationalArenaDom::orphans), returning how
+    /// many were freed. Handles to them stop resolving afterwards. Frozen orphans are kept.
+    pub fn sweep_orphans(&mut self) -> usize {
+        let before = self.arena.count();
+        let orphans = self.orphans();
+        // The contents of orphaned templates are orphan roots themselves, but are freed along
+        // with their template.
+        let owned: HashSet<Handle> = orphans
+            .iter()
+            .filter_map(|&handle| self.template_contents_of(handle))
+            .collect();
+        let roots: Vec<Handle> = orphans
+            .into_iter()
+            .filter(|&handle| self.arena[handle].parent().is_none() && !owned.contains(&handle))
+            .collect();
+        for root in roots {
+            self.free_subtree(root);
+        }
+        before - self.arena.count()
+    }
+
+    fn reachable(&self) -> HashSet<Handle> {
+        let mut reachable = HashSet::new();
+        let mut roots = vec![self.document];
+        while let Some(root) = roots.pop() {
+            for node in root.descendants(&self.arena) {
+                roots.extend(self.template_contents_of(node));
+                reachable.insert(node);
+            }
+        }
+        reachable
+    }
+
+    /// The template contents of `handle`, if it's a template whose contents are still alive.
+    pub(crate) fn template_contents_of(&self, handle: Handle) -> Option<Handle> {
+        match self.get_node(&handle) {
+            NodeData::Element {
+                template_contents, ..
+            } => template_contents
+                .borrow()
+                .filter(|&contents| self.arena.get(contents).is_some()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::interface::TreeSink;
+
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn finds_and_sweeps_detached_nodes() {
+        let mut dom = parse_html("<p>a<b>b</b></p><p>c</p>");
+        assert!(dom.orphans().is_empty());
+        let b = dom.query_selector(dom.document, "b").unwrap().unwrap();
+        dom.remove_from_parent(&b);
+        let text = dom.arena[b].first_child().unwrap();
+        assert_eq!(dom.orphans(), [b, text]);
+        assert_eq!(dom.sweep_orphans(), 2);
+        assert!(!dom.is_alive(b));
+        assert!(dom.orphans().is_empty());
+        assert_eq!(body_html(&dom), "<p>a</p><p>c</p>");
+    }
+
+    #[test]
+    fn sweeps_orphaned_templates() {
+        let mut dom = parse_html("<template><p>a</p></template><div><template></template></div>");
+        assert!(dom.orphans().is_empty());
+        let template = dom
+            .query_selector(dom.document, "template")
+            .unwrap()
+            .unwrap();
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        dom.remove_from_parent(&template);
+        dom.remove_from_parent(&div);
+        // Each template, its contents' document node, and the paragraph and text in the first.
+        assert_eq!(dom.orphans().len(), 7);
+        assert_eq!(dom.sweep_orphans(), 7);
+        assert!(dom.orphans().is_empty());
+    }
+
+    #[test]
+    fn keeps_frozen_orphans() {
+        let mut dom = parse_html("<p>a</p>");
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        dom.remove_from_parent(&p);
+        dom.freeze(p);
+        assert_eq!(dom.sweep_orphans(), 0);
+        assert!(dom.is_alive(p));
+        assert_eq!(dom.take_frozen_skips(), [p]);
+    }
+}
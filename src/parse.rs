@@ -0,0 +1,226 @@
+//! Driving a parser into the DOM.
+//!
+//! This crate only provides the `TreeSink`; the parser itself comes from `html5ever` (or another
+//! markup5ever-based parser). APIs that need to parse take a [`Parser`], which is implemented for
+//! any closure that feeds its input to a sink and returns the finished DOM, for example
+//! `|sink, html: &str| html5ever::parse_document(sink, Default::default()).one(html)`.
+//! Fragment parsing works the same way through [`FragmentParser`].
+//!
+//! XML is parsed by [`parse_xml`](crate::parse_xml), with the `xml` feature, which runs
+//! xml5ever into the same sink and is a `Parser` itself.
+
+use markup5ever::QualName;
+#[cfg(feature = "rayon")]
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::{AbortError, DomOptions, GenerationalArenaDom};
+
+/// Something that can parse a string into a [`GenerationalArenaDom`].
+pub trait Parser {
+    /// Parse `input` into `sink` and return the finished DOM.
+    fn parse(&self, sink: GenerationalArenaDom, input: &str) -> GenerationalArenaDom;
+}
+
+impl<F> Parser for F
+where
+    F: Fn(GenerationalArenaDom, &str) -> GenerationalArenaDom,
+{
+    fn parse(&self, sink: GenerationalArenaDom, input: &str) -> GenerationalArenaDom {
+        self(sink, input)
+    }
+}
+
+/// Something that can parse a string as a fragment in the context of an element.
+///
+/// Implemented for closures such as
+/// `|sink, context: &QualName, html: &str| html5ever::parse_fragment(sink, Default::default(),
+/// context.clone(), vec![]).one(html)`. As with html5ever, the parsed nodes end up as children
+/// of an `<html>` element that is the document's only child.
+pub trait FragmentParser {
+    /// Parse `input` into `sink` as if it were the contents of a `context` element.
+    fn parse_fragment(
+        &self,
+        sink: GenerationalArenaDom,
+        context: &QualName,
+        input: &str,
+    ) -> GenerationalArenaDom;
+}
+
+impl<F> FragmentParser for F
+where
+    F: Fn(GenerationalArenaDom, &QualName, &str) -> GenerationalArenaDom,
+{
+    fn parse_fragment(
+        &self,
+        sink: GenerationalArenaDom,
+        context: &QualName,
+        input: &str,
+    ) -> GenerationalArenaDom {
+        self(sink, context, input)
+    }
+}
+
+/// Parse every document in `inputs`, building each DOM with the options returned by `options`.
+///
+/// The options factory is the place to share state across the batch, e.g. handing every DOM a
+/// clone of the same [`Interner`](crate::Interner) so repeated attribute values are stored once
+/// for the whole corpus. Documents whose parse was aborted are returned as errors.
+///
+/// DOMs can't be sent between threads (tendrils aren't `Send`), so the batch is parsed on the
+/// calling thread. To use more cores, use [`par_parse_many`] with the `rayon` feature, or split
+/// the corpus and call `parse_many` once per thread.
+pub fn parse_many<I, P, O>(
+    inputs: I,
+    parser: &P,
+    mut options: O,
+) -> Vec<Result<GenerationalArenaDom, AbortError>>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+    P: Parser + ?Sized,
+    O: FnMut() -> DomOptions,
+{
+    inputs
+        .into_iter()
+        .map(|input| {
+            let sink = GenerationalArenaDom::with_options(options());
+            parser.parse(sink, input.as_ref()).into_result()
+        })
+        .collect()
+}
+
+/// Parse every document in `inputs` in parallel on rayon's thread pool, passing each DOM to
+/// `process` on the thread that parsed it and returning the results in the order of `inputs`.
+///
+/// DOMs can't be sent between threads, so unlike [`parse_many`] this can't return them; instead
+/// `process` extracts whatever is needed, such as links or serialized text, into a value that
+/// can be. `options` is called on the parsing thread too, so an [`Interner`](crate::Interner),
+/// which can't be shared between threads, can only be shared through a thread-local.
+/// Documents whose parse was aborted are returned as errors without being processed.
+#[cfg(feature = "rayon")]
+pub fn par_parse_many<I, P, O, F, R>(
+    inputs: I,
+    parser: &P,
+    options: O,
+    process: F,
+) -> Vec<Result<R, AbortError>>
+where
+    I: IntoParallelIterator,
+    I::Iter: IndexedParallelIterator,
+    I::Item: AsRef<str>,
+    P: Parser + Sync + ?Sized,
+    O: Fn() -> DomOptions + Sync,
+    F: Fn(GenerationalArenaDom) -> R + Sync,
+    R: Send,
+{
+    inputs
+        .into_par_iter()
+        .map(|input| {
+            let sink = GenerationalArenaDom::with_options(options());
+            parser
+                .parse(sink, input.as_ref())
+                .into_result()
+                .map(&process)
+        })
+        .collect()
+}
+
+/// Parse `html` as a document with html5ever, for tests.
+#[cfg(test)]
+pub(crate) fn parse_html(html: &str) -> GenerationalArenaDom {
+    parse_html_with(html, DomOptions::default())
+}
+
+/// Parse `html` as a document into a DOM built with `opts`, for tests.
+#[cfg(test)]
+pub(crate) fn parse_html_with(html: &str, opts: DomOptions) -> GenerationalArenaDom {
+    use html5ever::tendril::TendrilSink;
+
+    html5ever::parse_document(GenerationalArenaDom::with_options(opts), Default::default())
+        .one(html)
+}
+
+/// Parse `html` as a fragment in the context of `context` with html5ever, for tests.
+#[cfg(test)]
+pub(crate) fn parse_fragment_html(
+    sink: GenerationalArenaDom,
+    context: &QualName,
+    html: &str,
+) -> GenerationalArenaDom {
+    use html5ever::tendril::TendrilSink;
+
+    html5ever::parse_fragment(sink, Default::default(), context.clone(), vec![]).one(html)
+}
+
+/// The HTML of the children of `dom`'s `<body>`, for tests.
+#[cfg(test)]
+pub(crate) fn body_html(dom: &GenerationalArenaDom) -> String {
+    let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+    dom.inner_html(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::tendril::TendrilSink;
+
+    use super::parse_many;
+    use crate::{AbortError, DomOptions, GenerationalArenaDom, Interner};
+
+    fn parse(sink: GenerationalArenaDom, html: &str) -> GenerationalArenaDom {
+        html5ever::parse_document(sink, Default::default()).one(html)
+    }
+
+    fn corpus() -> Vec<String> {
+        vec![
+            "<a href=https://example.com/a>a</a>".to_string(),
+            "<a href=https://example.com/a>b</a>".to_string(),
+            "<p>".repeat(100),
+        ]
+    }
+
+    #[test]
+    fn parses_batches() {
+        let interner = Interner::new();
+        let doms = parse_many(corpus(), &parse, || DomOptions {
+            attr_interner: Some(interner.clone()),
+            max_nodes: Some(50),
+            ..DomOptions::default()
+        });
+        assert_eq!(doms.len(), 3);
+        let text: Vec<String> = doms[..2]
+            .iter()
+            .map(|dom| {
+                let dom = dom.as_ref().unwrap();
+                dom.text_content(dom.document)
+            })
+            .collect();
+        assert_eq!(text, ["a", "b"]);
+        assert_eq!(interner.len(), 1);
+        assert_eq!(
+            doms[2].as_ref().err(),
+            Some(&AbortError::NodeLimitExceeded { limit: 50 })
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parses_batches_in_parallel() {
+        let results = super::par_parse_many(
+            corpus(),
+            &parse,
+            || DomOptions {
+                max_nodes: Some(50),
+                ..DomOptions::default()
+            },
+            |dom| dom.text_content(dom.document),
+        );
+        assert_eq!(
+            results,
+            [
+                Ok("a".to_string()),
+                Ok("b".to_string()),
+                Err(AbortError::NodeLimitExceeded { limit: 50 })
+            ]
+        );
+    }
+}
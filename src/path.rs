@@ -0,0 +1,95 @@
+//! Structural addresses of nodes.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{GenerationalArenaDom, Handle};
+
+/// The position of a node as the child indices leading to it from the document node.
+///
+/// Unlike a [`Handle`], a path only depends on the shape of the tree, so it stays meaningful
+/// across serializing and reparsing the same document. It is written as the indices joined by
+/// `/`, e.g. `1/0/3`; the document node itself has the empty path.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TreePath(pub Vec<usize>);
+
+impl fmt::Display for TreePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, index) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str("/")?;
+            }
+            write!(f, "{}", index)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TreePath {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<TreePath, Self::Err> {
+        if s.is_empty() {
+            return Ok(TreePath::default());
+        }
+        s.split('/')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map(TreePath)
+    }
+}
+
+impl GenerationalArenaDom {
+    /// The path from the document node to `handle`, or `None` if `handle` isn't in the document
+    /// (e.g. it was detached, or lives inside template contents).
+    pub fn path_of(&self, handle: Handle) -> Option<TreePath> {
+        let mut indices = vec![];
+        let mut current = handle;
+        while current != self.document {
+            let parent = self.arena.get(current)?.parent()?;
+            indices.push(current.preceding_siblings(&self.arena).count() - 1);
+            current = parent;
+        }
+        indices.reverse();
+        Some(TreePath(indices))
+    }
+
+    /// The node at `path`, if the document has one there.
+    pub fn resolve_path(&self, path: &TreePath) -> Option<Handle> {
+        path.0.iter().try_fold(self.document, |node, &index| {
+            node.children(&self.arena).nth(index)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TreePath;
+    use crate::parse::parse_html;
+
+    #[test]
+    fn paths_survive_reparsing() {
+        let html = "<!DOCTYPE html><ul><li>a</li><li>b</li></ul>";
+        let dom = parse_html(html);
+        let b = dom.select(dom.document, &"li + li".parse().unwrap())[0];
+        let path = dom.path_of(b).unwrap();
+        // Doctype, then html > body (after head) > ul > second li.
+        assert_eq!(path.to_string(), "1/1/0/1");
+        assert_eq!("1/1/0/1".parse::<TreePath>().unwrap(), path);
+
+        let reparsed = parse_html(&dom.outer_html(dom.document));
+        let resolved = reparsed.resolve_path(&path).unwrap();
+        assert_eq!(reparsed.text_content(resolved), "b");
+        assert_eq!(dom.path_of(dom.document), Some(TreePath::default()));
+        assert_eq!(dom.resolve_path(&"1/1/0/5".parse().unwrap()), None);
+        assert!("1/x".parse::<TreePath>().is_err());
+    }
+
+    #[test]
+    fn detached_nodes_have_no_path() {
+        let mut dom = parse_html("<p>a</p>");
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        p.detach(&mut dom.arena);
+        assert_eq!(dom.path_of(p), None);
+    }
+}
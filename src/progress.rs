@@ -0,0 +1,130 @@
+//! Progress reporting for long parses.
+
+use std::fmt;
+
+/// Counts of what the parser has produced so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Nodes created, including text nodes.
+    pub nodes: usize,
+
+    /// Bytes of text and attribute values received from the parser.
+    pub bytes: usize,
+}
+
+enum Interval {
+    Nodes(usize),
+    Bytes(usize),
+}
+
+/// A callback invoked periodically while a DOM is being built.
+///
+/// The callback is also invoked once with the final counts when parsing finishes.
+pub struct ProgressReporter {
+    interval: Interval,
+    next: usize,
+    callback: Box<dyn FnMut(&Progress)>,
+}
+
+impl ProgressReporter {
+    /// Invoke `callback` every time another `n` nodes have been created.
+    pub fn every_nodes(n: usize, callback: impl FnMut(&Progress) + 'static) -> ProgressReporter {
+        ProgressReporter::new(Interval::Nodes(n.max(1)), callback)
+    }
+
+    /// Invoke `callback` every time another `n` bytes of text and attribute values have been
+    /// received.
+    pub fn every_bytes(n: usize, callback: impl FnMut(&Progress) + 'static) -> ProgressReporter {
+        ProgressReporter::new(Interval::Bytes(n.max(1)), callback)
+    }
+
+    fn new(interval: Interval, callback: impl FnMut(&Progress) + 'static) -> ProgressReporter {
+        let next = match interval {
+            Interval::Nodes(n) | Interval::Bytes(n) => n,
+        };
+        ProgressReporter {
+            interval,
+            next,
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Invoke the callback if `progress` crossed the next reporting threshold.
+    pub(crate) fn update(&mut self, progress: &Progress) {
+        let (current, step) = match self.interval {
+            Interval::Nodes(n) => (progress.nodes, n),
+            Interval::Bytes(n) => (progress.bytes, n),
+        };
+        if current >= self.next {
+            (self.callback)(progress);
+            self.next = (current / step + 1) * step;
+        }
+    }
+
+    /// Invoke the callback unconditionally.
+    pub(crate) fn finish(&mut self, progress: &Progress) {
+        (self.callback)(progress);
+    }
+}
+
+impl fmt::Debug for ProgressReporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, n) = match self.interval {
+            Interval::Nodes(n) => ("nodes", n),
+            Interval::Bytes(n) => ("bytes", n),
+        };
+        f.debug_struct("ProgressReporter")
+            .field(kind, &n)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::{Progress, ProgressReporter};
+    use crate::parse::parse_html_with;
+    use crate::DomOptions;
+
+    /// The progress reported while parsing `html` with `reporter`, which records into `reports`.
+    fn reports(
+        html: &str,
+        reporter: impl FnOnce(Box<dyn FnMut(&Progress)>) -> ProgressReporter,
+    ) -> Vec<Progress> {
+        let reports = Rc::new(RefCell::new(vec![]));
+        let recorder = reports.clone();
+        parse_html_with(
+            html,
+            DomOptions {
+                progress: Some(reporter(Box::new(move |progress: &Progress| {
+                    recorder.borrow_mut().push(*progress)
+                }))),
+                ..DomOptions::default()
+            },
+        );
+        let reports = reports.borrow().clone();
+        reports
+    }
+
+    #[test]
+    fn reports_every_n_nodes() {
+        let reports = reports("<p>one</p><p>two</p><p>three</p>", |callback| {
+            ProgressReporter::every_nodes(4, callback)
+        });
+        // html, head and body, then a paragraph and its text three times, and the final count.
+        let nodes: Vec<usize> = reports.iter().map(|progress| progress.nodes).collect();
+        assert_eq!(nodes, [4, 8, 9]);
+        assert_eq!(reports.last().unwrap().bytes, 11);
+    }
+
+    #[test]
+    fn reports_every_n_bytes() {
+        let reports = reports("<a href=abcdef>ghij</a><b>klmnopqrst</b>", |callback| {
+            ProgressReporter::every_bytes(5, callback)
+        });
+        let bytes: Vec<usize> = reports.iter().map(|progress| progress.bytes).collect();
+        assert_eq!(bytes, [6, 10, 20, 20]);
+    }
+}
@@ -0,0 +1,219 @@
+//! Recording where the parser put nodes other than where their tags were, and which nodes it
+//! created without tags.
+
+use markup5ever::{namespace_url, ns, QualName};
+
+use crate::{GenerationalArenaDom, Handle};
+
+/// Whether the parser created an element from a start tag in the source, as far as can be told.
+/// See [`GenerationalArenaDom::provenance`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Provenance {
+    /// Created from a start tag, or not recorded.
+    Written,
+    /// An `<html>`, `<head>`, `<body>`, `<tbody>` or `<colgroup>` without attributes, which the
+    /// parser creates the same way whether or not its start tag was left out.
+    MaybeImplied,
+    /// Created without a start tag, as shown by the parse error that made the parser insert
+    /// it.
+    Implied,
+}
+
+impl GenerationalArenaDom {
+    /// Whether the parser [foster-parented] `handle`: content found inside a table where it
+    /// isn't allowed, such as the text of `<table>oops<tr>`, which is moved to just before the
+    /// table. Only recorded if the DOM was built with
+    /// [`DomOptions::record_provenance`](crate::DomOptions::record_provenance).
+    ///
+    /// Foster-parented text that is merged into a text node already before the table isn't
+    /// recorded.
+    ///
+    /// [foster-parented]: https://html.spec.whatwg.org/multipage/#foster-parent
+    pub fn was_foster_parented(&self, handle: Handle) -> bool {
+        self.foster_parented.contains(&handle)
+    }
+
+    /// Whether the parser moved `handle` after inserting it, as the [adoption agency
+    /// algorithm] does to fix misnested formatting elements such as `<b><p></b></p>`. Only
+    /// recorded if the DOM was built with
+    /// [`DomOptions::record_provenance`](crate::DomOptions::record_provenance).
+    ///
+    /// [adoption agency algorithm]: https://html.spec.whatwg.org/multipage/#adoption-agency-algorithm
+    pub fn was_reparented(&self, handle: Handle) -> bool {
+        self.reparented.contains(&handle)
+    }
+
+    /// Whether the parser created `handle` from a start tag in the source, as it does for most
+    /// elements, or without one, as for the `<html>`, `<head>` and `<body>` of a document that
+    /// leaves them out, the `<tbody>` of `<table><tr>`, the `<tr>` of `<tbody><td>`, and the
+    /// `<p>` of a stray `</p>`. Only recorded if the DOM was built with
+    /// [`DomOptions::record_provenance`](crate::DomOptions::record_provenance); otherwise, and
+    /// for nodes other than elements, this is [`Provenance::Written`].
+    ///
+    /// The tree builder hands the sink an implied element the same way as one with a tag, so
+    /// this is inferred. An implied `<tr>` or `<p>` is recognized by the parse error that
+    /// precedes it, and is [`Provenance::Implied`]. Leaving out the other tags isn't an error,
+    /// so an `<html>`, `<head>`, `<body>`, `<tbody>` or `<colgroup>` without attributes is
+    /// [`Provenance::MaybeImplied`], whether its tag was written or not.
+    pub fn provenance(&self, handle: Handle) -> Provenance {
+        self.implied
+            .get(&handle)
+            .copied()
+            .unwrap_or(Provenance::Written)
+    }
+
+    /// Whether the parser certainly created `handle` without a start tag, that is, whether its
+    /// [`provenance`](GenerationalArenaDom::provenance) is [`Provenance::Implied`].
+    pub fn was_implied(&self, handle: Handle) -> bool {
+        self.provenance(handle) == Provenance::Implied
+    }
+
+    /// Whether the tree builder is creating an element named `name` without a start tag, given
+    /// that it has no attributes.
+    pub(crate) fn implied_provenance(&self, name: &QualName) -> Provenance {
+        if !self.opts.record_provenance || name.ns != ns!(html) {
+            return Provenance::Written;
+        }
+        // The error reported for the token that made the tree builder insert the element.
+        let error = self
+            .parse_errors
+            .get(self.errors_before_element..)
+            .and_then(|errors| errors.last())
+            .map(|error| &*error.message);
+        match &*name.local {
+            "html" | "head" | "body" | "tbody" | "colgroup" => Provenance::MaybeImplied,
+            "tr" if error.is_some_and(|message| message.starts_with("Unexpected token")) => {
+                Provenance::Implied
+            }
+            "p" if error == Some("No <p> tag to close") => Provenance::Implied,
+            _ => Provenance::Written,
+        }
+    }
+
+    /// Record that the parser created `handle` with `provenance`.
+    pub(crate) fn record_implied(&mut self, handle: Handle, provenance: Provenance) {
+        if provenance != Provenance::Written {
+            self.implied.insert(handle, provenance);
+        }
+    }
+
+    /// Record that the parser foster-parented `handle`.
+    pub(crate) fn record_foster_parented(&mut self, handle: Handle) {
+        if self.opts.record_provenance {
+            self.foster_parented.insert(handle);
+        }
+    }
+
+    /// Record that the parser moved `handle`.
+    pub(crate) fn record_reparented(&mut self, handle: Handle) {
+        if self.opts.record_provenance {
+            self.reparented.insert(handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{parse_html, parse_html_with};
+    use crate::{DomOptions, GenerationalArenaDom, Handle, Provenance};
+
+    fn parse(html: &str) -> GenerationalArenaDom {
+        parse_html_with(
+            html,
+            DomOptions {
+                record_provenance: true,
+                ..DomOptions::default()
+            },
+        )
+    }
+
+    fn select(dom: &GenerationalArenaDom, selector: &str) -> Handle {
+        dom.query_selector(dom.document, selector).unwrap().unwrap()
+    }
+
+    #[test]
+    fn records_foster_parenting() {
+        let dom = parse("<table><p>x</table>");
+        let p = select(&dom, "p");
+        let table = select(&dom, "table");
+        assert_eq!(dom.arena[p].next_sibling(), Some(table));
+        assert!(dom.was_foster_parented(p));
+        assert!(!dom.was_foster_parented(table));
+        assert!(!dom.was_reparented(p));
+    }
+
+    #[test]
+    fn records_reparenting() {
+        let dom = parse("<b><p>x</b>y");
+        let p = select(&dom, "p");
+        let bs: Vec<Handle> = dom.query_selector_all(dom.document, "b").unwrap().collect();
+        assert_eq!(bs.len(), 2);
+        // `<b></b><p><b>x</b>y</p>`: the `<p>` and its text were moved out of the first `<b>`.
+        let x = dom.arena[bs[1]].first_child().unwrap();
+        assert!(dom.was_reparented(p));
+        assert!(dom.was_reparented(x));
+        assert!(!dom.was_reparented(bs[0]));
+        assert!(!dom.was_foster_parented(p));
+    }
+
+    #[test]
+    fn records_implied_elements() {
+        let dom = parse("<table><tr><td>x</td></tr></table></p>");
+        for maybe in ["html", "head", "body", "tbody"] {
+            let element = select(&dom, maybe);
+            assert_eq!(
+                dom.provenance(element),
+                Provenance::MaybeImplied,
+                "{}",
+                maybe
+            );
+            assert!(!dom.was_implied(element), "{}", maybe);
+        }
+        assert_eq!(dom.provenance(select(&dom, "p")), Provenance::Implied);
+        assert!(dom.was_implied(select(&dom, "p")));
+        for written in ["table", "tr", "td"] {
+            assert_eq!(
+                dom.provenance(select(&dom, written)),
+                Provenance::Written,
+                "{}",
+                written
+            );
+        }
+
+        let dom = parse("<body class=a><table><tbody><td>x</table><p>y</p>");
+        assert!(dom.was_implied(select(&dom, "tr")));
+        assert_eq!(dom.provenance(select(&dom, "body")), Provenance::Written);
+        assert!(!dom.was_implied(select(&dom, "p")));
+    }
+
+    #[test]
+    fn written_document_structure_is_not_reported_as_implied() {
+        let dom = parse(
+            "<!DOCTYPE html><html><head><title>t</title></head>\
+             <body><table><tbody><tr><td>x</td></tr></tbody></table></body></html>",
+        );
+        for element in dom.document.descendants(&dom.arena) {
+            assert!(!dom.was_implied(element));
+        }
+        for maybe in ["html", "head", "body", "tbody"] {
+            assert_eq!(
+                dom.provenance(select(&dom, maybe)),
+                Provenance::MaybeImplied,
+                "{}",
+                maybe
+            );
+        }
+        assert_eq!(dom.provenance(select(&dom, "tr")), Provenance::Written);
+    }
+
+    #[test]
+    fn records_nothing_by_default() {
+        let dom = parse_html("<table><p>x</table><b><p>x</b>y");
+        for node in dom.document.descendants(&dom.arena) {
+            assert!(!dom.was_foster_parented(node));
+            assert!(!dom.was_reparented(node));
+            assert!(!dom.was_implied(node));
+            assert_eq!(dom.provenance(node), Provenance::Written);
+        }
+    }
+}
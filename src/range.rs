@@ -0,0 +1,254 @@
+//! Ranges of text, which may span several text nodes.
+
+use std::collections::HashMap;
+
+use markup5ever::tendril::StrTendril;
+
+use crate::{GenerationalArenaDom, Handle, InsertPosition, NodeData};
+
+/// Elements whose text isn't searched: it isn't displayed, or can't contain markup.
+const UNSEARCHED_ELEMENTS: &[&str] = &[
+    "noscript", "script", "style", "template", "textarea", "title",
+];
+
+/// A position in the text of a document: a byte offset into the contents of a text node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextPoint {
+    /// The text node.
+    pub node: Handle,
+    /// The byte offset, which must be at a character boundary.
+    pub offset: usize,
+}
+
+/// The text between two points, which may be in different text nodes. The start must not come
+/// after the end in document order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextRange {
+    /// Where the range starts.
+    pub start: TextPoint,
+    /// Where the range ends, exclusive.
+    pub end: TextPoint,
+}
+
+impl GenerationalArenaDom {
+    /// The ranges of the occurrences of `needle` in the text under `root`, in document order,
+    /// not overlapping.
+    ///
+    /// Matches can span text nodes, as in `foo <b>bar</b>` for `o b`, but not block-level
+    /// elements, and text in scripts, styles, `<title>` and `<textarea>` isn't searched.
+    pub fn find_text(&self, root: Handle, needle: &str) -> Vec<TextRange> {
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        // The searchable text, and where each text node's contents start in it.
+        let mut text = String::new();
+        let mut starts: Vec<(usize, Handle)> = Vec::new();
+        let mut last_block = None;
+        for node in root.descendants(&self.arena) {
+            let contents = match self.get_node(&node) {
+                NodeData::Text { contents } => contents.borrow(),
+                _ => continue,
+            };
+            if self.is_unsearched_text(node) {
+                continue;
+            }
+            let block = node
+                .ancestors(&self.arena)
+                .skip(1)
+                .find(|&ancestor| self.is_block(ancestor));
+            if !starts.is_empty() && block != last_block {
+                // Keeps matches from spanning blocks.
+                text.push('\0');
+            }
+            last_block = block;
+            starts.push((text.len(), node));
+            text.push_str(&contents);
+        }
+
+        let point = |position: usize, at_end: bool| {
+            // At a boundary between nodes, the end of a match is in the earlier node.
+            let index = if at_end {
+                starts.partition_point(|&(start, _)| start < position)
+            } else {
+                starts.partition_point(|&(start, _)| start <= position)
+            };
+            let (start, node) = starts[index.max(1) - 1];
+            TextPoint {
+                node,
+                offset: position - start,
+            }
+        };
+        text.match_indices(needle)
+            .map(|(position, _)| TextRange {
+                start: point(position, false),
+                end: point(position + needle.len(), true),
+            })
+            .collect()
+    }
+
+    /// The text of `range`.
+    pub fn range_text(&self, range: &TextRange) -> String {
+        let mut text = String::new();
+        for (node, start, end) in self.range_segments(range) {
+            if let NodeData::Text { contents } = self.get_node(&node) {
+                text.push_str(&contents.borrow()[start..end]);
+            }
+        }
+        text
+    }
+
+    /// The text nodes in `range`, in document order, with the byte span of each in the range.
+    pub(crate) fn range_segments(&self, range: &TextRange) -> Vec<(Handle, usize, usize)> {
+        let mut segments = Vec::new();
+        let mut started = false;
+        for node in self.document.descendants(&self.arena) {
+            started |= node == range.start.node;
+            if !started {
+                continue;
+            }
+            if let NodeData::Text { contents } = self.get_node(&node) {
+                let len = contents.borrow().len();
+                let start = if node == range.start.node {
+                    range.start.offset.min(len)
+                } else {
+                    0
+                };
+                let end = if node == range.end.node {
+                    range.end.offset.min(len)
+                } else {
+                    len
+                };
+                if start < end {
+                    segments.push((node, start, end));
+                }
+            }
+            if node == range.end.node {
+                break;
+            }
+        }
+        segments
+    }
+
+    /// Whether `range` has to be left as it is because some of its text is frozen, recording
+    /// that text as skipped if so.
+    pub(crate) fn skip_frozen_range(&self, range: &TextRange) -> bool {
+        self.range_segments(range)
+            .into_iter()
+            .any(|(node, _, _)| self.skip_frozen(node))
+    }
+
+    /// Whether the text node `node` is in a script, style, `<title>`, `<textarea>` or other
+    /// element whose text isn't displayed as such.
+    pub(crate) fn is_unsearched_text(&self, node: Handle) -> bool {
+        node.ancestors(&self.arena).skip(1).any(|ancestor| {
+            UNSEARCHED_ELEMENTS
+                .iter()
+                .any(|name| self.is_html_element(ancestor, name))
+        })
+    }
+
+    /// Split the text nodes at the ends of `range` so that it covers whole text nodes, and
+    /// return them in document order.
+    ///
+    /// The first part of a split node keeps its handle, so ranges ending before `range` starts
+    /// stay valid.
+    pub(crate) fn isolate_range(&mut self, range: &TextRange) -> Vec<Handle> {
+        let mut nodes = Vec::new();
+        for (node, start, end) in self.range_segments(range).into_iter().rev() {
+            let text = match self.get_node(&node) {
+                NodeData::Text { contents } => contents.borrow().clone(),
+                _ => continue,
+            };
+            if end < text.len() {
+                let after = self.create_text(&text[end..]);
+                self.insert_at(&[after], InsertPosition::After(node));
+            }
+            let isolated = if start > 0 {
+                let isolated = self.create_text(&text[start..end]);
+                self.insert_at(&[isolated], InsertPosition::After(node));
+                isolated
+            } else {
+                node
+            };
+            let kept = if start > 0 { start } else { end };
+            if let NodeData::Text { contents } = self.get_node(&node) {
+                *contents.borrow_mut() = StrTendril::from(&text[..kept]);
+            }
+            nodes.push(isolated);
+        }
+        nodes.reverse();
+        nodes
+    }
+
+    /// `ranges` that are in the document, in document order, with those that overlap or touch
+    /// merged.
+    pub(crate) fn merge_ranges(&self, ranges: &[TextRange]) -> Vec<TextRange> {
+        let order: HashMap<Handle, usize> = self
+            .document
+            .descendants(&self.arena)
+            .enumerate()
+            .map(|(index, node)| (node, index))
+            .collect();
+        let position = |point: TextPoint| (order.get(&point.node).copied(), point.offset);
+        let mut ranges: Vec<TextRange> = ranges
+            .iter()
+            .filter(|range| order.contains_key(&range.start.node))
+            .copied()
+            .collect();
+        ranges.sort_by_key(|range| position(range.start));
+        let mut merged: Vec<TextRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if position(range.start) <= position(last.end) => {
+                    if position(range.end) > position(last.end) {
+                        last.end = range.end;
+                    }
+                }
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_html;
+
+    #[test]
+    fn finds_text_across_nodes() {
+        let dom = parse_html(
+            "<p>foo <b>bar</b> foo</p><p>o</p><p> bar</p><script>foo</script><title>foo</title>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let texts: Vec<String> = dom
+            .find_text(body, "o b")
+            .iter()
+            .map(|range| dom.range_text(range))
+            .collect();
+        assert_eq!(texts, ["o b"]);
+        assert_eq!(dom.find_text(dom.document, "foo").len(), 2);
+        assert!(dom.find_text(body, "").is_empty());
+
+        let ranges = dom.find_text(body, "bar");
+        let b = dom.query_selector(dom.document, "b").unwrap().unwrap();
+        let text = dom.arena[b].first_child().unwrap();
+        assert_eq!((ranges[0].start.node, ranges[0].start.offset), (text, 0));
+        assert_eq!((ranges[0].end.node, ranges[0].end.offset), (text, 3));
+    }
+
+    #[test]
+    fn merges_ranges() {
+        let dom = parse_html("<p>abcdef</p>");
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let mut ranges = dom.find_text(body, "cd");
+        ranges.extend(dom.find_text(body, "ab"));
+        ranges.extend(dom.find_text(body, "f"));
+        let merged: Vec<String> = dom
+            .merge_ranges(&ranges)
+            .iter()
+            .map(|range| dom.range_text(range))
+            .collect();
+        assert_eq!(merged, ["abcd", "f"]);
+    }
+}
@@ -0,0 +1,145 @@
+//! Removing nodes from the arena.
+
+use crate::{GenerationalArenaDom, Handle, MutationError};
+
+impl GenerationalArenaDom {
+    /// Remove `handle` from the tree and free it and all its descendants, including the
+    /// contents of templates, so their slots in the arena can be reused. Handles to the freed
+    /// nodes stop resolving, even once their slots are reused.
+    ///
+    /// Only detaching a node, as the parser's `remove_from_parent` does, keeps the subtree in
+    /// the arena for the lifetime of the DOM; see also
+    /// [`sweep_orphans`](GenerationalArenaDom::sweep_orphans). Does nothing if `handle` was
+    /// already freed, fails if it or anything under it is
+    /// [frozen](GenerationalArenaDom::freeze), and panics if it is the document node.
+    pub fn remove_subtree(&mut self, handle: Handle) -> Result<(), MutationError> {
+        assert!(handle != self.document, "can't remove the document node");
+        if self.is_alive(handle) {
+            self.check_removable(handle)?;
+            self.free_subtree(handle);
+        }
+        Ok(())
+    }
+
+    /// Detach `handle` and free its slot and those of all its descendants (including template
+    /// contents), so any handles to them stop resolving. Does nothing if any of them is frozen.
+    pub(crate) fn free_subtree(&mut self, handle: Handle) {
+        if self.skip_frozen_removal(handle) {
+            return;
+        }
+        self.mark_mutated();
+        handle.detach(&mut self.arena);
+        let mut doomed = vec![];
+        let mut roots = vec![handle];
+        while let Some(root) = roots.pop() {
+            for node in root.descendants(&self.arena) {
+                roots.extend(self.template_contents_of(node));
+                doomed.push(node);
+            }
+        }
+        // Descendants come after their ancestors in `doomed`, so removing in reverse order only
+        // ever removes leaves.
+        for node in doomed.into_iter().rev() {
+            self.template_owners.remove(&node);
+            node.remove(&mut self.arena);
+        }
+    }
+
+    /// Replace `handle` with its children, then free it. Does nothing if `handle` is frozen.
+    pub(crate) fn unwrap_node(&mut self, handle: Handle) {
+        if self.skip_frozen(handle) {
+            return;
+        }
+        let children: Vec<Handle> = handle.children(&self.arena).collect();
+        for child in children {
+            handle.insert_before(child, &mut self.arena);
+        }
+        self.free_subtree(handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{body_html, parse_html};
+    use crate::{Handle, MutationError};
+
+    /// The arena slot of `handle`, without its generation.
+    fn slot(handle: Handle) -> String {
+        let index = handle.to_string();
+        index[..index.find("generation").unwrap()].to_string()
+    }
+
+    #[test]
+    fn freed_handles_stop_resolving() {
+        let mut dom = parse_html("<div><p>a<b>b</b></p><template><i>t</i></template></div>");
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let text = dom.arena[p].first_child().unwrap();
+        let template = dom
+            .query_selector(dom.document, "template")
+            .unwrap()
+            .unwrap();
+        let contents = dom.template_contents_of(template).unwrap();
+        let count = dom.arena.count();
+
+        dom.remove_subtree(div).unwrap();
+        for node in [div, p, text, template, contents] {
+            assert!(!dom.is_alive(node));
+            assert!(dom.arena.get(node).is_none());
+        }
+        assert_eq!(dom.arena.count(), count - 9);
+        assert_eq!(body_html(&dom), "");
+        // Removing it again does nothing.
+        assert_eq!(dom.remove_subtree(div), Ok(()));
+
+        // New nodes reuse the freed slots with a new generation, so the old handles still
+        // don't resolve to them.
+        let new = dom.create_text("new");
+        let old = [div, p, text, template, contents]
+            .into_iter()
+            .find(|&old| slot(old) == slot(new))
+            .unwrap();
+        assert_ne!(old, new);
+        assert!(dom.is_alive(new) && !dom.is_alive(old));
+    }
+
+    #[test]
+    fn refuses_frozen_subtrees() {
+        let mut dom = parse_html("<div><p><b>b</b></p></div>");
+        let div = dom.query_selector(dom.document, "div").unwrap().unwrap();
+        let b = dom.query_selector(dom.document, "b").unwrap().unwrap();
+        dom.freeze(b);
+        assert_eq!(
+            dom.remove_subtree(div),
+            Err(MutationError::Frozen { node: b })
+        );
+        assert!(dom.is_alive(div));
+    }
+
+    #[test]
+    #[should_panic(expected = "can't remove the document node")]
+    fn refuses_to_remove_the_document() {
+        let mut dom = parse_html("<p>a</p>");
+        let document = dom.document;
+        let _ = dom.remove_subtree(document);
+    }
+
+    #[test]
+    fn unwraps_nodes_in_order() {
+        let mut dom = parse_html("<p>x<span>a<b>b</b>c</span>y</p>");
+        let span = dom.query_selector(dom.document, "span").unwrap().unwrap();
+        let b = dom.query_selector(dom.document, "b").unwrap().unwrap();
+        dom.unwrap_node(span);
+        assert!(!dom.is_alive(span));
+        assert!(dom.is_alive(b));
+        assert_eq!(body_html(&dom), "<p>xa<b>b</b>cy</p>");
+
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        dom.unwrap_node(b);
+        let children: Vec<String> = dom
+            .child_nodes(p)
+            .map(|child| dom.text_content(child))
+            .collect();
+        assert_eq!(children, ["x", "a", "b", "c", "y"]);
+    }
+}
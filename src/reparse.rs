@@ -0,0 +1,756 @@
+//! Updating a DOM after an edit to its source.
+
+use std::cmp::Reverse;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+
+use markup5ever::{namespace_url, ns};
+
+use crate::{
+    Fragment, FragmentParser, GenerationalArenaDom, Handle, MutationError, NodeData, Parser,
+};
+
+/// Elements without contents or end tags.
+const VOID: &[&str] = &[
+    "area", "base", "basefont", "bgsound", "br", "col", "embed", "frame", "hr", "img", "input",
+    "keygen", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose contents are text up to their end tag rather than markup.
+const RAW_TEXT: &[&str] = &[
+    "iframe",
+    "noembed",
+    "noframes",
+    "noscript",
+    "plaintext",
+    "script",
+    "style",
+    "textarea",
+    "title",
+    "xmp",
+];
+
+/// Elements the parser reopens after they are closed without their own end tag.
+const FORMATTING: &[&str] = &[
+    "a", "b", "big", "code", "em", "font", "i", "nobr", "s", "small", "strike", "strong", "tt", "u",
+];
+
+/// Elements that change how the markup inside them is parsed, by closing themselves or moving
+/// or dropping what they can't contain, so that what is inside them can't be parsed on its own.
+const SCOPING: &[&str] = &[
+    "a", "button", "caption", "col", "colgroup", "dd", "dt", "form", "h1", "h2", "h3", "h4", "h5",
+    "h6", "head", "li", "math", "nobr", "option", "optgroup", "p", "rb", "rp", "rt", "rtc", "ruby",
+    "select", "svg", "table", "tbody", "td", "template", "tfoot", "th", "thead", "tr",
+];
+
+/// Elements that change the document outside the region they appear in.
+const DOCUMENT_LEVEL: &[&str] = &[
+    "body",
+    "form",
+    "frame",
+    "frameset",
+    "head",
+    "html",
+    "plaintext",
+    "template",
+];
+
+/// A replacement of a byte range of the source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The byte range of the original source being replaced.
+    pub range: Range<usize>,
+    /// The text replacing it.
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// The source after applying the edit. Fails if the edit's range isn't a range of
+    /// characters in `source`.
+    pub fn apply(&self, source: &str) -> Result<String, ReparseError> {
+        let Range { start, end } = self.range;
+        if start > end || !source.is_char_boundary(start) || !source.is_char_boundary(end) {
+            return Err(ReparseError::InvalidRange {
+                range: self.range.clone(),
+                len: source.len(),
+            });
+        }
+        let mut edited = String::with_capacity(source.len() + self.replacement.len());
+        edited.push_str(&source[..start]);
+        edited.push_str(&self.replacement);
+        edited.push_str(&source[end..]);
+        Ok(edited)
+    }
+}
+
+/// What [`GenerationalArenaDom::reparse_edit`] had to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReparseOutcome {
+    /// The edit only touched the contents of one text node, which was updated in place. Every
+    /// other handle is still valid.
+    Patched(Handle),
+    /// The contents of this element were reparsed and replaced its children. Handles to the
+    /// element and to nodes outside it are still valid.
+    Spliced(Handle),
+    /// The whole document was reparsed, so all previous handles are invalid.
+    Reparsed,
+}
+
+/// The reason [`GenerationalArenaDom::reparse_edit`] refused an edit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ReparseError {
+    /// The edit's range is reversed, ends past the end of the source or splits a character.
+    InvalidRange {
+        /// The edit's range.
+        range: Range<usize>,
+        /// The length of the source, in bytes.
+        len: usize,
+    },
+
+    /// Applying the edit would change a frozen node.
+    Mutation(MutationError),
+}
+
+impl fmt::Display for ReparseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReparseError::InvalidRange { range, len } => write!(
+                f,
+                "edit range {}..{} is not a range of characters in a source of {} bytes",
+                range.start, range.end, len
+            ),
+            ReparseError::Mutation(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReparseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReparseError::Mutation(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<MutationError> for ReparseError {
+    fn from(err: MutationError) -> ReparseError {
+        ReparseError::Mutation(err)
+    }
+}
+
+/// What a piece of markup found by [`scan`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TagKind {
+    Start,
+    SelfClosing,
+    End,
+    /// A comment, doctype, processing instruction or CDATA section.
+    Other,
+}
+
+/// A piece of markup in the source.
+#[derive(Debug)]
+struct Tag {
+    kind: TagKind,
+    /// The tag name, lowercased, or empty for other markup.
+    name: String,
+    /// The names of the tag's attributes, lowercased.
+    attrs: Vec<String>,
+    /// Where the markup is in the source.
+    range: Range<usize>,
+}
+
+impl Tag {
+    /// Whether this is a start tag that opens an element with contents.
+    fn opens(&self) -> bool {
+        self.kind == TagKind::Start && !VOID.contains(&&*self.name)
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Bring this DOM, parsed from `source`, up to date with `edit`.
+    ///
+    /// The source is scanned for markup to find what the edit touched, and only that is
+    /// updated where it can be done safely:
+    ///
+    /// - An edit inside a run of plain text (no markup or character references on either side
+    ///   of the edit) that can be matched to its text node updates that node in place.
+    /// - Otherwise, the innermost element around the edit whose edited contents are
+    ///   well-formed, with every element closed by its own end tag, and away from tables,
+    ///   paragraphs, lists and other elements the parser closes or moves content out of, has
+    ///   its contents reparsed with `fragment_parser` and spliced in as its new children. The
+    ///   fragment must parse without errors.
+    ///
+    /// Anything else falls back to parsing the whole edited source with `parser`, into a DOM
+    /// with this one's options and URL.
+    ///
+    /// Fails, leaving the DOM as it is, if the edit's range isn't a range of characters in
+    /// `source`, or if the edit can't be applied without changing a
+    /// [frozen](GenerationalArenaDom::freeze) node. Reparsing the whole document replaces every
+    /// node, so it is only possible while nothing is frozen.
+    pub fn reparse_edit<P, F>(
+        &mut self,
+        source: &str,
+        edit: &TextEdit,
+        parser: &P,
+        fragment_parser: &F,
+    ) -> Result<ReparseOutcome, ReparseError>
+    where
+        P: Parser + ?Sized,
+        F: FragmentParser + ?Sized,
+    {
+        let edited = edit.apply(source)?;
+        if let Some(tags) = scan(source) {
+            if let Some(handle) = self.patch_text(source, &tags, edit)? {
+                return Ok(ReparseOutcome::Patched(handle));
+            }
+            if let Some(handle) =
+                self.splice_region(source, &tags, edit, &edited, fragment_parser)?
+            {
+                return Ok(ReparseOutcome::Spliced(handle));
+            }
+        }
+        if let Some(&node) = self.frozen.iter().find(|&&node| self.is_alive(node)) {
+            return Err(MutationError::Frozen { node }.into());
+        }
+        let mut dom = parser.parse(
+            GenerationalArenaDom::with_options(mem::take(&mut self.opts)),
+            &edited,
+        );
+        dom.url = self.url.take();
+        dom.version = self.version + 1;
+        *self = dom;
+        Ok(ReparseOutcome::Reparsed)
+    }
+
+    /// Apply `edit` to the text node made from the run of text it falls in, if that node can
+    /// be told apart from the others, returning it.
+    fn patch_text(
+        &mut self,
+        source: &str,
+        tags: &[Tag],
+        edit: &TextEdit,
+    ) -> Result<Option<Handle>, MutationError> {
+        let next = tags.partition_point(|tag| tag.range.start < edit.range.end);
+        let start = next.checked_sub(1).map_or(0, |prev| tags[prev].range.end);
+        if start > edit.range.start || next > 0 && !is_plain_text_after(&tags[next - 1]) {
+            return Ok(None);
+        }
+        let end = tags.get(next).map_or(source.len(), |tag| tag.range.start);
+        let run = &source[start..end];
+        let is_special = |c: char| c == '<' || c == '&' || c == '\r' || c == '\0';
+        if run.contains(is_special) || edit.replacement.contains(is_special) {
+            return Ok(None);
+        }
+        let mut patched = run.to_string();
+        patched.replace_range(
+            edit.range.start - start..edit.range.end - start,
+            &edit.replacement,
+        );
+        // Whitespace-only text may be dropped or moved by the parser.
+        if run.trim().is_empty() || patched.trim().is_empty() {
+            return Ok(None);
+        }
+
+        // Match the run to the text nodes with the same contents, in order, as long as there
+        // is one for every such run; only foster parenting out of tables reorders text.
+        let runs: Vec<usize> = (0..=tags.len())
+            .filter(|&index| index == 0 || is_plain_text_after(&tags[index - 1]))
+            .map(|index| index.checked_sub(1).map_or(0, |prev| tags[prev].range.end))
+            .filter(|&other| {
+                let other_end = tags
+                    .iter()
+                    .find(|tag| tag.range.start >= other)
+                    .map_or(source.len(), |tag| tag.range.start);
+                &source[other..other_end] == run
+            })
+            .collect();
+        let nodes: Vec<Handle> = self
+            .document
+            .descendants(&self.arena)
+            .filter(|&node| match self.get_node(&node) {
+                NodeData::Text { contents } => {
+                    &**contents.borrow() == run && !self.in_raw_text(node)
+                }
+                _ => false,
+            })
+            .collect();
+        if runs.len() != nodes.len()
+            || nodes.len() > 1 && tags.iter().any(|tag| tag.name == "table")
+        {
+            return Ok(None);
+        }
+        let handle = match runs.iter().position(|&other| other == start) {
+            Some(index) => nodes[index],
+            None => return Ok(None),
+        };
+        self.check_mutable(handle)?;
+        if let NodeData::Text { contents } = self.get_node(&handle) {
+            *contents.borrow_mut() = patched.into();
+        }
+        self.mark_mutated();
+        Ok(Some(handle))
+    }
+
+    /// Reparse the contents of the innermost element around `edit` that can be parsed on their
+    /// own, and replace its children with them, returning the element.
+    fn splice_region<F>(
+        &mut self,
+        source: &str,
+        tags: &[Tag],
+        edit: &TextEdit,
+        edited: &str,
+        parser: &F,
+    ) -> Result<Option<Handle>, MutationError>
+    where
+        F: FragmentParser + ?Sized,
+    {
+        let (spans, reopened) = element_spans(tags);
+        let mut around: Vec<(usize, usize)> = spans
+            .into_iter()
+            .filter(|&(start, end)| {
+                tags[start].range.end <= edit.range.start && edit.range.end <= tags[end].range.start
+            })
+            .collect();
+        around.sort_unstable_by_key(|&(start, _)| Reverse(start));
+
+        for (depth, &(start, end)) in around.iter().enumerate() {
+            let name = &*tags[start].name;
+            if SCOPING.contains(&name)
+                || RAW_TEXT.contains(&name)
+                || ["body", "frameset", "html", "listing", "pre"].contains(&name)
+                || reopened < tags[start].range.start
+                || around[depth + 1..]
+                    .iter()
+                    .any(|&(outer, _)| SCOPING.contains(&&*tags[outer].name))
+            {
+                continue;
+            }
+            let contents_end = tags[end].range.start + edited.len() - source.len();
+            let contents = &edited[tags[start].range.end..contents_end];
+            if !is_well_formed(contents) {
+                continue;
+            }
+            let handle = match self.source_element(tags, start) {
+                Some(handle) => handle,
+                None => continue,
+            };
+            let nested = handle.ancestors(&self.arena).skip(1).any(|ancestor| {
+                self.element_name(ancestor)
+                    .is_some_and(|name| name.ns != ns!(html) || SCOPING.contains(&&*name.local))
+            });
+            if nested {
+                continue;
+            }
+            let context = self.element_name(handle).cloned().expect("an element");
+            let fragment = Fragment::parse(&context, contents, parser);
+            if !fragment.dom().errors.is_empty() {
+                continue;
+            }
+            self.replace_children(handle, fragment)?;
+            return Ok(Some(handle));
+        }
+        Ok(None)
+    }
+
+    /// The element made from the start tag `tags[index]`, found by counting the HTML elements
+    /// of its name, if there is one for every such start tag and it has the same attributes.
+    fn source_element(&self, tags: &[Tag], index: usize) -> Option<Handle> {
+        let tag = &tags[index];
+        let same_name = |other: &&Tag| other.kind != TagKind::End && other.name == tag.name;
+        let elements: Vec<Handle> = self
+            .document
+            .descendants(&self.arena)
+            .filter(|&node| self.is_html_element(node, &tag.name))
+            .collect();
+        if elements.len() != tags.iter().filter(same_name).count() {
+            return None;
+        }
+        let handle = elements[tags[..index].iter().filter(same_name).count()];
+        let mut attrs: Vec<String> = self
+            .as_element(handle)?
+            .attrs()
+            .iter()
+            .map(|attr| attr.name.local.to_string())
+            .collect();
+        let mut expected = tag.attrs.clone();
+        attrs.sort_unstable();
+        expected.sort_unstable();
+        (attrs == expected).then_some(handle)
+    }
+
+    /// Whether `handle` is inside an element whose text isn't parsed as markup.
+    fn in_raw_text(&self, handle: Handle) -> bool {
+        self.arena[handle].parent().is_some_and(|parent| {
+            RAW_TEXT
+                .iter()
+                .any(|name| self.is_html_element(parent, name))
+        })
+    }
+}
+
+/// Whether the text after `tag` is parsed as markup and kept as it is.
+fn is_plain_text_after(tag: &Tag) -> bool {
+    // `<pre>`, `<listing>` and `<textarea>` drop a newline at the start of their contents.
+    !(tag.kind == TagKind::Start
+        && (RAW_TEXT.contains(&&*tag.name) || tag.name == "pre" || tag.name == "listing"))
+}
+
+/// The markup in `source`, in order, or `None` if some of it runs to the end of the source,
+/// such as an unterminated tag or comment, which the parser treats specially.
+fn scan(source: &str) -> Option<Vec<Tag>> {
+    let mut tags = Vec::new();
+    let mut i = 0;
+    while let Some(offset) = source[i..].find('<') {
+        let start = i + offset;
+        let rest = &source[start..];
+        let end = if rest.starts_with("<!-->") {
+            start + 5
+        } else if rest.starts_with("<!--->") {
+            start + 6
+        } else if let Some(comment) = rest.strip_prefix("<!--") {
+            start + 4 + comment.find("-->")? + 3
+        } else if rest.starts_with("<![CDATA[") {
+            start + rest.find("]]>")? + 3
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            start + rest.find('>')? + 1
+        } else if rest.starts_with("</") || rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let tag = scan_tag(source, start)?;
+            i = tag.range.end;
+            if tag.kind == TagKind::Start && RAW_TEXT.contains(&&*tag.name) {
+                i += find_end_tag(&source[i..], &tag.name)?;
+            }
+            tags.push(tag);
+            continue;
+        } else {
+            i = start + 1;
+            continue;
+        };
+        tags.push(Tag {
+            kind: TagKind::Other,
+            name: String::new(),
+            attrs: Vec::new(),
+            range: start..end,
+        });
+        i = end;
+    }
+    Some(tags)
+}
+
+/// The tag at `start` in `source`, which is `</` or `<` followed by a letter.
+fn scan_tag(source: &str, start: usize) -> Option<Tag> {
+    let bytes = source.as_bytes();
+    let is_name_end = |c: char| c.is_ascii_whitespace() || c == '/' || c == '>';
+    let mut i = start + 1;
+    let end_tag = bytes[i] == b'/';
+    if end_tag {
+        i += 1;
+        if !bytes.get(i)?.is_ascii_alphabetic() {
+            // `</>` is dropped, and `</` followed by anything else starts a bogus comment.
+            return Some(Tag {
+                kind: TagKind::Other,
+                name: String::new(),
+                attrs: Vec::new(),
+                range: start..i + source[i..].find('>')? + 1,
+            });
+        }
+    }
+    let name_end = i + source[i..].find(is_name_end)?;
+    let name = source[i..name_end].to_ascii_lowercase();
+    i = name_end;
+
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+    loop {
+        match *bytes.get(i)? {
+            b'>' => break,
+            b'/' => {
+                self_closing = bytes.get(i + 1) == Some(&b'>');
+                i += 1;
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            _ => {
+                let attr_end =
+                    i + 1 + source[i + 1..].find(|c: char| is_name_end(c) || c == '=')?;
+                attrs.push(source[i..attr_end].to_ascii_lowercase());
+                i = attr_end + source[attr_end..].find(|c: char| !c.is_ascii_whitespace())?;
+                if bytes[i] == b'=' {
+                    i += 1;
+                    i += source[i..].find(|c: char| !c.is_ascii_whitespace())?;
+                    i = match bytes[i] {
+                        quote @ (b'"' | b'\'') => i + 1 + source[i + 1..].find(quote as char)? + 1,
+                        b'>' => i,
+                        _ => i + source[i..].find(|c: char| c.is_ascii_whitespace() || c == '>')?,
+                    };
+                }
+            }
+        }
+    }
+    let kind = match (end_tag, self_closing) {
+        (true, _) => TagKind::End,
+        (false, true) => TagKind::SelfClosing,
+        (false, false) => TagKind::Start,
+    };
+    Some(Tag {
+        kind,
+        name,
+        attrs,
+        range: start..i + 1,
+    })
+}
+
+/// Where the end tag of the raw text element `name` is in `text`, the text after its start tag.
+fn find_end_tag(text: &str, name: &str) -> Option<usize> {
+    let lower = text.to_ascii_lowercase();
+    let end_tag = format!("</{}", name);
+    let mut from = 0;
+    while let Some(offset) = lower[from..].find(&end_tag) {
+        let at = from + offset;
+        match lower.as_bytes().get(at + end_tag.len()) {
+            Some(&c) if c.is_ascii_whitespace() || c == b'/' || c == b'>' => return Some(at),
+            _ => from = at + 1,
+        }
+    }
+    None
+}
+
+/// The elements of the source with both a start and an end tag, as pairs of indexes into
+/// `tags`, and where the first formatting element closed without its own end tag was closed.
+/// After that point the parser may reopen the element in places the source doesn't show.
+fn element_spans(tags: &[Tag]) -> (Vec<(usize, usize)>, usize) {
+    let mut open: Vec<usize> = Vec::new();
+    let mut spans = Vec::new();
+    let mut reopened = usize::MAX;
+    for (index, tag) in tags.iter().enumerate() {
+        if tag.opens() {
+            open.push(index);
+        } else if tag.kind == TagKind::End {
+            if let Some(depth) = open.iter().rposition(|&start| tags[start].name == tag.name) {
+                if open[depth + 1..]
+                    .iter()
+                    .any(|&start| FORMATTING.contains(&&*tags[start].name))
+                {
+                    reopened = reopened.min(tag.range.start);
+                }
+                spans.push((open[depth], index));
+                open.truncate(depth);
+            }
+        }
+    }
+    (spans, reopened)
+}
+
+/// Whether `contents` parse the same on their own as in place: every element in them is closed
+/// by its own end tag, in order, and none of them affects the document outside them.
+fn is_well_formed(contents: &str) -> bool {
+    let tags = match scan(contents) {
+        Some(tags) => tags,
+        None => return false,
+    };
+    let mut open = Vec::new();
+    for tag in &tags {
+        if DOCUMENT_LEVEL.contains(&&*tag.name) {
+            return false;
+        }
+        let foreign = open.iter().any(|&name| name == "svg" || name == "math");
+        match tag.kind {
+            _ if tag.opens() => open.push(&*tag.name),
+            TagKind::SelfClosing if !foreign && !VOID.contains(&&*tag.name) => return false,
+            TagKind::End if open.pop() != Some(&*tag.name) => return false,
+            _ => {}
+        }
+    }
+    open.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::tendril::TendrilSink;
+    use markup5ever::QualName;
+
+    use super::{ReparseError, ReparseOutcome, TextEdit};
+    use crate::parse::body_html;
+    use crate::{DomOptions, GenerationalArenaDom, MutationError};
+
+    fn parse(sink: GenerationalArenaDom, html: &str) -> GenerationalArenaDom {
+        html5ever::parse_document(sink, Default::default()).one(html)
+    }
+
+    fn parse_fragment(
+        sink: GenerationalArenaDom,
+        context: &QualName,
+        html: &str,
+    ) -> GenerationalArenaDom {
+        html5ever::parse_fragment(sink, Default::default(), context.clone(), vec![]).one(html)
+    }
+
+    /// Apply the edit replacing the first `old` in `source` with `new` to `dom`, checking that
+    /// the result matches parsing the edited source from scratch.
+    fn reparse(
+        dom: &mut GenerationalArenaDom,
+        source: &str,
+        old: &str,
+        new: &str,
+    ) -> Result<ReparseOutcome, ReparseError> {
+        let start = source.find(old).unwrap();
+        let edit = TextEdit {
+            range: start..start + old.len(),
+            replacement: new.to_string(),
+        };
+        let outcome = dom.reparse_edit(source, &edit, &parse, &parse_fragment)?;
+        let expected = parse(
+            GenerationalArenaDom::default(),
+            &edit.apply(source).unwrap(),
+        );
+        assert_eq!(body_html(dom), body_html(&expected));
+        Ok(outcome)
+    }
+
+    fn id(dom: &GenerationalArenaDom, id: &str) -> crate::Handle {
+        dom.query_selector(dom.document, &format!("#{}", id))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn patches_text_runs() {
+        let source = "<p id=a>Hello world</p><p>Hello</p>";
+        let mut dom = parse(GenerationalArenaDom::default(), source);
+        let a = id(&dom, "a");
+        let text = dom.arena[a].first_child().unwrap();
+        let outcome = reparse(&mut dom, source, "world", "there").unwrap();
+        assert_eq!(outcome, ReparseOutcome::Patched(text));
+
+        // The second of two identical runs.
+        let source = "<p>Hi</p><p id=b>Hi</p>";
+        let mut dom = parse(GenerationalArenaDom::default(), source);
+        let text = dom.arena[id(&dom, "b")].first_child().unwrap();
+        let edit = TextEdit {
+            range: 17..19,
+            replacement: "Bye".to_string(),
+        };
+        let outcome = dom.reparse_edit(source, &edit, &parse, &parse_fragment);
+        assert_eq!(outcome, Ok(ReparseOutcome::Patched(text)));
+        assert_eq!(body_html(&dom), "<p>Hi</p><p id=\"b\">Bye</p>");
+    }
+
+    #[test]
+    fn does_not_patch_text_from_attribute_values() {
+        // The `>` in the title isn't the end of the tag, so "x" is part of the attribute, not
+        // the text node that reads "x" elsewhere.
+        let source = "<div id=a><a title=\"1>x\">link</a></div><p>x</p>";
+        let mut dom = parse(GenerationalArenaDom::default(), source);
+        let a = id(&dom, "a");
+        let outcome = reparse(&mut dom, source, "x\"", "y\"").unwrap();
+        assert_eq!(outcome, ReparseOutcome::Spliced(a));
+        assert!(body_html(&dom).ends_with("<p>x</p>"));
+    }
+
+    #[test]
+    fn splices_regions() {
+        let source = "<div id=a><span>one</span> &amp; <em>two</em></div><div id=b>three</div>";
+        let mut dom = parse(GenerationalArenaDom::default(), source);
+        let (a, b) = (id(&dom, "a"), id(&dom, "b"));
+        let three = dom.arena[b].first_child().unwrap();
+        let outcome = reparse(&mut dom, source, "<em>two</em>", "<b>2</b>").unwrap();
+        assert_eq!(outcome, ReparseOutcome::Spliced(a));
+        assert_eq!(dom.arena[b].first_child(), Some(three));
+
+        // Markup the fragment can't hold on its own falls back to the enclosing element.
+        let source = "<section id=s><div><span>x</span></div></section>";
+        let mut dom = parse(GenerationalArenaDom::default(), source);
+        let s = id(&dom, "s");
+        let outcome = reparse(&mut dom, source, "</span>", "</span></div><div>").unwrap();
+        assert_eq!(outcome, ReparseOutcome::Spliced(s));
+    }
+
+    #[test]
+    fn reparses_when_regions_are_unsafe() {
+        for (source, old, new) in [
+            (
+                "<table><tr><td id=a>x<b>y</b></td></tr></table>",
+                "<b>",
+                "<i>",
+            ),
+            ("<p>one<span>two</span></p>", "two", "<div>two</div>"),
+            (
+                "<div><p><b>x</p><div>y<i>z</i></div></div>",
+                "z",
+                "<s>z</s>",
+            ),
+            ("<div>a<span>b</span></div>", "<span>", "<span><p>"),
+        ] {
+            let mut dom = parse(GenerationalArenaDom::default(), source);
+            assert_eq!(
+                reparse(&mut dom, source, old, new),
+                Ok(ReparseOutcome::Reparsed),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_options_and_url() {
+        let source = "<p>one</p>";
+        let mut dom = parse(
+            GenerationalArenaDom::with_options(DomOptions {
+                record_lines: true,
+                ..DomOptions::default()
+            }),
+            source,
+        );
+        dom.set_url("https://example.com/");
+        let version = dom.version();
+        let outcome = reparse(&mut dom, source, "<p>", "<p>\n<b>").unwrap();
+        assert_eq!(outcome, ReparseOutcome::Reparsed);
+        assert_eq!(dom.url(), Some("https://example.com/"));
+        let b = dom.query_selector(dom.document, "b").unwrap().unwrap();
+        assert_eq!(dom.line_of(b), Some(2));
+        assert!(dom.version() > version);
+    }
+
+    #[test]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn rejects_invalid_ranges() {
+        let source = "<p>café</p>";
+        let mut dom = parse(GenerationalArenaDom::default(), source);
+        for range in [4..20, 7..6, 6..7] {
+            let edit = TextEdit {
+                range: range.clone(),
+                replacement: String::new(),
+            };
+            assert_eq!(
+                dom.reparse_edit(source, &edit, &parse, &parse_fragment),
+                Err(ReparseError::InvalidRange {
+                    range,
+                    len: source.len()
+                })
+            );
+        }
+        assert_eq!(body_html(&dom), "<p>café</p>");
+    }
+
+    #[test]
+    fn refuses_to_change_frozen_nodes() {
+        let source = "<div id=a><span>x</span></div><p id=b>y</p>";
+        let mut dom = parse(GenerationalArenaDom::default(), source);
+        dom.freeze(id(&dom, "a"));
+        let edit = TextEdit {
+            range: 16..17,
+            replacement: "<i>z</i>".to_string(),
+        };
+        let outcome = dom.reparse_edit(source, &edit, &parse, &parse_fragment);
+        assert!(matches!(
+            outcome,
+            Err(ReparseError::Mutation(MutationError::Frozen { .. }))
+        ));
+        // Edits elsewhere still apply.
+        let outcome = reparse(&mut dom, source, "y", "z").unwrap();
+        assert!(matches!(outcome, ReparseOutcome::Patched(_)));
+    }
+}
@@ -0,0 +1,173 @@
+//! Find and replace over the text of a document.
+
+use std::ops::Range;
+
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Something [`GenerationalArenaDom::replace_text`] can search for.
+///
+/// It is implemented for literal strings and characters, and for any search function wrapped
+/// in [`FindWith`]. To replace by regular expression, wrap the `regex` crate's `find_at`:
+/// `FindWith(|text: &str, start| regex.find_at(text, start).map(|found| found.range()))`.
+pub trait TextPattern {
+    /// The byte range of the first match in `haystack` that starts at or after `start`, which
+    /// is at a character boundary. A match must not be empty.
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>>;
+}
+
+impl TextPattern for str {
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        if self.is_empty() {
+            return None;
+        }
+        let offset = haystack[start..].find(self)? + start;
+        Some(offset..offset + self.len())
+    }
+}
+
+impl TextPattern for String {
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        self.as_str().find_at(haystack, start)
+    }
+}
+
+impl TextPattern for char {
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        let offset = haystack[start..].find(*self)? + start;
+        Some(offset..offset + self.len_utf8())
+    }
+}
+
+impl<P: TextPattern + ?Sized> TextPattern for &P {
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        (**self).find_at(haystack, start)
+    }
+}
+
+/// A [`TextPattern`] that searches with a function, which is given the haystack and the
+/// offset to search from and returns the byte range of the first match at or after it, as
+/// [`TextPattern::find_at`] does.
+#[derive(Clone, Copy, Debug)]
+pub struct FindWith<F>(pub F);
+
+impl<F> TextPattern for FindWith<F>
+where
+    F: Fn(&str, usize) -> Option<Range<usize>>,
+{
+    fn find_at(&self, haystack: &str, start: usize) -> Option<Range<usize>> {
+        (self.0)(haystack, start)
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Replace every match of `pattern` in the text under `scope` with `replacement`, returning
+    /// how many were replaced.
+    ///
+    /// Only the contents of text nodes change; markup is left alone, so a match can't span an
+    /// element boundary, and `o b` isn't found in `foo <b>bar</b>`. Runs of adjacent text
+    /// nodes, as mutations can leave behind, are searched as one text, and a run with a match
+    /// is merged into its first node. Text in scripts, styles, `<title>` and `<textarea>` isn't
+    /// searched, as in [`find_text`](GenerationalArenaDom::find_text), and frozen text is left
+    /// alone.
+    pub fn replace_text<P>(&mut self, pattern: &P, replacement: &str, scope: Handle) -> usize
+    where
+        P: TextPattern + ?Sized,
+    {
+        let span = TraceSpan::transform("replace_text");
+        // Runs of adjacent text nodes, each as the handles of its nodes.
+        let mut runs: Vec<Vec<Handle>> = Vec::new();
+        for node in scope.descendants(&self.arena) {
+            if !matches!(self.get_node(&node), NodeData::Text { .. })
+                || self.is_unsearched_text(node)
+            {
+                continue;
+            }
+            let follows_text = self.arena[node]
+                .previous_sibling()
+                .is_some_and(|previous| matches!(self.get_node(&previous), NodeData::Text { .. }));
+            match runs.last_mut() {
+                Some(run) if follows_text => run.push(node),
+                _ => runs.push(vec![node]),
+            }
+        }
+
+        let mut replaced = 0;
+        for run in runs {
+            let text: String = run
+                .iter()
+                .map(|&node| match self.get_node(&node) {
+                    NodeData::Text { contents } => contents.borrow().to_string(),
+                    _ => String::new(),
+                })
+                .collect();
+            let mut result = String::with_capacity(text.len());
+            let mut position = 0;
+            let mut count = 0;
+            while let Some(found) = pattern.find_at(&text, position) {
+                if found.is_empty() {
+                    break;
+                }
+                result.push_str(&text[position..found.start]);
+                result.push_str(replacement);
+                position = found.end;
+                count += 1;
+            }
+            if count == 0 || run.iter().any(|&node| self.skip_frozen(node)) {
+                continue;
+            }
+            result.push_str(&text[position..]);
+            if let NodeData::Text { contents } = self.get_node(&run[0]) {
+                *contents.borrow_mut() = StrTendril::from(result);
+            }
+            for &node in &run[1..] {
+                self.free_subtree(node);
+            }
+            replaced += count;
+        }
+        if replaced > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), replaced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::FindWith;
+    use crate::GenerationalArenaDom;
+
+    /// The first run of ASCII digits at or after `start`.
+    fn digits(text: &str, start: usize) -> Option<Range<usize>> {
+        let from = start + text[start..].find(|c: char| c.is_ascii_digit())?;
+        let len = text[from..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len() - from);
+        Some(from..from + len)
+    }
+
+    #[test]
+    fn replaces_literals_and_functions() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("p{a1b22}+p{x-y}+script{1}").unwrap();
+        let document = dom.document;
+        assert_eq!(dom.replace_text(&FindWith(digits), "#", document), 2);
+        assert_eq!(dom.replace_text("-", "+", document), 1);
+        assert_eq!(dom.replace_text(&'x', "z", document), 1);
+        assert_eq!(dom.text_content(document), "a#b#z+y1");
+    }
+
+    #[test]
+    fn leaves_frozen_text_alone() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("p{one}+p{one}").unwrap();
+        let document = dom.document;
+        let first = dom.document_element().unwrap();
+        dom.freeze(first);
+        assert_eq!(dom.replace_text("one", "two", document), 1);
+        assert_eq!(dom.text_content(document), "onetwo");
+        assert_eq!(dom.take_frozen_skips().len(), 1);
+    }
+}
@@ -0,0 +1,111 @@
+//! What the parser recorded about scripts and its position in the input.
+
+use crate::{GenerationalArenaDom, Handle};
+
+impl GenerationalArenaDom {
+    /// Whether the parser marked the `<script>` element `handle` as ["already started"], e.g.
+    /// because it was inserted by `document.write` or parsed as a fragment. Such scripts must
+    /// not be run.
+    ///
+    /// ["already started"]: https://html.spec.whatwg.org/multipage/#already-started
+    pub fn script_already_started(&self, handle: Handle) -> bool {
+        self.started_scripts.contains(&handle)
+    }
+
+    /// The `<script>` elements the parser finished, in the order it finished them, which is the
+    /// order a browser would run them in.
+    ///
+    /// The DOM never runs scripts, so the parser always continues after a script.
+    pub fn completed_scripts(&self) -> &[Handle] {
+        &self.completed_scripts
+    }
+
+    /// The line of the input the parser most recently reported, starting at 1.
+    pub fn current_line(&self) -> u64 {
+        self.current_line
+    }
+
+    /// The line of the input the node `handle` started on, if the DOM was built with
+    /// [`DomOptions::record_lines`](crate::DomOptions::record_lines) and the node was created by
+    /// the parser.
+    ///
+    /// This is the line the parser reported when it created the node, which for elements is the
+    /// line their start tag ended on.
+    pub fn line_of(&self, handle: Handle) -> Option<u64> {
+        self.lines.get(&handle).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::interface::tree_builder::{NextParserState, TreeSink};
+    use markup5ever::{namespace_url, ns, QualName};
+
+    use crate::parse::{parse_fragment_html, parse_html, parse_html_with};
+    use crate::{DomOptions, GenerationalArenaDom};
+
+    #[test]
+    fn records_started_scripts() {
+        let dom = parse_html("<script>run()</script><p>x");
+        let script = dom.query_selector(dom.document, "script").unwrap().unwrap();
+        assert!(!dom.script_already_started(script));
+
+        let dom = parse_html("<p>x<script>unterminated(");
+        let script = dom.query_selector(dom.document, "script").unwrap().unwrap();
+        assert!(dom.script_already_started(script));
+
+        let context = QualName::new(None, ns!(html), "div".into());
+        let dom = parse_fragment_html(
+            GenerationalArenaDom::default(),
+            &context,
+            "<script>run()</script>",
+        );
+        let script = dom.query_selector(dom.document, "script").unwrap().unwrap();
+        assert!(dom.script_already_started(script));
+    }
+
+    #[test]
+    fn records_completed_scripts() {
+        let mut dom = parse_html("<script>a()</script><script>b()</script>");
+        assert!(dom.completed_scripts().is_empty());
+        let scripts = dom.select(dom.document, &"script".parse().unwrap());
+        for script in scripts.iter().rev() {
+            assert!(matches!(
+                dom.complete_script(script),
+                NextParserState::Continue
+            ));
+        }
+        assert_eq!(dom.completed_scripts(), [scripts[1], scripts[0]]);
+    }
+
+    #[test]
+    fn tracks_the_current_line() {
+        assert_eq!(GenerationalArenaDom::default().current_line(), 1);
+        let dom = parse_html("<p>a</p>\n<p>b</p>\n\n<p>c</p>");
+        assert_eq!(dom.current_line(), 4);
+    }
+
+    #[test]
+    fn records_the_lines_nodes_start_on() {
+        let html = "<!-- a -->\n<p>one\ntwo</p>\n\n<div\nid=x>three</div>";
+        let dom = parse_html_with(
+            html,
+            DomOptions {
+                record_lines: true,
+                ..DomOptions::default()
+            },
+        );
+        let find = |selector: &str| dom.query_selector(dom.document, selector).unwrap().unwrap();
+        let (p, div) = (find("p"), find("div"));
+        let comment = dom.document.children(&dom.arena).next().unwrap();
+        assert_eq!(dom.line_of(comment), Some(1));
+        assert_eq!(dom.line_of(p), Some(2));
+        assert_eq!(dom.line_of(dom.arena[p].first_child().unwrap()), Some(2));
+        assert_eq!(dom.line_of(div), Some(6));
+        assert_eq!(dom.line_of(dom.document), None);
+
+        let dom = parse_html(html);
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        assert_eq!(dom.line_of(p), None);
+    }
+}
@@ -0,0 +1,949 @@
+//! CSS selectors, parsed and matched by the [selectors](https://docs.rs/selectors) crate that
+//! Servo uses.
+//!
+//! Supports the selectors of [Selectors Level 3], plus `:is()` and `:where()`, `:not()` with
+//! selector lists, `:nth-child(an+b of S)`, the `i` and `s` attribute flags, and `:any-link`,
+//! `:link`, `:checked`, `:enabled`, `:disabled` and `:lang()`. In quirks mode documents, class
+//! and id selectors match ASCII case-insensitively, as in browsers.
+//!
+//! Dynamic pseudo-classes (`:hover`, `:active`, `:focus`, `:visited` and `:target`) parse but
+//! never match, as a document nobody is interacting with has no such state. Namespace prefixes
+//! and pseudo-elements aren't supported.
+//!
+//! [`ElementRef`] implements [`selectors::Element`], with [`DomSelectorImpl`], so selectors
+//! parsed with the `selectors` crate directly can be matched against the DOM too.
+//!
+//! [Selectors Level 3]: https://www.w3.org/TR/selectors-3/
+
+use std::cell::Cell;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::str::FromStr;
+
+use cssparser::{
+    match_ignore_ascii_case, serialize_identifier, serialize_string, BasicParseErrorKind, CowRcStr,
+    ParseError, ParseErrorKind, ParserInput, SourceLocation, ToCss,
+};
+use markup5ever::{namespace_url, ns, LocalName, Namespace};
+use selectors::attr::{
+    AttrSelectorOperation, AttrSelectorOperator, CaseSensitivity, NamespaceConstraint,
+};
+use selectors::context::{
+    IgnoreNthChildForInvalidation, MatchingContext, MatchingMode, NeedsSelectorFlags, QuirksMode,
+};
+use selectors::matching::{matches_selector, matches_selector_list, ElementSelectorFlags};
+use selectors::parser::{ParseRelative, SelectorParseErrorKind};
+use selectors::{NthIndexCache, OpaqueElement, SelectorList};
+
+use crate::trace::TraceSpan;
+use crate::{DocumentOrder, ElementRef, GenerationalArenaDom, Handle, NodeData};
+
+/// An error from parsing a selector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectorError {
+    /// The byte offset in the selector at which parsing failed.
+    pub position: usize,
+    /// What went wrong.
+    pub message: &'static str,
+}
+
+impl fmt::Display for SelectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for SelectorError {}
+
+impl SelectorError {
+    fn new(selector: &str, err: ParseError<'_, SelectorParseErrorKind<'_>>) -> SelectorError {
+        let message = match err.kind {
+            ParseErrorKind::Basic(BasicParseErrorKind::EndOfInput) => "unexpected end of selector",
+            ParseErrorKind::Basic(_) => "unexpected token",
+            ParseErrorKind::Custom(kind) => match kind {
+                SelectorParseErrorKind::EmptySelector => "expected a selector",
+                SelectorParseErrorKind::DanglingCombinator => {
+                    "expected a selector after a combinator"
+                }
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(_) => {
+                    "unsupported pseudo-class or pseudo-element"
+                }
+                SelectorParseErrorKind::ExpectedNamespace(_)
+                | SelectorParseErrorKind::ExplicitNamespaceUnexpectedToken(_) => {
+                    "namespace prefixes aren't supported"
+                }
+                SelectorParseErrorKind::NoQualifiedNameInAttributeSelector(_)
+                | SelectorParseErrorKind::UnexpectedTokenInAttributeSelector(_)
+                | SelectorParseErrorKind::ExpectedBarInAttr(_)
+                | SelectorParseErrorKind::BadValueInAttr(_)
+                | SelectorParseErrorKind::InvalidQualNameInAttr(_) => "invalid attribute selector",
+                _ => "invalid selector",
+            },
+        };
+        SelectorError {
+            position: byte_offset(selector, err.location),
+            message,
+        }
+    }
+}
+
+/// The byte offset in `input` of `location`, whose column counts UTF-16 code units from 1.
+fn byte_offset(input: &str, location: SourceLocation) -> usize {
+    let mut offset = 0;
+    for (index, line) in input.split_inclusive(['\n', '\r', '\x0C']).enumerate() {
+        if index as u32 == location.line {
+            let mut units = 1;
+            for (position, c) in line.char_indices() {
+                if units >= location.column {
+                    return offset + position;
+                }
+                units += c.len_utf16() as u32;
+            }
+            return offset + line.len();
+        }
+        offset += line.len();
+    }
+    input.len()
+}
+
+/// A parsed selector list, e.g. `div.article > p, h1`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Selector {
+    list: SelectorList<DomSelectorImpl>,
+    /// Whether any alternative uses a dynamic pseudo-class.
+    dynamic: bool,
+}
+
+/// Form elements that can be disabled.
+const DISABLEABLE_ELEMENTS: &[&str] = &[
+    "button", "fieldset", "input", "optgroup", "option", "select", "textarea",
+];
+
+impl Selector {
+    /// Parse a selector list.
+    pub fn parse(selector: &str) -> Result<Selector, SelectorError> {
+        let parser = SelectorParser {
+            dynamic: Cell::new(false),
+        };
+        let mut input = ParserInput::new(selector);
+        let list = SelectorList::parse(
+            &parser,
+            &mut cssparser::Parser::new(&mut input),
+            ParseRelative::No,
+        )
+        .map_err(|err| SelectorError::new(selector, err))?;
+        Ok(Selector {
+            list,
+            dynamic: parser.dynamic.get(),
+        })
+    }
+
+    /// Whether the element `handle` matches this selector. Non-elements never match.
+    pub fn matches(&self, dom: &GenerationalArenaDom, handle: Handle) -> bool {
+        self.matches_with(dom, handle, &mut NthIndexCache::default())
+    }
+
+    /// Like [`matches`](Selector::matches), reusing the positions among siblings that `cache`
+    /// has from earlier matches against the same, unchanged DOM.
+    fn matches_with(
+        &self,
+        dom: &GenerationalArenaDom,
+        handle: Handle,
+        cache: &mut NthIndexCache,
+    ) -> bool {
+        dom.as_element(handle).is_some_and(|element| {
+            matches_selector_list(&self.list, &element, &mut matching_context(dom, cache))
+        })
+    }
+
+    /// The [specificity] of the most specific alternative that matches `handle`, as (ids,
+    /// classes, types), or `None` if none match.
+    ///
+    /// [specificity]: https://www.w3.org/TR/selectors-4/#specificity-rules
+    pub(crate) fn matching_specificity(
+        &self,
+        dom: &GenerationalArenaDom,
+        handle: Handle,
+    ) -> Option<(usize, usize, usize)> {
+        let element = dom.as_element(handle)?;
+        let mut cache = NthIndexCache::default();
+        let mut context = matching_context(dom, &mut cache);
+        self.list
+            .0
+            .iter()
+            .filter(|selector| matches_selector(selector, 0, None, &element, &mut context))
+            .map(|selector| {
+                let specificity = selector.specificity() as usize;
+                (
+                    specificity >> 20,
+                    (specificity >> 10) & 0x3FF,
+                    specificity & 0x3FF,
+                )
+            })
+            .max()
+    }
+
+    /// Whether any alternative uses a dynamic pseudo-class, so that what it matches depends on
+    /// state this crate doesn't have.
+    pub(crate) fn is_dynamic(&self) -> bool {
+        self.dynamic
+    }
+}
+
+/// Something that picks out nodes, for methods that act on every node it matches: a
+/// [`Selector`], or a closure taking the DOM and a node.
+pub trait NodeFilter {
+    /// Whether `handle` is picked out.
+    fn matches(&self, dom: &GenerationalArenaDom, handle: Handle) -> bool;
+}
+
+impl NodeFilter for Selector {
+    fn matches(&self, dom: &GenerationalArenaDom, handle: Handle) -> bool {
+        Selector::matches(self, dom, handle)
+    }
+}
+
+impl<F> NodeFilter for F
+where
+    F: Fn(&GenerationalArenaDom, Handle) -> bool,
+{
+    fn matches(&self, dom: &GenerationalArenaDom, handle: Handle) -> bool {
+        self(dom, handle)
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Selector, SelectorError> {
+        Selector::parse(s)
+    }
+}
+
+/// A context for matching against `dom`, in its quirks mode.
+fn matching_context<'a>(
+    dom: &GenerationalArenaDom,
+    cache: &'a mut NthIndexCache,
+) -> MatchingContext<'a, DomSelectorImpl> {
+    use markup5ever::interface::QuirksMode as DomQuirksMode;
+
+    let quirks_mode = match dom.quirks_mode {
+        DomQuirksMode::Quirks => QuirksMode::Quirks,
+        DomQuirksMode::LimitedQuirks => QuirksMode::LimitedQuirks,
+        DomQuirksMode::NoQuirks => QuirksMode::NoQuirks,
+    };
+    MatchingContext::new(
+        MatchingMode::Normal,
+        None,
+        cache,
+        quirks_mode,
+        NeedsSelectorFlags::No,
+        IgnoreNthChildForInvalidation::No,
+    )
+}
+
+/// The [`selectors::SelectorImpl`] of [`Selector`]s, for matching [`ElementRef`]s with the
+/// `selectors` crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomSelectorImpl;
+
+impl selectors::SelectorImpl for DomSelectorImpl {
+    type ExtraMatchingData<'a> = ();
+    type AttrValue = CssString;
+    type Identifier = CssLocalName;
+    type LocalName = CssLocalName;
+    type NamespaceUrl = Namespace;
+    type NamespacePrefix = CssLocalName;
+    type BorrowedNamespaceUrl = Namespace;
+    type BorrowedLocalName = CssLocalName;
+    type NonTSPseudoClass = PseudoClass;
+    type PseudoElement = PseudoElement;
+}
+
+/// A name in a selector: a type, class, id or attribute name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CssLocalName(pub LocalName);
+
+impl From<&str> for CssLocalName {
+    fn from(name: &str) -> CssLocalName {
+        CssLocalName(LocalName::from(name))
+    }
+}
+
+impl ToCss for CssLocalName {
+    fn to_css<W: fmt::Write>(&self, dest: &mut W) -> fmt::Result {
+        serialize_identifier(&self.0, dest)
+    }
+}
+
+/// An attribute value in a selector.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CssString(pub String);
+
+impl From<&str> for CssString {
+    fn from(value: &str) -> CssString {
+        CssString(value.to_string())
+    }
+}
+
+impl AsRef<str> for CssString {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ToCss for CssString {
+    fn to_css<W: fmt::Write>(&self, dest: &mut W) -> fmt::Result {
+        serialize_string(&self.0, dest)
+    }
+}
+
+/// A pseudo-class other than the structural ones the `selectors` crate handles itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PseudoClass {
+    /// `:any-link`
+    AnyLink,
+    /// `:link`
+    Link,
+    /// `:checked`
+    Checked,
+    /// `:enabled`
+    Enabled,
+    /// `:disabled`
+    Disabled,
+    /// `:lang()`, with its language range.
+    Lang(String),
+    /// `:hover`, which never matches.
+    Hover,
+    /// `:active`, which never matches.
+    Active,
+    /// `:focus`, which never matches.
+    Focus,
+    /// `:visited`, which never matches.
+    Visited,
+    /// `:target`, which never matches.
+    Target,
+}
+
+impl PseudoClass {
+    /// Whether what the pseudo-class matches depends on state this crate doesn't have.
+    fn is_dynamic(&self) -> bool {
+        matches!(
+            self,
+            PseudoClass::Hover
+                | PseudoClass::Active
+                | PseudoClass::Focus
+                | PseudoClass::Visited
+                | PseudoClass::Target
+        )
+    }
+}
+
+impl selectors::parser::NonTSPseudoClass for PseudoClass {
+    type Impl = DomSelectorImpl;
+
+    fn is_active_or_hover(&self) -> bool {
+        matches!(self, PseudoClass::Active | PseudoClass::Hover)
+    }
+
+    fn is_user_action_state(&self) -> bool {
+        matches!(
+            self,
+            PseudoClass::Active | PseudoClass::Hover | PseudoClass::Focus
+        )
+    }
+}
+
+impl ToCss for PseudoClass {
+    fn to_css<W: fmt::Write>(&self, dest: &mut W) -> fmt::Result {
+        dest.write_str(match self {
+            PseudoClass::AnyLink => ":any-link",
+            PseudoClass::Link => ":link",
+            PseudoClass::Checked => ":checked",
+            PseudoClass::Enabled => ":enabled",
+            PseudoClass::Disabled => ":disabled",
+            PseudoClass::Lang(lang) => {
+                dest.write_str(":lang(")?;
+                serialize_identifier(lang, dest)?;
+                return dest.write_str(")");
+            }
+            PseudoClass::Hover => ":hover",
+            PseudoClass::Active => ":active",
+            PseudoClass::Focus => ":focus",
+            PseudoClass::Visited => ":visited",
+            PseudoClass::Target => ":target",
+        })
+    }
+}
+
+/// A pseudo-element. None are supported, so there are no values of this type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PseudoElement {}
+
+impl selectors::parser::PseudoElement for PseudoElement {
+    type Impl = DomSelectorImpl;
+}
+
+impl ToCss for PseudoElement {
+    fn to_css<W: fmt::Write>(&self, _: &mut W) -> fmt::Result {
+        match *self {}
+    }
+}
+
+/// Parses the pseudo-classes of [`PseudoClass`], noting whether any are dynamic.
+struct SelectorParser {
+    dynamic: Cell<bool>,
+}
+
+impl<'i> selectors::Parser<'i> for SelectorParser {
+    type Impl = DomSelectorImpl;
+    type Error = SelectorParseErrorKind<'i>;
+
+    fn parse_is_and_where(&self) -> bool {
+        true
+    }
+
+    fn parse_nth_child_of(&self) -> bool {
+        true
+    }
+
+    fn parse_non_ts_pseudo_class(
+        &self,
+        location: SourceLocation,
+        name: CowRcStr<'i>,
+    ) -> Result<PseudoClass, ParseError<'i, Self::Error>> {
+        let class = match_ignore_ascii_case! { &name,
+            "any-link" => PseudoClass::AnyLink,
+            "link" => PseudoClass::Link,
+            "checked" => PseudoClass::Checked,
+            "enabled" => PseudoClass::Enabled,
+            "disabled" => PseudoClass::Disabled,
+            "hover" => PseudoClass::Hover,
+            "active" => PseudoClass::Active,
+            "focus" => PseudoClass::Focus,
+            "visited" => PseudoClass::Visited,
+            "target" => PseudoClass::Target,
+            _ => return Err(location.new_custom_error(
+                SelectorParseErrorKind::UnsupportedPseudoClassOrElement(name),
+            )),
+        };
+        if class.is_dynamic() {
+            self.dynamic.set(true);
+        }
+        Ok(class)
+    }
+
+    fn parse_non_ts_functional_pseudo_class<'t>(
+        &self,
+        name: CowRcStr<'i>,
+        arguments: &mut cssparser::Parser<'i, 't>,
+    ) -> Result<PseudoClass, ParseError<'i, Self::Error>> {
+        if name.eq_ignore_ascii_case("lang") {
+            let lang = arguments.expect_ident_or_string()?.to_string();
+            return Ok(PseudoClass::Lang(lang));
+        }
+        Err(
+            arguments.new_custom_error(SelectorParseErrorKind::UnsupportedPseudoClassOrElement(
+                name,
+            )),
+        )
+    }
+}
+
+impl selectors::Element for ElementRef<'_> {
+    type Impl = DomSelectorImpl;
+
+    fn opaque(&self) -> OpaqueElement {
+        OpaqueElement::new(self.name())
+    }
+
+    fn parent_element(&self) -> Option<Self> {
+        self.parent()
+            .and_then(|parent| self.dom().as_element(parent))
+    }
+
+    fn parent_node_is_shadow_root(&self) -> bool {
+        false
+    }
+
+    fn containing_shadow_host(&self) -> Option<Self> {
+        None
+    }
+
+    fn is_pseudo_element(&self) -> bool {
+        false
+    }
+
+    fn prev_sibling_element(&self) -> Option<Self> {
+        let dom = self.dom();
+        self.handle()
+            .preceding_siblings(&dom.arena)
+            .skip(1)
+            .find_map(|sibling| dom.as_element(sibling))
+    }
+
+    fn next_sibling_element(&self) -> Option<Self> {
+        let dom = self.dom();
+        self.handle()
+            .following_siblings(&dom.arena)
+            .skip(1)
+            .find_map(|sibling| dom.as_element(sibling))
+    }
+
+    fn first_element_child(&self) -> Option<Self> {
+        self.child_elements().next()
+    }
+
+    fn is_html_element_in_html_document(&self) -> bool {
+        self.name().ns == ns!(html)
+    }
+
+    fn has_local_name(&self, name: &CssLocalName) -> bool {
+        self.name().local == name.0
+    }
+
+    fn has_namespace(&self, namespace: &Namespace) -> bool {
+        &self.name().ns == namespace
+    }
+
+    fn is_same_type(&self, other: &Self) -> bool {
+        self.name().local == other.name().local && self.name().ns == other.name().ns
+    }
+
+    fn attr_matches(
+        &self,
+        namespace: &NamespaceConstraint<&Namespace>,
+        local_name: &CssLocalName,
+        operation: &AttrSelectorOperation<&CssString>,
+    ) -> bool {
+        // Operators other than `=` and `|=` never match an empty value.
+        if let AttrSelectorOperation::WithValue {
+            operator:
+                AttrSelectorOperator::Includes
+                | AttrSelectorOperator::Prefix
+                | AttrSelectorOperator::Suffix
+                | AttrSelectorOperator::Substring,
+            value,
+            ..
+        } = operation
+        {
+            if value.0.is_empty() {
+                return false;
+            }
+        }
+        self.attrs().iter().any(|attr| {
+            let in_namespace = match namespace {
+                NamespaceConstraint::Any => true,
+                NamespaceConstraint::Specific(namespace) => attr.name.ns == **namespace,
+            };
+            in_namespace && attr.name.local == local_name.0 && operation.eval_str(&attr.value)
+        })
+    }
+
+    fn match_non_ts_pseudo_class(
+        &self,
+        class: &PseudoClass,
+        _: &mut MatchingContext<'_, DomSelectorImpl>,
+    ) -> bool {
+        let dom = self.dom();
+        let is_html_element =
+            |local: &str| self.is_html_element_in_html_document() && self.local_name() == local;
+        match class {
+            PseudoClass::AnyLink | PseudoClass::Link => selectors::Element::is_link(self),
+            PseudoClass::Checked => {
+                (is_html_element("input")
+                    && dom.get_attr(self.handle(), "type").is_some_and(|kind| {
+                        kind.eq_ignore_ascii_case("checkbox") || kind.eq_ignore_ascii_case("radio")
+                    })
+                    && self.has_attr("checked"))
+                    || (is_html_element("option") && self.has_attr("selected"))
+            }
+            PseudoClass::Enabled => {
+                DISABLEABLE_ELEMENTS
+                    .iter()
+                    .any(|local| is_html_element(local))
+                    && !is_disabled(dom, self.handle())
+            }
+            PseudoClass::Disabled => {
+                DISABLEABLE_ELEMENTS
+                    .iter()
+                    .any(|local| is_html_element(local))
+                    && is_disabled(dom, self.handle())
+            }
+            PseudoClass::Lang(range) => self
+                .handle()
+                .ancestors(&dom.arena)
+                .find_map(|ancestor| language(dom, ancestor))
+                .is_some_and(|lang| {
+                    lang.eq_ignore_ascii_case(range)
+                        || lang.get(..range.len()).is_some_and(|prefix| {
+                            prefix.eq_ignore_ascii_case(range)
+                                && lang[range.len()..].starts_with('-')
+                        })
+                }),
+            PseudoClass::Hover
+            | PseudoClass::Active
+            | PseudoClass::Focus
+            | PseudoClass::Visited
+            | PseudoClass::Target => false,
+        }
+    }
+
+    fn match_pseudo_element(
+        &self,
+        element: &PseudoElement,
+        _: &mut MatchingContext<'_, DomSelectorImpl>,
+    ) -> bool {
+        match *element {}
+    }
+
+    fn apply_selector_flags(&self, _: ElementSelectorFlags) {}
+
+    fn is_link(&self) -> bool {
+        self.is_html_element_in_html_document()
+            && ["a", "area", "link"].contains(&self.local_name())
+            && self.has_attr("href")
+    }
+
+    fn is_html_slot_element(&self) -> bool {
+        false
+    }
+
+    fn has_id(&self, id: &CssLocalName, case_sensitivity: CaseSensitivity) -> bool {
+        self.dom()
+            .get_attr(self.handle(), "id")
+            .is_some_and(|value| case_sensitivity.eq(value.as_bytes(), id.0.as_bytes()))
+    }
+
+    fn has_class(&self, name: &CssLocalName, case_sensitivity: CaseSensitivity) -> bool {
+        let classes = self.dom().get_attr(self.handle(), "class");
+        classes.is_some_and(|classes| {
+            classes
+                .split_ascii_whitespace()
+                .any(|class| case_sensitivity.eq(class.as_bytes(), name.0.as_bytes()))
+        })
+    }
+
+    fn imported_part(&self, _: &CssLocalName) -> Option<CssLocalName> {
+        None
+    }
+
+    fn is_part(&self, _: &CssLocalName) -> bool {
+        false
+    }
+
+    fn is_empty(&self) -> bool {
+        self.children()
+            .all(|child| match self.dom().get_node(&child) {
+                NodeData::Element { .. } => false,
+                NodeData::Text { contents } => contents.borrow().is_empty(),
+                _ => true,
+            })
+    }
+
+    fn is_root(&self) -> bool {
+        self.parent()
+            .is_some_and(|parent| matches!(self.dom().get_node(&parent), NodeData::Document))
+    }
+}
+
+/// The language `handle` declares with a `lang` or `xml:lang` attribute, if it is an element
+/// that does.
+fn language(dom: &GenerationalArenaDom, handle: Handle) -> Option<String> {
+    let attrs = match dom.get_node(&handle) {
+        NodeData::Element { attrs, .. } => attrs.borrow(),
+        _ => return None,
+    };
+    let lang = attrs
+        .iter()
+        .find(|attr| attr.name.ns == ns!(xml) && &*attr.name.local == "lang")
+        .or_else(|| {
+            attrs
+                .iter()
+                .find(|attr| attr.name.ns == ns!() && &*attr.name.local == "lang")
+        })?;
+    Some(lang.value.to_string())
+}
+
+/// Whether the form element `handle` is disabled, by its own `disabled` attribute or that of a
+/// `<fieldset>` around it (other than in the fieldset's first `<legend>`), or for an
+/// `<option>`, of its `<optgroup>`.
+fn is_disabled(dom: &GenerationalArenaDom, handle: Handle) -> bool {
+    let has_disabled = |node: Handle| dom.get_attr(node, "disabled").is_some();
+    if has_disabled(handle) {
+        return true;
+    }
+    let parent = dom.arena[handle].parent();
+    if dom.is_html_element(handle, "option") {
+        return parent
+            .is_some_and(|parent| dom.is_html_element(parent, "optgroup") && has_disabled(parent));
+    }
+    let mut child = handle;
+    for ancestor in handle.ancestors(&dom.arena).skip(1) {
+        if dom.is_html_element(ancestor, "fieldset") && has_disabled(ancestor) {
+            let legend = ancestor
+                .children(&dom.arena)
+                .find(|&node| dom.element_name(node).is_some())
+                .filter(|&node| dom.is_html_element(node, "legend"));
+            if legend != Some(child) {
+                return true;
+            }
+        }
+        child = ancestor;
+    }
+    false
+}
+
+impl GenerationalArenaDom {
+    /// All elements under `scope` (not including `scope` itself) that match `selector`, in
+    /// document order.
+    pub fn select(&self, scope: Handle, selector: &Selector) -> Vec<Handle> {
+        let span = TraceSpan::select();
+        let mut visited = 0;
+        let mut cache = NthIndexCache::default();
+        let matches: Vec<Handle> = scope
+            .descendants(&self.arena)
+            .skip(1)
+            .inspect(|_| visited += 1)
+            .filter(|&node| selector.matches_with(self, node, &mut cache))
+            .collect();
+        span.matched(visited, matches.len());
+        matches
+    }
+
+    /// The first element under `scope` (not including `scope` itself) that matches
+    /// `selector`, like the DOM's `querySelector`.
+    ///
+    /// This parses `selector` on every call; parse it once with [`Selector::parse`] and use
+    /// [`select`](GenerationalArenaDom::select) to run the same query many times.
+    pub fn query_selector(
+        &self,
+        scope: Handle,
+        selector: &str,
+    ) -> Result<Option<Handle>, SelectorError> {
+        Ok(self.query_selector_all(scope, selector)?.next())
+    }
+
+    /// The elements under `scope` (not including `scope` itself) that match `selector`, in
+    /// document order, like the DOM's `querySelectorAll`.
+    ///
+    /// Unlike [`select`](GenerationalArenaDom::select), the matches are found as the iterator
+    /// is advanced, so taking only the first few doesn't search the rest of the subtree.
+    pub fn query_selector_all(
+        &self,
+        scope: Handle,
+        selector: &str,
+    ) -> Result<SelectorMatches<'_>, SelectorError> {
+        let mut nodes = self.document_order(scope);
+        nodes.next();
+        Ok(SelectorMatches {
+            dom: self,
+            selector: Selector::parse(selector)?,
+            nodes,
+        })
+    }
+}
+
+/// The elements matching a selector, from
+/// [`GenerationalArenaDom::query_selector_all`].
+///
+/// Walking it from the back gives the matches in reverse document order.
+#[derive(Clone)]
+pub struct SelectorMatches<'a> {
+    dom: &'a GenerationalArenaDom,
+    selector: Selector,
+    nodes: DocumentOrder<'a>,
+}
+
+impl Iterator for SelectorMatches<'_> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        let (dom, selector) = (self.dom, &self.selector);
+        self.nodes.find(|&node| selector.matches(dom, node))
+    }
+}
+
+impl DoubleEndedIterator for SelectorMatches<'_> {
+    fn next_back(&mut self) -> Option<Handle> {
+        let (dom, selector) = (self.dom, &self.selector);
+        self.nodes.rfind(|&node| selector.matches(dom, node))
+    }
+}
+
+impl FusedIterator for SelectorMatches<'_> {}
+
+impl fmt::Debug for SelectorMatches<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SelectorMatches")
+            .field("selector", &self.selector)
+            .field("nodes", &self.nodes)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Selector;
+    use crate::GenerationalArenaDom;
+
+    /// The ids of the elements in `dom` matching `selector`.
+    fn ids(dom: &GenerationalArenaDom, selector: &str) -> Vec<String> {
+        let selector = Selector::parse(selector).unwrap();
+        dom.select(dom.document, &selector)
+            .into_iter()
+            .map(|node| dom.get_attr(node, "id").unwrap_or_default().to_string())
+            .collect()
+    }
+
+    fn fixture() -> GenerationalArenaDom {
+        GenerationalArenaDom::from_abbreviation(
+            "html[lang=en-GB]>body>(\
+             ul#list>(li#a.x[data-v=\"alpha beta\"]+li#b[data-v=en-US]+p#c+li#d.x{text}))+\
+             (div#e>span#f+em#g)+p#h[title=Hello]+div#i[lang=fr]>(input#j[type=checkbox checked]\
+             +input#k[disabled])",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn attribute_operators() {
+        let dom = fixture();
+        assert_eq!(ids(&dom, "[data-v~=beta]"), ["a"]);
+        assert_eq!(ids(&dom, "[data-v~='alpha beta']"), Vec::<String>::new());
+        assert_eq!(ids(&dom, "[data-v|=en]"), ["b"]);
+        assert_eq!(ids(&dom, "[data-v^=al]"), ["a"]);
+        assert_eq!(ids(&dom, "[data-v$=\"US\"]"), ["b"]);
+        assert_eq!(ids(&dom, "[data-v*=ha]"), ["a"]);
+        assert_eq!(ids(&dom, "[data-v^='']"), Vec::<String>::new());
+        assert_eq!(ids(&dom, "[title=hello i]"), ["h"]);
+        assert_eq!(ids(&dom, "[title=hello]"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn combinators() {
+        let dom = fixture();
+        assert_eq!(ids(&dom, "li + li"), ["b"]);
+        assert_eq!(ids(&dom, "#a ~ li"), ["b", "d"]);
+        assert_eq!(ids(&dom, "ul > .x"), ["a", "d"]);
+        assert_eq!(ids(&dom, "body>div span"), ["f"]);
+        assert_eq!(ids(&dom, "span~em, #a+*"), ["b", "g"]);
+    }
+
+    #[test]
+    fn structural_pseudo_classes() {
+        let dom = fixture();
+        assert_eq!(ids(&dom, "li:first-child"), ["a"]);
+        assert_eq!(ids(&dom, "li:last-child"), ["d"]);
+        assert_eq!(ids(&dom, "ul > :nth-child(2n+1)"), ["a", "c"]);
+        assert_eq!(ids(&dom, "ul > :nth-last-child(1)"), ["d"]);
+        assert_eq!(ids(&dom, "li:nth-of-type(3)"), ["d"]);
+        assert_eq!(ids(&dom, "li:nth-last-of-type(odd)"), ["a", "d"]);
+        assert_eq!(ids(&dom, "ul :first-of-type"), ["a", "c"]);
+        assert_eq!(ids(&dom, "ul :last-of-type"), ["c", "d"]);
+        assert_eq!(ids(&dom, "ul :only-of-type"), ["c"]);
+        assert_eq!(ids(&dom, "div :only-child"), Vec::<String>::new());
+        assert_eq!(ids(&dom, "li:empty"), ["a", "b"]);
+        assert_eq!(ids(&dom, ":root"), [""]);
+    }
+
+    #[test]
+    fn other_pseudo_classes() {
+        let dom = fixture();
+        assert_eq!(ids(&dom, "li:not(.x)"), ["b"]);
+        assert_eq!(ids(&dom, "ul > :not(li):not([id=d])"), ["c"]);
+        assert_eq!(ids(&dom, "p:lang(en)"), ["c", "h"]);
+        assert_eq!(ids(&dom, ":lang(fr)"), ["i", "j", "k"]);
+        assert_eq!(ids(&dom, ":checked"), ["j"]);
+        assert_eq!(ids(&dom, ":disabled"), ["k"]);
+        assert_eq!(ids(&dom, "input:enabled"), ["j"]);
+        assert_eq!(ids(&dom, "p:hover"), Vec::<String>::new());
+        assert!(Selector::parse("p, a:not(:focus)").unwrap().is_dynamic());
+        assert!(!Selector::parse("p, a:not(.x)").unwrap().is_dynamic());
+    }
+
+    #[test]
+    fn selector_lists_in_pseudo_classes() {
+        let dom = fixture();
+        assert_eq!(ids(&dom, ":is(span, em)"), ["f", "g"]);
+        assert_eq!(ids(&dom, "ul > :where(#c, .x)"), ["a", "c", "d"]);
+        assert_eq!(ids(&dom, ":nth-child(2 of .x)"), ["d"]);
+        assert_eq!(ids(&dom, "li:not(#a, #b)"), ["d"]);
+    }
+
+    #[test]
+    fn deep_descendants() {
+        let mut abbreviation = String::from("section");
+        for _ in 0..50 {
+            abbreviation.push_str(">div");
+        }
+        abbreviation.push_str(">p#deep");
+        let dom = GenerationalArenaDom::from_abbreviation(&abbreviation).unwrap();
+        assert_eq!(ids(&dom, "section div div div p"), ["deep"]);
+        assert_eq!(ids(&dom, "section > div div > p"), ["deep"]);
+        assert_eq!(ids(&dom, "span div p"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn quirks_mode_classes_and_ids() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("p#Main.Intro").unwrap();
+        assert_eq!(ids(&dom, ".intro"), Vec::<String>::new());
+        assert_eq!(ids(&dom, "#main"), Vec::<String>::new());
+        dom.quirks_mode = markup5ever::interface::QuirksMode::Quirks;
+        assert_eq!(ids(&dom, ".intro"), ["Main"]);
+        assert_eq!(ids(&dom, "#main"), ["Main"]);
+        assert_eq!(ids(&dom, "[class=intro]"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn escapes() {
+        let dom =
+            GenerationalArenaDom::from_abbreviation("p[class=\"a:b\"]+p#x1+p.c+p[id=10]").unwrap();
+        let matched = |dom: &GenerationalArenaDom, selector: &str| {
+            dom.select(dom.document, &Selector::parse(selector).unwrap())
+                .len()
+        };
+        assert_eq!(matched(&dom, ".a\\:b"), 1);
+        assert_eq!(matched(&dom, "#\\31 0"), 1);
+        assert_eq!(matched(&dom, "#\\78\\31"), 1);
+        assert_eq!(matched(&dom, "[class='\\63 ']"), 1);
+    }
+
+    #[test]
+    fn parse_errors() {
+        for (selector, position, message) in [
+            ("", 0, "expected a selector"),
+            ("div >", 5, "expected a selector after a combinator"),
+            ("a[href!=x]", 6, "invalid attribute selector"),
+            ("p::before", 2, "unsupported pseudo-class or pseudo-element"),
+            ("p:nth-child(x)", 13, "unexpected token"),
+            ("p:unknown", 2, "unsupported pseudo-class or pseudo-element"),
+            ("p:lang()", 7, "unexpected end of selector"),
+            ("a > > b", 4, "expected a selector after a combinator"),
+            ("a,", 2, "expected a selector"),
+        ] {
+            let error = Selector::parse(selector).unwrap_err();
+            assert_eq!(error.position, position, "{}", selector);
+            assert_eq!(error.message, message, "{}", selector);
+        }
+        for selector in ["*, a > b + c ~ d e", "p:not(a b)", "a[href"] {
+            assert!(Selector::parse(selector).is_ok(), "{}", selector);
+        }
+    }
+
+    #[test]
+    fn specificity() {
+        let dom = fixture();
+        let specificity = |selector: &str, id: &str| {
+            let node = dom.select(dom.document, &Selector::parse(&format!("#{}", id)).unwrap())[0];
+            Selector::parse(selector)
+                .unwrap()
+                .matching_specificity(&dom, node)
+        };
+        assert_eq!(specificity("ul li.x:first-child", "a"), Some((0, 2, 2)));
+        assert_eq!(specificity("li:not(#b)", "a"), Some((1, 0, 1)));
+        assert_eq!(specificity("li, #a", "a"), Some((1, 0, 0)));
+        assert_eq!(specificity("p", "a"), None);
+    }
+}
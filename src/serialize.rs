@@ -0,0 +1,705 @@
+//! Serializing the DOM back to HTML.
+//!
+//! This follows the [HTML fragment serialization algorithm], the same rules html5ever's own
+//! serializer implements.
+//!
+//! [HTML fragment serialization algorithm]: https://html.spec.whatwg.org/multipage/#serialising-html-fragments
+
+use std::fmt;
+use std::io;
+use std::ops::Range;
+
+use markup5ever::serialize::{Serialize, Serializer, TraversalScope};
+use markup5ever::{local_name, namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::{is_boolean_attribute, GenerationalArenaDom, Handle, NodeData};
+
+/// The order attributes are written in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum AttributeOrder {
+    /// The order they were parsed or added in.
+    #[default]
+    Original,
+
+    /// Sorted by their serialized name.
+    Alphabetical,
+
+    /// The named attributes first, in the given order, then the rest in their original order.
+    Priority(Vec<String>),
+}
+
+impl AttributeOrder {
+    fn sort(&self, attrs: &mut [&Attribute]) {
+        match self {
+            AttributeOrder::Original => {}
+            AttributeOrder::Alphabetical => attrs.sort_by_cached_key(|attr| attr_name(&attr.name)),
+            AttributeOrder::Priority(names) => attrs.sort_by_key(|attr| {
+                let name = attr_name(&attr.name);
+                names
+                    .iter()
+                    .position(|priority| *priority == name)
+                    .unwrap_or(names.len())
+            }),
+        }
+    }
+}
+
+/// Options for serializing a DOM.
+#[derive(Clone)]
+pub struct SerializeOpts {
+    /// Whether scripting was enabled when parsing, which decides whether the contents of
+    /// `<noscript>` are raw text. Defaults to `true`, like html5ever's parser.
+    pub scripting_enabled: bool,
+
+    /// Whether to serialize the node itself or only its children.
+    pub traversal_scope: TraversalScope,
+
+    /// The order attributes are written in.
+    pub attribute_order: AttributeOrder,
+
+    /// Whether to write [boolean attributes](crate::is_boolean_attribute) of HTML elements
+    /// without a value (`<input disabled>`) when their value is empty or their own name.
+    /// Defaults to `false`, which writes them like any other attribute (`disabled=""`).
+    pub minimize_boolean_attributes: bool,
+
+    /// Whether to write polyglot XHTML, which XML parsers accept as well as HTML ones, as EPUB
+    /// requires. Void elements are self-closed (`<br />`), foreign and root `<html>` elements
+    /// declare their namespace, boolean attributes are written in full, `&nbsp;` is written as
+    /// `&#160;`, characters XML forbids are dropped, and the code of scripts and styles is
+    /// wrapped in a commented-out CDATA section if it has `<` or `&`. Defaults to `false`.
+    pub xhtml: bool,
+}
+
+impl Default for SerializeOpts {
+    fn default() -> SerializeOpts {
+        SerializeOpts {
+            scripting_enabled: true,
+            traversal_scope: TraversalScope::IncludeNode,
+            attribute_order: AttributeOrder::Original,
+            minimize_boolean_attributes: false,
+            xhtml: false,
+        }
+    }
+}
+
+/// One node's share of serialized output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    /// The byte range of the output produced by the node, including its descendants.
+    pub range: Range<usize>,
+    /// The node that produced the output.
+    pub handle: Handle,
+}
+
+/// Associates byte ranges of serialized output with the nodes that produced them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// One entry per serialized node, in document order. Entries of descendants are nested
+    /// inside those of their ancestors.
+    pub entries: Vec<SourceMapEntry>,
+}
+
+impl SourceMap {
+    /// The innermost node whose output contains the byte at `offset`.
+    pub fn node_at(&self, offset: usize) -> Option<Handle> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.range.contains(&offset))
+            .map(|entry| entry.handle)
+    }
+
+    /// The output produced by `handle`, if it was serialized.
+    pub fn range_of(&self, handle: Handle) -> Option<Range<usize>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.handle == handle)
+            .map(|entry| entry.range.clone())
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Serialize `handle` to HTML.
+    pub fn serialize(&self, handle: Handle, opts: &SerializeOpts) -> String {
+        let mut writer = HtmlWriter::new(self, opts, false);
+        writer.write_root(handle);
+        writer.out
+    }
+
+    /// Serialize `handle` to HTML, recording which node produced each part of the output.
+    pub fn serialize_with_source_map(
+        &self,
+        handle: Handle,
+        opts: &SerializeOpts,
+    ) -> (String, SourceMap) {
+        let mut writer = HtmlWriter::new(self, opts, true);
+        writer.write_root(handle);
+        (
+            writer.out,
+            SourceMap {
+                entries: writer.map,
+            },
+        )
+    }
+
+    /// The HTML of `handle` and its descendants.
+    pub fn outer_html(&self, handle: Handle) -> String {
+        self.serialize(handle, &SerializeOpts::default())
+    }
+
+    /// The HTML of the descendants of `handle`.
+    pub fn inner_html(&self, handle: Handle) -> String {
+        let opts = SerializeOpts {
+            traversal_scope: TraversalScope::ChildrenOnly(None),
+            ..SerializeOpts::default()
+        };
+        self.serialize(handle, &opts)
+    }
+}
+
+/// A node of a DOM that implements markup5ever's [`Serialize`], so it can be written with
+/// any [`Serializer`], such as html5ever's `serialize` function, from
+/// [`GenerationalArenaDom::serializable`].
+///
+/// [`GenerationalArenaDom::serialize`] needs no other crates and has more options; this is for
+/// code that already works with markup5ever serializers. The contents of a `<template>` are
+/// serialized as its children.
+#[derive(Clone, Copy)]
+pub struct SerializableHandle<'a> {
+    dom: &'a GenerationalArenaDom,
+    handle: Handle,
+}
+
+impl GenerationalArenaDom {
+    /// `handle` as a markup5ever [`Serialize`] implementation.
+    pub fn serializable(&self, handle: Handle) -> SerializableHandle<'_> {
+        SerializableHandle { dom: self, handle }
+    }
+}
+
+impl SerializableHandle<'_> {
+    fn serialize_node<S: Serializer>(&self, serializer: &mut S, handle: Handle) -> io::Result<()> {
+        match self.dom.get_node(&handle) {
+            NodeData::Document => self.serialize_children(serializer, handle),
+            NodeData::Doctype { name, .. } => serializer.write_doctype(name),
+            NodeData::Text { contents } => serializer.write_text(&contents.borrow()),
+            NodeData::Comment { contents } => serializer.write_comment(contents),
+            NodeData::ProcessingInstruction { target, contents } => {
+                serializer.write_processing_instruction(target, contents)
+            }
+            NodeData::Element { name, attrs, .. } => {
+                let attrs = attrs.borrow();
+                serializer.start_elem(
+                    name.clone(),
+                    attrs.iter().map(|attr| (&attr.name, &attr.value[..])),
+                )?;
+                self.serialize_children(serializer, handle)?;
+                serializer.end_elem(name.clone())
+            }
+        }
+    }
+
+    fn serialize_children<S: Serializer>(
+        &self,
+        serializer: &mut S,
+        handle: Handle,
+    ) -> io::Result<()> {
+        let parent = match self.dom.get_node(&handle) {
+            NodeData::Element {
+                template_contents, ..
+            } => template_contents.borrow().unwrap_or(handle),
+            _ => handle,
+        };
+        for child in parent.children(&self.dom.arena) {
+            self.serialize_node(serializer, child)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for SerializableHandle<'_> {
+    fn serialize<S>(&self, serializer: &mut S, traversal_scope: TraversalScope) -> io::Result<()>
+    where
+        S: Serializer,
+    {
+        match traversal_scope {
+            TraversalScope::IncludeNode => self.serialize_node(serializer, self.handle),
+            TraversalScope::ChildrenOnly(_) => self.serialize_children(serializer, self.handle),
+        }
+    }
+}
+
+impl Serialize for GenerationalArenaDom {
+    /// Serialize the whole document. The inherent
+    /// [`serialize`](GenerationalArenaDom::serialize) method takes precedence in method call
+    /// syntax, so call this as `Serialize::serialize(&dom, ..)`, or pass `&dom` to html5ever's
+    /// `serialize` function.
+    fn serialize<S>(&self, serializer: &mut S, traversal_scope: TraversalScope) -> io::Result<()>
+    where
+        S: Serializer,
+    {
+        self.serializable(self.document)
+            .serialize(serializer, traversal_scope)
+    }
+}
+
+impl fmt::Debug for SerializableHandle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerializableHandle")
+            .field("handle", &self.handle)
+            .finish_non_exhaustive()
+    }
+}
+
+struct HtmlWriter<'a> {
+    dom: &'a GenerationalArenaDom,
+    opts: &'a SerializeOpts,
+    out: String,
+    record: bool,
+    map: Vec<SourceMapEntry>,
+}
+
+impl<'a> HtmlWriter<'a> {
+    fn new(dom: &'a GenerationalArenaDom, opts: &'a SerializeOpts, record: bool) -> Self {
+        HtmlWriter {
+            dom,
+            opts,
+            out: String::new(),
+            record,
+            map: vec![],
+        }
+    }
+
+    fn write_root(&mut self, handle: Handle) {
+        match self.opts.traversal_scope {
+            TraversalScope::IncludeNode => self.write_node(handle),
+            TraversalScope::ChildrenOnly(_) => self.write_children(handle),
+        }
+    }
+
+    fn write_node(&mut self, handle: Handle) {
+        let entry = self.map.len();
+        if self.record {
+            self.map.push(SourceMapEntry {
+                range: self.out.len()..self.out.len(),
+                handle,
+            });
+        }
+        match self.dom.get_node(&handle) {
+            NodeData::Document => self.write_children(handle),
+            NodeData::Doctype { name, .. } => {
+                self.out.push_str("<!DOCTYPE ");
+                self.out.push_str(name);
+                self.out.push('>');
+            }
+            NodeData::Text { contents } => {
+                let raw = self.is_raw_text_parent(handle);
+                if self.opts.xhtml {
+                    self.write_xhtml_text(handle, &contents.borrow(), raw);
+                } else if raw {
+                    self.out.push_str(&contents.borrow());
+                } else {
+                    escape(&mut self.out, &contents.borrow(), false);
+                }
+            }
+            NodeData::Comment { contents } => {
+                self.out.push_str("<!--");
+                if self.opts.xhtml {
+                    // XML forbids `--` in comments and `-` at their end.
+                    let mut comment = contents.replace("--", "- -");
+                    while comment.contains("--") {
+                        comment = comment.replace("--", "- -");
+                    }
+                    if comment.ends_with('-') {
+                        comment.push(' ');
+                    }
+                    self.out.push_str(&comment);
+                } else {
+                    self.out.push_str(contents);
+                }
+                self.out.push_str("-->");
+            }
+            NodeData::ProcessingInstruction { target, contents } => {
+                self.out.push_str("<?");
+                self.out.push_str(target);
+                self.out.push(' ');
+                self.out.push_str(contents);
+                self.out.push_str(if self.opts.xhtml { "?>" } else { ">" });
+            }
+            NodeData::Element {
+                name,
+                attrs,
+                template_contents,
+                ..
+            } => {
+                self.out.push('<');
+                self.out.push_str(&tag_name(name));
+                let attrs = attrs.borrow();
+                let mut attrs: Vec<&Attribute> = attrs.iter().collect();
+                self.opts.attribute_order.sort(&mut attrs);
+                if self.opts.xhtml {
+                    self.write_namespace_declarations(handle, name, &attrs);
+                }
+                for attr in attrs {
+                    let attr_name = attr_name(&attr.name);
+                    self.out.push(' ');
+                    self.out.push_str(&attr_name);
+                    if self.opts.minimize_boolean_attributes
+                        && !self.opts.xhtml
+                        && name.ns == ns!(html)
+                        && is_boolean_attribute(&attr_name)
+                        && (attr.value.is_empty() || attr.value.eq_ignore_ascii_case(&attr_name))
+                    {
+                        continue;
+                    }
+                    self.out.push_str("=\"");
+                    if self.opts.xhtml {
+                        escape_xml(&mut self.out, &attr.value, true);
+                    } else {
+                        escape(&mut self.out, &attr.value, true);
+                    }
+                    self.out.push('"');
+                }
+                let empty = template_contents.borrow().is_none()
+                    && handle.children(&self.dom.arena).next().is_none();
+                let self_closed =
+                    self.opts.xhtml && (is_void(name) || (name.ns != ns!(html) && empty));
+                self.out.push_str(if self_closed { " />" } else { ">" });
+                if !self_closed && !is_void(name) {
+                    match *template_contents.borrow() {
+                        Some(contents) => self.write_children(contents),
+                        None => self.write_children(handle),
+                    }
+                    self.out.push_str("</");
+                    self.out.push_str(&tag_name(name));
+                    self.out.push('>');
+                }
+            }
+        }
+        if self.record {
+            self.map[entry].range.end = self.out.len();
+        }
+    }
+
+    fn write_children(&mut self, handle: Handle) {
+        for child in handle.children(&self.dom.arena) {
+            self.write_node(child);
+        }
+    }
+
+    /// Write the text of `handle` as XHTML. The code of scripts and styles, which HTML parsers
+    /// don't unescape, goes in a CDATA section commented out for them if it needs escaping.
+    fn write_xhtml_text(&mut self, handle: Handle, text: &str, raw: bool) {
+        if !raw || !text.contains(['<', '&']) {
+            escape_xml(&mut self.out, text, false);
+            return;
+        }
+        let in_style = self.dom.arena[handle]
+            .parent()
+            .is_some_and(|parent| self.dom.is_html_element(parent, "style"));
+        let (open, close) = if in_style {
+            ("/*<![CDATA[*/", "/*]]>*/")
+        } else {
+            ("//<![CDATA[\n", "\n//]]>")
+        };
+        self.out.push_str(open);
+        // `]]>` can't appear inside a CDATA section, so split it across two.
+        let text: String = text.chars().filter(|&c| is_xml_char(c)).collect();
+        self.out.push_str(&text.replace("]]>", "]]]]><![CDATA[>"));
+        self.out.push_str(close);
+    }
+
+    /// Write the `xmlns` attributes an XML parser needs for an element named `name`: the
+    /// namespace of the root `<html>` element, of HTML inside SVG's `<foreignObject>` and of the
+    /// outermost SVG and MathML elements, and the XLink prefix wherever it's used, unless
+    /// `attrs` already declare them.
+    fn write_namespace_declarations(
+        &mut self,
+        handle: Handle,
+        name: &QualName,
+        attrs: &[&Attribute],
+    ) {
+        let declared = |prefixed: &str| attrs.iter().any(|attr| attr_name(&attr.name) == prefixed);
+        let parent_ns = self.dom.arena[handle]
+            .parent()
+            .and_then(|parent| self.dom.element_name(parent))
+            .map(|parent| parent.ns.clone());
+        let namespace = match name.ns {
+            ns!(html) if parent_ns != Some(ns!(html)) => Some("http://www.w3.org/1999/xhtml"),
+            ns!(svg) if parent_ns != Some(ns!(svg)) => Some("http://www.w3.org/2000/svg"),
+            ns!(mathml) if parent_ns != Some(ns!(mathml)) => {
+                Some("http://www.w3.org/1998/Math/MathML")
+            }
+            _ => None,
+        };
+        if let Some(namespace) = namespace {
+            if !declared("xmlns") {
+                self.out.push_str(" xmlns=\"");
+                self.out.push_str(namespace);
+                self.out.push('"');
+            }
+            let uses_xlink =
+                handle
+                    .descendants(&self.dom.arena)
+                    .any(|node| match self.dom.get_node(&node) {
+                        NodeData::Element { attrs, .. } => {
+                            attrs.borrow().iter().any(|attr| attr.name.ns == ns!(xlink))
+                        }
+                        _ => false,
+                    });
+            if uses_xlink && !declared("xmlns:xlink") {
+                self.out
+                    .push_str(" xmlns:xlink=\"http://www.w3.org/1999/xlink\"");
+            }
+        }
+    }
+
+    /// Whether text inside the parent of `handle` is serialized without escaping.
+    fn is_raw_text_parent(&self, handle: Handle) -> bool {
+        let parent = match self.dom.arena[handle].parent() {
+            Some(parent) => parent,
+            None => return false,
+        };
+        match self.dom.element_name(parent) {
+            Some(name) if name.ns == ns!(html) => match name.local {
+                local_name!("style")
+                | local_name!("script")
+                | local_name!("xmp")
+                | local_name!("iframe")
+                | local_name!("noembed")
+                | local_name!("noframes")
+                | local_name!("plaintext") => true,
+                local_name!("noscript") => self.opts.scripting_enabled,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// Whether `name` is a void element, which has no end tag or contents.
+pub(crate) fn is_void(name: &QualName) -> bool {
+    name.ns == ns!(html)
+        && matches!(
+            name.local,
+            local_name!("area")
+                | local_name!("base")
+                | local_name!("basefont")
+                | local_name!("bgsound")
+                | local_name!("br")
+                | local_name!("col")
+                | local_name!("embed")
+                | local_name!("frame")
+                | local_name!("hr")
+                | local_name!("img")
+                | local_name!("input")
+                | local_name!("keygen")
+                | local_name!("link")
+                | local_name!("meta")
+                | local_name!("param")
+                | local_name!("source")
+                | local_name!("track")
+                | local_name!("wbr")
+        )
+}
+
+/// The tag name an element is serialized with.
+fn tag_name(name: &QualName) -> String {
+    if name.ns == ns!(html) || name.ns == ns!(svg) || name.ns == ns!(mathml) {
+        return name.local.to_string();
+    }
+    qualified(name.prefix.as_deref(), &name.local)
+}
+
+/// The name an attribute is serialized with.
+fn attr_name(name: &QualName) -> String {
+    let prefix = match name.ns {
+        ns!() => None,
+        ns!(xml) => Some("xml"),
+        ns!(xmlns) if name.local == local_name!("xmlns") => None,
+        ns!(xmlns) => Some("xmlns"),
+        ns!(xlink) => Some("xlink"),
+        _ => name.prefix.as_deref(),
+    };
+    qualified(prefix, &name.local)
+}
+
+fn qualified(prefix: Option<&str>, local: &LocalName) -> String {
+    match prefix {
+        Some(prefix) => format!("{}:{}", prefix, local),
+        None => local.to_string(),
+    }
+}
+
+/// Escape `text` for use in a text node, or in a double-quoted attribute value.
+pub(crate) fn escape(out: &mut String, text: &str, attr_mode: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '\u{00A0}' => out.push_str("&nbsp;"),
+            '"' if attr_mode => out.push_str("&quot;"),
+            '<' if !attr_mode => out.push_str("&lt;"),
+            '>' if !attr_mode => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Escape `text` for use in XML text, or in a double-quoted attribute value, dropping
+/// characters XML doesn't allow.
+fn escape_xml(out: &mut String, text: &str, attr_mode: bool) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' if !attr_mode => out.push_str("&gt;"),
+            '"' if attr_mode => out.push_str("&quot;"),
+            '\u{00A0}' => out.push_str("&#160;"),
+            c if is_xml_char(c) => out.push(c),
+            _ => {}
+        }
+    }
+}
+
+/// Whether XML 1.0 allows `c` in documents.
+fn is_xml_char(c: char) -> bool {
+    matches!(
+        c,
+        '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::serialize::{Serialize, TraversalScope};
+
+    use crate::parse::parse_html;
+    use crate::{AttributeOrder, SerializeOpts};
+
+    #[test]
+    fn orders_attributes() {
+        let dom = parse_html("<a title=t href=h id=i class=c>x</a>");
+        let a = dom.query_selector(dom.document, "a").unwrap().unwrap();
+        let html = |attribute_order| {
+            dom.serialize(
+                a,
+                &SerializeOpts {
+                    attribute_order,
+                    ..SerializeOpts::default()
+                },
+            )
+        };
+        assert_eq!(
+            html(AttributeOrder::Original),
+            "<a title=\"t\" href=\"h\" id=\"i\" class=\"c\">x</a>"
+        );
+        assert_eq!(
+            html(AttributeOrder::Alphabetical),
+            "<a class=\"c\" href=\"h\" id=\"i\" title=\"t\">x</a>"
+        );
+        assert_eq!(
+            html(AttributeOrder::Priority(vec!["id".into(), "class".into()])),
+            "<a id=\"i\" class=\"c\" title=\"t\" href=\"h\">x</a>"
+        );
+    }
+
+    #[test]
+    fn maps_output_to_nodes() {
+        let dom = parse_html("<p>Hi <b>there</b></p>");
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let b = dom.query_selector(dom.document, "b").unwrap().unwrap();
+        let (html, map) = dom.serialize_with_source_map(p, &SerializeOpts::default());
+        assert_eq!(html, "<p>Hi <b>there</b></p>");
+        assert_eq!(map.range_of(p), Some(0..html.len()));
+        assert_eq!(map.range_of(b), Some(6..18));
+        assert_eq!(map.node_at(8), Some(b));
+        let hi = dom.arena[p].first_child().unwrap();
+        assert_eq!(map.node_at(3), Some(hi));
+        assert_eq!(map.node_at(html.len()), None);
+
+        // Entries are in document order, ancestors before descendants.
+        let handles: Vec<_> = map.entries.iter().map(|entry| entry.handle).collect();
+        let text = dom.arena[b].first_child().unwrap();
+        assert_eq!(handles, [p, hi, b, text]);
+        assert_eq!(dom.serialize(p, &SerializeOpts::default()), html);
+    }
+
+    #[test]
+    fn writes_polyglot_xhtml() {
+        let dom = parse_html(
+            "<p>a&nbsp;b\u{1}<br><input disabled><!-- x -- y- -->\
+             <svg><use xlink:href=#i /><g></g></svg>\
+             <script>if (a < b) {}</script><style>p{}</style></p>",
+        );
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let opts = SerializeOpts {
+            xhtml: true,
+            minimize_boolean_attributes: true,
+            ..SerializeOpts::default()
+        };
+        assert_eq!(
+            dom.serialize(p, &opts),
+            "<p>a&#160;b<br /><input disabled=\"\" /><!-- x - - y- -->\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:xlink=\"http://www.w3.org/1999/xlink\"><use xlink:href=\"#i\" /><g /></svg>\
+             <script>//<![CDATA[\nif (a < b) {}\n//]]></script><style>p{}</style></p>"
+        );
+        let html = dom.query_selector(dom.document, "html").unwrap().unwrap();
+        assert!(dom.serialize(html, &opts).starts_with(
+            "<html xmlns=\"http://www.w3.org/1999/xhtml\" \
+                 xmlns:xlink=\"http://www.w3.org/1999/xlink\"><head></head>"
+        ));
+    }
+
+    /// `node` written by html5ever's serializer with `traversal_scope`.
+    fn html5ever_html(node: &impl Serialize, traversal_scope: TraversalScope) -> String {
+        let mut out = Vec::new();
+        let opts = html5ever::serialize::SerializeOpts {
+            traversal_scope,
+            ..Default::default()
+        };
+        html5ever::serialize::serialize(&mut out, node, opts).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn serializes_through_markup5ever() {
+        let html = "<!DOCTYPE html><html><head><title>T</title></head>\
+                    <body><p class=\"a\">x &amp; <b>y</b></p><!--c--></body></html>";
+        let dom = parse_html(html);
+        let with = |scope| html5ever_html(&dom, scope);
+        assert_eq!(with(TraversalScope::ChildrenOnly(None)), html);
+        assert_eq!(with(TraversalScope::IncludeNode), html);
+        let reparsed = parse_html(&with(TraversalScope::IncludeNode));
+        assert_eq!(html5ever_html(&reparsed, TraversalScope::IncludeNode), html);
+
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let p = dom.serializable(p);
+        assert_eq!(
+            html5ever_html(&p, TraversalScope::IncludeNode),
+            "<p class=\"a\">x &amp; <b>y</b></p>"
+        );
+        assert_eq!(
+            html5ever_html(&p, TraversalScope::ChildrenOnly(None)),
+            "x &amp; <b>y</b>"
+        );
+    }
+
+    #[test]
+    fn serializes_template_contents_through_markup5ever() {
+        let dom = parse_html("<template><p>in</p><i>side</i></template>");
+        let template = dom
+            .query_selector(dom.document, "template")
+            .unwrap()
+            .unwrap();
+        let template = dom.serializable(template);
+        assert_eq!(
+            html5ever_html(&template, TraversalScope::IncludeNode),
+            "<template><p>in</p><i>side</i></template>"
+        );
+        assert_eq!(
+            html5ever_html(&template, TraversalScope::ChildrenOnly(None)),
+            "<p>in</p><i>side</i>"
+        );
+    }
+}
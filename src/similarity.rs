@@ -0,0 +1,136 @@
+//! Comparing the structure of documents.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// How many consecutive structural tokens make up a shingle.
+const SHINGLE_LENGTH: usize = 4;
+
+/// How structurally similar documents `a` and `b` are, from 0 (nothing in common) to 1
+/// (identical structure).
+///
+/// This approximates tree edit distance with shingling: each document is flattened into the
+/// sequence of its element start and end tags and text nodes, and the score is the Jaccard
+/// similarity of the multisets of runs of four consecutive tokens. Text contents, attribute
+/// values and whitespace-only text are ignored, so two pages built from the same template score
+/// near 1 whatever they say, which is what near-duplicate detection usually wants.
+pub fn similarity(a: &GenerationalArenaDom, b: &GenerationalArenaDom) -> f64 {
+    subtree_similarity(a, a.document, b, b.document)
+}
+
+/// How structurally similar the subtree of `a` rooted at `a_root` and the subtree of `b`
+/// rooted at `b_root` are, from 0 to 1, as described for [`similarity`].
+pub fn subtree_similarity(
+    a: &GenerationalArenaDom,
+    a_root: Handle,
+    b: &GenerationalArenaDom,
+    b_root: Handle,
+) -> f64 {
+    let a = shingles(a, a_root);
+    let b = shingles(b, b_root);
+    let (mut shared, mut total) = (0, 0);
+    for (shingle, &count) in &a {
+        let other = b.get(shingle).copied().unwrap_or(0);
+        shared += count.min(other);
+        total += count.max(other);
+    }
+    total += b
+        .iter()
+        .filter(|(shingle, _)| !a.contains_key(shingle))
+        .map(|(_, &count)| count)
+        .sum::<usize>();
+    if total == 0 {
+        1.0
+    } else {
+        shared as f64 / total as f64
+    }
+}
+
+/// How many times each shingle occurs in the subtree rooted at `root`.
+fn shingles(dom: &GenerationalArenaDom, root: Handle) -> HashMap<u64, usize> {
+    let mut tokens = Vec::new();
+    push_tokens(dom, root, &mut tokens);
+    let mut counts = HashMap::new();
+    for window in tokens.windows(SHINGLE_LENGTH.min(tokens.len()).max(1)) {
+        let mut hasher = DefaultHasher::new();
+        window.hash(&mut hasher);
+        *counts.entry(hasher.finish()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Append the structural tokens of the subtree rooted at `node` to `tokens`.
+fn push_tokens(dom: &GenerationalArenaDom, node: Handle, tokens: &mut Vec<String>) {
+    match dom.get_node(&node) {
+        NodeData::Element { name, .. } => {
+            tokens.push(format!("<{}:{}", name.ns, name.local));
+            for child in node.children(&dom.arena) {
+                push_tokens(dom, child, tokens);
+            }
+            tokens.push(format!("/{}:{}", name.ns, name.local));
+        }
+        NodeData::Text { contents } if !contents.borrow().trim().is_empty() => {
+            tokens.push("#text".to_string());
+        }
+        NodeData::Document => {
+            for child in node.children(&dom.arena) {
+                push_tokens(dom, child, tokens);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{similarity, subtree_similarity};
+    use crate::parse::parse_html;
+    use crate::GenerationalArenaDom;
+
+    #[test]
+    fn scores_structure() {
+        let page = |title: &str, items: &[&str]| {
+            let items: String = items
+                .iter()
+                .map(|item| format!("<li class={}>{}</li>", item, item))
+                .collect();
+            parse_html(&format!(
+                "<h1>{}</h1><ul>{}</ul><footer>f</footer>",
+                title, items
+            ))
+        };
+        let a = page("Lamps", &["desk", "floor", "wall"]);
+        let b = page("Chairs", &["arm", "office", "stool"]);
+        assert_eq!(similarity(&a, &b), 1.0);
+        assert_eq!(similarity(&a, &a), 1.0);
+
+        let shorter = page("Lamps", &["desk"]);
+        let different = parse_html("<table><tr><td>Lamps</td></tr></table><p>x</p>");
+        let close = similarity(&a, &shorter);
+        let far = similarity(&a, &different);
+        assert!(0.5 < close && close < 1.0, "{}", close);
+        assert!(far < close, "{} {}", far, close);
+        assert!(far > 0.0);
+    }
+
+    #[test]
+    fn scores_subtrees() {
+        let a = parse_html("<b>x</b><p><i>y</i></p>");
+        let b = parse_html("<b>other text</b>");
+        let b_tag =
+            |dom: &GenerationalArenaDom| dom.query_selector(dom.document, "b").unwrap().unwrap();
+        let i = a.query_selector(a.document, "i").unwrap().unwrap();
+        assert_eq!(subtree_similarity(&a, b_tag(&a), &b, b_tag(&b)), 1.0);
+        assert_eq!(subtree_similarity(&a, i, &b, b_tag(&b)), 0.0);
+
+        let comment = parse_html("<!-- x -->");
+        let comment_node = comment.arena[comment.document].first_child().unwrap();
+        assert_eq!(
+            subtree_similarity(&comment, comment_node, &comment, comment_node),
+            1.0
+        );
+    }
+}
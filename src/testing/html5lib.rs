@@ -0,0 +1,245 @@
+//! Running [html5lib-tests] tree-construction tests against this `TreeSink`.
+//!
+//! Load the contents of a `.dat` file with [`parse_dat`] and run each [`TestCase`] with a
+//! [`Parser`] (or a [`FragmentParser`] for `#document-fragment` tests). The resulting DOM is
+//! dumped in the indented `| <html>` format the test files use and compared with the expected
+//! tree.
+//!
+//! [html5lib-tests]: https://github.com/html5lib/html5lib-tests
+
+use markup5ever::{namespace_url, ns, LocalName, QualName};
+
+use crate::tree_dump::dump;
+use crate::{FragmentParser, GenerationalArenaDom, Parser};
+
+/// One test from a tree-construction `.dat` file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TestCase {
+    /// The input HTML.
+    pub data: String,
+    /// The parse errors the test expects. They are informational only, since parsers report
+    /// errors with different wording.
+    pub errors: Vec<String>,
+    /// For fragment tests, the context element, e.g. `td` or `svg path`.
+    pub fragment_context: Option<String>,
+    /// `Some(true)` for `#script-on` tests and `Some(false)` for `#script-off` tests.
+    pub scripting: Option<bool>,
+    /// The expected tree, one `| `-prefixed line per node or attribute.
+    pub expected: String,
+}
+
+/// The result of running a [`TestCase`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TestOutcome {
+    /// The DOM matched the expected tree.
+    Passed,
+    /// The DOM didn't match. `actual` is its dump.
+    Failed { actual: String },
+    /// The test couldn't be run with the given parser.
+    Skipped,
+}
+
+/// Split the contents of a `.dat` file into test cases.
+pub fn parse_dat(contents: &str) -> Vec<TestCase> {
+    let mut tests = Vec::new();
+    let mut test: Option<TestCase> = None;
+    let mut section = String::new();
+    let mut text = String::new();
+
+    for line in contents.split_inclusive('\n') {
+        let header = line.trim_end_matches('\n');
+        let known = matches!(
+            header,
+            "#data"
+                | "#errors"
+                | "#new-errors"
+                | "#document-fragment"
+                | "#script-on"
+                | "#script-off"
+                | "#document"
+        );
+        if !known {
+            text.push_str(line);
+            continue;
+        }
+        if let Some(test) = test.as_mut() {
+            finish_section(test, &section, &text);
+        }
+        text.clear();
+        section = header.to_string();
+        if header == "#data" {
+            tests.extend(test.replace(TestCase::default()));
+        }
+    }
+    if let Some(mut test) = test {
+        finish_section(&mut test, &section, &text);
+        tests.push(test);
+    }
+    tests
+}
+
+fn finish_section(test: &mut TestCase, section: &str, text: &str) {
+    // Every section ends with a newline, and all but the last section of a test are followed
+    // by a blank line before the next `#data`.
+    match section {
+        "#data" => test.data = text.strip_suffix('\n').unwrap_or(text).to_string(),
+        "#errors" | "#new-errors" => test.errors.extend(
+            text.lines()
+                .filter(|line| !line.is_empty())
+                .map(String::from),
+        ),
+        "#document-fragment" => test.fragment_context = Some(text.trim().to_string()),
+        "#script-on" => test.scripting = Some(true),
+        "#script-off" => test.scripting = Some(false),
+        "#document" => test.expected = text.trim_end_matches('\n').to_string(),
+        _ => {}
+    }
+}
+
+impl TestCase {
+    /// Run a document test with `parser`.
+    ///
+    /// The parser is assumed to have scripting enabled, as html5ever does by default, so
+    /// `#script-off` tests are skipped, as are fragment tests.
+    pub fn run<P>(&self, parser: &P) -> TestOutcome
+    where
+        P: Parser + ?Sized,
+    {
+        if self.fragment_context.is_some() || self.scripting == Some(false) {
+            return TestOutcome::Skipped;
+        }
+        let dom = parser.parse(GenerationalArenaDom::default(), &self.data);
+        self.compare(dump(&dom, dom.document.children(&dom.arena)))
+    }
+
+    /// Run a fragment test with `parser`. Document tests and `#script-off` tests are skipped.
+    pub fn run_fragment<P>(&self, parser: &P) -> TestOutcome
+    where
+        P: FragmentParser + ?Sized,
+    {
+        let context = match &self.fragment_context {
+            Some(context) if self.scripting != Some(false) => context_name(context),
+            _ => return TestOutcome::Skipped,
+        };
+        let dom = parser.parse_fragment(GenerationalArenaDom::default(), &context, &self.data);
+        let root = match dom.document_element() {
+            Some(root) => root,
+            None => return self.compare(String::new()),
+        };
+        self.compare(dump(&dom, root.children(&dom.arena)))
+    }
+
+    fn compare(&self, actual: String) -> TestOutcome {
+        if actual == self.expected {
+            TestOutcome::Passed
+        } else {
+            TestOutcome::Failed { actual }
+        }
+    }
+}
+
+/// The element named by a `#document-fragment` line.
+fn context_name(context: &str) -> QualName {
+    let (ns, local) = match context.split_once(' ') {
+        Some(("svg", local)) => (ns!(svg), local),
+        Some(("math", local)) => (ns!(mathml), local),
+        _ => (ns!(html), context),
+    };
+    QualName::new(None, ns, LocalName::from(local))
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::tendril::TendrilSink;
+    use markup5ever::QualName;
+
+    use super::{parse_dat, TestOutcome};
+    use crate::GenerationalArenaDom;
+
+    const DAT: &str = r#"#data
+<p>One<p>Two
+#errors
+(1,3): expected-doctype-but-got-start-tag
+#document
+| <html>
+|   <head>
+|   <body>
+|     <p>
+|       "One"
+|     <p>
+|       "Two"
+
+#data
+<td>x
+#errors
+#document-fragment
+tr
+#document
+| <td>
+|   "x"
+
+#data
+<noscript><p>x</noscript>
+#errors
+#script-off
+#document
+| <html>
+|   <head>
+|     <noscript>
+|   <body>
+|     <p>
+|       "x"
+
+#data
+<b>wrong
+#errors
+#document
+| <html>
+|   <head>
+|   <body>
+|     <i>
+|       "wrong"
+"#;
+
+    fn parse(sink: GenerationalArenaDom, html: &str) -> GenerationalArenaDom {
+        html5ever::parse_document(sink, Default::default()).one(html)
+    }
+
+    fn parse_fragment(
+        sink: GenerationalArenaDom,
+        context: &QualName,
+        html: &str,
+    ) -> GenerationalArenaDom {
+        html5ever::parse_fragment(sink, Default::default(), context.clone(), vec![]).one(html)
+    }
+
+    #[test]
+    fn parses_dat_files() {
+        let tests = parse_dat(DAT);
+        assert_eq!(tests.len(), 4);
+        assert_eq!(tests[0].data, "<p>One<p>Two");
+        assert_eq!(
+            tests[0].errors,
+            ["(1,3): expected-doctype-but-got-start-tag"]
+        );
+        assert!(tests[0].expected.starts_with("| <html>\n|   <head>"));
+        assert!(tests[0].expected.ends_with("\"Two\""));
+        assert_eq!(tests[1].fragment_context.as_deref(), Some("tr"));
+        assert_eq!(tests[2].scripting, Some(false));
+        assert_eq!(tests[3].scripting, None);
+    }
+
+    #[test]
+    fn runs_tests() {
+        let tests = parse_dat(DAT);
+        assert_eq!(tests[0].run(&parse), TestOutcome::Passed);
+        assert_eq!(tests[0].run_fragment(&parse_fragment), TestOutcome::Skipped);
+        assert_eq!(tests[1].run(&parse), TestOutcome::Skipped);
+        assert_eq!(tests[1].run_fragment(&parse_fragment), TestOutcome::Passed);
+        assert_eq!(tests[2].run(&parse), TestOutcome::Skipped);
+        match tests[3].run(&parse) {
+            TestOutcome::Failed { actual } => assert!(actual.contains("|     <b>\n")),
+            outcome => panic!("expected a failure, got {:?}", outcome),
+        }
+    }
+}
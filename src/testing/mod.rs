@@ -0,0 +1,442 @@
+//! Utilities for testing code that produces DOMs.
+//!
+//! Enabled by the `test-utils` feature.
+
+use std::fmt::{self, Write};
+
+use crate::canonical::canonical_opts;
+use crate::{GenerationalArenaDom, Handle, NodeData, Parser, TreePath};
+
+pub mod html5lib;
+
+/// The first place where two documents differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomDifference {
+    /// Where the difference is, in the whitespace-normalized tree (see
+    /// [`canonical_string`](GenerationalArenaDom::canonical_string)).
+    pub path: TreePath,
+    /// A description of what was expected there.
+    pub expected: String,
+    /// A description of what was found there.
+    pub actual: String,
+}
+
+impl fmt::Display for DomDifference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "first difference at node /{}", self.path)?;
+        writeln!(f, "  expected: {}", self.expected)?;
+        write!(f, "    actual: {}", self.actual)
+    }
+}
+
+/// Compare two documents node by node, ignoring formatting whitespace and attribute order.
+pub fn diff_doms(
+    actual: &GenerationalArenaDom,
+    expected: &GenerationalArenaDom,
+) -> Option<DomDifference> {
+    let actual = actual.canonical_copy();
+    let expected = expected.canonical_copy();
+    diff_nodes(&actual, actual.document, &expected, expected.document)
+}
+
+/// Compare a document against the HTML it is expected to serialize to, ignoring formatting
+/// whitespace.
+///
+/// Without a parser the expected HTML can only be compared as text, so it must list attributes
+/// alphabetically and spell out implied elements, as in
+/// [`canonical_string`](GenerationalArenaDom::canonical_string). The difference is reported
+/// at the node whose output diverges from it. Use [`diff_parsed`] to compare structurally.
+pub fn diff_html(actual: &GenerationalArenaDom, expected: &str) -> Option<DomDifference> {
+    let copy = actual.canonical_copy();
+    let (output, map) = copy.serialize_with_source_map(copy.document, &canonical_opts());
+    let expected = normalize_html(expected);
+    let offset = output
+        .char_indices()
+        .zip(expected.chars())
+        .find(|((_, a), b)| a != b)
+        .map(|((i, _), _)| i)
+        .or_else(|| (output.len() != expected.len()).then(|| output.len().min(expected.len())))?;
+    let node = map
+        .node_at(offset)
+        .or_else(|| copy.document.children(&copy.arena).last())
+        .unwrap_or(copy.document);
+    // Show the expected text from where the differing node starts.
+    let start = map
+        .range_of(node)
+        .map_or(offset, |range| range.start.min(offset));
+    Some(DomDifference {
+        path: copy.path_of(node).unwrap_or_default(),
+        expected: snippet(&expected[start..]),
+        actual: describe(&copy, node),
+    })
+}
+
+/// Parse `expected` with `parser` and compare the result structurally with [`diff_doms`].
+pub fn diff_parsed<P>(
+    actual: &GenerationalArenaDom,
+    expected: &str,
+    parser: &P,
+) -> Option<DomDifference>
+where
+    P: Parser + ?Sized,
+{
+    let expected = parser.parse(GenerationalArenaDom::default(), expected);
+    diff_doms(actual, &expected)
+}
+
+/// Compare two documents like [`diff_doms`], but report every difference rather than the
+/// first, as a unified-diff-like listing for test failure messages. Returns an empty string if
+/// the documents are equal.
+///
+/// Each difference starts with an `@@ /path @@` line giving where it is in `actual`, followed
+/// by the expected nodes prefixed by `-` and the actual ones by `+`, with their descendants
+/// indented below them. Children are matched up by a longest common subsequence of their
+/// subtrees, so a node missing from a list doesn't make every later sibling differ. The
+/// children left over on both sides are compared in pairs, further down if they differ below
+/// themselves, such as in their text.
+pub fn diff_to_string(actual: &GenerationalArenaDom, expected: &GenerationalArenaDom) -> String {
+    let actual = actual.canonical_copy();
+    let expected = expected.canonical_copy();
+    let mut out = String::new();
+    diff_children(
+        &actual,
+        actual.document,
+        &expected,
+        expected.document,
+        &TreePath::default(),
+        &mut out,
+    );
+    if out.is_empty() {
+        return out;
+    }
+    format!("--- expected\n+++ actual\n{}", out)
+}
+
+/// Assert that a DOM matches another DOM or an HTML string, printing the first differing node
+/// on failure.
+///
+/// - `assert_dom_eq!(actual, expected_dom)` compares two [`GenerationalArenaDom`]s with
+///   [`diff_doms`](crate::testing::diff_doms).
+/// - `assert_dom_eq!(actual, "<html>...", parser)` parses the expected HTML with a
+///   [`Parser`](crate::Parser) and compares structurally.
+/// - `assert_dom_eq!(actual, html: "<html>...")` compares against canonical HTML text with
+///   [`diff_html`](crate::testing::diff_html).
+#[macro_export]
+macro_rules! assert_dom_eq {
+    ($actual:expr, html: $expected:expr $(,)?) => {
+        if let Some(difference) = $crate::testing::diff_html(&$actual, $expected) {
+            panic!("DOMs are not equal\n{}", difference);
+        }
+    };
+    ($actual:expr, $expected:expr, $parser:expr $(,)?) => {
+        if let Some(difference) = $crate::testing::diff_parsed(&$actual, $expected, &$parser) {
+            panic!("DOMs are not equal\n{}", difference);
+        }
+    };
+    ($actual:expr, $expected:expr $(,)?) => {
+        if let Some(difference) = $crate::testing::diff_doms(&$actual, &$expected) {
+            panic!("DOMs are not equal\n{}", difference);
+        }
+    };
+}
+
+fn diff_nodes(
+    actual: &GenerationalArenaDom,
+    actual_node: Handle,
+    expected: &GenerationalArenaDom,
+    expected_node: Handle,
+) -> Option<DomDifference> {
+    let difference = |expected_desc: String, actual_desc: String| DomDifference {
+        path: actual.path_of(actual_node).unwrap_or_default(),
+        expected: expected_desc,
+        actual: actual_desc,
+    };
+    let (actual_desc, expected_desc) = (
+        describe(actual, actual_node),
+        describe(expected, expected_node),
+    );
+    if actual_desc != expected_desc {
+        return Some(difference(expected_desc, actual_desc));
+    }
+
+    let mut actual_children = actual_node.children(&actual.arena);
+    let mut expected_children = expected_node.children(&expected.arena);
+    let mut index = 0;
+    loop {
+        match (actual_children.next(), expected_children.next()) {
+            (Some(a), Some(e)) => {
+                if let Some(difference) = diff_nodes(actual, a, expected, e) {
+                    return Some(difference);
+                }
+            }
+            (Some(a), None) => {
+                return Some(DomDifference {
+                    path: actual.path_of(a).unwrap_or_default(),
+                    expected: "(no node)".to_string(),
+                    actual: describe(actual, a),
+                })
+            }
+            (None, Some(e)) => {
+                let mut path = actual.path_of(actual_node).unwrap_or_default();
+                path.0.push(index);
+                return Some(DomDifference {
+                    path,
+                    expected: describe(expected, e),
+                    actual: "(no node)".to_string(),
+                });
+            }
+            (None, None) => return None,
+        }
+        index += 1;
+    }
+}
+
+/// Add the differences between the children of `actual_parent` at `path` and those of
+/// `expected_parent` to `out`.
+fn diff_children(
+    actual: &GenerationalArenaDom,
+    actual_parent: Handle,
+    expected: &GenerationalArenaDom,
+    expected_parent: Handle,
+    path: &TreePath,
+    out: &mut String,
+) {
+    let actual_children: Vec<(Handle, String)> = actual_parent
+        .children(&actual.arena)
+        .map(|child| (child, describe_subtree(actual, child)))
+        .collect();
+    let expected_children: Vec<(Handle, String)> = expected_parent
+        .children(&expected.arena)
+        .map(|child| (child, describe_subtree(expected, child)))
+        .collect();
+
+    // lengths[i][j] is the length of the longest common subsequence of the children from
+    // actual_children[i] and expected_children[j] on.
+    let (n, m) = (actual_children.len(), expected_children.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = if actual_children[i].1 == expected_children[j].1 {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let child_path = |index: usize| {
+        let mut path = path.clone();
+        path.0.push(index);
+        path
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && actual_children[i].1 == expected_children[j].1 {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        // A run of unmatched children on either side.
+        let (start_i, start_j) = (i, j);
+        while i < n || j < m {
+            if i < n && j < m && actual_children[i].1 == expected_children[j].1 {
+                break;
+            }
+            if j < m && (i == n || lengths[i][j + 1] >= lengths[i + 1][j]) {
+                j += 1;
+            } else {
+                i += 1;
+            }
+        }
+        let changed = (i - start_i).min(j - start_j);
+        for k in 0..changed {
+            let actual_child = actual_children[start_i + k].0;
+            let expected_child = expected_children[start_j + k].0;
+            let actual_desc = describe(actual, actual_child);
+            let expected_desc = describe(expected, expected_child);
+            if actual_desc != expected_desc {
+                let _ = writeln!(out, "@@ /{} @@", child_path(start_i + k));
+                let _ = writeln!(out, "- {}", expected_desc);
+                let _ = writeln!(out, "+ {}", actual_desc);
+            }
+            diff_children(
+                actual,
+                actual_child,
+                expected,
+                expected_child,
+                &child_path(start_i + k),
+                out,
+            );
+        }
+        for (expected_child, _) in &expected_children[start_j + changed..j] {
+            let _ = writeln!(out, "@@ /{} @@", child_path(start_i + changed));
+            write_subtree(expected, *expected_child, '-', 0, out);
+        }
+        for (k, (actual_child, _)) in actual_children[..i]
+            .iter()
+            .enumerate()
+            .skip(start_i + changed)
+        {
+            let _ = writeln!(out, "@@ /{} @@", child_path(k));
+            write_subtree(actual, *actual_child, '+', 0, out);
+        }
+    }
+}
+
+/// Add the descriptions of `handle` and its descendants to `out`, prefixed by `marker`.
+fn write_subtree(
+    dom: &GenerationalArenaDom,
+    handle: Handle,
+    marker: char,
+    depth: usize,
+    out: &mut String,
+) {
+    let _ = writeln!(
+        out,
+        "{} {}{}",
+        marker,
+        "  ".repeat(depth),
+        describe(dom, handle)
+    );
+    for child in handle.children(&dom.arena) {
+        write_subtree(dom, child, marker, depth + 1, out);
+    }
+}
+
+/// A description of `handle` and its descendants, the same for subtrees that are equal.
+fn describe_subtree(dom: &GenerationalArenaDom, handle: Handle) -> String {
+    let mut out = String::new();
+    write_subtree(dom, handle, ' ', 0, &mut out);
+    out
+}
+
+/// A one-line description of a node, without its descendants.
+pub(crate) fn describe(dom: &GenerationalArenaDom, handle: Handle) -> String {
+    match dom.get_node(&handle) {
+        NodeData::Document => "#document".to_string(),
+        NodeData::Doctype { name, .. } => format!("<!DOCTYPE {}>", name),
+        NodeData::Text { contents } => format!("{:?}", &**contents.borrow()),
+        NodeData::Comment { contents } => format!("<!--{}-->", contents),
+        NodeData::ProcessingInstruction { target, contents } => {
+            format!("<?{} {}>", target, contents)
+        }
+        NodeData::Element { name, attrs, .. } => {
+            let mut attrs: Vec<String> = attrs
+                .borrow()
+                .iter()
+                .map(|attr| format!(" {}={:?}", attr.name.local, &*attr.value))
+                .collect();
+            attrs.sort();
+            format!("<{}{}>", name.local, attrs.concat())
+        }
+    }
+}
+
+/// Collapse whitespace runs and drop whitespace between tags.
+fn normalize_html(html: &str) -> String {
+    let mut normalized = String::with_capacity(html.len());
+    let mut pending_space = false;
+    for c in html.trim().chars() {
+        if c.is_ascii_whitespace() {
+            pending_space = true;
+            continue;
+        }
+        if pending_space && !(normalized.ends_with('>') && c == '<') && !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        pending_space = false;
+        normalized.push(c);
+    }
+    normalized
+}
+
+/// The start of `text`, for showing in a difference report.
+fn snippet(text: &str) -> String {
+    const MAX_CHARS: usize = 40;
+    let mut snippet: String = text.chars().take(MAX_CHARS).collect();
+    if text.chars().count() > MAX_CHARS {
+        snippet.push_str("...");
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::tendril::TendrilSink;
+
+    use super::{diff_doms, diff_html, diff_parsed, diff_to_string};
+    use crate::parse::parse_html;
+    use crate::{GenerationalArenaDom, TreePath};
+
+    fn parse(sink: GenerationalArenaDom, html: &str) -> GenerationalArenaDom {
+        html5ever::parse_document(sink, Default::default()).one(html)
+    }
+
+    #[test]
+    fn compares_documents() {
+        let actual = parse_html("<ul><li b=2 a=1>one</li>\n<li>two</li></ul>");
+        let same = parse_html("<ul>\n  <li a=1 b=2>one</li>\n  <li>two</li>\n</ul>");
+        assert_eq!(diff_doms(&actual, &same), None);
+        assert_dom_eq!(actual, same);
+
+        let different = parse_html("<ul><li a=1 b=2>one</li><li>three</li></ul>");
+        let difference = diff_doms(&actual, &different).unwrap();
+        assert_eq!(difference.path, TreePath(vec![0, 1, 0, 1, 0]));
+        assert_eq!(difference.expected, "\"three\"");
+        assert_eq!(difference.actual, "\"two\"");
+        assert_eq!(
+            difference.to_string(),
+            "first difference at node /0/1/0/1/0\n  expected: \"three\"\n    actual: \"two\""
+        );
+
+        let shorter = parse_html("<ul><li a=1 b=2>one</li></ul>");
+        let difference = diff_doms(&actual, &shorter).unwrap();
+        assert_eq!(difference.expected, "(no node)");
+        assert_eq!(difference.actual, "<li>");
+    }
+
+    #[test]
+    fn compares_against_html() {
+        let actual = parse_html("<p id=x>hello</p>");
+        let expected = "<html><head></head><body>\n  <p id=\"x\">hello</p>\n</body></html>";
+        assert_eq!(diff_html(&actual, expected), None);
+        assert_dom_eq!(actual, html: expected);
+        assert_eq!(diff_parsed(&actual, "<p id=x>hello</p>", &parse), None);
+        assert_dom_eq!(actual, "<p id=x>hello</p>", parse);
+
+        let difference = diff_html(&actual, "<html><head></head><body><p id=\"y\">").unwrap();
+        assert_eq!(difference.path, TreePath(vec![0, 1, 0]));
+        assert_eq!(difference.actual, "<p id=\"x\">");
+        assert!(diff_parsed(&actual, "<p>hello</p>", &parse).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "DOMs are not equal")]
+    fn assert_dom_eq_panics_on_differences() {
+        let actual = parse_html("<p>a</p>");
+        assert_dom_eq!(actual, parse_html("<p>b</p>"));
+    }
+
+    #[test]
+    fn lists_every_difference() {
+        let actual = parse_html("<p>a</p><p>b</p><div>c</div>");
+        let expected = parse_html("<p>a</p><p>x</p><div>c</div><hr>");
+        assert_eq!(diff_to_string(&actual, &actual), "");
+        assert_eq!(
+            diff_to_string(&actual, &expected),
+            "--- expected\n+++ actual\n@@ /0/1/1/0 @@\n- \"x\"\n+ \"b\"\n@@ /0/1/3 @@\n- <hr>\n"
+        );
+    }
+
+    #[test]
+    fn diffs_changed_and_added_nodes() {
+        let actual = parse_html("<ul class=a><li>1</li><li>new</li><li>2</li></ul><p>x</p>");
+        let expected = parse_html("<ul class=b><li>1</li><li>2</li></ul><p>x</p>");
+        assert_eq!(
+            diff_to_string(&actual, &expected),
+            "--- expected\n+++ actual\n\
+             @@ /0/1/0 @@\n- <ul class=\"b\">\n+ <ul class=\"a\">\n\
+             @@ /0/1/0/1 @@\n+ <li>\n+   \"new\"\n"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Storage strategies for the contents of text nodes.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::mem;
+
+use markup5ever::tendril::StrTendril;
+#[cfg(feature = "unicode-normalization")]
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Tendrils address their contents with `u32`s, so a shared buffer can't grow past this.
+const MAX_BUFFER_LEN: usize = u32::MAX as usize;
+
+/// How the contents of text nodes are stored.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextStorage {
+    /// Every text node owns its own tendril.
+    #[default]
+    PerNode,
+
+    /// Once parsing finishes, the contents of every text node are packed into a shared buffer
+    /// and each node keeps a slice of it. This trades one allocation per node for a single large
+    /// one, and concatenating slices that are adjacent in the buffer doesn't copy.
+    Shared,
+}
+
+/// A Unicode normalization function applied to the contents of text nodes.
+///
+/// With the `unicode-normalization` feature, [`TextNormalizer::nfc`] and
+/// [`TextNormalizer::nfkc`] normalize with the `unicode-normalization` crate. Any other function
+/// can be wrapped with [`TextNormalizer::new`]. Text that is entirely ASCII is already normalized
+/// in every form and is never passed to the function.
+pub struct TextNormalizer {
+    normalize: Box<dyn Fn(&str) -> String>,
+}
+
+impl TextNormalizer {
+    /// Normalize to Normalization Form C, composing characters wherever Unicode defines a
+    /// canonical composite.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfc() -> TextNormalizer {
+        TextNormalizer::new(|text| text.nfc().collect())
+    }
+
+    /// Normalize to Normalization Form KC, which also replaces compatibility characters such as
+    /// ligatures and circled digits with their plain equivalents.
+    #[cfg(feature = "unicode-normalization")]
+    pub fn nfkc() -> TextNormalizer {
+        TextNormalizer::new(|text| text.nfkc().collect())
+    }
+
+    /// Wrap a normalization function.
+    pub fn new(normalize: impl Fn(&str) -> String + 'static) -> TextNormalizer {
+        TextNormalizer {
+            normalize: Box::new(normalize),
+        }
+    }
+
+    /// Normalize `text`, or return `None` if it is unchanged.
+    fn apply(&self, text: &str) -> Option<String> {
+        if text.is_ascii() {
+            return None;
+        }
+        let normalized = (self.normalize)(text);
+        (normalized != text).then_some(normalized)
+    }
+}
+
+impl fmt::Debug for TextNormalizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TextNormalizer").finish_non_exhaustive()
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Normalize the contents of every text node in the arena with `normalizer`.
+    ///
+    /// This is done automatically at the end of parsing when
+    /// [`DomOptions::text_normalizer`](crate::DomOptions::text_normalizer) is set. Adjacent
+    /// chunks of text are merged into a single node by the parser, so normalizing once the
+    /// parse is done handles combining sequences split across chunks. Frozen text is left alone.
+    pub fn normalize_text(&mut self, normalizer: &TextNormalizer) {
+        for (handle, node) in self.arena.iter_pairs() {
+            if let NodeData::Text { contents } = node.get() {
+                let normalized = normalizer.apply(&contents.borrow());
+                if let Some(normalized) = normalized {
+                    if self.skip_frozen(handle) {
+                        continue;
+                    }
+                    *contents.borrow_mut() = StrTendril::from(normalized);
+                }
+            }
+        }
+        self.mark_mutated();
+    }
+
+    /// Pack the contents of every text node in the arena into shared buffers.
+    ///
+    /// This is done automatically at the end of parsing when the DOM was created with
+    /// [`TextStorage::Shared`], but can be called again after heavy mutation. Modifying a text
+    /// node afterwards copies its contents out of the shared buffer first.
+    pub fn share_text(&mut self) {
+        let texts: Vec<Handle> = self
+            .arena
+            .iter_pairs()
+            .filter(|(_, node)| matches!(node.get(), NodeData::Text { .. }))
+            .map(|(id, _)| id)
+            .collect();
+
+        let mut buffer = String::new();
+        let mut pending = vec![];
+        for handle in texts {
+            let len = self.text_contents(handle).borrow().len();
+            if !buffer.is_empty() && buffer.len() + len > MAX_BUFFER_LEN {
+                self.flush_text_buffer(&mut buffer, &mut pending);
+            }
+            buffer.push_str(&self.text_contents(handle).borrow());
+            pending.push(handle);
+        }
+        self.flush_text_buffer(&mut buffer, &mut pending);
+    }
+
+    fn flush_text_buffer(&self, buffer: &mut String, pending: &mut Vec<Handle>) {
+        let shared = StrTendril::from_slice(&mem::take(buffer));
+        let mut offset = 0;
+        for handle in pending.drain(..) {
+            let mut contents = self.text_contents(handle).borrow_mut();
+            let len = contents.len32();
+            *contents = shared.subtendril(offset, len);
+            offset += len;
+        }
+    }
+
+    fn text_contents(&self, handle: Handle) -> &RefCell<StrTendril> {
+        match self.get_node(&handle) {
+            NodeData::Text { contents } => contents,
+            _ => panic!("not a text node!"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use html5ever::tendril::TendrilSink;
+
+    use crate::{DomOptions, GenerationalArenaDom, NodeData, TextNormalizer, TextStorage};
+
+    #[test]
+    fn shares_text_buffers() {
+        let html = "<p>The first paragraph</p><p>The second paragraph</p>";
+        let sink = GenerationalArenaDom::with_options(DomOptions {
+            text_storage: TextStorage::Shared,
+            ..DomOptions::default()
+        });
+        let dom = html5ever::parse_document(sink, Default::default()).one(html);
+        let texts: Vec<_> = dom
+            .document
+            .descendants(&dom.arena)
+            .filter_map(|node| match dom.get_node(&node) {
+                NodeData::Text { contents } => Some((node, contents.borrow().clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(texts.len(), 2);
+        assert!(texts.iter().all(|(_, text)| text.is_shared()));
+        assert_eq!(
+            dom.text_content(dom.document),
+            "The first paragraphThe second paragraph"
+        );
+
+        // Changing one node leaves the other's slice alone.
+        if let NodeData::Text { contents } = dom.get_node(&texts[0].0) {
+            contents.borrow_mut().push_slice(", changed");
+        }
+        assert_eq!(
+            dom.text_content(dom.document),
+            "The first paragraph, changedThe second paragraph"
+        );
+    }
+
+    #[test]
+    fn normalizes_text_nodes() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("p{Cafe\u{301}}+p{ascii}").unwrap();
+        let normalizer = TextNormalizer::new(|text| text.replace("e\u{301}", "\u{E9}"));
+        dom.normalize_text(&normalizer);
+        assert_eq!(dom.text_content(dom.document), "Caf\u{E9}ascii");
+
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        dom.freeze(p);
+        let shouting = TextNormalizer::new(|text| text.to_uppercase());
+        dom.normalize_text(&shouting);
+        assert_eq!(dom.text_content(dom.document), "Caf\u{E9}ascii");
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn normalizes_to_nfc_and_nfkc() {
+        let mut dom =
+            crate::parse::parse_html("<p>Cafe\u{301} \u{FB01}ne \u{1100}\u{1161}\u{11A8}</p>");
+        dom.normalize_text(&TextNormalizer::nfc());
+        assert_eq!(
+            dom.text_content(dom.document),
+            "Caf\u{E9} \u{FB01}ne \u{AC01}"
+        );
+        dom.normalize_text(&TextNormalizer::nfkc());
+        assert_eq!(dom.text_content(dom.document), "Caf\u{E9} fine \u{AC01}");
+    }
+}
@@ -0,0 +1,485 @@
+//! Parsers for attribute values that hold lists of tokens.
+//!
+//! These follow the HTML spec's microsyntaxes rather than `str::split_whitespace`, which also
+//! splits on non-ASCII spaces such as U+00A0 that HTML treats as part of a token.
+
+use std::iter::FusedIterator;
+
+/// Split a [set of space-separated tokens], such as the value of `class`, `rel` or `sandbox`,
+/// on ASCII whitespace. Duplicates are kept; see [`unique_tokens`] to drop them.
+///
+/// [set of space-separated tokens]: https://html.spec.whatwg.org/multipage/#set-of-space-separated-tokens
+pub fn space_tokens(value: &str) -> SpaceTokens<'_> {
+    SpaceTokens { rest: value }
+}
+
+/// The tokens of an [unordered set of unique space-separated tokens], keeping the first of each
+/// duplicate. `rel` and `sandbox` keywords are ASCII case-insensitive, so pass
+/// `ignore_case = true` for them and `false` for case-sensitive values such as `class`.
+///
+/// [unordered set of unique space-separated tokens]: https://html.spec.whatwg.org/multipage/#unordered-set-of-unique-space-separated-tokens
+pub fn unique_tokens(value: &str, ignore_case: bool) -> Vec<&str> {
+    let mut tokens: Vec<&str> = Vec::new();
+    for token in space_tokens(value) {
+        let seen = tokens.iter().any(|seen| {
+            if ignore_case {
+                seen.eq_ignore_ascii_case(token)
+            } else {
+                *seen == token
+            }
+        });
+        if !seen {
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+/// Whether the space-separated token list `value` contains `token`, ignoring ASCII case. This
+/// is the right comparison for keyword lists such as `rel` and `sandbox`, but not `class`.
+pub fn has_token(value: &str, token: &str) -> bool {
+    space_tokens(value).any(|candidate| candidate.eq_ignore_ascii_case(token))
+}
+
+/// Split a [comma-separated list] of tokens, such as the value of `accept`, stripping ASCII
+/// whitespace around each token. As in the spec, empty tokens (e.g. from a trailing comma) are
+/// kept, and an empty value has one empty token.
+///
+/// [comma-separated list]: https://html.spec.whatwg.org/multipage/#split-a-string-on-commas
+pub fn comma_tokens(value: &str) -> CommaTokens<'_> {
+    CommaTokens { rest: Some(value) }
+}
+
+/// Parse the `sizes` attribute of `<link rel="icon">`: ASCII case-insensitive `any` or
+/// `<width>x<height>` tokens. Invalid tokens, such as `0x16` or `016x16`, are skipped.
+pub fn icon_sizes(value: &str) -> IconSizes<'_> {
+    IconSizes {
+        tokens: space_tokens(value),
+    }
+}
+
+/// Parse the `sizes` attribute of `<img>` and `<source>`: a comma-separated list of
+/// [source sizes], each a length optionally preceded by a media condition, e.g.
+/// `(max-width: 600px) 100vw, 50vw`. Entries without a valid length are skipped.
+///
+/// [source sizes]: https://html.spec.whatwg.org/multipage/#parse-a-sizes-attribute
+pub fn source_sizes(value: &str) -> SourceSizes<'_> {
+    SourceSizes { rest: value }
+}
+
+/// Parse a [`srcset` attribute] into its image candidates. Candidates with invalid or
+/// conflicting descriptors are skipped, as browsers do.
+///
+/// [`srcset` attribute]: https://html.spec.whatwg.org/multipage/#parse-a-srcset-attribute
+pub fn srcset_candidates(value: &str) -> SrcsetCandidates<'_> {
+    SrcsetCandidates { rest: value }
+}
+
+/// An iterator over space-separated tokens, returned by [`space_tokens`].
+#[derive(Clone, Debug)]
+pub struct SpaceTokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for SpaceTokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let start = self.rest.trim_start_matches(is_ascii_whitespace);
+        if start.is_empty() {
+            self.rest = start;
+            return None;
+        }
+        let end = start.find(is_ascii_whitespace).unwrap_or(start.len());
+        self.rest = &start[end..];
+        Some(&start[..end])
+    }
+}
+
+impl FusedIterator for SpaceTokens<'_> {}
+
+/// An iterator over comma-separated tokens, returned by [`comma_tokens`].
+#[derive(Clone, Debug)]
+pub struct CommaTokens<'a> {
+    rest: Option<&'a str>,
+}
+
+impl<'a> Iterator for CommaTokens<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.rest?;
+        let token = match rest.split_once(',') {
+            Some((token, rest)) => {
+                self.rest = Some(rest);
+                token
+            }
+            None => {
+                self.rest = None;
+                rest
+            }
+        };
+        Some(token.trim_matches(is_ascii_whitespace))
+    }
+}
+
+impl FusedIterator for CommaTokens<'_> {}
+
+/// One token of an icon's `sizes` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum IconSize {
+    /// `any`: the icon is scalable, e.g. SVG.
+    Any,
+    /// A bitmap size in pixels.
+    Pixels {
+        /// The width.
+        width: u32,
+        /// The height.
+        height: u32,
+    },
+}
+
+/// An iterator over icon sizes, returned by [`icon_sizes`].
+#[derive(Clone, Debug)]
+pub struct IconSizes<'a> {
+    tokens: SpaceTokens<'a>,
+}
+
+impl Iterator for IconSizes<'_> {
+    type Item = IconSize;
+
+    fn next(&mut self) -> Option<IconSize> {
+        self.tokens.find_map(|token| {
+            if token.eq_ignore_ascii_case("any") {
+                return Some(IconSize::Any);
+            }
+            let (width, height) = token.split_once(['x', 'X'])?;
+            Some(IconSize::Pixels {
+                width: dimension(width)?,
+                height: dimension(height)?,
+            })
+        })
+    }
+}
+
+impl FusedIterator for IconSizes<'_> {}
+
+/// A valid non-negative integer without a leading zero, as `sizes` requires.
+fn dimension(digits: &str) -> Option<u32> {
+    let valid = !digits.is_empty()
+        && !digits.starts_with('0')
+        && digits.bytes().all(|byte| byte.is_ascii_digit());
+    valid.then(|| digits.parse().ok()).flatten()
+}
+
+/// One entry of an `<img sizes>` attribute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SourceSize<'a> {
+    /// The media condition the entry applies under, e.g. `(max-width: 600px)`, or `None` for an
+    /// unconditional entry.
+    pub condition: Option<&'a str>,
+    /// The length, e.g. `100vw`, `calc(100vw - 2em)` or `auto`.
+    pub length: &'a str,
+}
+
+/// An iterator over source sizes, returned by [`source_sizes`].
+#[derive(Clone, Debug)]
+pub struct SourceSizes<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for SourceSizes<'a> {
+    type Item = SourceSize<'a>;
+
+    fn next(&mut self) -> Option<SourceSize<'a>> {
+        while !self.rest.is_empty() {
+            // Commas inside parentheses, as in `min(50vw, 400px)`, don't separate entries.
+            let mut depth = 0usize;
+            let end = self
+                .rest
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth = depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    c == ',' && depth == 0
+                })
+                .map(|(index, _)| index);
+            let entry = match end {
+                Some(end) => {
+                    let entry = &self.rest[..end];
+                    self.rest = &self.rest[end + 1..];
+                    entry
+                }
+                None => std::mem::take(&mut self.rest),
+            };
+            if let Some(size) = source_size(entry.trim_matches(is_ascii_whitespace)) {
+                return Some(size);
+            }
+        }
+        None
+    }
+}
+
+impl FusedIterator for SourceSizes<'_> {}
+
+fn source_size(entry: &str) -> Option<SourceSize<'_>> {
+    // The length is the last component value: a function such as `calc(...)` or a token
+    // running back to whitespace or a closing parenthesis.
+    let start = if entry.ends_with(')') {
+        let mut depth = 0usize;
+        let open = entry.char_indices().rev().find_map(|(index, c)| {
+            match c {
+                ')' => depth += 1,
+                '(' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(index);
+                    }
+                }
+                _ => {}
+            }
+            None
+        })?;
+        entry[..open]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+            .map_or(0, |index| index + 1)
+    } else {
+        entry
+            .rfind(|c: char| is_ascii_whitespace(c) || c == ')')
+            .map_or(0, |index| index + 1)
+    };
+    let length = &entry[start..];
+    if !is_source_size_value(length) {
+        return None;
+    }
+    let condition = entry[..start].trim_matches(is_ascii_whitespace);
+    Some(SourceSize {
+        condition: (!condition.is_empty()).then_some(condition),
+        length,
+    })
+}
+
+/// Whether `value` is `auto`, zero, a non-negative length with a unit, or a math function.
+fn is_source_size_value(value: &str) -> bool {
+    if value.eq_ignore_ascii_case("auto") || value == "0" {
+        return true;
+    }
+    if let Some(open) = value.find('(') {
+        let name = &value[..open];
+        return ["calc", "min", "max", "clamp"]
+            .iter()
+            .any(|function| function.eq_ignore_ascii_case(name));
+    }
+    let number_end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '+'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(number_end);
+    number.parse::<f64>().is_ok_and(|number| number >= 0.0)
+        && !unit.is_empty()
+        && unit.bytes().all(|byte| byte.is_ascii_alphabetic())
+}
+
+/// How an image candidate from a `srcset` attribute describes its size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageDescriptor {
+    /// A width descriptor such as `640w`: the image's intrinsic width in pixels.
+    Width(u32),
+    /// A pixel density descriptor such as `2x`. A candidate without a descriptor has a density
+    /// of 1.
+    Density(f64),
+}
+
+/// One image candidate of a `srcset` attribute.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SrcsetCandidate<'a> {
+    /// The URL, as written.
+    pub url: &'a str,
+    /// The candidate's size.
+    pub descriptor: ImageDescriptor,
+}
+
+/// An iterator over the candidates of a `srcset` attribute, returned by [`srcset_candidates`].
+#[derive(Clone, Debug)]
+pub struct SrcsetCandidates<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for SrcsetCandidates<'a> {
+    type Item = SrcsetCandidate<'a>;
+
+    fn next(&mut self) -> Option<SrcsetCandidate<'a>> {
+        loop {
+            let rest = self
+                .rest
+                .trim_start_matches(|c| is_ascii_whitespace(c) || c == ',');
+            if rest.is_empty() {
+                self.rest = rest;
+                return None;
+            }
+            let url_end = rest.find(is_ascii_whitespace).unwrap_or(rest.len());
+            let url = &rest[..url_end];
+            // A URL ending in commas ends the candidate, without descriptors.
+            if url.ends_with(',') {
+                self.rest = &rest[url_end..];
+                return Some(SrcsetCandidate {
+                    url: url.trim_end_matches(','),
+                    descriptor: ImageDescriptor::Density(1.0),
+                });
+            }
+            // Descriptors run to the next comma outside parentheses.
+            let rest = &rest[url_end..];
+            let mut depth = 0usize;
+            let end = rest
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        '(' => depth += 1,
+                        ')' => depth = depth.saturating_sub(1),
+                        _ => {}
+                    }
+                    c == ',' && depth == 0
+                })
+                .map_or(rest.len(), |(index, _)| index);
+            self.rest = &rest[end..];
+            if let Some(descriptor) = image_descriptor(&rest[..end]) {
+                return Some(SrcsetCandidate { url, descriptor });
+            }
+        }
+    }
+}
+
+impl FusedIterator for SrcsetCandidates<'_> {}
+
+/// Parse a candidate's descriptors: at most one width or density, and a height only alongside
+/// a width.
+fn image_descriptor(descriptors: &str) -> Option<ImageDescriptor> {
+    let mut width = None;
+    let mut density = None;
+    let mut height = None;
+    for token in space_tokens(descriptors) {
+        let split = token.char_indices().last().map_or(0, |(index, _)| index);
+        let (number, kind) = token.split_at(split);
+        match kind {
+            "w" if width.is_none() && density.is_none() => {
+                width = Some(dimension(number)?);
+            }
+            "x" if width.is_none() && density.is_none() && height.is_none() => {
+                // `f64::from_str` also takes `inf` and `NaN`, which aren't valid here.
+                let numeric = number.bytes().all(|byte| {
+                    byte.is_ascii_digit() || matches!(byte, b'.' | b'e' | b'E' | b'-' | b'+')
+                });
+                let value: f64 = number.parse().ok().filter(|_| numeric)?;
+                if value < 0.0 {
+                    return None;
+                }
+                density = Some(value);
+            }
+            "h" if height.is_none() && density.is_none() => {
+                height = Some(dimension(number)?);
+            }
+            _ => return None,
+        }
+    }
+    if height.is_some() && width.is_none() {
+        return None;
+    }
+    Some(match (width, density) {
+        (Some(width), _) => ImageDescriptor::Width(width),
+        (None, Some(density)) => ImageDescriptor::Density(density),
+        (None, None) => ImageDescriptor::Density(1.0),
+    })
+}
+
+/// ASCII whitespace as HTML defines it: tab, line feed, form feed, carriage return and space.
+fn is_ascii_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\u{c}' | '\r' | ' ')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        comma_tokens, has_token, icon_sizes, source_sizes, space_tokens, srcset_candidates,
+        unique_tokens, IconSize, ImageDescriptor, SourceSize,
+    };
+
+    #[test]
+    fn splits_space_and_comma_separated_tokens() {
+        let tokens: Vec<&str> = space_tokens("\ta  b\u{a0}c \n").collect();
+        assert_eq!(tokens, ["a", "b\u{a0}c"]);
+        assert_eq!(space_tokens(" \t ").next(), None);
+
+        assert_eq!(unique_tokens("Next next prev", true), ["Next", "prev"]);
+        assert_eq!(
+            unique_tokens("Next next prev", false),
+            ["Next", "next", "prev"]
+        );
+        assert!(has_token("Alternate  STYLESHEET", "stylesheet"));
+        assert!(!has_token("nofollow", "follow"));
+
+        let tokens: Vec<&str> = comma_tokens(" a , b,,").collect();
+        assert_eq!(tokens, ["a", "b", "", ""]);
+        assert_eq!(comma_tokens("").collect::<Vec<_>>(), [""]);
+    }
+
+    #[test]
+    fn parses_icon_sizes() {
+        let sizes: Vec<IconSize> = icon_sizes("16x16 ANY 0x16 016x16 32X48 12 x16").collect();
+        assert_eq!(
+            sizes,
+            [
+                IconSize::Pixels {
+                    width: 16,
+                    height: 16
+                },
+                IconSize::Any,
+                IconSize::Pixels {
+                    width: 32,
+                    height: 48
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_source_sizes() {
+        let sizes: Vec<SourceSize> = source_sizes(
+            "(max-width: 600px) 100vw, min(50vw, 400px), bogus, -1px,\
+             (min-width: 1px) calc(100vw - 2em), auto",
+        )
+        .collect();
+        let size = |condition, length| SourceSize { condition, length };
+        assert_eq!(
+            sizes,
+            [
+                size(Some("(max-width: 600px)"), "100vw"),
+                size(None, "min(50vw, 400px)"),
+                size(Some("(min-width: 1px)"), "calc(100vw - 2em)"),
+                size(None, "auto"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_srcset_candidates() {
+        let candidates: Vec<(&str, ImageDescriptor)> = srcset_candidates(
+            "a.jpg 640w, b.jpg 2x,c.jpg, d.jpg 1.5x 2x, e.jpg 100h, f.jpg 300w 200h, \
+             g.jpg infx, data:image/png;base64,xyz 1x",
+        )
+        .map(|candidate| (candidate.url, candidate.descriptor))
+        .collect();
+        assert_eq!(
+            candidates,
+            [
+                ("a.jpg", ImageDescriptor::Width(640)),
+                ("b.jpg", ImageDescriptor::Density(2.0)),
+                ("c.jpg", ImageDescriptor::Density(1.0)),
+                ("f.jpg", ImageDescriptor::Width(300)),
+                ("data:image/png;base64,xyz", ImageDescriptor::Density(1.0)),
+            ]
+        );
+        assert_eq!(srcset_candidates(" , ").next(), None);
+    }
+}
@@ -0,0 +1,222 @@
+//! Tracing of parsing, selector queries and transforms, enabled by the `tracing` feature.
+//!
+//! Work is recorded in [`tracing`](https://docs.rs/tracing) spans, under targets named after
+//! the module doing it, e.g. `generational_arena_dom::select`:
+//!
+//! - `parse` lasts from a DOM's creation until its parser finishes, and records the number of
+//!   `nodes` and parse `errors`. Work done once the tree is built, such as text normalization,
+//!   is in a `parse_phase` span within it, named by its `phase` field.
+//! - `select` covers a selector query, and records how many nodes it `visited` and `matched`.
+//! - `transform` covers a transform, named by its `name` field, and records how many `nodes`
+//!   the DOM has and how many `changes` were made.
+//!
+//! Parse and transform spans are at debug level and selector queries, which are frequent, at
+//! trace level. Without the feature, the types here are empty and nothing is recorded.
+
+#[cfg(feature = "tracing")]
+use tracing::{field::Empty, span::EnteredSpan, Span};
+
+/// The span of a DOM's parse, kept open until the parser finishes.
+#[derive(Debug)]
+pub(crate) struct ParseSpan {
+    #[cfg(feature = "tracing")]
+    span: Span,
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+impl ParseSpan {
+    /// Open the span.
+    #[inline]
+    pub(crate) fn start() -> ParseSpan {
+        ParseSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                target: "generational_arena_dom::parse",
+                "parse",
+                nodes = Empty,
+                errors = Empty
+            ),
+        }
+    }
+
+    /// Enter a span for the parse phase `phase`, within the parse's span.
+    #[inline]
+    pub(crate) fn phase(&self, phase: &'static str) -> TraceSpan {
+        TraceSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                target: "generational_arena_dom::parse",
+                parent: &self.span,
+                "parse_phase",
+                phase
+            )
+            .entered(),
+        }
+    }
+
+    /// Record that the parse finished with `nodes` nodes and `errors` parse errors, and close
+    /// the span.
+    #[inline]
+    pub(crate) fn finish(&mut self, nodes: usize, errors: usize) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("nodes", nodes);
+            self.span.record("errors", errors);
+            self.span = Span::none();
+        }
+    }
+}
+
+/// The span of a selector query or a transform, entered until it's dropped.
+#[derive(Debug)]
+pub(crate) struct TraceSpan {
+    #[cfg(feature = "tracing")]
+    span: EnteredSpan,
+}
+
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+impl TraceSpan {
+    /// Enter a span for a selector query.
+    #[inline]
+    pub(crate) fn select() -> TraceSpan {
+        TraceSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!(
+                target: "generational_arena_dom::select",
+                "select",
+                visited = Empty,
+                matched = Empty
+            )
+            .entered(),
+        }
+    }
+
+    /// Enter a span for the transform `name`.
+    #[inline]
+    pub(crate) fn transform(name: &'static str) -> TraceSpan {
+        TraceSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                target: "generational_arena_dom::transform",
+                "transform",
+                name,
+                nodes = Empty,
+                changes = Empty
+            )
+            .entered(),
+        }
+    }
+
+    /// Record that a selector query visited `visited` nodes and matched `matched`.
+    #[inline]
+    pub(crate) fn matched(&self, visited: usize, matched: usize) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("visited", visited);
+            self.span.record("matched", matched);
+        }
+    }
+
+    /// Record that a transform made `changes` changes to a DOM of `nodes` nodes, and return
+    /// `changes`.
+    #[inline]
+    pub(crate) fn changed(&self, nodes: usize, changes: usize) -> usize {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("nodes", nodes);
+            self.span.record("changes", changes);
+        }
+        changes
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::parse::parse_html;
+    use crate::Selector;
+
+    /// Records the spans opened and the fields given to them, as `name field=value ...`.
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<String>>>,
+    }
+
+    /// Appends the fields it visits to a line of [`Recorder`]'s.
+    struct Fields<'a>(&'a mut String);
+
+    impl Visit for Fields<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut spans = self.spans.lock().unwrap();
+            let mut line = span.metadata().name().to_string();
+            span.record(&mut Fields(&mut line));
+            spans.push(line);
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut Fields(&mut spans[span.into_u64() as usize - 1]));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn traces_parses_queries_and_transforms() {
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let mut dom = parse_html("<!DOCTYPE html><p>One</p><p>Two<script>x()</script></p>");
+            dom.select(dom.document, &Selector::parse("p").unwrap());
+            dom.sanitize(dom.document, &Default::default());
+        });
+        assert_eq!(
+            *recorder.spans.lock().unwrap(),
+            [
+                "parse nodes=11 errors=0",
+                "select visited=10 matched=2",
+                "transform name=\"sanitize\" nodes=9 changes=1",
+            ]
+        );
+    }
+
+    #[test]
+    fn traces_nested_transforms() {
+        let mut dom = parse_html("<p>one two one</p>");
+        let recorder = Recorder::default();
+        tracing::subscriber::with_default(recorder.clone(), || {
+            dom.replace_text("two", "three", dom.document);
+            dom.highlight_text(dom.document, "one", &Default::default());
+        });
+        assert_eq!(
+            *recorder.spans.lock().unwrap(),
+            [
+                "transform name=\"replace_text\" nodes=6 changes=1",
+                "transform name=\"highlight_text\" nodes=10 changes=2",
+                "transform name=\"highlight_ranges\" nodes=10 changes=2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// AMP components with a plain HTML equivalent, and the element they become.
+const RENAMED_COMPONENTS: &[(&str, &str)] = &[
+    ("amp-img", "img"),
+    ("amp-anim", "img"),
+    ("amp-video", "video"),
+    ("amp-audio", "audio"),
+    ("amp-iframe", "iframe"),
+];
+
+/// AMP components that only serve ads, analytics or AMP's own runtime, and are removed.
+const REMOVED_COMPONENTS: &[&str] = &[
+    "amp-ad",
+    "amp-analytics",
+    "amp-auto-ads",
+    "amp-consent",
+    "amp-embed",
+    "amp-geo",
+    "amp-install-serviceworker",
+    "amp-pixel",
+    "amp-sticky-ad",
+];
+
+/// Attributes that only mean something to the AMP runtime.
+const AMP_ATTRIBUTES: &[&str] = &[
+    "layout",
+    "heights",
+    "noloading",
+    "on",
+    "placeholder",
+    "fallback",
+];
+
+impl GenerationalArenaDom {
+    /// Rewrite an [AMP](https://amp.dev/) document into conventional HTML, returning how many
+    /// elements were changed or removed.
+    ///
+    /// `amp-img`, `amp-anim`, `amp-video`, `amp-audio` and `amp-iframe` become the elements
+    /// they stand for, and `amp-youtube` an embedded player `<iframe>`; their fallback and
+    /// placeholder children are removed. Ad and analytics components are removed, and other
+    /// `amp-*` components are replaced by their contents. The AMP runtime scripts, the
+    /// `amp-boilerplate` styles and the `amp`/`⚡` attribute of `<html>` are removed, and
+    /// `<style amp-custom>` is kept as an ordinary stylesheet. Frozen elements are left alone.
+    pub fn normalize_amp(&mut self) -> usize {
+        let span = TraceSpan::transform("normalize_amp");
+        let nodes: Vec<Handle> = self.document.descendants(&self.arena).collect();
+        let mut changed = 0;
+        for node in nodes {
+            if self.arena.get(node).is_none() {
+                continue;
+            }
+            let local = match self.element_name(node) {
+                Some(name) => name.local.to_string(),
+                None => continue,
+            };
+            let renamed = RENAMED_COMPONENTS.iter().find(|(amp, _)| *amp == local);
+            if (renamed.is_some() || local.starts_with("amp-")) && self.skip_frozen(node) {
+                continue;
+            }
+            if let Some(&(_, html)) = renamed {
+                self.remove_amp_children(node);
+                for attr in AMP_ATTRIBUTES {
+                    self.remove_attr(node, attr);
+                }
+                self.rename_html_element(node, html);
+            } else if local == "amp-youtube" {
+                self.remove_amp_children(node);
+                let id = self.get_attr(node, "data-videoid").unwrap_or_default();
+                let src = format!("https://www.youtube.com/embed/{}", id.trim());
+                let attrs = match self.get_node(&node) {
+                    NodeData::Element { attrs, .. } => attrs,
+                    _ => continue,
+                };
+                attrs.borrow_mut().retain(|attr| {
+                    matches!(
+                        &*attr.name.local,
+                        "width" | "height" | "title" | "class" | "id"
+                    )
+                });
+                self.set_attr(node, "src", StrTendril::from(src));
+                self.set_attr(node, "allowfullscreen", StrTendril::new());
+                self.rename_html_element(node, "iframe");
+            } else if REMOVED_COMPONENTS.contains(&local.as_str()) || self.is_amp_runtime(node) {
+                if self.skip_frozen_removal(node) {
+                    continue;
+                }
+                self.free_subtree(node);
+            } else if local.starts_with("amp-") {
+                self.unwrap_node(node);
+            } else if self.is_html_element(node, "html") {
+                let had = self.remove_attr(node, "amp").is_some()
+                    | self.remove_attr(node, "\u{26a1}").is_some();
+                if !had {
+                    continue;
+                }
+            } else if self.is_html_element(node, "style")
+                && self.get_attr(node, "amp-custom").is_some()
+            {
+                if self.remove_attr(node, "amp-custom").is_none() {
+                    continue;
+                }
+            } else {
+                continue;
+            }
+            changed += 1;
+        }
+        span.changed(self.arena.count(), changed)
+    }
+
+    /// Remove the children of an AMP component that only stand in until it loads.
+    fn remove_amp_children(&mut self, component: Handle) {
+        let doomed: Vec<Handle> = component
+            .children(&self.arena)
+            .filter(|&child| {
+                self.is_html_element(child, "noscript")
+                    || self.get_attr(child, "fallback").is_some()
+                    || self.get_attr(child, "placeholder").is_some()
+            })
+            .collect();
+        for child in doomed {
+            self.free_subtree(child);
+        }
+    }
+
+    /// Whether `node` is part of the AMP runtime: its scripts, or the boilerplate styles that
+    /// hide the page until they load.
+    fn is_amp_runtime(&self, node: Handle) -> bool {
+        if self.is_html_element(node, "script") {
+            return self.get_attr(node, "custom-element").is_some()
+                || self.get_attr(node, "custom-template").is_some()
+                || self
+                    .get_attr(node, "src")
+                    .is_some_and(|src| src.contains("cdn.ampproject.org"));
+        }
+        if self.is_html_element(node, "style") {
+            return self.get_attr(node, "amp-boilerplate").is_some();
+        }
+        // `<noscript><style amp-boilerplate>` undoes the boilerplate when scripts are off.
+        self.is_html_element(node, "noscript")
+            && node.children(&self.arena).any(|child| {
+                self.get_attr(child, "amp-boilerplate").is_some()
+                    || matches!(self.get_node(&child), NodeData::Text { contents }
+                        if contents.borrow().contains("amp-boilerplate"))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn rewrites_amp_components() {
+        let mut dom = parse_html(
+            "<amp-img src=/a.jpg width=10 height=5 layout=responsive>\
+             <amp-img fallback src=/b.jpg></amp-img><noscript><img src=/a.jpg></noscript>\
+             </amp-img>\
+             <amp-youtube data-videoid=abc width=480 layout=responsive><div placeholder></div>\
+             </amp-youtube>\
+             <amp-ad type=x></amp-ad><amp-accordion><section>s</section></amp-accordion>",
+        );
+        assert_eq!(dom.normalize_amp(), 4);
+        assert_eq!(
+            body_html(&dom),
+            "<img src=\"/a.jpg\" width=\"10\" height=\"5\">\
+             <iframe width=\"480\" src=\"https://www.youtube.com/embed/abc\" allowfullscreen=\"\">\
+             </iframe><section>s</section>"
+        );
+    }
+
+    #[test]
+    fn removes_the_amp_runtime() {
+        let mut dom = parse_html(
+            "<html \u{26a1}><head>\
+             <script async src=https://cdn.ampproject.org/v0.js></script>\
+             <script async custom-element=amp-ad src=/amp-ad.js></script>\
+             <style amp-boilerplate>body{visibility:hidden}</style>\
+             <noscript><style amp-boilerplate>body{visibility:visible}</style></noscript>\
+             <style amp-custom>p{color:red}</style><script src=/app.js></script>\
+             </head><body></body></html>",
+        );
+        assert_eq!(dom.normalize_amp(), 6);
+        let html = dom.query_selector(dom.document, "html").unwrap().unwrap();
+        assert!(dom.get_attr(html, "\u{26a1}").is_none());
+        let scripts: Vec<_> = dom
+            .query_selector_all(dom.document, "script")
+            .unwrap()
+            .collect();
+        assert_eq!(scripts.len(), 1);
+        let styles: Vec<_> = dom
+            .query_selector_all(dom.document, "style")
+            .unwrap()
+            .collect();
+        assert_eq!(styles.len(), 1);
+        assert!(dom.get_attr(styles[0], "amp-custom").is_none());
+        assert!(dom
+            .query_selector(dom.document, "noscript")
+            .unwrap()
+            .is_none());
+    }
+}
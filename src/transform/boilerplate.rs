@@ -0,0 +1,315 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Elements that start a new block of text.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "caption",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "tbody",
+    "td",
+    "tfoot",
+    "th",
+    "thead",
+    "tr",
+    "ul",
+];
+
+/// Elements whose text is never shown as content.
+const HIDDEN_ELEMENTS: &[&str] = &["script", "style", "noscript", "template"];
+
+/// Whether a [`Block`] is part of the main content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    /// Main content.
+    Content,
+    /// Navigation, link lists, footers and other page furniture.
+    Boilerplate,
+}
+
+/// A run of text between block-level element boundaries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    /// The block-level element whose own text this is, not counting nested blocks.
+    pub handle: Handle,
+    /// How many words the block has.
+    pub words: usize,
+    /// How many of those words are inside links.
+    pub linked_words: usize,
+    /// The block's classification.
+    pub kind: BlockKind,
+}
+
+impl Block {
+    /// The fraction of the block's words that are inside links.
+    pub fn link_density(&self) -> f64 {
+        if self.words == 0 {
+            0.0
+        } else {
+            self.linked_words as f64 / self.words as f64
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Split the text under `root` into blocks and classify each as content or boilerplate.
+    ///
+    /// Each block-level element (and `root` itself) forms a block from the text it contains
+    /// outside nested blocks; blocks without words are skipped. Blocks are classified from their
+    /// word count and link density and those of their neighbours, using the decision tree of
+    /// boilerpipe's `NumWordsRulesClassifier`: short blocks surrounded by short blocks, and
+    /// blocks that are mostly links, are boilerplate.
+    pub fn classify_blocks(&self, root: Handle) -> Vec<Block> {
+        let mut blocks: Vec<Block> = root
+            .descendants(&self.arena)
+            .filter(|&node| node == root || self.is_block(node))
+            .filter(|&node| !self.is_hidden(node, root))
+            .filter_map(|node| {
+                let (words, linked_words) = self.block_words(node);
+                (words > 0).then_some(Block {
+                    handle: node,
+                    words,
+                    linked_words,
+                    kind: BlockKind::Content,
+                })
+            })
+            .collect();
+
+        let stats: Vec<(usize, f64)> = blocks
+            .iter()
+            .map(|block| (block.words, block.link_density()))
+            .collect();
+        for (i, block) in blocks.iter_mut().enumerate() {
+            let (prev_words, prev_density) = i.checked_sub(1).map_or((0, 0.0), |i| stats[i]);
+            let (next_words, _) = stats.get(i + 1).copied().unwrap_or((0, 0.0));
+            let (words, density) = stats[i];
+            let content = if density > 0.333_333 {
+                false
+            } else if prev_density <= 0.555_556 {
+                words > 16 || next_words > 15 || prev_words > 4
+            } else {
+                words > 40 || next_words > 17
+            };
+            if !content {
+                block.kind = BlockKind::Boilerplate;
+            }
+        }
+        blocks
+    }
+
+    /// Remove the boilerplate under `root`, as classified by
+    /// [`classify_blocks`](GenerationalArenaDom::classify_blocks), returning how many blocks
+    /// were removed.
+    ///
+    /// A boilerplate block is removed whole unless it contains content blocks, in which case
+    /// only its own text and inline elements are removed. Frozen nodes are left in place.
+    pub fn strip_boilerplate(&mut self, root: Handle) -> usize {
+        let span = TraceSpan::transform("strip_boilerplate");
+        let blocks = self.classify_blocks(root);
+        let content: Vec<Handle> = blocks
+            .iter()
+            .filter(|block| block.kind == BlockKind::Content)
+            .map(|block| block.handle)
+            .collect();
+        let mut removed = 0;
+        for block in blocks {
+            if block.kind == BlockKind::Content
+                || self.arena.get(block.handle).is_none()
+                || self.skip_frozen(block.handle)
+            {
+                continue;
+            }
+            let has_content = block
+                .handle
+                .descendants(&self.arena)
+                .any(|node| content.contains(&node) && node != block.handle);
+            if has_content {
+                let inline: Vec<Handle> = block
+                    .handle
+                    .children(&self.arena)
+                    .filter(|&child| !self.is_block(child))
+                    .collect();
+                for child in inline {
+                    self.free_subtree(child);
+                }
+            } else if block.handle == root {
+                let children: Vec<Handle> = root.children(&self.arena).collect();
+                for child in children {
+                    self.free_subtree(child);
+                }
+            } else if self.skip_frozen_removal(block.handle) {
+                continue;
+            } else {
+                self.free_subtree(block.handle);
+            }
+            removed += 1;
+        }
+        span.changed(self.arena.count(), removed)
+    }
+
+    /// Mark each boilerplate block under `root` by setting the attribute `attr` to
+    /// `"boilerplate"` on it, returning how many blocks were marked. Frozen blocks aren't
+    /// marked.
+    pub fn label_boilerplate(&mut self, root: Handle, attr: &str) -> usize {
+        let boilerplate: Vec<Handle> = self
+            .classify_blocks(root)
+            .into_iter()
+            .filter(|block| block.kind == BlockKind::Boilerplate && !self.skip_frozen(block.handle))
+            .map(|block| block.handle)
+            .collect();
+        for &handle in &boilerplate {
+            self.set_attr(handle, attr, StrTendril::from("boilerplate"));
+        }
+        boilerplate.len()
+    }
+
+    /// Whether `node` is a block-level element that separates runs of text.
+    pub(crate) fn is_block(&self, node: Handle) -> bool {
+        BLOCK_ELEMENTS
+            .iter()
+            .any(|name| self.is_html_element(node, name))
+    }
+
+    /// Whether `node` is or is inside an element whose text isn't displayed, below `root`.
+    fn is_hidden(&self, node: Handle, root: Handle) -> bool {
+        node.ancestors(&self.arena)
+            .take_while(|&ancestor| ancestor != root)
+            .any(|ancestor| {
+                HIDDEN_ELEMENTS
+                    .iter()
+                    .any(|name| self.is_html_element(ancestor, name))
+            })
+    }
+
+    /// The words of the text directly in `block`, and how many of them are in links.
+    fn block_words(&self, block: Handle) -> (usize, usize) {
+        let mut words = 0;
+        let mut linked = 0;
+        let mut stack: Vec<(Handle, bool)> = block
+            .children(&self.arena)
+            .map(|child| (child, false))
+            .collect();
+        stack.reverse();
+        while let Some((node, in_link)) = stack.pop() {
+            match self.get_node(&node) {
+                NodeData::Text { contents } => {
+                    let count = contents.borrow().split_whitespace().count();
+                    words += count;
+                    if in_link {
+                        linked += count;
+                    }
+                }
+                NodeData::Element { .. } => {
+                    let hidden = HIDDEN_ELEMENTS
+                        .iter()
+                        .any(|name| self.is_html_element(node, name));
+                    if self.is_block(node) || hidden {
+                        continue;
+                    }
+                    let in_link = in_link || self.is_html_element(node, "a");
+                    let start = stack.len();
+                    stack.extend(node.children(&self.arena).map(|child| (child, in_link)));
+                    stack[start..].reverse();
+                }
+                _ => {}
+            }
+        }
+        (words, linked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockKind;
+    use crate::parse::{body_html, parse_html};
+
+    fn page() -> String {
+        let words = |n: usize| vec!["word"; n].join(" ");
+        format!(
+            "<nav><a href=/>Home</a> <a href=/about>About</a> <a href=/contact>Contact</a></nav>\
+             <p>{}</p><p>{}<script>var ignored = 1;</script></p>\
+             <footer><a href=/privacy>Privacy</a> <a href=/terms>Terms</a></footer>",
+            words(30),
+            words(20)
+        )
+    }
+
+    #[test]
+    fn classifies_blocks_by_length_and_link_density() {
+        let dom = parse_html(&page());
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let blocks: Vec<(String, usize, usize, BlockKind)> = dom
+            .classify_blocks(body)
+            .iter()
+            .map(|block| {
+                let name = dom.element_name(block.handle).unwrap().local.to_string();
+                (name, block.words, block.linked_words, block.kind)
+            })
+            .collect();
+        assert_eq!(
+            blocks,
+            [
+                ("nav".to_string(), 3, 3, BlockKind::Boilerplate),
+                ("p".to_string(), 30, 0, BlockKind::Content),
+                ("p".to_string(), 20, 0, BlockKind::Content),
+                ("footer".to_string(), 2, 2, BlockKind::Boilerplate),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_and_labels_boilerplate() {
+        let mut dom = parse_html(&page());
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        assert_eq!(dom.label_boilerplate(body, "data-kind"), 2);
+        assert_eq!(
+            dom.select(dom.document, &"[data-kind=boilerplate]".parse().unwrap())
+                .len(),
+            2
+        );
+
+        let mut dom = parse_html(&page());
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let footer = dom.query_selector(body, "footer").unwrap().unwrap();
+        dom.freeze(footer);
+        assert_eq!(dom.strip_boilerplate(body), 1);
+        let html = body_html(&dom);
+        assert!(html.starts_with("<p>word"));
+        assert!(html.ends_with(
+            "<footer><a href=\"/privacy\">Privacy</a> <a href=\"/terms\">Terms</a></footer>"
+        ));
+    }
+}
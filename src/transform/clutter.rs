@@ -0,0 +1,348 @@
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// `id` and `class` words that name ad containers. Words are the parts of a name between
+/// punctuation, lowercased, so `sidebar-ad` has `ad` but `header` doesn't.
+const AD_WORDS: &[&str] = &[
+    "ad",
+    "adbox",
+    "adcontainer",
+    "adsbygoogle",
+    "ads",
+    "adslot",
+    "adunit",
+    "advert",
+    "advertisement",
+    "advertising",
+    "banner_ad",
+    "dfp",
+    "gpt",
+    "outbrain",
+    "sponsor",
+    "sponsored",
+    "taboola",
+];
+
+/// Hosts serving ads into `<iframe>`s and `<script>`s.
+const AD_HOSTS: &[&str] = &[
+    "adnxs.com",
+    "adservice.google.com",
+    "amazon-adsystem.com",
+    "doubleclick.net",
+    "googlesyndication.com",
+    "outbrain.com",
+    "taboola.com",
+];
+
+/// Labels that ad containers carry as their whole text, or as their `aria-label`.
+const AD_LABELS: &[&str] = &[
+    "ad",
+    "ads",
+    "advertisement",
+    "sponsored",
+    "sponsored content",
+];
+
+/// Parts of `id` and `class` names that mark cookie-consent banners, including the names of
+/// common consent management platforms.
+const CONSENT_NAMES: &[&str] = &[
+    "consent",
+    "cookie",
+    "cookiebot",
+    "didomi",
+    "gdpr",
+    "onetrust",
+    "quantcast",
+    "truste",
+    "usercentrics",
+];
+
+/// `id` and `class` words suggesting an element floats over the page.
+const OVERLAY_WORDS: &[&str] = &[
+    "banner", "dialog", "fixed", "modal", "overlay", "popup", "sticky", "toast",
+];
+
+/// Words in the text of consent banners.
+const CONSENT_TEXT: &[&str] = &["consent", "cookie", "cookies", "gdpr"];
+
+/// The text of the buttons that dismiss consent banners.
+const CONSENT_BUTTONS: &[&str] = &[
+    "accept",
+    "accept all",
+    "agree",
+    "allow",
+    "allow all",
+    "got it",
+    "i agree",
+    "ok",
+    "reject",
+    "reject all",
+];
+
+/// Elements never flagged, because they hold the whole page or its main content.
+const EXEMPT_ELEMENTS: &[&str] = &["article", "body", "head", "html", "main"];
+
+/// What a [`Clutter`] element is thought to be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ClutterKind {
+    /// An ad, or a container of ads.
+    Ad,
+    /// A cookie-consent banner or overlay.
+    ConsentBanner,
+}
+
+/// An element found by [`GenerationalArenaDom::detect_clutter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Clutter {
+    /// The element.
+    pub handle: Handle,
+    /// What it's thought to be.
+    pub kind: ClutterKind,
+}
+
+impl GenerationalArenaDom {
+    /// Find the elements under `root` that are likely ad containers or cookie-consent banners,
+    /// in document order, so they can be removed before extracting content. Elements inside
+    /// ones already found aren't reported.
+    ///
+    /// These are heuristics, which can be fooled either way. Ads are recognized by words such
+    /// as `ad`, `ads` or `sponsored` in their `id` or `class`, by `data-ad-*` attributes, by
+    /// frames and scripts from ad networks, and by an "Advertisement" label, counting for more
+    /// when the element is mostly links. Consent banners are recognized by names such as
+    /// `cookie-banner` or those of consent management platforms, by text about cookies with
+    /// buttons to accept or reject them, and by hints that the element floats over the page: a
+    /// `position: fixed` or `sticky` style, a dialog role, or names such as `modal` or
+    /// `overlay`.
+    pub fn detect_clutter(&self, root: Handle) -> Vec<Clutter> {
+        let mut clutter: Vec<Clutter> = Vec::new();
+        for node in root.descendants(&self.arena) {
+            let exempt = self.element_name(node).is_none()
+                || EXEMPT_ELEMENTS
+                    .iter()
+                    .any(|name| self.is_html_element(node, name));
+            if exempt {
+                continue;
+            }
+            let inside = clutter.iter().any(|found| {
+                node.ancestors(&self.arena)
+                    .any(|ancestor| ancestor == found.handle)
+            });
+            if inside {
+                continue;
+            }
+            let kind = if self.ad_score(node) >= 2 {
+                ClutterKind::Ad
+            } else if self.consent_score(node) >= 3 {
+                ClutterKind::ConsentBanner
+            } else {
+                continue;
+            };
+            clutter.push(Clutter { handle: node, kind });
+        }
+        clutter
+    }
+
+    /// Remove the elements found by [`detect_clutter`](GenerationalArenaDom::detect_clutter)
+    /// under `root`, returning how many were removed. Elements with anything frozen under them
+    /// are left in place.
+    pub fn strip_clutter(&mut self, root: Handle) -> usize {
+        let span = TraceSpan::transform("strip_clutter");
+        let mut removed = 0;
+        for found in self.detect_clutter(root) {
+            if !self.skip_frozen_removal(found.handle) {
+                self.free_subtree(found.handle);
+                removed += 1;
+            }
+        }
+        span.changed(self.arena.count(), removed)
+    }
+
+    fn ad_score(&self, node: Handle) -> usize {
+        let mut score = 0;
+        let words = self.name_words(node);
+        if words.iter().any(|word| AD_WORDS.contains(&word.as_str())) {
+            score += 2;
+        }
+        let attributed = match self.get_node(&node) {
+            NodeData::Element { attrs, .. } => attrs
+                .borrow()
+                .iter()
+                .any(|attr| attr.name.local.starts_with("data-ad-")),
+            _ => false,
+        };
+        let framed = (self.is_html_element(node, "iframe") || self.is_html_element(node, "script"))
+            && self.get_attr(node, "src").is_some_and(|src| {
+                let src = src.to_ascii_lowercase();
+                AD_HOSTS.iter().any(|host| src.contains(host))
+            });
+        if attributed || framed {
+            score += 3;
+        }
+        let labelled = self
+            .get_attr(node, "aria-label")
+            .is_some_and(|label| is_one_of(&label, AD_LABELS))
+            || is_one_of(&self.text_content(node), AD_LABELS);
+        if labelled {
+            score += 1;
+        }
+        let (words, linked_words) = self.word_counts(node);
+        if score > 0 && words > 0 && words <= 30 && linked_words * 2 > words {
+            score += 1;
+        }
+        score
+    }
+
+    fn consent_score(&self, node: Handle) -> usize {
+        let mut score = 0;
+        let names = [self.get_attr(node, "id"), self.get_attr(node, "class")];
+        let named = names.iter().flatten().any(|name| {
+            let name = name.to_ascii_lowercase();
+            CONSENT_NAMES.iter().any(|part| name.contains(part))
+        });
+        if named {
+            score += 2;
+        }
+
+        let text = self.text_content(node).to_ascii_lowercase();
+        let words: Vec<&str> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        if words.len() > 200 {
+            return 0;
+        }
+        if words.iter().any(|word| CONSENT_TEXT.contains(word)) {
+            score += 1;
+            let buttons = node.descendants(&self.arena).any(|descendant| {
+                ["a", "button"]
+                    .iter()
+                    .any(|name| self.is_html_element(descendant, name))
+                    && is_one_of(&self.text_content(descendant), CONSENT_BUTTONS)
+            });
+            if buttons {
+                score += 1;
+            }
+        }
+
+        let style = self
+            .get_attr(node, "style")
+            .map(|style| style.to_ascii_lowercase().replace(' ', ""))
+            .unwrap_or_default();
+        let floating = style.contains("position:fixed")
+            || style.contains("position:sticky")
+            || self.is_html_element(node, "dialog")
+            || self
+                .get_attr(node, "role")
+                .is_some_and(|role| role.contains("dialog"))
+            || self.get_attr(node, "aria-modal").as_deref() == Some("true")
+            || self
+                .name_words(node)
+                .iter()
+                .any(|word| OVERLAY_WORDS.contains(&word.as_str()));
+        if floating {
+            score += 1;
+        }
+        score
+    }
+
+    /// The lowercased words of the `id` and `class` of `node`.
+    fn name_words(&self, node: Handle) -> Vec<String> {
+        [self.get_attr(node, "id"), self.get_attr(node, "class")]
+            .iter()
+            .flatten()
+            .flat_map(|name| {
+                name.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .filter(|word| !word.is_empty())
+                    .map(|word| word.to_ascii_lowercase())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// How many words there are under `node`, and how many of them are in links.
+    fn word_counts(&self, node: Handle) -> (usize, usize) {
+        let (mut words, mut linked_words) = (0, 0);
+        for descendant in node.descendants(&self.arena) {
+            if let NodeData::Text { contents } = self.get_node(&descendant) {
+                let count = contents.borrow().split_whitespace().count();
+                words += count;
+                let linked = descendant
+                    .ancestors(&self.arena)
+                    .take_while(|&ancestor| ancestor != node)
+                    .any(|ancestor| self.is_html_element(ancestor, "a"));
+                if linked {
+                    linked_words += count;
+                }
+            }
+        }
+        (words, linked_words)
+    }
+}
+
+/// Whether `text`, trimmed and ignoring ASCII case, is one of `labels`.
+fn is_one_of(text: &str, labels: &[&str]) -> bool {
+    let text = text.trim();
+    labels.iter().any(|label| text.eq_ignore_ascii_case(label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClutterKind;
+    use crate::parse::{body_html, parse_html};
+
+    const HTML: &str = "<div class=sidebar-ad><ins class=adsbygoogle></ins></div>\
+                        <div data-ad-slot=1></div>\
+                        <iframe src=https://x.doubleclick.net/frame></iframe>\
+                        <div class=header>Header</div><p>Advertisement</p>\
+                        <div class=modal>Sign up</div>\
+                        <div id=cookie-notice>We use cookies. <button>Accept</button></div>\
+                        <div style='position: fixed'>Cookies! <a>OK</a></div>\
+                        <p>Read about cookies.</p>";
+
+    #[test]
+    fn detects_ads_and_consent_banners() {
+        let dom = parse_html(HTML);
+        let found: Vec<(String, ClutterKind)> = dom
+            .detect_clutter(dom.document)
+            .into_iter()
+            .map(|clutter| {
+                let html = dom.outer_html(clutter.handle);
+                (
+                    html[..html.find('>').unwrap() + 1].to_string(),
+                    clutter.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("<div class=\"sidebar-ad\">".to_string(), ClutterKind::Ad),
+                ("<div data-ad-slot=\"1\">".to_string(), ClutterKind::Ad),
+                (
+                    "<iframe src=\"https://x.doubleclick.net/frame\">".to_string(),
+                    ClutterKind::Ad
+                ),
+                (
+                    "<div id=\"cookie-notice\">".to_string(),
+                    ClutterKind::ConsentBanner
+                ),
+                (
+                    "<div style=\"position: fixed\">".to_string(),
+                    ClutterKind::ConsentBanner
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_clutter() {
+        let mut dom = parse_html(HTML);
+        let root = dom.document;
+        assert_eq!(dom.strip_clutter(root), 5);
+        assert_eq!(
+            body_html(&dom),
+            "<div class=\"header\">Header</div><p>Advertisement</p>\
+             <div class=\"modal\">Sign up</div><p>Read about cookies.</p>"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::trace::TraceSpan;
+use crate::transform::IntegrityAlgorithm;
+use crate::{GenerationalArenaDom, Handle, InsertPosition};
+
+/// The `type`s of scripts that run as JavaScript, besides `module`.
+const JAVASCRIPT_TYPES: &[&str] = &[
+    "application/ecmascript",
+    "application/javascript",
+    "application/x-ecmascript",
+    "application/x-javascript",
+    "text/ecmascript",
+    "text/javascript",
+    "text/javascript1.0",
+    "text/javascript1.1",
+    "text/javascript1.2",
+    "text/javascript1.3",
+    "text/javascript1.4",
+    "text/javascript1.5",
+    "text/jscript",
+    "text/livescript",
+    "text/x-ecmascript",
+    "text/x-javascript",
+];
+
+/// Whether code was found in a `<script>` or a `<style>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InlineKind {
+    /// An inline `<script>`.
+    Script,
+    /// A `<style>` element.
+    Style,
+}
+
+/// Code moved out of the document by [`GenerationalArenaDom::extract_inline_code`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtractedCode {
+    /// What the code was.
+    pub kind: InlineKind,
+
+    /// The code, to be served at `url`.
+    pub body: String,
+
+    /// Where the element that replaced the code loads it from.
+    pub url: String,
+
+    /// The CSP hash source for `body`, such as `'sha256-…'`.
+    pub hash: String,
+
+    /// The `<script>` or `<link>` element that loads the code.
+    pub handle: Handle,
+}
+
+/// The result of [`GenerationalArenaDom::extract_inline_code`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CspExtraction {
+    /// The code moved out of the document, in document order.
+    pub code: Vec<ExtractedCode>,
+
+    /// The nonce set on the elements that load the code.
+    pub nonce: String,
+}
+
+impl CspExtraction {
+    /// A `Content-Security-Policy` allowing the extracted code, such as
+    /// `script-src 'nonce-…'; style-src 'nonce-…'`, with a directive for each kind of code
+    /// that was extracted. Empty if there was none.
+    pub fn policy(&self) -> String {
+        [
+            (InlineKind::Script, "script-src"),
+            (InlineKind::Style, "style-src"),
+        ]
+        .iter()
+        .filter(|(kind, _)| self.code.iter().any(|code| code.kind == *kind))
+        .map(|(_, directive)| format!("{} 'nonce-{}'", directive, self.nonce))
+        .collect::<Vec<_>>()
+        .join("; ")
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Move the code of inline scripts and `<style>` elements out of the document, so that it
+    /// can be served under a `Content-Security-Policy` that forbids inline code.
+    ///
+    /// `url_for` is called with the kind and body of each piece of code, and returns the URL
+    /// it will be served at. Scripts then load it with `src`, and `<style>` elements are
+    /// replaced by `<link rel=stylesheet>` elements that keep their `media` and `title`. All of
+    /// these are given a newly generated nonce, returned along with the code and its hashes.
+    /// The nonce is 128 bits from the operating system's secure random number generator. A page
+    /// served dynamically should still be given a new nonce for each response.
+    ///
+    /// Data blocks such as JSON-LD, which don't run, and empty elements are left alone, as are
+    /// event handler attributes and `style` attributes, which a strict policy also forbids.
+    /// Frozen elements aren't moved out either.
+    pub fn extract_inline_code<F>(&mut self, mut url_for: F) -> CspExtraction
+    where
+        F: FnMut(InlineKind, &str) -> String,
+    {
+        let span = TraceSpan::transform("extract_inline_code");
+        let nonce = generate_nonce();
+        let elements: Vec<(Handle, InlineKind)> = self
+            .document
+            .descendants(&self.arena)
+            .filter_map(|node| {
+                if self.is_html_element(node, "script") && self.is_inline_javascript(node) {
+                    Some((node, InlineKind::Script))
+                } else if self.is_html_element(node, "style") {
+                    Some((node, InlineKind::Style))
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        let mut code = Vec::with_capacity(elements.len());
+        for (node, kind) in elements {
+            let body = self.text_content(node);
+            if body.trim().is_empty() || self.skip_frozen_removal(node) {
+                continue;
+            }
+            let url = url_for(kind, &body);
+            let hash = format!(
+                "'{}'",
+                IntegrityAlgorithm::Sha256.integrity(body.as_bytes())
+            );
+            let children: Vec<Handle> = node.children(&self.arena).collect();
+            for child in children {
+                self.free_subtree(child);
+            }
+            let handle = match kind {
+                InlineKind::Script => {
+                    self.set_attr(node, "src", StrTendril::from(&*url));
+                    self.set_attr(node, "nonce", StrTendril::from(&*nonce));
+                    node
+                }
+                InlineKind::Style => {
+                    let mut attrs = vec![attribute("rel", "stylesheet"), attribute("href", &url)];
+                    for name in ["media", "title"] {
+                        if let Some(value) = self.get_attr(node, name) {
+                            attrs.push(attribute(name, &value));
+                        }
+                    }
+                    attrs.push(attribute("nonce", &nonce));
+                    let link = self.create_html_element("link", attrs);
+                    self.insert_at(&[link], InsertPosition::Before(node));
+                    self.free_subtree(node);
+                    link
+                }
+            };
+            code.push(ExtractedCode {
+                kind,
+                body,
+                url,
+                hash,
+                handle,
+            });
+        }
+        if !code.is_empty() {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), code.len());
+        CspExtraction { code, nonce }
+    }
+
+    /// Whether `script` has no `src` and is run as JavaScript.
+    fn is_inline_javascript(&self, script: Handle) -> bool {
+        if self.get_attr(script, "src").is_some() {
+            return false;
+        }
+        match self.get_attr(script, "type") {
+            None => true,
+            Some(ty) => {
+                let essence = ty.split(';').next().unwrap_or_default().trim();
+                essence.is_empty()
+                    || essence.eq_ignore_ascii_case("module")
+                    || JAVASCRIPT_TYPES
+                        .iter()
+                        .any(|javascript| essence.eq_ignore_ascii_case(javascript))
+            }
+        }
+    }
+}
+
+/// 128 bits from the operating system's secure random number generator, base64-encoded.
+fn generate_nonce() -> String {
+    let mut bytes = [0; 16];
+    getrandom::fill(&mut bytes).expect("the operating system couldn't provide random bytes");
+    BASE64.encode(bytes)
+}
+
+fn attribute(name: &str, value: &str) -> Attribute {
+    Attribute {
+        name: QualName::new(None, ns!(), LocalName::from(name)),
+        value: StrTendril::from(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InlineKind;
+    use crate::{GenerationalArenaDom, Selector};
+
+    #[test]
+    fn moves_inline_code_out() {
+        let mut dom = GenerationalArenaDom::from_abbreviation(
+            "script{alert(1)}+script[type=application/ld+json]{x}+script[src=a.js]+style[media=print]",
+        )
+        .unwrap();
+        let style = dom.select(dom.document, &Selector::parse("style").unwrap())[0];
+        let text = dom.create_text("p { color: red }");
+        style.append(text, &mut dom.arena);
+
+        let mut urls = vec!["/b.css", "/a.js"];
+        let extraction = dom.extract_inline_code(|_, _| urls.pop().unwrap().to_string());
+        let nonce = &extraction.nonce;
+        assert_eq!(nonce.len(), 24);
+        let code: Vec<_> = extraction
+            .code
+            .iter()
+            .map(|code| (code.kind, code.body.as_str(), code.hash.as_str()))
+            .collect();
+        assert_eq!(
+            code,
+            [
+                (
+                    InlineKind::Script,
+                    "alert(1)",
+                    "'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='"
+                ),
+                (
+                    InlineKind::Style,
+                    "p { color: red }",
+                    "'sha256-ngewhhP73WDIbgwseeu52VAAJgKdGUsu1IUQQsAm8m4='"
+                ),
+            ]
+        );
+        assert_eq!(
+            extraction.policy(),
+            format!("script-src 'nonce-{}'; style-src 'nonce-{}'", nonce, nonce)
+        );
+        let script = extraction.code[0].handle;
+        assert_eq!(dom.get_attr(script, "src").as_deref(), Some("/a.js"));
+        assert_eq!(dom.text_content(script), "");
+        let link = extraction.code[1].handle;
+        assert!(dom.is_html_element(link, "link"));
+        assert_eq!(dom.get_attr(link, "media").as_deref(), Some("print"));
+        assert_eq!(dom.get_attr(link, "nonce").as_deref(), Some(&**nonce));
+        assert!(!dom.is_alive(style));
+
+        let again = GenerationalArenaDom::from_abbreviation("script{x}")
+            .unwrap()
+            .extract_inline_code(|_, _| String::new());
+        assert_ne!(again.nonce, extraction.nonce);
+    }
+}
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, Selector};
+
+/// A declaration from a style rule or `style` attribute.
+#[derive(Clone, Debug)]
+struct Declaration {
+    property: String,
+    value: String,
+    important: bool,
+}
+
+/// Selector specificity, as counts of IDs, other conditions and type selectors.
+type Specificity = (usize, usize, usize);
+
+impl GenerationalArenaDom {
+    /// Copy the rules of the document's `<style>` elements into the `style` attributes of the
+    /// elements they match, as email clients that ignore stylesheets require. Returns how many
+    /// elements were styled.
+    ///
+    /// Declarations are applied in cascade order: by specificity, then source order, with
+    /// existing `style` attributes overriding everything but `!important` rule declarations.
+    /// Rules whose selectors this crate can't match, such as `a:hover`, and at-rules such as
+    /// `@media` can't be inlined; if `remove_inlined` is set, everything else is removed from
+    /// the `<style>` elements, and elements left empty are removed. Frozen elements are left
+    /// alone.
+    pub fn inline_styles(&mut self, remove_inlined: bool) -> usize {
+        let span = TraceSpan::transform("inline_styles");
+        let styles: Vec<Handle> = self
+            .document
+            .descendants(&self.arena)
+            .filter(|&node| self.is_html_element(node, "style"))
+            .collect();
+
+        // For each element, its matching declarations with their (specificity, order).
+        let mut matched: HashMap<Handle, Vec<(Specificity, usize, Declaration)>> = HashMap::new();
+        let mut order = 0;
+        let mut leftovers = Vec::with_capacity(styles.len());
+        for &style in &styles {
+            let sheet = strip_comments(&self.text_content(style));
+            let mut leftover = String::new();
+            for rule in split_rules(&sheet) {
+                let parsed = match rule {
+                    Rule::Style { selector, body, .. } => Selector::parse(selector)
+                        .ok()
+                        .filter(|selector| !selector.is_dynamic())
+                        .map(|selector| (selector, body)),
+                    Rule::Other(_) => None,
+                };
+                let (selector, body) = match parsed {
+                    Some(parsed) => parsed,
+                    None => {
+                        leftover.push_str(rule.text().trim());
+                        leftover.push('\n');
+                        continue;
+                    }
+                };
+                let declarations = parse_declarations(body);
+                for node in self.document.descendants(&self.arena) {
+                    if let Some(specificity) = selector.matching_specificity(self, node) {
+                        let entry = matched.entry(node).or_default();
+                        for declaration in &declarations {
+                            entry.push((specificity, order, declaration.clone()));
+                            order += 1;
+                        }
+                    }
+                }
+            }
+            leftovers.push(leftover);
+        }
+
+        matched.retain(|&node, _| !self.skip_frozen(node));
+        let styled = matched.len();
+        for (node, mut declarations) in matched {
+            declarations.sort_by_key(|&(specificity, order, _)| (specificity, order));
+            let inline = self
+                .get_attr(node, "style")
+                .map(|style| parse_declarations(&style))
+                .unwrap_or_default();
+            let mut cascaded: Vec<Declaration> = Vec::new();
+            let normal = declarations
+                .iter()
+                .map(|(_, _, declaration)| declaration)
+                .filter(|declaration| !declaration.important);
+            let important = declarations
+                .iter()
+                .map(|(_, _, declaration)| declaration)
+                .filter(|declaration| declaration.important);
+            for declaration in normal.chain(inline.iter()).chain(important) {
+                cascaded.retain(|existing| {
+                    !existing
+                        .property
+                        .eq_ignore_ascii_case(&declaration.property)
+                });
+                cascaded.push(declaration.clone());
+            }
+            let style = format_declarations(&cascaded);
+            self.set_attr(node, "style", StrTendril::from(style));
+        }
+
+        if remove_inlined {
+            for (style, leftover) in styles.into_iter().zip(leftovers) {
+                if self.skip_frozen(style) {
+                    continue;
+                }
+                let children: Vec<Handle> = style.children(&self.arena).collect();
+                for child in children {
+                    self.free_subtree(child);
+                }
+                if leftover.trim().is_empty() {
+                    self.free_subtree(style);
+                } else {
+                    let text = self.create_text(leftover.trim_end());
+                    style.append(text, &mut self.arena);
+                }
+            }
+        }
+        if styled > 0 || remove_inlined {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), styled)
+    }
+}
+
+/// Remove the declarations of the `style` attribute value `style` for which `keep`, called
+/// with the property and value, returns false. Returns `None` if none were removed.
+pub(crate) fn filter_declarations<F>(style: &str, mut keep: F) -> Option<String>
+where
+    F: FnMut(&str, &str) -> bool,
+{
+    let mut declarations = parse_declarations(style);
+    let before = declarations.len();
+    declarations.retain(|declaration| keep(&declaration.property, &declaration.value));
+    if declarations.len() == before {
+        return None;
+    }
+    Some(format_declarations(&declarations))
+}
+
+fn format_declarations(declarations: &[Declaration]) -> String {
+    declarations
+        .iter()
+        .map(|declaration| {
+            let important = if declaration.important {
+                " !important"
+            } else {
+                ""
+            };
+            format!(
+                "{}: {}{};",
+                declaration.property, declaration.value, important
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A top-level item of a stylesheet.
+enum Rule<'a> {
+    /// A style rule, with its whole text, selector and declaration block contents.
+    Style {
+        text: &'a str,
+        selector: &'a str,
+        body: &'a str,
+    },
+    /// An at-rule, or anything unparseable.
+    Other(&'a str),
+}
+
+impl<'a> Rule<'a> {
+    fn text(&self) -> &'a str {
+        match *self {
+            Rule::Style { text, .. } | Rule::Other(text) => text,
+        }
+    }
+}
+
+fn split_rules(sheet: &str) -> Vec<Rule<'_>> {
+    let mut rules = Vec::new();
+    let bytes = sheet.as_bytes();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => i = skip_string(bytes, i),
+            b';' if sheet[start..i].trim_start().starts_with('@') => {
+                // A statement at-rule, such as `@import`.
+                rules.push(Rule::Other(&sheet[start..=i]));
+                start = i + 1;
+                i += 1;
+            }
+            b'{' => {
+                let open = i;
+                let close = matching_brace(bytes, open);
+                let text = &sheet[start..close.min(bytes.len() - 1) + 1];
+                let prelude = sheet[start..open].trim();
+                if prelude.starts_with('@') || close >= bytes.len() {
+                    rules.push(Rule::Other(text));
+                } else {
+                    rules.push(Rule::Style {
+                        text,
+                        selector: prelude,
+                        body: &sheet[open + 1..close],
+                    });
+                }
+                start = close + 1;
+                i = start;
+            }
+            _ => i += 1,
+        }
+    }
+    if !sheet[start.min(sheet.len())..].trim().is_empty() {
+        rules.push(Rule::Other(&sheet[start..]));
+    }
+    rules
+}
+
+/// The index of the `}` closing the `{` at `open`, or the length of `bytes` if it isn't closed.
+fn matching_brace(bytes: &[u8], open: usize) -> usize {
+    let mut depth = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' | b'\'' => {
+                i = skip_string(bytes, i);
+                continue;
+            }
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+/// The index just past the string starting with the quote at `start`.
+fn skip_string(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i] != quote {
+        if bytes[i] == b'\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    (i + 1).min(bytes.len())
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Parse the declarations of a declaration block or `style` attribute.
+fn parse_declarations(block: &str) -> Vec<Declaration> {
+    let bytes = block.as_bytes();
+    let mut declarations = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i <= bytes.len() {
+        match bytes.get(i) {
+            Some(b'"' | b'\'') => {
+                i = skip_string(bytes, i);
+                continue;
+            }
+            Some(b'(') => depth += 1,
+            Some(b')') => depth = depth.saturating_sub(1),
+            Some(b';') | None if depth == 0 || i == bytes.len() => {
+                if let Some((property, value)) = block[start..i].split_once(':') {
+                    let property = property.trim();
+                    let mut value = value.trim();
+                    let mut important = false;
+                    if let Some(bang) = value.rfind('!') {
+                        if value[bang + 1..].trim().eq_ignore_ascii_case("important") {
+                            important = true;
+                            value = value[..bang].trim_end();
+                        }
+                    }
+                    if !property.is_empty() && !value.is_empty() {
+                        declarations.push(Declaration {
+                            property: property.to_string(),
+                            value: value.to_string(),
+                            important,
+                        });
+                    }
+                }
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    declarations
+}
+
+/// Rewrite the URLs of the `url(…)` functions and `@import`s in `css`, a stylesheet or
+/// declaration list. `rewrite` is called with each URL and whether it's that of an imported
+/// stylesheet, and returns its replacement, or `None` to leave it alone.
+pub(crate) fn rewrite_css_urls<F>(css: &str, mut rewrite: F) -> String
+where
+    F: FnMut(&str, bool) -> Option<String>,
+{
+    let bytes = css.as_bytes();
+    let lower = css.to_ascii_lowercase().into_bytes();
+    let mut output = String::with_capacity(css.len());
+    let mut copied = 0;
+    let mut in_import = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let is_url = lower[i..].starts_with(b"url(")
+            && (i == 0 || !matches!(bytes[i - 1], b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-'));
+        let is_import = lower[i..].starts_with(b"@import");
+        if !is_url && !is_import {
+            i += 1;
+            continue;
+        }
+        let mut start = i + if is_url { 4 } else { 7 };
+        while start < bytes.len() && bytes[start].is_ascii_whitespace() {
+            start += 1;
+        }
+        // The URL, the end of its (possibly quoted) text, and where to continue scanning.
+        let (url_start, url_end, span_end, next) = match bytes.get(start) {
+            Some(&quote @ (b'"' | b'\'')) => {
+                let end = skip_string(bytes, start);
+                let closed = end - 1 > start && bytes[end - 1] == quote;
+                (start + 1, if closed { end - 1 } else { end }, end, end)
+            }
+            _ if is_url => {
+                let close = css[start..]
+                    .find(')')
+                    .map_or(bytes.len(), |close| start + close);
+                let url_end = start + css[start..close].trim_end().len();
+                (start, url_end, url_end, close)
+            }
+            // An `@import url(…)`, handled as a `url(…)`.
+            _ => {
+                in_import = true;
+                i = start;
+                continue;
+            }
+        };
+        let url = &css[url_start..url_end];
+        let imported = is_import || in_import;
+        in_import = false;
+        if let Some(replacement) = rewrite(url.trim(), imported) {
+            output.push_str(&css[copied..start]);
+            output.push('"');
+            for c in replacement.chars() {
+                match c {
+                    '"' | '\\' => {
+                        output.push('\\');
+                        output.push(c);
+                    }
+                    '\n' => output.push_str("\\a "),
+                    c => output.push(c),
+                }
+            }
+            output.push('"');
+            copied = span_end;
+        }
+        i = next.max(i + 1);
+    }
+    output.push_str(&css[copied..]);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_declarations, parse_declarations, rewrite_css_urls, split_rules};
+    use super::{strip_comments, Rule};
+    use crate::{GenerationalArenaDom, Handle, Selector};
+
+    #[test]
+    fn splits_rules() {
+        let sheet =
+            "@import 'a;b.css'; p, a[title='}'] { color: red } @media print { p { x: y } } \
+                     div { color: blue";
+        let rules = split_rules(sheet);
+        assert_eq!(rules.len(), 4);
+        assert!(matches!(rules[0], Rule::Other("@import 'a;b.css';")));
+        match rules[1] {
+            Rule::Style { selector, body, .. } => {
+                assert_eq!(selector, "p, a[title='}']");
+                assert_eq!(body, " color: red ");
+            }
+            Rule::Other(text) => panic!("expected a style rule, got {:?}", text),
+        }
+        assert_eq!(rules[2].text(), " @media print { p { x: y } }");
+        // An unclosed block isn't a style rule.
+        assert!(matches!(rules[3], Rule::Other(" div { color: blue")));
+    }
+
+    #[test]
+    fn parses_declarations() {
+        let declarations = parse_declarations(
+            "color: red !important; background: url(a;b.png); content: 'x;y' ; bad; : x",
+        );
+        let parsed: Vec<_> = declarations
+            .iter()
+            .map(|d| (d.property.as_str(), d.value.as_str(), d.important))
+            .collect();
+        assert_eq!(
+            parsed,
+            [
+                ("color", "red", true),
+                ("background", "url(a;b.png)", false),
+                ("content", "'x;y'", false),
+            ]
+        );
+        assert_eq!(
+            filter_declarations("color: red; position: fixed", |property, _| property
+                != "position"),
+            Some("color: red;".to_string())
+        );
+        assert_eq!(filter_declarations("color: red", |_, _| true), None);
+    }
+
+    #[test]
+    fn strips_comments() {
+        assert_eq!(strip_comments("a /* b */ c /* d"), "a  c ");
+    }
+
+    #[test]
+    fn rewrites_urls() {
+        let css = "@import 'a.css'; @import url(b.css); p { background: URL( c.png ) } \
+                   q { x: myurl(d) }";
+        let mut seen = Vec::new();
+        let rewritten = rewrite_css_urls(css, |url, imported| {
+            seen.push((url.to_string(), imported));
+            Some(format!("/{}", url))
+        });
+        assert_eq!(
+            rewritten,
+            "@import \"/a.css\"; @import url(\"/b.css\"); p { background: URL( \"/c.png\" ) } \
+             q { x: myurl(d) }"
+        );
+        assert_eq!(
+            seen,
+            [
+                ("a.css".to_string(), true),
+                ("b.css".to_string(), true),
+                ("c.png".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn inlines_in_cascade_order() {
+        let mut dom =
+            GenerationalArenaDom::from_abbreviation("style+p#x.y[style='color: green']").unwrap();
+        let select = |dom: &GenerationalArenaDom, selector: &str| -> Handle {
+            dom.select(dom.document, &Selector::parse(selector).unwrap())[0]
+        };
+        let style = select(&dom, "style");
+        let text = dom.create_text(
+            "#x { margin: 0 } p { color: red; margin: 1px; padding: 0 !important } \
+             .y { padding: 2px } a:hover { color: blue }",
+        );
+        style.append(text, &mut dom.arena);
+        assert_eq!(dom.inline_styles(true), 1);
+        let p = select(&dom, "p");
+        assert_eq!(
+            dom.get_attr(p, "style").as_deref(),
+            Some("margin: 0; color: green; padding: 0 !important;")
+        );
+        assert_eq!(dom.text_content(style), "a:hover { color: blue }");
+    }
+}
@@ -0,0 +1,237 @@
+use markup5ever::tendril::StrTendril;
+use markup5ever::{Attribute, LocalName, QualName};
+
+use crate::trace::TraceSpan;
+use crate::transform::css::filter_declarations;
+use crate::transform::sanitize::{strings, REMOVED_ELEMENTS};
+use crate::transform::SanitizeOptions;
+use crate::url::percent_decode;
+use crate::{srcset_candidates, GenerationalArenaDom, Handle, NodeData};
+
+/// Attributes that load a resource when the element is displayed.
+const RESOURCE_ATTRIBUTES: &[&str] = &["src", "srcset", "background", "poster"];
+
+/// Options for [`GenerationalArenaDom::harden_email`].
+#[derive(Clone, Debug)]
+pub struct EmailOptions {
+    /// Whether to keep remote images and other resources from loading, so that opening the
+    /// message can't be tracked. Defaults to `true`.
+    ///
+    /// Blocked attributes are renamed with a `data-remote-` prefix, e.g. `src` to
+    /// `data-remote-src`, so that they can be restored if the reader asks to load them. Inline
+    /// style declarations with remote `url(…)`s are removed, as are `<style>` elements that
+    /// still have any after CSS inlining.
+    pub block_remote_content: bool,
+
+    /// Whether to [inline](GenerationalArenaDom::inline_styles) the document's stylesheets
+    /// first. Defaults to `true`.
+    pub inline_css: bool,
+}
+
+impl Default for EmailOptions {
+    fn default() -> EmailOptions {
+        EmailOptions {
+            block_remote_content: true,
+            inline_css: true,
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Prepare the body of an HTML email for display, returning how many nodes and attributes
+    /// were removed or changed.
+    ///
+    /// Stylesheets are inlined if `opts.inline_css` is set, then the document is
+    /// [sanitized](GenerationalArenaDom::sanitize): scripts (SVG ones included), frames,
+    /// plugins, forms and their controls, SVG animations, `<base>`, `<meta>` and `<link>`
+    /// elements are removed, and only `http`, `https`, `mailto`, `tel` and `cid` URLs are kept.
+    /// Remote content is blocked if `opts.block_remote_content` is set. Finally `rewrite_cid` is
+    /// called with the content ID of each `cid:` URL, percent-decoded, and returns the URL to
+    /// use instead, such as that of the attachment or a `data:` URL; `None` leaves it as is.
+    pub fn harden_email<F>(&mut self, opts: &EmailOptions, mut rewrite_cid: F) -> usize
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        let span = TraceSpan::transform("harden_email");
+        let mut changed = 0;
+        if opts.inline_css {
+            changed += self.inline_styles(true);
+        }
+        let sanitize = SanitizeOptions {
+            remove: strings(
+                &[
+                    REMOVED_ELEMENTS,
+                    &["form", "input", "button", "select", "textarea"],
+                ]
+                .concat(),
+            ),
+            allowed_schemes: strings(&["http", "https", "mailto", "tel", "cid"]),
+            ..SanitizeOptions::default()
+        };
+        changed += self.sanitize(self.document, &sanitize);
+        if opts.block_remote_content {
+            changed += self.block_remote_content();
+        }
+        changed += self.rewrite_urls(self.document, |url| {
+            let scheme = url.get(..4)?;
+            if !scheme.eq_ignore_ascii_case("cid:") {
+                return None;
+            }
+            rewrite_cid(&percent_decode(&url[4..]))
+        });
+        span.changed(self.arena.count(), changed)
+    }
+
+    /// Keep remote resources from loading, as described for
+    /// [`EmailOptions::block_remote_content`], returning how many attributes and elements were
+    /// changed or removed.
+    fn block_remote_content(&mut self) -> usize {
+        let mut changed = 0;
+        let nodes: Vec<Handle> = self.document.descendants(&self.arena).collect();
+        for node in nodes {
+            if self.arena.get(node).is_none() {
+                continue;
+            }
+            if self.is_html_element(node, "style") {
+                if has_remote_url(&self.text_content(node)) && !self.skip_frozen_removal(node) {
+                    self.free_subtree(node);
+                    changed += 1;
+                }
+                continue;
+            }
+            let attrs = match self.get_node(&node) {
+                NodeData::Element { attrs, .. } => attrs,
+                _ => continue,
+            };
+            let mut attrs = attrs.borrow_mut();
+            for attr in attrs.iter_mut() {
+                let name = &*attr.name.local;
+                let value = &*attr.value;
+                if name == "style" {
+                    let filtered = filter_declarations(value, |_, value| !has_remote_url(value));
+                    if let Some(filtered) = filtered {
+                        if self.skip_frozen(node) {
+                            break;
+                        }
+                        attr.value = StrTendril::from(filtered);
+                        changed += 1;
+                    }
+                    continue;
+                }
+                if !RESOURCE_ATTRIBUTES.contains(&name) {
+                    continue;
+                }
+                let remote = if name == "srcset" {
+                    srcset_candidates(value).any(|candidate| is_remote(candidate.url))
+                } else {
+                    is_remote(value)
+                };
+                if remote {
+                    if self.skip_frozen(node) {
+                        break;
+                    }
+                    *attr = Attribute {
+                        name: QualName::new(
+                            None,
+                            attr.name.ns.clone(),
+                            LocalName::from(format!("data-remote-{}", name)),
+                        ),
+                        value: attr.value.clone(),
+                    };
+                    changed += 1;
+                }
+            }
+        }
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        changed
+    }
+}
+
+/// Whether `url` loads from the network: it's relative to the (remote) base URL, or uses
+/// anything but the `cid` and `data` schemes.
+fn is_remote(url: &str) -> bool {
+    let url = url.trim_start_matches(|c: char| c.is_ascii_whitespace());
+    let scheme = url
+        .split_once(':')
+        .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    !matches!(scheme.as_deref(), Some("cid" | "data"))
+}
+
+/// Whether the CSS `css` has a `url(…)` or `@import` of a remote URL.
+fn has_remote_url(css: &str) -> bool {
+    let lower = css.to_ascii_lowercase();
+    if lower.contains("@import") {
+        return true;
+    }
+    lower.split("url(").skip(1).any(|rest| {
+        let url = rest.split(')').next().unwrap_or(rest);
+        is_remote(url.trim().trim_matches(|c| c == '"' || c == '\''))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_remote_url, is_remote, EmailOptions};
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn recognizes_remote_urls() {
+        assert!(is_remote("https://example.com/a.png"));
+        assert!(is_remote("/a.png"));
+        assert!(!is_remote(" cid:logo@x"));
+        assert!(!is_remote("DATA:image/png;base64,AA"));
+        assert!(has_remote_url(
+            "background: url( 'https://t.example/p.gif' )"
+        ));
+        assert!(has_remote_url("@import 'x.css'"));
+        assert!(!has_remote_url("background: URL(cid:bg) no-repeat"));
+    }
+
+    #[test]
+    fn hardens_emails() {
+        let mut dom = parse_html(
+            "<style>p { color: red }</style><script>x()</script>\
+             <form><input name=q></form><iframe src=/f></iframe>\
+             <p onclick=x()>hi <a href=javascript:x()>a</a> <a href=mailto:me@x>b</a></p>\
+             <img src='cid:logo%40x' alt=logo>",
+        );
+        let mut cids = Vec::new();
+        dom.harden_email(&EmailOptions::default(), |cid| {
+            cids.push(cid.to_string());
+            Some("data:image/png;base64,AA".to_string())
+        });
+        assert_eq!(cids, ["logo@x"]);
+        assert_eq!(
+            body_html(&dom),
+            "<p style=\"color: red;\">hi <a>a</a> <a href=\"mailto:me@x\">b</a></p>\
+             <img src=\"data:image/png;base64,AA\" alt=\"logo\">"
+        );
+    }
+
+    #[test]
+    fn blocks_remote_content() {
+        let html = "<style>div { background: url(https://t.example/bg.png) }</style>\
+                    <img src=https://t.example/p.gif srcset='cid:a 1x'>\
+                    <p style='color: red; background: url(/bg.png)'>x</p>";
+        let mut dom = parse_html(html);
+        let opts = EmailOptions {
+            inline_css: false,
+            ..EmailOptions::default()
+        };
+        assert_eq!(dom.harden_email(&opts, |_| None), 3);
+        assert_eq!(
+            body_html(&dom),
+            "<img data-remote-src=\"https://t.example/p.gif\" srcset=\"cid:a 1x\">\
+             <p style=\"color: red;\">x</p>"
+        );
+
+        let mut dom = parse_html(html);
+        let opts = EmailOptions {
+            block_remote_content: false,
+            inline_css: false,
+        };
+        assert_eq!(dom.harden_email(&opts, |_| None), 0);
+    }
+}
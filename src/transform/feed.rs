@@ -0,0 +1,183 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::transform::sanitize::{strings, REMOVED_ELEMENTS};
+use crate::transform::SanitizeOptions;
+use crate::url::resolve;
+use crate::{GenerationalArenaDom, Handle};
+
+impl SanitizeOptions {
+    /// The options used by [`GenerationalArenaDom::sanitize_feed_html`], a policy for the
+    /// content of RSS and Atom entries in line with what feed readers commonly allow.
+    ///
+    /// Only elements for text, lists, tables, quotes, code, figures, images and media are kept,
+    /// without `class`, `id` or `style` attributes, so that entries take on the reader's own
+    /// styling. `<style>`, `<title>` and form controls are removed along with the defaults, and
+    /// only `http`, `https` and `mailto` URLs are allowed.
+    pub fn feed() -> SanitizeOptions {
+        SanitizeOptions {
+            remove: strings(
+                &[
+                    REMOVED_ELEMENTS,
+                    &[
+                        "style", "title", "form", "input", "button", "select", "textarea",
+                    ],
+                ]
+                .concat(),
+            ),
+            allowed_elements: Some(strings(&[
+                "a",
+                "abbr",
+                "address",
+                "audio",
+                "b",
+                "bdi",
+                "bdo",
+                "blockquote",
+                "br",
+                "caption",
+                "cite",
+                "code",
+                "col",
+                "colgroup",
+                "dd",
+                "del",
+                "details",
+                "dfn",
+                "div",
+                "dl",
+                "dt",
+                "em",
+                "figcaption",
+                "figure",
+                "h1",
+                "h2",
+                "h3",
+                "h4",
+                "h5",
+                "h6",
+                "hr",
+                "i",
+                "img",
+                "ins",
+                "kbd",
+                "li",
+                "mark",
+                "ol",
+                "p",
+                "picture",
+                "pre",
+                "q",
+                "rp",
+                "rt",
+                "ruby",
+                "s",
+                "samp",
+                "small",
+                "source",
+                "span",
+                "strong",
+                "sub",
+                "summary",
+                "sup",
+                "table",
+                "tbody",
+                "td",
+                "tfoot",
+                "th",
+                "thead",
+                "time",
+                "tr",
+                "track",
+                "u",
+                "ul",
+                "var",
+                "video",
+                "wbr",
+            ])),
+            allowed_attributes: Some(strings(&[
+                "abbr", "alt", "cite", "colspan", "controls", "datetime", "dir", "headers",
+                "height", "href", "kind", "label", "lang", "media", "poster", "reversed",
+                "rowspan", "scope", "sizes", "span", "src", "srclang", "srcset", "start", "title",
+                "type", "width",
+            ])),
+            allowed_schemes: strings(&["http", "https", "mailto"]),
+            allow_data_images: false,
+            remove_comments: true,
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Make the content of a feed entry under `root` safe to show in a feed reader, returning
+    /// how many nodes and attributes were removed, unwrapped or changed.
+    ///
+    /// Relative URLs are first resolved against the document's base URL, so set the document's
+    /// URL to that of the entry (or the feed's `xml:base`) beforehand; without one they're left
+    /// relative. Then the content is [sanitized](GenerationalArenaDom::sanitize) with
+    /// [`SanitizeOptions::feed`], and links get `rel="noopener noreferrer"` so that the sites
+    /// they lead to can neither script the reader nor learn where the reader came from. Frozen
+    /// nodes are left alone.
+    pub fn sanitize_feed_html(&mut self, root: Handle) -> usize {
+        let span = TraceSpan::transform("sanitize_feed_html");
+        let mut changed = 0;
+        if let Some(base) = self.base_url() {
+            changed += self.rewrite_urls(root, |url| resolve(&base, url));
+        }
+        changed += self.sanitize(root, &SanitizeOptions::feed());
+
+        let links: Vec<Handle> = root
+            .descendants(&self.arena)
+            .filter(|&node| {
+                self.is_html_element(node, "a")
+                    && self.get_attr(node, "href").is_some()
+                    && !self.skip_frozen(node)
+            })
+            .collect();
+        // `rel` isn't among the allowed attributes, so any the links had are gone by now.
+        for &link in &links {
+            self.set_attr(link, "rel", StrTendril::from("noopener noreferrer"));
+        }
+        changed += links.len();
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn sanitizes_feed_content() {
+        let mut dom = parse_html(
+            "<div class=entry style='color: red'><font color=red>Hi</font> \
+             <a href=post.html rel=author target=_blank>post</a> \
+             <a href=javascript:alert(1)>bad</a><img src=/i.png onerror=x()>\
+             <style>p{}</style><script>x()</script><!-- note -->\
+             <form><input name=q></form><p id=p lang=en>text</p></div>",
+        );
+        dom.set_url("https://example.com/blog/");
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        dom.sanitize_feed_html(body);
+        assert_eq!(
+            body_html(&dom),
+            "<div>Hi <a href=\"https://example.com/blog/post.html\" rel=\"noopener noreferrer\">\
+             post</a> <a>bad</a><img src=\"https://example.com/i.png\">\
+             <p lang=\"en\">text</p></div>"
+        );
+    }
+
+    #[test]
+    fn leaves_relative_urls_without_a_base() {
+        let mut dom = parse_html("<a href=post.html>post</a>");
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        dom.sanitize_feed_html(body);
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"post.html\" rel=\"noopener noreferrer\">post</a>"
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{has_token, GenerationalArenaDom, Handle};
+
+impl GenerationalArenaDom {
+    /// Shift the levels of the headings under `root` by `delta`, e.g. `h2` to `h3` for a delta
+    /// of 1, returning how many headings changed.
+    ///
+    /// This fits an extracted fragment into the heading structure of the page it is embedded
+    /// in: an article whose title is an `<h1>` goes under a host page's `<h2>` with a delta of
+    /// 2. Levels are clamped to 1–6, so headings that would go past `h6` all become `h6`, and
+    /// headings keep their attributes and contents. Elements with `role="heading"` have their
+    /// `aria-level` shifted the same way. Frozen headings are left alone.
+    pub fn rebase_headings(&mut self, root: Handle, delta: i32) -> usize {
+        let span = TraceSpan::transform("rebase_headings");
+        let nodes: Vec<Handle> = root.descendants(&self.arena).collect();
+        let mut changed = 0;
+        for node in nodes {
+            let shift = |level: usize| (level as i64 + i64::from(delta)).clamp(1, 6) as usize;
+            if let Some(rank) = self.heading_rank(node) {
+                let rank = shift(rank);
+                if self.heading_rank(node) != Some(rank) && !self.skip_frozen(node) {
+                    self.rename_html_element(node, &format!("h{}", rank));
+                    changed += 1;
+                }
+                continue;
+            }
+            let is_aria_heading = self
+                .get_attr(node, "role")
+                .is_some_and(|role| has_token(&role, "heading"));
+            let level = self
+                .get_attr(node, "aria-level")
+                .and_then(|level| level.trim().parse::<usize>().ok());
+            if let (true, Some(level)) = (is_aria_heading, level) {
+                let shifted = shift(level);
+                if shifted != level && !self.skip_frozen(node) {
+                    self.set_attr(node, "aria-level", StrTendril::from(shifted.to_string()));
+                    changed += 1;
+                }
+            }
+        }
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::{body_html, parse_html};
+
+    const HTML: &str = "<h1 id=\"t\" class=\"title\">T<em>!</em></h1><h2>A</h2><h5>B</h5>\
+                        <h6>C</h6><div role=\"heading\" aria-level=\"2\">D</div>";
+
+    #[test]
+    fn shifts_headings_down() {
+        let mut dom = parse_html(HTML);
+        assert_eq!(dom.rebase_headings(dom.document, 2), 4);
+        assert_eq!(
+            body_html(&dom),
+            "<h3 id=\"t\" class=\"title\">T<em>!</em></h3><h4>A</h4><h6>B</h6>\
+             <h6>C</h6><div role=\"heading\" aria-level=\"4\">D</div>"
+        );
+    }
+
+    #[test]
+    fn shifts_headings_up() {
+        let mut dom = parse_html(HTML);
+        assert_eq!(dom.rebase_headings(dom.document, -1), 4);
+        assert_eq!(
+            body_html(&dom),
+            "<h1 id=\"t\" class=\"title\">T<em>!</em></h1><h1>A</h1><h4>B</h4>\
+             <h5>C</h5><div role=\"heading\" aria-level=\"1\">D</div>"
+        );
+    }
+
+    #[test]
+    fn clamps_levels() {
+        let mut dom = parse_html(HTML);
+        assert_eq!(dom.rebase_headings(dom.document, 10), 4);
+        assert_eq!(dom.rebase_headings(dom.document, 1), 0);
+        assert_eq!(dom.rebase_headings(dom.document, -10), 5);
+        assert_eq!(
+            body_html(&dom),
+            "<h1 id=\"t\" class=\"title\">T<em>!</em></h1><h1>A</h1><h1>B</h1>\
+             <h1>C</h1><div role=\"heading\" aria-level=\"1\">D</div>"
+        );
+        assert_eq!(dom.rebase_headings(dom.document, 0), 0);
+    }
+}
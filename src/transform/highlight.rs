@@ -0,0 +1,122 @@
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, InsertPosition, TextRange};
+
+/// Options for [`GenerationalArenaDom::highlight_ranges`].
+#[derive(Clone, Debug)]
+pub struct HighlightOptions {
+    /// The name of the HTML elements wrapped around highlighted text. Defaults to `mark`.
+    pub element: String,
+
+    /// A class given to those elements. Defaults to none.
+    pub class: Option<String>,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> HighlightOptions {
+        HighlightOptions {
+            element: "mark".to_string(),
+            class: None,
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Wrap the text in `ranges` in `<mark>` elements, or whichever element `opts` names,
+    /// returning them in document order.
+    ///
+    /// Text nodes are split where ranges start and end inside them. A range spanning several
+    /// text nodes, as in `fo[o <b>ba]r</b>`, is highlighted with a `<mark>` around each part of
+    /// it, so the elements between them aren't broken up. Ranges that overlap or touch are
+    /// merged first, and text in scripts, styles, `<title>` and `<textarea>` is left alone, as
+    /// are ranges with frozen text in them.
+    ///
+    /// Ranges must be in the document, and their points must be at character boundaries.
+    pub fn highlight_ranges(
+        &mut self,
+        ranges: &[TextRange],
+        opts: &HighlightOptions,
+    ) -> Vec<Handle> {
+        let span = TraceSpan::transform("highlight_ranges");
+        let merged = self.merge_ranges(ranges);
+
+        // From last to first, since isolating a range keeps those before it valid.
+        let mut marks = Vec::new();
+        for range in merged.iter().rev() {
+            if self.skip_frozen_range(range) {
+                continue;
+            }
+            for text in self.isolate_range(range).into_iter().rev() {
+                if self.is_unsearched_text(text) {
+                    continue;
+                }
+                let mut attrs = Vec::new();
+                if let Some(class) = &opts.class {
+                    attrs.push(Attribute {
+                        name: QualName::new(None, ns!(), LocalName::from("class")),
+                        value: StrTendril::from(&**class),
+                    });
+                }
+                let mark = self.create_html_element(&opts.element, attrs);
+                self.insert_at(&[mark], InsertPosition::Before(text));
+                text.detach(&mut self.arena);
+                self.insert_at(&[text], InsertPosition::AppendTo(mark));
+                marks.push(mark);
+            }
+        }
+        marks.reverse();
+        span.changed(self.arena.count(), marks.len());
+        marks
+    }
+
+    /// Highlight the occurrences of `needle` in the text under `root`, as found by
+    /// [`find_text`](GenerationalArenaDom::find_text), with
+    /// [`highlight_ranges`](GenerationalArenaDom::highlight_ranges).
+    pub fn highlight_text(
+        &mut self,
+        root: Handle,
+        needle: &str,
+        opts: &HighlightOptions,
+    ) -> Vec<Handle> {
+        let span = TraceSpan::transform("highlight_text");
+        let ranges = self.find_text(root, needle);
+        let marks = self.highlight_ranges(&ranges, opts);
+        span.changed(self.arena.count(), marks.len());
+        marks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HighlightOptions;
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn highlights_text() {
+        let mut dom = parse_html("<p>foo <b>bar</b> baz</p><textarea>o b</textarea>");
+        let root = dom.document;
+        let marks = dom.highlight_text(root, "o b", &HighlightOptions::default());
+        assert_eq!(marks.len(), 2);
+        assert_eq!(
+            body_html(&dom),
+            "<p>fo<mark>o </mark><b><mark>b</mark>ar</b> baz</p><textarea>o b</textarea>"
+        );
+    }
+
+    #[test]
+    fn merges_overlapping_ranges() {
+        let mut dom = parse_html("<p>banana</p>");
+        let root = dom.document;
+        let mut ranges = dom.find_text(root, "ana");
+        ranges.extend(dom.find_text(root, "nan"));
+        let opts = HighlightOptions {
+            element: "span".to_string(),
+            class: Some("hit".to_string()),
+        };
+        let marks = dom.highlight_ranges(&ranges, &opts);
+        assert_eq!(marks.len(), 1);
+        assert_eq!(body_html(&dom), "<p>b<span class=\"hit\">anan</span>a</p>");
+    }
+}
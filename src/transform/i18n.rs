@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns};
+use sha2::{Digest, Sha256};
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, InsertPosition, NodeData, TreePath};
+
+/// Elements that separate messages, besides block-level elements.
+const BOUNDARY_ELEMENTS: &[&str] = &[
+    "button", "head", "html", "legend", "optgroup", "option", "select", "title",
+];
+
+/// Elements kept whole in messages rather than translated: their contents aren't prose.
+const OPAQUE_ELEMENTS: &[&str] = &[
+    "br", "code", "iframe", "img", "input", "kbd", "noscript", "picture", "samp", "script",
+    "style", "template", "textarea", "var", "video", "audio", "wbr",
+];
+
+/// Attributes whose values are translated.
+const TRANSLATABLE_ATTRIBUTES: &[&str] = &["alt", "aria-label", "placeholder", "title"];
+
+/// A piece of text to translate, found by [`GenerationalArenaDom::extract_messages`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    /// Identifies where the text is and what it was, for
+    /// [`apply_translations`](GenerationalArenaDom::apply_translations). It's made of the
+    /// [tree path](GenerationalArenaDom::path_of) of the text and a hash of `source`, so it
+    /// stays the same as long as the document's structure up to the text and the text itself
+    /// do.
+    pub key: String,
+
+    /// The text. Inline elements within it are numbered placeholders, in order:
+    /// `<0>…</0>` around translatable contents, and `<1/>` for elements kept whole, such as
+    /// `<img>`, `<code>` and elements with `translate="no"`. `<` and `&` in the text itself are
+    /// written `&lt;` and `&amp;`. Whitespace is collapsed, except in `<pre>`, and comments are
+    /// left out.
+    pub source: String,
+
+    /// The attribute the text is the value of, or `None` for text content.
+    pub attribute: Option<String>,
+
+    /// The first node of the text, or the element with the attribute.
+    pub handle: Handle,
+}
+
+/// What [`GenerationalArenaDom::apply_translations`] did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TranslationReport {
+    /// How many translations were applied.
+    pub applied: usize,
+
+    /// The keys of translations that no longer match the document, because its structure or
+    /// source text changed since the messages were extracted.
+    pub stale: Vec<String>,
+
+    /// The keys of translations with unbalanced or unknown placeholders.
+    pub invalid: Vec<String>,
+
+    /// The keys of translations that weren't applied because their text is
+    /// [frozen](GenerationalArenaDom::freeze).
+    pub frozen: Vec<String>,
+}
+
+/// Where a message's text is.
+enum Location {
+    /// `len` sibling nodes starting at the path.
+    Run(TreePath, usize),
+    /// An attribute of the element at the path.
+    Attribute(TreePath, String),
+}
+
+/// A part of a translation.
+enum Token<'a> {
+    Text(String),
+    Open(usize),
+    Close(usize),
+    Whole(usize),
+    /// Anything else starting with `<`, kept as text.
+    Other(&'a str),
+}
+
+impl GenerationalArenaDom {
+    /// Find the translatable text of the document, in document order.
+    ///
+    /// Text content is split into messages at block-level elements, so that each holds a
+    /// paragraph, heading, list item, table cell or the like, with the inline markup inside it
+    /// as placeholders. The `alt`, `aria-label`, `placeholder` and `title` attributes are
+    /// messages of their own. Elements with `translate="no"`, and scripts, styles and the like,
+    /// are left out.
+    pub fn extract_messages(&self) -> Vec<Message> {
+        let span = TraceSpan::transform("extract_messages");
+        let mut messages = Vec::new();
+        self.collect_messages(self.document, &mut messages);
+        span.changed(self.arena.count(), 0);
+        messages
+    }
+
+    /// Replace the text of the messages keyed in `translations` with the translations, in the
+    /// format of [`Message::source`]. Placeholders may be reordered, dropped or repeated; each
+    /// becomes a copy of the element it stands for.
+    ///
+    /// Translations whose keys don't match the document, or whose placeholders are malformed,
+    /// are left out and reported.
+    pub fn apply_translations(
+        &mut self,
+        translations: &HashMap<String, String>,
+    ) -> TranslationReport {
+        let span = TraceSpan::transform("apply_translations");
+        let mut report = TranslationReport::default();
+        let mut runs = Vec::new();
+        for (key, translation) in translations {
+            match parse_key(key) {
+                Some((Location::Attribute(path, name), hash)) => {
+                    let element = self.resolve_path(&path);
+                    let current = element.and_then(|element| self.get_attr(element, &name));
+                    match (element, current) {
+                        (Some(element), Some(current)) if hash == message_hash(&current) => {
+                            if self.skip_frozen(element) {
+                                report.frozen.push(key.clone());
+                                continue;
+                            }
+                            self.set_attr(element, &name, StrTendril::from(&**translation));
+                            report.applied += 1;
+                        }
+                        _ => report.stale.push(key.clone()),
+                    }
+                }
+                Some((Location::Run(path, len), hash)) => runs.push((path, len, hash, key)),
+                None => report.stale.push(key.clone()),
+            }
+        }
+
+        // Later runs first, so that replacing one doesn't move those still to be replaced.
+        runs.sort_by(|a, b| b.0.cmp(&a.0));
+        for (path, len, hash, key) in runs {
+            let nodes: Vec<Handle> = match self.resolve_path(&path) {
+                Some(first) => first.following_siblings(&self.arena).take(len).collect(),
+                None => Vec::new(),
+            };
+            let mut placeholders = Vec::new();
+            let source = self.render_message(&nodes, &mut placeholders);
+            if nodes.len() != len || hash != message_hash(&source) {
+                report.stale.push(key.clone());
+                continue;
+            }
+            if nodes.iter().any(|&node| self.skip_frozen_removal(node)) {
+                report.frozen.push(key.clone());
+                continue;
+            }
+            let replacement = match self.build_translation(&translations[key], &placeholders) {
+                Some(replacement) => replacement,
+                None => {
+                    report.invalid.push(key.clone());
+                    continue;
+                }
+            };
+            self.insert_at(&replacement, InsertPosition::Before(nodes[0]));
+            for node in nodes {
+                self.free_subtree(node);
+            }
+            report.applied += 1;
+        }
+        report.stale.sort();
+        report.invalid.sort();
+        report.frozen.sort();
+        if report.applied > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), report.applied);
+        report
+    }
+
+    /// Add the messages in `container`, an element or document, to `messages`.
+    fn collect_messages(&self, container: Handle, messages: &mut Vec<Message>) {
+        let mut run = Vec::new();
+        for child in container.children(&self.arena) {
+            if let NodeData::Element { .. } = self.get_node(&child) {
+                self.collect_attribute_messages(child, messages);
+            }
+            if self.is_message_boundary(child) {
+                self.add_run_message(&mut run, messages);
+                if !self.is_opaque(child) {
+                    self.collect_messages(child, messages);
+                }
+            } else {
+                run.push(child);
+            }
+        }
+        self.add_run_message(&mut run, messages);
+    }
+
+    /// Add the attribute messages of the element `node` and the inline elements within it,
+    /// which aren't otherwise visited.
+    fn collect_attribute_messages(&self, node: Handle, messages: &mut Vec<Message>) {
+        let inline = !self.is_message_boundary(node);
+        let elements: Vec<Handle> = if inline {
+            node.descendants(&self.arena).collect()
+        } else {
+            vec![node]
+        };
+        for element in elements {
+            let untranslated = element
+                .ancestors(&self.arena)
+                .any(|ancestor| self.get_attr(ancestor, "translate").as_deref() == Some("no"));
+            if untranslated {
+                continue;
+            }
+            for name in TRANSLATABLE_ATTRIBUTES {
+                let value = match self.get_attr(element, name) {
+                    Some(value) if !value.trim().is_empty() => value,
+                    _ => continue,
+                };
+                let path = match self.path_of(element) {
+                    Some(path) => path,
+                    None => continue,
+                };
+                messages.push(Message {
+                    key: format!("{}@{}#{}", path, name, message_hash(&value)),
+                    source: value.to_string(),
+                    attribute: Some(name.to_string()),
+                    handle: element,
+                });
+            }
+        }
+    }
+
+    /// Add the message made of the nodes in `run`, if they have any text, and clear it.
+    fn add_run_message(&self, run: &mut Vec<Handle>, messages: &mut Vec<Message>) {
+        let nodes = std::mem::take(run);
+        let has_text = nodes.iter().any(|&node| {
+            node.descendants(&self.arena).any(|node| {
+                let is_text = match self.get_node(&node) {
+                    NodeData::Text { contents } => !contents.borrow().trim().is_empty(),
+                    _ => false,
+                };
+                is_text
+                    && !node
+                        .ancestors(&self.arena)
+                        .any(|ancestor| self.is_opaque(ancestor))
+            })
+        });
+        if !has_text {
+            return;
+        }
+        let path = match self.path_of(nodes[0]) {
+            Some(path) => path,
+            None => return,
+        };
+        let source = self.render_message(&nodes, &mut Vec::new());
+        messages.push(Message {
+            key: format!("{}+{}#{}", path, nodes.len(), message_hash(&source)),
+            source,
+            attribute: None,
+            handle: nodes[0],
+        });
+    }
+
+    /// The message text of `nodes`, adding the elements its placeholders stand for to
+    /// `placeholders`.
+    fn render_message(&self, nodes: &[Handle], placeholders: &mut Vec<Handle>) -> String {
+        let mut text = String::new();
+        for &node in nodes {
+            self.render_message_node(node, placeholders, &mut text);
+        }
+        let preformatted = nodes.first().is_some_and(|&node| {
+            node.ancestors(&self.arena)
+                .any(|ancestor| self.is_html_element(ancestor, "pre"))
+        });
+        if preformatted {
+            text
+        } else {
+            text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+        }
+    }
+
+    fn render_message_node(&self, node: Handle, placeholders: &mut Vec<Handle>, text: &mut String) {
+        match self.get_node(&node) {
+            NodeData::Text { contents } => {
+                text.push_str(&contents.borrow().replace('&', "&amp;").replace('<', "&lt;"));
+            }
+            NodeData::Element { .. } => {
+                let index = placeholders.len();
+                placeholders.push(node);
+                if self.is_opaque(node) || node.children(&self.arena).next().is_none() {
+                    text.push_str(&format!("<{}/>", index));
+                } else {
+                    text.push_str(&format!("<{}>", index));
+                    for child in node.children(&self.arena) {
+                        self.render_message_node(child, placeholders, text);
+                    }
+                    text.push_str(&format!("</{}>", index));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// The nodes for `translation`, or `None` if its placeholders are malformed.
+    fn build_translation(
+        &mut self,
+        translation: &str,
+        placeholders: &[Handle],
+    ) -> Option<Vec<Handle>> {
+        let tokens = tokenize(translation);
+        if !is_well_formed(&tokens, placeholders.len()) {
+            return None;
+        }
+        // The children of each open placeholder, innermost last.
+        let mut stack: Vec<Vec<Handle>> = vec![Vec::new()];
+        for token in tokens {
+            let node = match token {
+                Token::Text(text) => self.create_text(&text),
+                Token::Other(text) => self.create_text(text),
+                Token::Whole(index) => self.clone_subtree(placeholders[index]),
+                Token::Open(_) => {
+                    stack.push(Vec::new());
+                    continue;
+                }
+                Token::Close(index) => {
+                    let children = stack.pop().unwrap_or_default();
+                    let element = self.clone_subtree(placeholders[index]);
+                    let old: Vec<Handle> = element.children(&self.arena).collect();
+                    for child in old {
+                        self.free_subtree(child);
+                    }
+                    for child in children {
+                        element.append(child, &mut self.arena);
+                    }
+                    element
+                }
+            };
+            if let Some(siblings) = stack.last_mut() {
+                siblings.push(node);
+            }
+        }
+        stack.pop()
+    }
+
+    /// Whether `node` isn't part of the messages around it.
+    fn is_message_boundary(&self, node: Handle) -> bool {
+        if self.element_name(node).is_none() {
+            return false;
+        }
+        let is_boundary = |node: Handle| {
+            self.is_block(node)
+                || BOUNDARY_ELEMENTS
+                    .iter()
+                    .any(|name| self.is_html_element(node, name))
+        };
+        // An inline element containing blocks can't be part of a message either.
+        !self.is_opaque(node) && node.descendants(&self.arena).any(is_boundary)
+            || self.is_opaque(node) && is_boundary(node)
+    }
+
+    /// Whether `node` is an element kept whole in messages.
+    fn is_opaque(&self, node: Handle) -> bool {
+        match self.element_name(node) {
+            Some(name) if name.ns == ns!(html) => {
+                OPAQUE_ELEMENTS.contains(&&*name.local)
+                    || self.get_attr(node, "translate").as_deref() == Some("no")
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+}
+
+/// A short hash of a message's source text.
+fn message_hash(source: &str) -> String {
+    Sha256::digest(source.as_bytes())[..4]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn parse_key(key: &str) -> Option<(Location, String)> {
+    let (location, hash) = key.rsplit_once('#')?;
+    let location = if let Some((path, name)) = location.split_once('@') {
+        Location::Attribute(path.parse().ok()?, name.to_string())
+    } else {
+        let (path, len) = location.split_once('+')?;
+        Location::Run(path.parse().ok()?, len.parse().ok()?)
+    };
+    Some((location, hash.to_string()))
+}
+
+/// Whether the placeholders in `tokens` are all below `count` and properly nested.
+fn is_well_formed(tokens: &[Token<'_>], count: usize) -> bool {
+    let mut open = Vec::new();
+    for token in tokens {
+        match *token {
+            Token::Open(index) if index < count => open.push(index),
+            Token::Close(index) if open.pop() == Some(index) => {}
+            Token::Whole(index) if index < count => {}
+            Token::Text(_) | Token::Other(_) => {}
+            _ => return false,
+        }
+    }
+    open.is_empty()
+}
+
+/// Split a translation into text and placeholders, decoding `&lt;`, `&gt;` and `&amp;`.
+fn tokenize(translation: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut rest = translation;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+            let tag = &rest[..end];
+            let inner = tag.trim_start_matches('<').trim_end_matches('>');
+            let token = if let Some(index) = inner.strip_prefix('/') {
+                index.parse().ok().map(Token::Close)
+            } else if let Some(index) = inner.strip_suffix('/') {
+                index.parse().ok().map(Token::Whole)
+            } else {
+                inner.parse().ok().map(Token::Open)
+            };
+            if !text.is_empty() {
+                tokens.push(Token::Text(std::mem::take(&mut text)));
+            }
+            tokens.push(token.unwrap_or(Token::Other(tag)));
+            rest = &rest[end..];
+        } else if let Some(after) = rest.strip_prefix("&lt;") {
+            text.push('<');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("&gt;") {
+            text.push('>');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("&amp;") {
+            text.push('&');
+            rest = after;
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(Token::Text(text));
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::parse::{body_html, parse_html};
+
+    const HTML: &str = "<p>Hello <b>dear <i>reader</i></b>, see <code>x < y</code> &amp; \
+                        <img alt='A cat'>!</p>\
+                        <ul><li>One</li><li translate=no>Two</li></ul>\
+                        <div>Lead <p>Para</p><input placeholder=Search></div><script>x</script>";
+
+    #[test]
+    fn extracts_messages() {
+        let dom = parse_html(HTML);
+        let messages: Vec<(String, Option<String>)> = dom
+            .extract_messages()
+            .into_iter()
+            .map(|message| (message.source, message.attribute))
+            .collect();
+        let text = |source: &str| (source.to_string(), None);
+        let attr = |source: &str, name: &str| (source.to_string(), Some(name.to_string()));
+        assert_eq!(
+            messages,
+            [
+                attr("A cat", "alt"),
+                text("Hello <0>dear <1>reader</1></0>, see <2/> &amp; <3/>!"),
+                text("One"),
+                text("Lead"),
+                text("Para"),
+                attr("Search", "placeholder"),
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_translations() {
+        let mut dom = parse_html(HTML);
+        let keys: HashMap<String, String> = dom
+            .extract_messages()
+            .into_iter()
+            .map(|message| (message.source, message.key))
+            .collect();
+        let translations = HashMap::from([
+            (keys["A cat"].clone(), "Un chat".to_string()),
+            (
+                keys["Hello <0>dear <1>reader</1></0>, see <2/> &amp; <3/>!"].clone(),
+                "<3/> Bonjour <0><1>lecteur</1> cher</0> &lt;3, voir <2/>".to_string(),
+            ),
+            (keys["One"].clone(), "Un <0>".to_string()),
+            (keys["Para"].clone(), "Paragraphe".to_string()),
+            ("0.1+1#00000000".to_string(), "Stale".to_string()),
+        ]);
+        let report = dom.apply_translations(&translations);
+        assert_eq!(report.applied, 3);
+        assert_eq!(report.stale, ["0.1+1#00000000"]);
+        assert_eq!(report.invalid, [keys["One"].clone()]);
+        assert!(report.frozen.is_empty());
+        assert_eq!(
+            body_html(&dom),
+            "<p><img alt=\"Un chat\"> Bonjour <b><i>lecteur</i> cher</b> &lt;3, voir \
+             <code>x &lt; y</code></p>\
+             <ul><li>One</li><li translate=\"no\">Two</li></ul>\
+             <div>Lead <p>Paragraphe</p><input placeholder=\"Search\"></div><script>x</script>"
+        );
+
+        // Applying them again finds the source text changed.
+        let report = dom.apply_translations(&translations);
+        assert_eq!(report.applied, 0);
+        assert_eq!(report.stale.len(), 4);
+    }
+}
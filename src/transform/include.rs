@@ -0,0 +1,284 @@
+use std::fmt;
+
+use markup5ever::{namespace_url, ns, LocalName, QualName};
+
+use crate::trace::TraceSpan;
+use crate::{Fragment, FragmentParser, GenerationalArenaDom, Handle, InsertPosition};
+
+/// Options for [`GenerationalArenaDom::resolve_includes`].
+#[derive(Clone, Debug)]
+pub struct IncludeOptions {
+    /// The name of the HTML elements replaced by partials. Defaults to `include`.
+    pub element: String,
+
+    /// The attribute of those elements naming the partial. Defaults to `src`.
+    pub attribute: String,
+
+    /// How deeply partials may include other partials. Defaults to 32.
+    pub max_depth: usize,
+}
+
+impl Default for IncludeOptions {
+    fn default() -> IncludeOptions {
+        IncludeOptions {
+            element: "include".to_string(),
+            attribute: "src".to_string(),
+            max_depth: 32,
+        }
+    }
+}
+
+/// Why [`GenerationalArenaDom::resolve_includes`] failed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IncludeError {
+    /// The loader had nothing for a partial.
+    NotFound {
+        /// The partial's name.
+        src: String,
+    },
+
+    /// A partial includes itself, directly or through others.
+    Cycle {
+        /// The names of the partials in the cycle, starting and ending with the same one.
+        chain: Vec<String>,
+    },
+
+    /// Partials are nested more deeply than [`IncludeOptions::max_depth`].
+    TooDeep {
+        /// The configured limit.
+        max_depth: usize,
+    },
+}
+
+impl fmt::Display for IncludeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IncludeError::NotFound { src } => write!(f, "partial {:?} not found", src),
+            IncludeError::Cycle { chain } => {
+                write!(f, "partials include themselves: {}", chain.join(" -> "))
+            }
+            IncludeError::TooDeep { max_depth } => {
+                write!(f, "partials are nested more than {} deep", max_depth)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {}
+
+impl GenerationalArenaDom {
+    /// Replace each `<include src="…">` element (or whichever element and attribute `opts`
+    /// names) with the partial `load` returns for its `src`, parsed with `parser` in the
+    /// context of the element's parent. Partials can include other partials, which are
+    /// resolved in turn. Elements in template contents are replaced too, with partials directly
+    /// in a template's contents parsed in the context of the template. Returns how many
+    /// elements were replaced. Frozen elements aren't.
+    ///
+    /// `load` is called with the value of the attribute as written, every time it appears.
+    /// If it returns `None`, a partial includes itself, or partials are nested too deeply, an
+    /// error is returned, leaving the includes resolved so far in place.
+    pub fn resolve_includes<P, F>(
+        &mut self,
+        parser: &P,
+        opts: &IncludeOptions,
+        mut load: F,
+    ) -> Result<usize, IncludeError>
+    where
+        P: FragmentParser + ?Sized,
+        F: FnMut(&str) -> Option<String>,
+    {
+        let span = TraceSpan::transform("resolve_includes");
+        let mut stack = Vec::new();
+        let replaced = self.expand_includes(self.document, parser, opts, &mut load, &mut stack)?;
+        Ok(span.changed(self.arena.count(), replaced))
+    }
+
+    fn expand_includes<P, F>(
+        &mut self,
+        root: Handle,
+        parser: &P,
+        opts: &IncludeOptions,
+        load: &mut F,
+        stack: &mut Vec<String>,
+    ) -> Result<usize, IncludeError>
+    where
+        P: FragmentParser + ?Sized,
+        F: FnMut(&str) -> Option<String>,
+    {
+        let mut markers = Vec::new();
+        let mut roots = vec![root];
+        while let Some(root) = roots.pop() {
+            for node in root.descendants(&self.arena) {
+                roots.extend(self.template_contents_of(node));
+                if self.is_html_element(node, &opts.element)
+                    && self.get_attr(node, &opts.attribute).is_some()
+                {
+                    markers.push(node);
+                }
+            }
+        }
+        let mut replaced = 0;
+        for marker in markers {
+            if self.arena.get(marker).is_none() {
+                // Inside a marker replaced earlier.
+                continue;
+            }
+            if self.skip_frozen_removal(marker) {
+                continue;
+            }
+            let src = self
+                .get_attr(marker, &opts.attribute)
+                .unwrap_or_default()
+                .to_string();
+            if stack.contains(&src) {
+                let start = stack.iter().position(|name| *name == src).unwrap_or(0);
+                let mut chain = stack[start..].to_vec();
+                chain.push(src);
+                return Err(IncludeError::Cycle { chain });
+            }
+            if stack.len() >= opts.max_depth {
+                return Err(IncludeError::TooDeep {
+                    max_depth: opts.max_depth,
+                });
+            }
+            let html = load(&src).ok_or_else(|| IncludeError::NotFound { src: src.clone() })?;
+            // Partials directly in template contents are parsed as if in the template.
+            let context = self.arena[marker]
+                .parent()
+                .map(|parent| self.template_owner(parent).unwrap_or(parent))
+                .and_then(|parent| self.element_name(parent).cloned())
+                .unwrap_or_else(|| QualName::new(None, ns!(html), LocalName::from("body")));
+            let fragment = Fragment::parse(&context, &html, parser);
+            let roots = self
+                .insert_fragment(fragment, InsertPosition::Before(marker))
+                .unwrap_or_default();
+            self.free_subtree(marker);
+            replaced += 1;
+
+            stack.push(src);
+            for root in roots {
+                replaced += self.expand_includes(root, parser, opts, load, stack)?;
+            }
+            stack.pop();
+        }
+        Ok(replaced)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IncludeError, IncludeOptions};
+    use crate::parse::{body_html, parse_fragment_html, parse_html};
+
+    fn partial(src: &str) -> Option<String> {
+        let html = match src {
+            "header" => "<h1>Title</h1><include src=nav></include>",
+            "nav" => "<nav>links</nav>",
+            "rows" => "<tr><td>1</td></tr>",
+            "loop" => "<p><include src=inner></include></p>",
+            "inner" => "<include src=loop></include>",
+            _ => return None,
+        };
+        Some(html.to_string())
+    }
+
+    #[test]
+    fn includes_partials_recursively() {
+        let mut dom = parse_html("<include src=header></include><p>body</p>");
+        let mut loaded = Vec::new();
+        let replaced =
+            dom.resolve_includes(&parse_fragment_html, &IncludeOptions::default(), |src| {
+                loaded.push(src.to_string());
+                partial(src)
+            });
+        assert_eq!(replaced, Ok(2));
+        assert_eq!(loaded, ["header", "nav"]);
+        assert_eq!(body_html(&dom), "<h1>Title</h1><nav>links</nav><p>body</p>");
+    }
+
+    #[test]
+    fn parses_partials_in_context() {
+        let mut dom = parse_html("<table><tbody><tr></tr></tbody></table>");
+        let marker = dom.query_selector(dom.document, "tr").unwrap().unwrap();
+        dom.rename_html_element(marker, "include");
+        dom.set_attr(marker, "src", "rows".into());
+        let opts = IncludeOptions::default();
+        assert_eq!(
+            dom.resolve_includes(&parse_fragment_html, &opts, partial),
+            Ok(1)
+        );
+        assert_eq!(
+            body_html(&dom),
+            "<table><tbody><tr><td>1</td></tr></tbody></table>"
+        );
+    }
+
+    #[test]
+    fn uses_configured_markers() {
+        let mut dom = parse_html("<div data-partial=nav></div><div>kept</div>");
+        let opts = IncludeOptions {
+            element: "div".to_string(),
+            attribute: "data-partial".to_string(),
+            ..IncludeOptions::default()
+        };
+        assert_eq!(
+            dom.resolve_includes(&parse_fragment_html, &opts, partial),
+            Ok(1)
+        );
+        assert_eq!(body_html(&dom), "<nav>links</nav><div>kept</div>");
+    }
+
+    #[test]
+    fn includes_partials_in_templates() {
+        let mut dom = parse_html(
+            "<p>x</p><template><include src=header></include></template>\
+             <table><template><include src=rows></include></template></table>",
+        );
+        let opts = IncludeOptions::default();
+        assert_eq!(
+            dom.resolve_includes(&parse_fragment_html, &opts, partial),
+            Ok(3)
+        );
+        assert_eq!(
+            body_html(&dom),
+            "<p>x</p><template><h1>Title</h1><nav>links</nav></template>\
+             <table><template><tr><td>1</td></tr></template></table>"
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        let opts = IncludeOptions::default();
+        let mut dom = parse_html("<include src=missing></include>");
+        assert_eq!(
+            dom.resolve_includes(&parse_fragment_html, &opts, partial),
+            Err(IncludeError::NotFound {
+                src: "missing".to_string()
+            })
+        );
+
+        let mut dom = parse_html("<include src=loop></include>");
+        let error = dom
+            .resolve_includes(&parse_fragment_html, &opts, partial)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "partials include themselves: loop -> inner -> loop"
+        );
+
+        let mut dom = parse_html("<include src=header></include>");
+        let opts = IncludeOptions {
+            max_depth: 1,
+            ..IncludeOptions::default()
+        };
+        assert_eq!(
+            dom.resolve_includes(&parse_fragment_html, &opts, partial),
+            Err(IncludeError::TooDeep { max_depth: 1 })
+        );
+        assert_eq!(
+            body_html(&dom),
+            "<h1>Title</h1><include src=\"nav\"></include>"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use markup5ever::tendril::StrTendril;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::trace::TraceSpan;
+use crate::{has_token, GenerationalArenaDom, Handle};
+
+/// A hash function for [Subresource Integrity](https://www.w3.org/TR/SRI/) metadata.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IntegrityAlgorithm {
+    /// SHA-256.
+    Sha256,
+    /// SHA-384, the default, as the specification recommends.
+    #[default]
+    Sha384,
+    /// SHA-512.
+    Sha512,
+}
+
+impl IntegrityAlgorithm {
+    /// The name of the algorithm as used in metadata, such as `sha384`.
+    pub fn name(self) -> &'static str {
+        match self {
+            IntegrityAlgorithm::Sha256 => "sha256",
+            IntegrityAlgorithm::Sha384 => "sha384",
+            IntegrityAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// The integrity metadata for a resource with the contents `bytes`, such as
+    /// `sha384-oqVuAfXRKap7fdgcCY5uykM6+R9GqQ8K/uxy9rx7HNQlGYl1kPzQho1wx4JwY8wC`.
+    pub fn integrity(self, bytes: &[u8]) -> String {
+        let digest = match self {
+            IntegrityAlgorithm::Sha256 => BASE64.encode(Sha256::digest(bytes)),
+            IntegrityAlgorithm::Sha384 => BASE64.encode(Sha384::digest(bytes)),
+            IntegrityAlgorithm::Sha512 => BASE64.encode(Sha512::digest(bytes)),
+        };
+        format!("{}-{}", self.name(), digest)
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Set the `integrity` attribute of each `<script src>` and `<link>` to a stylesheet,
+    /// preload or module preload, returning how many were set.
+    ///
+    /// `resolve` is called once per URL, resolved against the document's base URL, and returns
+    /// the resource's contents exactly as they will be served, or `None` to leave the element
+    /// alone. Existing `integrity` attributes are replaced. Browsers only check the integrity
+    /// of resources from other origins if they are loaded with CORS, which needs a
+    /// `crossorigin` attribute that this doesn't add. Frozen elements are left alone.
+    pub fn add_integrity<F>(&mut self, algorithm: IntegrityAlgorithm, mut resolve: F) -> usize
+    where
+        F: FnMut(&str) -> Option<Vec<u8>>,
+    {
+        let span = TraceSpan::transform("add_integrity");
+        let targets: Vec<(Handle, String)> = self
+            .document
+            .descendants(&self.arena)
+            .filter_map(|node| {
+                let url = if self.is_html_element(node, "script") {
+                    self.get_attr(node, "src")?
+                } else if self.is_html_element(node, "link") {
+                    let rel = self.get_attr(node, "rel")?;
+                    let subresource = ["stylesheet", "preload", "modulepreload"]
+                        .iter()
+                        .any(|keyword| has_token(&rel, keyword));
+                    if !subresource {
+                        return None;
+                    }
+                    self.get_attr(node, "href")?
+                } else {
+                    return None;
+                };
+                if self.skip_frozen(node) {
+                    return None;
+                }
+                Some((node, self.resolve_url(&url)))
+            })
+            .collect();
+
+        let mut cache: HashMap<String, Option<String>> = HashMap::new();
+        let mut added = 0;
+        for (node, url) in targets {
+            let integrity = cache
+                .entry(url)
+                .or_insert_with_key(|url| resolve(url).map(|bytes| algorithm.integrity(&bytes)))
+                .clone();
+            if let Some(integrity) = integrity {
+                self.set_attr(node, "integrity", StrTendril::from(integrity));
+                added += 1;
+            }
+        }
+        if added > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntegrityAlgorithm;
+    use crate::{GenerationalArenaDom, Selector};
+
+    #[test]
+    fn computes_metadata() {
+        let script = b"alert('Hello, world.');";
+        assert_eq!(
+            IntegrityAlgorithm::Sha256.integrity(script),
+            "sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng="
+        );
+        assert_eq!(
+            IntegrityAlgorithm::default().integrity(script),
+            "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO"
+        );
+        assert_eq!(
+            IntegrityAlgorithm::Sha512.integrity(script),
+            "sha512-Q2bFTOhEALkN8hOms2FKTDLy7eugP2zFZ1T8LCvX42Fp3WoNr3bjZSAHeOsHrbV1Fu9/A0EzCinRE7Af1ofPrw=="
+        );
+    }
+
+    #[test]
+    fn adds_integrity_to_subresources() {
+        let mut dom = GenerationalArenaDom::from_abbreviation(
+            "script[src=a.js]+link[rel='preload stylesheet' href=a.css]+link[rel=icon href=a.ico]\
+             +script[src=missing.js]+script[src=a.js]",
+        )
+        .unwrap();
+        dom.set_url("https://example.com/");
+        let mut requested = Vec::new();
+        let added = dom.add_integrity(IntegrityAlgorithm::Sha384, |url| {
+            requested.push(url.to_string());
+            match url {
+                "https://example.com/a.js" => Some(b"alert('Hello, world.');".to_vec()),
+                "https://example.com/a.css" => Some(b"body{}".to_vec()),
+                _ => None,
+            }
+        });
+        assert_eq!(added, 3);
+        assert_eq!(
+            requested,
+            [
+                "https://example.com/a.js",
+                "https://example.com/a.css",
+                "https://example.com/missing.js"
+            ]
+        );
+        let all = Selector::parse("*").unwrap();
+        let integrity: Vec<_> = dom
+            .select(dom.document, &all)
+            .into_iter()
+            .filter(|&node| {
+                dom.get_attr(node, "src").is_some() || dom.get_attr(node, "href").is_some()
+            })
+            .map(|node| {
+                dom.get_attr(node, "integrity")
+                    .map(|value| value[..12].to_string())
+            })
+            .collect();
+        assert_eq!(
+            integrity,
+            [
+                Some("sha384-H8BRh".to_string()),
+                Some("sha384-myyg/".to_string()),
+                None,
+                None,
+                Some("sha384-H8BRh".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,184 @@
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle};
+
+/// Elements whose `src` is lazy-loaded by scripts.
+const LAZY_ELEMENTS: &[&str] = &["img", "iframe", "source", "video", "audio", "embed"];
+
+/// Options for [`GenerationalArenaDom::normalize_lazy_loading`].
+#[derive(Clone, Debug)]
+pub struct LazyLoadOptions {
+    /// Attributes that lazy-loading scripts read the real `src` from, in order of preference.
+    /// Defaults to `data-src`, `data-lazy-src`, `data-lazy`, `data-original` and `data-url`.
+    pub src: Vec<String>,
+
+    /// Attributes holding the real `srcset`. Defaults to `data-srcset`, `data-lazy-srcset`
+    /// and `data-original-set`.
+    pub srcset: Vec<String>,
+
+    /// Attributes holding the real `sizes`. Defaults to `data-sizes`.
+    pub sizes: Vec<String>,
+
+    /// Attributes holding the real `poster` of a video. Defaults to `data-poster`.
+    pub poster: Vec<String>,
+
+    /// Whether to remove the lazy-loading attributes once they have been promoted.
+    pub remove_promoted: bool,
+}
+
+impl Default for LazyLoadOptions {
+    fn default() -> LazyLoadOptions {
+        let strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        LazyLoadOptions {
+            src: strings(&[
+                "data-src",
+                "data-lazy-src",
+                "data-lazy",
+                "data-original",
+                "data-url",
+            ]),
+            srcset: strings(&["data-srcset", "data-lazy-srcset", "data-original-set"]),
+            sizes: strings(&["data-sizes"]),
+            poster: strings(&["data-poster"]),
+            remove_promoted: false,
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Promote the attributes lazy-loading scripts use to hold the real URLs of images, frames
+    /// and media under `root` (such as `data-src` and `data-srcset`) to the attributes browsers
+    /// load from, returning how many attributes were set.
+    ///
+    /// A real attribute is only replaced if it is missing, empty, or a placeholder: a `data:`
+    /// URL, `about:blank`, or a file named like `blank.gif`, `spacer.gif`, `pixel.gif`,
+    /// `placeholder.png` or similar. Frozen elements are skipped.
+    pub fn normalize_lazy_loading(&mut self, root: Handle, opts: &LazyLoadOptions) -> usize {
+        let span = TraceSpan::transform("normalize_lazy_loading");
+        let elements: Vec<Handle> = root
+            .descendants(&self.arena)
+            .filter(|&node| {
+                LAZY_ELEMENTS
+                    .iter()
+                    .any(|name| self.is_html_element(node, name))
+            })
+            .collect();
+        let mut promoted = 0;
+        for element in elements {
+            let mut targets = vec![
+                ("src", &opts.src),
+                ("srcset", &opts.srcset),
+                ("sizes", &opts.sizes),
+            ];
+            if self.is_html_element(element, "video") {
+                targets.push(("poster", &opts.poster));
+            }
+            for (target, sources) in targets {
+                let current = self.get_attr(element, target);
+                if current.is_some_and(|current| !is_placeholder(&current)) {
+                    continue;
+                }
+                let found = sources.iter().find_map(|source| {
+                    self.get_attr(element, source)
+                        .filter(|value| !value.trim().is_empty() && !is_placeholder(value))
+                        .map(|value| (source, value))
+                });
+                if let Some((source, value)) = found {
+                    if self.skip_frozen(element) {
+                        break;
+                    }
+                    self.set_attr(element, target, value);
+                    if opts.remove_promoted {
+                        self.remove_attr(element, source);
+                    }
+                    promoted += 1;
+                }
+            }
+        }
+        span.changed(self.arena.count(), promoted)
+    }
+}
+
+/// Whether `url` is empty or a stand-in shown until a script loads the real resource.
+fn is_placeholder(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    if url.is_empty() || url.starts_with("data:") || url == "about:blank" || url == "#" {
+        return true;
+    }
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let file = path.rsplit('/').next().unwrap_or_default();
+    let stem = file.split('.').next().unwrap_or_default();
+    [
+        "blank",
+        "spacer",
+        "pixel",
+        "placeholder",
+        "transparent",
+        "lazy",
+        "loading",
+        "1x1",
+    ]
+    .iter()
+    .any(|name| stem == *name || stem.starts_with(&format!("{}-", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_placeholder, LazyLoadOptions};
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn recognizes_placeholders() {
+        for url in ["", " ", "data:image/gif;base64,R0lG", "about:blank", "#"] {
+            assert!(is_placeholder(url), "{:?}", url);
+        }
+        for url in [
+            "/img/Blank.GIF?v=2",
+            "spacer.png",
+            "lazy-300x200.jpg",
+            "1x1.gif",
+        ] {
+            assert!(is_placeholder(url), "{:?}", url);
+        }
+        for url in ["/photo.jpg", "blanket.png", "pixels/cat.png"] {
+            assert!(!is_placeholder(url), "{:?}", url);
+        }
+    }
+
+    #[test]
+    fn promotes_lazy_attributes() {
+        let mut dom = parse_html(
+            "<img src=blank.gif data-src=/a.jpg data-srcset='/a.jpg 1x, /a2.jpg 2x'>\
+             <img src=/real.jpg data-src=/other.jpg>\
+             <img data-lazy-src=data:, data-original=/b.jpg>\
+             <video data-poster=/p.jpg></video><div data-src=/d.jpg></div>",
+        );
+        let root = dom.document;
+        assert_eq!(
+            dom.normalize_lazy_loading(root, &LazyLoadOptions::default()),
+            4
+        );
+        assert_eq!(
+            body_html(&dom),
+            "<img src=\"/a.jpg\" data-src=\"/a.jpg\" data-srcset=\"/a.jpg 1x, /a2.jpg 2x\" \
+             srcset=\"/a.jpg 1x, /a2.jpg 2x\">\
+             <img src=\"/real.jpg\" data-src=\"/other.jpg\">\
+             <img data-lazy-src=\"data:,\" data-original=\"/b.jpg\" src=\"/b.jpg\">\
+             <video data-poster=\"/p.jpg\" poster=\"/p.jpg\"></video><div data-src=\"/d.jpg\"></div>"
+        );
+    }
+
+    #[test]
+    fn removes_promoted_attributes() {
+        let mut dom = parse_html("<img data-src=/a.jpg data-sizes=50vw data-url=/b.jpg>");
+        let root = dom.document;
+        let opts = LazyLoadOptions {
+            remove_promoted: true,
+            ..LazyLoadOptions::default()
+        };
+        assert_eq!(dom.normalize_lazy_loading(root, &opts), 2);
+        assert_eq!(
+            body_html(&dom),
+            "<img data-url=\"/b.jpg\" src=\"/a.jpg\" sizes=\"50vw\">"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::url::UrlParts;
+use crate::{space_tokens, GenerationalArenaDom, Handle};
+
+/// What to do with external links, for [`GenerationalArenaDom::apply_link_policy`].
+#[derive(Clone, Debug)]
+pub struct LinkPolicy {
+    /// Keywords to add to the `rel` of external links, keeping any it already has. Defaults to
+    /// `nofollow` and `noopener`.
+    pub rel: Vec<String>,
+
+    /// Whether to make external links open in a new browsing context with
+    /// `target="_blank"`.
+    pub target_blank: bool,
+
+    /// Whether to remove external links altogether, keeping their contents. The other options
+    /// are then irrelevant.
+    pub strip: bool,
+}
+
+impl Default for LinkPolicy {
+    fn default() -> LinkPolicy {
+        LinkPolicy {
+            rel: vec!["nofollow".to_string(), "noopener".to_string()],
+            target_blank: false,
+            strip: false,
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Apply `policy` to the external `<a>` and `<area>` links under `root`, returning how many
+    /// there were.
+    ///
+    /// A link is external if its `href`, resolved against the document's base URL, is an
+    /// `http` or `https` URL with a different origin (scheme, host and port) than `origin`,
+    /// e.g. `https://example.com`. Relative links that can't be resolved are internal, and
+    /// links with other schemes, such as `mailto:`, are left alone, as are frozen links.
+    pub fn apply_link_policy(&mut self, root: Handle, origin: &str, policy: &LinkPolicy) -> usize {
+        let span = TraceSpan::transform("apply_link_policy");
+        let origin = Origin::parse(origin);
+        let external: Vec<Handle> = root
+            .descendants(&self.arena)
+            .filter(|&node| self.is_html_element(node, "a") || self.is_html_element(node, "area"))
+            .filter(|&link| {
+                let href = match self.get_attr(link, "href") {
+                    Some(href) => self.resolve_url(&href),
+                    None => return false,
+                };
+                Origin::parse(&href).is_some_and(|target| Some(target) != origin)
+            })
+            .filter(|&link| !self.skip_frozen(link))
+            .collect();
+
+        for &link in &external {
+            if policy.strip {
+                self.unwrap_node(link);
+                continue;
+            }
+            if !policy.rel.is_empty() {
+                let rel = self.get_attr(link, "rel").unwrap_or_default();
+                let mut tokens: Vec<&str> = space_tokens(&rel).collect();
+                for keyword in &policy.rel {
+                    if !tokens
+                        .iter()
+                        .any(|token| token.eq_ignore_ascii_case(keyword))
+                    {
+                        tokens.push(keyword);
+                    }
+                }
+                let rel = tokens.join(" ");
+                self.set_attr(link, "rel", StrTendril::from(rel));
+            }
+            if policy.target_blank {
+                self.set_attr(link, "target", StrTendril::from("_blank"));
+            }
+        }
+        self.mark_mutated();
+        span.changed(self.arena.count(), external.len())
+    }
+}
+
+/// The origin of an `http` or `https` URL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Origin {
+    scheme: String,
+    host: String,
+    port: u16,
+}
+
+impl Origin {
+    fn parse(url: &str) -> Option<Origin> {
+        let parts = UrlParts::parse(url.trim());
+        let scheme = parts.scheme?.to_ascii_lowercase();
+        let default_port = match scheme.as_str() {
+            "http" => 80,
+            "https" => 443,
+            _ => return None,
+        };
+        Some(Origin {
+            host: parts.host().filter(|host| !host.is_empty())?,
+            port: parts.port().unwrap_or(default_port),
+            scheme,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LinkPolicy;
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn marks_external_links() {
+        let mut dom = parse_html(
+            "<a href=https://other.com/ rel='external NOFOLLOW'>a</a>\
+             <a href=https://example.com:443/x>b</a><a href=/local>c</a>\
+             <a href=http://example.com/>d</a><a href=mailto:x@other.com>e</a>\
+             <map><area href=HTTPS://Other.com/></map>",
+        );
+        let root = dom.document;
+        let policy = LinkPolicy {
+            target_blank: true,
+            ..LinkPolicy::default()
+        };
+        assert_eq!(
+            dom.apply_link_policy(root, "https://example.com", &policy),
+            3
+        );
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"https://other.com/\" rel=\"external NOFOLLOW noopener\" target=\"_blank\">a</a>\
+             <a href=\"https://example.com:443/x\">b</a><a href=\"/local\">c</a>\
+             <a href=\"http://example.com/\" rel=\"nofollow noopener\" target=\"_blank\">d</a>\
+             <a href=\"mailto:x@other.com\">e</a><map><area href=\"HTTPS://Other.com/\" rel=\"nofollow noopener\" target=\"_blank\"></map>"
+        );
+    }
+
+    #[test]
+    fn resolves_links_against_the_base_url() {
+        let mut dom = parse_html(
+            "<base href=https://cdn.example.net/><a href=/x>a</a><a href=https://example.com/>b</a>",
+        );
+        let root = dom.document;
+        let policy = LinkPolicy {
+            strip: true,
+            ..LinkPolicy::default()
+        };
+        assert_eq!(
+            dom.apply_link_policy(root, "https://example.com", &policy),
+            1
+        );
+        assert_eq!(body_html(&dom), "a<a href=\"https://example.com/\">b</a>");
+    }
+}
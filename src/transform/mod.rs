@@ -0,0 +1,51 @@
+//! In-place transforms of a parsed document.
+//!
+//! The transforms themselves are methods on [`GenerationalArenaDom`](crate::GenerationalArenaDom);
+//! this module holds the types used to configure them.
+
+mod amp;
+mod boilerplate;
+mod clutter;
+mod csp;
+mod css;
+mod email;
+mod feed;
+mod headings;
+mod highlight;
+mod i18n;
+mod include;
+mod integrity;
+mod lazy;
+mod links;
+mod numbering;
+mod redact;
+mod sanitize;
+mod scramble;
+mod slugs;
+mod snapshot;
+mod template;
+mod toc;
+mod tracking;
+mod typography;
+mod whitespace;
+
+pub use self::boilerplate::{Block, BlockKind};
+pub use self::clutter::{Clutter, ClutterKind};
+pub use self::csp::{CspExtraction, ExtractedCode, InlineKind};
+pub use self::email::EmailOptions;
+pub use self::highlight::HighlightOptions;
+pub use self::i18n::{Message, TranslationReport};
+pub use self::include::{IncludeError, IncludeOptions};
+pub use self::integrity::IntegrityAlgorithm;
+pub use self::lazy::LazyLoadOptions;
+pub use self::links::LinkPolicy;
+pub use self::numbering::NumberingOptions;
+pub use self::redact::{Redaction, RedactionStyle};
+pub use self::sanitize::SanitizeOptions;
+pub use self::scramble::ScrambleOptions;
+pub use self::slugs::{slugify, SlugOptions};
+pub use self::snapshot::Resource;
+pub use self::toc::TocOptions;
+pub use self::tracking::{Redirector, UrlPolicy};
+pub use self::typography::TypographyOptions;
+pub use self::whitespace::WhitespaceOptions;
@@ -0,0 +1,155 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::extract::{outline, OutlineEntry};
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle};
+
+/// Options for [`GenerationalArenaDom::number_sections`].
+#[derive(Clone, Debug)]
+pub struct NumberingOptions {
+    /// Headings with an outline level below this aren't numbered; the headings under them are
+    /// numbered as if they were top-level. Set it to 2 to leave a page title `<h1>` alone.
+    /// Defaults to 1.
+    pub start_level: usize,
+
+    /// Headings with an outline level above this, and the headings under them, aren't
+    /// numbered. Defaults to no limit.
+    pub max_level: usize,
+
+    /// Whether to insert each number, followed by a space, at the start of its heading's text.
+    pub insert_text: bool,
+
+    /// An attribute to set on each numbered heading, such as `data-section`, holding its
+    /// number.
+    pub attribute: Option<String>,
+}
+
+impl Default for NumberingOptions {
+    fn default() -> NumberingOptions {
+        NumberingOptions {
+            start_level: 1,
+            max_level: usize::MAX,
+            insert_text: false,
+            attribute: None,
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Number the document's headings hierarchically (`1.`, `1.1.`, `1.2.`, `2.`, …) following
+    /// its [outline](crate::extract::outline), returning each numbered heading with its number.
+    ///
+    /// The numbers are only written into the document if `opts` asks for it, as text at the
+    /// start of the heading or as an attribute. Numbering an already numbered document inserts
+    /// the numbers again. Frozen headings are numbered but left unchanged.
+    pub fn number_sections(&mut self, opts: &NumberingOptions) -> Vec<(Handle, String)> {
+        let span = TraceSpan::transform("number_sections");
+        let mut numbers = Vec::new();
+        number_entries(&outline(self), &[], &mut 0, opts, &mut numbers);
+        let writes = opts.attribute.is_some() || opts.insert_text;
+        let mut changes = 0;
+        for (heading, number) in &numbers {
+            if !writes || self.skip_frozen(*heading) {
+                continue;
+            }
+            changes += 1;
+            if let Some(attr) = &opts.attribute {
+                self.set_attr(*heading, attr, StrTendril::from(number.as_str()));
+            }
+            if opts.insert_text {
+                let text = self.create_text(&format!("{} ", number));
+                heading.prepend(text, &mut self.arena);
+            }
+        }
+        self.mark_mutated();
+        span.changed(self.arena.count(), changes);
+        numbers
+    }
+}
+
+fn number_entries(
+    entries: &[OutlineEntry],
+    prefix: &[usize],
+    counter: &mut usize,
+    opts: &NumberingOptions,
+    numbers: &mut Vec<(Handle, String)>,
+) {
+    for entry in entries {
+        if entry.level < opts.start_level {
+            number_entries(&entry.children, prefix, counter, opts, numbers);
+            continue;
+        }
+        if entry.level > opts.max_level {
+            continue;
+        }
+        *counter += 1;
+        let mut path = prefix.to_vec();
+        path.push(*counter);
+        let number: String = path.iter().map(|part| format!("{}.", part)).collect();
+        numbers.push((entry.handle, number));
+        number_entries(&entry.children, &path, &mut 0, opts, numbers);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NumberingOptions;
+    use crate::parse::{body_html, parse_html};
+
+    const HTML: &str = "<h1>Title</h1><h2>A</h2><h3>A1</h3><h3>A2</h3><h2>B</h2><h4>B deep</h4>";
+
+    fn numbers(opts: &NumberingOptions) -> Vec<(String, String)> {
+        let mut dom = parse_html(HTML);
+        dom.number_sections(opts)
+            .into_iter()
+            .map(|(heading, number)| (dom.text_content(heading), number))
+            .collect()
+    }
+
+    #[test]
+    fn numbers_headings_by_outline() {
+        let pairs = |list: &[(&str, &str)]| -> Vec<(String, String)> {
+            list.iter()
+                .map(|(text, number)| (text.to_string(), number.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            numbers(&NumberingOptions::default()),
+            pairs(&[
+                ("Title", "1."),
+                ("A", "1.1."),
+                ("A1", "1.1.1."),
+                ("A2", "1.1.2."),
+                ("B", "1.2."),
+                ("B deep", "1.2.1."),
+            ])
+        );
+        assert_eq!(
+            numbers(&NumberingOptions {
+                start_level: 2,
+                max_level: 2,
+                ..NumberingOptions::default()
+            }),
+            pairs(&[("A", "1."), ("B", "2.")])
+        );
+    }
+
+    #[test]
+    fn writes_numbers_into_the_document() {
+        let mut dom = parse_html(HTML);
+        let b = dom.select(dom.document, &"h2".parse().unwrap())[1];
+        dom.freeze(b);
+        dom.number_sections(&NumberingOptions {
+            start_level: 2,
+            insert_text: true,
+            attribute: Some("data-section".to_string()),
+            ..NumberingOptions::default()
+        });
+        assert_eq!(
+            body_html(&dom),
+            "<h1>Title</h1><h2 data-section=\"1.\">1. A</h2>\
+             <h3 data-section=\"1.1.\">1.1. A1</h3><h3 data-section=\"1.2.\">1.2. A2</h3>\
+             <h2>B</h2><h4 data-section=\"2.1.\">2.1. B deep</h4>"
+        );
+    }
+}
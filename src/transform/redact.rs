@@ -0,0 +1,189 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, InsertPosition, NodeData, Selector, TextRange};
+
+/// What [`GenerationalArenaDom::redact`] and
+/// [`redact_ranges`](GenerationalArenaDom::redact_ranges) put in place of redacted content.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RedactionStyle {
+    /// Replace it with this text, whatever its length, so the length isn't revealed either.
+    Placeholder(String),
+    /// Remove it, along with matched elements themselves.
+    Remove,
+}
+
+impl Default for RedactionStyle {
+    fn default() -> RedactionStyle {
+        RedactionStyle::Placeholder("[REDACTED]".to_string())
+    }
+}
+
+/// A piece of content removed by [`GenerationalArenaDom::redact`] or
+/// [`redact_ranges`](GenerationalArenaDom::redact_ranges).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Redaction {
+    /// The element whose content was redacted, or the text node holding the placeholder for a
+    /// range. Once content is removed, with [`RedactionStyle::Remove`] or an empty placeholder
+    /// for a range, this handle is stale.
+    pub handle: Handle,
+
+    /// How many characters of text were redacted.
+    pub length: usize,
+}
+
+impl GenerationalArenaDom {
+    /// Redact the content of the elements matching `selector`, returning what was redacted in
+    /// document order.
+    ///
+    /// With [`RedactionStyle::Placeholder`], the children of each element are replaced by the
+    /// placeholder text, and the element and its own attributes are kept. With
+    /// [`RedactionStyle::Remove`], elements are removed altogether. Elements inside others that
+    /// match are redacted along with them, and not recorded separately. Elements with anything
+    /// frozen under them aren't redacted.
+    pub fn redact(&mut self, selector: &Selector, style: &RedactionStyle) -> Vec<Redaction> {
+        let span = TraceSpan::transform("redact");
+        let elements = self.select(self.document, selector);
+        let mut redactions = Vec::with_capacity(elements.len());
+        for element in elements {
+            if self.arena.get(element).is_none() {
+                // Inside an element redacted earlier.
+                continue;
+            }
+            if self.skip_frozen_removal(element) {
+                continue;
+            }
+            let length = self.text_content(element).chars().count();
+            match style {
+                RedactionStyle::Placeholder(placeholder) => {
+                    let children: Vec<Handle> = element.children(&self.arena).collect();
+                    for child in children {
+                        self.free_subtree(child);
+                    }
+                    if !placeholder.is_empty() {
+                        let text = self.create_text(placeholder);
+                        self.insert_at(&[text], InsertPosition::AppendTo(element));
+                    }
+                }
+                RedactionStyle::Remove => self.free_subtree(element),
+            }
+            redactions.push(Redaction {
+                handle: element,
+                length,
+            });
+        }
+        if !redactions.is_empty() {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), redactions.len());
+        redactions
+    }
+
+    /// Redact the text in `ranges`, returning what was redacted in document order.
+    ///
+    /// Ranges that overlap or touch are merged first. With [`RedactionStyle::Placeholder`],
+    /// each range is replaced by the placeholder text, in the text node where it starts; the
+    /// elements it spans are kept, emptied of the redacted text. With
+    /// [`RedactionStyle::Remove`], the text is removed. Ranges with frozen text in them aren't
+    /// redacted.
+    pub fn redact_ranges(
+        &mut self,
+        ranges: &[TextRange],
+        style: &RedactionStyle,
+    ) -> Vec<Redaction> {
+        let span = TraceSpan::transform("redact_ranges");
+        let merged = self.merge_ranges(ranges);
+        let mut redactions = Vec::with_capacity(merged.len());
+        // From last to first, since isolating a range keeps those before it valid.
+        for range in merged.iter().rev() {
+            if self.skip_frozen_range(range) {
+                continue;
+            }
+            let texts = self.isolate_range(range);
+            let first = match texts.first() {
+                Some(&first) => first,
+                None => continue,
+            };
+            let mut length = 0;
+            for &text in &texts {
+                if let NodeData::Text { contents } = self.get_node(&text) {
+                    length += contents.borrow().chars().count();
+                }
+            }
+            let placeholder = match style {
+                RedactionStyle::Placeholder(placeholder) if !placeholder.is_empty() => {
+                    Some(placeholder)
+                }
+                _ => None,
+            };
+            for &text in &texts[placeholder.is_some() as usize..] {
+                self.free_subtree(text);
+            }
+            if let Some(placeholder) = placeholder {
+                if let NodeData::Text { contents } = self.get_node(&first) {
+                    *contents.borrow_mut() = StrTendril::from(&**placeholder);
+                }
+            }
+            redactions.push(Redaction {
+                handle: first,
+                length,
+            });
+        }
+        if !redactions.is_empty() {
+            self.mark_mutated();
+        }
+        redactions.reverse();
+        span.changed(self.arena.count(), redactions.len());
+        redactions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RedactionStyle;
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn redacts_elements() {
+        let mut dom = parse_html(
+            "<p class=pii>Jane <span class=pii>Doe</span></p><p>Hi</p><p class=pii>é</p>",
+        );
+        let redactions = dom.redact(&".pii".parse().unwrap(), &RedactionStyle::default());
+        let lengths: Vec<usize> = redactions
+            .iter()
+            .map(|redaction| redaction.length)
+            .collect();
+        assert_eq!(lengths, [8, 1]);
+        assert_eq!(
+            body_html(&dom),
+            "<p class=\"pii\">[REDACTED]</p><p>Hi</p><p class=\"pii\">[REDACTED]</p>"
+        );
+
+        let mut dom = parse_html("<p class=pii>Jane</p><p>Hi</p>");
+        let redactions = dom.redact(&".pii".parse().unwrap(), &RedactionStyle::Remove);
+        assert_eq!(redactions.len(), 1);
+        assert!(dom.arena.get(redactions[0].handle).is_none());
+        assert_eq!(body_html(&dom), "<p>Hi</p>");
+    }
+
+    #[test]
+    fn redacts_ranges() {
+        let mut dom = parse_html("<p>Call 555 <b>0199</b> now, 555 0100</p>");
+        let root = dom.document;
+        let mut ranges = dom.find_text(root, "555 0199");
+        ranges.extend(dom.find_text(root, "555 0100"));
+        let style = RedactionStyle::Placeholder("###".to_string());
+        let redactions = dom.redact_ranges(&ranges, &style);
+        let lengths: Vec<usize> = redactions
+            .iter()
+            .map(|redaction| redaction.length)
+            .collect();
+        assert_eq!(lengths, [8, 8]);
+        assert_eq!(body_html(&dom), "<p>Call ###<b></b> now, ###</p>");
+
+        let mut dom = parse_html("<p>Call 555 <b>0199</b> now</p>");
+        let ranges = dom.find_text(dom.document, "555 0199");
+        dom.redact_ranges(&ranges, &RedactionStyle::Remove);
+        assert_eq!(body_html(&dom), "<p>Call <b></b> now</p>");
+    }
+}
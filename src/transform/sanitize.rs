@@ -0,0 +1,333 @@
+use markup5ever::{namespace_url, ns};
+
+use crate::trace::TraceSpan;
+use crate::url::UrlParts;
+use crate::{srcset_candidates, GenerationalArenaDom, Handle, NodeData};
+
+/// Attributes whose value is a URL, which must use an allowed scheme.
+const URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "data",
+    "formaction",
+    "href",
+    "longdesc",
+    "poster",
+    "src",
+];
+
+/// Elements removed by default: those that run code or load other documents, and SVG animation
+/// elements, which can set attributes such as `href` to `javascript:` URLs.
+pub(crate) const REMOVED_ELEMENTS: &[&str] = &[
+    "script",
+    "noscript",
+    "iframe",
+    "frame",
+    "frameset",
+    "object",
+    "embed",
+    "applet",
+    "base",
+    "meta",
+    "link",
+    "template",
+    "animate",
+    "animateMotion",
+    "animateTransform",
+    "set",
+];
+
+/// Attributes of SVG animation elements holding values for the animated attribute.
+const ANIMATION_VALUES: &[&str] = &["by", "from", "to", "values"];
+
+/// Options for [`GenerationalArenaDom::sanitize`].
+#[derive(Clone, Debug)]
+pub struct SanitizeOptions {
+    /// Elements removed along with their contents, in any namespace, so that `script` also
+    /// removes SVG scripts. Defaults to elements that run code or load other documents:
+    /// `script`, `noscript`, `iframe`, `frame`, `frameset`, `object`, `embed`, `applet`, `base`,
+    /// `meta`, `link` and `template`, and the SVG animation elements `animate`,
+    /// `animateMotion`, `animateTransform` and `set`, which can set any attribute. Leave those
+    /// out to allow animations; they are then kept from animating URL attributes to a
+    /// disallowed scheme.
+    pub remove: Vec<String>,
+
+    /// If set, the only HTML elements that are kept; others are replaced by their contents.
+    /// Elements in `remove` are removed either way. Defaults to `None`, keeping everything.
+    pub allowed_elements: Option<Vec<String>>,
+
+    /// If set, the only attributes that are kept. Defaults to `None`, keeping everything but
+    /// event handlers.
+    pub allowed_attributes: Option<Vec<String>>,
+
+    /// The URL schemes allowed in attributes such as `href` and `src`, compared ignoring ASCII
+    /// case. Relative URLs are always allowed; attributes with other URLs are removed. Defaults
+    /// to `http`, `https`, `mailto` and `tel`.
+    pub allowed_schemes: Vec<String>,
+
+    /// Whether to allow `data:image/…` URLs, other than SVG, in `<img src>` and `srcset`.
+    /// Defaults to `true`.
+    pub allow_data_images: bool,
+
+    /// Whether to remove comments. Defaults to `true`.
+    pub remove_comments: bool,
+}
+
+impl Default for SanitizeOptions {
+    fn default() -> SanitizeOptions {
+        SanitizeOptions {
+            remove: strings(REMOVED_ELEMENTS),
+            allowed_elements: None,
+            allowed_attributes: None,
+            allowed_schemes: strings(&["http", "https", "mailto", "tel"]),
+            allow_data_images: true,
+            remove_comments: true,
+        }
+    }
+}
+
+pub(crate) fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+impl GenerationalArenaDom {
+    /// Make the content under `root` safe to display, as configured by `opts`, returning how
+    /// many nodes and attributes were removed or unwrapped.
+    ///
+    /// Whatever the options, event handler attributes (`onclick` and the like) are removed, as
+    /// are inline styles that can run script (`expression(…)` or `javascript:` URLs). Elements
+    /// outside the HTML namespace, such as SVG, are kept only if `allowed_elements` is `None`,
+    /// but are removed by name and have their attributes checked the same way.
+    ///
+    /// Frozen nodes are trusted and left as they are, so freeze only content that is already
+    /// known to be safe.
+    pub fn sanitize(&mut self, root: Handle, opts: &SanitizeOptions) -> usize {
+        let span = TraceSpan::transform("sanitize");
+        let mut changed = 0;
+        let nodes: Vec<Handle> = root.descendants(&self.arena).collect();
+        for node in nodes {
+            if node == root {
+                changed += self.sanitize_attrs(node, opts);
+                continue;
+            }
+            if self.arena.get(node).is_none() {
+                // Freed along with an ancestor.
+                continue;
+            }
+            let action = match self.get_node(&node) {
+                NodeData::Comment { .. } if opts.remove_comments => Action::Remove,
+                NodeData::ProcessingInstruction { .. } => Action::Remove,
+                NodeData::Element { name, .. } => {
+                    let local = &*name.local;
+                    let is_html = name.ns == ns!(html);
+                    if opts
+                        .remove
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(local))
+                    {
+                        Action::Remove
+                    } else if !opts.allowed_elements.as_ref().is_none_or(|allowed| {
+                        is_html && allowed.iter().any(|name| name.eq_ignore_ascii_case(local))
+                    }) {
+                        Action::Unwrap
+                    } else {
+                        Action::Keep
+                    }
+                }
+                _ => Action::Keep,
+            };
+            match action {
+                Action::Remove => {
+                    if !self.skip_frozen_removal(node) {
+                        self.free_subtree(node);
+                        changed += 1;
+                    }
+                }
+                Action::Unwrap => {
+                    if !self.skip_frozen(node) {
+                        self.unwrap_node(node);
+                        changed += 1;
+                    }
+                }
+                Action::Keep => changed += self.sanitize_attrs(node, opts),
+            }
+        }
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), changed)
+    }
+
+    /// Remove the attributes of `node` that `opts` doesn't allow, returning how many.
+    fn sanitize_attrs(&mut self, node: Handle, opts: &SanitizeOptions) -> usize {
+        let is_img = self.is_html_element(node, "img");
+        let attrs = match self.get_node(&node) {
+            NodeData::Element { attrs, .. } => attrs,
+            _ => return 0,
+        };
+        let mut kept = attrs.borrow().clone();
+        let before = kept.len();
+        // An SVG animation of a URL attribute, whose values are URLs too.
+        let animates_url = kept.iter().any(|attr| {
+            attr.name
+                .local
+                .as_ref()
+                .eq_ignore_ascii_case("attributeName")
+                && {
+                    let target = attr.value.trim().to_ascii_lowercase();
+                    let target = target.strip_prefix("xlink:").unwrap_or(&target);
+                    URL_ATTRIBUTES.contains(&target)
+                }
+        });
+        kept.retain(|attr| {
+            let name = attr.name.local.as_ref().to_ascii_lowercase();
+            let value = &*attr.value;
+            let allowed = opts
+                .allowed_attributes
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(&name));
+            if !allowed || name.starts_with("on") {
+                return false;
+            }
+            if name == "style" {
+                let lower = value.to_ascii_lowercase();
+                return !lower.contains("expression(") && !lower.contains("javascript:");
+            }
+            let data_image_allowed = is_img && opts.allow_data_images;
+            if URL_ATTRIBUTES.contains(&name.as_str()) {
+                return url_allowed(value, opts, data_image_allowed && name == "src");
+            }
+            if animates_url && ANIMATION_VALUES.contains(&name.as_str()) {
+                return value
+                    .split(';')
+                    .all(|url| url_allowed(url.trim(), opts, false));
+            }
+            if name == "srcset" {
+                return srcset_candidates(value)
+                    .all(|candidate| url_allowed(candidate.url, opts, data_image_allowed));
+            }
+            true
+        });
+        let removed = before - kept.len();
+        if removed == 0 || self.skip_frozen(node) {
+            return 0;
+        }
+        *attrs.borrow_mut() = kept;
+        removed
+    }
+}
+
+enum Action {
+    Keep,
+    Remove,
+    Unwrap,
+}
+
+/// Whether `url` is relative or uses an allowed scheme.
+fn url_allowed(url: &str, opts: &SanitizeOptions, allow_data_image: bool) -> bool {
+    // Browsers ignore control characters and whitespace within schemes, as in `java\tscript:`.
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_control() && !c.is_ascii_whitespace())
+        .collect();
+    let scheme = match UrlParts::parse(&url).scheme {
+        Some(scheme) => scheme.to_ascii_lowercase(),
+        None => return true,
+    };
+    if scheme == "data" && allow_data_image {
+        let media_type = url[5..].to_ascii_lowercase();
+        return media_type.starts_with("image/") && !media_type.starts_with("image/svg");
+    }
+    opts.allowed_schemes
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(&scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strings, SanitizeOptions, REMOVED_ELEMENTS};
+    use crate::parse::{body_html, parse_html};
+    use crate::transform::EmailOptions;
+
+    fn sanitize(html: &str, opts: &SanitizeOptions) -> (usize, String) {
+        let mut dom = parse_html(html);
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let changed = dom.sanitize(body, opts);
+        (changed, body_html(&dom))
+    }
+
+    #[test]
+    fn removes_scripts_and_unsafe_attributes() {
+        let (changed, html) = sanitize(
+            "<p onclick=x() style='width: expression(x)'>a<script>x()</script></p>\
+             <a href=' java\tscript:x()'>b</a><a href=/c title=c>c</a>\
+             <img src='data:image/png;base64,AA' srcset='data:image/svg+xml,x 2x'><!-- d -->",
+            &SanitizeOptions::default(),
+        );
+        assert_eq!(changed, 6);
+        assert_eq!(
+            html,
+            "<p>a</p><a>b</a><a href=\"/c\" title=\"c\">c</a>\
+             <img src=\"data:image/png;base64,AA\">"
+        );
+    }
+
+    #[test]
+    fn removes_scripts_in_foreign_content() {
+        let (changed, html) = sanitize(
+            "<svg><script>alert(1)</script><circle r=1></circle></svg>\
+             <math><mi xlink:href=javascript:alert(1)>x</mi></math>",
+            &SanitizeOptions::default(),
+        );
+        assert_eq!(changed, 2);
+        assert_eq!(
+            html,
+            "<svg><circle r=\"1\"></circle></svg><math><mi>x</mi></math>"
+        );
+
+        let mut dom = parse_html("<svg><script>alert(1)</script></svg>");
+        assert_eq!(dom.harden_email(&EmailOptions::default(), |_| None), 1);
+        assert_eq!(body_html(&dom), "<svg></svg>");
+    }
+
+    #[test]
+    fn removes_svg_animations() {
+        let html = "<svg><a><animate attributeName=href values='https://a;javascript:alert(1)'/>\
+                    <set attributeName=xlink:href to=javascript:alert(1) />\
+                    <animateMotion path='M0 0'/><animateTransform type=scale />\
+                    <set attributeName=fill to=red /><text>x</text></a></svg>";
+        let (_, sanitized) = sanitize(html, &SanitizeOptions::default());
+        assert_eq!(sanitized, "<svg><a><text>x</text></a></svg>");
+
+        // Animations that are allowed can't animate URLs to disallowed schemes.
+        let opts = SanitizeOptions {
+            remove: strings(&REMOVED_ELEMENTS[..REMOVED_ELEMENTS.len() - 4]),
+            ..SanitizeOptions::default()
+        };
+        let (changed, sanitized) = sanitize(html, &opts);
+        assert_eq!(changed, 2);
+        assert_eq!(
+            sanitized,
+            "<svg><a><animate attributeName=\"href\"></animate>\
+             <set attributeName=\"xlink:href\"></set>\
+             <animateMotion path=\"M0 0\"></animateMotion>\
+             <animateTransform type=\"scale\"></animateTransform>\
+             <set attributeName=\"fill\" to=\"red\"></set><text>x</text></a></svg>"
+        );
+    }
+
+    #[test]
+    fn allow_lists() {
+        let opts = SanitizeOptions {
+            allowed_elements: Some(strings(&["p", "a"])),
+            allowed_attributes: Some(strings(&["href"])),
+            ..SanitizeOptions::default()
+        };
+        let (_, html) = sanitize(
+            "<p class=x><b>a</b> <a href=https://a id=a>b</a><svg><g>c</g></svg></p>",
+            &opts,
+        );
+        assert_eq!(html, "<p>a <a href=\"https://a\">b</a>c</p>");
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Options for [`GenerationalArenaDom::scramble`].
+#[derive(Clone, Debug)]
+pub struct ScrambleOptions {
+    /// The seed the replacement words are generated from. The same document scrambled with the
+    /// same seed always comes out the same. Defaults to 0.
+    pub seed: u64,
+
+    /// Attributes whose values are kept, because they affect how the document is processed
+    /// more than they say about its content. Defaults to `charset`, `dir`, `http-equiv`,
+    /// `method`, `rel`, `role` and `type`.
+    pub kept_attributes: Vec<String>,
+}
+
+impl Default for ScrambleOptions {
+    fn default() -> ScrambleOptions {
+        ScrambleOptions {
+            seed: 0,
+            kept_attributes: [
+                "charset",
+                "dir",
+                "http-equiv",
+                "method",
+                "rel",
+                "role",
+                "type",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Replace the words in the text, comments and attribute values of the document with
+    /// pseudonyms, so that it can be shared, say in a bug report, without revealing its
+    /// content. Returns how many text nodes, comments and attribute values were changed.
+    ///
+    /// The structure of the document, element and attribute names, and the values of
+    /// [`ScrambleOptions::kept_attributes`] are left as they are. Each letter is replaced by a
+    /// random ASCII letter of the same case and each digit by a random digit, so words keep
+    /// their lengths in characters, and whitespace and punctuation are kept. The same word is
+    /// always replaced by the same pseudonym, so an `id` and the fragments of links to it, or a
+    /// `class` and the selectors in stylesheets, still correspond. Scripts and stylesheets are
+    /// scrambled like any other text, so they're unlikely to still work. Frozen nodes aren't
+    /// scrambled, so freezing content keeps it in the clear.
+    pub fn scramble(&mut self, opts: &ScrambleOptions) -> usize {
+        let span = TraceSpan::transform("scramble");
+        let mut scrambler = Scrambler {
+            rng: SplitMix64(opts.seed),
+            pseudonyms: HashMap::new(),
+        };
+        let mut nodes = Vec::new();
+        let mut roots = vec![self.document];
+        while let Some(root) = roots.pop() {
+            for node in root.descendants(&self.arena) {
+                if let NodeData::Element {
+                    template_contents, ..
+                } = self.get_node(&node)
+                {
+                    roots.extend(*template_contents.borrow());
+                }
+                nodes.push(node);
+            }
+        }
+
+        let mut changed = 0;
+        for node in nodes {
+            if self.is_frozen(node) {
+                if self.would_scramble(node, opts) {
+                    self.skip_frozen(node);
+                }
+                continue;
+            }
+            changed += self.scramble_node(node, opts, &mut scrambler);
+        }
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), changed)
+    }
+
+    /// Whether `node` has text, a comment or attribute values with words to scramble.
+    fn would_scramble(&self, node: Handle, opts: &ScrambleOptions) -> bool {
+        let has_words = |text: &str| text.chars().any(char::is_alphanumeric);
+        match self.get_node(&node) {
+            NodeData::Text { contents } => has_words(&contents.borrow()),
+            NodeData::Comment { contents } => has_words(contents),
+            NodeData::Element { attrs, .. } => attrs.borrow().iter().any(|attr| {
+                !opts
+                    .kept_attributes
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&attr.name.local))
+                    && has_words(&attr.value)
+            }),
+            _ => false,
+        }
+    }
+
+    fn scramble_node(
+        &mut self,
+        node: Handle,
+        opts: &ScrambleOptions,
+        scrambler: &mut Scrambler,
+    ) -> usize {
+        let mut changed = 0;
+        match self.arena[node].get_mut() {
+            NodeData::Text { contents } => {
+                let contents = contents.get_mut();
+                if let Some(scrambled) = scrambler.scramble(contents) {
+                    *contents = scrambled;
+                    changed += 1;
+                }
+            }
+            NodeData::Comment { contents } => {
+                if let Some(scrambled) = scrambler.scramble(contents) {
+                    *contents = scrambled;
+                    changed += 1;
+                }
+            }
+            NodeData::Element { attrs, .. } => {
+                for attr in attrs.get_mut().iter_mut() {
+                    let kept = opts
+                        .kept_attributes
+                        .iter()
+                        .any(|name| name.eq_ignore_ascii_case(&attr.name.local));
+                    if kept {
+                        continue;
+                    }
+                    if let Some(scrambled) = scrambler.scramble(&attr.value) {
+                        attr.value = scrambled;
+                        changed += 1;
+                    }
+                }
+            }
+            _ => {}
+        }
+        changed
+    }
+}
+
+/// Replaces words with consistent pseudonyms.
+struct Scrambler {
+    rng: SplitMix64,
+    pseudonyms: HashMap<String, String>,
+}
+
+impl Scrambler {
+    /// `text` with each word replaced by its pseudonym, or `None` if it has no words.
+    fn scramble(&mut self, text: &str) -> Option<StrTendril> {
+        if !text.chars().any(char::is_alphanumeric) {
+            return None;
+        }
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while !rest.is_empty() {
+            let end = rest
+                .find(|c: char| c.is_alphanumeric() != rest.starts_with(char::is_alphanumeric))
+                .unwrap_or(rest.len());
+            let (run, after) = rest.split_at(end);
+            if run.starts_with(char::is_alphanumeric) {
+                output.push_str(self.pseudonym(run));
+            } else {
+                output.push_str(run);
+            }
+            rest = after;
+        }
+        Some(StrTendril::from(output))
+    }
+
+    fn pseudonym(&mut self, word: &str) -> &str {
+        let Scrambler { rng, pseudonyms } = self;
+        pseudonyms.entry(word.to_string()).or_insert_with(|| {
+            word.chars()
+                .map(|c| {
+                    if c.is_numeric() {
+                        (b'0' + rng.below(10)) as char
+                    } else if c.is_uppercase() {
+                        (b'A' + rng.below(26)) as char
+                    } else {
+                        (b'a' + rng.below(26)) as char
+                    }
+                })
+                .collect()
+        })
+    }
+}
+
+/// The SplitMix64 generator: not cryptographic, but fast, and the same on every platform.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number below `bound`, which is small enough that the bias doesn't matter.
+    fn below(&mut self, bound: u8) -> u8 {
+        (self.next() % u64::from(bound)) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ScrambleOptions, SplitMix64};
+    use crate::parse::{body_html, parse_html};
+
+    const HTML: &str = "<p id=Intro class='lead x1' dir=rtl>Hello, World 42!</p>\
+                        <!-- note --><a href=#Intro title=hello>hello</a>";
+
+    fn scrambled(seed: u64) -> String {
+        let mut dom = parse_html(HTML);
+        let opts = ScrambleOptions {
+            seed,
+            ..ScrambleOptions::default()
+        };
+        assert_eq!(dom.scramble(&opts), 7);
+        dom.inner_html(dom.document)
+    }
+
+    #[test]
+    fn generates_splitmix64() {
+        let mut rng = SplitMix64(0);
+        assert_eq!(rng.next(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next(), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn scrambles_deterministically() {
+        assert_eq!(scrambled(7), scrambled(7));
+        assert_ne!(scrambled(7), scrambled(8));
+    }
+
+    #[test]
+    fn keeps_structure_and_word_shapes() {
+        let mut dom = parse_html(HTML);
+        dom.scramble(&ScrambleOptions::default());
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let a = dom.query_selector(dom.document, "a").unwrap().unwrap();
+        let id = dom.get_attr(p, "id").unwrap();
+        assert_ne!(&*id, "Intro");
+        assert!(id.starts_with(|c: char| c.is_ascii_uppercase()) && id.len() == 5);
+        assert_eq!(&*dom.get_attr(a, "href").unwrap(), format!("#{}", id));
+        assert_eq!(dom.get_attr(p, "dir").as_deref(), Some("rtl"));
+
+        let shape = |text: &str| -> String {
+            text.chars()
+                .map(|c| match c {
+                    'a'..='z' => 'a',
+                    'A'..='Z' => 'A',
+                    '0'..='9' => '0',
+                    c => c,
+                })
+                .collect()
+        };
+        assert_eq!(shape(&dom.get_attr(p, "class").unwrap()), "aaaa a0");
+        assert_eq!(shape(&dom.text_content(p)), "Aaaaa, Aaaaa 00!");
+        assert_eq!(&*dom.get_attr(a, "title").unwrap(), dom.text_content(a));
+    }
+
+    #[test]
+    fn leaves_frozen_nodes_alone() {
+        let mut dom = parse_html("<p>secret</p><p>public</p>");
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        dom.freeze(p);
+        dom.scramble(&ScrambleOptions::default());
+        assert!(body_html(&dom).starts_with("<p>secret</p><p>"));
+        assert!(!body_html(&dom).contains("public"));
+    }
+}
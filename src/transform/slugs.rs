@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle};
+
+/// Options for [`GenerationalArenaDom::add_heading_ids`].
+#[derive(Clone, Debug)]
+pub struct SlugOptions {
+    /// Whether to append a link to the heading itself, like
+    /// `<a class="anchor" href="#intro" aria-hidden="true">#</a>`, to each heading.
+    pub self_links: bool,
+
+    /// The class of the inserted links. Defaults to `anchor`.
+    pub link_class: String,
+
+    /// The text of the inserted links. Defaults to `#`.
+    pub link_text: String,
+}
+
+impl Default for SlugOptions {
+    fn default() -> SlugOptions {
+        SlugOptions {
+            self_links: false,
+            link_class: "anchor".to_string(),
+            link_text: "#".to_string(),
+        }
+    }
+}
+
+/// Turn `text` into a slug for use as an id or URL fragment: lowercased letters and digits,
+/// with runs of anything else turned into single hyphens, e.g. `"What's new?"` becomes
+/// `whats-new`. Apostrophes are dropped rather than separating words. Returns `section` if
+/// nothing is left.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let mut pending_hyphen = false;
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            if pending_hyphen && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_hyphen = false;
+            slug.extend(c.to_lowercase());
+        } else if !matches!(c, '\'' | '\u{2019}') {
+            pending_hyphen = true;
+        }
+    }
+    if slug.is_empty() {
+        slug.push_str("section");
+    }
+    slug
+}
+
+impl GenerationalArenaDom {
+    /// Give every `h1`–`h6` heading in the document an `id`, returning each heading with its
+    /// id in document order.
+    ///
+    /// Headings that already have an id keep it. Others get a [slug](slugify) of their text,
+    /// with `-1`, `-2`, … appended if another element already uses it. Frozen headings that
+    /// would be changed are left out.
+    pub fn add_heading_ids(&mut self, opts: &SlugOptions) -> Vec<(Handle, String)> {
+        let span = TraceSpan::transform("add_heading_ids");
+        let headings: Vec<Handle> = self
+            .document
+            .descendants(&self.arena)
+            .filter(|&node| self.heading_rank(node).is_some())
+            .collect();
+        let mut used: HashSet<String> = self
+            .document
+            .descendants(&self.arena)
+            .filter_map(|node| self.get_attr(node, "id"))
+            .map(|id| id.to_string())
+            .collect();
+
+        let mut ids = Vec::with_capacity(headings.len());
+        let mut changes = 0;
+        for heading in headings {
+            let has_id = self
+                .get_attr(heading, "id")
+                .is_some_and(|id| !id.is_empty());
+            if (!has_id || opts.self_links) && self.skip_frozen(heading) {
+                continue;
+            }
+            if !has_id || opts.self_links {
+                changes += 1;
+            }
+            let id = match self.get_attr(heading, "id") {
+                Some(id) if !id.is_empty() => id.to_string(),
+                _ => {
+                    let slug = slugify(&self.text_content(heading));
+                    let mut id = slug.clone();
+                    let mut suffix = 0;
+                    while used.contains(&id) {
+                        suffix += 1;
+                        id = format!("{}-{}", slug, suffix);
+                    }
+                    used.insert(id.clone());
+                    self.set_attr(heading, "id", StrTendril::from(id.as_str()));
+                    id
+                }
+            };
+            if opts.self_links {
+                let attr = |name: &str, value: &str| Attribute {
+                    name: QualName::new(None, ns!(), LocalName::from(name)),
+                    value: StrTendril::from(value),
+                };
+                let link = self.create_html_element(
+                    "a",
+                    vec![
+                        attr("class", &opts.link_class),
+                        attr("href", &format!("#{}", id)),
+                        attr("aria-hidden", "true"),
+                    ],
+                );
+                let text = self.create_text(&opts.link_text);
+                link.append(text, &mut self.arena);
+                heading.append(link, &mut self.arena);
+            }
+            ids.push((heading, id));
+        }
+        self.mark_mutated();
+        span.changed(self.arena.count(), changes);
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{slugify, SlugOptions};
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn slugifies_text() {
+        assert_eq!(slugify("What's new?"), "whats-new");
+        assert_eq!(slugify("  Über  Straße_2 "), "über-straße_2");
+        assert_eq!(slugify("It\u{2019}s a -- test!"), "its-a-test");
+        assert_eq!(slugify("?!"), "section");
+    }
+
+    #[test]
+    fn adds_unique_heading_ids() {
+        let mut dom = parse_html(
+            "<h1>Intro</h1><p id=intro>taken</p><h2 id=kept>Kept</h2><h2>Intro</h2><h3 id=''>?</h3>",
+        );
+        let ids: Vec<String> = dom
+            .add_heading_ids(&SlugOptions::default())
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect();
+        assert_eq!(ids, ["intro-1", "kept", "intro-2", "section"]);
+    }
+
+    #[test]
+    fn adds_self_links() {
+        let mut dom = parse_html("<h2>Setup</h2><h2 id=x>Done</h2>");
+        let done = dom.query_selector(dom.document, "#x").unwrap().unwrap();
+        dom.freeze(done);
+        let ids = dom.add_heading_ids(&SlugOptions {
+            self_links: true,
+            link_text: "¶".to_string(),
+            ..SlugOptions::default()
+        });
+        assert_eq!(ids.len(), 1);
+        assert_eq!(
+            body_html(&dom),
+            "<h2 id=\"setup\">Setup<a class=\"anchor\" href=\"#setup\" aria-hidden=\"true\">¶</a></h2>\
+             <h2 id=\"x\">Done</h2>"
+        );
+    }
+}
@@ -0,0 +1,420 @@
+use std::collections::HashMap;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns};
+
+use crate::trace::TraceSpan;
+use crate::transform::css::rewrite_css_urls;
+use crate::url::{self, UrlParts};
+use crate::{has_token, srcset_candidates, GenerationalArenaDom, Handle};
+
+/// How deeply stylesheets imported by stylesheets are inlined, which also stops import cycles.
+const MAX_IMPORT_DEPTH: usize = 8;
+
+/// A resource loaded for [`GenerationalArenaDom::inline_resources`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resource {
+    /// The resource's MIME type, such as `image/png`, as given by its `Content-Type`.
+    pub media_type: String,
+
+    /// The resource's contents.
+    pub bytes: Vec<u8>,
+}
+
+impl Resource {
+    /// A resource of type `media_type` with the contents `bytes`.
+    pub fn new(media_type: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Resource {
+        Resource {
+            media_type: media_type.into(),
+            bytes: bytes.into(),
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Replace the URLs of the document's stylesheets, scripts and images with `data:` URLs of
+    /// their contents, making a snapshot that can be saved as a single file. Returns how many
+    /// URLs were replaced.
+    ///
+    /// `resolve` is called with the URL of each resource, resolved against the document's base
+    /// URL, and returns its contents, or `None` to leave the URL alone. It's called once per
+    /// URL. The resources inlined are the targets of `<link rel=stylesheet>` and
+    /// `<link rel=icon>`, `<script src>`, the `src` and `srcset` of `<img>`,
+    /// `<input type=image>` and `<picture>` sources, `<video poster>`, and `url(…)`s and
+    /// `@import`s in `<style>` elements and `style` attributes. Linked and imported stylesheets
+    /// have their own URLs inlined in turn, resolved against the stylesheet's URL.
+    ///
+    /// Afterwards, all other URLs in the document are made absolute and `<base>` elements are
+    /// removed, so links keep working from wherever the snapshot is saved. Frozen elements keep
+    /// their URLs.
+    pub fn inline_resources<F>(&mut self, resolve: F) -> usize
+    where
+        F: FnMut(&str) -> Option<Resource>,
+    {
+        let span = TraceSpan::transform("inline_resources");
+        let base = self.base_url();
+        let mut inliner = Inliner {
+            resolve,
+            cache: HashMap::new(),
+            inlined: 0,
+        };
+        let nodes: Vec<Handle> = self.document.descendants(&self.arena).collect();
+        for node in nodes {
+            let local = match self.element_name(node) {
+                Some(name) if name.ns == ns!(html) => name.local.clone(),
+                Some(_) => {
+                    self.inline_style_attr(node, base.as_deref(), &mut inliner);
+                    continue;
+                }
+                None => continue,
+            };
+            self.inline_style_attr(node, base.as_deref(), &mut inliner);
+            match &*local {
+                "link" => {
+                    let rel = self.get_attr(node, "rel").unwrap_or_default();
+                    if has_token(&rel, "stylesheet") {
+                        let inlined = self.inline_attr(node, "href", |href| {
+                            let href = absolute(base.as_deref(), href);
+                            inliner.stylesheet(&href, 0)
+                        });
+                        if inlined {
+                            // The rewritten stylesheet won't match its hash.
+                            self.remove_attr(node, "integrity");
+                        }
+                    } else if has_token(&rel, "icon") {
+                        self.inline_attr(node, "href", |href| {
+                            inliner.resource(base.as_deref(), href)
+                        });
+                    }
+                }
+                "style" => {
+                    let css = self.text_content(node);
+                    let inlined = inliner.css(&css, base.as_deref(), 0);
+                    if inlined != css && !self.skip_frozen(node) {
+                        let children: Vec<Handle> = node.children(&self.arena).collect();
+                        for child in children {
+                            self.free_subtree(child);
+                        }
+                        let text = self.create_text(&inlined);
+                        node.append(text, &mut self.arena);
+                    }
+                }
+                "script" => {
+                    self.inline_attr(node, "src", |src| inliner.resource(base.as_deref(), src));
+                }
+                "img" | "source" | "input" => {
+                    let is_image = match &*local {
+                        "source" => self.arena[node]
+                            .parent()
+                            .is_some_and(|parent| self.is_html_element(parent, "picture")),
+                        "input" => self
+                            .get_attr(node, "type")
+                            .is_some_and(|kind| kind.eq_ignore_ascii_case("image")),
+                        _ => true,
+                    };
+                    if !is_image {
+                        continue;
+                    }
+                    self.inline_attr(node, "src", |src| inliner.resource(base.as_deref(), src));
+                    self.inline_attr(node, "srcset", |srcset| {
+                        let mut output = String::with_capacity(srcset.len());
+                        let mut copied = 0;
+                        for candidate in srcset_candidates(srcset) {
+                            if let Some(data) = inliner.resource(base.as_deref(), candidate.url) {
+                                let start =
+                                    candidate.url.as_ptr() as usize - srcset.as_ptr() as usize;
+                                output.push_str(&srcset[copied..start]);
+                                output.push_str(&data);
+                                copied = start + candidate.url.len();
+                            }
+                        }
+                        if copied == 0 {
+                            return None;
+                        }
+                        output.push_str(&srcset[copied..]);
+                        Some(output)
+                    });
+                }
+                "video" => {
+                    self.inline_attr(node, "poster", |poster| {
+                        inliner.resource(base.as_deref(), poster)
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(base) = &base {
+            self.rewrite_urls(self.document, |reference| {
+                if is_local(reference) {
+                    return None;
+                }
+                url::resolve(base, reference)
+            });
+            let bases: Vec<Handle> = self
+                .document
+                .descendants(&self.arena)
+                .filter(|&node| self.is_html_element(node, "base"))
+                .collect();
+            for node in bases {
+                if !self.skip_frozen_removal(node) {
+                    self.free_subtree(node);
+                }
+            }
+        }
+        self.mark_mutated();
+        span.changed(self.arena.count(), inliner.inlined)
+    }
+
+    /// Replace the value of the attribute `name` of `node` with what `inline` returns for it.
+    /// Returns whether it was replaced.
+    fn inline_attr<F>(&mut self, node: Handle, name: &str, inline: F) -> bool
+    where
+        F: FnOnce(&str) -> Option<String>,
+    {
+        let value = match self.get_attr(node, name) {
+            Some(value) => value,
+            None => return false,
+        };
+        if self.skip_frozen(node) {
+            return false;
+        }
+        match inline(&value) {
+            Some(inlined) => {
+                self.set_attr(node, name, StrTendril::from(inlined));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn inline_style_attr<F>(&mut self, node: Handle, base: Option<&str>, inliner: &mut Inliner<F>)
+    where
+        F: FnMut(&str) -> Option<Resource>,
+    {
+        self.inline_attr(node, "style", |style| {
+            let inlined = inliner.css(style, base, 0);
+            (inlined != style).then_some(inlined)
+        });
+    }
+}
+
+/// The state of [`GenerationalArenaDom::inline_resources`].
+struct Inliner<F> {
+    resolve: F,
+    /// The `data:` URLs of the resources resolved so far, by URL. Stylesheets, whose URLs are
+    /// inlined in turn, are keyed with a `css ` prefix.
+    cache: HashMap<String, Option<String>>,
+    inlined: usize,
+}
+
+impl<F> Inliner<F>
+where
+    F: FnMut(&str) -> Option<Resource>,
+{
+    /// The `data:` URL for `reference`, resolved against `base`.
+    fn resource(&mut self, base: Option<&str>, reference: &str) -> Option<String> {
+        if is_local(reference) {
+            return None;
+        }
+        let url = absolute(base, reference);
+        let data = match self.cache.get(&url) {
+            Some(data) => data.clone(),
+            None => {
+                let data = (self.resolve)(&url)
+                    .map(|resource| data_url(&resource.media_type, &resource.bytes));
+                self.cache.insert(url, data.clone());
+                data
+            }
+        };
+        if data.is_some() {
+            self.inlined += 1;
+        }
+        data
+    }
+
+    /// The `data:` URL for the stylesheet at the absolute URL `url`, with its own URLs inlined.
+    fn stylesheet(&mut self, url: &str, depth: usize) -> Option<String> {
+        if is_local(url) || depth > MAX_IMPORT_DEPTH {
+            return None;
+        }
+        let key = format!("css {}", url);
+        let data = match self.cache.get(&key) {
+            Some(data) => data.clone(),
+            None => {
+                // Keep a cycle from resolving the stylesheet again.
+                self.cache.insert(key.clone(), None);
+                let data = (self.resolve)(url).map(|resource| {
+                    let css = String::from_utf8_lossy(&resource.bytes);
+                    let css = self.css(css.trim_start_matches('\u{feff}'), Some(url), depth + 1);
+                    data_url("text/css;charset=utf-8", css.as_bytes())
+                });
+                self.cache.insert(key, data.clone());
+                data
+            }
+        };
+        if data.is_some() {
+            self.inlined += 1;
+        }
+        data
+    }
+
+    /// `css` with the URLs of its `url(…)`s and `@import`s, resolved against `base`, inlined or
+    /// made absolute.
+    fn css(&mut self, css: &str, base: Option<&str>, depth: usize) -> String {
+        rewrite_css_urls(css, |reference, imported| {
+            let inlined = if imported {
+                self.stylesheet(&absolute(base, reference), depth)
+            } else {
+                self.resource(base, reference)
+            };
+            // URLs left alone must still work from within a `data:` URL.
+            inlined.or_else(|| {
+                let absolute = absolute(base, reference);
+                (!is_local(reference) && absolute != reference).then_some(absolute)
+            })
+        })
+    }
+}
+
+/// Whether `reference` refers to something within the document or needs no loading: a
+/// fragment, or a `data:`, `javascript:` or `about:` URL.
+fn is_local(reference: &str) -> bool {
+    let reference = reference.trim();
+    if reference.is_empty() || reference.starts_with('#') {
+        return true;
+    }
+    UrlParts::parse(reference).scheme.is_some_and(|scheme| {
+        ["data", "javascript", "about"]
+            .iter()
+            .any(|local| scheme.eq_ignore_ascii_case(local))
+    })
+}
+
+/// `reference` resolved against `base`, or as is if there's no base.
+fn absolute(base: Option<&str>, reference: &str) -> String {
+    base.and_then(|base| url::resolve(base, reference))
+        .unwrap_or_else(|| reference.trim().to_string())
+}
+
+/// A base64 `data:` URL of `bytes` with the MIME type `media_type`.
+fn data_url(media_type: &str, bytes: &[u8]) -> String {
+    let media_type: String = media_type
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let media_type = if media_type.is_empty() {
+        "application/octet-stream"
+    } else {
+        &media_type
+    };
+    format!("data:{};base64,{}", media_type, BASE64.encode(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{data_url, is_local, Resource};
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn makes_data_urls() {
+        assert_eq!(data_url("image/png", b"hi"), "data:image/png;base64,aGk=");
+        assert_eq!(
+            data_url("text/css; charset=utf-8", b""),
+            "data:text/css;charset=utf-8;base64,"
+        );
+        assert_eq!(
+            data_url(" ", b"a"),
+            "data:application/octet-stream;base64,YQ=="
+        );
+        assert!(is_local("#top"));
+        assert!(is_local(" JavaScript:x()"));
+        assert!(is_local("data:,"));
+        assert!(!is_local("/a.png"));
+    }
+
+    #[test]
+    fn inlines_images_and_scripts() {
+        let mut dom = parse_html(
+            "<base href=https://example.com/dir/><script src=app.js></script>\
+             <img src=a.png srcset='a.png 1x, b.png 2x'><a href=page.html#x>x</a>\
+             <p style='background: url(a.png)'>p</p>",
+        );
+        let mut requested = Vec::new();
+        let inlined = dom.inline_resources(|url| {
+            requested.push(url.to_string());
+            match url {
+                "https://example.com/dir/app.js" => Some(Resource::new("text/javascript", "x()")),
+                "https://example.com/dir/a.png" => Some(Resource::new("image/png", "hi")),
+                _ => None,
+            }
+        });
+        assert_eq!(inlined, 4);
+        assert_eq!(
+            requested,
+            [
+                "https://example.com/dir/app.js",
+                "https://example.com/dir/a.png",
+                "https://example.com/dir/b.png"
+            ]
+        );
+        let png = data_url("image/png", b"hi");
+        assert_eq!(
+            body_html(&dom),
+            format!(
+                "<img src=\"{png}\" srcset=\"{png} 1x, https://example.com/dir/b.png 2x\">\
+                 <a href=\"https://example.com/dir/page.html#x\">x</a>\
+                 <p style=\"background: url(&quot;{png}&quot;)\">p</p>"
+            )
+        );
+        let script = dom.query_selector(dom.document, "script").unwrap().unwrap();
+        assert_eq!(
+            dom.get_attr(script, "src").as_deref(),
+            Some(&*data_url("text/javascript", b"x()"))
+        );
+    }
+
+    #[test]
+    fn inlines_stylesheets_and_their_imports() {
+        let mut dom = parse_html(
+            "<link rel=stylesheet href=https://example.com/css/a.css integrity=sha384-x>\
+             <style>@import url(/css/b.css);</style>",
+        );
+        let mut requested = Vec::new();
+        dom.inline_resources(|url| {
+            requested.push(url.to_string());
+            let css = match url {
+                "https://example.com/css/a.css" => "@import 'b.css'; p { background: url(bg.png) }",
+                "https://example.com/css/b.css" => "@import 'a.css'; b { color: red }",
+                _ => return None,
+            };
+            Some(Resource::new("text/css", css))
+        });
+        assert_eq!(
+            requested,
+            [
+                "https://example.com/css/a.css",
+                "https://example.com/css/b.css",
+                "https://example.com/css/bg.png",
+                "/css/b.css"
+            ]
+        );
+        let link = dom.query_selector(dom.document, "link").unwrap().unwrap();
+        assert!(dom.get_attr(link, "integrity").is_none());
+        let b = data_url(
+            "text/css;charset=utf-8",
+            b"@import \"https://example.com/css/a.css\"; b { color: red }",
+        );
+        let a = data_url(
+            "text/css;charset=utf-8",
+            format!(
+                "@import \"{}\"; p {{ background: url(\"https://example.com/css/bg.png\") }}",
+                b
+            )
+            .as_bytes(),
+        );
+        assert_eq!(dom.get_attr(link, "href").as_deref(), Some(&*a));
+    }
+}
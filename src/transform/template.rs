@@ -0,0 +1,620 @@
+use std::collections::HashMap;
+
+use markup5ever::tendril::StrTendril;
+#[cfg(feature = "serde")]
+use serde_json::{json, Value};
+
+use crate::trace::TraceSpan;
+use crate::url::{percent_encode, UrlParts, URL_ATTRIBUTES};
+#[cfg(feature = "serde")]
+use crate::InsertPosition;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// What to replace a URL whose scheme could run script with.
+const UNSAFE_URL: &str = "about:invalid#unsafe";
+
+/// Elements whose text the serializer writes without escaping.
+const RAW_TEXT_PARENTS: &[&str] = &[
+    "xmp",
+    "iframe",
+    "noembed",
+    "noframes",
+    "noscript",
+    "plaintext",
+];
+
+/// Where a placeholder is, which decides how its value is escaped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Context {
+    /// Text or an ordinary attribute value, which the serializer escapes.
+    Text,
+    /// Text the serializer writes as it is, such as the contents of `<noscript>`.
+    RawText,
+    /// An attribute value that is parsed as HTML, such as `srcdoc`.
+    Html,
+    /// A URL attribute value.
+    Url,
+    /// A script or event handler, where values are expected inside string literals.
+    Script,
+    /// A stylesheet or `style` attribute.
+    Style,
+}
+
+impl GenerationalArenaDom {
+    /// Replace `{{name}}` placeholders in the text and attribute values under `root` with the
+    /// values of `vars`, returning how many were replaced. Placeholders whose names aren't in
+    /// `vars` are left as they are.
+    ///
+    /// Names are made of ASCII letters, digits, `_`, `-` and `.`, and may have spaces around
+    /// them, as in `{{ name }}`. Values are escaped for where they end up:
+    ///
+    /// - In most text and attributes, values are used as is: they become text or attribute
+    ///   values, which the serializer escapes.
+    /// - In `<noscript>`, `<xmp>`, `<iframe>`, `<noembed>`, `<noframes>` and `<plaintext>`,
+    ///   whose text is serialized without escaping, values containing `<` are refused, as are
+    ///   values starting with `/` right after a `<`, and their placeholders are left as they
+    ///   are.
+    /// - In `srcdoc` attributes, which browsers parse as HTML, `&`, `<`, `>` and quotes in
+    ///   values are written as character references.
+    /// - In URL attributes such as `href` and `src`, a placeholder at the start of the value
+    ///   may supply a whole URL, and values of other placeholders are percent-encoded. If the
+    ///   resulting URL has a scheme other than `http`, `https`, `mailto` or `tel`, it's replaced
+    ///   with `about:invalid#unsafe`.
+    /// - In `<script>` elements and event handler attributes, values are escaped to be used
+    ///   inside JavaScript string literals, template literals included: quotes, backticks, `$`,
+    ///   braces, backslashes, `<`, `>`, `&`, `/`, line separators and control characters are
+    ///   written as `\uXXXX` escapes, so a value can't end the literal, interpolate with `${`,
+    ///   or close the `<script>` element.
+    /// - In `<style>` elements and `style` attributes, values are escaped as CSS identifiers
+    ///   or string contents.
+    ///
+    /// Placeholders in frozen nodes are left as they are.
+    pub fn fill_placeholders(&mut self, root: Handle, vars: &HashMap<String, String>) -> usize {
+        let span = TraceSpan::transform("fill_placeholders");
+        let replaced = self.substitute_placeholders(root, &mut |name| vars.get(name).cloned());
+        span.changed(self.arena.count(), replaced)
+    }
+
+    /// Replace the placeholders under `root` with the values `lookup` returns for their names,
+    /// as described for [`fill_placeholders`](GenerationalArenaDom::fill_placeholders).
+    pub(crate) fn substitute_placeholders(
+        &mut self,
+        root: Handle,
+        lookup: &mut dyn FnMut(&str) -> Option<String>,
+    ) -> usize {
+        let nodes: Vec<Handle> = root.descendants(&self.arena).collect();
+        let replaced = nodes
+            .into_iter()
+            .map(|node| self.substitute_node(node, lookup))
+            .sum();
+        if replaced > 0 {
+            self.mark_mutated();
+        }
+        replaced
+    }
+
+    /// Replace the placeholders in the text or attribute values of `node` itself, returning
+    /// how many were replaced.
+    fn substitute_node(
+        &mut self,
+        node: Handle,
+        lookup: &mut dyn FnMut(&str) -> Option<String>,
+    ) -> usize {
+        let mut replaced = 0;
+        match self.get_node(&node) {
+            NodeData::Text { contents } => {
+                let parent = self.arena[node].parent();
+                let context = match parent {
+                    Some(parent) if self.is_html_element(parent, "script") => Context::Script,
+                    Some(parent) if self.is_html_element(parent, "style") => Context::Style,
+                    Some(parent)
+                        if RAW_TEXT_PARENTS
+                            .iter()
+                            .any(|local| self.is_html_element(parent, local)) =>
+                    {
+                        Context::RawText
+                    }
+                    _ => Context::Text,
+                };
+                let substituted = substitute(&contents.borrow(), context, lookup);
+                if let Some((text, count)) = substituted.filter(|_| !self.skip_frozen(node)) {
+                    *contents.borrow_mut() = StrTendril::from(text);
+                    replaced += count;
+                }
+            }
+            NodeData::Element { attrs, .. } => {
+                for attr in attrs.borrow_mut().iter_mut() {
+                    let name = &*attr.name.local;
+                    let context = if name.starts_with("on") {
+                        Context::Script
+                    } else if name == "style" {
+                        Context::Style
+                    } else if name == "srcdoc" {
+                        Context::Html
+                    } else if URL_ATTRIBUTES.contains(&name) {
+                        Context::Url
+                    } else {
+                        Context::Text
+                    };
+                    let substituted = substitute(&attr.value, context, lookup);
+                    if let Some((value, count)) = substituted.filter(|_| !self.skip_frozen(node)) {
+                        attr.value = StrTendril::from(value);
+                        replaced += count;
+                    }
+                }
+            }
+            _ => {}
+        }
+        replaced
+    }
+}
+
+#[cfg(feature = "serde")]
+impl GenerationalArenaDom {
+    /// Render the template under `root` with the data in `context`, returning how many
+    /// directives were evaluated.
+    ///
+    /// Elements with a `data-for="item in path"` attribute are repeated for each item of the
+    /// array at `path`, and removed if there is none. Within each copy, `item` refers to the
+    /// item, and `loop.index`, `loop.first` and `loop.last` to its zero-based index and whether
+    /// it's the first or last. Elements with a `data-if="path"` attribute are removed unless the
+    /// value at `path` is truthy, or with `data-if="!path"`, unless it's falsy. An element with
+    /// both is repeated first, so the condition can test the item. Directive attributes are
+    /// removed once evaluated; malformed ones are left alone, as are elements whose directives
+    /// would change frozen nodes.
+    ///
+    /// Paths are names separated by `.`, each a member of an object or an index into an array,
+    /// starting from the innermost loop variable of that name, or else from `context`. Values
+    /// are truthy unless they are missing, `null`, `false`, `0`, `""` or `[]`.
+    ///
+    /// Placeholders are then filled as by
+    /// [`fill_placeholders`](GenerationalArenaDom::fill_placeholders), with `{{path}}` replaced
+    /// by the value at `path`: strings as they are, `null` as nothing, and other values as
+    /// JSON.
+    ///
+    /// [`render_template_from`](GenerationalArenaDom::render_template_from) takes the context
+    /// as any serializable data instead.
+    pub fn render_template(&mut self, root: Handle, context: &Value) -> usize {
+        let span = TraceSpan::transform("render_template");
+        let mut scope = Scope {
+            context,
+            locals: Vec::new(),
+        };
+        let mut directives = 0;
+        self.render_node(root, &mut scope, &mut directives);
+        self.mark_mutated();
+        span.changed(self.arena.count(), directives)
+    }
+
+    /// Like [`render_template`](GenerationalArenaDom::render_template), with the context
+    /// converted from `context` by [`serde_json::to_value`]. Fails if it can't be converted.
+    pub fn render_template_from<T: serde::Serialize + ?Sized>(
+        &mut self,
+        root: Handle,
+        context: &T,
+    ) -> Result<usize, serde_json::Error> {
+        Ok(self.render_template(root, &serde_json::to_value(context)?))
+    }
+
+    fn render_node(&mut self, node: Handle, scope: &mut Scope<'_>, directives: &mut usize) {
+        let each = self
+            .get_attr(node, "data-for")
+            .and_then(|spec| parse_for(&spec));
+        if let Some((name, path)) = each {
+            if self.skip_frozen_removal(node) {
+                return;
+            }
+            *directives += 1;
+            self.remove_attr(node, "data-for");
+            let items = match scope.lookup(&path) {
+                Some(Value::Array(items)) => items.clone(),
+                _ => Vec::new(),
+            };
+            let count = items.len();
+            for (index, item) in items.into_iter().enumerate() {
+                let copy = self.clone_subtree(node);
+                self.insert_at(&[copy], InsertPosition::Before(node));
+                let info = json!({
+                    "index": index,
+                    "first": index == 0,
+                    "last": index + 1 == count,
+                });
+                scope.locals.push(("loop".to_string(), info));
+                scope.locals.push((name.clone(), item));
+                self.render_node(copy, scope, directives);
+                scope.locals.truncate(scope.locals.len() - 2);
+            }
+            self.free_subtree(node);
+            return;
+        }
+
+        let condition = self.get_attr(node, "data-if").and_then(|condition| {
+            let condition = condition.trim();
+            let (negated, path) = match condition.strip_prefix('!') {
+                Some(path) => (true, path.trim()),
+                None => (false, condition),
+            };
+            is_path(path).then(|| (negated, path.to_string()))
+        });
+        if let Some((negated, path)) = condition {
+            let keep = is_truthy(scope.lookup(&path)) != negated;
+            if (keep && self.skip_frozen(node)) || (!keep && self.skip_frozen_removal(node)) {
+                return;
+            }
+            *directives += 1;
+            if !keep {
+                self.free_subtree(node);
+                return;
+            }
+            self.remove_attr(node, "data-if");
+        }
+
+        self.substitute_node(node, &mut |path| scope.lookup(path).map(format_value));
+        let children: Vec<Handle> = node.children(&self.arena).collect();
+        for child in children {
+            self.render_node(child, scope, directives);
+        }
+    }
+}
+
+/// The data a template is rendered with.
+#[cfg(feature = "serde")]
+struct Scope<'a> {
+    context: &'a Value,
+    /// Loop variables, innermost last.
+    locals: Vec<(String, Value)>,
+}
+
+#[cfg(feature = "serde")]
+impl Scope<'_> {
+    /// The value at `path`.
+    fn lookup(&self, path: &str) -> Option<&Value> {
+        let mut segments = path.split('.');
+        let first = segments.next()?;
+        let mut value = match self.locals.iter().rev().find(|(name, _)| name == first) {
+            Some((_, value)) => value,
+            None => self.context.get(first)?,
+        };
+        for segment in segments {
+            value = match value {
+                Value::Object(members) => members.get(segment)?,
+                Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// The variable name and path of a `data-for` value, `name in path`.
+#[cfg(feature = "serde")]
+fn parse_for(spec: &str) -> Option<(String, String)> {
+    let mut words = spec.split_ascii_whitespace();
+    let (name, keyword, path) = (words.next()?, words.next()?, words.next()?);
+    let valid = keyword == "in"
+        && words.next().is_none()
+        && is_path(path)
+        && is_path(name)
+        && !name.contains('.');
+    valid.then(|| (name.to_string(), path.to_string()))
+}
+
+#[cfg(feature = "serde")]
+fn is_path(path: &str) -> bool {
+    !path.is_empty()
+        && path
+            .split('.')
+            .all(|segment| !segment.is_empty() && segment.chars().all(is_name_char))
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-')
+}
+
+#[cfg(feature = "serde")]
+fn is_truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+        Some(Value::Number(number)) => number.as_f64().is_some_and(|number| number != 0.0),
+        Some(Value::String(string)) => !string.is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+#[cfg(feature = "serde")]
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// `text` with its placeholders replaced, and how many were, or `None` if none were.
+fn substitute(
+    text: &str,
+    context: Context,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<(String, usize)> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut replaced = 0;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start + 2..].find("}}") {
+            Some(end) => start + 2 + end,
+            None => break,
+        };
+        let name = rest[start + 2..end].trim();
+        let valid = !name.is_empty() && name.chars().all(|c| is_name_char(c) || c == '.');
+        let value = if valid { lookup(name) } else { None };
+        match value {
+            Some(value) => {
+                output.push_str(&rest[..start]);
+                let at_start = output.trim_start().is_empty();
+                if escape(&mut output, &value, context, at_start) {
+                    replaced += 1;
+                } else {
+                    output.push_str(&rest[start..end + 2]);
+                }
+                rest = &rest[end + 2..];
+            }
+            None => {
+                // Leave it, but look for placeholders within it, as in `{{{{name}}`.
+                output.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    if replaced == 0 {
+        return None;
+    }
+    output.push_str(rest);
+    // Placeholders can supply part of a scheme, as in `{{proto}}:alert(1)`, so it's the whole
+    // URL that's checked.
+    if context == Context::Url && !is_safe_url(&output) {
+        output = UNSAFE_URL.to_string();
+    }
+    Some((output, replaced))
+}
+
+/// Whether `url` is relative or has a scheme that can't run script.
+fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore control characters and whitespace when parsing schemes.
+    let stripped: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_control() && !c.is_ascii_whitespace())
+        .collect();
+    UrlParts::parse(&stripped).scheme.is_none_or(|scheme| {
+        ["http", "https", "mailto", "tel"]
+            .iter()
+            .any(|safe| scheme.eq_ignore_ascii_case(safe))
+    })
+}
+
+/// Append `value`, escaped for `context`, to `output`, or return `false` if it can't be used
+/// there. `at_start` is whether it's at the start of the text or attribute value.
+fn escape(output: &mut String, value: &str, context: Context, at_start: bool) -> bool {
+    match context {
+        Context::Text => output.push_str(value),
+        Context::RawText => {
+            // Nothing is escaped in raw text, so a value that could end the element, or start
+            // an end tag with the text before it, can't be used.
+            if value.contains('<') || (output.ends_with('<') && value.starts_with('/')) {
+                return false;
+            }
+            output.push_str(value);
+        }
+        Context::Html => {
+            for c in value.chars() {
+                match c {
+                    '&' => output.push_str("&amp;"),
+                    '<' => output.push_str("&lt;"),
+                    '>' => output.push_str("&gt;"),
+                    '"' => output.push_str("&quot;"),
+                    '\'' => output.push_str("&#39;"),
+                    c => output.push(c),
+                }
+            }
+        }
+        Context::Url if at_start => output.push_str(value),
+        Context::Url => output.push_str(&percent_encode(value)),
+        Context::Script => {
+            for c in value.chars() {
+                match c {
+                    '\\' | '"' | '\'' | '`' | '$' | '{' | '}' | '<' | '>' | '&' | '/'
+                    | '\u{2028}' | '\u{2029}' => {
+                        output.push_str(&format!("\\u{:04x}", c as u32));
+                    }
+                    c if c.is_control() => output.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => output.push(c),
+                }
+            }
+        }
+        Context::Style => {
+            for c in value.chars() {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii() {
+                    output.push(c);
+                } else {
+                    // The trailing space ends the escape, in case a hex digit follows.
+                    output.push_str(&format!("\\{:x} ", c as u32));
+                }
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{escape, Context};
+    #[cfg(feature = "serde")]
+    use super::{is_truthy, parse_for};
+    use crate::parse::{body_html, parse_html};
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|&(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn escaped(value: &str, context: Context) -> String {
+        let mut output = String::new();
+        escape(&mut output, value, context, false);
+        output
+    }
+
+    #[test]
+    fn script_values_cant_escape_template_literals() {
+        assert_eq!(
+            escaped("${alert(1)}`", Context::Script),
+            "\\u0024\\u007balert(1)\\u007d\\u0060"
+        );
+        assert_eq!(
+            escaped("</script><b>\"'\\", Context::Script),
+            "\\u003c\\u002fscript\\u003e\\u003cb\\u003e\\u0022\\u0027\\u005c"
+        );
+        assert_eq!(escaped("plain text", Context::Script), "plain text");
+    }
+
+    #[test]
+    fn raw_text_values_cant_end_their_element() {
+        let mut dom = parse_html(
+            "<p></p><noscript><img src=\"{{pixel}}\"></noscript><xmp><{{slash}}</xmp>\
+             <noembed>{{name}}</noembed>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let filled = dom.fill_placeholders(
+            body,
+            &vars(&[
+                ("pixel", "\"></noscript><script>alert(1)</script>"),
+                ("slash", "/xmp><script>alert(1)</script>"),
+                ("name", "Lamp & co"),
+            ]),
+        );
+        assert_eq!(filled, 1);
+        assert_eq!(
+            body_html(&dom),
+            "<p></p><noscript><img src=\"{{pixel}}\"></noscript><xmp><{{slash}}</xmp>\
+             <noembed>Lamp & co</noembed>"
+        );
+    }
+
+    #[test]
+    fn srcdoc_values_are_escaped_as_html() {
+        let mut dom = parse_html("<iframe srcdoc=\"<p title='{{title}}'>{{name}}</p>\"></iframe>");
+        let iframe = dom.query_selector(dom.document, "iframe").unwrap().unwrap();
+        let filled = dom.fill_placeholders(
+            iframe,
+            &vars(&[
+                ("title", "' onclick='x"),
+                ("name", "<script>alert(1)</script> & more"),
+            ]),
+        );
+        assert_eq!(filled, 2);
+        assert_eq!(
+            dom.get_attr(iframe, "srcdoc").as_deref(),
+            Some(
+                "<p title='&#39; onclick=&#39;x'>&lt;script&gt;alert(1)&lt;/script&gt; &amp; more</p>"
+            )
+        );
+    }
+
+    #[test]
+    fn url_schemes_are_checked_after_substitution() {
+        let mut dom = parse_html(
+            "<a href=\"{{proto}}:alert(1)\"></a><a href=\"java{{rest}}\"></a>\
+             <a href=\"{{base}}/items?q={{query}}\"></a>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        dom.fill_placeholders(
+            body,
+            &vars(&[
+                ("proto", "javascript"),
+                ("rest", "script:alert(1)"),
+                ("base", "https://shop.example"),
+                ("query", "a b"),
+            ]),
+        );
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"about:invalid#unsafe\"></a><a href=\"javascript%3Aalert%281%29\"></a>\
+             <a href=\"https://shop.example/items?q=a%20b\"></a>"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn renders_templates() {
+        let mut dom = parse_html(
+            "<h1 data-if=title>{{ title }}</h1><p data-if=!items>Empty</p>\
+             <ul><li data-for='item in items' data-if=item.shown class='{{loop.index}}'>\
+             <a href='{{item.url}}'>{{item.name}}</a></li></ul>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let context = serde_json::json!({
+            "title": "<Lamps>",
+            "items": [
+                {"name": "Desk", "url": "/desk", "shown": true},
+                {"name": "Hidden", "url": "/hidden", "shown": false},
+                {"name": "Floor", "url": "javascript:alert(1)", "shown": 1},
+            ],
+        });
+        dom.render_template(body, &context);
+        assert_eq!(
+            body_html(&dom),
+            "<h1>&lt;Lamps&gt;</h1><ul><li class=\"0\"><a href=\"/desk\">Desk</a></li>\
+             <li class=\"2\"><a href=\"about:invalid#unsafe\">Floor</a></li></ul>"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn parses_directives() {
+        assert_eq!(
+            parse_for(" row  in table.rows "),
+            Some(("row".to_string(), "table.rows".to_string()))
+        );
+        assert_eq!(parse_for("row.x in rows"), None);
+        assert_eq!(parse_for("row of rows"), None);
+        assert_eq!(parse_for("row in rows extra"), None);
+        assert_eq!(parse_for("row in rows..x"), None);
+
+        let falsy = serde_json::json!([null, false, 0, 0.0, "", []]);
+        for value in falsy.as_array().unwrap() {
+            assert!(!is_truthy(Some(value)), "{}", value);
+        }
+        let truthy = serde_json::json!([true, -1, "0", [0], {}]);
+        for value in truthy.as_array().unwrap() {
+            assert!(is_truthy(Some(value)), "{}", value);
+        }
+        assert!(!is_truthy(None));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn renders_nested_loops() {
+        let mut dom = parse_html(
+            "<p data-for='g in groups'><b data-if=loop.first>{{g.name}}</b>\
+             <i data-for='g in g.tags' data-if=!loop.last>{{g}}{{loop.index}}</i></p>\
+             <hr data-for='bad spec' data-if='no spaces'>",
+        );
+        let body = dom.query_selector(dom.document, "body").unwrap().unwrap();
+        let context = serde_json::json!({
+            "groups": [
+                {"name": "a", "tags": ["x", "y", "z"]},
+                {"name": "b", "tags": []},
+            ],
+        });
+        let directives = dom.render_template(body, &context);
+        assert_eq!(directives, 8);
+        assert_eq!(
+            body_html(&dom),
+            "<p><b>a</b><i>x0</i><i>y1</i></p><p></p>\
+             <hr data-for=\"bad spec\" data-if=\"no spaces\">"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::extract::{outline, OutlineEntry};
+use crate::trace::TraceSpan;
+use crate::transform::SlugOptions;
+use crate::{GenerationalArenaDom, Handle, InsertPosition, NodeData, Selector};
+
+/// Options for [`GenerationalArenaDom::insert_toc`].
+#[derive(Clone, Debug)]
+pub struct TocOptions {
+    /// Headings with an outline level above this are left out. Defaults to 3.
+    pub max_level: usize,
+
+    /// The class of the `<nav>` element. Defaults to `toc`.
+    pub class: String,
+
+    /// The accessible label of the `<nav>` element, set as its `aria-label`. Defaults to
+    /// `Table of contents`.
+    pub label: Option<String>,
+}
+
+impl Default for TocOptions {
+    fn default() -> TocOptions {
+        TocOptions {
+            max_level: 3,
+            class: "toc".to_string(),
+            label: Some("Table of contents".to_string()),
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Render a table of contents for the document as a `<nav>` of nested `<ol>` lists of links
+    /// to its headings, following its [outline](crate::extract::outline), and insert it at
+    /// `position`. Returns the `<nav>`, or `None` if the document has no headings to list.
+    ///
+    /// Headings without an id are given one first, as by
+    /// [`add_heading_ids`](GenerationalArenaDom::add_heading_ids). Links from a heading to
+    /// itself are left out of its entry's text; other text in the headings, such as
+    /// [section numbers](GenerationalArenaDom::number_sections), is kept. Nothing is inserted
+    /// into a frozen element.
+    pub fn insert_toc(&mut self, position: InsertPosition, opts: &TocOptions) -> Option<Handle> {
+        let span = TraceSpan::transform("insert_toc");
+        if self.skip_frozen_insertion(position) {
+            span.changed(self.arena.count(), 0);
+            return None;
+        }
+        let nav = self.build_toc(opts);
+        if let Some(nav) = nav {
+            self.insert_at(&[nav], position);
+        }
+        span.changed(self.arena.count(), nav.is_some() as usize);
+        nav
+    }
+
+    /// Like [`insert_toc`](GenerationalArenaDom::insert_toc), but replace the first element
+    /// matching `marker`, such as an empty `<div id="toc">`, with the table of contents. If
+    /// nothing matches `marker`, nothing is inserted; if there are no headings to list, the
+    /// marker is still removed. A frozen marker is left alone.
+    pub fn insert_toc_at(&mut self, marker: &Selector, opts: &TocOptions) -> Option<Handle> {
+        let span = TraceSpan::transform("insert_toc_at");
+        let marker = self.select(self.document, marker).first().copied();
+        let marker = match marker {
+            Some(marker) if !self.skip_frozen_removal(marker) => marker,
+            _ => {
+                span.changed(self.arena.count(), 0);
+                return None;
+            }
+        };
+        let nav = self.insert_toc(InsertPosition::Before(marker), opts);
+        self.free_subtree(marker);
+        span.changed(self.arena.count(), 1 + nav.is_some() as usize);
+        nav
+    }
+
+    fn build_toc(&mut self, opts: &TocOptions) -> Option<Handle> {
+        self.add_heading_ids(&SlugOptions::default());
+        let entries = outline(self);
+        let list = self.toc_list(&entries, opts)?;
+        let mut attrs = vec![attribute("class", &opts.class)];
+        if let Some(label) = &opts.label {
+            attrs.push(attribute("aria-label", label));
+        }
+        let nav = self.create_html_element("nav", attrs);
+        nav.append(list, &mut self.arena);
+        Some(nav)
+    }
+
+    /// An `<ol>` of links to `entries`, or `None` if none of them are shallow enough to list.
+    fn toc_list(&mut self, entries: &[OutlineEntry], opts: &TocOptions) -> Option<Handle> {
+        let entries: Vec<&OutlineEntry> = entries
+            .iter()
+            .filter(|entry| entry.level <= opts.max_level)
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+        let list = self.create_html_element("ol", vec![]);
+        for entry in entries {
+            let id = self.get_attr(entry.handle, "id").unwrap_or_default();
+            let text = self.heading_text(entry.handle, &id);
+            let link = self.create_html_element("a", vec![attribute("href", &format!("#{}", id))]);
+            let text = self.create_text(&text);
+            link.append(text, &mut self.arena);
+            let item = self.create_html_element("li", vec![]);
+            item.append(link, &mut self.arena);
+            if let Some(children) = self.toc_list(&entry.children, opts) {
+                item.append(children, &mut self.arena);
+            }
+            list.append(item, &mut self.arena);
+        }
+        Some(list)
+    }
+
+    /// The collapsed text of `heading`, leaving out links to itself.
+    fn heading_text(&self, heading: Handle, id: &str) -> String {
+        let self_link = format!("#{}", id);
+        let mut text = String::new();
+        let mut stack = vec![heading];
+        while let Some(node) = stack.pop() {
+            match self.get_node(&node) {
+                NodeData::Text { contents } => text.push_str(&contents.borrow()),
+                NodeData::Element { .. }
+                    if self.is_html_element(node, "a")
+                        && self.get_attr(node, "href").as_deref() == Some(&*self_link) => {}
+                NodeData::Element { .. } => {
+                    let start = stack.len();
+                    stack.extend(node.children(&self.arena));
+                    stack[start..].reverse();
+                }
+                _ => {}
+            }
+        }
+        text.split_ascii_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+fn attribute(name: &str, value: &str) -> Attribute {
+    Attribute {
+        name: QualName::new(None, ns!(), LocalName::from(name)),
+        value: StrTendril::from(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TocOptions;
+    use crate::parse::{body_html, parse_html};
+    use crate::transform::SlugOptions;
+    use crate::InsertPosition;
+
+    #[test]
+    fn inserts_nested_tables_of_contents() {
+        let mut dom = parse_html(
+            "<h1 id=top>Guide</h1><h2>Install</h2><h3>Linux</h3><h4>Too deep</h4><h2>Use</h2>",
+        );
+        dom.add_heading_ids(&SlugOptions {
+            self_links: true,
+            ..SlugOptions::default()
+        });
+        let top = dom.query_selector(dom.document, "#top").unwrap().unwrap();
+        let nav = dom
+            .insert_toc(InsertPosition::After(top), &TocOptions::default())
+            .unwrap();
+        assert_eq!(dom.arena[top].next_sibling(), Some(nav));
+        assert_eq!(
+            dom.outer_html(nav),
+            "<nav class=\"toc\" aria-label=\"Table of contents\"><ol><li><a href=\"#top\">Guide</a>\
+             <ol><li><a href=\"#install\">Install</a><ol><li><a href=\"#linux\">Linux</a></li>\
+             </ol></li><li><a href=\"#use\">Use</a></li></ol></li></ol></nav>"
+        );
+    }
+
+    #[test]
+    fn replaces_markers() {
+        let opts = TocOptions {
+            label: None,
+            class: "contents".to_string(),
+            ..TocOptions::default()
+        };
+        let mut dom = parse_html("<div id=toc></div><h2>One</h2>");
+        dom.insert_toc_at(&"#toc".parse().unwrap(), &opts).unwrap();
+        assert_eq!(
+            body_html(&dom),
+            "<nav class=\"contents\"><ol><li><a href=\"#one\">One</a></li></ol></nav>\
+             <h2 id=\"one\">One</h2>"
+        );
+
+        let mut dom = parse_html("<div id=toc></div><p>no headings</p>");
+        assert_eq!(dom.insert_toc_at(&"#toc".parse().unwrap(), &opts), None);
+        assert_eq!(body_html(&dom), "<p>no headings</p>");
+        assert_eq!(dom.insert_toc_at(&"#toc".parse().unwrap(), &opts), None);
+
+        let mut dom = parse_html("<div id=toc></div><h2>One</h2>");
+        let marker = dom.query_selector(dom.document, "#toc").unwrap().unwrap();
+        dom.freeze(marker);
+        assert_eq!(dom.insert_toc_at(&"#toc".parse().unwrap(), &opts), None);
+        assert_eq!(
+            dom.insert_toc(InsertPosition::AppendTo(marker), &opts),
+            None
+        );
+        assert!(dom.is_alive(marker));
+    }
+}
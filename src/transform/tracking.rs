@@ -0,0 +1,247 @@
+use crate::trace::TraceSpan;
+use crate::url::{percent_decode, UrlParts};
+use crate::{GenerationalArenaDom, Handle};
+
+/// A link-wrapping redirect service, whose URLs carry the real destination in a query
+/// parameter, e.g. `https://www.google.com/url?q=https://example.com/`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Redirector {
+    /// The redirector's host, e.g. `www.google.com`. Compared ignoring ASCII case.
+    pub host: String,
+    /// The path of its redirect endpoint, e.g. `/url`.
+    pub path: String,
+    /// The query parameter holding the destination, e.g. `q`.
+    pub param: String,
+}
+
+impl Redirector {
+    /// A redirector at `host` and `path` whose destination is the `param` query parameter.
+    pub fn new(host: &str, path: &str, param: &str) -> Redirector {
+        Redirector {
+            host: host.to_string(),
+            path: path.to_string(),
+            param: param.to_string(),
+        }
+    }
+}
+
+/// Options for [`GenerationalArenaDom::clean_urls`].
+#[derive(Clone, Debug)]
+pub struct UrlPolicy {
+    /// Query parameters to remove, compared ignoring ASCII case. A trailing `*` matches any
+    /// parameter starting with the rest, as in `utm_*`. Defaults to common analytics and
+    /// click-tracking parameters.
+    pub strip_params: Vec<String>,
+
+    /// Redirectors whose links are replaced with their destination. Defaults to those of
+    /// Google, Facebook, YouTube, Instagram and Steam. Only destinations with an `http` or
+    /// `https` scheme are unwrapped.
+    pub redirectors: Vec<Redirector>,
+}
+
+impl Default for UrlPolicy {
+    fn default() -> UrlPolicy {
+        UrlPolicy {
+            strip_params: [
+                "utm_*",
+                "gclid",
+                "gclsrc",
+                "dclid",
+                "gbraid",
+                "wbraid",
+                "fbclid",
+                "msclkid",
+                "yclid",
+                "twclid",
+                "ttclid",
+                "li_fat_id",
+                "igshid",
+                "mc_cid",
+                "mc_eid",
+                "_ga",
+                "_gl",
+                "_hsenc",
+                "_hsmi",
+                "mkt_tok",
+                "oly_anon_id",
+                "oly_enc_id",
+                "vero_id",
+            ]
+            .iter()
+            .map(|param| param.to_string())
+            .collect(),
+            redirectors: vec![
+                Redirector::new("www.google.com", "/url", "q"),
+                Redirector::new("google.com", "/url", "q"),
+                Redirector::new("l.facebook.com", "/l.php", "u"),
+                Redirector::new("lm.facebook.com", "/l.php", "u"),
+                Redirector::new("www.youtube.com", "/redirect", "q"),
+                Redirector::new("l.instagram.com", "/", "u"),
+                Redirector::new("steamcommunity.com", "/linkfilter/", "url"),
+            ],
+        }
+    }
+}
+
+/// How many redirectors wrapped around each other are unwrapped.
+const MAX_UNWRAP: usize = 5;
+
+impl UrlPolicy {
+    /// Apply the policy to `url`, returning the cleaned URL, or `None` if there was nothing to
+    /// change.
+    pub fn clean(&self, url: &str) -> Option<String> {
+        let mut current = url.to_string();
+        for _ in 0..MAX_UNWRAP {
+            match self.unwrap(&current) {
+                Some(destination) => current = destination,
+                None => break,
+            }
+        }
+        if let Some(stripped) = self.strip(&current) {
+            current = stripped;
+        }
+        (current != url).then_some(current)
+    }
+
+    fn unwrap(&self, url: &str) -> Option<String> {
+        let parts = UrlParts::parse(url);
+        if !matches!(parts.scheme, Some(scheme) if is_http(scheme)) {
+            return None;
+        }
+        let host = parts.host()?;
+        let redirector = self.redirectors.iter().find(|redirector| {
+            redirector.host.eq_ignore_ascii_case(&host) && redirector.path == parts.path
+        })?;
+        let destination = parts
+            .query?
+            .split('&')
+            .find_map(|pair| {
+                let (name, value) = pair.split_once('=')?;
+                (name == redirector.param).then_some(value)
+            })
+            .map(percent_decode)?;
+        let scheme = UrlParts::parse(&destination).scheme;
+        matches!(scheme, Some(scheme) if is_http(scheme)).then_some(destination)
+    }
+
+    fn strip(&self, url: &str) -> Option<String> {
+        let parts = UrlParts::parse(url);
+        let query = parts.query?;
+        let kept: Vec<&str> = query
+            .split('&')
+            .filter(|pair| {
+                let name = pair.split_once('=').map_or(*pair, |(name, _)| name);
+                !self
+                    .strip_params
+                    .iter()
+                    .any(|param| match param.strip_suffix('*') {
+                        Some(prefix) => {
+                            name.len() >= prefix.len()
+                                && name.is_char_boundary(prefix.len())
+                                && name[..prefix.len()].eq_ignore_ascii_case(prefix)
+                        }
+                        None => name.eq_ignore_ascii_case(param),
+                    })
+            })
+            .collect();
+        if kept.len() == query.split('&').count() {
+            return None;
+        }
+        let query = kept.join("&");
+        Some(
+            UrlParts {
+                query: (!query.is_empty()).then_some(&query),
+                ..parts
+            }
+            .to_string(),
+        )
+    }
+}
+
+fn is_http(scheme: &str) -> bool {
+    scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+}
+
+impl GenerationalArenaDom {
+    /// Remove tracking parameters from the URLs under `root` and unwrap links that go through
+    /// known redirectors, as configured by `policy`. Returns how many attributes changed.
+    ///
+    /// URLs are found as by [`rewrite_urls`](GenerationalArenaDom::rewrite_urls). Relative
+    /// URLs have their parameters stripped too, but are only unwrapped if they are absolute.
+    pub fn clean_urls(&mut self, root: Handle, policy: &UrlPolicy) -> usize {
+        let span = TraceSpan::transform("clean_urls");
+        let changed = self.rewrite_urls(root, |url| policy.clean(url));
+        span.changed(self.arena.count(), changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Redirector, UrlPolicy};
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn strips_tracking_parameters() {
+        let policy = UrlPolicy::default();
+        assert_eq!(
+            policy
+                .clean("https://example.com/a?utm_source=x&id=7&FBCLID=y#top")
+                .as_deref(),
+            Some("https://example.com/a?id=7#top")
+        );
+        assert_eq!(
+            policy.clean("/page?UTM_Medium=email&gclid=1").as_deref(),
+            Some("/page")
+        );
+        assert_eq!(policy.clean("https://example.com/a?id=7&utm"), None);
+    }
+
+    #[test]
+    fn unwraps_redirectors() {
+        let policy = UrlPolicy::default();
+        assert_eq!(
+            policy
+                .clean("https://www.google.com/url?sa=t&q=https%3A%2F%2Fexample.com%2F%3Fa%3D1%26utm_id%3D2")
+                .as_deref(),
+            Some("https://example.com/?a=1")
+        );
+        let nested = "https://L.facebook.com/l.php?u=https%3A%2F%2Fwww.google.com%2Furl%3Fq%3Dhttps%253A%252F%252Fexample.com%252F";
+        assert_eq!(
+            policy.clean(nested).as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(
+            policy.clean("https://www.google.com/url?q=javascript:alert(1)"),
+            None
+        );
+        assert_eq!(
+            policy.clean("https://www.google.com/search?q=https://x.com/"),
+            None
+        );
+
+        let custom = UrlPolicy {
+            strip_params: vec![],
+            redirectors: vec![Redirector::new("out.example", "/go", "to")],
+        };
+        assert_eq!(
+            custom
+                .clean("http://out.example/go?to=http://a.example/?utm_x=1")
+                .as_deref(),
+            Some("http://a.example/?utm_x=1")
+        );
+    }
+
+    #[test]
+    fn cleans_urls_in_documents() {
+        let mut dom = parse_html(
+            "<a href='https://example.com/?fbclid=1'>a</a><img src=/i.png?utm_source=x>\
+             <a href=/keep?q=1>b</a>",
+        );
+        let root = dom.document;
+        assert_eq!(dom.clean_urls(root, &UrlPolicy::default()), 2);
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"https://example.com/\">a</a><img src=\"/i.png\"><a href=\"/keep?q=1\">b</a>"
+        );
+    }
+}
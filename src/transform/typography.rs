@@ -0,0 +1,213 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::transform::sanitize::strings;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Options for [`GenerationalArenaDom::smarten_typography`].
+#[derive(Clone, Debug)]
+pub struct TypographyOptions {
+    /// Whether to turn straight quotes into curly ones: `"` into `“` or `”`, and `'` into `‘`
+    /// or `’`, which is also the apostrophe. Defaults to `true`.
+    pub quotes: bool,
+
+    /// Whether to turn `---` into an em dash (`—`) and `--` into an en dash (`–`). Defaults to
+    /// `true`.
+    pub dashes: bool,
+
+    /// Whether to turn `...` into an ellipsis (`…`). Defaults to `true`.
+    pub ellipses: bool,
+
+    /// HTML elements whose descendant text is left untouched because it is code or otherwise
+    /// literal. Defaults to `code`, `pre`, `kbd`, `samp`, `var`, `tt`, `script`, `style`,
+    /// `textarea` and `template`.
+    pub skipped_elements: Vec<String>,
+}
+
+impl Default for TypographyOptions {
+    fn default() -> TypographyOptions {
+        TypographyOptions {
+            quotes: true,
+            dashes: true,
+            ellipses: true,
+            skipped_elements: strings(&[
+                "code", "pre", "kbd", "samp", "var", "tt", "script", "style", "textarea",
+                "template",
+            ]),
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Replace typewriter punctuation in the text under `root` with its typographic forms, as
+    /// configured by `opts`, returning how many text nodes changed.
+    ///
+    /// Whether a quote opens or closes is decided by the character before it, which may be at
+    /// the end of the previous text node in the same block, so `"<em>Yes</em>"` gets the right
+    /// quotes. A `'` after a letter or digit, or before a digit as in `'90s`, is an apostrophe.
+    /// Frozen text is left as it is.
+    pub fn smarten_typography(&mut self, root: Handle, opts: &TypographyOptions) -> usize {
+        let span = TraceSpan::transform("smarten_typography");
+        let mut changed = 0;
+        let mut previous: Option<char> = None;
+        let mut last_block = None;
+        for node in root.descendants(&self.arena) {
+            let contents = match self.get_node(&node) {
+                NodeData::Text { contents } => contents,
+                _ => continue,
+            };
+            let block = node
+                .ancestors(&self.arena)
+                .skip(1)
+                .find(|&ancestor| self.is_block(ancestor));
+            if block != last_block {
+                previous = None;
+                last_block = block;
+            }
+            let skipped = node.ancestors(&self.arena).skip(1).any(|ancestor| {
+                opts.skipped_elements
+                    .iter()
+                    .any(|name| self.is_html_element(ancestor, name))
+            });
+            let smartened = if skipped {
+                None
+            } else {
+                smarten(&contents.borrow(), previous, opts)
+            };
+            if let Some(smartened) = smartened.filter(|_| !self.skip_frozen(node)) {
+                *contents.borrow_mut() = StrTendril::from(smartened);
+                changed += 1;
+            }
+            previous = contents.borrow().chars().last().or(previous);
+        }
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), changed)
+    }
+}
+
+/// `text` with typographic punctuation, or `None` if nothing changed. `previous` is the
+/// character before it.
+fn smarten(text: &str, mut previous: Option<char>, opts: &TypographyOptions) -> Option<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut changed = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let replacement = match c {
+            '"' if opts.quotes => Some(if opens(previous) { '“' } else { '”' }),
+            '\'' if opts.quotes => {
+                let next = chars.peek().copied();
+                let apostrophe = previous.is_some_and(char::is_alphanumeric)
+                    || (opens(previous) && next.is_some_and(|next| next.is_ascii_digit()));
+                Some(if !apostrophe && opens(previous) {
+                    '‘'
+                } else {
+                    '’'
+                })
+            }
+            '-' if opts.dashes && chars.peek() == Some(&'-') => {
+                chars.next();
+                if chars.peek() == Some(&'-') {
+                    chars.next();
+                    Some('—')
+                } else {
+                    Some('–')
+                }
+            }
+            '.' if opts.ellipses && chars.clone().take(2).eq(['.', '.']) => {
+                chars.next();
+                chars.next();
+                Some('…')
+            }
+            _ => None,
+        };
+        let c = match replacement {
+            Some(replacement) => {
+                changed = true;
+                replacement
+            }
+            None => c,
+        };
+        out.push(c);
+        previous = Some(c);
+    }
+    changed.then_some(out)
+}
+
+/// Whether a quote after `previous` opens a quotation: at the start of a block, or after
+/// whitespace, an opening bracket, a dash or another opening quote.
+fn opens(previous: Option<char>) -> bool {
+    match previous {
+        None => true,
+        Some(c) => c.is_whitespace() || "([{<–—“‘".contains(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TypographyOptions;
+    use crate::parse::{body_html, parse_html};
+
+    fn smarten(html: &str) -> (String, usize) {
+        let mut dom = parse_html(html);
+        let changed = dom.smarten_typography(dom.document, &TypographyOptions::default());
+        (body_html(&dom), changed)
+    }
+
+    #[test]
+    fn curls_quotes() {
+        assert_eq!(
+            smarten("<p>\"Hi,\" she said, 'really'.</p>").0,
+            "<p>“Hi,” she said, ‘really’.</p>"
+        );
+        assert_eq!(
+            smarten("<p>(\"nested 'quotes'\")</p>").0,
+            "<p>(“nested ‘quotes’”)</p>"
+        );
+    }
+
+    #[test]
+    fn makes_apostrophes() {
+        assert_eq!(
+            smarten("<p>It's the '90s, isn't it? The dogs' bowls.</p>").0,
+            "<p>It’s the ’90s, isn’t it? The dogs’ bowls.</p>"
+        );
+    }
+
+    #[test]
+    fn replaces_dashes_and_ellipses() {
+        assert_eq!(
+            smarten("<p>1--2, wait---no... a-b</p>").0,
+            "<p>1–2, wait—no… a-b</p>"
+        );
+    }
+
+    #[test]
+    fn quotes_across_inline_elements() {
+        let (html, changed) = smarten("<p>\"<em>Yes</em>\" and '<b>no</b>'</p><p>\"new\"</p>");
+        assert_eq!(html, "<p>“<em>Yes</em>” and ‘<b>no</b>’</p><p>“new”</p>");
+        assert_eq!(changed, 4);
+    }
+
+    #[test]
+    fn leaves_code_alone() {
+        let html = "<p>\"a\"</p><code>\"b\"</code><pre>c--d</pre>\
+                    <script>var e = 'e...';</script><style>f::after { content: \"--\" }</style>";
+        let (smartened, changed) = smarten(html);
+        assert_eq!(smartened, html.replacen("<p>\"a\"</p>", "<p>“a”</p>", 1));
+        assert_eq!(changed, 1);
+    }
+
+    #[test]
+    fn follows_options() {
+        let mut dom = parse_html("<p>\"a\" -- b...</p>");
+        let opts = TypographyOptions {
+            quotes: false,
+            ellipses: false,
+            ..TypographyOptions::default()
+        };
+        assert_eq!(dom.smarten_typography(dom.document, &opts), 1);
+        assert_eq!(body_html(&dom), "<p>\"a\" – b...</p>");
+    }
+}
@@ -0,0 +1,135 @@
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+/// Options for [`GenerationalArenaDom::collapse_whitespace`].
+#[derive(Clone, Debug)]
+pub struct WhitespaceOptions {
+    /// HTML elements whose descendant text is left untouched because whitespace in it is
+    /// significant. Defaults to `pre`, `textarea`, `listing`, `plaintext`, `xmp`, `script` and
+    /// `style`.
+    pub preserve: Vec<String>,
+
+    /// Whether to remove text nodes that only contain whitespace. This shrinks the tree further
+    /// but can join up words separated only by a space between inline elements.
+    pub remove_blank: bool,
+}
+
+impl Default for WhitespaceOptions {
+    fn default() -> WhitespaceOptions {
+        WhitespaceOptions {
+            preserve: [
+                "pre",
+                "textarea",
+                "listing",
+                "plaintext",
+                "xmp",
+                "script",
+                "style",
+            ]
+            .iter()
+            .map(|name| name.to_string())
+            .collect(),
+            remove_blank: false,
+        }
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Collapse each run of ASCII whitespace in the text under `root` into a single space.
+    /// Frozen text is left alone.
+    pub fn collapse_whitespace(&mut self, root: Handle, opts: &WhitespaceOptions) {
+        let span = TraceSpan::transform("collapse_whitespace");
+        let mut changes = 0;
+        let mut blank = vec![];
+        for node in root.descendants(&self.arena) {
+            let contents = match self.get_node(&node) {
+                NodeData::Text { contents } => contents,
+                _ => continue,
+            };
+            if self.is_whitespace_preserved(node, opts) {
+                continue;
+            }
+            let collapsed = collapse(&contents.borrow());
+            if let Some(collapsed) = collapsed {
+                if self.skip_frozen(node) {
+                    continue;
+                }
+                *contents.borrow_mut() = StrTendril::from(collapsed);
+                changes += 1;
+            }
+            if opts.remove_blank && &**contents.borrow() == " " {
+                blank.push(node);
+            }
+        }
+        changes += blank.len();
+        for node in blank {
+            self.free_subtree(node);
+        }
+        self.mark_mutated();
+        span.changed(self.arena.count(), changes);
+    }
+
+    fn is_whitespace_preserved(&self, node: Handle, opts: &WhitespaceOptions) -> bool {
+        node.ancestors(&self.arena).skip(1).any(|ancestor| {
+            opts.preserve
+                .iter()
+                .any(|name| self.is_html_element(ancestor, name))
+        })
+    }
+}
+
+/// `text` with whitespace runs collapsed, or `None` if there was nothing to collapse.
+fn collapse(text: &str) -> Option<String> {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_run = false;
+    let mut changed = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if in_run || c != ' ' {
+                changed = true;
+            }
+            if !in_run {
+                collapsed.push(' ');
+            }
+            in_run = true;
+        } else {
+            collapsed.push(c);
+            in_run = false;
+        }
+    }
+    changed.then_some(collapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WhitespaceOptions;
+    use crate::parse::{body_html, parse_html};
+
+    const HTML: &str = "<p>  Some \n\t text </p> <pre>  kept\n  as is</pre>\n<b>a</b> <i>b</i>";
+
+    #[test]
+    fn collapses_runs_outside_preserved_elements() {
+        let mut dom = parse_html(HTML);
+        dom.collapse_whitespace(dom.document, &WhitespaceOptions::default());
+        assert_eq!(
+            body_html(&dom),
+            "<p> Some text </p> <pre>  kept\n  as is</pre> <b>a</b> <i>b</i>"
+        );
+    }
+
+    #[test]
+    fn removes_blank_text() {
+        let mut dom = parse_html(HTML);
+        let opts = WhitespaceOptions {
+            preserve: vec![],
+            remove_blank: true,
+        };
+        dom.collapse_whitespace(dom.document, &opts);
+        assert_eq!(
+            body_html(&dom),
+            "<p> Some text </p><pre> kept as is</pre><b>a</b><i>b</i>"
+        );
+    }
+}
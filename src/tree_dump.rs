@@ -0,0 +1,154 @@
+//! The indented tree format of html5lib-tests.
+
+use std::fmt::Write;
+
+use markup5ever::{namespace_url, ns, Namespace};
+
+use crate::{GenerationalArenaDom, Handle, NodeData};
+
+impl GenerationalArenaDom {
+    /// Dump the document in the format of the expected trees of [html5lib-tests], which
+    /// html5ever's own tests use, so that it can be compared directly with them.
+    ///
+    /// Each node is on its own line starting with `| `, indented by two spaces per level, as
+    /// in `| <html>`, `|   <head>` and `|     "text"`. Attributes follow their element one level
+    /// deeper, sorted by name, and the contents of a template are under a `content` line.
+    /// Elements and attributes outside the HTML namespace are prefixed by `svg `, `math ` and
+    /// so on. The `#document` line that starts the section in test files is not included.
+    ///
+    /// [html5lib-tests]: https://github.com/html5lib/html5lib-tests
+    pub fn tree_dump(&self) -> String {
+        dump(self, self.document.children(&self.arena))
+    }
+}
+
+/// Dump `roots` and their descendants in the html5lib format.
+pub(crate) fn dump(dom: &GenerationalArenaDom, roots: impl Iterator<Item = Handle>) -> String {
+    let mut out = String::new();
+    for root in roots {
+        dump_node(dom, root, 0, &mut out);
+    }
+    out.pop();
+    out
+}
+
+fn dump_node(dom: &GenerationalArenaDom, handle: Handle, depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    match dom.get_node(&handle) {
+        NodeData::Document => {}
+        NodeData::Doctype {
+            name,
+            public_id,
+            system_id,
+        } => {
+            if public_id.is_empty() && system_id.is_empty() {
+                let _ = writeln!(out, "| {}<!DOCTYPE {}>", indent, name);
+            } else {
+                let _ = writeln!(
+                    out,
+                    "| {}<!DOCTYPE {} \"{}\" \"{}\">",
+                    indent, name, public_id, system_id
+                );
+            }
+        }
+        NodeData::Text { contents } => {
+            let _ = writeln!(out, "| {}\"{}\"", indent, &**contents.borrow());
+        }
+        NodeData::Comment { contents } => {
+            let _ = writeln!(out, "| {}<!-- {} -->", indent, contents);
+        }
+        NodeData::ProcessingInstruction { target, contents } => {
+            let _ = writeln!(out, "| {}<?{} {}>", indent, target, contents);
+        }
+        NodeData::Element {
+            name,
+            attrs,
+            template_contents,
+            ..
+        } => {
+            let _ = writeln!(out, "| {}<{}{}>", indent, ns_prefix(&name.ns), name.local);
+            let mut attrs: Vec<(String, String)> = attrs
+                .borrow()
+                .iter()
+                .map(|attr| {
+                    let name = format!("{}{}", ns_prefix(&attr.name.ns), attr.name.local);
+                    (name, attr.value.to_string())
+                })
+                .collect();
+            attrs.sort();
+            for (name, value) in attrs {
+                let _ = writeln!(out, "| {}  {}=\"{}\"", indent, name, value);
+            }
+            if let Some(contents) = *template_contents.borrow() {
+                let _ = writeln!(out, "| {}  content", indent);
+                for child in contents.children(&dom.arena) {
+                    dump_node(dom, child, depth + 2, out);
+                }
+            }
+        }
+    }
+    for child in handle.children(&dom.arena) {
+        dump_node(dom, child, depth + 1, out);
+    }
+}
+
+/// How the dump format prefixes names in a namespace.
+fn ns_prefix(ns: &Namespace) -> &'static str {
+    if *ns == ns!(svg) {
+        "svg "
+    } else if *ns == ns!(mathml) {
+        "math "
+    } else if *ns == ns!(xlink) {
+        "xlink "
+    } else if *ns == ns!(xml) {
+        "xml "
+    } else if *ns == ns!(xmlns) {
+        "xmlns "
+    } else {
+        ""
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_html;
+
+    #[test]
+    fn dumps_in_html5lib_format() {
+        let dom = parse_html(
+            "<!DOCTYPE html><!--c--><p b=2 a=1>x</p>\
+             <svg viewBox='0 0 1 1'><a xlink:href=#y></a></svg><template><i></i></template>",
+        );
+        assert_eq!(
+            dom.tree_dump(),
+            "| <!DOCTYPE html>\n\
+             | <!-- c -->\n\
+             | <html>\n\
+             |   <head>\n\
+             |   <body>\n\
+             |     <p>\n\
+             |       a=\"1\"\n\
+             |       b=\"2\"\n\
+             |       \"x\"\n\
+             |     <svg svg>\n\
+             |       viewBox=\"0 0 1 1\"\n\
+             |       <svg a>\n\
+             |         xlink href=\"#y\"\n\
+             |     <template>\n\
+             |       content\n\
+             |         <i>"
+        );
+    }
+
+    #[test]
+    fn dumps_legacy_doctypes() {
+        let dom = parse_html(
+            "<!DOCTYPE html PUBLIC \"-//W3C//DTD HTML 4.01//EN\" \
+             \"http://www.w3.org/TR/html4/strict.dtd\">",
+        );
+        assert!(dom.tree_dump().starts_with(
+            "| <!DOCTYPE html \"-//W3C//DTD HTML 4.01//EN\" \
+             \"http://www.w3.org/TR/html4/strict.dtd\">\n| <html>"
+        ));
+    }
+}
@@ -0,0 +1,175 @@
+//! Generating selectors that identify a single element.
+
+use crate::{GenerationalArenaDom, Handle, NodeData, Selector};
+
+/// Attributes that tend to identify an element and are worth trying in a selector.
+const IDENTIFYING_ATTRIBUTES: &[&str] = &["name", "type", "role", "itemprop", "rel", "for"];
+
+impl GenerationalArenaDom {
+    /// The shortest selector found that matches `handle` and no other element in the document,
+    /// or `None` if `handle` isn't an element in the document.
+    ///
+    /// Selectors are built from the element's id, tag, classes and a few identifying
+    /// attributes, optionally qualified by a single ancestor. When none of those are unique, the
+    /// shortest unique chain of `:nth-child` steps leading to the element is used. Every
+    /// candidate is checked against the document with the query engine before being returned.
+    pub fn unique_selector(&self, handle: Handle) -> Option<String> {
+        self.path_of(handle)?;
+        let own = self.selector_candidates(handle)?;
+        if let Some(found) = own
+            .iter()
+            .find(|candidate| self.is_unique(candidate, handle))
+        {
+            return Some(found.clone());
+        }
+
+        let ancestors: Vec<Handle> = handle
+            .ancestors(&self.arena)
+            .skip(1)
+            .filter(|&ancestor| self.element_name(ancestor).is_some())
+            .collect();
+        for &ancestor in &ancestors {
+            let ancestor_candidates = self.selector_candidates(ancestor)?;
+            let mut combined: Vec<String> = ancestor_candidates
+                .iter()
+                .flat_map(|outer| own.iter().map(move |inner| format!("{} {}", outer, inner)))
+                .collect();
+            combined.sort_by_key(String::len);
+            if let Some(found) = combined
+                .into_iter()
+                .find(|candidate| self.is_unique(candidate, handle))
+            {
+                return Some(found);
+            }
+        }
+
+        let steps: Vec<String> = ancestors
+            .iter()
+            .rev()
+            .chain(Some(&handle))
+            .map(|&node| self.nth_child_step(node))
+            .collect::<Option<_>>()?;
+        (1..=steps.len())
+            .map(|len| steps[steps.len() - len..].join(" > "))
+            .find(|path| self.is_unique(path, handle))
+    }
+
+    fn is_unique(&self, selector: &str, handle: Handle) -> bool {
+        match Selector::parse(selector) {
+            Ok(selector) => self.select(self.document, &selector) == [handle],
+            Err(_) => false,
+        }
+    }
+
+    /// Selectors for `handle` on its own, shortest first.
+    pub(crate) fn selector_candidates(&self, handle: Handle) -> Option<Vec<String>> {
+        let (name, attrs) = match self.get_node(&handle) {
+            NodeData::Element { name, attrs, .. } => (name, attrs.borrow()),
+            _ => return None,
+        };
+        let tag = name.local.to_string();
+        if !is_ident(&tag) {
+            return None;
+        }
+        let mut candidates = vec![tag.clone()];
+        for attr in attrs.iter() {
+            let value = &*attr.value;
+            match &*attr.name.local {
+                "id" if is_ident(value) => candidates.push(format!("#{}", value)),
+                "class" => {
+                    let classes: Vec<&str> = value
+                        .split_ascii_whitespace()
+                        .filter(|c| is_ident(c))
+                        .collect();
+                    for class in &classes {
+                        candidates.push(format!(".{}", class));
+                        candidates.push(format!("{}.{}", tag, class));
+                    }
+                    if classes.len() > 1 {
+                        candidates.push(format!("{}.{}", tag, classes.join(".")));
+                    }
+                }
+                local if IDENTIFYING_ATTRIBUTES.contains(&local) && !value.contains('"') => {
+                    candidates.push(format!("{}[{}=\"{}\"]", tag, local, value));
+                }
+                _ => {}
+            }
+        }
+        candidates.extend(self.nth_child_step(handle));
+        candidates.sort_by_key(String::len);
+        candidates.dedup();
+        Some(candidates)
+    }
+
+    /// `tag:nth-child(n)` for `handle`.
+    fn nth_child_step(&self, handle: Handle) -> Option<String> {
+        let name = self.element_name(handle)?;
+        let index = handle
+            .preceding_siblings(&self.arena)
+            .filter(|&sibling| self.element_name(sibling).is_some())
+            .count();
+        Some(format!("{}:nth-child({})", name.local, index))
+    }
+}
+
+/// Whether `s` can be written in a selector without escaping.
+pub(crate) fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '-')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_ident;
+    use crate::parse::parse_html;
+    use crate::Selector;
+
+    #[test]
+    fn finds_unique_selectors() {
+        let dom = parse_html(
+            "<div id=main><p class='note'>a</p><p>b</p><ul><li>c</li><li>d</li></ul></div>\
+             <form><input name=q><input name=r></form><div></div>",
+        );
+        let find = |selector: &str| dom.query_selector(dom.document, selector).unwrap().unwrap();
+        let expected = [
+            ("#main", "#main"),
+            ("p.note", ".note"),
+            ("input[name=r]", "input[name=\"r\"]"),
+            ("li + li", "li:nth-child(2)"),
+            ("p + p", "p:nth-child(2)"),
+        ];
+        for (query, selector) in expected {
+            let handle = find(query);
+            assert_eq!(dom.unique_selector(handle).as_deref(), Some(selector));
+            let parsed = Selector::parse(selector).unwrap();
+            assert_eq!(dom.select(dom.document, &parsed), [handle]);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_child_paths() {
+        let dom = parse_html("<div><span>a</span></div><div><span>b</span></div>");
+        let second = dom.select(dom.document, &"span".parse().unwrap())[1];
+        let selector = dom.unique_selector(second).unwrap();
+        let parsed = Selector::parse(&selector).unwrap();
+        assert_eq!(dom.select(dom.document, &parsed), [second]);
+    }
+
+    #[test]
+    fn skips_non_elements_and_detached_nodes() {
+        let mut dom = parse_html("<p>text</p>");
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let text = dom.arena[p].first_child().unwrap();
+        assert_eq!(dom.unique_selector(text), None);
+        p.detach(&mut dom.arena);
+        assert_eq!(dom.unique_selector(p), None);
+
+        assert!(is_ident("main-nav_2"));
+        assert!(!is_ident("2col"));
+        assert!(!is_ident("a.b"));
+        assert!(!is_ident(""));
+    }
+}
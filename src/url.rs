@@ -0,0 +1,454 @@
+//! Just enough URL handling to resolve references found in documents.
+//!
+//! This follows the reference resolution algorithm of RFC 3986 rather than the full WHATWG URL
+//! standard: URLs are not percent-encoded or otherwise normalized beyond removing dot segments.
+
+use markup5ever::tendril::StrTendril;
+
+use crate::trace::TraceSpan;
+use crate::{space_tokens, srcset_candidates, GenerationalArenaDom, Handle, NodeData};
+
+/// Attributes whose whole value is a URL.
+pub(crate) const URL_ATTRIBUTES: &[&str] = &[
+    "action",
+    "background",
+    "cite",
+    "codebase",
+    "data",
+    "formaction",
+    "href",
+    "icon",
+    "longdesc",
+    "manifest",
+    "poster",
+    "src",
+];
+
+/// Attributes holding a list of URLs with descriptors, in `srcset` syntax.
+const SRCSET_ATTRIBUTES: &[&str] = &["srcset", "imagesrcset"];
+
+impl GenerationalArenaDom {
+    /// The URL the document was loaded from, if known.
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Record the URL the document was loaded from, which relative URLs in it are resolved
+    /// against.
+    pub fn set_url(&mut self, url: impl Into<String>) {
+        self.url = Some(url.into());
+    }
+
+    /// The URL relative URLs in the document are resolved against: the first `<base href>`
+    /// resolved against the document's URL, or the document's URL itself.
+    pub fn base_url(&self) -> Option<String> {
+        self.base_url_for(self.url())
+    }
+
+    /// The base URL the document would have if it was loaded from `url`.
+    pub(crate) fn base_url_for(&self, url: Option<&str>) -> Option<String> {
+        let base = self
+            .document
+            .descendants(&self.arena)
+            .find(|&node| {
+                self.is_html_element(node, "base") && self.get_attr(node, "href").is_some()
+            })
+            .and_then(|base| self.get_attr(base, "href"));
+        match (base, url) {
+            (Some(href), Some(url)) => resolve(url, &href),
+            (Some(href), None) => resolve(&href, ""),
+            (None, url) => url.map(String::from),
+        }
+    }
+
+    /// Resolve `reference` against the document's [base URL](GenerationalArenaDom::base_url),
+    /// or return it unchanged (apart from surrounding whitespace) if there is none.
+    pub fn resolve_url(&self, reference: &str) -> String {
+        self.base_url()
+            .and_then(|base| resolve(&base, reference))
+            .unwrap_or_else(|| reference.trim().to_string())
+    }
+
+    /// Rewrite the URLs in the attributes of `root` and the elements under it, returning how
+    /// many attribute values changed.
+    ///
+    /// `rewrite` is called with each URL as written, with surrounding whitespace removed, and
+    /// returns its replacement or `None` to leave it alone. URLs are found in attributes such
+    /// as `href`, `src`, `action` and `poster` (in any namespace, so SVG's `xlink:href` is
+    /// included), each candidate of `srcset` and `imagesrcset`, and each URL of `ping`. URLs in
+    /// inline styles and the attributes of frozen elements are not rewritten.
+    pub fn rewrite_urls<F>(&mut self, root: Handle, mut rewrite: F) -> usize
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        let span = TraceSpan::transform("rewrite_urls");
+        let mut changed = 0;
+        for node in root.descendants(&self.arena) {
+            let attrs = match self.get_node(&node) {
+                NodeData::Element { attrs, .. } => attrs,
+                _ => continue,
+            };
+            for attr in attrs.borrow_mut().iter_mut() {
+                let name = &*attr.name.local;
+                let value = &*attr.value;
+                let rewritten = if URL_ATTRIBUTES.contains(&name) {
+                    rewrite(value.trim_matches(|c: char| c.is_ascii_whitespace()))
+                } else if SRCSET_ATTRIBUTES.contains(&name) {
+                    let urls = srcset_candidates(value).map(|candidate| candidate.url);
+                    splice_urls(value, urls, &mut rewrite)
+                } else if name == "ping" {
+                    splice_urls(value, space_tokens(value), &mut rewrite)
+                } else {
+                    None
+                };
+                if let Some(rewritten) = rewritten {
+                    if rewritten != value {
+                        if self.skip_frozen(node) {
+                            break;
+                        }
+                        attr.value = StrTendril::from(rewritten);
+                        changed += 1;
+                    }
+                }
+            }
+        }
+        if changed > 0 {
+            self.mark_mutated();
+        }
+        span.changed(self.arena.count(), changed)
+    }
+}
+
+/// Replace the `urls`, which are slices of `value`, with their rewrites, keeping the text
+/// around them. Returns `None` if no URL was rewritten.
+fn splice_urls<'a, F>(
+    value: &'a str,
+    urls: impl Iterator<Item = &'a str>,
+    rewrite: &mut F,
+) -> Option<String>
+where
+    F: FnMut(&str) -> Option<String>,
+{
+    let mut output = String::with_capacity(value.len());
+    let mut copied = 0;
+    let mut changed = false;
+    for url in urls {
+        if let Some(replacement) = rewrite(url) {
+            let start = url.as_ptr() as usize - value.as_ptr() as usize;
+            output.push_str(&value[copied..start]);
+            output.push_str(&replacement);
+            copied = start + url.len();
+            changed = true;
+        }
+    }
+    if !changed {
+        return None;
+    }
+    output.push_str(&value[copied..]);
+    Some(output)
+}
+
+/// Percent-encode the bytes of `text` other than unreserved characters (ASCII letters, digits,
+/// `-`, `.`, `_` and `~`), so that it can be used as any URL component.
+pub(crate) fn percent_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Decode the percent-encoded bytes in `text`, replacing invalid UTF-8 with U+FFFD.
+pub(crate) fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The components of an absolute URL or a relative reference.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct UrlParts<'a> {
+    pub(crate) scheme: Option<&'a str>,
+    pub(crate) authority: Option<&'a str>,
+    pub(crate) path: &'a str,
+    pub(crate) query: Option<&'a str>,
+    pub(crate) fragment: Option<&'a str>,
+}
+
+impl<'a> UrlParts<'a> {
+    /// Split `url` into its components.
+    pub(crate) fn parse(url: &'a str) -> UrlParts<'a> {
+        let mut rest = url;
+        let mut parts = UrlParts::default();
+        if let Some((before, fragment)) = rest.split_once('#') {
+            parts.fragment = Some(fragment);
+            rest = before;
+        }
+        if let Some((before, query)) = rest.split_once('?') {
+            parts.query = Some(query);
+            rest = before;
+        }
+        if let Some((scheme, after)) = rest.split_once(':') {
+            let is_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+            if is_scheme {
+                parts.scheme = Some(scheme);
+                rest = after;
+            }
+        }
+        if let Some(after) = rest.strip_prefix("//") {
+            let end = after.find('/').unwrap_or(after.len());
+            parts.authority = Some(&after[..end]);
+            rest = &after[end..];
+        }
+        parts.path = rest;
+        parts
+    }
+
+    /// The host of the authority, without user information or port, lowercased.
+    pub(crate) fn host(&self) -> Option<String> {
+        let authority = self.authority?;
+        let host_port = authority
+            .rsplit_once('@')
+            .map_or(authority, |(_, host)| host);
+        let host = if host_port.starts_with('[') {
+            host_port
+                .find(']')
+                .map_or(host_port, |end| &host_port[..=end])
+        } else {
+            host_port.split(':').next().unwrap_or(host_port)
+        };
+        Some(host.to_ascii_lowercase())
+    }
+
+    /// The port given in the authority, if any.
+    pub(crate) fn port(&self) -> Option<u16> {
+        let authority = self.authority?;
+        let (host, port) = authority.rsplit_once(':')?;
+        if host.starts_with('[') && !host.ends_with(']') {
+            return None;
+        }
+        port.parse().ok()
+    }
+}
+
+impl std::fmt::Display for UrlParts<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(scheme) = self.scheme {
+            write!(f, "{}:", scheme)?;
+        }
+        if let Some(authority) = self.authority {
+            write!(f, "//{}", authority)?;
+        }
+        f.write_str(self.path)?;
+        if let Some(query) = self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolve `reference` (e.g. the value of an `href`) against the absolute URL `base`.
+///
+/// Returns `None` if `base` has no scheme, since nothing can be resolved against it.
+pub(crate) fn resolve(base: &str, reference: &str) -> Option<String> {
+    let base = UrlParts::parse(base.trim());
+    base.scheme?;
+    // HTML strips leading and trailing whitespace and ignores tabs and newlines in URLs.
+    let reference: String = reference
+        .trim_matches(|c: char| c.is_ascii_whitespace())
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .collect();
+    let parsed = UrlParts::parse(&reference);
+
+    let merged;
+    let path;
+    let target = if parsed.scheme.is_some() {
+        path = remove_dot_segments(parsed.path);
+        UrlParts {
+            path: &path,
+            ..parsed
+        }
+    } else if parsed.authority.is_some() {
+        path = remove_dot_segments(parsed.path);
+        UrlParts {
+            scheme: base.scheme,
+            path: &path,
+            ..parsed
+        }
+    } else if parsed.path.is_empty() {
+        UrlParts {
+            query: parsed.query.or(base.query),
+            fragment: parsed.fragment,
+            ..base.clone()
+        }
+    } else {
+        path = if parsed.path.starts_with('/') {
+            remove_dot_segments(parsed.path)
+        } else {
+            merged = match (base.authority, base.path.rfind('/')) {
+                (Some(_), None) => format!("/{}", parsed.path),
+                (_, Some(slash)) => format!("{}{}", &base.path[..=slash], parsed.path),
+                (None, None) => parsed.path.to_string(),
+            };
+            remove_dot_segments(&merged)
+        };
+        UrlParts {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: &path,
+            query: parsed.query,
+            fragment: parsed.fragment,
+        }
+    };
+    Some(target.to_string())
+}
+
+/// Remove `.` and `..` segments from a path, as in RFC 3986 section 5.2.4.
+fn remove_dot_segments(path: &str) -> String {
+    let mut output: Vec<&str> = Vec::new();
+    let absolute = path.starts_with('/');
+    let segments: Vec<&str> = path.split('/').skip(usize::from(absolute)).collect();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match *segment {
+            "." => {
+                if last {
+                    output.push("");
+                }
+            }
+            ".." => {
+                output.pop();
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+    let joined = output.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{percent_decode, resolve};
+    use crate::parse::{body_html, parse_html};
+
+    #[test]
+    fn resolves_rfc_3986_examples() {
+        // The examples of RFC 3986 section 5.4.
+        let base = "http://a/b/c/d;p?q";
+        let cases = [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("g?y#s", "http://a/b/c/g?y#s"),
+            (";x", "http://a/b/c/;x"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            ("..g", "http://a/b/c/..g"),
+            ("./g/.", "http://a/b/c/g/"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+        ];
+        for (reference, resolved) in cases {
+            assert_eq!(
+                resolve(base, reference).as_deref(),
+                Some(resolved),
+                "{:?}",
+                reference
+            );
+        }
+    }
+
+    #[test]
+    fn cleans_up_references() {
+        let base = "https://example.com/docs/";
+        assert_eq!(
+            resolve(base, "  page\n.html\t ").as_deref(),
+            Some("https://example.com/docs/page.html")
+        );
+        assert_eq!(
+            resolve("https://example.com", "page").as_deref(),
+            Some("https://example.com/page")
+        );
+        assert_eq!(resolve("/docs/", "page"), None);
+    }
+
+    #[test]
+    fn rewrites_urls_in_attributes() {
+        let mut dom = parse_html(
+            "<a href=' /a ' ping='/p1 /p2' title=/a>x</a>\
+             <img src=/a srcset='/a 1x, /b 2x'><svg><image xlink:href=/a /></svg>",
+        );
+        let root = dom.document;
+        let changed = dom.rewrite_urls(root, |url| (url == "/a").then(|| "/z".to_string()));
+        assert_eq!(changed, 4);
+        assert_eq!(
+            body_html(&dom),
+            "<a href=\"/z\" ping=\"/p1 /p2\" title=\"/a\">x</a>\
+             <img src=\"/z\" srcset=\"/z 1x, /b 2x\"><svg><image xlink:href=\"/z\"></image></svg>"
+        );
+        let version = dom.version();
+        assert_eq!(dom.rewrite_urls(root, |url| Some(url.to_string())), 0);
+        assert_eq!(dom.version(), version);
+    }
+
+    #[test]
+    fn percent_decodes() {
+        assert_eq!(percent_decode("a%20b%2Fc"), "a b/c");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%C3%A9%FF"), "é\u{fffd}");
+    }
+}
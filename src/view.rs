@@ -0,0 +1,530 @@
+//! Access to a DOM restricted to one subtree.
+
+use std::fmt;
+
+use markup5ever::tendril::StrTendril;
+use markup5ever::{namespace_url, ns, Attribute, LocalName, QualName};
+
+use crate::{
+    Fragment, FragmentParser, GenerationalArenaDom, Handle, InsertPosition, MutationError,
+    NodeData, Selector, SerializeOpts,
+};
+
+/// A read-only view of the subtree rooted at one node, from
+/// [`GenerationalArenaDom::view`].
+///
+/// The view borrows the DOM immutably and hands out copies of text and attribute values rather
+/// than the nodes themselves, so code given a `SubtreeView` can't modify the document. Handles
+/// outside the subtree are treated as if they didn't exist: the root has no parent, and only
+/// nodes under it are returned. (Selectors are the one exception, see
+/// [`select`](SubtreeView::select).) Template contents aren't part of their element's subtree.
+#[derive(Clone, Copy)]
+pub struct SubtreeView<'a> {
+    dom: &'a GenerationalArenaDom,
+    root: Handle,
+}
+
+impl GenerationalArenaDom {
+    /// A read-only view of `root` and its descendants.
+    pub fn view(&self, root: Handle) -> SubtreeView<'_> {
+        SubtreeView { dom: self, root }
+    }
+}
+
+impl<'a> SubtreeView<'a> {
+    /// The root of the subtree.
+    pub fn root(&self) -> Handle {
+        self.root
+    }
+
+    /// Whether `handle` is the root or one of its descendants.
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.dom.is_alive(handle)
+            && handle
+                .ancestors(&self.dom.arena)
+                .any(|ancestor| ancestor == self.root)
+    }
+
+    /// The name of the element `handle`, if it is an element in the subtree.
+    pub fn name(&self, handle: Handle) -> Option<&'a QualName> {
+        if !self.contains(handle) {
+            return None;
+        }
+        match self.dom.arena[handle].get() {
+            NodeData::Element { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// The contents of the text node `handle`, if it is a text node in the subtree.
+    pub fn text(&self, handle: Handle) -> Option<String> {
+        if !self.contains(handle) {
+            return None;
+        }
+        match self.dom.arena[handle].get() {
+            NodeData::Text { contents } => Some(contents.borrow().to_string()),
+            _ => None,
+        }
+    }
+
+    /// The value of the attribute called `name` (with no namespace) of the element `handle`, if
+    /// it is in the subtree and has one.
+    pub fn attr(&self, handle: Handle, name: &str) -> Option<String> {
+        if !self.contains(handle) {
+            return None;
+        }
+        self.dom
+            .get_attr(handle, name)
+            .map(|value| value.to_string())
+    }
+
+    /// The parent of `handle`, if both are in the subtree.
+    pub fn parent(&self, handle: Handle) -> Option<Handle> {
+        if handle == self.root || !self.contains(handle) {
+            return None;
+        }
+        self.dom.arena[handle].parent()
+    }
+
+    /// The children of `handle`, or nothing if it isn't in the subtree.
+    pub fn children(&self, handle: Handle) -> impl Iterator<Item = Handle> + 'a {
+        let first = if self.contains(handle) {
+            self.dom.arena[handle].first_child()
+        } else {
+            None
+        };
+        let arena = &self.dom.arena;
+        std::iter::successors(first, move |&child| arena[child].next_sibling())
+    }
+
+    /// The root and all its descendants, in document order.
+    pub fn descendants(&self) -> impl Iterator<Item = Handle> + 'a {
+        let alive = self.dom.is_alive(self.root);
+        alive
+            .then(|| self.root.descendants(&self.dom.arena))
+            .into_iter()
+            .flatten()
+    }
+
+    /// The elements under the root (not including the root itself) that match `selector`, in
+    /// document order.
+    ///
+    /// As with `querySelectorAll`, only matches are restricted to the subtree: combinators can
+    /// still match ancestors of the root, so `article p` finds paragraphs in a view rooted
+    /// inside an `<article>`.
+    pub fn select(&self, selector: &Selector) -> Vec<Handle> {
+        if !self.dom.is_alive(self.root) {
+            return Vec::new();
+        }
+        self.dom.select(self.root, selector)
+    }
+
+    /// The text of all the text nodes in the subtree, concatenated in document order.
+    pub fn text_content(&self) -> String {
+        if !self.dom.is_alive(self.root) {
+            return String::new();
+        }
+        self.dom.text_content(self.root)
+    }
+
+    /// The subtree serialized according to `opts`.
+    pub fn serialize(&self, opts: &SerializeOpts) -> String {
+        if !self.dom.is_alive(self.root) {
+            return String::new();
+        }
+        self.dom.serialize(self.root, opts)
+    }
+
+    /// A copy of `handle` and its descendants, if it is in the subtree, as by
+    /// [`GenerationalArenaDom::copy_fragment`].
+    pub fn copy_fragment(&self, handle: Handle) -> Option<Fragment> {
+        self.contains(handle)
+            .then(|| self.dom.copy_fragment(handle))
+    }
+
+    /// A view of the subtree rooted at `handle`, if it is in this one.
+    pub fn subview(&self, handle: Handle) -> Option<SubtreeView<'a>> {
+        self.contains(handle).then_some(SubtreeView {
+            dom: self.dom,
+            root: handle,
+        })
+    }
+}
+
+impl fmt::Debug for SubtreeView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubtreeView")
+            .field("root", &self.root)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Mutable access to the subtree rooted at one node, from
+/// [`GenerationalArenaDom::subtree_mut`].
+///
+/// Every change is checked at run time to fall within the subtree, and refused with
+/// [`MutationError::OutsideSubtree`] otherwise, so a stage of a transform pipeline handed a
+/// `SubtreeMut` can only change the region it was given. Changes to
+/// [frozen](GenerationalArenaDom::freeze) nodes are refused with [`MutationError::Frozen`].
+/// The root itself can be changed, but not removed or given siblings, as that would change its
+/// parent. Reading goes through [`view`](SubtreeMut::view).
+pub struct SubtreeMut<'a> {
+    dom: &'a mut GenerationalArenaDom,
+    root: Handle,
+}
+
+impl GenerationalArenaDom {
+    /// Mutable access restricted to `root` and its descendants.
+    pub fn subtree_mut(&mut self, root: Handle) -> SubtreeMut<'_> {
+        SubtreeMut { dom: self, root }
+    }
+}
+
+impl SubtreeMut<'_> {
+    /// The root of the subtree.
+    pub fn root(&self) -> Handle {
+        self.root
+    }
+
+    /// A read-only view of the subtree.
+    pub fn view(&self) -> SubtreeView<'_> {
+        self.dom.view(self.root)
+    }
+
+    /// Mutable access to the part of this subtree rooted at `handle`.
+    pub fn narrow(&mut self, handle: Handle) -> Result<SubtreeMut<'_>, MutationError> {
+        self.check_scope(handle)?;
+        Ok(SubtreeMut {
+            dom: &mut *self.dom,
+            root: handle,
+        })
+    }
+
+    /// Set the attribute called `name` (with no namespace) on the element `handle`, replacing
+    /// any existing value.
+    pub fn set_attr(
+        &mut self,
+        handle: Handle,
+        name: &str,
+        value: &str,
+    ) -> Result<(), MutationError> {
+        self.check(handle)?;
+        self.dom.set_attr(handle, name, StrTendril::from(value));
+        Ok(())
+    }
+
+    /// Remove the attribute called `name` (with no namespace) from the element `handle`,
+    /// returning its value if it had one.
+    pub fn remove_attr(
+        &mut self,
+        handle: Handle,
+        name: &str,
+    ) -> Result<Option<String>, MutationError> {
+        self.check(handle)?;
+        let removed = self.dom.remove_attr(handle, name);
+        Ok(removed.map(|value| value.to_string()))
+    }
+
+    /// Create an HTML element called `local` with the given attributes and insert it at
+    /// `position`, returning it.
+    pub fn insert_element(
+        &mut self,
+        local: &str,
+        attrs: &[(&str, &str)],
+        position: InsertPosition,
+    ) -> Result<Handle, MutationError> {
+        self.check_position(position)?;
+        let attrs = attrs
+            .iter()
+            .map(|&(name, value)| Attribute {
+                name: QualName::new(None, ns!(), LocalName::from(name)),
+                value: StrTendril::from(value),
+            })
+            .collect();
+        let element = self.dom.create_html_element(local, attrs);
+        self.dom.insert_at(&[element], position);
+        Ok(element)
+    }
+
+    /// Create a text node and insert it at `position`, returning it.
+    pub fn insert_text(
+        &mut self,
+        text: &str,
+        position: InsertPosition,
+    ) -> Result<Handle, MutationError> {
+        self.check_position(position)?;
+        let node = self.dom.create_text(text);
+        self.dom.insert_at(&[node], position);
+        Ok(node)
+    }
+
+    /// Insert the nodes of `fragment` at `position`, returning their handles.
+    pub fn insert_fragment(
+        &mut self,
+        fragment: Fragment,
+        position: InsertPosition,
+    ) -> Result<Vec<Handle>, MutationError> {
+        self.check_position(position)?;
+        self.dom.insert_fragment(fragment, position)
+    }
+
+    /// Replace the children of the element `handle` with `input` parsed as a fragment in its
+    /// context, as [`GenerationalArenaDom::set_inner_html`] does. As with
+    /// [`remove`](SubtreeMut::remove), this fails if anything under the children is frozen.
+    pub fn set_inner_html<P>(
+        &mut self,
+        handle: Handle,
+        input: &str,
+        parser: &P,
+    ) -> Result<Vec<Handle>, MutationError>
+    where
+        P: FragmentParser + ?Sized,
+    {
+        self.check(handle)?;
+        let children: Vec<Handle> = self.view().children(handle).collect();
+        for child in children {
+            self.dom.check_removable(child)?;
+        }
+        self.dom.set_inner_html(handle, input, parser)
+    }
+
+    /// Remove `handle` and its descendants, freeing them. The root can't be removed, nor can a
+    /// node with anything [frozen](GenerationalArenaDom::freeze) under it.
+    pub fn remove(&mut self, handle: Handle) -> Result<(), MutationError> {
+        self.check_below_root(handle)?;
+        self.dom.check_removable(handle)?;
+        self.dom.free_subtree(handle);
+        Ok(())
+    }
+
+    /// Replace `handle` with its children. The root can't be unwrapped.
+    pub fn unwrap(&mut self, handle: Handle) -> Result<(), MutationError> {
+        self.check_below_root(handle)?;
+        self.dom.check_mutable(handle)?;
+        self.dom.unwrap_node(handle);
+        Ok(())
+    }
+
+    /// Check that `handle` is in the subtree.
+    fn check_scope(&self, handle: Handle) -> Result<(), MutationError> {
+        if self.dom.view(self.root).contains(handle) {
+            Ok(())
+        } else {
+            Err(MutationError::OutsideSubtree { node: handle })
+        }
+    }
+
+    /// Check that `handle` is in the subtree and not its root.
+    fn check_below_root(&self, handle: Handle) -> Result<(), MutationError> {
+        if handle == self.root {
+            return Err(MutationError::OutsideSubtree { node: handle });
+        }
+        self.check_scope(handle)
+    }
+
+    /// Check that `handle` is in the subtree and may be changed.
+    fn check(&self, handle: Handle) -> Result<(), MutationError> {
+        self.check_scope(handle)?;
+        self.dom.check_mutable(handle)
+    }
+
+    /// Check that inserting at `position` puts nodes in the subtree, under a parent that may be
+    /// changed.
+    fn check_position(&self, position: InsertPosition) -> Result<(), MutationError> {
+        match position {
+            InsertPosition::AppendTo(parent) | InsertPosition::PrependTo(parent) => {
+                self.check(parent)
+            }
+            InsertPosition::Before(sibling) | InsertPosition::After(sibling) => {
+                self.check_below_root(sibling)?;
+                match self.dom.arena[sibling].parent() {
+                    Some(parent) => self.dom.check_mutable(parent),
+                    None => Err(MutationError::OutsideSubtree { node: sibling }),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SubtreeMut<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SubtreeMut")
+            .field("root", &self.root)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::QualName;
+
+    use crate::parse::parse_fragment_html;
+    use crate::{
+        GenerationalArenaDom, Handle, InsertPosition, MutationError, Selector, SubtreeMut,
+    };
+
+    fn select(dom: &GenerationalArenaDom, selector: &str) -> Handle {
+        dom.select(dom.document, &Selector::parse(selector).unwrap())[0]
+    }
+
+    #[test]
+    fn views_only_the_subtree() {
+        let dom = GenerationalArenaDom::from_abbreviation("div>(p#in>em{a})+(p#out{b})").unwrap();
+        let inside = select(&dom, "#in");
+        let em = select(&dom, "em");
+        let outside = select(&dom, "#out");
+        let text = dom.arena[em].first_child().unwrap();
+        let view = dom.view(inside);
+
+        assert!(view.contains(inside) && view.contains(text));
+        assert_eq!(&*view.name(em).unwrap().local, "em");
+        assert_eq!(view.attr(inside, "id").as_deref(), Some("in"));
+        assert_eq!(view.text(text).as_deref(), Some("a"));
+        assert_eq!(view.text(em), None);
+        assert_eq!(view.parent(em), Some(inside));
+        assert_eq!(view.parent(inside), None);
+        assert_eq!(view.descendants().collect::<Vec<_>>(), [inside, em, text]);
+        assert_eq!(view.select(&Selector::parse("em, p").unwrap()), [em]);
+        assert_eq!(view.text_content(), "a");
+        assert!(view.subview(em).is_some());
+        let copy = view.copy_fragment(em).unwrap();
+        assert_eq!(copy.roots().len(), 1);
+
+        let outside_text = dom.arena[outside].first_child().unwrap();
+        assert!(!view.contains(outside));
+        assert_eq!(view.name(outside), None);
+        assert_eq!(view.attr(outside, "id"), None);
+        assert_eq!(view.text(outside_text), None);
+        assert_eq!(view.parent(outside_text), None);
+        assert_eq!(view.children(outside).count(), 0);
+        assert!(view.subview(outside).is_none());
+        assert!(view.copy_fragment(outside).is_none());
+        assert_eq!(view.subview(em).unwrap().name(inside), None);
+    }
+
+    #[test]
+    fn set_inner_html_keeps_frozen_children() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("div>p>em{kept}").unwrap();
+        let div = dom.select(dom.document, &Selector::parse("div").unwrap())[0];
+        let em = dom.select(dom.document, &Selector::parse("em").unwrap())[0];
+        dom.freeze(em);
+        let parser = |_: GenerationalArenaDom, _: &QualName, _: &str| -> GenerationalArenaDom {
+            panic!("nothing should be parsed")
+        };
+        let result = dom.subtree_mut(div).set_inner_html(div, "new", &parser);
+        assert_eq!(result, Err(MutationError::Frozen { node: em }));
+        assert_eq!(dom.text_content(div), "kept");
+    }
+
+    #[test]
+    fn refuses_changes_outside_the_subtree() {
+        let mut dom =
+            GenerationalArenaDom::from_abbreviation("div>(p#in>em{a})+(p#out>b{b})").unwrap();
+        let inside = select(&dom, "#in");
+        let outside = select(&dom, "#out");
+        let b = select(&dom, "b");
+        let fragment = dom.copy_fragment(b);
+        let html = dom.serialize(dom.document, &Default::default());
+        let version = dom.version();
+        let outside_error = |node| MutationError::OutsideSubtree { node };
+
+        let mut subtree = dom.subtree_mut(inside);
+        assert_eq!(subtree.narrow(outside).unwrap_err(), outside_error(outside));
+        assert_eq!(
+            subtree.set_attr(outside, "class", "x").unwrap_err(),
+            outside_error(outside)
+        );
+        assert_eq!(
+            subtree.remove_attr(outside, "id").unwrap_err(),
+            outside_error(outside)
+        );
+        assert_eq!(
+            subtree
+                .insert_element("i", &[], InsertPosition::AppendTo(outside))
+                .unwrap_err(),
+            outside_error(outside)
+        );
+        assert_eq!(
+            subtree
+                .insert_text("x", InsertPosition::Before(b))
+                .unwrap_err(),
+            outside_error(b)
+        );
+        assert_eq!(
+            subtree
+                .insert_fragment(fragment, InsertPosition::After(b))
+                .unwrap_err(),
+            outside_error(b)
+        );
+        assert_eq!(
+            subtree
+                .set_inner_html(outside, "x", &parse_fragment_html)
+                .unwrap_err(),
+            outside_error(outside)
+        );
+        assert_eq!(subtree.remove(b).unwrap_err(), outside_error(b));
+        assert_eq!(subtree.unwrap(b).unwrap_err(), outside_error(b));
+
+        // The root itself can't be removed, unwrapped or given siblings.
+        assert_eq!(subtree.remove(inside).unwrap_err(), outside_error(inside));
+        assert_eq!(subtree.unwrap(inside).unwrap_err(), outside_error(inside));
+        assert_eq!(
+            subtree
+                .insert_text("x", InsertPosition::After(inside))
+                .unwrap_err(),
+            outside_error(inside)
+        );
+
+        assert_eq!(dom.version(), version);
+        assert_eq!(dom.serialize(dom.document, &Default::default()), html);
+    }
+
+    #[test]
+    fn changes_inside_the_subtree_bump_the_version() {
+        let mut dom = GenerationalArenaDom::from_abbreviation("div>p#in>em{a}").unwrap();
+        let inside = select(&dom, "#in");
+        let em = select(&dom, "em");
+        let fragment = dom.copy_fragment(em);
+        let mut subtree = dom.subtree_mut(inside);
+
+        let mut version = subtree.dom.version();
+        let mut changed = |subtree: &SubtreeMut| {
+            let new = subtree.dom.version();
+            let bumped = new > version;
+            version = new;
+            bumped
+        };
+        subtree.set_attr(inside, "class", "x").unwrap();
+        assert!(changed(&subtree));
+        assert_eq!(
+            subtree.remove_attr(inside, "class").unwrap().as_deref(),
+            Some("x")
+        );
+        assert!(changed(&subtree));
+        let i = subtree
+            .insert_element("i", &[("title", "t")], InsertPosition::AppendTo(inside))
+            .unwrap();
+        assert!(changed(&subtree));
+        subtree
+            .insert_text("b", InsertPosition::PrependTo(i))
+            .unwrap();
+        assert!(changed(&subtree));
+        subtree
+            .insert_fragment(fragment, InsertPosition::After(i))
+            .unwrap();
+        assert!(changed(&subtree));
+        subtree.unwrap(em).unwrap();
+        assert!(changed(&subtree));
+        subtree.remove(i).unwrap();
+        assert!(changed(&subtree));
+        assert_eq!(subtree.view().text_content(), "aa");
+        subtree
+            .set_inner_html(inside, "<b>c</b>", &parse_fragment_html)
+            .unwrap();
+        assert!(changed(&subtree));
+        assert_eq!(
+            subtree.view().serialize(&Default::default()),
+            "<p id=\"in\"><b>c</b></p>"
+        );
+    }
+}
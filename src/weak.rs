@@ -0,0 +1,83 @@
+//! Handles that can be checked for liveness before use.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{GenerationalArenaDom, Handle};
+
+static NEXT_DOM_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A unique identifier for a DOM, so handles from one can be told apart from another's.
+pub(crate) fn next_dom_id() -> u64 {
+    NEXT_DOM_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A handle that remembers which DOM it belongs to and may outlive its node.
+///
+/// A plain [`Handle`] is only meaningful for the DOM it came from, and using it after its node
+/// was freed panics in most APIs. A `WeakHandle` is safe to keep in long-lived caches: it only
+/// [upgrades](GenerationalArenaDom::upgrade) back to a `Handle` while the node is still alive in
+/// the same DOM. Freed slots are reused with a new generation, so a stale weak handle never
+/// resolves to an unrelated node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WeakHandle {
+    dom: u64,
+    handle: Handle,
+}
+
+impl WeakHandle {
+    /// Whether this handle still refers to a live node in `dom`.
+    pub fn is_alive(&self, dom: &GenerationalArenaDom) -> bool {
+        dom.upgrade(*self).is_some()
+    }
+}
+
+impl GenerationalArenaDom {
+    /// Whether `handle` refers to a node that hasn't been freed.
+    pub fn is_alive(&self, handle: Handle) -> bool {
+        self.arena.get(handle).is_some()
+    }
+
+    /// Create a [`WeakHandle`] for `handle`.
+    pub fn downgrade(&self, handle: Handle) -> WeakHandle {
+        WeakHandle {
+            dom: self.id,
+            handle,
+        }
+    }
+
+    /// Turn `weak` back into a `Handle`, if it belongs to this DOM and its node is still alive.
+    pub fn upgrade(&self, weak: WeakHandle) -> Option<Handle> {
+        if weak.dom == self.id && self.is_alive(weak.handle) {
+            Some(weak.handle)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse::parse_html;
+
+    #[test]
+    fn upgrades_only_live_nodes_of_the_same_dom() {
+        let mut dom = parse_html("<p>one</p><p>two</p>");
+        let p = dom.query_selector(dom.document, "p").unwrap().unwrap();
+        let weak = dom.downgrade(p);
+        assert_eq!(dom.upgrade(weak), Some(p));
+        assert!(weak.is_alive(&dom));
+
+        // A DOM with the same shape has different ids.
+        let other = parse_html("<p>one</p><p>two</p>");
+        assert_eq!(other.upgrade(weak), None);
+
+        dom.remove_subtree(p).unwrap();
+        assert_eq!(dom.upgrade(weak), None);
+        assert!(!dom.is_alive(p));
+
+        // The freed slot is reused with a new generation.
+        let reused = dom.arena.new_node(crate::NodeData::Document);
+        assert_ne!(reused, p);
+        assert!(!weak.is_alive(&dom));
+    }
+}
@@ -0,0 +1,124 @@
+//! Parsing XML into the DOM. Enabled by the `xml` feature.
+//!
+//! [`parse_xml`] is a [`Parser`](crate::Parser) that runs
+//! [xml5ever](https://docs.rs/xml5ever)'s tree builder with the DOM as its `TreeSink`, so the
+//! options, limits and hooks of [`DomOptions`](crate::DomOptions) apply to XML too. Elements
+//! and attributes get the namespaces their prefixes are bound to, and processing instructions
+//! are kept as [`NodeData::ProcessingInstruction`](crate::NodeData::ProcessingInstruction)
+//! nodes.
+//!
+//! Like xml5ever, the parser doesn't validate or read DTDs, and recovers from well-formedness
+//! errors, reporting them as parse errors like html5ever does for HTML.
+
+use xml5ever::driver::{parse_document, XmlParseOpts};
+use xml5ever::tendril::TendrilSink;
+
+use crate::GenerationalArenaDom;
+
+/// Parse the XML document `xml` into `sink` and return the finished DOM.
+pub fn parse_xml(sink: GenerationalArenaDom, xml: &str) -> GenerationalArenaDom {
+    parse_document(sink, XmlParseOpts::default()).one(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use markup5ever::{namespace_url, ns};
+
+    use super::parse_xml;
+    use crate::{GenerationalArenaDom, NodeData};
+
+    fn parse(xml: &str) -> GenerationalArenaDom {
+        parse_xml(GenerationalArenaDom::default(), xml)
+    }
+
+    fn processing_instruction(dom: &GenerationalArenaDom, node: crate::Handle) -> (&str, &str) {
+        match dom.arena[node].get() {
+            NodeData::ProcessingInstruction { target, contents } => (target, contents),
+            other => panic!("expected a processing instruction, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn processing_instructions_and_namespaces() {
+        let dom = parse(concat!(
+            "<?xml version=\"1.0\"?>\n",
+            "<?xml-stylesheet href=\"style.xsl\" type=\"text/xsl\"?>\n",
+            "<feed xmlns=\"http://www.w3.org/2005/Atom\" ",
+            "xmlns:media=\"http://search.yahoo.com/mrss/\">",
+            "<media:thumbnail url=\"a.png\" xml:lang=\"en\"/>",
+            "<title type=\"text\">A &amp; B &#x263A;<![CDATA[ <raw> ]]></title>",
+            "<?php echo 1; ?>",
+            "</feed>",
+        ));
+        assert!(dom.errors.is_empty(), "{:?}", dom.errors);
+
+        let children: Vec<_> = dom.child_nodes(dom.document).collect();
+        assert_eq!(children.len(), 3);
+        assert_eq!(
+            processing_instruction(&dom, children[0]),
+            ("xml", "version=\"1.0\"")
+        );
+        assert_eq!(
+            processing_instruction(&dom, children[1]),
+            ("xml-stylesheet", "href=\"style.xsl\" type=\"text/xsl\"")
+        );
+
+        let feed = dom.as_element(children[2]).unwrap();
+        assert_eq!(feed.local_name(), "feed");
+        assert_eq!(&*feed.name().ns, "http://www.w3.org/2005/Atom");
+
+        let parts: Vec<_> = feed.children().collect();
+        assert_eq!(parts.len(), 3);
+        let thumbnail = dom.as_element(parts[0]).unwrap();
+        assert_eq!(thumbnail.local_name(), "thumbnail");
+        assert_eq!(thumbnail.name().prefix.as_deref(), Some("media"));
+        assert_eq!(&*thumbnail.name().ns, "http://search.yahoo.com/mrss/");
+        let attrs = thumbnail.attrs();
+        assert_eq!(attrs[0].name.ns, ns!());
+        assert_eq!(attrs[1].name.ns, ns!(xml));
+        assert_eq!(&*attrs[1].name.local, "lang");
+
+        let title = dom.as_element(parts[1]).unwrap();
+        assert_eq!(&*title.name().ns, "http://www.w3.org/2005/Atom");
+        assert_eq!(title.attr("type").as_deref(), Some("text"));
+        assert_eq!(title.text_content(), "A & B \u{263A} <raw> ");
+
+        assert_eq!(processing_instruction(&dom, parts[2]), ("php", "echo 1; "));
+    }
+
+    #[test]
+    fn doctype_and_comments() {
+        let dom =
+            parse("<!DOCTYPE note SYSTEM \"note.dtd\"><!-- before --><note><!-- inside --></note>");
+        assert!(dom.errors.is_empty(), "{:?}", dom.errors);
+        let children: Vec<_> = dom.child_nodes(dom.document).collect();
+        assert_eq!(children.len(), 3);
+        match dom.arena[children[0]].get() {
+            NodeData::Doctype {
+                name, system_id, ..
+            } => {
+                assert_eq!(&**name, "note");
+                assert_eq!(&**system_id, "note.dtd");
+            }
+            other => panic!("expected a doctype, got {:?}", other),
+        }
+        assert_eq!(dom.as_comment(children[1]), Some(" before "));
+        let note = dom.as_element(children[2]).unwrap();
+        assert_eq!(
+            dom.as_comment(note.children().next().unwrap()),
+            Some(" inside ")
+        );
+    }
+
+    #[test]
+    fn errors_are_recovered_from() {
+        let dom = parse("<a xmlns:p=\"urn:p\"><p:b>one</a>");
+        assert!(!dom.errors.is_empty());
+        assert_eq!(dom.parse_errors.len(), dom.errors.len());
+        let root = dom.child_nodes(dom.document).next().unwrap();
+        let a = dom.as_element(root).unwrap();
+        let b = a.child_elements().next().unwrap();
+        assert_eq!(&*b.name().ns, "urn:p");
+        assert_eq!(b.text_content(), "one");
+    }
+}